@@ -0,0 +1,201 @@
+//! Append-only audit log of destructive operations
+//!
+//! Every deletion, restore, snapshot removal and Docker prune performed by
+//! DragonFly is recorded as one JSON line in `~/.dragonfly/audit.jsonl`.
+//! Entries are only ever appended, so the log is a complete history of what
+//! the tool has done to the system.
+
+use crate::jsonl;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Kind of destructive operation recorded in the audit log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    /// Files were deleted
+    Delete,
+    /// Files were restored from a recovery archive
+    Restore,
+    /// A local Time Machine snapshot was removed
+    SnapshotDelete,
+    /// Expired recovery archives were purged
+    RecoveryPurge,
+    /// The protection policy kept an operation from modifying paths
+    Blocked,
+    /// Duplicate copies were replaced with clones or hard links
+    Dedupe,
+    /// Stopped containers, dangling images and build cache were pruned
+    DockerPrune,
+}
+
+impl std::fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Delete => write!(f, "delete"),
+            Self::Restore => write!(f, "restore"),
+            Self::SnapshotDelete => write!(f, "snapshot-delete"),
+            Self::RecoveryPurge => write!(f, "recovery-purge"),
+            Self::Blocked => write!(f, "blocked"),
+            Self::Dedupe => write!(f, "dedupe"),
+            Self::DockerPrune => write!(f, "docker-prune"),
+        }
+    }
+}
+
+/// A single audit log record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// When the operation happened
+    pub timestamp: DateTime<Utc>,
+    /// Command line that triggered the operation
+    pub command: String,
+    /// What was done
+    pub action: AuditAction,
+    /// Paths (or snapshot IDs) affected by the operation
+    pub paths: Vec<PathBuf>,
+    /// Number of items affected
+    pub items: usize,
+    /// Bytes affected
+    pub bytes: u64,
+    /// Recovery ID covering this operation, if any
+    pub recovery_id: Option<String>,
+}
+
+impl AuditEntry {
+    /// Create a new entry timestamped now
+    pub fn new(action: AuditAction, command: impl Into<String>) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            command: command.into(),
+            action,
+            paths: Vec::new(),
+            items: 0,
+            bytes: 0,
+            recovery_id: None,
+        }
+    }
+
+    /// Set the affected paths
+    pub fn with_paths(mut self, paths: Vec<PathBuf>) -> Self {
+        self.paths = paths;
+        self
+    }
+
+    /// Set the number of affected items
+    pub fn with_items(mut self, items: usize) -> Self {
+        self.items = items;
+        self
+    }
+
+    /// Set the number of affected bytes
+    pub fn with_bytes(mut self, bytes: u64) -> Self {
+        self.bytes = bytes;
+        self
+    }
+
+    /// Set the recovery ID covering this operation
+    pub fn with_recovery_id(mut self, recovery_id: impl Into<String>) -> Self {
+        self.recovery_id = Some(recovery_id.into());
+        self
+    }
+}
+
+/// Append-only audit log stored as JSON Lines
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl AuditLog {
+    /// Create an audit log backed by the given file
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Get default audit log location
+    pub fn default_path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_else(|| PathBuf::from("~"))
+            .join(".dragonfly")
+            .join("audit.jsonl")
+    }
+
+    /// Path of the backing file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append an entry to the log
+    pub fn record(&self, entry: &AuditEntry) -> std::io::Result<()> {
+        jsonl::append(&self.path, entry)
+    }
+
+    /// Read all entries, oldest first
+    ///
+    /// Lines that cannot be parsed are skipped with a warning so a single
+    /// corrupted record doesn't hide the rest of the history.
+    pub fn entries(&self) -> std::io::Result<Vec<AuditEntry>> {
+        jsonl::read(&self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_record_and_read_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let log = AuditLog::new(temp_dir.path().join("nested").join("audit.jsonl"));
+
+        let entry = AuditEntry::new(AuditAction::Delete, "clean --caches")
+            .with_paths(vec![PathBuf::from("/tmp/cache")])
+            .with_items(3)
+            .with_bytes(1024);
+        log.record(&entry).unwrap();
+        log.record(
+            &AuditEntry::new(AuditAction::Restore, "recover restore x").with_recovery_id("x"),
+        )
+        .unwrap();
+
+        let entries = log.entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].action, AuditAction::Delete);
+        assert_eq!(entries[0].bytes, 1024);
+        assert_eq!(entries[0].items, 3);
+        assert_eq!(entries[1].recovery_id.as_deref(), Some("x"));
+    }
+
+    #[test]
+    fn test_entries_skip_malformed_lines() {
+        let temp_dir = TempDir::new().unwrap();
+        let log = AuditLog::new(temp_dir.path().join("audit.jsonl"));
+
+        log.record(&AuditEntry::new(
+            AuditAction::SnapshotDelete,
+            "time-machine delete",
+        ))
+        .unwrap();
+        let mut file = OpenOptions::new().append(true).open(log.path()).unwrap();
+        writeln!(file, "not json").unwrap();
+        log.record(&AuditEntry::new(
+            AuditAction::RecoveryPurge,
+            "recover cleanup",
+        ))
+        .unwrap();
+
+        assert_eq!(log.entries().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_missing_log_is_empty() {
+        let temp_dir = TempDir::new().unwrap();
+        let log = AuditLog::new(temp_dir.path().join("audit.jsonl"));
+        assert!(log.entries().unwrap().is_empty());
+    }
+}
@@ -1,13 +1,27 @@
 //! System cleaning orchestration
 
-use crate::targets::CleanTarget;
+use crate::recovery::{RecoveryManager, RecoveryManifest};
+use crate::safety::SafetyPolicy;
+use crate::targets::{CleanScope, CleanTarget};
+use crate::time_machine::TimeMachineManager;
+use crate::xcode;
+use chrono::{DateTime, Utc};
+use dragonfly_core::domain::protection::{SkipReason, SkippedPath};
+use dragonfly_core::domain::scan_options::ScanOptions;
 use dragonfly_core::error::Result;
-use jwalk::WalkDir;
+use dragonfly_core::ports::{CancellationToken, NoProgress, ProgressReporter, ProgressUpdate};
+use dragonfly_disk::walk::{skipped_from_walk_error, walker};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::BTreeSet;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
 
 /// Cleaning result
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct CleanResult {
     /// Number of files cleaned
     pub files_cleaned: usize,
@@ -15,8 +29,84 @@ pub struct CleanResult {
     pub bytes_freed: u64,
     /// Files that would be cleaned (for dry-run)
     pub files_found: Vec<PathBuf>,
+    /// Files that were refused or could not be removed, with the reason
+    pub skipped: Vec<SkippedPath>,
+    /// Whether cleaning was cancelled before completing (results are partial)
+    pub interrupted: bool,
+    /// Files left in place because they were modified more recently than
+    /// the age limit
+    pub files_kept_recent: usize,
+    /// What was cleaned, by category, in the order the target lists them
+    pub categories: Vec<CategoryTotal>,
+    /// Bytes of `bytes_freed` the newest local snapshot still references,
+    /// because the files were last modified before it was taken; the volume
+    /// only gets them back once the snapshot is thinned or expires
+    pub bytes_retained_by_snapshots: u64,
 }
 
+impl CleanResult {
+    /// Bytes of `bytes_freed` the volume gets back right away
+    pub fn bytes_reclaimable(&self) -> u64 {
+        self.bytes_freed
+            .saturating_sub(self.bytes_retained_by_snapshots)
+    }
+
+    /// Whether local snapshots hold on to most of the freed space
+    pub fn snapshots_dominate(&self) -> bool {
+        self.bytes_retained_by_snapshots > self.bytes_reclaimable()
+    }
+}
+
+/// Items and bytes cleaned in one category of a target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CategoryTotal {
+    /// Category name, e.g. `cache` or `derived-data`
+    pub category: &'static str,
+    /// Files cleaned, or simulators deleted
+    pub items: usize,
+    /// Bytes they took
+    pub bytes: u64,
+}
+
+/// A folder or file directly inside one of a target's locations, which an
+/// interactive clean offers as one choice
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CleanGroup {
+    /// Location it is in, as the target lists it, e.g. `~/Library/Caches`
+    pub location: &'static str,
+    /// The folder or file
+    pub path: PathBuf,
+    /// Files in it a clean would remove
+    pub files: usize,
+    /// Bytes they take
+    pub bytes: u64,
+}
+
+/// Result of pruning an empty directory tree
+#[derive(Debug, Clone, Default)]
+pub struct PruneResult {
+    /// Directories that were removed
+    pub dirs_removed: Vec<PathBuf>,
+    /// Directories that were refused or could not be removed, with the reason
+    pub skipped: Vec<SkippedPath>,
+}
+
+/// Finder metadata, the only file pruning removes
+const FINDER_METADATA: &str = ".DS_Store";
+
+/// Source recorded on recovery items archived by a clean
+const CLEAN_SOURCE: &str = "clean";
+
+/// Category of the simulators an Xcode clean deletes
+const SIMULATORS: &str = "simulators";
+
+/// Workers removing files at once, unless the scan options set a thread
+/// count; more mostly contend for the same directory locks
+const DELETE_WORKERS: usize = 8;
+
+/// Files removed between progress reports
+const REPORT_EVERY: u64 = 500;
+
 /// Cleans system caches and temporary files
 #[derive(Debug, Clone, Copy)]
 pub struct SystemCleaner;
@@ -29,35 +119,340 @@ impl SystemCleaner {
 
     /// Clean based on target
     pub async fn clean(&self, target: CleanTarget, dry_run: bool) -> Result<CleanResult> {
-        let paths = target.paths();
-        let mut total_files = 0;
-        let mut total_bytes = 0u64;
-        let mut all_files = Vec::new();
+        self.clean_with_options(
+            target,
+            dry_run,
+            &ScanOptions::default(),
+            &CancellationToken::new(),
+        )
+        .await
+    }
 
-        for path_str in paths {
+    /// Clean based on target with the given scan options
+    ///
+    /// Stops between files if `cancel` fires; files removed before
+    /// cancellation are reported in the result.
+    pub async fn clean_with_options(
+        &self,
+        target: CleanTarget,
+        dry_run: bool,
+        options: &ScanOptions,
+        cancel: &CancellationToken,
+    ) -> Result<CleanResult> {
+        self.clean_in_scope(target, None, dry_run, None, options, cancel, &NoProgress)
+            .await
+    }
+
+    /// Clean the locations of `target` in `scope` (every location for
+    /// `None`) with the given scan options
+    ///
+    /// With `older_than_days`, only files last modified at least that many
+    /// days ago are cleaned; newer ones are counted as kept. Stops between
+    /// files if `cancel` fires; files removed before cancellation are
+    /// reported in the result. `progress` hears the files removed and bytes
+    /// freed so far every few hundred files.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn clean_in_scope(
+        &self,
+        target: CleanTarget,
+        scope: Option<CleanScope>,
+        dry_run: bool,
+        older_than_days: Option<u32>,
+        options: &ScanOptions,
+        cancel: &CancellationToken,
+        progress: &dyn ProgressReporter,
+    ) -> Result<CleanResult> {
+        let cutoff = older_than_days.map(cutoff);
+        let tally = Tally::new(progress);
+        self.sweep(
+            target, scope, dry_run, cutoff, options, cancel, None, &tally,
+        )
+    }
+
+    /// Clean the locations of `target` in `scope` like
+    /// [`Self::clean_in_scope`], moving each file into the recovery archive
+    /// of `manifest` instead of deleting it
+    ///
+    /// Files are archived with a checksum and only then removed, so
+    /// [`RecoveryManager::restore_recovery`] brings them back. The space
+    /// they take is held in the archive until the recovery is cleaned up.
+    /// The caller saves the manifest, including after cancellation.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn clean_to_recovery(
+        &self,
+        target: CleanTarget,
+        scope: Option<CleanScope>,
+        older_than_days: Option<u32>,
+        options: &ScanOptions,
+        cancel: &CancellationToken,
+        progress: &dyn ProgressReporter,
+        recovery: &RecoveryManager,
+        manifest: &mut RecoveryManifest,
+    ) -> Result<CleanResult> {
+        let cutoff = older_than_days.map(cutoff);
+        let archive = Archive { recovery, manifest };
+        let tally = Tally::new(progress);
+        self.sweep(
+            target,
+            scope,
+            false,
+            cutoff,
+            options,
+            cancel,
+            Some(archive),
+            &tally,
+        )
+    }
+
+    /// Report, delete or archive the files of `target` in `scope` last
+    /// modified before `cutoff`
+    #[allow(clippy::too_many_arguments)]
+    fn sweep(
+        &self,
+        target: CleanTarget,
+        scope: Option<CleanScope>,
+        dry_run: bool,
+        cutoff: Option<DateTime<Utc>>,
+        options: &ScanOptions,
+        cancel: &CancellationToken,
+        mut archive: Option<Archive<'_>>,
+        tally: &Tally<'_>,
+    ) -> Result<CleanResult> {
+        let mut result = CleanResult::default();
+        let safety = SafetyPolicy::current();
+        let snapshot = TimeMachineManager::newest_snapshot_time();
+
+        for path_str in target.paths_in(scope) {
+            if cancel.is_cancelled() {
+                break;
+            }
             let expanded_path = expand_path(path_str)?;
             let path = Path::new(&expanded_path);
 
             if !path.exists() {
                 continue;
             }
+            if safety.refuses_tree(path) {
+                result
+                    .skipped
+                    .push(SkippedPath::new(expanded_path, SkipReason::PolicyProtected));
+                continue;
+            }
 
-            let (files, bytes) = if dry_run {
-                scan_directory(path)?
+            let category = category_of(path_str);
+            let options = location_options(path_str, path, options);
+            let sweep = if dry_run {
+                scan_directory(path, cutoff, snapshot, &options, &safety, cancel)?
             } else {
-                clean_directory(path)?
+                let sweep = clean_directory(
+                    path,
+                    cutoff,
+                    snapshot,
+                    &options,
+                    &safety,
+                    cancel,
+                    archive.as_mut(),
+                    category,
+                    tally,
+                )?;
+                remove_emptied_dirs(&sweep.files, path, &safety);
+                sweep
             };
+            add_sweep(&mut result, category, sweep);
+        }
 
-            total_files += files.len();
-            total_bytes += bytes;
-            all_files.extend(files);
+        // Simulators have no location of their own; simctl finds them
+        let user_files = scope != Some(CleanScope::System);
+        if target == CleanTarget::Xcode && user_files && !cancel.is_cancelled() {
+            match clean_simulators(dry_run) {
+                Ok(simulators) => {
+                    result.bytes_freed += simulators.bytes;
+                    add_category(&mut result.categories, simulators);
+                }
+                Err(e) => tracing::warn!(error = %e, "Failed to clean unavailable simulators"),
+            }
         }
 
-        Ok(CleanResult {
-            files_cleaned: total_files,
-            bytes_freed: total_bytes,
-            files_found: all_files,
-        })
+        if !dry_run {
+            tally.finish();
+        }
+        result.interrupted = cancel.is_cancelled();
+        Ok(result)
+    }
+
+    /// What cleaning `target` in `scope` would remove, as one group per
+    /// folder or file directly inside each location
+    ///
+    /// Groups come in the order the target lists its locations, largest
+    /// first within each. Groups with nothing to clean, after `older_than_days`
+    /// and the safety checks, are left out, as are simulators, which have no
+    /// location. Stops early with the groups found so far if `cancel` fires.
+    pub async fn groups(
+        &self,
+        target: CleanTarget,
+        scope: Option<CleanScope>,
+        older_than_days: Option<u32>,
+        options: &ScanOptions,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<CleanGroup>> {
+        let cutoff = older_than_days.map(cutoff);
+        let safety = SafetyPolicy::current();
+        let mut groups = Vec::new();
+
+        for location in target.paths_in(scope) {
+            let expanded_path = expand_path(location)?;
+            let path = Path::new(&expanded_path);
+            if safety.refuses_tree(path) {
+                continue;
+            }
+            let Ok(entries) = fs::read_dir(path) else {
+                continue;
+            };
+            let options = location_options(location, path, options);
+            let mut in_location = Vec::new();
+            for entry in entries.flatten() {
+                if cancel.is_cancelled() {
+                    break;
+                }
+                let entry_path = entry.path();
+                let hidden = entry.file_name().to_string_lossy().starts_with('.');
+                if (hidden && !options.include_hidden)
+                    || options.is_excluded(&entry_path.to_string_lossy())
+                    || safety.refuses_tree(&entry_path)
+                {
+                    continue;
+                }
+                let sweep = scan_directory(&entry_path, cutoff, None, &options, &safety, cancel)?;
+                if !sweep.files.is_empty() {
+                    in_location.push(CleanGroup {
+                        location,
+                        path: entry_path,
+                        files: sweep.files.len(),
+                        bytes: sweep.bytes,
+                    });
+                }
+            }
+            in_location.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.path.cmp(&b.path)));
+            groups.extend(in_location);
+        }
+        Ok(groups)
+    }
+
+    /// Delete the files of `groups`, found by [`Self::groups`], and nothing
+    /// else
+    ///
+    /// Files are checked again as they are removed: anything newer than
+    /// `older_than_days` or refused by the safety checks by then is kept.
+    pub async fn clean_groups(
+        &self,
+        groups: &[CleanGroup],
+        older_than_days: Option<u32>,
+        options: &ScanOptions,
+        cancel: &CancellationToken,
+        progress: &dyn ProgressReporter,
+    ) -> Result<CleanResult> {
+        let cutoff = older_than_days.map(cutoff);
+        let tally = Tally::new(progress);
+        self.sweep_groups(groups, cutoff, options, cancel, None, &tally)
+    }
+
+    /// Clean the files of `groups` like [`Self::clean_groups`], moving them
+    /// into the recovery archive of `manifest` instead of deleting them
+    ///
+    /// The caller saves the manifest, as with [`Self::clean_to_recovery`].
+    #[allow(clippy::too_many_arguments)]
+    pub async fn clean_groups_to_recovery(
+        &self,
+        groups: &[CleanGroup],
+        older_than_days: Option<u32>,
+        options: &ScanOptions,
+        cancel: &CancellationToken,
+        progress: &dyn ProgressReporter,
+        recovery: &RecoveryManager,
+        manifest: &mut RecoveryManifest,
+    ) -> Result<CleanResult> {
+        let cutoff = older_than_days.map(cutoff);
+        let archive = Archive { recovery, manifest };
+        let tally = Tally::new(progress);
+        self.sweep_groups(groups, cutoff, options, cancel, Some(archive), &tally)
+    }
+
+    /// Delete or archive the files of `groups` last modified before `cutoff`
+    fn sweep_groups(
+        &self,
+        groups: &[CleanGroup],
+        cutoff: Option<DateTime<Utc>>,
+        options: &ScanOptions,
+        cancel: &CancellationToken,
+        mut archive: Option<Archive<'_>>,
+        tally: &Tally<'_>,
+    ) -> Result<CleanResult> {
+        let mut result = CleanResult::default();
+        let safety = SafetyPolicy::current();
+        let snapshot = TimeMachineManager::newest_snapshot_time();
+
+        for group in groups {
+            if cancel.is_cancelled() {
+                break;
+            }
+            let location = expand_path(group.location)?;
+            let category = category_of(group.location);
+            let options = location_options(group.location, Path::new(&location), options);
+            let sweep = clean_directory(
+                &group.path,
+                cutoff,
+                snapshot,
+                &options,
+                &safety,
+                cancel,
+                archive.as_mut(),
+                category,
+                tally,
+            )?;
+            remove_emptied_dirs(&sweep.files, Path::new(&location), &safety);
+            add_sweep(&mut result, category, sweep);
+        }
+
+        tally.finish();
+        result.interrupted = cancel.is_cancelled();
+        Ok(result)
+    }
+
+    /// Remove an empty directory tree, deepest directories first
+    ///
+    /// A directory is only removed if it holds nothing but `.DS_Store` files
+    /// once its subdirectories are gone, so anything created since the tree
+    /// was found keeps that directory and its ancestors. Protected
+    /// directories are refused, which keeps their ancestors as well.
+    pub fn prune_empty_tree(&self, root: &Path) -> Result<PruneResult> {
+        let mut result = PruneResult::default();
+        let safety = SafetyPolicy::paths_only();
+        if let Some(skipped) = safety.refuse(root) {
+            result.skipped.push(skipped);
+            return Ok(result);
+        }
+
+        let entries = walkdir::WalkDir::new(root)
+            .follow_links(false)
+            .contents_first(true);
+        for entry in entries.into_iter().flatten() {
+            if !entry.file_type().is_dir() {
+                continue;
+            }
+            let dir = entry.path();
+            if let Some(skipped) = safety.refuse(dir) {
+                result.skipped.push(skipped);
+                continue;
+            }
+            match remove_empty_dir(dir) {
+                Ok(true) => result.dirs_removed.push(dir.to_path_buf()),
+                Ok(false) => {}
+                Err(err) => result
+                    .skipped
+                    .extend(SkippedPath::from_io_error(&dir.to_string_lossy(), &err)),
+            }
+        }
+        Ok(result)
     }
 
     /// Clean caches
@@ -91,44 +486,371 @@ fn expand_path(path: &str) -> Result<String> {
     }
 }
 
-/// Scan directory and return files with sizes
-fn scan_directory(path: &Path) -> Result<(Vec<PathBuf>, u64)> {
-    let mut files = Vec::new();
-    let mut total_size = 0u64;
+/// The moment `days` days ago
+fn cutoff(days: u32) -> DateTime<Utc> {
+    Utc::now() - chrono::Duration::days(i64::from(days))
+}
+
+/// Whether a file modified as `metadata` says is newer than `cutoff`
+///
+/// Files whose modification time can't be read count as recent, so an age
+/// limit never cleans them.
+fn is_recent(metadata: &fs::Metadata, cutoff: Option<DateTime<Utc>>) -> bool {
+    cutoff.is_some_and(|cutoff| {
+        metadata
+            .modified()
+            .map_or(true, |modified| DateTime::<Utc>::from(modified) > cutoff)
+    })
+}
 
-    for entry in WalkDir::new(path).into_iter().flatten() {
+/// Whether a file modified as `metadata` says is still referenced by a
+/// snapshot taken at `snapshot`
+///
+/// Files whose modification time can't be read count as referenced, so
+/// estimates never overstate the space a clean gets back.
+fn is_pinned(metadata: &fs::Metadata, snapshot: Option<DateTime<Utc>>) -> bool {
+    snapshot.is_some_and(|snapshot| {
+        metadata
+            .modified()
+            .map_or(true, |modified| DateTime::<Utc>::from(modified) <= snapshot)
+    })
+}
+
+/// Category of a target location: its own for Xcode's, otherwise after
+/// the target it belongs to
+fn category_of(path: &str) -> &'static str {
+    match path {
+        xcode::DERIVED_DATA => return "derived-data",
+        xcode::ARCHIVES => return "archives",
+        xcode::DEVICE_SUPPORT => return "device-support",
+        _ => {}
+    }
+    [
+        (CleanTarget::Caches, "cache"),
+        (CleanTarget::Logs, "logs"),
+        (CleanTarget::Temp, "temp"),
+    ]
+    .into_iter()
+    .find(|(target, _)| target.paths().contains(&path))
+    .map_or("cache", |(_, category)| category)
+}
+
+/// Scan options for the target location `path_str`, expanded to `path`
+///
+/// iOS DeviceSupport keeps the folder for the newest iOS version.
+fn location_options<'a>(
+    path_str: &str,
+    path: &Path,
+    options: &'a ScanOptions,
+) -> Cow<'a, ScanOptions> {
+    if path_str != xcode::DEVICE_SUPPORT {
+        return Cow::Borrowed(options);
+    }
+    match xcode::newest_device_support(path) {
+        Some(newest) => Cow::Owned(options.clone().exclude(newest.to_string_lossy())),
+        None => Cow::Borrowed(options),
+    }
+}
+
+/// Add what `sweep` found or removed in a location of `category` to
+/// `result`
+fn add_sweep(result: &mut CleanResult, category: &'static str, sweep: Sweep) {
+    add_category(
+        &mut result.categories,
+        CategoryTotal {
+            category,
+            items: sweep.files.len(),
+            bytes: sweep.bytes,
+        },
+    );
+    result.files_cleaned += sweep.files.len();
+    result.bytes_freed += sweep.bytes;
+    result.files_found.extend(sweep.files);
+    result.skipped.extend(sweep.skipped);
+    result.files_kept_recent += sweep.kept_recent;
+    result.bytes_retained_by_snapshots += sweep.retained;
+}
+
+/// Add `total` to the entry for its category, or as a new one if it has any
+/// items
+fn add_category(categories: &mut Vec<CategoryTotal>, total: CategoryTotal) {
+    match categories
+        .iter_mut()
+        .find(|existing| existing.category == total.category)
+    {
+        Some(existing) => {
+            existing.items += total.items;
+            existing.bytes += total.bytes;
+        }
+        None if total.items > 0 => categories.push(total),
+        None => {}
+    }
+}
+
+/// Find simulators whose runtime is gone, and delete them unless `dry_run`
+fn clean_simulators(dry_run: bool) -> Result<CategoryTotal> {
+    let simulators = xcode::unavailable_simulators()?;
+    if !dry_run && !simulators.is_empty() {
+        xcode::delete_unavailable_simulators()?;
+    }
+    Ok(CategoryTotal {
+        category: SIMULATORS,
+        items: simulators.len(),
+        bytes: simulators.iter().map(|simulator| simulator.size).sum(),
+    })
+}
+
+/// The recovery archive a clean moves files into
+#[derive(Debug)]
+struct Archive<'a> {
+    recovery: &'a RecoveryManager,
+    manifest: &'a mut RecoveryManifest,
+}
+
+/// Files found (or removed) under a single target directory
+#[derive(Debug, Default)]
+struct Sweep {
+    files: Vec<PathBuf>,
+    bytes: u64,
+    skipped: Vec<SkippedPath>,
+    kept_recent: usize,
+    /// Bytes of `bytes` a local snapshot still references
+    retained: u64,
+}
+
+/// Remove `dir` if it holds nothing but Finder metadata
+///
+/// Returns whether the directory was removed.
+fn remove_empty_dir(dir: &Path) -> std::io::Result<bool> {
+    let mut metadata_files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_name() != FINDER_METADATA || !entry.file_type()?.is_file() {
+            return Ok(false);
+        }
+        metadata_files.push(entry.path());
+    }
+    for file in metadata_files {
+        fs::remove_file(file)?;
+    }
+    fs::remove_dir(dir)?;
+    Ok(true)
+}
+
+/// Scan directory and return files with sizes, leaving out those `safety`
+/// refuses; the sizes of files older than `snapshot` are counted as retained
+fn scan_directory(
+    path: &Path,
+    cutoff: Option<DateTime<Utc>>,
+    snapshot: Option<DateTime<Utc>>,
+    options: &ScanOptions,
+    safety: &SafetyPolicy,
+    cancel: &CancellationToken,
+) -> Result<Sweep> {
+    let mut sweep = Sweep::default();
+
+    for entry in walker(path, options) {
+        if cancel.is_cancelled() {
+            break;
+        }
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                sweep.skipped.extend(skipped_from_walk_error(&err));
+                continue;
+            }
+        };
         if entry.file_type().is_file() {
+            let file_path = entry.path();
+            if let Some(skipped) = safety.refuse(&file_path) {
+                sweep.skipped.push(skipped);
+                continue;
+            }
             if let Ok(metadata) = entry.metadata() {
-                let size = metadata.len();
-                total_size += size;
-                files.push(entry.path().to_path_buf());
+                if is_recent(&metadata, cutoff) {
+                    sweep.kept_recent += 1;
+                    continue;
+                }
+                if is_pinned(&metadata, snapshot) {
+                    sweep.retained += metadata.len();
+                }
+                sweep.bytes += metadata.len();
+                sweep.files.push(file_path);
             }
         }
     }
 
-    Ok((files, total_size))
+    Ok(sweep)
 }
 
-/// Clean directory (delete files)
-fn clean_directory(path: &Path) -> Result<(Vec<PathBuf>, u64)> {
-    let mut files = Vec::new();
-    let mut total_size = 0u64;
+/// Clean directory: delete its files, or move them into `archive` under
+/// `category`, except those `safety` refuses
+///
+/// The walk finds the files first; a bounded number of workers on the
+/// shared pool then remove them, each taking a contiguous run so that
+/// workers mostly stay out of each other's directories. Every file removed
+/// is added to `tally`. The sizes of files older than `snapshot` are
+/// counted as retained.
+#[allow(clippy::too_many_arguments)]
+fn clean_directory(
+    path: &Path,
+    cutoff: Option<DateTime<Utc>>,
+    snapshot: Option<DateTime<Utc>>,
+    options: &ScanOptions,
+    safety: &SafetyPolicy,
+    cancel: &CancellationToken,
+    archive: Option<&mut Archive<'_>>,
+    category: &str,
+    tally: &Tally<'_>,
+) -> Result<Sweep> {
+    let mut sweep = Sweep::default();
+    let mut candidates = Vec::new();
 
-    for entry in WalkDir::new(path).into_iter().flatten() {
+    for entry in walker(path, options) {
+        if cancel.is_cancelled() {
+            break;
+        }
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                sweep.skipped.extend(skipped_from_walk_error(&err));
+                continue;
+            }
+        };
         if entry.file_type().is_file() {
+            let file_path = entry.path();
+            if let Some(skipped) = safety.refuse(&file_path) {
+                sweep.skipped.push(skipped);
+                continue;
+            }
             if let Ok(metadata) = entry.metadata() {
-                let size = metadata.len();
-                let file_path = entry.path().to_path_buf();
+                if is_recent(&metadata, cutoff) {
+                    sweep.kept_recent += 1;
+                    continue;
+                }
+                let pinned = is_pinned(&metadata, snapshot);
+                candidates.push((file_path, metadata.len(), pinned));
+            }
+        }
+    }
+
+    let archive = archive.map(|archive| {
+        (
+            archive.recovery,
+            archive.manifest.id.clone(),
+            Mutex::new(&mut *archive.manifest),
+        )
+    });
+    let remove = |file_path: &Path| match &archive {
+        Some((recovery, recovery_id, manifest)) => recovery
+            .archive_entry(recovery_id, file_path, category, CLEAN_SOURCE)
+            .map(|item| {
+                let mut manifest = manifest.lock().unwrap_or_else(|e| e.into_inner());
+                manifest.total_size += item.size;
+                manifest.items.push(item);
+            }),
+        None => fs::remove_file(file_path),
+    };
+    let workers = options
+        .threads
+        .unwrap_or(DELETE_WORKERS)
+        .min(rayon::current_num_threads())
+        .max(1);
+    let run = candidates.len().div_ceil(workers).max(1);
+    let outcomes: Vec<(&PathBuf, u64, bool, std::io::Result<()>)> = candidates
+        .par_chunks(run)
+        .flat_map_iter(|run| {
+            run.iter().map_while(|(file_path, size, pinned)| {
+                if cancel.is_cancelled() {
+                    return None;
+                }
+                let removed = remove(file_path);
+                if removed.is_ok() {
+                    tally.add(*size);
+                }
+                Some((file_path, *size, *pinned, removed))
+            })
+        })
+        .collect();
 
-                if fs::remove_file(&file_path).is_ok() {
-                    total_size += size;
-                    files.push(file_path);
+    for (file_path, size, pinned, removed) in outcomes {
+        match removed {
+            Ok(()) => {
+                if pinned {
+                    sweep.retained += size;
                 }
+                sweep.bytes += size;
+                sweep.files.push(file_path.clone());
             }
+            Err(err) => {
+                sweep.skipped.extend(SkippedPath::from_io_error(
+                    &file_path.to_string_lossy(),
+                    &err,
+                ));
+            }
+        }
+    }
+
+    Ok(sweep)
+}
+
+/// Remove the folders under `location` that `removed` files left empty,
+/// deepest first
+///
+/// Folders directly inside the location are kept even when empty: apps
+/// expect their own cache or log folder to be there. Returns how many
+/// folders were removed.
+fn remove_emptied_dirs(removed: &[PathBuf], location: &Path, safety: &SafetyPolicy) -> usize {
+    let mut dirs: BTreeSet<&Path> = BTreeSet::new();
+    for file in removed {
+        dirs.extend(
+            file.ancestors()
+                .skip(1)
+                .take_while(|dir| dir.starts_with(location) && dir.parent() != Some(location))
+                .filter(|dir| *dir != location),
+        );
+    }
+    let mut dirs: Vec<&Path> = dirs.into_iter().collect();
+    dirs.sort_by_key(|dir| std::cmp::Reverse(dir.components().count()));
+    dirs.into_iter()
+        .filter(|dir| safety.check(dir).is_none())
+        // Fails, leaving the folder, unless it's empty
+        .filter(|dir| fs::remove_dir(dir).is_ok())
+        .count()
+}
+
+/// Files removed and bytes freed so far by a clean, reported as they grow
+struct Tally<'a> {
+    files: AtomicU64,
+    bytes: AtomicU64,
+    progress: &'a dyn ProgressReporter,
+}
+
+impl<'a> Tally<'a> {
+    fn new(progress: &'a dyn ProgressReporter) -> Self {
+        Self {
+            files: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+            progress,
+        }
+    }
+
+    /// Count a removed file of `size` bytes
+    fn add(&self, size: u64) {
+        let files = self.files.fetch_add(1, Ordering::Relaxed) + 1;
+        let bytes = self.bytes.fetch_add(size, Ordering::Relaxed) + size;
+        if files % REPORT_EVERY == 0 {
+            self.progress.report(&ProgressUpdate::new(files, bytes));
         }
     }
 
-    Ok((files, total_size))
+    /// Report the final totals
+    fn finish(&self) {
+        self.progress.report(&ProgressUpdate::new(
+            self.files.load(Ordering::Relaxed),
+            self.bytes.load(Ordering::Relaxed),
+        ));
+    }
 }
 
 impl Default for SystemCleaner {
@@ -140,6 +862,7 @@ impl Default for SystemCleaner {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use dragonfly_core::domain::policy::ProtectionPolicy;
     use std::fs;
     use tempfile::TempDir;
 
@@ -159,6 +882,374 @@ mod tests {
         assert_eq!(absolute, "/tmp/test");
     }
 
+    #[test]
+    fn test_refuse_protected_paths() {
+        let safety = SafetyPolicy::paths_only();
+        let refused = safety
+            .refuse(Path::new("/System/Library/Caches/com.apple.foo"))
+            .unwrap();
+        assert_eq!(refused.reason, SkipReason::SipProtected);
+        assert!(safety.refuse(Path::new("/tmp/cache/file.bin")).is_none());
+    }
+
+    #[test]
+    fn test_scan_directory_reports_files() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("a.log"), b"12345").unwrap();
+
+        let sweep = scan_directory(
+            temp_dir.path(),
+            None,
+            None,
+            &ScanOptions::default(),
+            &SafetyPolicy::default(),
+            &CancellationToken::new(),
+        )
+        .unwrap();
+        assert_eq!(sweep.files.len(), 1);
+        assert_eq!(sweep.bytes, 5);
+        assert!(sweep.skipped.is_empty());
+        assert!(temp_dir.path().join("a.log").exists());
+    }
+
+    #[test]
+    fn test_clean_directory_stops_when_cancelled() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("a.log"), b"12345").unwrap();
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        let sweep = clean_directory(
+            temp_dir.path(),
+            None,
+            None,
+            &ScanOptions::default(),
+            &SafetyPolicy::default(),
+            &cancel,
+            None,
+            "cache",
+            &Tally::new(&NoProgress),
+        )
+        .unwrap();
+        assert!(sweep.files.is_empty());
+        assert!(temp_dir.path().join("a.log").exists());
+    }
+
+    #[test]
+    fn should_archive_files_before_removing_them() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = temp_dir.path().join("cache");
+        fs::create_dir_all(cache.join("app")).unwrap();
+        fs::write(cache.join("app/blob.bin"), b"cached").unwrap();
+        let recovery = RecoveryManager::new(temp_dir.path().join("recovery"));
+        recovery.initialize().unwrap();
+        let mut manifest = recovery.create_manifest(30);
+
+        let mut archive = Archive {
+            recovery: &recovery,
+            manifest: &mut manifest,
+        };
+        let sweep = clean_directory(
+            &cache,
+            None,
+            None,
+            &ScanOptions::default(),
+            &SafetyPolicy::default(),
+            &CancellationToken::new(),
+            Some(&mut archive),
+            "cache",
+            &Tally::new(&NoProgress),
+        )
+        .unwrap();
+        recovery.save_manifest(&manifest).unwrap();
+
+        assert_eq!((sweep.files.len(), sweep.bytes), (1, 6));
+        assert!(!cache.join("app/blob.bin").exists());
+        assert_eq!(manifest.items.len(), 1);
+        assert_eq!(manifest.items[0].category, "cache");
+        assert!(!manifest.items[0].checksum.is_empty());
+        assert!(recovery.verify_recovery(&manifest.id).unwrap().is_empty());
+
+        recovery.restore_recovery(&manifest.id).unwrap();
+        assert_eq!(fs::read(cache.join("app/blob.bin")).unwrap(), b"cached");
+    }
+
+    #[tokio::test]
+    async fn should_clean_only_the_chosen_groups() {
+        let temp_dir = TempDir::new().unwrap();
+        let chosen = temp_dir.path().join("com.example.chosen");
+        let declined = temp_dir.path().join("com.example.declined");
+        let loose = temp_dir.path().join("loose.db");
+        fs::create_dir_all(chosen.join("blobs")).unwrap();
+        fs::create_dir_all(&declined).unwrap();
+        fs::write(chosen.join("blobs/a.bin"), b"12345").unwrap();
+        fs::write(declined.join("b.bin"), b"123").unwrap();
+        fs::write(&loose, b"12").unwrap();
+        let group = |path: &Path| CleanGroup {
+            location: "~/Library/Caches",
+            path: path.to_path_buf(),
+            files: 1,
+            bytes: 0,
+        };
+
+        let result = SystemCleaner::new()
+            .clean_groups(
+                &[group(&chosen), group(&loose)],
+                None,
+                &ScanOptions::default(),
+                &CancellationToken::new(),
+                &NoProgress,
+            )
+            .await
+            .unwrap();
+        assert_eq!((result.files_cleaned, result.bytes_freed), (2, 7));
+        assert_eq!(result.categories[0].category, "cache");
+        assert!(!chosen.join("blobs/a.bin").exists());
+        assert!(!loose.exists());
+        assert!(declined.join("b.bin").exists());
+    }
+
+    #[test]
+    fn should_report_progress_while_removing_files() {
+        let temp_dir = TempDir::new().unwrap();
+        for dir in 0..4 {
+            let dir = temp_dir.path().join(format!("d{dir}"));
+            fs::create_dir(&dir).unwrap();
+            for file in 0..300 {
+                fs::write(dir.join(format!("{file}.bin")), b"12").unwrap();
+            }
+        }
+        let reports = Mutex::new(Vec::new());
+        let reporter = |update: &ProgressUpdate| reports.lock().unwrap().push(update.clone());
+        let tally = Tally::new(&reporter);
+
+        let sweep = clean_directory(
+            temp_dir.path(),
+            None,
+            None,
+            &ScanOptions::default(),
+            &SafetyPolicy::default(),
+            &CancellationToken::new(),
+            None,
+            "cache",
+            &tally,
+        )
+        .unwrap();
+        tally.finish();
+
+        assert_eq!((sweep.files.len(), sweep.bytes), (1200, 2400));
+        let reports = reports.into_inner().unwrap();
+        assert_eq!(reports.len(), 3);
+        assert_eq!(reports[0].items_processed, 500);
+        assert_eq!(reports[2], ProgressUpdate::new(1200, 2400));
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 4);
+    }
+
+    #[test]
+    fn test_remove_emptied_dirs() {
+        let temp_dir = TempDir::new().unwrap();
+        let location = temp_dir.path();
+        fs::create_dir_all(location.join("app/blobs/0a")).unwrap();
+        fs::create_dir_all(location.join("app/index")).unwrap();
+        fs::write(location.join("app/index/keep.db"), b"db").unwrap();
+        let removed = [
+            location.join("app/blobs/0a/gone.bin"),
+            location.join("app/index/gone.bin"),
+        ];
+
+        let count = remove_emptied_dirs(&removed, location, &SafetyPolicy::default());
+        assert_eq!(count, 2);
+        assert!(!location.join("app/blobs").exists());
+        assert!(location.join("app/index/keep.db").exists());
+    }
+
+    #[test]
+    fn should_skip_files_held_open() {
+        let temp_dir = TempDir::new().unwrap();
+        let open = temp_dir.path().join("open.log");
+        fs::write(&open, b"12345").unwrap();
+        fs::write(temp_dir.path().join("closed.log"), b"123").unwrap();
+        let safety = SafetyPolicy::new(
+            ProtectionPolicy::default(),
+            std::collections::HashSet::from([open.clone()]),
+        );
+
+        let sweep = clean_directory(
+            temp_dir.path(),
+            None,
+            None,
+            &ScanOptions::default(),
+            &safety,
+            &CancellationToken::new(),
+            None,
+            "logs",
+            &Tally::new(&NoProgress),
+        )
+        .unwrap();
+        assert_eq!(sweep.bytes, 3);
+        assert_eq!(
+            sweep.skipped,
+            [SkippedPath::new(
+                open.to_string_lossy().to_string(),
+                SkipReason::InUse
+            )]
+        );
+        assert!(open.exists());
+    }
+
+    #[test]
+    fn should_keep_recently_modified_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let old = temp_dir.path().join("old.log");
+        let new = temp_dir.path().join("new.log");
+        fs::write(&old, b"12345").unwrap();
+        fs::write(&new, b"123").unwrap();
+        let ten_days_ago =
+            std::time::SystemTime::now() - std::time::Duration::from_secs(10 * 86400);
+        fs::File::options()
+            .write(true)
+            .open(&old)
+            .unwrap()
+            .set_modified(ten_days_ago)
+            .unwrap();
+
+        let cancel = CancellationToken::new();
+        let sweep = scan_directory(
+            temp_dir.path(),
+            Some(cutoff(7)),
+            None,
+            &ScanOptions::default(),
+            &SafetyPolicy::default(),
+            &cancel,
+        )
+        .unwrap();
+        assert_eq!(
+            (sweep.files, sweep.bytes, sweep.kept_recent),
+            (vec![old.clone()], 5, 1)
+        );
+
+        let sweep = clean_directory(
+            temp_dir.path(),
+            Some(cutoff(7)),
+            None,
+            &ScanOptions::default(),
+            &SafetyPolicy::default(),
+            &cancel,
+            None,
+            "logs",
+            &Tally::new(&NoProgress),
+        )
+        .unwrap();
+        assert_eq!(sweep.kept_recent, 1);
+        assert!(!old.exists());
+        assert!(new.exists());
+    }
+
+    #[test]
+    fn should_count_files_older_than_the_snapshot_as_retained() {
+        let temp_dir = TempDir::new().unwrap();
+        let old = temp_dir.path().join("old.log");
+        fs::write(&old, b"12345").unwrap();
+        fs::write(temp_dir.path().join("new.log"), b"123").unwrap();
+        let ten_days_ago =
+            std::time::SystemTime::now() - std::time::Duration::from_secs(10 * 86400);
+        fs::File::options()
+            .write(true)
+            .open(&old)
+            .unwrap()
+            .set_modified(ten_days_ago)
+            .unwrap();
+
+        let sweep = clean_directory(
+            temp_dir.path(),
+            None,
+            Some(cutoff(7)),
+            &ScanOptions::default(),
+            &SafetyPolicy::default(),
+            &CancellationToken::new(),
+            None,
+            "logs",
+            &Tally::new(&NoProgress),
+        )
+        .unwrap();
+        assert_eq!((sweep.bytes, sweep.retained), (8, 5));
+
+        let mut result = CleanResult::default();
+        add_sweep(&mut result, "logs", sweep);
+        assert_eq!(result.bytes_retained_by_snapshots, 5);
+        assert_eq!(result.bytes_reclaimable(), 3);
+        assert!(result.snapshots_dominate());
+    }
+
+    #[test]
+    fn should_keep_newest_device_support() {
+        let temp_dir = TempDir::new().unwrap();
+        for version in ["16.4 (20E247)", "17.2 (21C62)"] {
+            let symbols = temp_dir.path().join(version).join("Symbols");
+            fs::create_dir_all(&symbols).unwrap();
+            fs::write(symbols.join("dyld"), b"symbols").unwrap();
+        }
+
+        let default = ScanOptions::default();
+        let options = location_options(xcode::DEVICE_SUPPORT, temp_dir.path(), &default);
+        let sweep = scan_directory(
+            temp_dir.path(),
+            None,
+            None,
+            &options,
+            &SafetyPolicy::default(),
+            &CancellationToken::new(),
+        )
+        .unwrap();
+        assert_eq!(
+            sweep.files,
+            [temp_dir.path().join("16.4 (20E247)/Symbols/dyld")]
+        );
+
+        let options = location_options(xcode::DERIVED_DATA, temp_dir.path(), &default);
+        assert!(matches!(options, Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_add_category() {
+        let mut categories = Vec::new();
+        let total = |category, items, bytes| CategoryTotal {
+            category,
+            items,
+            bytes,
+        };
+        add_category(&mut categories, total("cache", 2, 10));
+        add_category(&mut categories, total("logs", 0, 0));
+        add_category(&mut categories, total("cache", 1, 5));
+        assert_eq!(categories, [total("cache", 3, 15)]);
+    }
+
+    #[test]
+    fn test_category_of() {
+        assert_eq!(category_of("~/Library/Caches"), "cache");
+        assert_eq!(category_of("/var/log"), "logs");
+        assert_eq!(category_of("/var/tmp"), "temp");
+        assert_eq!(category_of(xcode::DEVICE_SUPPORT), "device-support");
+    }
+
+    #[test]
+    fn test_prune_empty_tree_keeps_content() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("old");
+        fs::create_dir_all(root.join("build/debug")).unwrap();
+        fs::create_dir_all(root.join("cache")).unwrap();
+        fs::write(root.join("build/.DS_Store"), b"finder").unwrap();
+        fs::write(root.join("cache/new.bin"), b"data").unwrap();
+
+        let result = SystemCleaner::new().prune_empty_tree(&root).unwrap();
+
+        assert_eq!(result.dirs_removed.len(), 2);
+        assert!(!root.join("build").exists());
+        assert!(root.join("cache/new.bin").exists());
+        assert!(result.skipped.is_empty());
+    }
+
     #[tokio::test]
     async fn test_clean_dry_run() {
         let temp_dir = TempDir::new().unwrap();
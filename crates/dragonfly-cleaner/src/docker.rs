@@ -0,0 +1,308 @@
+//! Docker storage
+//!
+//! Images, containers and build cache live inside Docker's own storage: the
+//! Docker Desktop disk image on macOS, or the engine's data directories on
+//! Linux. They can't be cleaned as files, so usage is read from the `docker`
+//! CLI and space is reclaimed with its prune commands. Docker Desktop hands
+//! freed blocks back to the host in the background, so its disk image
+//! shrinks some time after a prune.
+
+use dragonfly_core::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Where Docker Desktop keeps its disk image, under the home directory
+const DESKTOP_DISK_IMAGES: [&str; 2] = [
+    "Library/Containers/com.docker.docker/Data/vms/0/data/Docker.raw",
+    ".docker/desktop/vms/0/data/Docker.raw",
+];
+
+/// Data directories of the Docker engine and containerd on Linux
+const ENGINE_DIRS: [&str; 2] = ["/var/lib/docker", "/var/lib/containerd"];
+
+/// Things a prune would remove, and the space they take
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Reclaimable {
+    /// Images, containers or cache entries
+    pub count: usize,
+    /// Bytes they take
+    pub bytes: u64,
+}
+
+/// Docker's storage on this machine
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DockerStorage {
+    /// Disk image or data directory
+    pub path: PathBuf,
+    /// Bytes it takes on disk
+    pub size_on_disk: u64,
+}
+
+/// What Docker stores and how much of it a prune would reclaim
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DockerUsage {
+    /// Where the data lives, if found and readable
+    pub storage: Vec<DockerStorage>,
+    /// Untagged images no other image builds on
+    pub dangling_images: Reclaimable,
+    /// Containers that exited or were never started
+    pub stopped_containers: Reclaimable,
+    /// Build cache no image uses
+    pub build_cache: Reclaimable,
+}
+
+impl DockerUsage {
+    /// Images, containers and cache entries a prune would remove
+    pub fn reclaimable_items(&self) -> usize {
+        self.dangling_images.count + self.stopped_containers.count + self.build_cache.count
+    }
+
+    /// Bytes a prune would reclaim
+    pub fn reclaimable_bytes(&self) -> u64 {
+        self.dangling_images.bytes + self.stopped_containers.bytes + self.build_cache.bytes
+    }
+}
+
+/// Docker's storage and what a prune would reclaim, or `None` where Docker
+/// isn't installed
+///
+/// Dangling images are counted at their full size, so layers they share
+/// make the estimate an upper bound.
+///
+/// # Errors
+///
+/// Fails if the Docker daemon isn't running or doesn't answer.
+pub fn usage() -> Result<Option<DockerUsage>> {
+    let Some(usage) = docker(&["system", "df", "--format", "{{json .}}"])? else {
+        return Ok(None);
+    };
+    let rows = parse_lines::<UsageRow>(&usage)?;
+    let images = docker(&[
+        "image",
+        "ls",
+        "--filter",
+        "dangling=true",
+        "--format",
+        "{{json .}}",
+    ])?
+    .unwrap_or_default();
+    let dangling_images =
+        parse_lines::<ImageRow>(&images)?
+            .iter()
+            .fold(Reclaimable::default(), |total, image| Reclaimable {
+                count: total.count + 1,
+                bytes: total.bytes + parse_size(&image.size).unwrap_or(0),
+            });
+
+    let reclaimable = |kind: &str| {
+        rows.iter()
+            .find(|row| row.kind == kind)
+            .map_or_else(Reclaimable::default, UsageRow::reclaimable)
+    };
+    Ok(Some(DockerUsage {
+        storage: storage(),
+        dangling_images,
+        stopped_containers: reclaimable("Containers"),
+        build_cache: reclaimable("Build Cache"),
+    }))
+}
+
+/// Remove stopped containers, dangling images and unused build cache
+///
+/// Returns the bytes Docker reports reclaiming.
+///
+/// # Errors
+///
+/// Fails if Docker isn't installed or a prune command fails.
+pub fn prune() -> Result<u64> {
+    let mut reclaimed = 0;
+    for kind in ["container", "image", "builder"] {
+        let output = docker(&[kind, "prune", "--force"])?
+            .ok_or_else(|| Error::NotFound("Docker is not installed".to_string()))?;
+        reclaimed += parse_reclaimed(&output).unwrap_or(0);
+    }
+    Ok(reclaimed)
+}
+
+/// Output of `docker` with `args`, or `None` if Docker isn't installed
+fn docker(args: &[&str]) -> Result<Option<String>> {
+    let output = match Command::new("docker").args(args).output() {
+        Ok(output) => output,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(Error::Internal(format!("Failed to run docker: {}", e))),
+    };
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::Internal(format!(
+            "docker {} failed: {}",
+            args[..2].join(" "),
+            stderr.trim()
+        )));
+    }
+    Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()))
+}
+
+/// A row of `docker system df --format '{{json .}}'`
+#[derive(Deserialize)]
+struct UsageRow {
+    #[serde(rename = "Type")]
+    kind: String,
+    #[serde(rename = "TotalCount")]
+    total_count: String,
+    #[serde(rename = "Active")]
+    active: String,
+    #[serde(rename = "Reclaimable")]
+    reclaimable: String,
+}
+
+impl UsageRow {
+    /// Inactive entries and the space Docker says they free, e.g. from
+    /// `1.2GB (45%)`
+    fn reclaimable(&self) -> Reclaimable {
+        let count = |value: &str| value.trim().parse::<usize>().unwrap_or(0);
+        let bytes = self.reclaimable.split_whitespace().next().unwrap_or("");
+        Reclaimable {
+            count: count(&self.total_count).saturating_sub(count(&self.active)),
+            bytes: parse_size(bytes).unwrap_or(0),
+        }
+    }
+}
+
+/// A row of `docker image ls --format '{{json .}}'`
+#[derive(Deserialize)]
+struct ImageRow {
+    #[serde(rename = "Size")]
+    size: String,
+}
+
+/// One JSON value per non-empty line
+fn parse_lines<T: for<'de> Deserialize<'de>>(output: &str) -> Result<Vec<T>> {
+    output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line)
+                .map_err(|e| Error::Internal(format!("Unexpected docker output: {}", e)))
+        })
+        .collect()
+}
+
+/// Bytes in a size as Docker prints it: `0B`, `12.5kB`, `1.2GB`, with
+/// decimal units
+fn parse_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let split = size.find(|c: char| c.is_ascii_alphabetic())?;
+    let (number, unit) = size.split_at(split);
+    let multiplier: f64 = match unit.to_ascii_uppercase().as_str() {
+        "B" => 1.0,
+        "KB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "TB" => 1e12,
+        "PB" => 1e15,
+        _ => return None,
+    };
+    let number: f64 = number.trim().parse().ok()?;
+    Some((number * multiplier).round() as u64)
+}
+
+/// Bytes in the `Total reclaimed space: 1.2GB` line a prune ends with
+fn parse_reclaimed(output: &str) -> Option<u64> {
+    output
+        .lines()
+        .find_map(|line| line.strip_prefix("Total reclaimed space:"))
+        .and_then(parse_size)
+}
+
+/// Docker Desktop's disk image, or the engine's data directories
+fn storage() -> Vec<DockerStorage> {
+    let home = dirs::home_dir().unwrap_or_default();
+    let images: Vec<DockerStorage> = DESKTOP_DISK_IMAGES
+        .iter()
+        .map(|image| home.join(image))
+        .filter_map(|path| {
+            let metadata = std::fs::metadata(&path).ok()?;
+            Some(DockerStorage {
+                size_on_disk: size_on_disk(&metadata),
+                path,
+            })
+        })
+        .collect();
+    if !images.is_empty() {
+        return images;
+    }
+    ENGINE_DIRS
+        .iter()
+        .map(Path::new)
+        .filter(|dir| std::fs::read_dir(dir).is_ok())
+        .map(|dir| DockerStorage {
+            path: dir.to_path_buf(),
+            size_on_disk: tree_size_on_disk(dir),
+        })
+        .collect()
+}
+
+/// Bytes a file takes on disk, which for a sparse disk image is far less
+/// than its length
+#[cfg(unix)]
+fn size_on_disk(metadata: &std::fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.blocks() * 512
+}
+
+#[cfg(not(unix))]
+fn size_on_disk(metadata: &std::fs::Metadata) -> u64 {
+    metadata.len()
+}
+
+/// Bytes the readable files under `dir` take on disk
+fn tree_size_on_disk(dir: &Path) -> u64 {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| size_on_disk(&metadata))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("0B"), Some(0));
+        assert_eq!(parse_size("12.5kB"), Some(12_500));
+        assert_eq!(parse_size("1.2GB"), Some(1_200_000_000));
+        assert_eq!(parse_size(" 850MB "), Some(850_000_000));
+        assert_eq!(parse_size("lots"), None);
+        assert_eq!(
+            parse_reclaimed("Deleted build cache objects:\nabc\n\nTotal reclaimed space: 3.4GB\n"),
+            Some(3_400_000_000)
+        );
+    }
+
+    #[test]
+    fn test_parse_usage_rows() {
+        let output = r#"{"Active":"2","Reclaimable":"1.5GB (60%)","Size":"2.5GB","TotalCount":"5","Type":"Images"}
+{"Active":"1","Reclaimable":"20MB (10%)","Size":"200MB","TotalCount":"4","Type":"Containers"}
+
+{"Active":"0","Reclaimable":"3GB","Size":"3GB","TotalCount":"12","Type":"Build Cache"}
+"#;
+        let rows = parse_lines::<UsageRow>(output).unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(
+            rows[1].reclaimable(),
+            Reclaimable {
+                count: 3,
+                bytes: 20_000_000
+            }
+        );
+        assert_eq!(rows[2].kind, "Build Cache");
+        assert_eq!(rows[2].reclaimable().bytes, 3_000_000_000);
+        assert!(parse_lines::<UsageRow>("not json").is_err());
+    }
+}
@@ -0,0 +1,142 @@
+//! History of cleans
+//!
+//! Every clean, dry runs included, is recorded as one JSON line in
+//! `~/.dragonfly/history.jsonl`: when it ran, what it targeted, and how many
+//! files and bytes it removed (or would have). Unlike recovery manifests,
+//! which expire, the history is kept for good, and unlike the audit log it
+//! covers only cleans, one record per run.
+
+use crate::jsonl;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// A single clean
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CleanRecord {
+    /// When the clean finished
+    pub timestamp: DateTime<Utc>,
+    /// What was cleaned, e.g. `Caches`, `Xcode` or `Docker`
+    pub target: String,
+    /// `user` or `system` when the clean was limited to one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// Whether the clean only reported what it would remove
+    pub dry_run: bool,
+    /// Files (or other items, such as backups) removed or found
+    pub files: usize,
+    /// Bytes they take
+    pub bytes: u64,
+    /// Recovery holding the removed files, if they were archived
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recovery_id: Option<String>,
+    /// Whether the clean was cancelled before it finished
+    #[serde(default)]
+    pub interrupted: bool,
+}
+
+impl CleanRecord {
+    /// Create a record of a clean of `target` finishing now
+    pub fn new(target: impl Into<String>, dry_run: bool) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            target: target.into(),
+            scope: None,
+            dry_run,
+            files: 0,
+            bytes: 0,
+            recovery_id: None,
+            interrupted: false,
+        }
+    }
+
+    /// Set the scope the clean was limited to
+    pub fn with_scope(mut self, scope: Option<impl Into<String>>) -> Self {
+        self.scope = scope.map(Into::into);
+        self
+    }
+
+    /// Set the files and bytes removed or found
+    pub fn with_totals(mut self, files: usize, bytes: u64) -> Self {
+        self.files = files;
+        self.bytes = bytes;
+        self
+    }
+
+    /// Set the recovery holding the removed files
+    pub fn with_recovery_id(mut self, recovery_id: Option<impl Into<String>>) -> Self {
+        self.recovery_id = recovery_id.map(Into::into);
+        self
+    }
+
+    /// Mark the clean as cancelled partway
+    pub fn with_interrupted(mut self, interrupted: bool) -> Self {
+        self.interrupted = interrupted;
+        self
+    }
+}
+
+/// Append-only clean history stored as JSON Lines
+#[derive(Debug, Clone)]
+pub struct CleanHistory {
+    path: PathBuf,
+}
+
+impl CleanHistory {
+    /// Create a history backed by the given file
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Get default history location
+    pub fn default_path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_else(|| PathBuf::from("~"))
+            .join(".dragonfly")
+            .join("history.jsonl")
+    }
+
+    /// Path of the backing file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append a record to the history
+    pub fn record(&self, record: &CleanRecord) -> std::io::Result<()> {
+        jsonl::append(&self.path, record)
+    }
+
+    /// Read all records, oldest first
+    ///
+    /// Lines that cannot be parsed are skipped with a warning.
+    pub fn records(&self) -> std::io::Result<Vec<CleanRecord>> {
+        jsonl::read(&self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_record_and_read_history() {
+        let temp_dir = TempDir::new().unwrap();
+        let history = CleanHistory::new(temp_dir.path().join("nested").join("history.jsonl"));
+        assert!(history.records().unwrap().is_empty());
+
+        let dry_run = CleanRecord::new("Caches", true)
+            .with_scope(Some("user"))
+            .with_totals(12, 4096);
+        let clean = CleanRecord::new("Docker", false)
+            .with_totals(3, 1024)
+            .with_recovery_id(None::<String>)
+            .with_interrupted(true);
+        history.record(&dry_run).unwrap();
+        history.record(&clean).unwrap();
+
+        assert_eq!(history.records().unwrap(), [dry_run, clean]);
+        let line = std::fs::read_to_string(history.path()).unwrap();
+        assert!(!line.lines().nth(1).unwrap().contains("recovery_id"));
+    }
+}
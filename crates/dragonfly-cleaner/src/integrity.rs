@@ -0,0 +1,192 @@
+//! Integrity seals for the recovery store
+//!
+//! Each recovery manifest and the recovery index carry a seal: a keyed
+//! BLAKE3 hash of their contents under a random key kept next to them in
+//! `seal.key`. Anything that edits or corrupts a manifest without going
+//! through [`crate::RecoveryManager`] breaks its seal, so a restore never
+//! trusts paths that were changed behind DragonFly's back. The key lives in the
+//! same directory, so the seal guards against accidents and casual edits,
+//! not against someone determined to forge a manifest.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// File holding the seal key, inside the recovery directory
+const KEY_FILE: &str = "seal.key";
+
+/// Key the seals are computed with
+#[derive(Clone)]
+pub(crate) struct SealKey([u8; blake3::KEY_LEN]);
+
+impl std::fmt::Debug for SealKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SealKey(..)")
+    }
+}
+
+impl SealKey {
+    /// Key of the store at `recovery_dir`, if it has one
+    pub(crate) fn load(recovery_dir: &Path) -> std::io::Result<Option<Self>> {
+        let bytes = match std::fs::read(recovery_dir.join(KEY_FILE)) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let key = bytes.try_into().map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Recovery seal key has the wrong length",
+            )
+        })?;
+        Ok(Some(Self(key)))
+    }
+
+    /// Key of the store at `recovery_dir`, creating a random one if needed
+    pub(crate) fn load_or_create(recovery_dir: &Path) -> std::io::Result<Self> {
+        if let Some(key) = Self::load(recovery_dir)? {
+            return Ok(key);
+        }
+        let mut key = [0u8; blake3::KEY_LEN];
+        std::fs::File::open("/dev/urandom")?.read_exact(&mut key)?;
+
+        use std::os::unix::fs::OpenOptionsExt;
+        let created = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(recovery_dir.join(KEY_FILE));
+        match created {
+            Ok(mut file) => {
+                file.write_all(&key)?;
+                Ok(Self(key))
+            }
+            // Another process created it first; use theirs
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Self::load(recovery_dir)?
+                .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::NotFound)),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Seal of `contents`, as hex
+    pub(crate) fn seal(&self, contents: &[u8]) -> String {
+        blake3::keyed_hash(&self.0, contents).to_hex().to_string()
+    }
+}
+
+/// Something wrong with the recovery store
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "problem", rename_all = "snake_case")]
+pub enum IntegrityProblem {
+    /// Written before seals were introduced, so it can't be checked
+    Unsealed,
+    /// Contents don't match the seal: edited or corrupted since written
+    SealMismatch,
+    /// Sealed, but the seal key is gone
+    MissingKey,
+    /// Couldn't be read or parsed
+    Unreadable {
+        /// Underlying error
+        error: String,
+    },
+    /// Listed in the index, but the manifest is gone
+    MissingManifest,
+    /// The manifest exists, but the index doesn't list it
+    NotIndexed,
+    /// An archived item is gone
+    MissingItem {
+        /// Original location of the item
+        path: PathBuf,
+    },
+    /// An archived item's size differs from the manifest
+    SizeMismatch {
+        /// Original location of the item
+        path: PathBuf,
+        /// Size recorded in the manifest
+        expected: u64,
+        /// Size of the archived file
+        actual: u64,
+    },
+    /// An archived item's contents differ from the checksum taken when it
+    /// was archived
+    ChecksumMismatch {
+        /// Original location of the item
+        path: PathBuf,
+    },
+}
+
+impl IntegrityProblem {
+    /// Whether the problem means the store can't be trusted, as opposed to
+    /// merely not being checkable
+    pub fn is_damage(&self) -> bool {
+        !matches!(self, Self::Unsealed)
+    }
+}
+
+/// Result of checking the whole recovery store
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IntegrityReport {
+    /// Number of recoveries checked
+    pub checked: usize,
+    /// Problems with the index itself
+    pub index: Vec<IntegrityProblem>,
+    /// Problems by recovery ID; intact recoveries are left out
+    pub recoveries: BTreeMap<String, Vec<IntegrityProblem>>,
+}
+
+impl IntegrityReport {
+    /// Whether nothing in the store was damaged
+    pub fn is_intact(&self) -> bool {
+        self.index
+            .iter()
+            .chain(self.recoveries.values().flatten())
+            .all(|problem| !problem.is_damage())
+    }
+}
+
+/// Check `seal` against `contents`
+pub(crate) fn check_seal(
+    key: Option<&SealKey>,
+    seal: Option<&str>,
+    contents: &[u8],
+) -> Option<IntegrityProblem> {
+    match (seal, key) {
+        (None, _) => Some(IntegrityProblem::Unsealed),
+        (Some(_), None) => Some(IntegrityProblem::MissingKey),
+        (Some(seal), Some(key)) => {
+            (key.seal(contents) != seal).then_some(IntegrityProblem::SealMismatch)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_seal_key_is_created_once() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(SealKey::load(temp_dir.path()).unwrap().is_none());
+        let key = SealKey::load_or_create(temp_dir.path()).unwrap();
+        let again = SealKey::load_or_create(temp_dir.path()).unwrap();
+        assert_eq!(key.seal(b"manifest"), again.seal(b"manifest"));
+        assert_ne!(key.seal(b"manifest"), key.seal(b"manifest!"));
+
+        let seal = key.seal(b"manifest");
+        assert_eq!(check_seal(Some(&key), Some(&seal), b"manifest"), None);
+        assert_eq!(
+            check_seal(Some(&key), Some(&seal), b"edited"),
+            Some(IntegrityProblem::SealMismatch)
+        );
+        assert_eq!(
+            check_seal(None, Some(&seal), b"manifest"),
+            Some(IntegrityProblem::MissingKey)
+        );
+        assert_eq!(
+            check_seal(Some(&key), None, b"manifest"),
+            Some(IntegrityProblem::Unsealed)
+        );
+    }
+}
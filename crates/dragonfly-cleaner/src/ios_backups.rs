@@ -0,0 +1,224 @@
+//! iPhone and iPad backups
+//!
+//! Finder (and iTunes before it) keeps one folder per device backup in
+//! MobileSync, named after the device's identifier. Each holds an
+//! `Info.plist` naming the device and the date of its last backup, so
+//! backups of devices long since sold or replaced are easy to spot; they
+//! often take tens of gigabytes. Reading the folder needs Full Disk Access.
+
+use chrono::{DateTime, Utc};
+use dragonfly_core::error::{Error, Result};
+use serde::Serialize;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Where device backups are kept
+pub const BACKUP_DIR: &str = "~/Library/Application Support/MobileSync/Backup";
+
+/// Days after its last backup a backup counts as stale
+pub const STALE_AFTER_DAYS: u32 = 90;
+
+/// A device backup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IosBackup {
+    /// Backup folder
+    pub path: PathBuf,
+    /// Name the device had, e.g. `Anna's iPhone`
+    pub device_name: Option<String>,
+    /// Model identifier, e.g. `iPhone14,2`
+    pub product_type: Option<String>,
+    /// iOS or iPadOS version at the last backup
+    pub product_version: Option<String>,
+    /// When the device was last backed up
+    pub last_backup: Option<DateTime<Utc>>,
+    /// Bytes the backup takes
+    pub size: u64,
+}
+
+impl IosBackup {
+    /// Whether the device was last backed up before `cutoff`
+    ///
+    /// Backups whose date is unknown are never stale.
+    pub fn is_stale(&self, cutoff: DateTime<Utc>) -> bool {
+        self.last_backup.is_some_and(|date| date < cutoff)
+    }
+}
+
+/// The device backups in [`BACKUP_DIR`], oldest first
+///
+/// # Errors
+///
+/// Fails if the folder exists but can't be read, as happens without Full
+/// Disk Access.
+pub fn backups() -> Result<Vec<IosBackup>> {
+    let dir = dirs::home_dir()
+        .unwrap_or_default()
+        .join(BACKUP_DIR.trim_start_matches("~/"));
+    backups_in(&dir)
+}
+
+/// The device backups in `dir`, oldest first
+///
+/// Folders without an `Info.plist` aren't backups and are left out.
+pub fn backups_in(dir: &Path) -> Result<Vec<IosBackup>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+            return Err(Error::PermissionDenied(format!(
+                "Can't read {}; grant Full Disk Access to your terminal",
+                dir.display()
+            )))
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let mut backups: Vec<IosBackup> = entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+        .filter_map(|entry| read_backup(&entry.path()))
+        .collect();
+    backups.sort_by(|a, b| a.last_backup.cmp(&b.last_backup).then(a.path.cmp(&b.path)));
+    Ok(backups)
+}
+
+/// The backup in `path`, from its `Info.plist`
+fn read_backup(path: &Path) -> Option<IosBackup> {
+    let info_path = path.join("Info.plist");
+    let info = read_plist(&info_path)?;
+    let last_backup = plist_value(&info, "Last Backup Date", "date")
+        .and_then(|date| DateTime::parse_from_rfc3339(&date).ok())
+        .map(|date| date.with_timezone(&Utc))
+        // Finder rewrites Info.plist at the end of every backup
+        .or_else(|| {
+            let modified = std::fs::metadata(&info_path).ok()?.modified().ok()?;
+            Some(modified.into())
+        });
+    Some(IosBackup {
+        path: path.to_path_buf(),
+        device_name: plist_value(&info, "Device Name", "string")
+            .or_else(|| plist_value(&info, "Display Name", "string")),
+        product_type: plist_value(&info, "Product Type", "string"),
+        product_version: plist_value(&info, "Product Version", "string"),
+        last_backup,
+        size: tree_size(path),
+    })
+}
+
+/// A property list as XML
+///
+/// Binary ones are converted with `plutil`, which is only available on
+/// macOS.
+fn read_plist(path: &Path) -> Option<String> {
+    let content = std::fs::read(path).ok()?;
+    if !content.starts_with(b"bplist") {
+        return Some(String::from_utf8_lossy(&content).into_owned());
+    }
+    let output = Command::new("plutil")
+        .args(["-convert", "xml1", "-o", "-"])
+        .arg(path)
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Value of a top-level `key` of type `tag` in an XML property list
+fn plist_value(xml: &str, key: &str, tag: &str) -> Option<String> {
+    let after_key = xml.split_once(&format!("<key>{}</key>", key))?.1;
+    let value = after_key.trim_start().strip_prefix(&format!("<{}>", tag))?;
+    let value = value.split_once(&format!("</{}>", tag))?.0.trim();
+    (!value.is_empty()).then(|| unescape(value))
+}
+
+/// `text` with XML's predefined entities replaced
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Bytes the files under `path` take
+fn tree_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const INFO: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0">
+<dict>
+	<key>Build Version</key>
+	<string>20G75</string>
+	<key>Device Name</key>
+	<string>Anna&apos;s iPhone</string>
+	<key>Last Backup Date</key>
+	<date>2023-02-14T09:30:00Z</date>
+	<key>Product Type</key>
+	<string>iPhone12,1</string>
+	<key>Product Version</key>
+	<string>16.3</string>
+</dict>
+</plist>"#;
+
+    #[test]
+    fn test_plist_value() {
+        assert_eq!(
+            plist_value(INFO, "Device Name", "string").as_deref(),
+            Some("Anna's iPhone")
+        );
+        assert_eq!(
+            plist_value(INFO, "Last Backup Date", "date").as_deref(),
+            Some("2023-02-14T09:30:00Z")
+        );
+        assert_eq!(plist_value(INFO, "Last Backup Date", "string"), None);
+        assert_eq!(plist_value(INFO, "Serial Number", "string"), None);
+    }
+
+    #[test]
+    fn test_backups_oldest_first() {
+        let temp_dir = TempDir::new().unwrap();
+        let old = temp_dir.path().join("00008030-old");
+        std::fs::create_dir_all(old.join("0a")).unwrap();
+        std::fs::write(old.join("Info.plist"), INFO).unwrap();
+        std::fs::write(old.join("0a/0a1b2c"), [0; 1000]).unwrap();
+        let recent = temp_dir.path().join("00008101-recent");
+        std::fs::create_dir(&recent).unwrap();
+        std::fs::write(
+            recent.join("Info.plist"),
+            "<dict><key>Display Name</key><string>iPad</string></dict>",
+        )
+        .unwrap();
+        std::fs::create_dir(temp_dir.path().join("not-a-backup")).unwrap();
+
+        let backups = backups_in(temp_dir.path()).unwrap();
+        assert_eq!(backups.len(), 2);
+        assert_eq!(backups[0].path, old);
+        assert_eq!(backups[0].device_name.as_deref(), Some("Anna's iPhone"));
+        assert_eq!(backups[0].product_type.as_deref(), Some("iPhone12,1"));
+        assert_eq!(backups[0].size, 1000 + INFO.len() as u64);
+        // Without a date, the time Info.plist was written stands in
+        assert_eq!(backups[1].device_name.as_deref(), Some("iPad"));
+        assert!(backups[1].last_backup.is_some());
+
+        let cutoff = Utc::now() - chrono::Duration::days(STALE_AFTER_DAYS as i64);
+        assert!(backups[0].is_stale(cutoff));
+        assert!(!backups[1].is_stale(cutoff));
+        assert!(backups_in(&temp_dir.path().join("missing"))
+            .unwrap()
+            .is_empty());
+    }
+}
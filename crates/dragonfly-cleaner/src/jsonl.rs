@@ -0,0 +1,55 @@
+//! Append-only JSON Lines files shared by the audit log and clean history
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+/// Append `record` to `path` as one line, creating the file and its parent
+/// directories if needed
+pub(crate) fn append<T: Serialize>(path: &Path, record: &T) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut line = serde_json::to_string(record)?;
+    line.push('\n');
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(line.as_bytes())?;
+    file.sync_data()?;
+
+    Ok(())
+}
+
+/// Read every record in `path`, oldest first; a missing file has none
+///
+/// Lines that cannot be parsed are skipped with a warning so a single
+/// corrupted record doesn't hide the rest of the history.
+pub(crate) fn read<T: DeserializeOwned>(path: &Path) -> std::io::Result<Vec<T>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let file = std::fs::File::open(path)?;
+    let mut records = Vec::new();
+
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(record) => records.push(record),
+            Err(e) => tracing::warn!(
+                path = %path.display(),
+                line = number + 1,
+                error = %e,
+                "Skipping malformed record"
+            ),
+        }
+    }
+
+    Ok(records)
+}
@@ -8,7 +8,7 @@
 //! This module specializes in cleaning artifacts generated by AI agents:
 //! - Git repositories and pack files
 //! - AI agent caches (Cursor, GitHub Copilot, Claude, etc.)
-//! - Xcode derived data and archives
+//! - Xcode derived data, archives, device support files and simulators
 //! - Homebrew cache files
 //! - Time Machine snapshots
 //!
@@ -22,16 +22,35 @@
 )]
 
 pub mod ai_artifacts;
+pub mod audit;
 pub mod cleaner;
+pub mod docker;
+pub mod history;
+pub mod integrity;
+pub mod ios_backups;
+mod jsonl;
 pub mod recovery;
+pub mod safety;
 pub mod targets;
+pub mod thinning;
 pub mod time_machine;
+pub mod trash;
+pub mod xcode;
 
 pub use ai_artifacts::{AIArtifactCleaner, AIArtifactLocations};
-pub use cleaner::SystemCleaner;
+pub use audit::{AuditAction, AuditEntry, AuditLog};
+pub use cleaner::{CategoryTotal, CleanGroup, CleanResult, PruneResult, SystemCleaner};
+pub use docker::{DockerStorage, DockerUsage, Reclaimable};
+pub use history::{CleanHistory, CleanRecord};
+pub use integrity::{IntegrityProblem, IntegrityReport};
+pub use ios_backups::IosBackup;
 pub use recovery::{RecoveryItem, RecoveryManager, RecoveryManifest};
-pub use targets::CleanTarget;
+pub use safety::SafetyPolicy;
+pub use targets::{CleanScope, CleanTarget};
+pub use thinning::thin_binary;
 pub use time_machine::{Snapshot, TimeMachineManager};
+pub use trash::{Trash, TrashEmptied, TrashItem};
+pub use xcode::Simulator;
 
 /// Module version
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
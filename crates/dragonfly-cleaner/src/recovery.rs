@@ -2,10 +2,26 @@
 //!
 //! This module implements a recovery-first approach where files are archived
 //! before deletion, allowing users to restore them if needed.
+//!
+//! Items are moved into a folder per recovery as they are archived. Once a
+//! recovery is complete, [`RecoveryManager::compress`] packs that folder
+//! into a single zstd-compressed tar file, which restoring and verifying
+//! read directly.
 
+use crate::integrity::{check_seal, IntegrityProblem, IntegrityReport, SealKey};
 use chrono::{DateTime, Utc};
+use dragonfly_core::domain::policy;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Extension of a recovery's compressed archive
+const COMPRESSED_EXTENSION: &str = "tar.zst";
+
+/// zstd level for recovery archives; higher levels gain little on the
+/// already-compressed data caches often hold and cost a lot more time
+const COMPRESSION_LEVEL: i32 = 3;
 
 /// Recovery manifest entry for a single cleaned item
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,7 +32,9 @@ pub struct RecoveryItem {
     pub archive_path: PathBuf,
     /// Size in bytes
     pub size: u64,
-    /// SHA-256 checksum for verification
+    /// BLAKE3 checksum of the archived file, as hex (empty for links,
+    /// directories, and items archived before checksums were recorded)
+    #[serde(default)]
     pub checksum: String,
     /// Category (git, cache, xcode, etc.)
     pub category: String,
@@ -39,6 +57,34 @@ pub struct RecoveryManifest {
     pub items: Vec<RecoveryItem>,
     /// Retention expiration date
     pub retention_until: DateTime<Utc>,
+    /// Whether the cleanup was interrupted (only the listed items were archived)
+    #[serde(default)]
+    pub interrupted: bool,
+    /// Integrity seal over the other fields, set when the manifest is saved
+    /// (absent in manifests saved before seals were introduced)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seal: Option<String>,
+    /// Bytes archived per byte of the compressed archive, e.g. `4.0` when it
+    /// takes a quarter of the space; absent while the items are still stored
+    /// as plain files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression_ratio: Option<f64>,
+}
+
+impl RecoveryManifest {
+    /// Whether the items are stored in a compressed archive
+    pub fn is_compressed(&self) -> bool {
+        self.compression_ratio.is_some()
+    }
+
+    /// Bytes the seal covers: the manifest without its seal
+    fn sealed_contents(&self) -> std::io::Result<Vec<u8>> {
+        let unsealed = Self {
+            seal: None,
+            ..self.clone()
+        };
+        Ok(serde_json::to_vec(&unsealed)?)
+    }
 }
 
 /// Recovery manager handles archiving and restoring
@@ -69,13 +115,11 @@ impl RecoveryManager {
 
         std::fs::create_dir_all(&manifests_dir)?;
         std::fs::create_dir_all(&archives_dir)?;
+        SealKey::load_or_create(&self.recovery_dir)?;
 
         // Create index if it doesn't exist
         if !index_file.exists() {
-            let index = RecoveryIndex {
-                recoveries: Vec::new(),
-            };
-            std::fs::write(&index_file, serde_json::to_string_pretty(&index)?)?;
+            self.write_index(RecoveryIndex::default())?;
         }
 
         Ok(())
@@ -93,18 +137,30 @@ impl RecoveryManager {
             total_size: 0,
             items: Vec::new(),
             retention_until,
+            interrupted: false,
+            seal: None,
+            compression_ratio: None,
         }
     }
 
     /// Save manifest to disk
+    ///
+    /// The manifest is written atomically, so saving a partial manifest after
+    /// an interruption never leaves a truncated file behind. It is sealed on
+    /// the way out.
     pub fn save_manifest(&self, manifest: &RecoveryManifest) -> std::io::Result<()> {
         let manifest_file = self
             .recovery_dir
             .join("manifests")
             .join(format!("{}.json", manifest.id));
 
-        let json = serde_json::to_string_pretty(manifest)?;
-        std::fs::write(manifest_file, json)?;
+        let key = SealKey::load_or_create(&self.recovery_dir)?;
+        let sealed = RecoveryManifest {
+            seal: Some(key.seal(&manifest.sealed_contents()?)),
+            ..manifest.clone()
+        };
+        let json = serde_json::to_string_pretty(&sealed)?;
+        write_atomic(&manifest_file, json.as_bytes())?;
 
         // Update index
         self.update_index(manifest)?;
@@ -141,7 +197,7 @@ impl RecoveryManager {
         }
 
         // Sort by timestamp (newest first)
-        recoveries.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        recoveries.sort_by_key(|r| std::cmp::Reverse(r.timestamp));
 
         Ok(recoveries)
     }
@@ -151,36 +207,409 @@ impl RecoveryManager {
         self.recovery_dir.join("archives").join(recovery_id)
     }
 
+    /// Compressed archive of a recovery, which replaces its archive
+    /// directory once [`Self::compress`] has run
+    pub fn archive_file(&self, recovery_id: &str) -> PathBuf {
+        self.recovery_dir
+            .join("archives")
+            .join(format!("{}.{}", recovery_id, COMPRESSED_EXTENSION))
+    }
+
+    /// Where the items of `manifest` are stored: its compressed archive, or
+    /// its archive directory if it isn't compressed
+    pub fn archive_location(&self, manifest: &RecoveryManifest) -> PathBuf {
+        if manifest.is_compressed() {
+            self.archive_file(&manifest.id)
+        } else {
+            self.archive_dir(&manifest.id)
+        }
+    }
+
+    /// Pack the archive directory of `manifest` into one zstd-compressed tar
+    /// file, then remove the directory
+    ///
+    /// Call once nothing more will be archived into the recovery, before
+    /// saving the manifest. The directory is only removed after the
+    /// compressed archive is complete and on disk, so a failure leaves the
+    /// recovery as it was. Records the compression ratio in `manifest`;
+    /// does nothing if it's already compressed or nothing was archived.
+    pub fn compress(&self, manifest: &mut RecoveryManifest) -> std::io::Result<()> {
+        let dir = self.archive_dir(&manifest.id);
+        if manifest.is_compressed() || !dir.is_dir() {
+            return Ok(());
+        }
+        let file = self.archive_file(&manifest.id);
+        let tmp = file.with_extension("zst.tmp");
+        if let Err(e) = pack(&dir, &tmp) {
+            let _ = std::fs::remove_file(&tmp);
+            return Err(e);
+        }
+        std::fs::rename(&tmp, &file)?;
+        std::fs::remove_dir_all(&dir)?;
+
+        let compressed = std::fs::metadata(&file)?.len();
+        manifest.compression_ratio = Some(manifest.total_size as f64 / compressed.max(1) as f64);
+        Ok(())
+    }
+
+    /// Open the compressed archive of the recovery `recovery_id` for reading
+    fn open_archive(
+        &self,
+        recovery_id: &str,
+    ) -> std::io::Result<tar::Archive<zstd::Decoder<'static, std::io::BufReader<std::fs::File>>>>
+    {
+        let file = std::fs::File::open(self.archive_file(recovery_id))?;
+        Ok(tar::Archive::new(zstd::Decoder::new(file)?))
+    }
+
+    /// Move `path` into the archive of `manifest` and record it there
+    ///
+    /// Regular files, symlinks and empty directories are supported; a symlink
+    /// is archived as a link with the same target, so broken links can be
+    /// archived too. To archive a tree of empty directories, archive its
+    /// files first and then each directory, deepest first. The original is
+    /// removed once the archived copy is in place; a file copied across
+    /// volumes is only removed once the copy's checksum matches. Paths the
+    /// protection policy refuses fail with a permission error.
+    pub fn archive_item(
+        &self,
+        manifest: &mut RecoveryManifest,
+        path: &Path,
+        category: &str,
+        source: &str,
+    ) -> std::io::Result<()> {
+        let item = self.archive_entry(&manifest.id, path, category, source)?;
+        manifest.total_size += item.size;
+        manifest.items.push(item);
+        Ok(())
+    }
+
+    /// Move `path` into the archive of the recovery `recovery_id` like
+    /// [`Self::archive_item`], returning the item for the caller to record
+    ///
+    /// Lets several threads archive into one recovery, recording the items
+    /// in its manifest as they finish.
+    pub(crate) fn archive_entry(
+        &self,
+        recovery_id: &str,
+        path: &Path,
+        category: &str,
+        source: &str,
+    ) -> std::io::Result<RecoveryItem> {
+        if let Some(reason) = policy::policy().check(&path.to_string_lossy()) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                format!("{}: {}", path.display(), reason),
+            ));
+        }
+        let metadata = std::fs::symlink_metadata(path)?;
+        let empty_dir = metadata.is_dir() && std::fs::read_dir(path)?.next().is_none();
+        if !metadata.is_file() && !metadata.file_type().is_symlink() && !empty_dir {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "Only files, symlinks and empty directories can be archived: {}",
+                    path.display()
+                ),
+            ));
+        }
+
+        // Mirror the original location so items with the same name don't clash
+        let relative = path.strip_prefix("/").unwrap_or(path).to_path_buf();
+        let archive_path = self.archive_dir(recovery_id).join(&relative);
+        if let Some(parent) = archive_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let (size, checksum) = if empty_dir {
+            std::fs::create_dir_all(&archive_path)?;
+            std::fs::remove_dir(path)?;
+            (0, String::new())
+        } else if metadata.file_type().is_symlink() {
+            std::os::unix::fs::symlink(std::fs::read_link(path)?, &archive_path)?;
+            std::fs::remove_file(path)?;
+            (0, String::new())
+        } else {
+            let checksum = file_checksum(path)?;
+            // Renaming fails across volumes; fall back to copying
+            if std::fs::rename(path, &archive_path).is_err() {
+                std::fs::copy(path, &archive_path)?;
+                if file_checksum(&archive_path)? != checksum {
+                    std::fs::remove_file(&archive_path)?;
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("Archived copy of {} doesn't match it", path.display()),
+                    ));
+                }
+                std::fs::remove_file(path)?;
+            }
+            (metadata.len(), checksum)
+        };
+
+        Ok(RecoveryItem {
+            original_path: path.to_path_buf(),
+            archive_path: relative,
+            size,
+            checksum,
+            category: category.to_string(),
+            source: source.to_string(),
+            can_regenerate: false,
+        })
+    }
+
+    /// Move everything under the directory `path` into the archive of
+    /// `manifest`, then the directory itself
+    ///
+    /// Files go first and directories after them, deepest first, so each
+    /// directory is empty by the time it's archived. Stops at the first item
+    /// that can't be archived; what was archived until then stays recorded.
+    pub fn archive_tree(
+        &self,
+        manifest: &mut RecoveryManifest,
+        path: &Path,
+        category: &str,
+        source: &str,
+    ) -> std::io::Result<()> {
+        for entry in walkdir::WalkDir::new(path).contents_first(true) {
+            let entry = entry.map_err(std::io::Error::from)?;
+            self.archive_item(manifest, entry.path(), category, source)?;
+        }
+        Ok(())
+    }
+
     /// Update recovery index
     fn update_index(&self, manifest: &RecoveryManifest) -> std::io::Result<()> {
         let index_file = self.recovery_dir.join("index.json");
         let mut index = if index_file.exists() {
             let content = std::fs::read_to_string(&index_file)?;
-            serde_json::from_str(&content).unwrap_or_else(|_| RecoveryIndex {
-                recoveries: Vec::new(),
-            })
+            serde_json::from_str(&content).unwrap_or_default()
         } else {
-            RecoveryIndex {
-                recoveries: Vec::new(),
-            }
+            RecoveryIndex::default()
         };
 
         if !index.recoveries.contains(&manifest.id) {
             index.recoveries.push(manifest.id.clone());
         }
 
-        std::fs::write(index_file, serde_json::to_string_pretty(&index)?)?;
-        Ok(())
+        self.write_index(index)
+    }
+
+    /// Seal and write the index
+    fn write_index(&self, index: RecoveryIndex) -> std::io::Result<()> {
+        let key = SealKey::load_or_create(&self.recovery_dir)?;
+        let unsealed = RecoveryIndex {
+            seal: None,
+            ..index
+        };
+        let sealed = RecoveryIndex {
+            seal: Some(key.seal(&serde_json::to_vec(&unsealed)?)),
+            ..unsealed
+        };
+        write_atomic(
+            &self.recovery_dir.join("index.json"),
+            serde_json::to_string_pretty(&sealed)?.as_bytes(),
+        )
+    }
+
+    /// Check the seals of the index and every manifest, and that archived
+    /// items are still in place
+    pub fn verify(&self) -> std::io::Result<IntegrityReport> {
+        let key = SealKey::load(&self.recovery_dir)?;
+        let mut report = IntegrityReport::default();
+
+        let index_file = self.recovery_dir.join("index.json");
+        let indexed = match std::fs::read_to_string(&index_file) {
+            Ok(content) => match serde_json::from_str::<RecoveryIndex>(&content) {
+                Ok(index) => {
+                    let unsealed = RecoveryIndex {
+                        seal: None,
+                        recoveries: index.recoveries.clone(),
+                    };
+                    report.index.extend(check_seal(
+                        key.as_ref(),
+                        index.seal.as_deref(),
+                        &serde_json::to_vec(&unsealed)?,
+                    ));
+                    index.recoveries
+                }
+                Err(e) => {
+                    report.index.push(IntegrityProblem::Unreadable {
+                        error: e.to_string(),
+                    });
+                    Vec::new()
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                report.index.push(IntegrityProblem::Unreadable {
+                    error: e.to_string(),
+                });
+                Vec::new()
+            }
+        };
+
+        let mut on_disk = Vec::new();
+        if let Ok(entries) = std::fs::read_dir(self.recovery_dir.join("manifests")) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().is_some_and(|ext| ext == "json") {
+                    if let Some(id) = path.file_stem() {
+                        on_disk.push(id.to_string_lossy().to_string());
+                    }
+                }
+            }
+        }
+
+        let mut ids: Vec<&String> = indexed.iter().chain(&on_disk).collect();
+        ids.sort();
+        ids.dedup();
+        for id in ids {
+            let problems = if !on_disk.contains(id) {
+                vec![IntegrityProblem::MissingManifest]
+            } else {
+                let mut problems = self.verify_with(key.as_ref(), id);
+                if !indexed.contains(id) {
+                    problems.push(IntegrityProblem::NotIndexed);
+                }
+                problems
+            };
+            report.checked += 1;
+            if !problems.is_empty() {
+                report.recoveries.insert(id.clone(), problems);
+            }
+        }
+        Ok(report)
+    }
+
+    /// Check the seal of one manifest and that its archived items are in
+    /// place; empty if the recovery is intact
+    pub fn verify_recovery(&self, id: &str) -> std::io::Result<Vec<IntegrityProblem>> {
+        let key = SealKey::load(&self.recovery_dir)?;
+        Ok(self.verify_with(key.as_ref(), id))
+    }
+
+    fn verify_with(&self, key: Option<&SealKey>, id: &str) -> Vec<IntegrityProblem> {
+        let manifest = match self.load_manifest(id) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                return vec![IntegrityProblem::Unreadable {
+                    error: e.to_string(),
+                }]
+            }
+        };
+        let mut problems: Vec<IntegrityProblem> = match manifest.sealed_contents() {
+            Ok(contents) => check_seal(key, manifest.seal.as_deref(), &contents)
+                .into_iter()
+                .collect(),
+            Err(e) => vec![IntegrityProblem::Unreadable {
+                error: e.to_string(),
+            }],
+        };
+
+        if manifest.is_compressed() {
+            match self.check_compressed(&manifest) {
+                Ok(found) => problems.extend(found),
+                Err(e) => problems.push(IntegrityProblem::Unreadable {
+                    error: e.to_string(),
+                }),
+            }
+            return problems;
+        }
+
+        let archive_dir = self.archive_dir(id);
+        for item in &manifest.items {
+            let path = item.original_path.clone();
+            let archived = archive_dir.join(&item.archive_path);
+            match std::fs::symlink_metadata(&archived) {
+                Err(_) => problems.push(IntegrityProblem::MissingItem { path }),
+                Ok(metadata) if metadata.is_file() && metadata.len() != item.size => {
+                    problems.push(IntegrityProblem::SizeMismatch {
+                        path,
+                        expected: item.size,
+                        actual: metadata.len(),
+                    })
+                }
+                Ok(metadata)
+                    if metadata.is_file()
+                        && !item.checksum.is_empty()
+                        && file_checksum(&archived).ok().as_ref() != Some(&item.checksum) =>
+                {
+                    problems.push(IntegrityProblem::ChecksumMismatch { path })
+                }
+                Ok(_) => {}
+            }
+        }
+        problems
+    }
+
+    /// Check that every item of the compressed `manifest` is in its archive
+    /// with the recorded size and checksum
+    fn check_compressed(
+        &self,
+        manifest: &RecoveryManifest,
+    ) -> std::io::Result<Vec<IntegrityProblem>> {
+        let items = items_by_archive_path(manifest);
+        let mut problems = Vec::new();
+        let mut found = HashSet::new();
+
+        let mut archive = self.open_archive(&manifest.id)?;
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let Some(item) = items.get(entry.path()?.as_ref()).copied() else {
+                continue;
+            };
+            found.insert(&item.archive_path);
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let path = item.original_path.clone();
+            if entry.size() != item.size {
+                problems.push(IntegrityProblem::SizeMismatch {
+                    path,
+                    expected: item.size,
+                    actual: entry.size(),
+                });
+            } else if !item.checksum.is_empty() && checksum(&mut entry)? != item.checksum {
+                problems.push(IntegrityProblem::ChecksumMismatch { path });
+            }
+        }
+
+        problems.extend(
+            manifest
+                .items
+                .iter()
+                .filter(|item| !found.contains(&item.archive_path))
+                .map(|item| IntegrityProblem::MissingItem {
+                    path: item.original_path.clone(),
+                }),
+        );
+        Ok(problems)
     }
 
     /// Restore files from a recovery
+    ///
+    /// Compressed recoveries are unpacked straight to the original
+    /// locations; the archive itself is kept either way.
     pub fn restore_recovery(&self, recovery_id: &str) -> std::io::Result<(usize, u64)> {
+        self.restore_items(recovery_id, |_| true)
+    }
+
+    /// Restore the items of a recovery that `selected` picks, like
+    /// [`Self::restore_recovery`]; the others stay in the archive
+    pub fn restore_items(
+        &self,
+        recovery_id: &str,
+        selected: impl Fn(&RecoveryItem) -> bool,
+    ) -> std::io::Result<(usize, u64)> {
         let manifest = self.load_manifest(recovery_id)?;
+        if manifest.is_compressed() {
+            return self.restore_compressed(&manifest, selected);
+        }
         let archive_dir = self.archive_dir(recovery_id);
         let mut restored_count = 0;
         let mut restored_size = 0u64;
 
-        for item in &manifest.items {
+        for item in manifest.items.iter().filter(|item| selected(item)) {
             let archive_path = archive_dir.join(&item.archive_path);
             let original_path = &item.original_path;
 
@@ -189,40 +618,101 @@ impl RecoveryManager {
                 std::fs::create_dir_all(parent)?;
             }
 
-            // Copy file from archive to original location
-            if archive_path.exists() {
-                std::fs::copy(&archive_path, original_path)?;
-                restored_count += 1;
-                restored_size += item.size;
+            // Copy file from archive to original location; archived links are
+            // recreated, since copying would follow them
+            match std::fs::symlink_metadata(&archive_path) {
+                Ok(metadata) if metadata.file_type().is_symlink() => {
+                    if std::fs::symlink_metadata(original_path).is_err() {
+                        std::os::unix::fs::symlink(
+                            std::fs::read_link(&archive_path)?,
+                            original_path,
+                        )?;
+                    }
+                }
+                Ok(metadata) if metadata.is_dir() => {
+                    std::fs::create_dir_all(original_path)?;
+                }
+                Ok(_) => {
+                    std::fs::copy(&archive_path, original_path)?;
+                }
+                Err(_) => continue,
+            }
+            restored_count += 1;
+            restored_size += item.size;
+        }
+
+        Ok((restored_count, restored_size))
+    }
+
+    /// Unpack the items of the compressed `manifest` that `selected` picks
+    /// to their original locations
+    fn restore_compressed(
+        &self,
+        manifest: &RecoveryManifest,
+        selected: impl Fn(&RecoveryItem) -> bool,
+    ) -> std::io::Result<(usize, u64)> {
+        let mut items = items_by_archive_path(manifest);
+        items.retain(|_, item| selected(item));
+        let mut restored_count = 0;
+        let mut restored_size = 0u64;
+
+        let mut archive = self.open_archive(&manifest.id)?;
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let Some(item) = items.get(entry.path()?.as_ref()).copied() else {
+                continue;
+            };
+            let original_path = &item.original_path;
+            if let Some(parent) = original_path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            // Like uncompressed restores, existing links are left alone
+            let entry_type = entry.header().entry_type();
+            if entry_type.is_dir() {
+                std::fs::create_dir_all(original_path)?;
+            } else if !entry_type.is_symlink() || std::fs::symlink_metadata(original_path).is_err()
+            {
+                entry.unpack(original_path)?;
             }
+            restored_count += 1;
+            restored_size += item.size;
         }
 
         Ok((restored_count, restored_size))
     }
 
+    /// Recoveries past their retention date, which cleanup removes
+    pub fn expired_recoveries(&self) -> std::io::Result<Vec<RecoveryManifest>> {
+        let now = Utc::now();
+        let mut recoveries = self.list_recoveries()?;
+        recoveries.retain(|manifest| manifest.retention_until < now);
+        Ok(recoveries)
+    }
+
     /// Clean up expired recoveries
     pub fn cleanup_expired(&self) -> std::io::Result<Vec<String>> {
-        let recoveries = self.list_recoveries()?;
-        let now = Utc::now();
         let mut cleaned = Vec::new();
 
-        for manifest in recoveries {
-            if manifest.retention_until < now {
-                let archive_dir = self.archive_dir(&manifest.id);
-                if archive_dir.exists() {
-                    std::fs::remove_dir_all(&archive_dir)?;
-                }
-
-                let manifest_file = self
-                    .recovery_dir
-                    .join("manifests")
-                    .join(format!("{}.json", manifest.id));
-                if manifest_file.exists() {
-                    std::fs::remove_file(manifest_file)?;
-                }
+        for manifest in self.expired_recoveries()? {
+            let archive_dir = self.archive_dir(&manifest.id);
+            if archive_dir.exists() {
+                std::fs::remove_dir_all(&archive_dir)?;
+            }
+            let archive_file = self.archive_file(&manifest.id);
+            if archive_file.exists() {
+                std::fs::remove_file(&archive_file)?;
+            }
 
-                cleaned.push(manifest.id);
+            let manifest_file = self
+                .recovery_dir
+                .join("manifests")
+                .join(format!("{}.json", manifest.id));
+            if manifest_file.exists() {
+                std::fs::remove_file(manifest_file)?;
             }
+
+            cleaned.push(manifest.id);
         }
 
         // Update index
@@ -231,17 +721,64 @@ impl RecoveryManager {
             let content = std::fs::read_to_string(&index_file)?;
             let mut index: RecoveryIndex = serde_json::from_str(&content)?;
             index.recoveries.retain(|id| !cleaned.contains(id));
-            std::fs::write(index_file, serde_json::to_string_pretty(&index)?)?;
+            self.write_index(index)?;
         }
 
         Ok(cleaned)
     }
 }
 
+/// BLAKE3 checksum of the contents of the file at `path`, as hex
+fn file_checksum(path: &Path) -> std::io::Result<String> {
+    checksum(&mut std::fs::File::open(path)?)
+}
+
+/// BLAKE3 checksum of everything `reader` yields, as hex
+fn checksum(reader: &mut impl Read) -> std::io::Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    std::io::copy(reader, &mut hasher)?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Write everything under `dir` to a zstd-compressed tar file at `file`,
+/// named relative to `dir`
+///
+/// Links are stored as links, and directories as entries of their own so
+/// that empty ones survive.
+fn pack(dir: &Path, file: &Path) -> std::io::Result<()> {
+    let encoder = zstd::Encoder::new(std::fs::File::create(file)?, COMPRESSION_LEVEL)?;
+    let mut builder = tar::Builder::new(encoder);
+    builder.follow_symlinks(false);
+    for entry in walkdir::WalkDir::new(dir).min_depth(1) {
+        let entry = entry.map_err(std::io::Error::from)?;
+        let relative = entry.path().strip_prefix(dir).unwrap_or(entry.path());
+        builder.append_path_with_name(entry.path(), relative)?;
+    }
+    builder.into_inner()?.finish()?.sync_all()
+}
+
+/// The items of `manifest` by their path inside the archive
+fn items_by_archive_path(manifest: &RecoveryManifest) -> HashMap<&Path, &RecoveryItem> {
+    manifest
+        .items
+        .iter()
+        .map(|item| (item.archive_path.as_path(), item))
+        .collect()
+}
+
+/// Write a file by renaming a fully written temporary file over it
+fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, contents)?;
+    std::fs::rename(&tmp, path)
+}
+
 /// Recovery index file structure
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 struct RecoveryIndex {
     recoveries: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    seal: Option<String>,
 }
 
 #[cfg(test)]
@@ -266,5 +803,277 @@ mod tests {
         assert!(!manifest.id.is_empty());
         assert!(manifest.items.is_empty());
         assert_eq!(manifest.total_size, 0);
+        assert!(!manifest.interrupted);
+    }
+
+    #[test]
+    fn test_archive_and_restore_broken_symlink() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = RecoveryManager::new(temp_dir.path().join("recovery"));
+        manager.initialize().unwrap();
+        let link = temp_dir.path().join("dangling");
+        std::os::unix::fs::symlink("missing.txt", &link).unwrap();
+        let file = temp_dir.path().join("file.txt");
+        std::fs::write(&file, b"data").unwrap();
+
+        let mut manifest = manager.create_manifest(30);
+        manager
+            .archive_item(&mut manifest, &link, "broken-link", "test")
+            .unwrap();
+        manager
+            .archive_item(&mut manifest, &file, "file", "test")
+            .unwrap();
+        manager.save_manifest(&manifest).unwrap();
+        assert!(std::fs::symlink_metadata(&link).is_err());
+        assert!(!file.exists());
+        assert_eq!(manifest.total_size, 4);
+        assert!(manifest.items[0].checksum.is_empty());
+        assert_eq!(
+            manifest.items[1].checksum,
+            blake3::hash(b"data").to_hex().to_string()
+        );
+
+        let (restored, size) = manager.restore_recovery(&manifest.id).unwrap();
+        assert_eq!((restored, size), (2, 4));
+        assert_eq!(
+            std::fs::read_link(&link).unwrap(),
+            PathBuf::from("missing.txt")
+        );
+        assert_eq!(std::fs::read(&file).unwrap(), b"data");
+    }
+
+    #[test]
+    fn test_archive_and_restore_empty_directories() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = RecoveryManager::new(temp_dir.path().join("recovery"));
+        manager.initialize().unwrap();
+        let tree = temp_dir.path().join("old");
+        std::fs::create_dir_all(tree.join("build")).unwrap();
+        std::fs::write(tree.join(".DS_Store"), b"finder").unwrap();
+
+        let mut manifest = manager.create_manifest(30);
+        assert!(manager
+            .archive_item(&mut manifest, &tree, "empty", "test")
+            .is_err());
+        for path in [tree.join(".DS_Store"), tree.join("build"), tree.clone()] {
+            manager
+                .archive_item(&mut manifest, &path, "empty", "test")
+                .unwrap();
+        }
+        manager.save_manifest(&manifest).unwrap();
+        assert!(!tree.exists());
+        assert!(manager.verify_recovery(&manifest.id).unwrap().is_empty());
+
+        let (restored, _) = manager.restore_recovery(&manifest.id).unwrap();
+        assert_eq!(restored, 3);
+        assert!(tree.join("build").is_dir());
+        assert_eq!(std::fs::read(tree.join(".DS_Store")).unwrap(), b"finder");
+    }
+
+    #[test]
+    fn test_archive_and_restore_tree() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = RecoveryManager::new(temp_dir.path().join("recovery"));
+        manager.initialize().unwrap();
+        let tree = temp_dir.path().join("backup");
+        std::fs::create_dir_all(tree.join("0a/empty")).unwrap();
+        std::fs::write(tree.join("Info.plist"), b"info").unwrap();
+        std::fs::write(tree.join("0a/0a1b"), b"block").unwrap();
+
+        let mut manifest = manager.create_manifest(30);
+        manager
+            .archive_tree(&mut manifest, &tree, "ios-backup", "test")
+            .unwrap();
+        manager.save_manifest(&manifest).unwrap();
+        assert!(!tree.exists());
+        assert_eq!((manifest.items.len(), manifest.total_size), (5, 9));
+
+        manager.restore_recovery(&manifest.id).unwrap();
+        assert_eq!(std::fs::read(tree.join("0a/0a1b")).unwrap(), b"block");
+        assert!(tree.join("0a/empty").is_dir());
+    }
+
+    #[test]
+    fn test_compress_and_restore_tree() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = RecoveryManager::new(temp_dir.path().join("recovery"));
+        manager.initialize().unwrap();
+        let tree = temp_dir.path().join("cache");
+        std::fs::create_dir_all(tree.join("a/empty")).unwrap();
+        std::fs::write(tree.join("a/data.bin"), vec![7u8; 64 * 1024]).unwrap();
+        std::os::unix::fs::symlink("data.bin", tree.join("a/link")).unwrap();
+
+        let mut manifest = manager.create_manifest(30);
+        manager
+            .archive_tree(&mut manifest, &tree, "cache", "test")
+            .unwrap();
+        manager.compress(&mut manifest).unwrap();
+        manager.save_manifest(&manifest).unwrap();
+        assert!(!manager.archive_dir(&manifest.id).exists());
+        assert_eq!(
+            manager.archive_location(&manifest),
+            manager.archive_file(&manifest.id)
+        );
+        assert!(manifest.compression_ratio.unwrap() > 10.0);
+        assert!(manager.verify_recovery(&manifest.id).unwrap().is_empty());
+
+        let (restored, size) = manager.restore_recovery(&manifest.id).unwrap();
+        assert_eq!((restored, size), (manifest.items.len(), 64 * 1024));
+        assert_eq!(
+            std::fs::read(tree.join("a/data.bin")).unwrap(),
+            vec![7u8; 64 * 1024]
+        );
+        assert_eq!(
+            std::fs::read_link(tree.join("a/link")).unwrap(),
+            PathBuf::from("data.bin")
+        );
+        assert!(tree.join("a/empty").is_dir());
+        assert!(manager.archive_file(&manifest.id).exists());
+    }
+
+    #[test]
+    fn test_verify_compressed_detects_missing_items() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = RecoveryManager::new(temp_dir.path().join("recovery"));
+        manager.initialize().unwrap();
+        let file = temp_dir.path().join("file.txt");
+        std::fs::write(&file, b"data").unwrap();
+
+        let mut manifest = manager.create_manifest(30);
+        manager
+            .archive_item(&mut manifest, &file, "file", "test")
+            .unwrap();
+        manager.compress(&mut manifest).unwrap();
+        let mut extra = manifest.items[0].clone();
+        extra.original_path = temp_dir.path().join("other.txt");
+        extra.archive_path = PathBuf::from("other.txt");
+        manifest.items.push(extra);
+        manager.save_manifest(&manifest).unwrap();
+
+        assert_eq!(
+            manager.verify_recovery(&manifest.id).unwrap(),
+            vec![IntegrityProblem::MissingItem {
+                path: temp_dir.path().join("other.txt")
+            }]
+        );
+    }
+
+    #[test]
+    fn test_restore_selected_items() {
+        let temp_dir = TempDir::new().unwrap();
+        let caches = temp_dir.path().join("Caches");
+        for app in ["com.a", "com.b"] {
+            std::fs::create_dir_all(caches.join(app)).unwrap();
+            std::fs::write(caches.join(app).join("db"), app).unwrap();
+        }
+
+        for compress in [false, true] {
+            // A store each, since recovery IDs only change every second
+            let manager =
+                RecoveryManager::new(temp_dir.path().join(format!("recovery-{}", compress)));
+            manager.initialize().unwrap();
+            let mut manifest = manager.create_manifest(30);
+            manager
+                .archive_tree(&mut manifest, &caches, "cache", "test")
+                .unwrap();
+            if compress {
+                manager.compress(&mut manifest).unwrap();
+            }
+            manager.save_manifest(&manifest).unwrap();
+
+            let wanted = caches.join("com.b");
+            let (restored, size) = manager
+                .restore_items(&manifest.id, |item| item.original_path.starts_with(&wanted))
+                .unwrap();
+            assert_eq!((restored, size), (2, 5));
+            assert_eq!(std::fs::read(wanted.join("db")).unwrap(), b"com.b");
+            assert!(!caches.join("com.a").exists());
+
+            // Put it back for the next round
+            std::fs::remove_dir_all(&caches).unwrap();
+            manager.restore_recovery(&manifest.id).unwrap();
+            assert!(caches.join("com.a/db").exists());
+        }
+    }
+
+    #[test]
+    fn test_save_partial_manifest() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = RecoveryManager::new(temp_dir.path().to_path_buf());
+        manager.initialize().unwrap();
+
+        let mut manifest = manager.create_manifest(30);
+        manifest.interrupted = true;
+        manager.save_manifest(&manifest).unwrap();
+
+        let loaded = manager.load_manifest(&manifest.id).unwrap();
+        assert!(loaded.interrupted);
+        assert_eq!(manager.list_recoveries().unwrap().len(), 1);
+        assert!(!temp_dir.path().join("index.json.tmp").exists());
+    }
+
+    #[test]
+    fn test_verify_detects_tampering() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = RecoveryManager::new(temp_dir.path().join("recovery"));
+        manager.initialize().unwrap();
+        let file = temp_dir.path().join("file.txt");
+        std::fs::write(&file, b"data").unwrap();
+
+        let mut manifest = manager.create_manifest(30);
+        manager
+            .archive_item(&mut manifest, &file, "file", "test")
+            .unwrap();
+        manager.save_manifest(&manifest).unwrap();
+        let report = manager.verify().unwrap();
+        assert_eq!(report.checked, 1);
+        assert!(report.is_intact() && report.recoveries.is_empty());
+
+        // Point the item somewhere else behind the manager's back
+        let manifest_file = temp_dir
+            .path()
+            .join("recovery/manifests")
+            .join(format!("{}.json", manifest.id));
+        let content = std::fs::read_to_string(&manifest_file).unwrap();
+        std::fs::write(&manifest_file, content.replace("file.txt", "other.txt")).unwrap();
+        std::fs::remove_file(
+            manager
+                .archive_dir(&manifest.id)
+                .join(temp_dir.path().strip_prefix("/").unwrap().join("file.txt")),
+        )
+        .unwrap();
+
+        let report = manager.verify().unwrap();
+        assert!(!report.is_intact());
+        let problems = &report.recoveries[&manifest.id];
+        assert_eq!(problems[0], IntegrityProblem::SealMismatch);
+        assert!(matches!(problems[1], IntegrityProblem::MissingItem { .. }));
+        assert_eq!(&manager.verify_recovery(&manifest.id).unwrap(), problems);
+    }
+
+    #[test]
+    fn test_verify_detects_changed_contents() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = RecoveryManager::new(temp_dir.path().join("recovery"));
+        manager.initialize().unwrap();
+        let file = temp_dir.path().join("file.txt");
+        std::fs::write(&file, b"data").unwrap();
+
+        let mut manifest = manager.create_manifest(30);
+        manager
+            .archive_item(&mut manifest, &file, "file", "test")
+            .unwrap();
+        manager.save_manifest(&manifest).unwrap();
+
+        // Same size, different bytes
+        let archived = manager
+            .archive_dir(&manifest.id)
+            .join(&manifest.items[0].archive_path);
+        std::fs::write(archived, b"DATA").unwrap();
+
+        assert_eq!(
+            manager.verify_recovery(&manifest.id).unwrap(),
+            vec![IntegrityProblem::ChecksumMismatch { path: file }]
+        );
     }
 }
@@ -0,0 +1,172 @@
+//! Checks the cleaner runs before it touches a file
+//!
+//! A [`SafetyPolicy`] combines the protection policy (system locations and
+//! the user's `never_touch`, `protect_patterns` and `allow_roots`) with the
+//! files running processes have open. Deleting a file an app still has open
+//! doesn't free its space until the app closes it, and can lose whatever the
+//! app writes next, so open files are skipped like protected ones.
+//!
+//! Open files are listed once, when the policy is created, with `lsof`; where
+//! `lsof` is missing, `/proc` stands in on Linux.
+
+use dragonfly_core::domain::policy::{self, ProtectionPolicy};
+use dragonfly_core::domain::protection::{SkipReason, SkippedPath};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Which files the cleaner may modify
+#[derive(Debug, Clone, Default)]
+pub struct SafetyPolicy {
+    protection: ProtectionPolicy,
+    open_files: HashSet<PathBuf>,
+}
+
+impl SafetyPolicy {
+    /// Policy with the given protection and set of open files
+    pub fn new(protection: ProtectionPolicy, open_files: HashSet<PathBuf>) -> Self {
+        Self {
+            protection,
+            open_files,
+        }
+    }
+
+    /// The installed protection policy and the files open right now
+    pub fn current() -> Self {
+        Self::new(policy::policy().clone(), open_files())
+    }
+
+    /// The installed protection policy alone, for operations on folders
+    /// that no process holds open
+    pub fn paths_only() -> Self {
+        Self::new(policy::policy().clone(), HashSet::new())
+    }
+
+    /// Why `path` must not be modified, or `None` if it may be
+    pub fn check(&self, path: &Path) -> Option<SkipReason> {
+        self.protection
+            .check(&path.to_string_lossy())
+            .or_else(|| self.is_open(path).then_some(SkipReason::InUse))
+    }
+
+    /// Whether a process has `path` open
+    ///
+    /// Processes report the path with symlinks resolved, so `/tmp/a` is
+    /// open if `/private/tmp/a` is.
+    fn is_open(&self, path: &Path) -> bool {
+        !self.open_files.is_empty()
+            && (self.open_files.contains(path)
+                || path
+                    .canonicalize()
+                    .is_ok_and(|real| self.open_files.contains(&real)))
+    }
+
+    /// The skip to report for `path`, or `None` if it may be modified
+    pub fn refuse(&self, path: &Path) -> Option<SkippedPath> {
+        self.check(path)
+            .map(|reason| SkippedPath::new(path.to_string_lossy().to_string(), reason))
+    }
+
+    /// Whether nothing beneath `path` may be modified, so a walk can skip
+    /// it as a whole
+    pub fn refuses_tree(&self, path: &Path) -> bool {
+        self.protection.refuses_tree(&path.to_string_lossy())
+    }
+}
+
+/// Files and folders any process has open, by path
+///
+/// Empty if neither `lsof` nor `/proc` can be read; the cleaner then relies
+/// on the protection policy alone.
+pub fn open_files() -> HashSet<PathBuf> {
+    // -F n prints one field per line: `p<pid>`, `f<fd>` and `n<name>`;
+    // -n and -P skip host and port lookups, -w silences warnings
+    match Command::new("lsof")
+        .args(["-n", "-P", "-w", "-F", "n"])
+        .output()
+    {
+        // lsof exits with 1 when some processes couldn't be inspected
+        Ok(output) if !output.stdout.is_empty() => {
+            parse_lsof(&String::from_utf8_lossy(&output.stdout))
+        }
+        _ => proc_open_files(),
+    }
+}
+
+/// Absolute paths named in `lsof -F n` output
+fn parse_lsof(output: &str) -> HashSet<PathBuf> {
+    output
+        .lines()
+        .filter_map(|line| line.strip_prefix('n'))
+        // Deleted files are reported with a suffix; they're gone already
+        .filter(|name| name.starts_with('/') && !name.ends_with(" (deleted)"))
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Files open in processes listed under `/proc`
+fn proc_open_files() -> HashSet<PathBuf> {
+    let Ok(processes) = std::fs::read_dir("/proc") else {
+        return HashSet::new();
+    };
+    processes
+        .flatten()
+        .filter_map(|process| std::fs::read_dir(process.path().join("fd")).ok())
+        .flat_map(|fds| fds.flatten())
+        .filter_map(|fd| std::fs::read_link(fd.path()).ok())
+        .filter(|target| target.is_absolute() && target.is_file())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_lsof() {
+        let output = "p1\nfcwd\nn/\np42\nf3\nn/Users/me/Library/Caches/app/db\nf4\nn/tmp/old (deleted)\nf5\nn*:5353\n";
+        assert_eq!(
+            parse_lsof(output),
+            HashSet::from([
+                PathBuf::from("/"),
+                PathBuf::from("/Users/me/Library/Caches/app/db")
+            ])
+        );
+    }
+
+    #[test]
+    fn test_check_skips_protected_and_open_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let open = temp_dir.path().join("open.db");
+        let closed = temp_dir.path().join("closed.db");
+        let protection = ProtectionPolicy::default().with_protect_patterns(vec!["*.keep".into()]);
+        let safety = SafetyPolicy::new(protection, HashSet::from([open.clone()]));
+
+        assert_eq!(safety.check(&open), Some(SkipReason::InUse));
+        assert_eq!(safety.check(&closed), None);
+        assert_eq!(
+            safety.check(&temp_dir.path().join("a.keep")),
+            Some(SkipReason::PolicyProtected)
+        );
+        assert_eq!(
+            safety
+                .refuse(Path::new("/System/Library/a"))
+                .unwrap()
+                .reason,
+            SkipReason::SipProtected
+        );
+    }
+
+    #[test]
+    fn test_open_files_finds_a_file_held_open() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("held.log");
+        let _file = std::fs::File::create(&path).unwrap();
+        let open = open_files();
+        // Skip where neither lsof nor /proc is available
+        if !open.is_empty() {
+            assert!(open.contains(&path.canonicalize().unwrap()));
+        }
+    }
+}
@@ -1,5 +1,6 @@
 //! Cleaning targets
 
+use crate::xcode;
 use serde::{Deserialize, Serialize};
 
 /// Targets that can be cleaned
@@ -11,17 +12,51 @@ pub enum CleanTarget {
     Logs,
     /// Temporary files
     Temp,
-    /// All targets
+    /// Xcode DerivedData, Archives, iOS DeviceSupport for older versions,
+    /// and simulators whose runtime is gone
+    Xcode,
+    /// Caches, logs and temporary files
     All,
 }
 
+/// Whose files a clean may touch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CleanScope {
+    /// Only locations under the user's home directory (`~/...`)
+    User,
+    /// Only locations shared by every user (`/Library/Caches`, `/var/log`,
+    /// ...), which need root to clean
+    System,
+}
+
+impl CleanScope {
+    /// The scope a target path belongs to
+    pub fn of(path: &str) -> Self {
+        if path.starts_with("~/") {
+            Self::User
+        } else {
+            Self::System
+        }
+    }
+}
+
 impl CleanTarget {
+    /// Paths for this target within `scope`, or every path for `None`
+    pub fn paths_in(&self, scope: Option<CleanScope>) -> Vec<&'static str> {
+        self.paths()
+            .into_iter()
+            .filter(|path| scope.map_or(true, |scope| CleanScope::of(path) == scope))
+            .collect()
+    }
+
     /// Get list of paths for this target
     pub fn paths(&self) -> Vec<&'static str> {
         match self {
             Self::Caches => vec!["~/Library/Caches", "/Library/Caches"],
             Self::Logs => vec!["~/Library/Logs", "/var/log"],
             Self::Temp => vec!["/tmp", "/var/tmp"],
+            Self::Xcode => vec![xcode::DERIVED_DATA, xcode::ARCHIVES, xcode::DEVICE_SUPPORT],
             Self::All => vec![
                 "~/Library/Caches",
                 "/Library/Caches",
@@ -33,3 +68,24 @@ impl CleanTarget {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paths_in_scope() {
+        assert_eq!(
+            CleanTarget::Caches.paths_in(Some(CleanScope::User)),
+            ["~/Library/Caches"]
+        );
+        assert_eq!(
+            CleanTarget::All.paths_in(Some(CleanScope::System)),
+            ["/Library/Caches", "/var/log", "/tmp", "/var/tmp"]
+        );
+        assert!(CleanTarget::Temp
+            .paths_in(Some(CleanScope::User))
+            .is_empty());
+        assert_eq!(CleanTarget::Logs.paths_in(None), CleanTarget::Logs.paths());
+    }
+}
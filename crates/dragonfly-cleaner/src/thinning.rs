@@ -0,0 +1,77 @@
+//! Universal binary thinning
+//!
+//! Strips the slices for other architectures out of a universal binary with
+//! `lipo`. The original binary is archived in a recovery first, so a thinned
+//! app can be restored if it turns out to need the removed slice (for
+//! example when the app is copied to an Intel Mac).
+
+use crate::recovery::{RecoveryManager, RecoveryManifest};
+use dragonfly_core::error::{Error, Result};
+use std::path::Path;
+use std::process::Command;
+
+/// Recovery category for archived universal binaries
+pub const THINNING_CATEGORY: &str = "universal-binary";
+
+/// Replace the universal binary at `binary` with its `arch` slice
+///
+/// The thinned copy is written next to the original and only swapped in once
+/// `lipo` succeeded and the original has been archived into `manifest`, so a
+/// failure at any step leaves the binary as it was. Returns the bytes saved.
+pub fn thin_binary(
+    binary: &Path,
+    arch: &str,
+    recovery: &RecoveryManager,
+    manifest: &mut RecoveryManifest,
+) -> Result<u64> {
+    let name = binary
+        .file_name()
+        .ok_or_else(|| Error::InvalidInput(format!("Not a file: {}", binary.display())))?;
+    let metadata = std::fs::metadata(binary)?;
+    let thinned = binary.with_file_name(format!(".{}.dragonfly-thin", name.to_string_lossy()));
+
+    let output = Command::new("lipo")
+        .arg(binary)
+        .args(["-thin", arch, "-output"])
+        .arg(&thinned)
+        .output()
+        .map_err(|e| Error::NotSupported(format!("Failed to run lipo: {}", e)))?;
+    if !output.status.success() {
+        let _ = std::fs::remove_file(&thinned);
+        return Err(Error::FileSystem(format!(
+            "lipo failed for {}: {}",
+            binary.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let thinned_size = std::fs::metadata(&thinned)?.len();
+    let swapped = std::fs::set_permissions(&thinned, metadata.permissions())
+        .and_then(|()| recovery.archive_item(manifest, binary, THINNING_CATEGORY, "disk thinning"))
+        .and_then(|()| std::fs::rename(&thinned, binary));
+    if let Err(err) = swapped {
+        let _ = std::fs::remove_file(&thinned);
+        return Err(err.into());
+    }
+    Ok(metadata.len().saturating_sub(thinned_size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_thin_binary_leaves_original_on_failure() {
+        let temp_dir = TempDir::new().unwrap();
+        let binary = temp_dir.path().join("tool");
+        std::fs::write(&binary, b"not a universal binary").unwrap();
+        let recovery = RecoveryManager::new(temp_dir.path().join("recovery"));
+        let mut manifest = recovery.create_manifest(30);
+
+        assert!(thin_binary(&binary, "arm64", &recovery, &mut manifest).is_err());
+        assert_eq!(std::fs::read(&binary).unwrap(), b"not a universal binary");
+        assert!(manifest.items.is_empty());
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+}
@@ -65,9 +65,14 @@ impl TimeMachineManager {
     }
 
     /// Delete a local snapshot
+    ///
+    /// Accepts either a full snapshot ID or its bare date stamp; `tmutil`
+    /// itself only takes the stamp.
     pub fn delete_snapshot(snapshot_id: &str) -> Result<()> {
+        let stamp = Self::date_stamp(snapshot_id)
+            .ok_or_else(|| Error::InvalidInput(format!("Not a snapshot ID: {}", snapshot_id)))?;
         let output = Command::new("tmutil")
-            .args(["deletelocalsnapshot", snapshot_id])
+            .args(["deletelocalsnapshots", stamp])
             .output()
             .map_err(|e| Error::Internal(format!("Failed to run tmutil: {}", e)))?;
 
@@ -82,24 +87,93 @@ impl TimeMachineManager {
         Ok(())
     }
 
-    /// Delete snapshots older than specified days
-    pub fn delete_old_snapshots(days: u32, dry_run: bool) -> Result<Vec<String>> {
-        let snapshots = Self::list_snapshots()?;
+    /// When the newest local snapshot was taken, or `None` if there are
+    /// none or `tmutil` isn't available
+    ///
+    /// Files last modified before then are still referenced by that
+    /// snapshot, so deleting them frees no space until it is thinned.
+    pub fn newest_snapshot_time() -> Option<chrono::DateTime<chrono::Utc>> {
+        Self::list_snapshots()
+            .ok()?
+            .iter()
+            .filter_map(|snapshot| Self::parse_snapshot_date(&snapshot.date).ok())
+            .max()
+    }
+
+    /// Ask macOS to thin local snapshots until `bytes` are free on the
+    /// startup volume, oldest first
+    ///
+    /// Returns the IDs of the snapshots removed.
+    pub fn thin_snapshots(bytes: u64) -> Result<Vec<String>> {
+        // Urgency 4 is the highest, the level macOS uses when space runs out
+        let output = Command::new("tmutil")
+            .args(["thinlocalsnapshots", "/", &bytes.to_string(), "4"])
+            .output()
+            .map_err(|e| Error::Internal(format!("Failed to run tmutil: {}", e)))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(Error::Internal(format!(
+                "Failed to thin snapshots: {}",
+                stderr
+            )));
+        }
+
+        Ok(Self::parse_thinned(&String::from_utf8_lossy(
+            &output.stdout,
+        )))
+    }
+
+    /// Snapshot IDs listed in `tmutil thinlocalsnapshots` output
+    ///
+    /// Format: "Thinned local snapshots:" followed by one date-stamp per line
+    fn parse_thinned(output: &str) -> Vec<String> {
+        output
+            .lines()
+            .skip_while(|line| !line.starts_with("Thinned local snapshots"))
+            .skip(1)
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|stamp| format!("com.apple.TimeMachine.{}.local", stamp))
+            .collect()
+    }
+
+    /// IDs of snapshots older than specified days
+    pub fn old_snapshots(days: u32) -> Result<Vec<String>> {
         let cutoff_date = chrono::Utc::now() - chrono::Duration::days(days as i64);
-        let mut deleted = Vec::new();
 
-        for snapshot in snapshots {
-            if let Ok(snapshot_date) = Self::parse_snapshot_date(&snapshot.date) {
-                if snapshot_date < cutoff_date {
-                    if !dry_run {
-                        Self::delete_snapshot(&snapshot.id)?;
-                    }
-                    deleted.push(snapshot.id);
-                }
+        Ok(Self::list_snapshots()?
+            .into_iter()
+            .filter(|snapshot| {
+                Self::parse_snapshot_date(&snapshot.date)
+                    .is_ok_and(|snapshot_date| snapshot_date < cutoff_date)
+            })
+            .map(|snapshot| snapshot.id)
+            .collect())
+    }
+
+    /// Delete snapshots older than specified days
+    pub fn delete_old_snapshots(days: u32, dry_run: bool) -> Result<Vec<String>> {
+        let old = Self::old_snapshots(days)?;
+        if !dry_run {
+            for id in &old {
+                Self::delete_snapshot(id)?;
             }
         }
+        Ok(old)
+    }
 
-        Ok(deleted)
+    /// The `YYYY-MM-DD-HHMMSS` stamp `tmutil deletelocalsnapshots` expects
+    ///
+    /// Format: com.apple.TimeMachine.2025-01-20-143000.local, or the stamp alone
+    fn date_stamp(snapshot_id: &str) -> Option<&str> {
+        snapshot_id.split('.').find(|part| {
+            part.len() == 17
+                && part.bytes().enumerate().all(|(i, b)| match i {
+                    4 | 7 | 10 => b == b'-',
+                    _ => b.is_ascii_digit(),
+                })
+        })
     }
 
     /// Extract date from snapshot ID
@@ -113,17 +187,12 @@ impl TimeMachineManager {
             if part.len() >= 10 && part.chars().take(4).all(|c| c.is_ascii_digit()) {
                 // Found date part like "2025-01-20-143000"
                 let date = &part[..10]; // 2025-01-20
-                let time_str = if part.len() >= 16 {
-                    let hour = &part[11..13];
-                    let min = &part[13..15];
-                    let sec = if part.len() >= 16 {
-                        &part[15..17]
-                    } else {
-                        "00"
-                    };
-                    format!("{}:{}:{}", hour, min, sec)
-                } else {
-                    "00:00:00".to_string()
+                let time_str = match (part.get(11..13), part.get(13..15)) {
+                    (Some(hour), Some(min)) => {
+                        let sec = part.get(15..17).unwrap_or("00");
+                        format!("{}:{}:{}", hour, min, sec)
+                    }
+                    _ => "00:00:00".to_string(),
                 };
                 return Some(format!("{} {}", date, time_str));
             }
@@ -132,13 +201,22 @@ impl TimeMachineManager {
     }
 
     /// Parse snapshot date string
+    ///
+    /// `tmutil` names snapshots in local time, so the stamp is converted from
+    /// the machine's time zone rather than read as UTC.
     fn parse_snapshot_date(date_str: &str) -> Result<chrono::DateTime<chrono::Utc>> {
+        use chrono::TimeZone;
+
         // Try various date formats
-        let formats = ["%Y-%m-%d %H%M%S", "%Y-%m-%d", "%Y-%m-%d-%H%M%S"];
+        let formats = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H%M%S", "%Y-%m-%d-%H%M%S"];
 
         for format in &formats {
             if let Ok(dt) = chrono::NaiveDateTime::parse_from_str(date_str, format) {
-                return Ok(dt.and_utc());
+                // A stamp inside a DST fall-back hour is ambiguous; take the
+                // earlier instant. One skipped by spring-forward can't exist.
+                if let Some(local) = chrono::Local.from_local_datetime(&dt).earliest() {
+                    return Ok(local.with_timezone(&chrono::Utc));
+                }
             }
         }
 
@@ -166,4 +244,67 @@ mod tests {
         let date = TimeMachineManager::extract_date(id);
         assert!(date.is_some());
     }
+
+    #[test]
+    fn test_parse_extracted_date_as_local_time() {
+        // The only test in this crate that reads or changes the time zone
+        std::env::set_var("TZ", "America/New_York");
+
+        let date =
+            TimeMachineManager::extract_date("com.apple.TimeMachine.2025-01-20-143000.local")
+                .unwrap();
+        let parsed = TimeMachineManager::parse_snapshot_date(&date).unwrap();
+        assert_eq!(parsed.to_rfc3339(), "2025-01-20T19:30:00+00:00");
+
+        let summer = TimeMachineManager::parse_snapshot_date("2025-07-01 09:00:00").unwrap();
+        assert_eq!(summer.to_rfc3339(), "2025-07-01T13:00:00+00:00");
+
+        std::env::remove_var("TZ");
+    }
+
+    #[test]
+    fn test_extract_date_short_time() {
+        assert_eq!(
+            TimeMachineManager::extract_date("com.apple.TimeMachine.2025-01-20-14300.local")
+                .as_deref(),
+            Some("2025-01-20 14:30:00")
+        );
+        assert_eq!(
+            TimeMachineManager::extract_date("com.apple.TimeMachine.2025-01-20-1430").as_deref(),
+            Some("2025-01-20 14:30:00")
+        );
+        assert_eq!(
+            TimeMachineManager::extract_date("com.apple.TimeMachine.2025-01-20-1").as_deref(),
+            Some("2025-01-20 00:00:00")
+        );
+    }
+
+    #[test]
+    fn test_date_stamp() {
+        assert_eq!(
+            TimeMachineManager::date_stamp("com.apple.TimeMachine.2025-01-20-143000.local"),
+            Some("2025-01-20-143000")
+        );
+        assert_eq!(
+            TimeMachineManager::date_stamp("2025-01-20-143000"),
+            Some("2025-01-20-143000")
+        );
+        assert_eq!(
+            TimeMachineManager::date_stamp("com.apple.TimeMachine"),
+            None
+        );
+        assert_eq!(TimeMachineManager::date_stamp("2025-01-20-1430"), None);
+    }
+
+    #[test]
+    fn test_parse_thinned() {
+        let output = "Thinned local snapshots:\n2025-01-20-143000\n2025-01-21-090000\n";
+        assert_eq!(
+            TimeMachineManager::parse_thinned(output),
+            vec![
+                "com.apple.TimeMachine.2025-01-20-143000.local",
+                "com.apple.TimeMachine.2025-01-21-090000.local"
+            ]
+        );
+    }
 }
@@ -0,0 +1,315 @@
+//! The Trash
+//!
+//! On macOS each user has a trash in `~/.Trash` for the startup volume, and
+//! one per external volume in `/Volumes/<name>/.Trashes/<uid>`. Linux
+//! desktops follow the freedesktop.org layout instead: `files` and `info`
+//! folders in `~/.local/share/Trash` and in `.Trash-<uid>` at the top of
+//! each mounted volume, where every trashed item has a `.trashinfo` file
+//! beside it that goes when the item does.
+//!
+//! Moving an item into the trash renames it, which sets its status change
+//! time, so that time tells how long an item has been in the trash.
+
+use chrono::{DateTime, Utc};
+use dragonfly_core::domain::policy;
+use dragonfly_core::domain::protection::SkippedPath;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One trash folder
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Trash {
+    /// Folder holding the trashed items
+    pub path: PathBuf,
+    /// Volume the trash belongs to, `None` for the home folder's
+    pub volume: Option<PathBuf>,
+    /// freedesktop.org `info` folder describing the items, on Linux
+    #[serde(skip)]
+    info: Option<PathBuf>,
+}
+
+/// An item in a trash
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TrashItem {
+    /// Where the item is now
+    pub path: PathBuf,
+    /// Bytes it takes, including everything in a folder
+    pub bytes: u64,
+    /// When it was moved to the trash, if known
+    pub trashed: Option<DateTime<Utc>>,
+}
+
+/// What emptying a trash removed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrashEmptied {
+    /// Items removed, or that would be for a dry run
+    pub removed: Vec<TrashItem>,
+    /// Items left because they were trashed too recently
+    pub kept: usize,
+    /// Items refused or that could not be removed, with the reason
+    pub skipped: Vec<SkippedPath>,
+}
+
+impl TrashEmptied {
+    /// Bytes the removed items took
+    pub fn bytes(&self) -> u64 {
+        self.removed.iter().map(|item| item.bytes).sum()
+    }
+}
+
+impl Trash {
+    /// Every trash of the current user that exists on this machine, the
+    /// home folder's first
+    pub fn all() -> Vec<Self> {
+        let Some(home) = dirs::home_dir() else {
+            return Vec::new();
+        };
+        let mut trashes = vec![home_trash(&home)];
+        trashes.extend(volume_trashes(&home));
+        trashes.retain(|trash| trash.path.is_dir());
+        trashes
+    }
+
+    /// The trash in `path`, with freedesktop.org item descriptions in `info`
+    pub fn at(path: PathBuf, volume: Option<PathBuf>, info: Option<PathBuf>) -> Self {
+        Self { path, volume, info }
+    }
+
+    /// The items in the trash, largest first
+    ///
+    /// Items that vanish or can't be read while listing are left out.
+    pub fn items(&self) -> Vec<TrashItem> {
+        let Ok(entries) = fs::read_dir(&self.path) else {
+            return Vec::new();
+        };
+        let mut items: Vec<TrashItem> = entries
+            .flatten()
+            // Finder's view settings for the trash itself
+            .filter(|entry| entry.file_name() != ".DS_Store")
+            .filter_map(|entry| {
+                let metadata = fs::symlink_metadata(entry.path()).ok()?;
+                Some(TrashItem {
+                    bytes: item_size(&entry.path(), &metadata),
+                    trashed: trashed_at(&metadata),
+                    path: entry.path(),
+                })
+            })
+            .collect();
+        items.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.path.cmp(&b.path)));
+        items
+    }
+
+    /// Remove the items trashed before `cutoff`, or all of them for `None`,
+    /// unless `dry_run`
+    ///
+    /// Items whose trashing time is unknown are only removed without a
+    /// cutoff. Items the protection policy refuses are skipped.
+    pub fn empty(&self, cutoff: Option<DateTime<Utc>>, dry_run: bool) -> TrashEmptied {
+        let mut emptied = TrashEmptied::default();
+        for item in self.items() {
+            let old_enough = match (cutoff, item.trashed) {
+                (None, _) => true,
+                (Some(cutoff), Some(trashed)) => trashed < cutoff,
+                (Some(_), None) => false,
+            };
+            if !old_enough {
+                emptied.kept += 1;
+                continue;
+            }
+            let path = item.path.to_string_lossy().to_string();
+            if let Some(reason) = policy::policy().check(&path) {
+                emptied.skipped.push(SkippedPath::new(path, reason));
+                continue;
+            }
+            if !dry_run {
+                if let Err(err) = self.remove(&item.path) {
+                    emptied
+                        .skipped
+                        .extend(SkippedPath::from_io_error(&path, &err));
+                    continue;
+                }
+            }
+            emptied.removed.push(item);
+        }
+        emptied
+    }
+
+    /// Remove an item, and its description if the trash keeps them
+    fn remove(&self, item: &Path) -> std::io::Result<()> {
+        let metadata = fs::symlink_metadata(item)?;
+        if metadata.is_dir() {
+            fs::remove_dir_all(item)?;
+        } else {
+            fs::remove_file(item)?;
+        }
+        if let (Some(info), Some(name)) = (&self.info, item.file_name()) {
+            let mut description = name.to_os_string();
+            description.push(".trashinfo");
+            match fs::remove_file(info.join(description)) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => return Err(err),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Bytes an item takes: a file's length, or the total of a folder's files
+fn item_size(path: &Path, metadata: &fs::Metadata) -> u64 {
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// When an item was moved to the trash: its last status change
+#[cfg(unix)]
+fn trashed_at(metadata: &fs::Metadata) -> Option<DateTime<Utc>> {
+    use std::os::unix::fs::MetadataExt;
+    DateTime::from_timestamp(metadata.ctime(), 0)
+}
+
+#[cfg(not(unix))]
+fn trashed_at(_metadata: &fs::Metadata) -> Option<DateTime<Utc>> {
+    None
+}
+
+/// The current user's id, as owner of their home folder
+#[cfg(unix)]
+fn user_id(home: &Path) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(home).ok().map(|metadata| metadata.uid())
+}
+
+#[cfg(target_os = "macos")]
+fn home_trash(home: &Path) -> Trash {
+    Trash::at(home.join(".Trash"), None, None)
+}
+
+/// `.Trashes/<uid>` on every volume mounted under `/Volumes`
+///
+/// The startup volume appears there too, as a link to `/`, and is skipped:
+/// its trash is the home folder's.
+#[cfg(target_os = "macos")]
+fn volume_trashes(home: &Path) -> Vec<Trash> {
+    let Some(uid) = user_id(home) else {
+        return Vec::new();
+    };
+    let Ok(volumes) = fs::read_dir("/Volumes") else {
+        return Vec::new();
+    };
+    volumes
+        .flatten()
+        .filter(|volume| volume.file_type().is_ok_and(|kind| kind.is_dir()))
+        .map(|volume| {
+            let path = volume.path().join(".Trashes").join(uid.to_string());
+            Trash::at(path, Some(volume.path()), None)
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "macos"))]
+fn home_trash(home: &Path) -> Trash {
+    let trash = home.join(".local/share/Trash");
+    Trash::at(trash.join("files"), None, Some(trash.join("info")))
+}
+
+/// `.Trash-<uid>` at the top of every volume mounted where desktops mount
+/// removable media
+#[cfg(all(unix, not(target_os = "macos")))]
+fn volume_trashes(home: &Path) -> Vec<Trash> {
+    let Some(uid) = user_id(home) else {
+        return Vec::new();
+    };
+    let user = home.file_name().map(PathBuf::from).unwrap_or_default();
+    [
+        Path::new("/media").join(&user),
+        Path::new("/run/media").join(&user),
+        PathBuf::from("/mnt"),
+    ]
+    .iter()
+    .filter_map(|parent| fs::read_dir(parent).ok())
+    .flat_map(|volumes| volumes.flatten())
+    .filter(|volume| volume.file_type().is_ok_and(|kind| kind.is_dir()))
+    .map(|volume| {
+        let trash = volume.path().join(format!(".Trash-{}", uid));
+        Trash::at(
+            trash.join("files"),
+            Some(volume.path()),
+            Some(trash.join("info")),
+        )
+    })
+    .collect()
+}
+
+#[cfg(not(unix))]
+fn volume_trashes(_home: &Path) -> Vec<Trash> {
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// A freedesktop.org trash holding a file and a folder
+    fn trash(root: &Path) -> Trash {
+        let trash = Trash::at(root.join("files"), None, Some(root.join("info")));
+        fs::create_dir_all(trash.path.join("project/src")).unwrap();
+        fs::create_dir_all(root.join("info")).unwrap();
+        fs::write(trash.path.join("notes.txt"), b"12345").unwrap();
+        fs::write(trash.path.join("project/src/main.rs"), b"fn main() {}").unwrap();
+        fs::write(trash.path.join(".DS_Store"), b"finder").unwrap();
+        for name in ["notes.txt", "project"] {
+            fs::write(
+                root.join(format!("info/{}.trashinfo", name)),
+                b"[Trash Info]",
+            )
+            .unwrap();
+        }
+        trash
+    }
+
+    #[test]
+    fn test_items_largest_first() {
+        let temp_dir = TempDir::new().unwrap();
+        let trash = trash(temp_dir.path());
+
+        let items = trash.items();
+        let sizes: Vec<u64> = items.iter().map(|item| item.bytes).collect();
+        assert_eq!(sizes, [12, 5]);
+        assert_eq!(items[0].path, trash.path.join("project"));
+        assert!(items[0].trashed.is_some());
+    }
+
+    #[test]
+    fn should_empty_only_items_trashed_before_the_cutoff() {
+        let temp_dir = TempDir::new().unwrap();
+        let trash = trash(temp_dir.path());
+
+        let emptied = trash.empty(Some(Utc::now() - chrono::Duration::days(30)), false);
+        assert!(emptied.removed.is_empty());
+        assert_eq!(emptied.kept, 2);
+
+        let emptied = trash.empty(None, true);
+        assert_eq!((emptied.removed.len(), emptied.bytes()), (2, 17));
+        assert!(trash.path.join("notes.txt").exists());
+
+        let emptied = trash.empty(None, false);
+        assert_eq!(emptied.removed.len(), 2);
+        assert!(emptied.skipped.is_empty());
+        assert!(trash.items().is_empty());
+        assert!(trash.path.join(".DS_Store").exists());
+        assert_eq!(
+            fs::read_dir(temp_dir.path().join("info")).unwrap().count(),
+            0
+        );
+    }
+}
@@ -0,0 +1,232 @@
+//! Xcode leftovers
+//!
+//! DerivedData and Archives are plain folders the cleaner sweeps like any
+//! other target. Two parts need more care. iOS DeviceSupport holds debug
+//! symbols copied from every iOS version a device was ever attached with;
+//! only the newest is kept, since it is the one the attached device most
+//! likely runs. Simulators whose runtime is no longer installed can't boot
+//! again, and are deleted through `xcrun simctl` so CoreSimulator's list of
+//! devices stays consistent; they can't be archived or restored.
+
+use dragonfly_core::error::{Error, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Build products and indexes, one folder per project
+pub const DERIVED_DATA: &str = "~/Library/Developer/Xcode/DerivedData";
+
+/// Archived app builds with their debug symbols
+pub const ARCHIVES: &str = "~/Library/Developer/Xcode/Archives";
+
+/// Debug symbols copied from attached devices, one folder per iOS version
+pub const DEVICE_SUPPORT: &str = "~/Library/Developer/Xcode/iOS DeviceSupport";
+
+/// A simulator whose runtime is no longer installed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Simulator {
+    /// Device identifier
+    pub udid: String,
+    /// Device name, e.g. `iPhone 14`
+    pub name: String,
+    /// Runtime identifier, e.g. `com.apple.CoreSimulator.SimRuntime.iOS-16-4`
+    pub runtime: String,
+    /// Folder holding the device's data
+    pub path: PathBuf,
+    /// Bytes its data takes
+    pub size: u64,
+}
+
+/// The version folder in a DeviceSupport `dir` to keep: the one for the
+/// highest iOS version
+///
+/// Folders are named after the version and build, e.g. `17.2 (21C62)`, and
+/// recent Xcode versions put the device model first, as in
+/// `iPhone15,2 17.2 (21C62)`. Folders without a version are never chosen.
+pub fn newest_device_support(dir: &Path) -> Option<PathBuf> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+        .filter_map(|entry| {
+            let version = parse_version(&entry.file_name().to_string_lossy())?;
+            Some((version, entry.path()))
+        })
+        .max()
+        .map(|(_, path)| path)
+}
+
+/// First dotted version number among the words of `name`
+fn parse_version(name: &str) -> Option<Vec<u32>> {
+    name.split_whitespace().find_map(|word| {
+        let parts: Option<Vec<u32>> = word.split('.').map(|part| part.parse().ok()).collect();
+        parts.filter(|parts| parts.len() >= 2)
+    })
+}
+
+/// Simulators whose runtime is no longer installed
+///
+/// Empty where Xcode's command line tools aren't installed.
+pub fn unavailable_simulators() -> Result<Vec<Simulator>> {
+    let output = match Command::new("xcrun")
+        .args(["simctl", "list", "devices", "unavailable", "--json"])
+        .output()
+    {
+        Ok(output) => output,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(Error::Internal(format!("Failed to run xcrun: {}", e))),
+    };
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::Internal(format!(
+            "xcrun simctl list failed: {}",
+            stderr.trim()
+        )));
+    }
+    parse_simulators(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Delete every simulator whose runtime is no longer installed
+pub fn delete_unavailable_simulators() -> Result<()> {
+    let output = Command::new("xcrun")
+        .args(["simctl", "delete", "unavailable"])
+        .output()
+        .map_err(|e| Error::Internal(format!("Failed to run xcrun: {}", e)))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::Internal(format!(
+            "xcrun simctl delete failed: {}",
+            stderr.trim()
+        )));
+    }
+    Ok(())
+}
+
+/// `simctl list devices --json` output
+#[derive(Deserialize)]
+struct DeviceList {
+    devices: HashMap<String, Vec<Device>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Device {
+    udid: String,
+    name: String,
+    /// Missing from older Xcode versions, which only list unavailable
+    /// devices when asked for them
+    #[serde(default)]
+    is_available: bool,
+    data_path: Option<PathBuf>,
+    data_path_size: Option<u64>,
+}
+
+/// The unavailable devices in `simctl list devices --json` output
+fn parse_simulators(json: &str) -> Result<Vec<Simulator>> {
+    let list: DeviceList = serde_json::from_str(json)
+        .map_err(|e| Error::Internal(format!("Unexpected simctl output: {}", e)))?;
+    let mut simulators: Vec<Simulator> = list
+        .devices
+        .into_iter()
+        .flat_map(|(runtime, devices)| {
+            devices
+                .into_iter()
+                .filter(|device| !device.is_available)
+                .map(move |device| {
+                    // The data folder sits inside the device's folder
+                    let path = device
+                        .data_path
+                        .as_deref()
+                        .and_then(Path::parent)
+                        .map(Path::to_path_buf)
+                        .unwrap_or_default();
+                    let size = device.data_path_size.unwrap_or_else(|| tree_size(&path));
+                    Simulator {
+                        udid: device.udid,
+                        name: device.name,
+                        runtime: runtime.clone(),
+                        path,
+                        size,
+                    }
+                })
+        })
+        .collect();
+    simulators.sort_by(|a, b| a.runtime.cmp(&b.runtime).then(a.name.cmp(&b.name)));
+    Ok(simulators)
+}
+
+/// Bytes the files under `path` take
+fn tree_size(path: &Path) -> u64 {
+    if path.as_os_str().is_empty() {
+        return 0;
+    }
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_newest_device_support() {
+        let temp_dir = TempDir::new().unwrap();
+        for name in [
+            "16.4.1 (20E252)",
+            "iPhone15,2 17.2 (21C62)",
+            "17.10 (21X1)",
+            "Other",
+        ] {
+            std::fs::create_dir(temp_dir.path().join(name)).unwrap();
+        }
+
+        assert_eq!(
+            newest_device_support(temp_dir.path()),
+            Some(temp_dir.path().join("17.10 (21X1)"))
+        );
+        assert_eq!(parse_version("iPhone15,2 17.2 (21C62)"), Some(vec![17, 2]));
+        assert_eq!(parse_version("Other"), None);
+    }
+
+    #[test]
+    fn test_parse_simulators() {
+        let json = r#"{
+            "devices": {
+                "com.apple.CoreSimulator.SimRuntime.iOS-16-4": [
+                    {
+                        "udid": "A1",
+                        "name": "iPhone 14",
+                        "isAvailable": false,
+                        "availabilityError": "runtime profile not found",
+                        "dataPath": "/Devices/A1/data",
+                        "dataPathSize": 2048,
+                        "state": "Shutdown"
+                    },
+                    { "udid": "B2", "name": "iPad", "isAvailable": true }
+                ],
+                "com.apple.CoreSimulator.SimRuntime.iOS-17-2": []
+            }
+        }"#;
+
+        let simulators = parse_simulators(json).unwrap();
+        assert_eq!(
+            simulators,
+            [Simulator {
+                udid: "A1".to_string(),
+                name: "iPhone 14".to_string(),
+                runtime: "com.apple.CoreSimulator.SimRuntime.iOS-16-4".to_string(),
+                path: PathBuf::from("/Devices/A1"),
+                size: 2048,
+            }]
+        );
+        assert!(parse_simulators("not json").is_err());
+    }
+}
@@ -0,0 +1,208 @@
+// DragonFly local dashboard
+
+const PALETTE = ["#39c5cf", "#6cb6ff", "#8ddb8c", "#e5b94a", "#dcbdfb", "#f69d50", "#96d0ff", "#ff938a"];
+
+function formatSize(bytes) {
+  const units = ["B", "kB", "MB", "GB", "TB"];
+  let value = bytes;
+  let unit = 0;
+  while (value >= 1000 && unit < units.length - 1) {
+    value /= 1000;
+    unit += 1;
+  }
+  return `${value.toFixed(unit === 0 ? 0 : 2)} ${units[unit]}`;
+}
+
+function setMeter(name, percent, text) {
+  const bar = document.getElementById(`${name}-bar`);
+  bar.style.width = `${Math.min(percent, 100)}%`;
+  bar.className = percent > 90 ? "bad" : percent > 70 ? "warn" : "";
+  document.getElementById(`${name}-text`).textContent = text;
+}
+
+async function refreshMetrics() {
+  try {
+    const response = await fetch("/api/metrics");
+    const { metrics, memory_usage_percent, disk_usage_percent } = await response.json();
+    setMeter("cpu", metrics.cpu_usage_percent, `${metrics.cpu_usage_percent.toFixed(1)}%`);
+    setMeter("memory", memory_usage_percent,
+      `${formatSize(metrics.memory_used_bytes)} / ${formatSize(metrics.memory_total_bytes)}`);
+    setMeter("disk", disk_usage_percent,
+      `${formatSize(metrics.disk_available_bytes)} free`);
+    const swapPercent = metrics.swap_total_bytes > 0
+      ? (metrics.swap_used_bytes / metrics.swap_total_bytes) * 100 : 0;
+    setMeter("swap", swapPercent,
+      `${formatSize(metrics.swap_used_bytes)} / ${formatSize(metrics.swap_total_bytes)}`);
+  } catch (e) {
+    console.error("metrics", e);
+  }
+}
+
+async function refreshCleanEstimates() {
+  const rows = document.getElementById("clean-rows");
+  try {
+    const response = await fetch("/api/clean-estimates");
+    const { estimates, total_bytes } = await response.json();
+    rows.innerHTML = "";
+    for (const estimate of estimates) {
+      const row = rows.insertRow();
+      row.insertCell().textContent = estimate.target;
+      row.insertCell().textContent = estimate.files.toLocaleString();
+      row.insertCell().textContent = formatSize(estimate.bytes);
+    }
+    const total = rows.insertRow();
+    total.insertCell().textContent = "Total";
+    total.insertCell();
+    total.insertCell().textContent = formatSize(total_bytes);
+  } catch (e) {
+    rows.innerHTML = "<tr><td colspan=\"3\">Could not estimate.</td></tr>";
+  }
+}
+
+// Treemap ------------------------------------------------------------------
+
+let path = [];
+
+// Squarified layout: returns [{node, x, y, w, h}]
+function layout(nodes, x, y, w, h) {
+  const total = nodes.reduce((sum, n) => sum + n.size, 0);
+  if (total === 0 || w <= 0 || h <= 0) return [];
+  const scale = (w * h) / total;
+  const items = nodes.map((node) => ({ node, area: node.size * scale }));
+  const tiles = [];
+
+  let rect = { x, y, w, h };
+  let row = [];
+  const worst = (r, side) => {
+    const sum = r.reduce((s, i) => s + i.area, 0);
+    const max = Math.max(...r.map((i) => i.area));
+    const min = Math.min(...r.map((i) => i.area));
+    return Math.max((side * side * max) / (sum * sum), (sum * sum) / (side * side * min));
+  };
+  const place = (r) => {
+    const sum = r.reduce((s, i) => s + i.area, 0);
+    if (rect.w >= rect.h) {
+      const width = sum / rect.h;
+      let offset = rect.y;
+      for (const i of r) {
+        const height = i.area / width;
+        tiles.push({ node: i.node, x: rect.x, y: offset, w: width, h: height });
+        offset += height;
+      }
+      rect = { x: rect.x + width, y: rect.y, w: rect.w - width, h: rect.h };
+    } else {
+      const height = sum / rect.w;
+      let offset = rect.x;
+      for (const i of r) {
+        const width = i.area / height;
+        tiles.push({ node: i.node, x: offset, y: rect.y, w: width, h: height });
+        offset += width;
+      }
+      rect = { x: rect.x, y: rect.y + height, w: rect.w, h: rect.h - height };
+    }
+  };
+
+  for (const item of items) {
+    const side = Math.min(rect.w, rect.h);
+    if (row.length === 0 || worst([...row, item], side) <= worst(row, side)) {
+      row.push(item);
+    } else {
+      place(row);
+      row = [item];
+    }
+  }
+  if (row.length > 0) place(row);
+  return tiles;
+}
+
+function renderTreemap(root) {
+  const container = document.getElementById("treemap");
+  container.innerHTML = "";
+
+  let node = root;
+  for (const name of path) {
+    const next = node.children.find((c) => c.name === name);
+    if (!next) break;
+    node = next;
+  }
+
+  const crumbs = document.getElementById("breadcrumbs");
+  crumbs.innerHTML = "";
+  [root.name, ...path].forEach((name, depth) => {
+    if (depth > 0) crumbs.append(" / ");
+    const link = document.createElement("a");
+    link.textContent = name;
+    link.onclick = () => {
+      path = path.slice(0, depth);
+      renderTreemap(root);
+    };
+    crumbs.append(link);
+  });
+
+  const children = node.children.filter((c) => c.size > 0);
+  const tiles = layout(children, 0, 0, container.clientWidth, container.clientHeight);
+  tiles.forEach((tile, index) => {
+    const div = document.createElement("div");
+    div.className = tile.node.children.length > 0 ? "tile" : "tile leaf";
+    div.style.left = `${tile.x}px`;
+    div.style.top = `${tile.y}px`;
+    div.style.width = `${tile.w}px`;
+    div.style.height = `${tile.h}px`;
+    div.style.background = PALETTE[index % PALETTE.length];
+    div.title = `${tile.node.name}\n${formatSize(tile.node.size)}`;
+    div.innerHTML = "<span></span><span></span>";
+    div.children[0].textContent = tile.node.name;
+    div.children[1].textContent = formatSize(tile.node.size);
+    if (tile.node.children.length > 0) {
+      div.onclick = () => {
+        path.push(tile.node.name);
+        renderTreemap(root);
+      };
+    }
+    container.append(div);
+  });
+}
+
+function showScan(scan) {
+  const status = document.getElementById("scan-status");
+  if (!scan) {
+    status.textContent = "No scan yet.";
+    return;
+  }
+  const when = new Date(scan.scanned_at).toLocaleString();
+  status.textContent =
+    `${formatSize(scan.total_size)} in ${scan.total_files.toLocaleString()} files - scanned ${when}`;
+  path = [];
+  renderTreemap(scan.tree);
+}
+
+async function loadLastScan() {
+  const response = await fetch("/api/scan");
+  const { root, scan } = await response.json();
+  document.getElementById("scan-button").textContent = `Scan ${root}`;
+  showScan(scan);
+}
+
+async function runScan() {
+  const button = document.getElementById("scan-button");
+  const status = document.getElementById("scan-status");
+  button.disabled = true;
+  status.textContent = "Scanning...";
+  try {
+    const response = await fetch("/api/scan", { method: "POST" });
+    const body = await response.json();
+    if (body.status === "ok") {
+      showScan(body.scan);
+    } else {
+      status.textContent = `Scan failed: ${body.message}`;
+    }
+  } finally {
+    button.disabled = false;
+  }
+}
+
+document.getElementById("scan-button").onclick = runScan;
+refreshMetrics();
+setInterval(refreshMetrics, 2000);
+refreshCleanEstimates();
+loadLastScan();
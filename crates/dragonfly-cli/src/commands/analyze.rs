@@ -1,16 +1,126 @@
 //! Disk analysis command handler
 
+use crate::commands::index::watch_growth;
+use crate::commands::snapshots::{handle_snapshots, saved_scan_file};
+use crate::commands::{audit, trends};
+use crate::error_tracking::{breadcrumb, breadcrumb_path};
 use crate::types::DiskCommand;
-use anyhow::{Context, Result};
+use crate::ui::{
+    create_spinner, ncdu_json, print_json, print_profile, print_skipped, profile_json,
+    scan_progress_message, treemap_json, write_delimited,
+};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
 use colored::Colorize;
+use dragonfly_cleaner::{
+    thin_binary, AuditAction, AuditEntry, RecoveryManager, RecoveryManifest, SystemCleaner,
+    TimeMachineManager,
+};
+use dragonfly_core::domain::entities::FileEntity;
+use dragonfly_core::domain::policy::policy;
+use dragonfly_core::domain::scan_options::ScanOptions;
+use dragonfly_core::domain::scan_profile::{micros_since, ScanProfile};
 use dragonfly_core::domain::value_objects::FilePath;
-use dragonfly_disk::DiskAnalyzer;
+use dragonfly_core::ports::{CancellationToken, ProgressUpdate};
+use dragonfly_core::t;
+use dragonfly_disk::{
+    snapshot_volume, AnalysisResult, AnalysisStrategy, BrokenLinkFinder, DevArtifactFinder,
+    DevArtifactKind, DirectoryNode, DiskAnalyzer, EmptyDirFinder, FileCategory, FileChange,
+    MailAnalyzer, MountedVolume, PathGrowth, PhotosLibraryAnalyzer, QuickEstimate, RefreshStats,
+    RootTotals, SavedFile, SavedScan, ScanCheckpoint, ScreenshotFinder, SizeIndex, SnapshotDiff,
+    SnapshotMount, ThinningAnalyzer, ThinningReport, VolumeProgress, VolumeReport,
+    VolumeScanCoordinator, DEFAULT_MAIL_DIR, DEFAULT_MAIL_DOWNLOADS, DEFAULT_PHOTOS_LIBRARY,
+    DEFAULT_PROBES,
+};
 use humansize::{format_size, DECIMAL};
+use indicatif::{MultiProgress, ProgressBar};
 use serde_json::json;
 use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// How long removed items stay restorable
+pub(crate) const RECOVERY_RETENTION_DAYS: u32 = 30;
+
+/// `file` relative to the scanned `root`, so history matches however the
+/// root was spelled on the command line
+fn relative_to(root: &Path, file: &str) -> String {
+    Path::new(file)
+        .strip_prefix(root)
+        .map_or_else(|_| file.to_string(), |p| p.to_string_lossy().into_owned())
+}
+
+/// Annotation for a file that changed (or didn't) since the previous scan
+fn change_marker(change: FileChange, size: u64, previous: Option<u64>) -> String {
+    let difference = format_size(size.abs_diff(previous.unwrap_or(size)), DECIMAL);
+    match change {
+        FileChange::New => t!("disk.change_new").green().to_string(),
+        FileChange::Grown => t!("disk.change_grown", size = difference).red().to_string(),
+        FileChange::Shrunk => t!("disk.change_shrunk", size = difference)
+            .cyan()
+            .to_string(),
+        FileChange::Unchanged => t!("disk.change_unchanged").dimmed().to_string(),
+    }
+}
+
+/// Show a sampled size estimate with its confidence interval
+fn print_quick_estimate(path: &str, estimate: &QuickEstimate, json: bool) -> Result<()> {
+    let (low, high) = estimate.range();
+    if json {
+        let json_output = json!({
+            "status": "ok",
+            "path": path,
+            "strategy": AnalysisStrategy::Quick,
+            "total_size": estimate.total_size,
+            "total_size_low": low,
+            "total_size_high": high,
+            "confidence": 0.95,
+            "estimated_files": estimate.file_count,
+            "probes": estimate.probes,
+            "directories_read": estimate.directories_read,
+            "skipped": estimate.skipped,
+            "interrupted": estimate.interrupted
+        });
+        return print_json(&json_output);
+    }
+
+    println!("{}", t!("disk.title").bold().bright_cyan());
+    if estimate.interrupted {
+        println!("{}", t!("common.interrupted").yellow());
+    }
+    println!("{}", t!("disk.path", path = path));
+    println!(
+        "{}",
+        t!(
+            "disk.quick_total",
+            size = format_size(estimate.total_size, DECIMAL).bold(),
+            margin = format_size(estimate.margin, DECIMAL)
+        )
+    );
+    println!(
+        "{}",
+        t!(
+            "disk.quick_range",
+            low = format_size(low, DECIMAL),
+            high = format_size(high, DECIMAL)
+        )
+    );
+    println!("{}", t!("disk.quick_files", count = estimate.file_count));
+    println!(
+        "{}",
+        t!(
+            "disk.quick_sampled",
+            dirs = estimate.directories_read,
+            probes = estimate.probes
+        )
+        .dimmed()
+    );
+    print_skipped(&estimate.skipped);
+    Ok(())
+}
 
 /// Parse size string like "100MB", "1GB" to bytes
-fn parse_size(size_str: &str) -> Result<u64> {
+pub(crate) fn parse_size(size_str: &str) -> Result<u64> {
     let size_str = size_str.trim().to_uppercase();
     let (num_str, unit) = if size_str.ends_with("KB") {
         (size_str.trim_end_matches("KB"), 1024)
@@ -33,73 +143,455 @@ fn parse_size(size_str: &str) -> Result<u64> {
     Ok(num * unit)
 }
 
-pub async fn handle_disk(command: DiskCommand, json: bool) -> Result<()> {
+/// Parse a point in time: a date (`2024-01-31`), a local date and time
+/// (`2024-01-31 14:00`), RFC 3339, or how long ago (`30d`, `12h`, `2w`,
+/// `6mo`, `1y`)
+pub(crate) fn parse_time(value: &str) -> Result<DateTime<Utc>> {
+    const DAY: i64 = 24 * 60 * 60;
+    let value = value.trim();
+    let ago = [
+        ("mo", 30 * DAY),
+        ("y", 365 * DAY),
+        ("w", 7 * DAY),
+        ("d", DAY),
+        ("h", 60 * 60),
+    ]
+    .iter()
+    .find_map(|(unit, seconds)| {
+        let count: i64 = value.strip_suffix(unit)?.parse().ok()?;
+        Some(count * seconds)
+    });
+    if let Some(seconds) = ago {
+        return Ok(Utc::now() - chrono::Duration::seconds(seconds));
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M")
+        .ok()
+        .or_else(|| {
+            chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .and_then(|time| time.and_local_timezone(chrono::Local).earliest())
+        .map(|time| time.with_timezone(&Utc))
+        .with_context(|| t!("disk.invalid_time", value = value))
+}
+
+pub async fn handle_disk(
+    command: DiskCommand,
+    json: bool,
+    dry_run: bool,
+    cancel: &CancellationToken,
+) -> Result<()> {
     match command {
         DiskCommand::Analyze {
-            path,
+            paths,
             min_size,
             top,
+            dirs,
+            index,
+            strategy,
+            profile,
+            physical,
+            format,
+            package_mode,
+            modified_after,
+            modified_before,
+            save,
+            resume,
+            walk,
             json: cmd_json,
         } => {
+            let options = walk.scan_options();
+            // The size index always covers whole trees
+            if walk.is_set() && (index || strategy == "incremental") {
+                bail!(t!("disk.walk_options_with_index"));
+            }
+            // Spreadsheet formats list the files the table would show;
+            // the others export the whole scan
+            let separator = format.as_deref().and_then(delimiter);
+            let export = format.as_deref().filter(|_| separator.is_none());
+            // Several roots are always walked in full, and history is kept
+            // per root
+            let multi_root = paths.len() > 1;
+            if multi_root
+                && (index || strategy != "deep" || export.is_some() || save.is_some() || resume)
+            {
+                bail!(t!("disk.multi_root_needs_deep_scan"));
+            }
+            let path = paths[0].clone();
+            let modified_after = modified_after.as_deref().map(parse_time).transpose()?;
+            let modified_before = modified_before.as_deref().map(parse_time).transpose()?;
+            // The size index keeps no modification times
+            if (modified_after.is_some() || modified_before.is_some())
+                && (index || strategy != "deep" || export.is_some())
+            {
+                bail!(t!("disk.modified_needs_deep_scan"));
+            }
             let output_json = json || cmd_json;
             let file_path = FilePath::new(path.to_string_lossy().to_string());
             let analyzer = DiskAnalyzer::new();
 
-            let result = analyzer
-                .analyze(&file_path)
-                .await
-                .context("Failed to analyze directory")?;
+            if format.is_some() && strategy != "deep" {
+                bail!(t!("disk.format_needs_deep_scan"));
+            }
+            if let Some(format) = export {
+                breadcrumb("scan", "Scan started", &[("path", breadcrumb_path(&path))]);
+                return export_scan(&file_path, format, &options, cancel).await;
+            }
+
+            if save.is_some() && (strategy == "quick" || export.is_some()) {
+                bail!(t!("disk.save_needs_file_list"));
+            }
+            if resume && strategy != "deep" {
+                bail!(t!("disk.resume_needs_deep_scan"));
+            }
+
+            if strategy == "quick" {
+                breadcrumb(
+                    "scan",
+                    "Estimate started",
+                    &[("path", breadcrumb_path(&path))],
+                );
+                let estimate = analyzer
+                    .estimate_quick(&file_path, &options, DEFAULT_PROBES, cancel)
+                    .await
+                    .context("Failed to estimate directory size")?;
+                return print_quick_estimate(file_path.as_str(), &estimate, output_json);
+            }
 
+            let indexed = if index {
+                indexed_analysis(&path, 0)?
+            } else {
+                None
+            };
+            let mut refresh = None;
+            let mut roots = None;
+            let (mut result, as_of, previous) = match indexed {
+                Some((result, as_of)) => (result, Some(as_of), None),
+                None => {
+                    breadcrumb("scan", "Scan started", &[("path", breadcrumb_path(&path))]);
+                    let result = if multi_root {
+                        let (result, subtotals) =
+                            analyze_roots(&paths, &options, output_json, cancel).await?;
+                        roots = Some(subtotals);
+                        result
+                    } else if strategy == "incremental" {
+                        let (result, stats) = incremental_analysis(&path, output_json, cancel)?;
+                        refresh = stats;
+                        result
+                    } else {
+                        let spinner = (!output_json).then(|| {
+                            let spinner = create_spinner(t!("disk.scanning"));
+                            spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+                            spinner
+                        });
+                        let report = |update: &ProgressUpdate| {
+                            if let Some(spinner) = &spinner {
+                                spinner.set_message(scan_progress_message(update));
+                            }
+                        };
+                        let checkpoint = ScanCheckpoint::default_path();
+                        let resumed = if resume {
+                            if !checkpoint.exists() {
+                                bail!(t!("disk.resume_no_checkpoint"));
+                            }
+                            let resumed = ScanCheckpoint::load(&checkpoint)
+                                .context("Failed to read scan checkpoint")?;
+                            if let Some(spinner) = &spinner {
+                                spinner.println(t!(
+                                    "disk.resuming",
+                                    count = resumed.files.len(),
+                                    saved = resumed
+                                        .saved_at
+                                        .with_timezone(&chrono::Local)
+                                        .format("%Y-%m-%d %H:%M")
+                                ));
+                            }
+                            Some(resumed)
+                        } else {
+                            None
+                        };
+                        let result = analyzer
+                            .analyze_checkpointed(
+                                &file_path,
+                                &options,
+                                &checkpoint,
+                                resumed,
+                                &report,
+                                cancel,
+                            )
+                            .await
+                            .context("Failed to analyze directory")?;
+                        if let Some(spinner) = spinner {
+                            spinner.finish_and_clear();
+                            if result.interrupted {
+                                eprintln!("{}", t!("disk.resume_hint").yellow());
+                            }
+                        }
+                        result
+                    };
+                    scan_finished_breadcrumb(&result);
+                    // Only complete scans say what the largest files are
+                    let previous = if result.interrupted || multi_root {
+                        None
+                    } else {
+                        trends::record_scan(&path, result.total_size, result.files.len() as u64);
+                        trends::record_free_space(&path);
+                        let sizes: Vec<(String, u64)> = result
+                            .files
+                            .iter()
+                            .map(|f| (relative_to(&path, &f.path), f.size))
+                            .collect();
+                        trends::track_largest_files(&path, &sizes)
+                    };
+                    (result, None, previous)
+                }
+            };
+            // A partial scan would show everything it missed as deleted
+            let saved_to = match &save {
+                Some(save) if !result.interrupted => {
+                    let save = saved_scan_file(save)?;
+                    SavedScan::from_result(&path, &result)
+                        .save(&save)
+                        .with_context(|| format!("Failed to save scan to {}", save.display()))?;
+                    Some(save)
+                }
+                Some(_) => {
+                    eprintln!("{}", t!("disk.save_skipped_interrupted").yellow());
+                    None
+                }
+                None => None,
+            };
+            // History and saved scans keep individual files
+            match package_mode.as_str() {
+                "collapse" => {
+                    for root in &paths {
+                        result.collapse_packages(root);
+                    }
+                }
+                "expand" => {}
+                other => bail!("{}", t!("disk.unknown_package_mode", mode = other)),
+            }
+            // How each file changed since the previous scan, with its
+            // earlier size
+            let change_of = |file: &FileEntity| {
+                previous.as_ref().map(|previous| {
+                    let relative = relative_to(&path, &file.path);
+                    (
+                        previous.change(&relative, file.size),
+                        previous.previous_size(&relative),
+                    )
+                })
+            };
+
+            // Only a walk sees allocated blocks; the index keeps logical sizes
+            let physical_known = result.allocated_size.is_some();
+            let shown_size = |file: &FileEntity| {
+                if physical {
+                    file.size_on_disk()
+                } else {
+                    file.size
+                }
+            };
+            let shown_total = if physical {
+                result.allocated_size.unwrap_or(result.total_size)
+            } else {
+                result.total_size
+            };
+            // Directories count every file, whatever the filters leave out
+            let top_dirs = dirs.then(|| top_directories(&paths, &result.files, shown_size, top));
             let mut files = result.files;
 
             // Filter by min_size if provided
             if let Some(ref ms) = min_size {
                 let min_bytes = parse_size(ms)?;
-                files.retain(|f| f.size >= min_bytes);
+                files.retain(|f| shown_size(f) >= min_bytes);
             }
+            files.retain(|f| f.modified_between(modified_after, modified_before));
 
             // Sort by size descending
-            files.sort_by_key(|f| Reverse(f.size));
+            files.sort_by_key(|f| Reverse(shown_size(f)));
 
             // Take top N
             let top_files: Vec<_> = files.into_iter().take(top).collect();
 
-            if output_json {
+            if let Some(separator) = separator {
+                if result.interrupted {
+                    eprintln!("{}", t!("common.interrupted").yellow());
+                }
+                print_delimited(&top_files, separator)?;
+            } else if output_json {
                 let json_output = json!({
                     "status": "ok",
                     "path": file_path.as_str(),
                     "total_size": result.total_size,
+                    "allocated_size": result.allocated_size,
+                    "physical": physical,
+                    "roots": roots.as_ref().map(|roots| roots.iter().map(|root| json!({
+                        "path": root.root,
+                        "total_size": root.totals.total_size,
+                        "allocated_size": root.totals.allocated_size,
+                        "file_count": root.totals.file_count
+                    })).collect::<Vec<_>>()),
                     "total_files": top_files.len(),
-                    "files": top_files.iter().map(|f| json!({
-                        "path": f.path,
-                        "size": f.size
-                    })).collect::<Vec<_>>()
+                    "top_directories": top_dirs.as_ref().map(|dirs| dirs.iter().map(|dir| json!({
+                        "path": dir.path,
+                        "size": dir.size,
+                        "file_count": dir.file_count
+                    })).collect::<Vec<_>>()),
+                    "modified_after": modified_after,
+                    "modified_before": modified_before,
+                    "files": top_files.iter().map(|f| {
+                        let change = change_of(f);
+                        json!({
+                            "path": f.path,
+                            "size": f.size,
+                            "allocated_size": f.allocated_size,
+                            "hardlink_count": f.hardlink_count,
+                            "package_files": f.package_files,
+                            "owner_uid": f.owner_uid,
+                            "modified": f.modified,
+                            "protection": f.protection,
+                            "change": change.map(|(change, _)| change),
+                            "previous_size": change.and_then(|(_, previous)| previous)
+                        })
+                    }).collect::<Vec<_>>(),
+                    "skipped": result.skipped,
+                    "strategy": result.strategy,
+                    "compared_with": previous.as_ref().map(|p| p.recorded_at),
+                    "index_updated_at": as_of,
+                    "refresh": refresh,
+                    "saved_to": saved_to,
+                    "interrupted": result.interrupted,
+                    "profile": profile.then(|| profile_json(&result.profile))
                 });
-                println!("{}", serde_json::to_string_pretty(&json_output)?);
+                print_json(&json_output)?;
             } else {
-                println!("{}", "Disk Analysis".bold().bright_cyan());
-                println!("Path: {}", file_path.as_str());
-                println!("Total size: {}", format_size(result.total_size, DECIMAL));
-                println!("Total files: {}", top_files.len());
+                println!("{}", t!("disk.title").bold().bright_cyan());
+                if result.interrupted {
+                    println!("{}", t!("common.interrupted").yellow());
+                }
+                match &roots {
+                    Some(roots) => {
+                        for root in roots {
+                            let size = if physical {
+                                root.totals.allocated_size
+                            } else {
+                                root.totals.total_size
+                            };
+                            println!(
+                                "{}",
+                                t!(
+                                    "disk.root_size",
+                                    path = root.root.display(),
+                                    size = format_size(size, DECIMAL).bold(),
+                                    files = t!("disk.tree_files", count = root.totals.file_count)
+                                        .dimmed()
+                                )
+                            );
+                        }
+                    }
+                    None => println!("{}", t!("disk.path", path = file_path.as_str())),
+                }
+                print_index_source(index, as_of);
+                if physical && !physical_known {
+                    println!("{}", t!("disk.physical_unavailable").yellow());
+                }
+                let total_label = if physical {
+                    t!("disk.size_on_disk")
+                } else {
+                    t!("disk.total_size")
+                };
+                println!("{}: {}", total_label, format_size(shown_total, DECIMAL));
+                println!("{}", t!("disk.total_files", count = top_files.len()));
                 if let Some(ref ms) = min_size {
-                    println!("Minimum size filter: {}", ms);
+                    println!("{}", t!("disk.min_size", size = ms));
+                }
+                if let Some(refresh) = &refresh {
+                    print_refresh(refresh);
                 }
-                println!("\nTop {} largest files:\n", top);
+                if let Some(saved_to) = saved_to {
+                    let file = saved_to.display().to_string();
+                    println!("{}", t!("disk.saved_scan", file = file).dimmed());
+                }
+                if let Some(previous) = &previous {
+                    let date = previous.recorded_at.with_timezone(&chrono::Local);
+                    let date = date.format("%Y-%m-%d %H:%M").to_string();
+                    println!("{}", t!("disk.compared_with", date = date).dimmed());
+                }
+                if let Some(top_dirs) = &top_dirs {
+                    println!("\n{}\n", t!("disk.tree_heaviest", count = top_dirs.len()));
+                    for (i, dir) in top_dirs.iter().enumerate() {
+                        println!(
+                            "{:3}. {} - {} {}",
+                            i + 1,
+                            format_size(dir.size, DECIMAL).bold(),
+                            dir.path.display(),
+                            t!("disk.tree_files", count = dir.file_count).dimmed()
+                        );
+                    }
+                }
+                println!("\n{}\n", t!("disk.top_files", count = top));
                 for (i, file) in top_files.iter().enumerate() {
+                    let marker = if file.protection.is_protected() {
+                        format!(" {}", t!("common.protected")).yellow().to_string()
+                    } else {
+                        String::new()
+                    };
+                    let change = change_of(file)
+                        .map(|(change, previous)| {
+                            format!(" {}", change_marker(change, file.size, previous))
+                        })
+                        .unwrap_or_default();
+                    let links = match (file.package_files, file.hardlink_count) {
+                        (Some(count), _) => {
+                            format!(" {}", t!("disk.package_files", count = count).dimmed())
+                        }
+                        (None, Some(count)) if count > 1 => {
+                            format!(" {}", t!("disk.hardlinks", count = count).dimmed())
+                        }
+                        _ => String::new(),
+                    };
                     println!(
-                        "{:3}. {} - {}",
+                        "{:3}. {} - {}{}{}{}",
                         i + 1,
-                        format_size(file.size, DECIMAL).bold(),
-                        file.path
+                        format_size(shown_size(file), DECIMAL).bold(),
+                        file.path,
+                        marker,
+                        links,
+                        change
                     );
                 }
+                print_skipped(&result.skipped);
+                if profile {
+                    print_profile(&result.profile);
+                }
             }
         }
         DiskCommand::Large {
             path,
             min_size,
+            spotlight,
+            index,
+            modified_after,
+            modified_before,
+            profile,
+            format,
+            walk,
             json: cmd_json,
         } => {
+            let options = walk.scan_options();
+            if walk.is_set() && index {
+                bail!(t!("disk.walk_options_with_index"));
+            }
+            let modified_after = modified_after.as_deref().map(parse_time).transpose()?;
+            let modified_before = modified_before.as_deref().map(parse_time).transpose()?;
+            if (modified_after.is_some() || modified_before.is_some()) && index {
+                bail!(t!("disk.modified_needs_deep_scan"));
+            }
             let output_json = json || cmd_json;
             let file_path = FilePath::new(path.to_string_lossy().to_string());
             let analyzer = DiskAnalyzer::new();
@@ -107,16 +599,42 @@ pub async fn handle_disk(command: DiskCommand, json: bool) -> Result<()> {
             let min_bytes = parse_size(&min_size)
                 .with_context(|| format!("Invalid size format: {}", min_size))?;
 
-            let large_files = analyzer
-                .find_large_files(&file_path, min_bytes)
-                .await
-                .context("Failed to find large files")?;
+            let indexed = if index {
+                indexed_analysis(&path, min_bytes)?
+            } else {
+                None
+            };
+            let (result, as_of) = match indexed {
+                Some((result, as_of)) => (result, Some(as_of)),
+                None => {
+                    breadcrumb("scan", "Scan started", &[("path", breadcrumb_path(&path))]);
+                    let strategy = if spotlight {
+                        AnalysisStrategy::Spotlight
+                    } else {
+                        AnalysisStrategy::Deep
+                    };
+                    let result = analyzer
+                        .find_large_files_with_strategy(
+                            &file_path, min_bytes, strategy, &options, cancel,
+                        )
+                        .await
+                        .context("Failed to find large files")?;
+                    scan_finished_breadcrumb(&result);
+                    (result, None)
+                }
+            };
 
             // Sort by size descending
-            let mut sorted_files = large_files;
+            let mut sorted_files = result.files;
+            sorted_files.retain(|f| f.modified_between(modified_after, modified_before));
             sorted_files.sort_by_key(|f| Reverse(f.size));
 
-            if output_json {
+            if let Some(separator) = format.as_deref().and_then(delimiter) {
+                if result.interrupted {
+                    eprintln!("{}", t!("common.interrupted").yellow());
+                }
+                print_delimited(&sorted_files, separator)?;
+            } else if output_json {
                 let json_output = json!({
                     "status": "ok",
                     "path": file_path.as_str(),
@@ -125,19 +643,41 @@ pub async fn handle_disk(command: DiskCommand, json: bool) -> Result<()> {
                     "files_found": sorted_files.len(),
                     "files": sorted_files.iter().map(|f| json!({
                         "path": f.path,
-                        "size": f.size
-                    })).collect::<Vec<_>>()
+                        "size": f.size,
+                        "hardlink_count": f.hardlink_count,
+                        "modified": f.modified
+                    })).collect::<Vec<_>>(),
+                    "modified_after": modified_after,
+                    "modified_before": modified_before,
+                    "strategy": result.strategy,
+                    "index_updated_at": as_of,
+                    "interrupted": result.interrupted,
+                    "profile": profile.then(|| profile_json(&result.profile))
                 });
-                println!("{}", serde_json::to_string_pretty(&json_output)?);
+                print_json(&json_output)?;
             } else {
-                println!("{}", "Finding Large Files".bold().bright_cyan());
-                println!("Path: {}", file_path.as_str());
+                println!("{}", t!("disk.large_title").bold().bright_cyan());
+                if result.interrupted {
+                    println!("{}", t!("common.interrupted").yellow());
+                }
+                println!("{}", t!("disk.path", path = file_path.as_str()));
                 println!(
-                    "Minimum size: {} ({})",
-                    min_size,
-                    format_size(min_bytes, DECIMAL)
+                    "{}",
+                    t!(
+                        "disk.large_min_size",
+                        size = min_size,
+                        bytes = format_size(min_bytes, DECIMAL)
+                    )
                 );
-                println!("Files found: {}\n", sorted_files.len());
+                match (spotlight, result.strategy) {
+                    (true, AnalysisStrategy::Spotlight) => {
+                        println!("{}", t!("disk.large_spotlight"))
+                    }
+                    (true, _) => println!("{}", t!("disk.large_spotlight_unavailable").yellow()),
+                    _ => {}
+                }
+                print_index_source(index, as_of);
+                println!("{}\n", t!("disk.large_found", count = sorted_files.len()));
                 for (i, file) in sorted_files.iter().enumerate() {
                     println!(
                         "{:3}. {} - {}",
@@ -146,8 +686,1813 @@ pub async fn handle_disk(command: DiskCommand, json: bool) -> Result<()> {
                         file.path
                     );
                 }
+                if profile {
+                    print_profile(&result.profile);
+                }
+            }
+        }
+        DiskCommand::Tree {
+            path,
+            top,
+            depth,
+            walk,
+            json: cmd_json,
+        } => {
+            let options = walk.scan_options();
+            let output_json = json || cmd_json;
+            let file_path = FilePath::new(path.to_string_lossy().to_string());
+            breadcrumb("scan", "Scan started", &[("path", breadcrumb_path(&path))]);
+            let tree = DiskAnalyzer::new()
+                .analyze_tree(&file_path, &options, cancel)
+                .await
+                .context("Failed to analyze directory")?;
+            let heaviest = tree.root.heaviest(top, depth);
+
+            if output_json {
+                let json_output = json!({
+                    "status": "ok",
+                    "path": file_path.as_str(),
+                    "total_size": tree.root.size,
+                    "file_count": tree.root.file_count,
+                    "directories": heaviest.iter().map(|(dir, depth)| json!({
+                        "path": dir.path,
+                        "size": dir.size,
+                        "file_count": dir.file_count,
+                        "depth": depth
+                    })).collect::<Vec<_>>(),
+                    "skipped": tree.skipped,
+                    "interrupted": tree.interrupted
+                });
+                print_json(&json_output)?;
+            } else {
+                println!("{}", t!("disk.tree_title").bold().bright_cyan());
+                if tree.interrupted {
+                    println!("{}", t!("common.interrupted").yellow());
+                }
+                println!("{}", t!("disk.path", path = file_path.as_str()));
+                println!(
+                    "{}",
+                    t!(
+                        "disk.tree_total",
+                        size = format_size(tree.root.size, DECIMAL),
+                        count = tree.root.file_count
+                    )
+                );
+                if heaviest.is_empty() {
+                    println!("\n{}", t!("disk.tree_empty"));
+                } else {
+                    println!("\n{}\n", t!("disk.tree_heaviest", count = heaviest.len()));
+                }
+                for (i, (dir, _)) in heaviest.iter().enumerate() {
+                    println!(
+                        "{:3}. {} - {} {}",
+                        i + 1,
+                        format_size(dir.size, DECIMAL).bold(),
+                        dir.path.display(),
+                        t!("disk.tree_files", count = dir.file_count).dimmed()
+                    );
+                }
+                print_skipped(&tree.skipped);
+            }
+        }
+        DiskCommand::Types {
+            path,
+            walk,
+            json: cmd_json,
+        } => {
+            let options = walk.scan_options();
+            let output_json = json || cmd_json;
+            let file_path = FilePath::new(path.to_string_lossy().to_string());
+            breadcrumb("scan", "Scan started", &[("path", breadcrumb_path(&path))]);
+            let result = DiskAnalyzer::new()
+                .analyze_with_options(&file_path, &options, cancel)
+                .await
+                .context("Failed to analyze directory")?;
+            scan_finished_breadcrumb(&result);
+            let categories = result.by_category();
+
+            if output_json {
+                let json_output = json!({
+                    "status": "ok",
+                    "path": file_path.as_str(),
+                    "total_size": result.total_size,
+                    "file_count": result.files.len(),
+                    "categories": categories,
+                    "skipped": result.skipped,
+                    "interrupted": result.interrupted
+                });
+                print_json(&json_output)?;
+            } else {
+                println!("{}", t!("disk.types_title").bold().bright_cyan());
+                if result.interrupted {
+                    println!("{}", t!("common.interrupted").yellow());
+                }
+                println!("{}", t!("disk.path", path = file_path.as_str()));
+                println!(
+                    "{}\n",
+                    t!(
+                        "disk.tree_total",
+                        size = format_size(result.total_size, DECIMAL),
+                        count = result.files.len()
+                    )
+                );
+                for usage in &categories {
+                    let share = if result.total_size == 0 {
+                        0.0
+                    } else {
+                        usage.total_size as f64 * 100.0 / result.total_size as f64
+                    };
+                    println!(
+                        "  {:<12} {:>10} {:>5.1}%  {}",
+                        category_label(usage.category),
+                        format_size(usage.total_size, DECIMAL).bold(),
+                        share,
+                        t!("disk.tree_files", count = usage.file_count).dimmed()
+                    );
+                }
+                print_skipped(&result.skipped);
+            }
+        }
+        DiskCommand::ByOwner {
+            path,
+            walk,
+            json: cmd_json,
+        } => {
+            let options = walk.scan_options();
+            let output_json = json || cmd_json;
+            let file_path = FilePath::new(path.to_string_lossy().to_string());
+            breadcrumb("scan", "Scan started", &[("path", breadcrumb_path(&path))]);
+            let result = DiskAnalyzer::new()
+                .analyze_with_options(&file_path, &options, cancel)
+                .await
+                .context("Failed to analyze directory")?;
+            scan_finished_breadcrumb(&result);
+            let owners = result.by_owner();
+
+            if output_json {
+                let json_output = json!({
+                    "status": "ok",
+                    "path": file_path.as_str(),
+                    "total_size": result.total_size,
+                    "file_count": result.files.len(),
+                    "owners": owners,
+                    "skipped": result.skipped,
+                    "interrupted": result.interrupted
+                });
+                print_json(&json_output)?;
+            } else {
+                println!("{}", t!("disk.owners_title").bold().bright_cyan());
+                if result.interrupted {
+                    println!("{}", t!("common.interrupted").yellow());
+                }
+                println!("{}", t!("disk.path", path = file_path.as_str()));
+                println!(
+                    "{}\n",
+                    t!(
+                        "disk.tree_total",
+                        size = format_size(result.total_size, DECIMAL),
+                        count = result.files.len()
+                    )
+                );
+                for usage in &owners {
+                    let share = if result.total_size == 0 {
+                        0.0
+                    } else {
+                        usage.total_size as f64 * 100.0 / result.total_size as f64
+                    };
+                    let owner = match (&usage.user, usage.uid) {
+                        (Some(user), _) => user.clone(),
+                        (None, Some(uid)) => t!("disk.owners_uid", uid = uid),
+                        (None, None) => t!("disk.owners_unknown").to_string(),
+                    };
+                    println!(
+                        "  {:<16} {:>10} {:>5.1}%  {}",
+                        owner,
+                        format_size(usage.total_size, DECIMAL).bold(),
+                        share,
+                        t!("disk.tree_files", count = usage.file_count).dimmed()
+                    );
+                }
+                print_skipped(&result.skipped);
+            }
+        }
+        DiskCommand::DevJunk {
+            path,
+            top,
+            json: cmd_json,
+        } => {
+            let output_json = json || cmd_json;
+            let spinner = (!output_json).then(|| create_spinner(t!("disk.dev_searching")));
+            breadcrumb(
+                "scan",
+                "Developer artifact scan started",
+                &[("path", breadcrumb_path(&path))],
+            );
+            let report = DevArtifactFinder::new()
+                .find(&path, cancel)
+                .await
+                .context("Failed to search for developer artifacts")?;
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+            let projects: Vec<_> = report.projects.iter().take(top).collect();
+
+            if output_json {
+                let json_output = json!({
+                    "status": "ok",
+                    "path": report.root,
+                    "total_size": report.total_size,
+                    "project_count": report.projects.len(),
+                    "projects": projects,
+                    "skipped": report.skipped,
+                    "interrupted": report.interrupted
+                });
+                print_json(&json_output)?;
+            } else {
+                println!("{}", t!("disk.dev_title").bold().bright_cyan());
+                if report.interrupted {
+                    println!("{}", t!("common.interrupted").yellow());
+                }
+                println!("{}", t!("disk.path", path = report.root.display()));
+                if projects.is_empty() {
+                    println!("\n{}", t!("disk.dev_none"));
+                }
+                for project in &projects {
+                    let name = match &project.project {
+                        Some(project) => project.display().to_string(),
+                        None => t!("disk.dev_shared_caches").to_string(),
+                    };
+                    println!(
+                        "\n{} - {}",
+                        format_size(project.total_size, DECIMAL).bold(),
+                        name.bold()
+                    );
+                    for artifact in &project.artifacts {
+                        let marker = if artifact.protection.is_protected() {
+                            format!(" {}", t!("common.protected")).yellow().to_string()
+                        } else {
+                            String::new()
+                        };
+                        println!(
+                            "   {:>10}  {:<22} {}{}",
+                            format_size(artifact.size, DECIMAL),
+                            dev_artifact_label(artifact.kind),
+                            artifact.path.display(),
+                            marker
+                        );
+                    }
+                }
+                if report.projects.len() > projects.len() {
+                    let more = report.projects.len() - projects.len();
+                    println!("\n{}", t!("disk.dev_more", count = more).dimmed());
+                }
+                if report.total_size > 0 {
+                    let size = format_size(report.total_size, DECIMAL);
+                    println!("\n{}", t!("disk.dev_reclaimable", size = size).bold());
+                    println!("{}", t!("disk.dev_hint").dimmed());
+                }
+                print_skipped(&report.skipped);
+            }
+        }
+        DiskCommand::Stale {
+            path,
+            days,
+            min_size,
+            top,
+            walk,
+            json: cmd_json,
+        } => {
+            let options = walk.scan_options();
+            let output_json = json || cmd_json;
+            let file_path = FilePath::new(path.to_string_lossy().to_string());
+            let min_bytes = parse_size(&min_size)?;
+            let spinner = (!output_json).then(|| create_spinner(t!("disk.stale_searching")));
+            breadcrumb(
+                "scan",
+                "Stale file search started",
+                &[("path", breadcrumb_path(&path))],
+            );
+            let mut report = DiskAnalyzer::new()
+                .find_stale_files(&file_path, days, min_bytes, &options, cancel)
+                .await
+                .context("Failed to search for stale files")?;
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+            let found = report.files.len();
+            report.files.truncate(top);
+
+            if output_json {
+                let mut json_output = serde_json::to_value(&report)?;
+                json_output["status"] = json!("ok");
+                json_output["path"] = json!(file_path.as_str());
+                json_output["older_than_days"] = json!(days);
+                json_output["min_size"] = json!(min_bytes);
+                json_output["files_found"] = json!(found);
+                print_json(&json_output)?;
+            } else {
+                println!("{}", t!("disk.stale_title").bold().bright_cyan());
+                if report.interrupted {
+                    println!("{}", t!("common.interrupted").yellow());
+                }
+                println!("{}", t!("disk.path", path = file_path.as_str()));
+                println!(
+                    "{}",
+                    t!(
+                        "disk.stale_summary",
+                        count = found,
+                        size = format_size(report.total_size, DECIMAL),
+                        days = days,
+                        min_size = min_size
+                    )
+                );
+                if !report.files.is_empty() {
+                    println!();
+                }
+                for (i, file) in report.files.iter().enumerate() {
+                    let marker = if file.protection.is_protected() {
+                        format!(" {}", t!("common.protected")).yellow().to_string()
+                    } else {
+                        String::new()
+                    };
+                    let last_used = file.last_used.with_timezone(&chrono::Local);
+                    println!(
+                        "{:3}. {} - {} {}{}",
+                        i + 1,
+                        format_size(file.size, DECIMAL).bold(),
+                        file.path.display(),
+                        t!(
+                            "disk.stale_last_used",
+                            date = last_used.format("%Y-%m-%d").to_string()
+                        )
+                        .dimmed(),
+                        marker
+                    );
+                }
+                print_skipped(&report.skipped);
+            }
+        }
+        DiskCommand::ScanVolumes {
+            volumes,
+            threads,
+            top,
+            json: cmd_json,
+        } => {
+            let output_json = json || cmd_json;
+            let roots = if volumes.is_empty() {
+                mounted_volumes()
+            } else {
+                volumes
+            };
+
+            // One spinner per volume; hidden in JSON mode
+            let multi = MultiProgress::new();
+            let spinners: HashMap<PathBuf, ProgressBar> = roots
+                .iter()
+                .map(|root| {
+                    let spinner = if output_json {
+                        ProgressBar::hidden()
+                    } else {
+                        let spinner = multi.add(create_spinner(&root.display().to_string()));
+                        spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+                        spinner
+                    };
+                    (root.clone(), spinner)
+                })
+                .collect();
+
+            breadcrumb(
+                "scan",
+                "Multi-volume scan started",
+                &[("volumes", roots.len().into()), ("threads", threads.into())],
+            );
+            let report = VolumeScanCoordinator::new()
+                .with_options(ScanOptions::new().with_threads(Some(threads)))
+                .with_top_files(top)
+                .scan(&roots, cancel, |progress: &VolumeProgress| {
+                    if let Some(spinner) = spinners.get(&progress.root) {
+                        let message = format!(
+                            "{} - {} files, {}",
+                            progress.root.display(),
+                            progress.files_scanned,
+                            format_size(progress.bytes_scanned, DECIMAL)
+                        );
+                        if progress.finished {
+                            spinner.finish_with_message(message);
+                        } else {
+                            spinner.set_message(message);
+                        }
+                    }
+                })
+                .await;
+            breadcrumb(
+                "scan",
+                "Multi-volume scan finished",
+                &[
+                    ("files", report.file_count.into()),
+                    ("bytes", report.total_size.into()),
+                    ("interrupted", report.interrupted.into()),
+                ],
+            );
+
+            if output_json {
+                let json_output = json!({
+                    "status": "ok",
+                    "total_size": report.total_size,
+                    "total_files": report.file_count,
+                    "volumes": report.volumes.iter().map(|v| json!({
+                        "root": v.root,
+                        "total_size": v.total_size,
+                        "file_count": v.file_count,
+                        "skipped": v.skipped,
+                        "error": v.error
+                    })).collect::<Vec<_>>(),
+                    "files": report.largest_files.iter().map(|f| json!({
+                        "path": f.path,
+                        "size": f.size,
+                        "protection": f.protection
+                    })).collect::<Vec<_>>(),
+                    "interrupted": report.interrupted
+                });
+                print_json(&json_output)?;
+            } else {
+                println!();
+                println!("{}", t!("disk.multi_volume_title").bold().bright_cyan());
+                if report.interrupted {
+                    println!("{}", t!("common.interrupted").yellow());
+                }
+                println!(
+                    "{}",
+                    t!("disk.total", size = format_size(report.total_size, DECIMAL))
+                );
+                println!("{}", t!("disk.total_files", count = report.file_count));
+                println!();
+                for volume in &report.volumes {
+                    match volume.error {
+                        Some(ref error) => println!(
+                            "  {} - {}",
+                            volume.root.display(),
+                            format!("error: {}", error).red()
+                        ),
+                        None => println!(
+                            "  {} - {} ({} files)",
+                            volume.root.display(),
+                            format_size(volume.total_size, DECIMAL).bold(),
+                            volume.file_count
+                        ),
+                    }
+                }
+                println!("\n{}\n", t!("disk.top_files", count = top));
+                for (i, file) in report.largest_files.iter().enumerate() {
+                    println!(
+                        "{:3}. {} - {}",
+                        i + 1,
+                        format_size(file.size, DECIMAL).bold(),
+                        file.path
+                    );
+                }
+                let skipped: Vec<_> = report.volumes.into_iter().flat_map(|v| v.skipped).collect();
+                print_skipped(&skipped);
+            }
+        }
+        DiskCommand::BrokenLinks {
+            path,
+            aliases,
+            remove,
+            json: cmd_json,
+        } => {
+            let output_json = json || cmd_json;
+            let spinner = (!output_json).then(|| create_spinner(t!("disk.links_searching")));
+            breadcrumb(
+                "scan",
+                "Broken link scan started",
+                &[("path", breadcrumb_path(&path))],
+            );
+            let report = BrokenLinkFinder::new()
+                .with_aliases(aliases)
+                .find(&path, &ScanOptions::default(), cancel)
+                .await
+                .context("Failed to search for broken links")?;
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+
+            let candidates = report
+                .links
+                .iter()
+                .filter(|link| !link.protection.is_protected())
+                .map(|link| link.path.as_path());
+            let would_remove = (remove && dry_run).then(|| permitted(candidates.clone()).len());
+            let removal = if remove && !dry_run && !report.links.is_empty() {
+                Some(archive_to_recovery(
+                    candidates,
+                    "broken-link",
+                    "disk broken-links",
+                )?)
+            } else {
+                None
+            };
+
+            if output_json {
+                let mut json_output = serde_json::to_value(&report)?;
+                json_output["status"] = json!("ok");
+                json_output["dry_run"] = json!(dry_run);
+                if let Some((ref recovery_id, removed)) = removal {
+                    json_output["removed"] = json!(removed);
+                    json_output["recovery_id"] = json!(recovery_id);
+                }
+                if let Some(removed) = would_remove {
+                    json_output["removed"] = json!(removed);
+                }
+                print_json(&json_output)?;
+            } else {
+                println!("{}", t!("disk.links_title").bold().bright_cyan());
+                if would_remove.is_some() {
+                    println!("{}", t!("common.dry_run").yellow());
+                }
+                if report.interrupted {
+                    println!("{}", t!("common.interrupted").yellow());
+                }
+                println!("{}", t!("disk.path", path = report.root.display()));
+                println!("{}\n", t!("disk.links_count", count = report.links.len()));
+                for link in &report.links {
+                    let marker = if link.protection.is_protected() {
+                        format!(" {}", t!("common.protected")).yellow().to_string()
+                    } else {
+                        String::new()
+                    };
+                    println!(
+                        "  {} -> {}{}",
+                        link.path.display(),
+                        link.target.display().to_string().red(),
+                        marker
+                    );
+                }
+                match removal {
+                    Some((recovery_id, removed)) => {
+                        println!(
+                            "\n{}",
+                            t!("disk.links_moved", count = removed, id = recovery_id)
+                        );
+                    }
+                    None if would_remove.is_some() => println!(
+                        "\n{}",
+                        t!("disk.would_archive", count = would_remove.unwrap_or(0))
+                    ),
+                    None if !report.links.is_empty() => {
+                        println!("\n{}", t!("disk.links_hint").dimmed())
+                    }
+                    None => {}
+                }
+                print_skipped(&report.skipped);
+            }
+        }
+        DiskCommand::EmptyDirs {
+            path,
+            min_depth,
+            prune,
+            json: cmd_json,
+        } => {
+            let output_json = json || cmd_json;
+            let spinner = (!output_json).then(|| create_spinner(t!("disk.empty_searching")));
+            breadcrumb(
+                "scan",
+                "Empty directory scan started",
+                &[("path", breadcrumb_path(&path))],
+            );
+            let mut report = EmptyDirFinder::new()
+                .find(&path, cancel)
+                .await
+                .context("Failed to search for empty directories")?;
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+            report.trees.retain(|tree| tree.depth >= min_depth);
+
+            let would_prune = (prune && dry_run && !report.interrupted).then(|| {
+                permitted(
+                    report
+                        .trees
+                        .iter()
+                        .filter(|tree| !tree.protection.is_protected())
+                        .map(|tree| tree.path.as_path()),
+                )
+                .len()
+            });
+            let mut removed = Vec::new();
+            if prune && !dry_run && !report.interrupted {
+                let cleaner = SystemCleaner::new();
+                for tree in &report.trees {
+                    if cancel.is_cancelled() {
+                        break;
+                    }
+                    let result = cleaner
+                        .prune_empty_tree(&tree.path)
+                        .context("Failed to prune empty directories")?;
+                    removed.extend(result.dirs_removed);
+                    audit::record_refused(&result.skipped);
+                    report.skipped.extend(result.skipped);
+                }
+                if !removed.is_empty() {
+                    audit::record(
+                        AuditEntry::new(AuditAction::Delete, audit::command_line())
+                            .with_paths(report.trees.iter().map(|tree| tree.path.clone()).collect())
+                            .with_items(removed.len()),
+                    );
+                }
+            }
+
+            if output_json {
+                let mut json_output = serde_json::to_value(&report)?;
+                json_output["status"] = json!("ok");
+                json_output["dry_run"] = json!(dry_run);
+                if prune && !dry_run {
+                    json_output["dirs_removed"] = json!(removed.len());
+                }
+                if let Some(trees) = would_prune {
+                    json_output["trees_removed"] = json!(trees);
+                }
+                print_json(&json_output)?;
+            } else {
+                println!("{}", t!("disk.empty_title").bold().bright_cyan());
+                if prune && dry_run {
+                    println!("{}", t!("common.dry_run").yellow());
+                }
+                if report.interrupted {
+                    println!("{}", t!("disk.empty_interrupted").yellow());
+                }
+                println!("{}", t!("disk.path", path = report.root.display()));
+                println!("{}\n", t!("disk.empty_count", count = report.trees.len()));
+                for tree in &report.trees {
+                    let marker = if tree.protection.is_protected() {
+                        format!(" {}", t!("common.protected")).yellow().to_string()
+                    } else {
+                        String::new()
+                    };
+                    println!(
+                        "  {} {}{}",
+                        tree.path.display(),
+                        t!("disk.empty_tree", dirs = tree.dirs, depth = tree.depth),
+                        marker
+                    );
+                }
+                if let Some(trees) = would_prune {
+                    println!("\n{}", t!("disk.would_prune", count = trees));
+                } else if prune && !dry_run {
+                    println!("\n{}", t!("disk.empty_removed", count = removed.len()));
+                } else if !report.trees.is_empty() {
+                    println!("\n{}", t!("disk.empty_hint").dimmed());
+                }
+                print_skipped(&report.skipped);
+            }
+        }
+        DiskCommand::Screenshots {
+            folders,
+            older_than,
+            move_to,
+            delete,
+            json: cmd_json,
+        } => {
+            let output_json = json || cmd_json;
+            let folders = if folders.is_empty() {
+                ScreenshotFinder::default_folders()
+            } else {
+                folders
+            };
+            let cutoff = chrono::Utc::now() - chrono::Duration::days(older_than);
+            let report = ScreenshotFinder::new(folders).find(cutoff, cancel).await;
+            let candidates: Vec<&Path> = report
+                .screenshots
+                .iter()
+                .filter(|screenshot| !screenshot.protection.is_protected())
+                .map(|screenshot| screenshot.path.as_path())
+                .collect();
+
+            let would_change = (dry_run && (move_to.is_some() || delete))
+                .then(|| permitted(candidates.iter().copied()).len());
+            let mut moved = 0;
+            let mut recovery = None;
+            let move_to_dir = move_to.as_ref().filter(|_| !dry_run);
+            if let Some(dir) = move_to_dir {
+                std::fs::create_dir_all(dir)
+                    .with_context(|| format!("Failed to create {}", dir.display()))?;
+                for path in audit::allowed_by_policy(candidates.iter().copied()) {
+                    match move_into(path, dir) {
+                        Ok(()) => moved += 1,
+                        Err(e) => {
+                            tracing::warn!(error = %e, path = %path.display(), "Failed to move screenshot")
+                        }
+                    }
+                }
+            } else if delete && !dry_run && !candidates.is_empty() {
+                recovery = Some(archive_to_recovery(
+                    candidates.iter().copied(),
+                    "screenshot",
+                    "disk screenshots",
+                )?);
+            }
+
+            if output_json {
+                let mut json_output = serde_json::to_value(&report)?;
+                json_output["status"] = json!("ok");
+                json_output["older_than_days"] = json!(older_than);
+                json_output["dry_run"] = json!(dry_run);
+                if move_to.is_some() {
+                    json_output["moved"] = json!(would_change.unwrap_or(moved));
+                } else if let Some(removed) = would_change {
+                    json_output["removed"] = json!(removed);
+                }
+                if let Some((ref recovery_id, removed)) = recovery {
+                    json_output["removed"] = json!(removed);
+                    json_output["recovery_id"] = json!(recovery_id);
+                }
+                print_json(&json_output)?;
+            } else {
+                println!("{}", t!("disk.screenshots_title").bold().bright_cyan());
+                if would_change.is_some() {
+                    println!("{}", t!("common.dry_run").yellow());
+                }
+                if report.interrupted {
+                    println!("{}", t!("common.interrupted").yellow());
+                }
+                for folder in &report.folders {
+                    println!("{}", t!("disk.screenshots_folder", path = folder.display()));
+                }
+                println!(
+                    "{}\n",
+                    t!(
+                        "disk.screenshots_summary",
+                        days = older_than,
+                        count = report.screenshots.len(),
+                        size = format_size(report.total_size, DECIMAL).bold()
+                    )
+                );
+                for screenshot in &report.screenshots {
+                    println!(
+                        "  {}  {:>10}  {}",
+                        screenshot.taken.format("%Y-%m-%d"),
+                        format_size(screenshot.size, DECIMAL),
+                        screenshot.path.display()
+                    );
+                }
+                if let (Some(count), Some(ref dir)) = (would_change, &move_to) {
+                    println!(
+                        "\n{}",
+                        t!("disk.would_move", count = count, dir = dir.display())
+                    );
+                } else if let Some(count) = would_change {
+                    println!("\n{}", t!("disk.would_archive", count = count));
+                } else if let Some(ref dir) = move_to {
+                    println!(
+                        "\n{}",
+                        t!("disk.screenshots_moved", count = moved, dir = dir.display())
+                    );
+                } else if let Some((recovery_id, removed)) = recovery {
+                    println!(
+                        "\n{}",
+                        t!(
+                            "disk.screenshots_deleted",
+                            count = removed,
+                            id = recovery_id
+                        )
+                    );
+                } else if !report.screenshots.is_empty() {
+                    println!("\n{}", t!("disk.screenshots_hint").dimmed());
+                }
+                print_skipped(&report.skipped);
+            }
+        }
+        DiskCommand::SnapshotDiff {
+            path,
+            snapshot,
+            depth,
+            top,
+            json: cmd_json,
+        } => {
+            let output_json = json || cmd_json;
+            let path = match path {
+                Some(path) => path,
+                None => dirs::home_dir().context("Could not determine home directory")?,
+            };
+            let path = std::fs::canonicalize(&path)
+                .with_context(|| format!("Invalid path: {}", path.display()))?;
+            let snapshot = match snapshot {
+                Some(snapshot) => snapshot,
+                None => TimeMachineManager::list_snapshots()
+                    .context("Failed to list local snapshots")?
+                    .into_iter()
+                    .map(|snapshot| snapshot.id)
+                    .max()
+                    .context("No local snapshots found")?,
+            };
+
+            let (volume, relative) = snapshot_volume(&path);
+            let spinner =
+                (!output_json).then(|| create_spinner(&format!("Comparing with {}...", snapshot)));
+            breadcrumb(
+                "scan",
+                "Snapshot diff started",
+                &[("path", breadcrumb_path(&path))],
+            );
+            let mount =
+                SnapshotMount::mount(&volume, &snapshot).context("Failed to mount snapshot")?;
+            let report = SnapshotDiff::new(depth)
+                .compare(&path, &mount.path().join(&relative), cancel)
+                .await
+                .context("Failed to compare with snapshot")?;
+            drop(mount);
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+            let grown: Vec<_> = report.grown.iter().take(top).collect();
+
+            if output_json {
+                let json_output = json!({
+                    "status": "ok",
+                    "path": report.root,
+                    "snapshot": snapshot,
+                    "size_before": report.size_before,
+                    "size_after": report.size_after,
+                    "net_change": report.net_change(),
+                    "grown": grown.iter().map(|change| json!({
+                        "path": change.path,
+                        "before": change.before,
+                        "after": change.after,
+                        "growth": change.growth()
+                    })).collect::<Vec<_>>(),
+                    "skipped": report.skipped,
+                    "interrupted": report.interrupted
+                });
+                print_json(&json_output)?;
+            } else {
+                println!("{}", t!("disk.snapshot_diff_title").bold().bright_cyan());
+                if report.interrupted {
+                    println!("{}", t!("common.interrupted").yellow());
+                }
+                println!("{}", t!("disk.path", path = report.root.display()));
+                println!("{}", t!("disk.snapshot_diff_snapshot", id = snapshot));
+                let net = report.net_change();
+                let sign = if net < 0 { "-" } else { "+" };
+                println!(
+                    "{}",
+                    t!(
+                        "disk.diff_size",
+                        before = format_size(report.size_before, DECIMAL),
+                        after = format_size(report.size_after, DECIMAL),
+                        change = format!("{}{}", sign, format_size(net.unsigned_abs(), DECIMAL))
+                    )
+                );
+
+                if grown.is_empty() {
+                    println!("\n{}", t!("disk.snapshot_diff_none"));
+                } else {
+                    println!("\n{}\n", t!("disk.snapshot_diff_grown"));
+                    for (i, change) in grown.iter().enumerate() {
+                        let growth =
+                            format!("+{}", format_size(change.growth().unsigned_abs(), DECIMAL));
+                        let note = if change.before == 0 {
+                            format!(" {}", t!("disk.change_new")).green().to_string()
+                        } else {
+                            String::new()
+                        };
+                        println!(
+                            "{:3}. {} - {}{}",
+                            i + 1,
+                            growth.bold(),
+                            change.path.display(),
+                            note
+                        );
+                    }
+                }
+                print_skipped(&report.skipped);
+            }
+        }
+        DiskCommand::Watch {
+            path,
+            threshold,
+            depth,
+            json: cmd_json,
+        } => {
+            let threshold = parse_size(&threshold)?;
+            breadcrumb("scan", "Watch started", &[("path", breadcrumb_path(&path))]);
+            watch_growth(path, depth, threshold, json || cmd_json, cancel).await?;
+        }
+        DiskCommand::Snapshots { command } => handle_snapshots(command, json, dry_run)?,
+        DiskCommand::Diff {
+            before,
+            after,
+            depth,
+            min_size,
+            top,
+            json: cmd_json,
+        } => {
+            let output_json = json || cmd_json;
+            let min_size = parse_size(&min_size)?;
+            let load = |file: &Path| {
+                SavedScan::load(file)
+                    .with_context(|| format!("Failed to read saved scan {}", file.display()))
+            };
+            let (before, after) = (
+                load(&saved_scan_file(&before)?)?,
+                load(&saved_scan_file(&after)?)?,
+            );
+            let report = before.diff(&after, depth, min_size);
+            let roots_differ = before.root != after.root;
+            let grown: Vec<_> = report.grown.iter().take(top).collect();
+            let shrunk: Vec<_> = report.shrunk.iter().take(top).collect();
+            let new_files: Vec<_> = report.new_files.iter().take(top).collect();
+            let deleted_files: Vec<_> = report.deleted_files.iter().take(top).collect();
+            let change_json = |change: &&PathGrowth| {
+                json!({
+                    "path": change.path,
+                    "before": change.before,
+                    "after": change.after,
+                    "growth": change.growth()
+                })
+            };
+
+            let file_json = |file: &&SavedFile| {
+                json!({
+                    "path": report.root.join(&file.path),
+                    "size": file.size
+                })
+            };
+
+            if output_json {
+                let json_output = json!({
+                    "status": "ok",
+                    "path": report.root,
+                    "before_path": before.root,
+                    "scanned_before": report.scanned_before,
+                    "scanned_after": report.scanned_after,
+                    "size_before": report.size_before,
+                    "size_after": report.size_after,
+                    "net_change": report.net_change(),
+                    "grown": grown.iter().map(change_json).collect::<Vec<_>>(),
+                    "shrunk": shrunk.iter().map(change_json).collect::<Vec<_>>(),
+                    "new_files": new_files.iter().map(file_json).collect::<Vec<_>>(),
+                    "deleted_files": deleted_files.iter().map(file_json).collect::<Vec<_>>()
+                });
+                print_json(&json_output)?;
+            } else {
+                println!("{}", t!("disk.diff_title").bold().bright_cyan());
+                println!("{}", t!("disk.path", path = report.root.display()));
+                if roots_differ {
+                    let path = before.root.display().to_string();
+                    println!("{}", t!("disk.diff_roots_differ", path = path).yellow());
+                }
+                let date = |at: DateTime<Utc>| {
+                    at.with_timezone(&chrono::Local)
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string()
+                };
+                println!(
+                    "{}",
+                    t!(
+                        "disk.diff_scans",
+                        before = date(report.scanned_before),
+                        after = date(report.scanned_after)
+                    )
+                );
+                let net = report.net_change();
+                let sign = if net < 0 { "-" } else { "+" };
+                println!(
+                    "{}",
+                    t!(
+                        "disk.diff_size",
+                        before = format_size(report.size_before, DECIMAL),
+                        after = format_size(report.size_after, DECIMAL),
+                        change = format!("{}{}", sign, format_size(net.unsigned_abs(), DECIMAL))
+                    )
+                );
+
+                let print_changes = |title: &str, changes: &[&PathGrowth]| {
+                    if changes.is_empty() {
+                        return;
+                    }
+                    println!("\n{}\n", title.bold());
+                    for (i, change) in changes.iter().enumerate() {
+                        let growth = change.growth();
+                        let sign = if growth < 0 { "-" } else { "+" };
+                        let amount =
+                            format!("{}{}", sign, format_size(growth.unsigned_abs(), DECIMAL));
+                        let amount = if growth < 0 {
+                            amount.green()
+                        } else {
+                            amount.red()
+                        };
+                        println!("{:3}. {} - {}", i + 1, amount.bold(), change.path.display());
+                    }
+                };
+                let print_files = |title: &str, files: &[&SavedFile]| {
+                    if files.is_empty() {
+                        return;
+                    }
+                    println!("\n{}\n", title.bold());
+                    for (i, file) in files.iter().enumerate() {
+                        println!(
+                            "{:3}. {} - {}",
+                            i + 1,
+                            format_size(file.size, DECIMAL).bold(),
+                            report.root.join(&file.path).display()
+                        );
+                    }
+                };
+                print_changes(t!("disk.diff_grown"), &grown);
+                print_changes(t!("disk.diff_shrunk"), &shrunk);
+                print_files(t!("disk.diff_new_files"), &new_files);
+                print_files(t!("disk.diff_deleted_files"), &deleted_files);
+                if grown.is_empty()
+                    && shrunk.is_empty()
+                    && new_files.is_empty()
+                    && deleted_files.is_empty()
+                {
+                    println!("\n{}", t!("disk.diff_unchanged"));
+                }
+            }
+        }
+        DiskCommand::Thinning {
+            app_dirs,
+            app,
+            thin,
+            json: cmd_json,
+        } => {
+            let output_json = json || cmd_json;
+            let app_dirs = if app_dirs.is_empty() {
+                let home = dirs::home_dir().context("Could not determine home directory")?;
+                vec![PathBuf::from("/Applications"), home.join("Applications")]
+            } else {
+                app_dirs
+            };
+
+            let spinner = (!output_json).then(|| create_spinner(t!("disk.thin_reading")));
+            let mut report = ThinningAnalyzer::new().analyze(&app_dirs, cancel).await;
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+            if let Some(ref filter) = app {
+                let filter = filter.to_lowercase();
+                report
+                    .apps
+                    .retain(|candidate| candidate.name.to_lowercase().contains(&filter));
+                report.total_reclaimable = report.apps.iter().map(|a| a.reclaimable).sum();
+            }
+
+            let would_thin = (thin && dry_run && !report.interrupted).then(|| {
+                let apps: Vec<_> = report
+                    .apps
+                    .iter()
+                    .filter(|app| !app.protection.is_protected())
+                    .collect();
+                let binaries = permitted(
+                    apps.iter()
+                        .flat_map(|app| &app.binaries)
+                        .map(|binary| binary.path.as_path()),
+                );
+                let bytes: u64 = apps
+                    .iter()
+                    .flat_map(|app| &app.binaries)
+                    .filter(|binary| binaries.contains(&binary.path.as_path()))
+                    .map(|binary| binary.reclaimable)
+                    .sum();
+                (binaries.len(), bytes)
+            });
+            let thinned = if thin && !dry_run && !report.interrupted && !report.apps.is_empty() {
+                Some(thin_apps(&report, cancel)?)
+            } else {
+                None
+            };
+
+            if output_json {
+                let mut json_output = serde_json::to_value(&report)?;
+                json_output["status"] = json!("ok");
+                json_output["dry_run"] = json!(dry_run);
+                if let Some((ref recovery_id, binaries, saved)) = thinned {
+                    json_output["thinned_binaries"] = json!(binaries);
+                    json_output["bytes_saved"] = json!(saved);
+                    json_output["recovery_id"] = json!(recovery_id);
+                }
+                if let Some((binaries, saved)) = would_thin {
+                    json_output["thinned_binaries"] = json!(binaries);
+                    json_output["bytes_saved"] = json!(saved);
+                }
+                print_json(&json_output)?;
+            } else {
+                println!("{}", t!("disk.thin_title").bold().bright_cyan());
+                if would_thin.is_some() {
+                    println!("{}", t!("common.dry_run").yellow());
+                }
+                if report.interrupted {
+                    println!("{}", t!("common.interrupted").yellow());
+                }
+                println!("{}", t!("disk.thin_native", arch = report.native_arch));
+                println!(
+                    "{}\n",
+                    t!(
+                        "disk.thin_reclaimable",
+                        size = format_size(report.total_reclaimable, DECIMAL).bold(),
+                        count = report.apps.len()
+                    )
+                );
+                for app in &report.apps {
+                    let marker = if app.protection.is_protected() {
+                        format!(" {}", t!("common.protected")).yellow().to_string()
+                    } else {
+                        String::new()
+                    };
+                    println!(
+                        "  {:>10}  {} {}{}",
+                        format_size(app.reclaimable, DECIMAL),
+                        app.name,
+                        t!("disk.thin_binaries", count = app.binaries.len()),
+                        marker
+                    );
+                }
+                if let Some((binaries, saved)) = would_thin {
+                    println!(
+                        "\n{}",
+                        t!(
+                            "disk.would_thin",
+                            count = binaries,
+                            size = format_size(saved, DECIMAL)
+                        )
+                    );
+                } else if let Some((recovery_id, binaries, saved)) = thinned {
+                    println!(
+                        "\n{}",
+                        t!(
+                            "disk.thinned",
+                            count = binaries,
+                            size = format_size(saved, DECIMAL),
+                            id = recovery_id
+                        )
+                    );
+                } else if !report.apps.is_empty() {
+                    println!("\n{}", t!("disk.thin_hint").dimmed());
+                }
+                print_skipped(&report.skipped);
+            }
+        }
+        DiskCommand::Volumes {
+            all,
+            scan,
+            threads,
+            json: cmd_json,
+        } => {
+            let output_json = json || cmd_json;
+            let volumes: Vec<MountedVolume> = dragonfly_disk::mounted_volumes()
+                .context("Failed to list mounted volumes")?
+                .into_iter()
+                .filter(|volume| all || volume.browsable)
+                .collect();
+
+            let scanned = if scan {
+                let roots: Vec<PathBuf> = volumes.iter().map(|v| v.mount_point.clone()).collect();
+                let spinner = (!output_json).then(|| {
+                    let spinner = create_spinner(t!("disk.volumes_scanning"));
+                    spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+                    spinner
+                });
+                breadcrumb(
+                    "scan",
+                    "Multi-volume scan started",
+                    &[("volumes", roots.len().into()), ("threads", threads.into())],
+                );
+                let report = VolumeScanCoordinator::new()
+                    .with_options(ScanOptions::new().with_threads(Some(threads)))
+                    .with_top_files(0)
+                    .scan(&roots, cancel, |_: &VolumeProgress| {})
+                    .await;
+                if let Some(spinner) = spinner {
+                    spinner.finish_and_clear();
+                }
+                Some(report)
+            } else {
+                None
+            };
+            let scan_of = |volume: &MountedVolume| {
+                scanned
+                    .as_ref()
+                    .and_then(|report| report.volumes.iter().find(|v| v.root == volume.mount_point))
+            };
+            let interrupted = scanned.as_ref().is_some_and(|report| report.interrupted);
+
+            if output_json {
+                let json_output = json!({
+                    "status": "ok",
+                    "volumes": volumes.iter().map(|volume| {
+                        let mut entry = json!(volume);
+                        entry["scan"] = json!(scan_of(volume).map(|v| json!({
+                            "total_size": v.total_size,
+                            "file_count": v.file_count,
+                            "skipped": v.skipped,
+                            "error": v.error
+                        })));
+                        entry
+                    }).collect::<Vec<_>>(),
+                    "interrupted": interrupted
+                });
+                print_json(&json_output)?;
+            } else {
+                println!("{}", t!("disk.volumes_title").bold().bright_cyan());
+                if interrupted {
+                    println!("{}", t!("common.interrupted").yellow());
+                }
+                for volume in &volumes {
+                    let read_only = if volume.read_only {
+                        format!(", {}", t!("disk.volumes_read_only"))
+                    } else {
+                        String::new()
+                    };
+                    println!(
+                        "\n{} ({}{}) - {}",
+                        volume.mount_point.display().to_string().bold(),
+                        volume.filesystem,
+                        read_only,
+                        volume.source.dimmed()
+                    );
+                    let percent = if volume.capacity == 0 {
+                        0.0
+                    } else {
+                        volume.used as f64 / volume.capacity as f64 * 100.0
+                    };
+                    println!(
+                        "   {}",
+                        t!(
+                            "disk.volumes_usage",
+                            used = format_size(volume.used, DECIMAL),
+                            capacity = format_size(volume.capacity, DECIMAL),
+                            percent = format!("{:.0}", percent),
+                            available = format_size(volume.space.available, DECIMAL)
+                        )
+                    );
+                    if volume.space.purgeable > 0 {
+                        let purgeable = format_size(volume.space.purgeable, DECIMAL);
+                        println!("   {}", t!("disk.volumes_purgeable", size = purgeable));
+                    }
+                    match scan_of(volume) {
+                        Some(VolumeReport {
+                            error: Some(error), ..
+                        }) => println!("   {}", format!("error: {}", error).red()),
+                        Some(report) => println!(
+                            "   {}",
+                            t!(
+                                "disk.volumes_scanned",
+                                size = format_size(report.total_size, DECIMAL),
+                                count = report.file_count
+                            )
+                        ),
+                        None => {}
+                    }
+                }
+                if !all {
+                    println!("\n{}", t!("disk.volumes_hidden_hint").dimmed());
+                }
+            }
+        }
+        DiskCommand::Mail {
+            path,
+            top,
+            json: cmd_json,
+        } => {
+            let output_json = json || cmd_json;
+            let home = dirs::home_dir().context("Could not determine home directory")?;
+            let mail_root = path.unwrap_or_else(|| home.join(DEFAULT_MAIL_DIR));
+            let downloads = home.join(DEFAULT_MAIL_DOWNLOADS);
+
+            breadcrumb(
+                "scan",
+                "Mail scan started",
+                &[("path", breadcrumb_path(&mail_root))],
+            );
+            let report = MailAnalyzer::new()
+                .analyze(&mail_root, Some(&downloads), cancel)
+                .await
+                .context("Failed to analyze Mail storage")?;
+
+            if output_json {
+                let mut json_output = serde_json::to_value(&report)?;
+                json_output["status"] = json!("ok");
+                json_output["attachment_share"] = json!(report.attachment_share());
+                print_json(&json_output)?;
+            } else {
+                println!("{}", t!("disk.mail_title").bold().bright_cyan());
+                if report.interrupted {
+                    println!("{}", t!("common.interrupted").yellow());
+                }
+                println!("{}", t!("disk.path", path = report.root.display()));
+                println!(
+                    "{}",
+                    t!(
+                        "disk.total",
+                        size = format_size(report.total_size, DECIMAL).bold()
+                    )
+                );
+                println!(
+                    "{}",
+                    t!(
+                        "disk.mail_attachments",
+                        size = format_size(report.attachments_size, DECIMAL),
+                        percent = format!("{:.0}", report.attachment_share() * 100.0)
+                    )
+                );
+                println!(
+                    "{}",
+                    t!(
+                        "disk.mail_downloads",
+                        size = format_size(report.downloads_size, DECIMAL)
+                    )
+                );
+
+                for account in &report.accounts {
+                    println!(
+                        "\n{} - {} {}",
+                        account.name.bold(),
+                        format_size(account.total_size, DECIMAL),
+                        t!(
+                            "disk.mail_account_attachments",
+                            size = format_size(account.attachments_size, DECIMAL)
+                        )
+                    );
+                    for mailbox in account.mailboxes.iter().take(top) {
+                        println!(
+                            "  {:<30} {:>10}  {}",
+                            mailbox.name,
+                            format_size(mailbox.total_size, DECIMAL),
+                            t!("disk.mail_messages", count = mailbox.message_count)
+                        );
+                    }
+                    if account.mailboxes.len() > top {
+                        println!(
+                            "  {}",
+                            t!("disk.mail_more", count = account.mailboxes.len() - top)
+                        );
+                    }
+                }
+
+                println!();
+                if report.downloads_size > 0 {
+                    println!("{}", t!("disk.mail_downloads_hint").dimmed());
+                }
+                if report.attachment_share() > 0.5 {
+                    println!("{}", t!("disk.mail_attachments_hint").dimmed());
+                }
+                print_skipped(&report.skipped);
+            }
+        }
+        DiskCommand::Photos {
+            library,
+            json: cmd_json,
+        } => {
+            let output_json = json || cmd_json;
+            let library = match library {
+                Some(library) => library,
+                None => dirs::home_dir()
+                    .context("Could not determine home directory")?
+                    .join(DEFAULT_PHOTOS_LIBRARY),
+            };
+
+            breadcrumb(
+                "scan",
+                "Photos scan started",
+                &[("path", breadcrumb_path(&library))],
+            );
+            let report = PhotosLibraryAnalyzer::new()
+                .analyze(&library, cancel)
+                .await
+                .context("Failed to analyze Photos library")?;
+
+            if output_json {
+                let mut json_output = serde_json::to_value(&report)?;
+                json_output["status"] = json!("ok");
+                print_json(&json_output)?;
+            } else {
+                println!("{}", t!("disk.photos_title").bold().bright_cyan());
+                if report.interrupted {
+                    println!("{}", t!("common.interrupted").yellow());
+                }
+                println!(
+                    "{}",
+                    t!("disk.photos_library", path = report.library.display())
+                );
+                println!(
+                    "{}",
+                    t!(
+                        "disk.total",
+                        size = format_size(report.total_size, DECIMAL).bold()
+                    )
+                );
+                println!();
+                for (label, size) in [
+                    (t!("disk.photos_originals"), report.originals_size),
+                    (t!("disk.photos_derivatives"), report.derivatives_size),
+                    (t!("disk.photos_caches"), report.caches_size),
+                    (t!("disk.photos_database"), report.database_size),
+                    (t!("disk.photos_other"), report.other_size),
+                ] {
+                    println!("  {:<22} {}", label, format_size(size, DECIMAL));
+                }
+                println!();
+                println!(
+                    "{}",
+                    t!(
+                        "disk.photos_items",
+                        count = report.item_count,
+                        local = report.local_originals
+                    )
+                );
+                let storage = match (report.icloud_photos, report.optimized_storage) {
+                    (false, _) => t!("disk.photos_local"),
+                    (true, false) => t!("disk.photos_icloud"),
+                    (true, true) => t!("disk.photos_icloud_optimized"),
+                };
+                println!("{}", t!("disk.photos_storage", storage = storage));
+                println!("{}", t!("disk.photos_hint").dimmed());
+                print_skipped(&report.skipped);
             }
         }
     }
     Ok(())
 }
+
+/// Paths a destructive command would modify, leaving out the ones the
+/// protection policy refuses
+fn permitted<'a>(paths: impl IntoIterator<Item = &'a Path>) -> Vec<&'a Path> {
+    paths
+        .into_iter()
+        .filter(|path| policy().check(&path.to_string_lossy()).is_none())
+        .collect()
+}
+
+/// Compress the archive of a recovery nothing more goes into, then save its
+/// manifest
+///
+/// A recovery that can't be compressed is kept as plain files; only failing
+/// to save the manifest is an error.
+pub(crate) fn finish_recovery(
+    manager: &RecoveryManager,
+    manifest: &mut RecoveryManifest,
+) -> std::io::Result<()> {
+    if let Err(e) = manager.compress(manifest) {
+        tracing::warn!(error = %e, id = %manifest.id, "Failed to compress recovery archive");
+    }
+    manager.save_manifest(manifest)
+}
+
+/// Move files or links into a new recovery archive and audit the removal
+///
+/// Callers filter out protected paths first; paths refused by the user's
+/// protection policy are left alone and audited as blocked. Returns the
+/// recovery ID and how many items were moved.
+pub(crate) fn archive_to_recovery<'a>(
+    paths: impl IntoIterator<Item = &'a Path>,
+    category: &str,
+    source: &str,
+) -> Result<(String, usize)> {
+    let manager = RecoveryManager::new(RecoveryManager::default_dir());
+    manager.initialize()?;
+    let mut manifest = manager.create_manifest(RECOVERY_RETENTION_DAYS);
+    for path in audit::allowed_by_policy(paths) {
+        if let Err(e) = manager.archive_item(&mut manifest, path, category, source) {
+            tracing::warn!(error = %e, path = %path.display(), "Failed to archive item");
+        }
+    }
+    finish_recovery(&manager, &mut manifest)?;
+
+    let removed = manifest.items.len();
+    audit::record(
+        AuditEntry::new(AuditAction::Delete, audit::command_line())
+            .with_paths(
+                manifest
+                    .items
+                    .iter()
+                    .map(|item| item.original_path.clone())
+                    .collect(),
+            )
+            .with_items(removed)
+            .with_bytes(manifest.total_size)
+            .with_recovery_id(manifest.id.clone()),
+    );
+    Ok((manifest.id, removed))
+}
+
+/// Thin the binaries of the unprotected apps in `report` to the native
+/// architecture, archiving the originals in one recovery
+///
+/// Returns the recovery id, the number of binaries thinned, and the bytes saved.
+fn thin_apps(report: &ThinningReport, cancel: &CancellationToken) -> Result<(String, usize, u64)> {
+    let manager = RecoveryManager::new(RecoveryManager::default_dir());
+    manager.initialize()?;
+    let mut manifest = manager.create_manifest(RECOVERY_RETENTION_DAYS);
+    let mut saved = 0;
+    let binaries = report
+        .apps
+        .iter()
+        .filter(|app| !app.protection.is_protected())
+        .flat_map(|app| &app.binaries)
+        .map(|binary| binary.path.as_path());
+    for binary in audit::allowed_by_policy(binaries) {
+        if cancel.is_cancelled() {
+            break;
+        }
+        match thin_binary(binary, &report.native_arch, &manager, &mut manifest) {
+            Ok(bytes) => saved += bytes,
+            Err(e) => {
+                tracing::warn!(error = %e, path = %binary.display(), "Failed to thin binary")
+            }
+        }
+    }
+    finish_recovery(&manager, &mut manifest)?;
+
+    let thinned = manifest.items.len();
+    audit::record(
+        AuditEntry::new(AuditAction::Delete, audit::command_line())
+            .with_paths(
+                manifest
+                    .items
+                    .iter()
+                    .map(|item| item.original_path.clone())
+                    .collect(),
+            )
+            .with_items(thinned)
+            .with_bytes(saved)
+            .with_recovery_id(manifest.id.clone()),
+    );
+    Ok((manifest.id, thinned, saved))
+}
+
+/// Move a file into `dir`, keeping its name
+///
+/// Refuses to overwrite a file of the same name already in `dir`.
+fn move_into(path: &Path, dir: &Path) -> std::io::Result<()> {
+    let name = path.file_name().ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "path has no file name")
+    })?;
+    let target = dir.join(name);
+    if target.exists() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            format!("{} already exists", target.display()),
+        ));
+    }
+    // Renaming fails across volumes; fall back to copying
+    if std::fs::rename(path, &target).is_err() {
+        std::fs::copy(path, &target)?;
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Answer from the size index if it covers `path`, with when it was last updated
+fn indexed_analysis(path: &Path, min_size: u64) -> Result<Option<(AnalysisResult, DateTime<Utc>)>> {
+    // The index stores canonical paths
+    let path =
+        std::fs::canonicalize(path).with_context(|| format!("Invalid path: {}", path.display()))?;
+    let index = SizeIndex::open(&SizeIndex::default_path()).context("Failed to open size index")?;
+    let Some(root) = index.covering_root(&path)? else {
+        return Ok(None);
+    };
+    Ok(index
+        .analysis(&path, min_size)?
+        .map(|result| (result, root.updated_at)))
+}
+
+/// The `count` largest directories below `roots`, largest first, with files
+/// sized by `size_of`
+fn top_directories(
+    roots: &[PathBuf],
+    files: &[FileEntity],
+    size_of: impl Fn(&FileEntity) -> u64,
+    count: usize,
+) -> Vec<DirectoryNode> {
+    let mut heaviest = Vec::new();
+    for root in roots {
+        // The size index keeps canonical paths
+        let canonical = std::fs::canonicalize(root).unwrap_or_else(|_| root.clone());
+        let base = if files.iter().any(|f| Path::new(&f.path).starts_with(root)) {
+            root
+        } else {
+            &canonical
+        };
+        let tree =
+            DirectoryNode::from_files(base, files.iter().map(|f| (f.path.as_str(), size_of(f))));
+        heaviest.extend(
+            tree.heaviest(count, None)
+                .into_iter()
+                .map(|(dir, _)| DirectoryNode {
+                    path: dir.path.clone(),
+                    size: dir.size,
+                    file_count: dir.file_count,
+                    children: Vec::new(),
+                }),
+        );
+    }
+    heaviest.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+    heaviest.truncate(count);
+    heaviest
+}
+
+/// Scan several roots at once, merging their files
+async fn analyze_roots(
+    paths: &[PathBuf],
+    options: &ScanOptions,
+    json: bool,
+    cancel: &CancellationToken,
+) -> Result<(AnalysisResult, Vec<RootTotals>)> {
+    let spinner = (!json).then(|| {
+        let spinner = create_spinner(t!("disk.scanning"));
+        spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+        spinner
+    });
+    let report = |update: &ProgressUpdate| {
+        if let Some(spinner) = &spinner {
+            spinner.set_message(scan_progress_message(update));
+        }
+    };
+    let paths: Vec<FilePath> = paths
+        .iter()
+        .map(|path| FilePath::new(path.to_string_lossy().to_string()))
+        .collect();
+    let analysis = DiskAnalyzer::new()
+        .analyze_roots(&paths, options, &report, cancel)
+        .await
+        .context("Failed to analyze directories")?;
+    if let Some(spinner) = spinner {
+        spinner.finish_and_clear();
+    }
+    Ok((analysis.result, analysis.roots))
+}
+
+/// Bring the size index up to date for `path`, re-reading only changed
+/// directories, and answer from it
+///
+/// If the refresh is cancelled the index keeps its previous contents, which
+/// are returned as an interrupted result.
+pub(crate) fn incremental_analysis(
+    path: &Path,
+    json: bool,
+    cancel: &CancellationToken,
+) -> Result<(AnalysisResult, Option<RefreshStats>)> {
+    let started = std::time::Instant::now();
+    // The index stores canonical paths
+    let path =
+        std::fs::canonicalize(path).with_context(|| format!("Invalid path: {}", path.display()))?;
+    let mut index =
+        SizeIndex::open(&SizeIndex::default_path()).context("Failed to open size index")?;
+    let spinner = (!json).then(|| create_spinner(t!("disk.incremental_refreshing")));
+    let refresh = index
+        .refresh(&path, &ScanOptions::default(), cancel)
+        .context("Failed to update size index")?;
+    if let Some(spinner) = spinner {
+        spinner.finish_and_clear();
+    }
+    let mut result = index.analysis(&path, 0)?.unwrap_or_else(|| AnalysisResult {
+        total_size: 0,
+        allocated_size: None,
+        files: Vec::new(),
+        skipped: Vec::new(),
+        interrupted: true,
+        strategy: AnalysisStrategy::Incremental,
+        profile: ScanProfile::default(),
+    });
+    result.interrupted |= refresh.is_none();
+    result.profile.elapsed_micros = micros_since(started);
+    Ok((result, refresh))
+}
+
+/// Say how much of the tree an incremental refresh had to read again
+pub(crate) fn print_refresh(refresh: &RefreshStats) {
+    let line = if refresh.full_walk {
+        t!("disk.incremental_first_run").to_string()
+    } else {
+        t!(
+            "disk.incremental_stats",
+            rescanned = refresh.dirs_rescanned,
+            reused = refresh.dirs_reused,
+            removed = refresh.dirs_removed
+        )
+    };
+    println!("{}", line.dimmed());
+}
+
+/// Say where results came from when `--index` was requested
+fn print_index_source(requested: bool, as_of: Option<DateTime<Utc>>) {
+    match (requested, as_of) {
+        (true, Some(as_of)) => println!(
+            "{}",
+            t!(
+                "index.source",
+                date = as_of.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+            )
+        ),
+        (true, None) => println!("{}", t!("index.not_indexed").yellow()),
+        _ => {}
+    }
+}
+
+/// Scan `path` and print it in an export `format` for other tools
+async fn export_scan(
+    path: &FilePath,
+    format: &str,
+    options: &ScanOptions,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let analyzer = DiskAnalyzer::new();
+    let (output, interrupted) = match format {
+        "treemap-json" => {
+            let tree = analyzer
+                .analyze_tree(path, options, cancel)
+                .await
+                .context("Failed to analyze directory")?;
+            (treemap_json(&tree.root), tree.interrupted)
+        }
+        "ncdu" => {
+            let started = Utc::now().timestamp();
+            let result = analyzer
+                .analyze_with_options(path, options, cancel)
+                .await
+                .context("Failed to analyze directory")?;
+            let root = Path::new(path.as_str());
+            (ncdu_json(root, &result.files, started), result.interrupted)
+        }
+        other => bail!("{}", t!("disk.unknown_format", format = other)),
+    };
+    if interrupted {
+        eprintln!("{}", t!("common.interrupted").yellow());
+    }
+    print_json(&output)
+}
+
+/// Field separator of a spreadsheet `--format`, `None` for other formats
+fn delimiter(format: &str) -> Option<char> {
+    match format {
+        "csv" => Some(','),
+        "tsv" => Some('\t'),
+        _ => None,
+    }
+}
+
+/// Write `files` to stdout as a table with `separator` between fields
+fn print_delimited(files: &[FileEntity], separator: char) -> Result<()> {
+    let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+    write_delimited(&mut out, files, separator).context("Failed to write table")
+}
+
+/// Display name of a file category
+fn category_label(category: FileCategory) -> &'static str {
+    match category {
+        FileCategory::Video => t!("disk.type_video"),
+        FileCategory::Images => t!("disk.type_images"),
+        FileCategory::Audio => t!("disk.type_audio"),
+        FileCategory::Archives => t!("disk.type_archives"),
+        FileCategory::DiskImages => t!("disk.type_disk_images"),
+        FileCategory::Documents => t!("disk.type_documents"),
+        FileCategory::Code => t!("disk.type_code"),
+        FileCategory::Caches => t!("disk.type_caches"),
+        FileCategory::Other => t!("disk.type_other"),
+    }
+}
+
+fn dev_artifact_label(kind: DevArtifactKind) -> &'static str {
+    match kind {
+        DevArtifactKind::NodeModules => t!("disk.dev_node_modules"),
+        DevArtifactKind::CargoTarget => t!("disk.dev_cargo_target"),
+        DevArtifactKind::PythonVirtualenv => t!("disk.dev_python_virtualenv"),
+        DevArtifactKind::GradleBuild => t!("disk.dev_gradle_build"),
+        DevArtifactKind::GradleCache => t!("disk.dev_gradle_cache"),
+        DevArtifactKind::MavenTarget => t!("disk.dev_maven_target"),
+        DevArtifactKind::MavenRepository => t!("disk.dev_maven_repository"),
+        DevArtifactKind::CocoaPods => t!("disk.dev_cocoapods"),
+        DevArtifactKind::CocoaPodsCache => t!("disk.dev_cocoapods_cache"),
+        DevArtifactKind::XcodeDerivedData => t!("disk.dev_xcode_derived_data"),
+    }
+}
+
+/// Record how far a scan got
+fn scan_finished_breadcrumb(result: &AnalysisResult) {
+    breadcrumb(
+        "scan",
+        "Scan finished",
+        &[
+            ("files", result.files.len().into()),
+            ("bytes", result.total_size.into()),
+            ("skipped", result.skipped.len().into()),
+            ("interrupted", result.interrupted.into()),
+        ],
+    );
+}
+
+/// Root volume plus everything mounted under /Volumes
+///
+/// Symlinks are skipped; on macOS the boot volume appears in /Volumes as a
+/// link back to /.
+fn mounted_volumes() -> Vec<PathBuf> {
+    match dragonfly_disk::mounted_volumes() {
+        Ok(volumes) => volumes
+            .into_iter()
+            .filter(|volume| volume.browsable)
+            .map(|volume| volume.mount_point)
+            .collect(),
+        Err(_) => vec![PathBuf::from("/")],
+    }
+}
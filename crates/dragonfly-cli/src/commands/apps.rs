@@ -0,0 +1,121 @@
+//! Installed applications command handler
+
+use super::analyze::parse_size;
+use crate::ui::{create_spinner, print_json};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use dragonfly_core::ports::CancellationToken;
+use dragonfly_core::t;
+use dragonfly_disk::{AppInventory, AppReport};
+use humansize::{format_size, DECIMAL};
+use serde_json::json;
+use std::path::PathBuf;
+
+/// List installed apps with their size and when they were last used
+///
+/// Apps at least `min_size` in total that haven't been opened for
+/// `unused_days` are highlighted as candidates for removal.
+pub async fn handle_apps(
+    unused_days: i64,
+    min_size: String,
+    json: bool,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let min_bytes = parse_size(&min_size)?;
+    let home = dirs::home_dir().context("Could not determine home directory")?;
+    let inventory = AppInventory::new(
+        vec![PathBuf::from("/Applications"), home.join("Applications")],
+        home.join("Library"),
+    );
+
+    let spinner = (!json).then(|| create_spinner(t!("apps.sizing")));
+    let apps = inventory.scan(cancel).await;
+    if let Some(spinner) = spinner {
+        spinner.finish_and_clear();
+    }
+
+    let cutoff = chrono::Utc::now() - chrono::Duration::days(unused_days);
+    let is_stale = |app: &AppReport| app.total_size() >= min_bytes && app.unused_since(cutoff);
+    let stale_bytes: u64 = apps
+        .iter()
+        .filter(|a| is_stale(a))
+        .map(|a| a.total_size())
+        .sum();
+
+    if json {
+        let json_output = json!({
+            "status": "ok",
+            "unused_days": unused_days,
+            "min_size_bytes": min_bytes,
+            "apps": apps.iter().map(|app| json!({
+                "name": app.name,
+                "path": app.path,
+                "bundle_id": app.bundle_id,
+                "bundle_size": app.bundle_size,
+                "data_size": app.data_size,
+                "total_size": app.total_size(),
+                "data_paths": app.data_paths,
+                "last_used": app.last_used,
+                "stale": is_stale(app)
+            })).collect::<Vec<_>>(),
+            "stale_bytes": stale_bytes,
+            "interrupted": cancel.is_cancelled()
+        });
+        print_json(&json_output)?;
+        return Ok(());
+    }
+
+    println!("{}", t!("apps.title").bold().bright_cyan());
+    if cancel.is_cancelled() {
+        println!("{}", t!("common.interrupted").yellow());
+    }
+    println!();
+    println!(
+        "{:<32} {:>10} {:>10} {:>10}  {}",
+        t!("apps.column_app").bold(),
+        t!("apps.column_bundle").bold(),
+        t!("apps.column_data").bold(),
+        t!("apps.column_total").bold(),
+        t!("apps.column_last_used").bold()
+    );
+    for app in &apps {
+        let last_used = app
+            .last_used
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .unwrap_or_else(|| t!("apps.never_used").to_string());
+        let line = format!(
+            "{:<32} {:>10} {:>10} {:>10}  {}",
+            app.name,
+            format_size(app.bundle_size, DECIMAL),
+            format_size(app.data_size, DECIMAL),
+            format_size(app.total_size(), DECIMAL),
+            last_used
+        );
+        if is_stale(app) {
+            println!("{}", line.yellow());
+        } else {
+            println!("{}", line);
+        }
+    }
+
+    println!();
+    if stale_bytes > 0 {
+        println!(
+            "{}",
+            t!(
+                "apps.stale",
+                size = min_size,
+                days = unused_days,
+                total = format_size(stale_bytes, DECIMAL)
+            )
+            .yellow()
+        );
+    } else {
+        println!(
+            "{}",
+            t!("apps.none_stale", size = min_size, days = unused_days).green()
+        );
+    }
+
+    Ok(())
+}
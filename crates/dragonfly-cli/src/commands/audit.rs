@@ -0,0 +1,110 @@
+//! Audit log command handler and recording helpers
+
+use crate::ui::print_json;
+use anyhow::Result;
+use colored::Colorize;
+use dragonfly_cleaner::{AuditAction, AuditEntry, AuditLog};
+use dragonfly_core::domain::policy::policy;
+use dragonfly_core::domain::protection::SkippedPath;
+use dragonfly_core::t;
+use humansize::{format_size, DECIMAL};
+use std::path::{Path, PathBuf};
+
+/// Command line of the current invocation, as recorded in audit entries
+pub fn command_line() -> String {
+    std::env::args().skip(1).collect::<Vec<_>>().join(" ")
+}
+
+/// Append an entry to the default audit log
+///
+/// The operation being audited has already happened at this point, so a
+/// failure to write the log is reported as a warning rather than an error.
+pub fn record(entry: AuditEntry) {
+    let log = AuditLog::new(AuditLog::default_path());
+    if let Err(e) = log.record(&entry) {
+        tracing::warn!(error = %e, path = %log.path().display(), "Failed to write audit log");
+    }
+}
+
+/// Audit paths an operation left alone because the protection policy
+/// refused them
+pub fn record_blocked(paths: Vec<PathBuf>) {
+    if paths.is_empty() {
+        return;
+    }
+    record(
+        AuditEntry::new(AuditAction::Blocked, command_line())
+            .with_items(paths.len())
+            .with_paths(paths),
+    );
+}
+
+/// Audit the refusals among paths an operation skipped
+pub fn record_refused(skipped: &[SkippedPath]) {
+    record_blocked(
+        skipped
+            .iter()
+            .filter(|skipped| skipped.reason.is_refusal())
+            .map(|skipped| PathBuf::from(&skipped.path))
+            .collect(),
+    );
+}
+
+/// Keep the paths the protection policy allows modifying, auditing the rest
+/// as blocked
+pub fn allowed_by_policy<'a>(paths: impl IntoIterator<Item = &'a Path>) -> Vec<&'a Path> {
+    let (allowed, blocked): (Vec<&Path>, Vec<&Path>) = paths
+        .into_iter()
+        .partition(|path| policy().check(&path.to_string_lossy()).is_none());
+    record_blocked(blocked.into_iter().map(Path::to_path_buf).collect());
+    allowed
+}
+
+/// List audit log entries
+pub async fn handle_audit_list(limit: Option<usize>, json: bool) -> Result<()> {
+    let log = AuditLog::new(AuditLog::default_path());
+    let mut entries = log.entries()?;
+
+    // Newest first
+    entries.reverse();
+    if let Some(limit) = limit {
+        entries.truncate(limit);
+    }
+
+    if json {
+        print_json(&entries)?;
+        return Ok(());
+    }
+
+    println!("{}", t!("audit.title").bold().bright_cyan());
+    println!("{}", t!("audit.log_file", path = log.path().display()));
+    println!();
+
+    if entries.is_empty() {
+        println!("{}", t!("audit.empty"));
+        return Ok(());
+    }
+
+    for entry in entries {
+        println!(
+            "{}  {}  {}",
+            entry.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            entry.action.to_string().bold(),
+            format_size(entry.bytes, DECIMAL)
+        );
+        println!("   {}", t!("audit.command", command = entry.command));
+        println!("   {}", t!("audit.items", count = entry.items));
+        if let Some(ref id) = entry.recovery_id {
+            println!("   {}", t!("audit.recovery_id", id = id));
+        }
+        for path in entry.paths.iter().take(5) {
+            println!("   - {}", path.display());
+        }
+        if entry.paths.len() > 5 {
+            println!("   {}", t!("audit.more", count = entry.paths.len() - 5));
+        }
+        println!();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,152 @@
+//! Capabilities command handler - what this build and machine can do
+//!
+//! Wrappers (scripts, the REST dashboard, AI tool integrations) call
+//! `dragonfly capabilities --json` once and adapt, instead of parsing
+//! `--help` or guessing from the platform.
+
+use crate::commands::doctor::enabled_features;
+use crate::ui::print_json;
+use anyhow::Result;
+use colored::Colorize;
+use dragonfly_core::t;
+use dragonfly_disk::supports_clones;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+
+/// Output formats every reporting command supports
+const OUTPUT_FORMATS: &[&str] = &["text", "json"];
+
+/// Global options that change how any command behaves
+const GLOBAL_OPTIONS: &[&str] = &[
+    "json",
+    "dry_run",
+    "redact_paths",
+    "nice",
+    "throttle",
+    "debug",
+];
+
+/// Full path of the executable `name` on `PATH`, if there is one
+fn find_on_path(name: &str) -> Option<PathBuf> {
+    use std::os::unix::fs::PermissionsExt;
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| {
+            std::fs::metadata(candidate).is_ok_and(|metadata| {
+                metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+            })
+        })
+}
+
+/// Platform abilities the optional subsystems depend on
+fn platform(home: &Path) -> serde_json::Value {
+    let notifier = if cfg!(target_os = "macos") {
+        "osascript"
+    } else {
+        "notify-send"
+    };
+    json!({
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "tmutil": find_on_path("tmutil").is_some(),
+        "launchctl": find_on_path("launchctl").is_some(),
+        "desktop_notifications": find_on_path(notifier).is_some(),
+        "apfs_clones": supports_clones(home).unwrap_or(false),
+    })
+}
+
+/// Subsystems and whether each one can run here
+fn subsystems(platform: &serde_json::Value) -> serde_json::Value {
+    let has = |ability: &str| platform[ability].as_bool().unwrap_or(false);
+    json!({
+        "disk": true,
+        "duplicates": true,
+        "monitor": true,
+        "health": true,
+        "clean": true,
+        "recover": true,
+        "audit": true,
+        "index": true,
+        "apps": true,
+        "trends": true,
+        "time_machine": has("tmutil"),
+        "watch": true,
+        "watch_notifications": has("desktop_notifications"),
+        "watch_install": has("launchctl"),
+        "tui": cfg!(feature = "tui"),
+        "serve": cfg!(feature = "web"),
+        "monitor_serve": cfg!(feature = "exporter"),
+        "monitor_gpu": cfg!(feature = "gpu") && cfg!(target_os = "macos"),
+        "skills": cfg!(feature = "skills"),
+    })
+}
+
+/// Report compiled features, available subsystems, output formats, and
+/// platform abilities
+pub async fn handle_capabilities(json: bool) -> Result<()> {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("/"));
+    let platform = platform(&home);
+    let subsystems = subsystems(&platform);
+    let features = enabled_features();
+
+    if json {
+        let json_output = json!({
+            "status": "ok",
+            "version": crate::VERSION,
+            "features": features,
+            "subsystems": subsystems,
+            "output_formats": OUTPUT_FORMATS,
+            "global_options": GLOBAL_OPTIONS,
+            "platform": platform,
+        });
+        print_json(&json_output)?;
+        return Ok(());
+    }
+
+    let print_flags = |title: &str, flags: &serde_json::Value| {
+        println!("{}", title.bold());
+        for (name, value) in flags.as_object().into_iter().flatten() {
+            match value.as_bool() {
+                Some(true) => println!("  {} {}", "✓".green(), name),
+                Some(false) => println!("  {} {}", "✗".dimmed(), name.dimmed()),
+                None => println!("  {}: {}", name, value.as_str().unwrap_or_default()),
+            }
+        }
+        println!();
+    };
+
+    println!(
+        "{}",
+        t!("capabilities.title", version = crate::VERSION)
+            .bold()
+            .bright_cyan()
+    );
+    println!();
+    let features = if features.is_empty() {
+        t!("capabilities.none").to_string()
+    } else {
+        features.join(", ")
+    };
+    println!("{} {}", t!("capabilities.features").bold(), features);
+    println!(
+        "{} {}",
+        t!("capabilities.formats").bold(),
+        OUTPUT_FORMATS.join(", ")
+    );
+    println!();
+    print_flags(t!("capabilities.subsystems"), &subsystems);
+    print_flags(t!("capabilities.platform"), &platform);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_on_path() {
+        assert!(find_on_path("sh").is_some());
+        assert!(find_on_path("dragonfly-no-such-tool").is_none());
+    }
+}
@@ -1,20 +1,63 @@
 //! Cache and temporary file cleaning command handler
 
-use anyhow::{Context, Result};
+use crate::commands::analyze::{finish_recovery, RECOVERY_RETENTION_DAYS};
+use crate::commands::{audit, trends};
+use crate::error_tracking::breadcrumb;
+use crate::notify::PromptNotifier;
+use crate::ui::{create_spinner, print_json, print_skipped};
+use anyhow::{bail, Context, Result};
 use colored::Colorize;
-use dragonfly_cleaner::{CleanTarget, SystemCleaner};
+use dragonfly_cleaner::{
+    docker as docker_storage, ios_backups, AuditAction, AuditEntry, CategoryTotal, CleanGroup,
+    CleanHistory, CleanRecord, CleanResult, CleanScope, CleanTarget, IosBackup, RecoveryManager,
+    SystemCleaner, TimeMachineManager,
+};
+use dragonfly_core::domain::protection::SkippedPath;
+use dragonfly_core::domain::scan_options::ScanOptions;
+use dragonfly_core::ports::{CancellationToken, Notifier, ProgressUpdate};
+use dragonfly_core::t;
+use dragonfly_disk::{OrphanFinder, OrphanGroup, ReclaimCheck, VolumeSpace};
 use humansize::{format_size, DECIMAL};
+use indicatif::ProgressBar;
 use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
+/// Flags of `clean` that pick what it cleans
+const TARGET_FLAGS: [&str; 7] = [
+    "--all",
+    "--caches",
+    "--logs",
+    "--temp",
+    "--xcode",
+    "--docker",
+    "--ios-backups",
+];
+
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_clean(
     dry_run: bool,
     all: bool,
     caches: bool,
     logs: bool,
     temp: bool,
+    xcode: bool,
+    docker: bool,
+    prune: bool,
+    ios_backups: bool,
     interactive: bool,
+    scope: Option<&str>,
+    older_than: Option<u32>,
+    permanent: bool,
     json: bool,
+    cancel: &CancellationToken,
 ) -> Result<()> {
+    if docker {
+        return handle_docker(dry_run, prune, json).await;
+    }
+    if ios_backups {
+        return handle_ios_backups(dry_run, interactive, older_than, permanent, json).await;
+    }
     let cleaner = SystemCleaner::new();
 
     // Determine target
@@ -26,76 +69,1125 @@ pub async fn handle_clean(
         CleanTarget::Logs
     } else if temp {
         CleanTarget::Temp
+    } else if xcode {
+        CleanTarget::Xcode
     } else {
         // No target specified
+        let message = t!("clean.no_target", flags = TARGET_FLAGS.join(", "));
         if json {
-            println!(
-                r#"{{"status":"error","message":"No target specified. Use --all, --caches, --logs, or --temp"}}"#
-            );
+            print_json(&json!({
+                "status": "error",
+                "message": message,
+                "targets": TARGET_FLAGS
+            }))?;
         } else {
-            println!("{}", "Cache Cleaner".bold().bright_cyan());
-            println!(
-                "{}",
-                "No target specified. Use --all, --caches, --logs, or --temp".yellow()
-            );
+            println!("{}", t!("clean.title").bold().bright_cyan());
+            println!("{}", message.yellow());
         }
         return Ok(());
     };
 
+    let scope_name = scope;
+    let scope = match scope {
+        Some("user") => Some(CleanScope::User),
+        Some("system") => Some(CleanScope::System),
+        _ => None,
+    };
+    // Shared locations belong to every account; only root may empty them
+    let needs_root = scope == Some(CleanScope::System) && !is_root();
+    if needs_root && !dry_run {
+        bail!(t!("clean.system_needs_root"));
+    }
+
+    let options = ScanOptions::default();
+    // An interactive clean asks about each folder it would clean first, and
+    // then cleans only the chosen ones
+    let chosen = if asks_first(interactive, dry_run, json)? {
+        let chosen = choose_groups(&cleaner, target, scope, older_than, &options, cancel).await?;
+        if chosen.groups.is_empty() {
+            println!("{}", t!("clean.interactive_none"));
+            return Ok(());
+        }
+        Some(chosen)
+    } else {
+        None
+    };
+
     // Perform cleaning
-    let result = cleaner
-        .clean(target, dry_run)
-        .await
-        .context("Failed to clean files")?;
+    let target_name = format!("{:?}", target);
+    breadcrumb(
+        "clean",
+        if dry_run {
+            "Dry run began"
+        } else {
+            "Deletion began"
+        },
+        &[("target", target_name.as_str().into())],
+    );
+    // Real cleans move files into a recovery archive unless asked to delete
+    // them outright, so `recover restore` can undo them
+    let mut recovery = if dry_run || permanent {
+        None
+    } else {
+        let manager = RecoveryManager::new(RecoveryManager::default_dir());
+        manager
+            .initialize()
+            .context("Failed to prepare the recovery archive")?;
+        let manifest = manager.create_manifest(RECOVERY_RETENTION_DAYS);
+        Some((manager, manifest))
+    };
+    // Free space is measured around permanent cleans to check the reported
+    // bytes; archived files still take their space
+    let volume = dirs::home_dir().unwrap_or_else(|| "/".into());
+    let space_before = if dry_run || recovery.is_some() {
+        None
+    } else {
+        VolumeSpace::of(&volume).ok()
+    };
+    let spinner = (!dry_run && !json).then(|| {
+        let spinner = create_spinner(t!("clean.removing"));
+        spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+        spinner
+    });
+    let progress = removal_progress(spinner.clone());
+    let result = match (recovery.as_mut(), &chosen) {
+        (Some((manager, manifest)), Some(chosen)) => {
+            cleaner
+                .clean_groups_to_recovery(
+                    &chosen.groups,
+                    older_than,
+                    &options,
+                    cancel,
+                    &progress,
+                    manager,
+                    manifest,
+                )
+                .await
+        }
+        (None, Some(chosen)) => {
+            cleaner
+                .clean_groups(&chosen.groups, older_than, &options, cancel, &progress)
+                .await
+        }
+        (Some((manager, manifest)), None) => {
+            cleaner
+                .clean_to_recovery(
+                    target, scope, older_than, &options, cancel, &progress, manager, manifest,
+                )
+                .await
+        }
+        (None, None) => {
+            cleaner
+                .clean_in_scope(
+                    target, scope, dry_run, older_than, &options, cancel, &progress,
+                )
+                .await
+        }
+    };
+    if let Some(spinner) = spinner {
+        spinner.finish_and_clear();
+    }
+    // Whatever was archived is recorded, even if the clean failed partway
+    let recovery = match recovery {
+        Some((manager, mut manifest)) if !manifest.items.is_empty() => {
+            manifest.interrupted = result.as_ref().map_or(true, |result| result.interrupted);
+            finish_recovery(&manager, &mut manifest)
+                .context("Failed to save the recovery manifest")?;
+            Some(manifest)
+        }
+        _ => None,
+    };
+    let result = result.context("Failed to clean files")?;
+    let reclaim = space_before
+        .filter(|_| result.bytes_freed > 0)
+        .and_then(|before| ReclaimCheck::measure(&volume, before, result.bytes_freed).ok());
+    // Snapshots can pin freed space even where purgeable space isn't reported
+    let pinned = reclaim.is_some_and(|check| {
+        check.pinned_by_snapshots()
+            || (check.is_short()
+                && TimeMachineManager::list_snapshots().is_ok_and(|s| !s.is_empty()))
+    });
+    breadcrumb(
+        "clean",
+        "Clean finished",
+        &[
+            ("target", target_name.as_str().into()),
+            ("files_found", result.files_found.len().into()),
+            ("files_cleaned", result.files_cleaned.into()),
+            ("bytes_freed", result.bytes_freed.into()),
+            ("files_kept_recent", result.files_kept_recent.into()),
+            ("interrupted", result.interrupted.into()),
+        ],
+    );
+    record_history(
+        CleanRecord::new(target_name.as_str(), dry_run)
+            .with_scope(scope_name)
+            .with_totals(result.files_cleaned, result.bytes_freed)
+            .with_recovery_id(recovery.as_ref().map(|manifest| manifest.id.as_str()))
+            .with_interrupted(result.interrupted),
+    );
+
+    if !dry_run {
+        trends::record_free_space(&volume);
+        audit::record_refused(&result.skipped);
+    }
+    if !dry_run && result.files_cleaned > 0 {
+        let paths = match &chosen {
+            Some(chosen) => chosen
+                .groups
+                .iter()
+                .map(|group| group.path.clone())
+                .collect(),
+            None => target.paths_in(scope).into_iter().map(Into::into).collect(),
+        };
+        let mut entry = AuditEntry::new(AuditAction::Delete, audit::command_line())
+            .with_paths(paths)
+            .with_items(result.files_cleaned)
+            .with_bytes(result.bytes_freed);
+        if let Some(manifest) = &recovery {
+            entry = entry.with_recovery_id(manifest.id.clone());
+        }
+        audit::record(entry);
+    }
 
     if json {
         let json_output = json!({
             "status": "ok",
             "dry_run": dry_run,
             "target": format!("{:?}", target),
+            "scope": scope,
+            "older_than_days": older_than,
             "files_found": result.files_found.len(),
             "files_cleaned": result.files_cleaned,
+            "files_kept_recent": result.files_kept_recent,
+            "categories": result.categories,
             "bytes_freed": result.bytes_freed,
-            "bytes_freed_human": format_size(result.bytes_freed, DECIMAL)
+            "bytes_freed_human": format_size(result.bytes_freed, DECIMAL),
+            "bytes_reclaimable": result.bytes_reclaimable(),
+            "bytes_retained_by_snapshots": result.bytes_retained_by_snapshots,
+            "free_space": reclaim.map(|check| json!({
+                "before": check.before,
+                "after": check.after,
+                "reclaimed": check.reclaimed(),
+                "shortfall": check.shortfall(),
+                "pinned_by_snapshots": pinned
+            })),
+            "recovery_id": recovery.as_ref().map(|manifest| &manifest.id),
+            "skipped": result.skipped,
+            "interrupted": result.interrupted
         });
-        println!("{}", serde_json::to_string_pretty(&json_output)?);
+        print_json(&json_output)?;
         return Ok(());
     }
 
-    // Human-readable output
-    println!("{}", "Cache Cleaner".bold().bright_cyan());
+    // Human-readable output; an interactive clean printed the title before
+    // asking
+    if chosen.is_none() {
+        println!("{}", t!("clean.title").bold().bright_cyan());
+    }
     if dry_run {
-        println!("{}", "Mode: Dry run (no files will be deleted)".yellow());
+        println!("{}", t!("common.dry_run").yellow());
+    } else if permanent {
+        println!("{}", t!("clean.mode_permanent").red().bold());
     } else {
-        println!("{}", "Mode: Cleaning (files will be deleted)".red().bold());
+        println!("{}", t!("clean.mode_clean").red().bold());
     }
 
-    println!("Target: {:?}", target);
+    println!("{}", t!("clean.target", target = target_name));
+    match scope {
+        Some(CleanScope::User) => println!("{}", t!("clean.scope_user")),
+        Some(CleanScope::System) => {
+            println!("{}", t!("clean.scope_system"));
+            if needs_root {
+                println!("{}", t!("clean.system_preview").yellow());
+            } else {
+                println!("{}", t!("clean.system_warning").yellow());
+            }
+        }
+        None => {}
+    }
+    if let Some(days) = older_than {
+        println!("{}", t!("clean.older_than", days = days));
+    }
+    if result.interrupted {
+        println!("{}", t!("clean.interrupted").yellow());
+    }
     println!();
 
     if dry_run {
-        println!("Found {} files", result.files_found.len());
+        println!("{}", t!("clean.found", count = result.files_found.len()));
         println!(
-            "Would free: {}",
-            format_size(result.bytes_freed, DECIMAL).bold()
+            "{}",
+            t!(
+                "clean.would_free",
+                size = format_size(result.bytes_freed, DECIMAL).bold()
+            )
         );
+        print_retained(&result);
+        if target == CleanTarget::Xcode || result.categories.len() > 1 {
+            print_categories(&result.categories);
+        }
 
         if interactive && !result.files_found.is_empty() {
-            println!("\n{}", "Files that would be cleaned:".cyan());
+            println!("\n{}", t!("clean.file_list").cyan());
             for (i, file) in result.files_found.iter().take(20).enumerate() {
                 println!("  {}. {}", i + 1, file.display());
             }
             if result.files_found.len() > 20 {
-                println!("  ... and {} more files", result.files_found.len() - 20);
+                println!(
+                    "  {}",
+                    t!("clean.more_files", count = result.files_found.len() - 20)
+                );
             }
         }
     } else {
-        println!("Cleaned {} files", result.files_cleaned);
+        println!("{}", t!("clean.cleaned", count = result.files_cleaned));
+        if let Some(chosen) = &chosen {
+            println!(
+                "{}",
+                t!(
+                    "clean.interactive_summary",
+                    cleaned = chosen.groups.len(),
+                    skipped = chosen.offered - chosen.groups.len()
+                )
+            );
+        }
+        let size = format_size(result.bytes_freed, DECIMAL).bold().green();
+        if let Some(manifest) = &recovery {
+            println!("{}", t!("clean.archived", size = size, id = manifest.id));
+            println!(
+                "{}",
+                t!(
+                    "clean.archived_hint",
+                    date = manifest.retention_until.format("%Y-%m-%d")
+                )
+                .dimmed()
+            );
+        } else {
+            println!("{}", t!("clean.freed", size = size));
+        }
+        print_retained(&result);
+        if let Some(check) = reclaim {
+            print_reclaim(&check, pinned);
+        }
+    }
+
+    if result.files_kept_recent > 0 {
+        println!(
+            "{}",
+            t!("clean.kept_recent", count = result.files_kept_recent).dimmed()
+        );
+    }
+    if result
+        .categories
+        .iter()
+        .any(|total| total.category == "simulators")
+    {
+        println!("{}", t!("clean.simulators_permanent").yellow());
+    }
+    print_skipped(&result.skipped);
+
+    Ok(())
+}
+
+/// List the data apps no longer installed left in ~/Library, and with
+/// `interactive` ask which apps' leftovers to move to recovery
+pub async fn handle_orphans(
+    dry_run: bool,
+    interactive: bool,
+    json: bool,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let home = dirs::home_dir().context("Could not determine home directory")?;
+    let finder = OrphanFinder::new(
+        vec![
+            PathBuf::from("/Applications"),
+            PathBuf::from("/Applications/Utilities"),
+            home.join("Applications"),
+        ],
+        home.join("Library"),
+    );
+    let spinner = (!json).then(|| create_spinner(t!("clean.orphans_scanning")));
+    let orphans = finder.scan(cancel);
+    if let Some(spinner) = spinner {
+        spinner.finish_and_clear();
+    }
+    let total: u64 = orphans.iter().map(|orphan| orphan.size).sum();
+
+    if json {
+        print_json(&json!({
+            "status": "ok",
+            "dry_run": dry_run,
+            "orphans": orphans,
+            "bytes": total
+        }))?;
+        return Ok(());
+    }
+
+    println!("{}", t!("clean.title").bold().bright_cyan());
+    if dry_run {
+        println!("{}", t!("common.dry_run").yellow());
+    }
+    println!("{}", t!("clean.target", target = "App leftovers"));
+    println!();
+    if orphans.is_empty() {
+        println!("{}", t!("clean.orphans_none"));
+        return Ok(());
+    }
+    for orphan in &orphans {
         println!(
-            "Freed: {}",
-            format_size(result.bytes_freed, DECIMAL).bold().green()
+            "{}  {}",
+            orphan.bundle_id.bold(),
+            format_size(orphan.size, DECIMAL)
+        );
+        for leftover in &orphan.leftovers {
+            println!(
+                "    {}  {}",
+                leftover.path.display().to_string().dimmed(),
+                format_size(leftover.size, DECIMAL).dimmed()
+            );
+        }
+    }
+    println!();
+    println!(
+        "{}",
+        t!(
+            "clean.orphans_total",
+            count = orphans.len(),
+            size = format_size(total, DECIMAL).bold()
+        )
+    );
+    if !interactive {
+        println!("{}", t!("clean.orphans_interactive_hint").dimmed());
+        return Ok(());
+    }
+
+    println!();
+    let notifier = PromptNotifier::new();
+    let mut chosen: Vec<&OrphanGroup> = Vec::new();
+    for orphan in &orphans {
+        if notifier.has_quit() {
+            break;
+        }
+        let prompt = t!(
+            "clean.orphans_prompt",
+            id = orphan.bundle_id,
+            size = format_size(orphan.size, DECIMAL)
         );
+        if notifier.confirm(&prompt).await? {
+            chosen.push(orphan);
+        }
     }
+    let chosen_bytes: u64 = chosen.iter().map(|orphan| orphan.size).sum();
 
+    println!();
+    if dry_run {
+        if !chosen.is_empty() {
+            let leftovers = chosen.iter().map(|orphan| orphan.leftovers.len()).sum();
+            record_history(CleanRecord::new("Orphans", true).with_totals(leftovers, chosen_bytes));
+        }
+        println!(
+            "{}",
+            t!(
+                "clean.would_free",
+                size = format_size(chosen_bytes, DECIMAL).bold()
+            )
+        );
+        return Ok(());
+    }
+    if chosen.is_empty() {
+        return Ok(());
+    }
+    breadcrumb("clean", "Leftover deletion began", &[]);
+    let leftovers: Vec<(&Path, u64)> = chosen
+        .iter()
+        .flat_map(|orphan| &orphan.leftovers)
+        .map(|leftover| (leftover.path.as_path(), leftover.size))
+        .collect();
+    let removal = remove_trees(leftovers, "app-leftovers", false)?;
+    let bytes: u64 = chosen
+        .iter()
+        .flat_map(|orphan| &orphan.leftovers)
+        .filter(|leftover| removal.removed.contains(&leftover.path.as_path()))
+        .map(|leftover| leftover.size)
+        .sum();
+    record_history(
+        CleanRecord::new("Orphans", false)
+            .with_totals(removal.removed.len(), bytes)
+            .with_recovery_id(removal.recovery_id.as_deref()),
+    );
+    if let Some(id) = &removal.recovery_id {
+        println!(
+            "{}",
+            t!(
+                "clean.orphans_archived",
+                count = removal.removed.len(),
+                size = format_size(bytes, DECIMAL).bold().green(),
+                id = id
+            )
+        );
+    }
+    print_skipped(&removal.skipped);
     Ok(())
 }
+
+/// List past cleans, newest first
+pub fn handle_history(json: bool) -> Result<()> {
+    let history = CleanHistory::new(CleanHistory::default_path());
+    let mut records = history
+        .records()
+        .context("Failed to read the clean history")?;
+    records.reverse();
+
+    if json {
+        print_json(&records)?;
+        return Ok(());
+    }
+
+    println!("{}", t!("clean.history_title").bold().bright_cyan());
+    println!(
+        "{}",
+        t!("clean.history_file", path = history.path().display()).dimmed()
+    );
+    println!();
+    if records.is_empty() {
+        println!("{}", t!("clean.history_none"));
+        return Ok(());
+    }
+    for record in &records {
+        let mode = if record.dry_run {
+            t!("clean.history_dry_run")
+        } else if record.recovery_id.is_some() {
+            t!("clean.history_archived")
+        } else {
+            t!("clean.history_deleted")
+        };
+        let target = match &record.scope {
+            Some(scope) => format!("{} ({})", record.target, scope),
+            None => record.target.clone(),
+        };
+        println!(
+            "{}  {:<22} {:>8} {:>10}  {}",
+            record
+                .timestamp
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M"),
+            target.bold(),
+            record.files,
+            format_size(record.bytes, DECIMAL),
+            if record.dry_run {
+                mode.dimmed()
+            } else {
+                mode.normal()
+            }
+        );
+        if let Some(id) = &record.recovery_id {
+            println!("    {}", t!("clean.history_recovery", id = id).dimmed());
+        }
+        if record.interrupted {
+            println!("    {}", t!("clean.history_interrupted").yellow());
+        }
+    }
+    let cleans: Vec<&CleanRecord> = records.iter().filter(|record| !record.dry_run).collect();
+    println!();
+    println!(
+        "{}",
+        t!(
+            "clean.history_total",
+            count = cleans.len(),
+            size = format_size(
+                cleans.iter().map(|record| record.bytes).sum::<u64>(),
+                DECIMAL
+            )
+            .bold()
+        )
+    );
+    Ok(())
+}
+
+/// Append a record to the clean history
+///
+/// The clean has already happened, so a failure to write the history is
+/// logged rather than failing the command.
+fn record_history(record: CleanRecord) {
+    let history = CleanHistory::new(CleanHistory::default_path());
+    if let Err(e) = history.record(&record) {
+        tracing::warn!(error = %e, path = %history.path().display(), "Failed to write clean history");
+    }
+}
+
+/// Show what pruning Docker would reclaim, and with `prune` prune it once
+/// the user confirms
+///
+/// Only a requested prune goes into the clean history, as a dry run when
+/// `--dry-run` or the user's answer stopped it; a plain report is not a clean.
+async fn handle_docker(dry_run: bool, prune: bool, json: bool) -> Result<()> {
+    let requested = prune;
+    let prune = prune && !dry_run;
+    if prune && json {
+        bail!(t!("clean.docker_prune_json"));
+    }
+    let usage = docker_storage::usage().context("Failed to read Docker's disk usage")?;
+    let Some(usage) = usage else {
+        if json {
+            print_json(&json!({
+                "status": "ok",
+                "dry_run": dry_run,
+                "target": "Docker",
+                "installed": false
+            }))?;
+        } else {
+            println!("{}", t!("clean.title").bold().bright_cyan());
+            println!("{}", t!("clean.docker_missing").yellow());
+        }
+        return Ok(());
+    };
+
+    if json {
+        if requested {
+            record_history(
+                CleanRecord::new("Docker", true)
+                    .with_totals(usage.reclaimable_items(), usage.reclaimable_bytes()),
+            );
+        }
+        print_json(&json!({
+            "status": "ok",
+            "dry_run": dry_run,
+            "target": "Docker",
+            "installed": true,
+            "storage": usage.storage,
+            "dangling_images": usage.dangling_images,
+            "stopped_containers": usage.stopped_containers,
+            "build_cache": usage.build_cache,
+            "reclaimable_bytes": usage.reclaimable_bytes()
+        }))?;
+        return Ok(());
+    }
+
+    println!("{}", t!("clean.title").bold().bright_cyan());
+    if dry_run {
+        println!("{}", t!("common.dry_run").yellow());
+    } else if prune {
+        println!("{}", t!("clean.mode_docker").red().bold());
+    }
+    println!("{}", t!("clean.target", target = "Docker"));
+    for storage in &usage.storage {
+        println!(
+            "{}",
+            t!(
+                "clean.docker_storage",
+                path = storage.path.display(),
+                size = format_size(storage.size_on_disk, DECIMAL)
+            )
+        );
+    }
+    println!();
+    let lines = [
+        ("clean.docker_images", usage.dangling_images),
+        ("clean.docker_containers", usage.stopped_containers),
+        ("clean.docker_build_cache", usage.build_cache),
+    ];
+    for (key, reclaimable) in lines {
+        println!(
+            "  {}",
+            t!(
+                key,
+                count = reclaimable.count,
+                size = format_size(reclaimable.bytes, DECIMAL)
+            )
+        );
+    }
+    let estimate = format_size(usage.reclaimable_bytes(), DECIMAL);
+    println!(
+        "{}",
+        t!("clean.docker_reclaimable", size = estimate.clone().bold())
+    );
+
+    let confirmed = if prune && usage.reclaimable_items() > 0 {
+        println!();
+        let prompt = t!("clean.docker_prune_confirm", size = estimate);
+        PromptNotifier::new().confirm(&prompt).await?
+    } else {
+        false
+    };
+    if !confirmed {
+        if requested {
+            record_history(
+                CleanRecord::new("Docker", true)
+                    .with_totals(usage.reclaimable_items(), usage.reclaimable_bytes()),
+            );
+        }
+        if prune {
+            println!("{}", t!("clean.docker_prune_declined"));
+        } else if !dry_run && usage.reclaimable_items() > 0 {
+            println!("{}", t!("clean.docker_prune_hint").dimmed());
+        }
+        return Ok(());
+    }
+
+    breadcrumb("clean", "Docker prune began", &[]);
+    let bytes = docker_storage::prune().context("Failed to prune Docker storage")?;
+    audit::record(
+        AuditEntry::new(AuditAction::DockerPrune, audit::command_line())
+            .with_items(usage.reclaimable_items())
+            .with_bytes(bytes),
+    );
+    record_history(CleanRecord::new("Docker", false).with_totals(usage.reclaimable_items(), bytes));
+    println!(
+        "{}",
+        t!(
+            "clean.docker_reclaimed",
+            size = format_size(bytes, DECIMAL).bold().green()
+        )
+    );
+    println!("{}", t!("clean.docker_hint").dimmed());
+    Ok(())
+}
+
+/// List device backups, and with `interactive` ask which stale ones to
+/// delete
+///
+/// A backup is stale once its device hasn't been backed up for
+/// `older_than` days, or [`ios_backups::STALE_AFTER_DAYS`].
+async fn handle_ios_backups(
+    dry_run: bool,
+    interactive: bool,
+    older_than: Option<u32>,
+    permanent: bool,
+    json: bool,
+) -> Result<()> {
+    let backups = ios_backups::backups().context("Failed to read device backups")?;
+    let days = older_than.unwrap_or(ios_backups::STALE_AFTER_DAYS);
+    let cutoff = chrono::Utc::now() - chrono::Duration::days(i64::from(days));
+    let stale: Vec<&IosBackup> = backups
+        .iter()
+        .filter(|backup| backup.is_stale(cutoff))
+        .collect();
+
+    if !json {
+        println!("{}", t!("clean.title").bold().bright_cyan());
+        if dry_run {
+            println!("{}", t!("common.dry_run").yellow());
+        }
+        println!("{}", t!("clean.target", target = t!("clean.ios_target")));
+        println!();
+        if backups.is_empty() {
+            println!("{}", t!("clean.ios_none"));
+            return Ok(());
+        }
+        for (i, backup) in backups.iter().enumerate() {
+            let line = format!("{:>3}. {}", i + 1, describe_backup(backup));
+            if backup.is_stale(cutoff) {
+                println!("{}  {}", line, t!("clean.ios_stale").yellow());
+            } else {
+                println!("{}", line);
+            }
+        }
+        println!();
+        println!(
+            "{}",
+            t!(
+                "clean.ios_stale_total",
+                count = stale.len(),
+                days = days,
+                size = format_size(stale.iter().map(|b| b.size).sum::<u64>(), DECIMAL).bold()
+            )
+        );
+    }
+
+    // Deleting a backup is only ever a choice made one backup at a time
+    let chosen: Vec<&IosBackup> = if interactive && !json {
+        let notifier = PromptNotifier::new();
+        let mut chosen = Vec::new();
+        for backup in &stale {
+            if notifier.has_quit() {
+                break;
+            }
+            let name = backup
+                .device_name
+                .as_deref()
+                .unwrap_or(t!("clean.ios_unknown_device"));
+            let prompt = t!(
+                "clean.ios_delete_prompt",
+                name = name,
+                size = format_size(backup.size, DECIMAL)
+            );
+            if notifier.confirm(&prompt).await? {
+                chosen.push(*backup);
+            }
+        }
+        chosen
+    } else {
+        Vec::new()
+    };
+    let removal = if dry_run || chosen.is_empty() {
+        Removal::default()
+    } else {
+        breadcrumb("clean", "Device backup deletion began", &[]);
+        remove_trees(
+            chosen
+                .iter()
+                .map(|backup| (backup.path.as_path(), backup.size))
+                .collect(),
+            "ios-backup",
+            permanent,
+        )?
+    };
+    let removed: Vec<&IosBackup> = if dry_run {
+        chosen
+    } else {
+        chosen
+            .into_iter()
+            .filter(|backup| removal.removed.contains(&backup.path.as_path()))
+            .collect()
+    };
+    let bytes: u64 = removed.iter().map(|backup| backup.size).sum();
+    if !removed.is_empty() {
+        record_history(
+            CleanRecord::new("IosBackups", dry_run)
+                .with_totals(removed.len(), bytes)
+                .with_recovery_id(removal.recovery_id.as_deref()),
+        );
+    }
+
+    if json {
+        print_json(&json!({
+            "status": "ok",
+            "dry_run": dry_run,
+            "target": "iOS backups",
+            "stale_after_days": days,
+            "backups": backups.iter().map(|backup| json!({
+                "path": backup.path,
+                "device_name": backup.device_name,
+                "product_type": backup.product_type,
+                "product_version": backup.product_version,
+                "last_backup": backup.last_backup,
+                "size": backup.size,
+                "stale": backup.is_stale(cutoff)
+            })).collect::<Vec<_>>(),
+            "stale_bytes": stale.iter().map(|backup| backup.size).sum::<u64>()
+        }))?;
+        return Ok(());
+    }
+
+    if !interactive {
+        if !stale.is_empty() {
+            println!("{}", t!("clean.ios_interactive_hint").dimmed());
+        }
+        return Ok(());
+    }
+    println!();
+    let size = format_size(bytes, DECIMAL).bold().green();
+    if dry_run {
+        println!("{}", t!("clean.would_free", size = size));
+    } else if let Some(id) = &removal.recovery_id {
+        println!(
+            "{}",
+            t!(
+                "clean.ios_archived",
+                count = removed.len(),
+                size = size,
+                id = id
+            )
+        );
+    } else {
+        println!(
+            "{}",
+            t!("clean.ios_deleted", count = removed.len(), size = size)
+        );
+    }
+    print_skipped(&removal.skipped);
+    Ok(())
+}
+
+/// Device name, model and iOS version, date of the last backup and size
+fn describe_backup(backup: &IosBackup) -> String {
+    let name = backup
+        .device_name
+        .as_deref()
+        .unwrap_or(t!("clean.ios_unknown_device"));
+    let model = [
+        backup.product_type.as_deref(),
+        backup.product_version.as_deref(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join(", ");
+    let date = backup.last_backup.map_or_else(
+        || "?".to_string(),
+        |date| {
+            date.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d")
+                .to_string()
+        },
+    );
+    let size = format_size(backup.size, DECIMAL);
+    if model.is_empty() {
+        t!("clean.ios_backup", name = name, date = date, size = size)
+    } else {
+        t!(
+            "clean.ios_backup_model",
+            name = name,
+            model = model,
+            date = date,
+            size = size
+        )
+    }
+}
+
+/// What [`remove_trees`] removed
+#[derive(Default)]
+struct Removal<'a> {
+    /// Folders and files removed
+    removed: Vec<&'a Path>,
+    /// Recovery they were moved to, unless deleted outright
+    recovery_id: Option<String>,
+    /// Folders and files refused or that couldn't be removed, with the reason
+    skipped: Vec<SkippedPath>,
+}
+
+/// Move folders and files, each with the bytes it takes, into a new
+/// recovery, or delete them outright if `permanent`, and audit the removal
+///
+/// Paths the protection policy refuses are left alone and audited as
+/// blocked.
+fn remove_trees<'a>(
+    items: Vec<(&'a Path, u64)>,
+    category: &str,
+    permanent: bool,
+) -> Result<Removal<'a>> {
+    let allowed = audit::allowed_by_policy(items.iter().map(|(path, _)| *path));
+    let mut recovery = if permanent {
+        None
+    } else {
+        let manager = RecoveryManager::new(RecoveryManager::default_dir());
+        manager
+            .initialize()
+            .context("Failed to prepare the recovery archive")?;
+        let manifest = manager.create_manifest(RECOVERY_RETENTION_DAYS);
+        Some((manager, manifest))
+    };
+
+    let mut removal = Removal::default();
+    let mut bytes = 0;
+    for (path, size) in items.into_iter().filter(|(path, _)| allowed.contains(path)) {
+        let result = match recovery.as_mut() {
+            Some((manager, manifest)) => manager.archive_tree(manifest, path, category, "clean"),
+            None if path.is_dir() => std::fs::remove_dir_all(path),
+            None => std::fs::remove_file(path),
+        };
+        match result {
+            Ok(()) => {
+                removal.removed.push(path);
+                bytes += size;
+            }
+            Err(e) => removal
+                .skipped
+                .extend(SkippedPath::from_io_error(&path.to_string_lossy(), &e)),
+        }
+    }
+    // Whatever was archived is recorded, even if some items failed
+    if let Some((manager, mut manifest)) =
+        recovery.filter(|(_, manifest)| !manifest.items.is_empty())
+    {
+        finish_recovery(&manager, &mut manifest).context("Failed to save the recovery manifest")?;
+        removal.recovery_id = Some(manifest.id);
+    }
+
+    audit::record_refused(&removal.skipped);
+    if !removal.removed.is_empty() {
+        let mut entry = AuditEntry::new(AuditAction::Delete, audit::command_line())
+            .with_paths(
+                removal
+                    .removed
+                    .iter()
+                    .map(|path| path.to_path_buf())
+                    .collect(),
+            )
+            .with_items(removal.removed.len())
+            .with_bytes(bytes);
+        if let Some(id) = &removal.recovery_id {
+            entry = entry.with_recovery_id(id.clone());
+        }
+        audit::record(entry);
+    }
+    Ok(removal)
+}
+
+/// Reporter showing the files a clean removed, the bytes it freed and how
+/// many files a second it's removing on `spinner`
+fn removal_progress(spinner: Option<ProgressBar>) -> impl Fn(&ProgressUpdate) + Send + Sync {
+    let started = Instant::now();
+    move |update: &ProgressUpdate| {
+        let Some(spinner) = &spinner else {
+            return;
+        };
+        let seconds = started.elapsed().as_secs_f64().max(0.001);
+        spinner.set_message(t!(
+            "clean.progress",
+            count = update.items_processed,
+            size = format_size(update.bytes_processed, DECIMAL),
+            rate = (update.items_processed as f64 / seconds).round()
+        ));
+    }
+}
+
+/// Whether a clean asks about each folder before cleaning it
+///
+/// `--json` leaves no room for questions, and cleaning without them would
+/// clean folders nobody confirmed, so the two together are refused.
+fn asks_first(interactive: bool, dry_run: bool, json: bool) -> Result<bool> {
+    if interactive && json {
+        bail!(t!("clean.interactive_json"));
+    }
+    Ok(interactive && !dry_run)
+}
+
+/// The folders an interactive clean chose, out of how many it offered
+#[derive(Debug)]
+struct Chosen {
+    groups: Vec<CleanGroup>,
+    offered: usize,
+}
+
+/// Offer each folder cleaning `target` would touch, asking whether to clean
+/// it
+async fn choose_groups(
+    cleaner: &SystemCleaner,
+    target: CleanTarget,
+    scope: Option<CleanScope>,
+    older_than: Option<u32>,
+    options: &ScanOptions,
+    cancel: &CancellationToken,
+) -> Result<Chosen> {
+    let spinner = create_spinner(t!("clean.scanning"));
+    let groups = cleaner
+        .groups(target, scope, older_than, options, cancel)
+        .await
+        .context("Failed to list files to clean")?;
+    spinner.finish_and_clear();
+
+    println!("{}", t!("clean.title").bold().bright_cyan());
+    println!("{}", t!("clean.interactive_intro", count = groups.len()));
+    println!();
+    let offered = groups.len();
+    let notifier = PromptNotifier::new();
+    let mut chosen = Vec::new();
+    for group in groups {
+        if notifier.has_quit() {
+            break;
+        }
+        let prompt = t!(
+            "clean.interactive_prompt",
+            path = group.path.display(),
+            size = format_size(group.bytes, DECIMAL),
+            count = group.files
+        );
+        if notifier.confirm(&prompt).await? {
+            chosen.push(group);
+        }
+    }
+    println!();
+    Ok(Chosen {
+        groups: chosen,
+        offered,
+    })
+}
+
+/// Show the size of each category a dry run found
+fn print_categories(categories: &[CategoryTotal]) {
+    for total in categories {
+        println!(
+            "  {}",
+            t!(
+                "clean.category",
+                name = category_label(total.category),
+                size = format_size(total.bytes, DECIMAL),
+                count = total.items
+            )
+        );
+    }
+}
+
+/// Name of a cleaning category in the installed language
+fn category_label(category: &str) -> &'static str {
+    match category {
+        "cache" => t!("clean.category_cache"),
+        "logs" => t!("clean.category_logs"),
+        "temp" => t!("clean.category_temp"),
+        "derived-data" => t!("clean.category_derived_data"),
+        "archives" => t!("clean.category_archives"),
+        "device-support" => t!("clean.category_device_support"),
+        "simulators" => t!("clean.category_simulators"),
+        _ => t!("clean.category_other"),
+    }
+}
+
+/// Split the freed space into what comes back now and what local snapshots
+/// keep, suggesting thinning them when they keep most of it
+fn print_retained(result: &CleanResult) {
+    if result.bytes_retained_by_snapshots == 0 {
+        return;
+    }
+    println!(
+        "  {}",
+        t!(
+            "clean.reclaimable",
+            size = format_size(result.bytes_reclaimable(), DECIMAL)
+        )
+    );
+    println!(
+        "  {}",
+        t!(
+            "clean.retained",
+            size = format_size(result.bytes_retained_by_snapshots, DECIMAL)
+        )
+    );
+    if result.snapshots_dominate() {
+        println!("{}", t!("clean.thin_hint").yellow());
+    }
+}
+
+/// Show how much of the reported space the volume actually gained
+fn print_reclaim(check: &ReclaimCheck, pinned: bool) {
+    let reclaimed = check.reclaimed();
+    let size = if reclaimed < 0 {
+        format!("-{}", format_size(reclaimed.unsigned_abs(), DECIMAL))
+    } else {
+        format_size(reclaimed.unsigned_abs(), DECIMAL)
+    };
+    println!("{}", t!("clean.reclaimed", size = size));
+    if !check.is_short() {
+        return;
+    }
+    let shortfall = format_size(check.shortfall(), DECIMAL);
+    if pinned {
+        println!("{}", t!("clean.pinned", size = shortfall).yellow());
+        println!("{}", t!("clean.pinned_hint").dimmed());
+    } else {
+        println!("{}", t!("clean.shortfall", size = shortfall).yellow());
+    }
+}
+
+/// Whether the process runs as root
+fn is_root() -> bool {
+    // SAFETY: `geteuid` takes no arguments and cannot fail
+    unsafe { libc::geteuid() == 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interactive_json_never_cleans_unconfirmed_groups() {
+        // Nothing is cleaned without asking: the combination is an error
+        assert!(asks_first(true, false, true).is_err());
+        assert!(asks_first(true, true, true).is_err());
+
+        assert!(asks_first(true, false, false).unwrap());
+        assert!(!asks_first(true, true, false).unwrap());
+        assert!(!asks_first(false, false, true).unwrap());
+    }
+}
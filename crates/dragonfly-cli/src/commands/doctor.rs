@@ -0,0 +1,365 @@
+//! Doctor command handler - self-checks of the DragonFly installation
+//!
+//! `doctor --privacy` backs up the "zero network activity" promise with
+//! evidence: it inspects the features this binary was built with and the
+//! runtime configuration, and only attests to no network activity when every
+//! path that could send data off the machine is shown to be inactive.
+
+use crate::error_tracking::{is_local_dsn, load_config};
+use crate::ui::print_json;
+use anyhow::Result;
+use colored::Colorize;
+use dragonfly_core::t;
+use serde_json::json;
+
+/// Default dashboard port used by `dragonfly serve`
+#[cfg(feature = "web")]
+const DEFAULT_SERVE_PORT: u16 = 7878;
+
+/// Default port of the Prometheus exporter, `dragonfly monitor serve`
+#[cfg(feature = "exporter")]
+const DEFAULT_EXPORTER_PORT: u16 = 9095;
+
+/// A server mode built into this binary
+struct ServerMode {
+    /// Catalog key of what it is, e.g. "the web dashboard"
+    name: &'static str,
+    /// Command that starts it
+    command: &'static str,
+    /// Port it listens on by default
+    port: u16,
+    /// Catalog key of where it binds
+    binds: &'static str,
+}
+
+/// Server modes compiled into this binary
+// Which modes are pushed depends on the features built
+#[allow(unused_mut, clippy::vec_init_then_push)]
+fn server_modes() -> Vec<ServerMode> {
+    let mut modes = Vec::new();
+    #[cfg(feature = "web")]
+    modes.push(ServerMode {
+        name: "doctor.mode_dashboard",
+        command: "dragonfly serve",
+        port: DEFAULT_SERVE_PORT,
+        binds: "doctor.binds_dashboard",
+    });
+    #[cfg(feature = "exporter")]
+    modes.push(ServerMode {
+        name: "doctor.mode_exporter",
+        command: "dragonfly monitor serve",
+        port: DEFAULT_EXPORTER_PORT,
+        binds: "doctor.binds_exporter",
+    });
+    modes
+}
+
+/// Outcome of a single privacy check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl CheckStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Pass => "pass",
+            Self::Warn => "warn",
+            Self::Fail => "fail",
+        }
+    }
+}
+
+/// Privacy check result
+#[derive(Debug, Clone)]
+struct PrivacyCheck {
+    /// Catalog key of the check's name
+    name: &'static str,
+    status: CheckStatus,
+    detail: String,
+}
+
+impl PrivacyCheck {
+    fn new(name: &'static str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Features compiled into this binary
+pub(crate) fn enabled_features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if cfg!(feature = "skills") {
+        features.push("skills");
+    }
+    if cfg!(feature = "tui") {
+        features.push("tui");
+    }
+    if cfg!(feature = "web") {
+        features.push("web");
+    }
+    if cfg!(feature = "exporter") {
+        features.push("exporter");
+    }
+    if cfg!(feature = "gpu") {
+        features.push("gpu");
+    }
+    features
+}
+
+/// Error tracking must be off, or only able to reach this machine
+fn check_error_tracking(tracking_enabled: bool, dsn: Option<&str>) -> PrivacyCheck {
+    match (tracking_enabled, dsn) {
+        (false, None) => PrivacyCheck::new(
+            "doctor.check_error_tracking",
+            CheckStatus::Pass,
+            t!("doctor.tracking_off"),
+        ),
+        (false, Some(_)) => PrivacyCheck::new(
+            "doctor.check_error_tracking",
+            CheckStatus::Pass,
+            t!("doctor.tracking_off_for_run"),
+        ),
+        (true, None) => PrivacyCheck::new(
+            "doctor.check_error_tracking",
+            CheckStatus::Pass,
+            t!("doctor.tracking_no_dsn"),
+        ),
+        (true, Some(dsn)) if is_local_dsn(dsn) => PrivacyCheck::new(
+            "doctor.check_error_tracking",
+            CheckStatus::Warn,
+            t!("doctor.tracking_local"),
+        ),
+        (true, Some(_)) => PrivacyCheck::new(
+            "doctor.check_error_tracking",
+            CheckStatus::Fail,
+            t!("doctor.tracking_remote"),
+        ),
+    }
+}
+
+/// The error tracking client must not have a transport
+fn check_error_client() -> PrivacyCheck {
+    let active = sentry::Hub::current()
+        .client()
+        .is_some_and(|client| client.is_enabled());
+    if active {
+        PrivacyCheck::new(
+            "doctor.check_error_client",
+            CheckStatus::Fail,
+            t!("doctor.client_active"),
+        )
+    } else {
+        PrivacyCheck::new(
+            "doctor.check_error_client",
+            CheckStatus::Pass,
+            t!("doctor.client_inactive"),
+        )
+    }
+}
+
+/// Server modes must not be listening
+fn check_server() -> PrivacyCheck {
+    let modes = server_modes();
+    if modes.is_empty() {
+        return PrivacyCheck::new(
+            "doctor.check_server",
+            CheckStatus::Pass,
+            t!("doctor.server_none"),
+        );
+    }
+
+    // Binding succeeds only if nothing (e.g. `dragonfly serve`) holds the port
+    let listening: Vec<String> = modes
+        .iter()
+        .filter(|mode| {
+            std::net::TcpListener::bind((std::net::Ipv4Addr::LOCALHOST, mode.port)).is_err()
+        })
+        .map(|mode| {
+            t!(
+                "doctor.server_port",
+                port = mode.port,
+                command = mode.command
+            )
+        })
+        .collect();
+    if listening.is_empty() {
+        let built: Vec<String> = modes
+            .iter()
+            .map(|mode| {
+                t!(
+                    "doctor.server_mode",
+                    name = t!(mode.name),
+                    binds = t!(mode.binds)
+                )
+            })
+            .collect();
+        let key = if built.len() == 1 {
+            "doctor.server_idle"
+        } else {
+            "doctor.servers_idle"
+        };
+        let modes = built.join(&format!(" {} ", t!("doctor.and")));
+        PrivacyCheck::new(
+            "doctor.check_server",
+            CheckStatus::Pass,
+            t!(key, modes = modes),
+        )
+    } else {
+        PrivacyCheck::new(
+            "doctor.check_server",
+            CheckStatus::Warn,
+            t!("doctor.server_listening", ports = listening.join("; ")),
+        )
+    }
+}
+
+/// Run the privacy checks
+fn run_privacy_checks(tracking_enabled: bool) -> Vec<PrivacyCheck> {
+    let config = load_config();
+    vec![
+        check_error_tracking(tracking_enabled, config.dsn.as_deref()),
+        check_error_client(),
+        check_server(),
+    ]
+}
+
+/// Report the build, and with `privacy` run the privacy audit and print the
+/// attestation
+///
+/// `tracking_enabled` is whether `--enable-error-tracking` was passed to this
+/// invocation.
+pub async fn handle_doctor(tracking_enabled: bool, privacy: bool, json: bool) -> Result<()> {
+    let features = enabled_features();
+    if !privacy {
+        return print_build(&features, json);
+    }
+
+    let checks = run_privacy_checks(tracking_enabled);
+    let no_network = checks.iter().all(|c| c.status == CheckStatus::Pass);
+
+    if json {
+        let json_output = json!({
+            "status": "ok",
+            "version": env!("CARGO_PKG_VERSION"),
+            "target": format!("{}-{}", std::env::consts::ARCH, std::env::consts::OS),
+            "features": features,
+            "checks": checks.iter().map(|c| json!({
+                "check": t!(c.name),
+                "status": c.status.as_str(),
+                "detail": c.detail
+            })).collect::<Vec<_>>(),
+            "no_network_activity": no_network,
+            "checked_at": chrono::Utc::now()
+        });
+        print_json(&json_output)?;
+        return Ok(());
+    }
+
+    println!("{}", t!("doctor.privacy_title").bold().bright_cyan());
+    println!(
+        "{}",
+        t!("doctor.version", version = env!("CARGO_PKG_VERSION"))
+    );
+    let features = if features.is_empty() {
+        t!("capabilities.none").to_string()
+    } else {
+        features.join(", ")
+    };
+    println!("{}", t!("doctor.features", features = features));
+    println!();
+
+    for check in &checks {
+        let icon = match check.status {
+            CheckStatus::Pass => "✅".green(),
+            CheckStatus::Warn => "⚠️ ".yellow(),
+            CheckStatus::Fail => "❌".red(),
+        };
+        println!("{} {}", icon, t!(check.name).bold());
+        println!("   {}", check.detail.dimmed());
+    }
+    println!();
+
+    if no_network {
+        println!("{}", t!("doctor.no_network").green().bold());
+    } else {
+        println!("{}", t!("doctor.network_possible").yellow().bold());
+    }
+
+    Ok(())
+}
+
+/// Print the version, target and features of this build, pointing at
+/// `--privacy` for the network audit
+fn print_build(features: &[&str], json: bool) -> Result<()> {
+    if json {
+        let json_output = json!({
+            "status": "ok",
+            "version": env!("CARGO_PKG_VERSION"),
+            "target": format!("{}-{}", std::env::consts::ARCH, std::env::consts::OS),
+            "features": features
+        });
+        print_json(&json_output)?;
+        return Ok(());
+    }
+
+    println!("{}", t!("doctor.title").bold().bright_cyan());
+    println!(
+        "{}",
+        t!("doctor.version", version = env!("CARGO_PKG_VERSION"))
+    );
+    println!(
+        "{}",
+        t!(
+            "doctor.target",
+            target = format!("{}-{}", std::env::consts::ARCH, std::env::consts::OS)
+        )
+    );
+    let features = if features.is_empty() {
+        t!("capabilities.none").to_string()
+    } else {
+        features.join(", ")
+    };
+    println!("{}", t!("doctor.features", features = features));
+    println!();
+    println!("{}", t!("doctor.privacy_hint").dimmed());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_tracking_check() {
+        assert_eq!(check_error_tracking(false, None).status, CheckStatus::Pass);
+        assert_eq!(
+            check_error_tracking(false, Some("https://k@remote.example/1")).status,
+            CheckStatus::Pass
+        );
+        assert_eq!(
+            check_error_tracking(true, Some("http://k@localhost:8000/1")).status,
+            CheckStatus::Warn
+        );
+        assert_eq!(
+            check_error_tracking(true, Some("https://k@remote.example/1")).status,
+            CheckStatus::Fail
+        );
+    }
+
+    #[test]
+    fn test_checks_are_named_from_the_catalog() {
+        for check in run_privacy_checks(false) {
+            assert_ne!(t!(check.name), check.name);
+        }
+        for mode in server_modes() {
+            assert_ne!(t!(mode.name), mode.name);
+            assert_ne!(t!(mode.binds), mode.binds);
+        }
+    }
+}
@@ -1,64 +1,1166 @@
 //! Duplicate files command handler
 
+use crate::commands::analyze::{
+    archive_to_recovery, incremental_analysis, parse_size, print_refresh,
+};
+use crate::commands::audit;
+use crate::error_tracking::{breadcrumb, breadcrumb_path};
 use crate::types::DuplicatesCommand;
-use anyhow::Result;
+use crate::ui::{
+    create_spinner, print_json, print_profile, print_skipped, profile_json, scan_progress_message,
+    show_byte_progress, write_duplicates_csv, write_duplicates_ndjson,
+};
+use anyhow::{bail, Context, Result};
 use colored::Colorize;
+use dragonfly_cleaner::{AuditAction, AuditEntry};
+use dragonfly_core::domain::entities::FileEntity;
+use dragonfly_core::domain::protection::PathProtection;
+use dragonfly_core::domain::scan_options::ScanOptions;
+use dragonfly_core::domain::value_objects::FilePath;
+use dragonfly_core::ports::{CancellationToken, ProgressUpdate};
+use dragonfly_core::t;
+use dragonfly_disk::{supports_clones, EmptyDirFinder};
+use dragonfly_duplicates::{
+    phase, DedupeMode, Deduplicator, DetectionStage, DetectionStrategy, DuplicateDetector,
+    DuplicateGroup, DuplicateResult, DuplicateStats, HashCache, KeepStrategy, ProtectedPaths,
+    SimilarityDetector, SkipReason,
+};
+use humansize::{format_size, DECIMAL};
+use indicatif::ProgressBar;
+use serde_json::json;
+use std::collections::HashSet;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
-pub async fn handle_duplicates(command: DuplicatesCommand, json: bool) -> Result<()> {
+pub async fn handle_duplicates(
+    command: DuplicatesCommand,
+    json: bool,
+    dry_run: bool,
+    cancel: &CancellationToken,
+) -> Result<()> {
     match command {
         DuplicatesCommand::Scan {
-            path,
+            paths,
             min_size,
-            dry_run,
             interactive,
+            delete,
+            keep,
+            strategy,
+            profile,
+            no_cache,
+            incremental,
+            excludes,
+            export,
+            verify,
+            media,
+            walk,
             json: cmd_json,
         } => {
             let output_json = json || cmd_json;
+            if interactive && output_json {
+                bail!(t!("duplicates.interactive_json"));
+            }
+            if incremental && walk.is_set() {
+                bail!(t!("disk.walk_options_with_index"));
+            }
+            let strategy = detection_strategy(&strategy, delete || interactive, verify)?;
+            let keep_strategy = keep_strategy(&keep)?;
+            let export_format = export.as_deref().map(export_format).transpose()?;
+            let min_bytes = min_bytes(min_size.as_deref())?;
+            let roots: Vec<FilePath> = paths
+                .iter()
+                .map(|path| FilePath::new(path.to_string_lossy().to_string()))
+                .collect();
+            let mut result = detect(
+                &roots,
+                min_bytes,
+                strategy,
+                &walk.scan_options().with_excludes(excludes),
+                !no_cache,
+                verify,
+                media,
+                incremental,
+                !output_json,
+                cancel,
+            )
+            .await?;
+            sort_groups(&mut result);
+
+            // Copy to keep per group; the suggestion of --keep until the user
+            // picks another interactively. `None` leaves the group alone.
+            let deleting = delete || interactive;
+            let mut kept: Vec<Option<usize>> = result
+                .duplicates
+                .iter()
+                .map(|group| {
+                    if deleting {
+                        keep_strategy.pick(&group.files)
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+
+            // The export suggests copies to keep even when nothing is removed
+            let export_report = |kept: &[Option<usize>]| -> Result<()> {
+                let (Some(path), Some(format)) = (&export, export_format) else {
+                    return Ok(());
+                };
+                let suggested: Vec<Option<usize>> = if deleting {
+                    kept.to_vec()
+                } else {
+                    result
+                        .duplicates
+                        .iter()
+                        .map(|group| keep_strategy.pick(&group.files))
+                        .collect()
+                };
+                write_report(path, format, &result.duplicates, &suggested)
+            };
+
             if output_json {
+                export_report(&kept)?;
+                let remove = redundant_copies(&result.duplicates, &kept);
+                let recovery = if !remove.is_empty() && !dry_run {
+                    Some(archive_to_recovery(
+                        remove.clone(),
+                        "duplicate",
+                        "duplicates scan",
+                    )?)
+                } else {
+                    None
+                };
+                return print_json(&json!({
+                    "status": "ok",
+                    "path": roots[0].as_str(),
+                    "roots": roots.iter().map(FilePath::as_str).collect::<Vec<_>>(),
+                    "min_size_bytes": min_bytes,
+                    "strategy": strategy,
+                    "dry_run": dry_run,
+                    "group_count": result.duplicates.len(),
+                    "potential_savings": result.potential_savings,
+                    "groups": result.duplicates.iter().zip(&kept).map(|(group, keep)| json!({
+                        "size": group.files[0].size,
+                        "hash": group.hash,
+                        "wasted": wasted(group),
+                        "confirmed_by": group.confirmed_by,
+                        "verification": group.verification,
+                        "keep": keep.map(|k| &group.files[k].path),
+                        "files": group.files.iter().map(|f| json!({
+                            "path": f.path,
+                            "root": f.root.map(|root| roots[root].as_str()),
+                            "protection": f.protection
+                        })).collect::<Vec<_>>()
+                    })).collect::<Vec<_>>(),
+                    "delete": delete.then(|| json!({
+                        "keep": keep,
+                        "files": remove,
+                        "removed": recovery.as_ref().map_or(0, |(_, moved)| *moved),
+                        "recovery_id": recovery.map(|(id, _)| id)
+                    })),
+                    "skipped": result.skipped,
+                    "interrupted": result.interrupted,
+                    "cache_hits": result.cache_hits,
+                    "profile": profile.then(|| profile_json(&result.profile))
+                }));
+            }
+
+            println!("{}", t!("duplicates.title").bold().bright_cyan());
+            if dry_run {
+                println!("{}", t!("common.dry_run").yellow());
+            }
+            if result.interrupted {
+                println!("{}", t!("common.interrupted").yellow());
+            }
+            for root in &roots {
+                println!("{}", t!("duplicates.path", path = root.as_str()));
+            }
+            if let Some(ref ms) = min_size {
+                println!("{}", t!("duplicates.min_size", size = ms));
+            }
+            if result.duplicates.is_empty() {
+                println!("\n{}", t!("duplicates.none"));
+            } else {
                 println!(
-                    r#"{{"status":"ok","message":"Duplicate scan (MVP stub)","path":"{}","min_size":"{:?}","dry_run":{},"interactive":{}}}"#,
-                    path.display(),
-                    min_size,
-                    dry_run,
-                    interactive
+                    "{}",
+                    t!(
+                        "duplicates.summary",
+                        groups = result.duplicates.len(),
+                        size = format_size(result.potential_savings, DECIMAL).bold()
+                    )
                 );
-            } else {
-                println!("{}", "Duplicate File Scanner".bold().bright_cyan());
-                println!("Path: {}", path.display());
-                if let Some(ref ms) = min_size {
-                    println!("Minimum size: {}", ms);
+            }
+
+            for (i, group) in result.duplicates.iter().enumerate() {
+                print_group(i + 1, group, kept[i]);
+                if interactive {
+                    match ask_keep(group, kept[i].unwrap_or(0))? {
+                        Choice::Keep(keep) => kept[i] = Some(keep),
+                        Choice::KeepAll => kept[i] = None,
+                        Choice::Stop => {
+                            kept[i..].fill(None);
+                            break;
+                        }
+                    }
                 }
+            }
+            print_skipped(&result.skipped);
+            export_report(&kept)?;
+            if let Some(path) = &export {
+                println!(
+                    "\n{}",
+                    t!("duplicates.exported", path = path.display()).green()
+                );
+            }
+
+            let remove = redundant_copies(&result.duplicates, &kept);
+            if !remove.is_empty() {
                 if dry_run {
-                    println!("{}", "Mode: Dry run".yellow());
+                    println!("\n{}", t!("disk.would_archive", count = remove.len()));
+                } else {
+                    let (recovery_id, moved) =
+                        archive_to_recovery(remove, "duplicate", "duplicates scan")?;
+                    println!(
+                        "\n{}",
+                        t!("duplicates.moved", count = moved, id = recovery_id).green()
+                    );
                 }
-                if interactive {
-                    println!("{}", "Mode: Interactive".cyan());
+            }
+            if profile {
+                print_profile(&result.profile);
+                println!(
+                    "{}",
+                    t!("duplicates.cache_hits", count = result.cache_hits).dimmed()
+                );
+            }
+        }
+        DuplicatesCommand::Dedupe {
+            path,
+            mode,
+            keep,
+            min_size,
+            no_cache,
+            excludes,
+            walk,
+            json: cmd_json,
+        } => {
+            let output_json = json || cmd_json;
+            let mode = match mode.as_str() {
+                "clone" => DedupeMode::Clone,
+                "hardlink" => DedupeMode::Hardlink,
+                other => bail!("{}", t!("duplicates.unknown_mode", mode = other)),
+            };
+            let keep_strategy = keep_strategy(&keep)?;
+            if mode == DedupeMode::Clone && !supports_clones(&path).unwrap_or(false) {
+                bail!(t!("duplicates.clones_unsupported"));
+            }
+            let min_bytes = min_bytes(min_size.as_deref())?;
+            let file_path = FilePath::new(path.to_string_lossy().to_string());
+            let mut result = detect(
+                std::slice::from_ref(&file_path),
+                min_bytes,
+                DetectionStrategy::Full,
+                &walk.scan_options().with_excludes(excludes),
+                !no_cache,
+                false,
+                false,
+                false,
+                !output_json,
+                cancel,
+            )
+            .await?;
+            sort_groups(&mut result);
+
+            let deduplicator = Deduplicator::new(mode);
+            let mut plan = deduplicator.plan(&result.duplicates, keep_strategy);
+            // Only the copies are modified; the policy decides which may be
+            let allowed: HashSet<PathBuf> =
+                audit::allowed_by_policy(plan.actions.iter().map(|a| Path::new(&a.copy)))
+                    .into_iter()
+                    .map(Path::to_path_buf)
+                    .collect();
+            plan.actions
+                .retain(|action| allowed.contains(Path::new(&action.copy)));
+
+            let report = if dry_run || plan.actions.is_empty() {
+                None
+            } else {
+                let report = deduplicator.apply(&plan.actions, cancel);
+                audit::record(
+                    AuditEntry::new(AuditAction::Dedupe, audit::command_line())
+                        .with_paths(
+                            report
+                                .replaced
+                                .iter()
+                                .map(|a| PathBuf::from(&a.copy))
+                                .collect(),
+                        )
+                        .with_items(report.replaced.len())
+                        .with_bytes(report.reclaimed),
+                );
+                Some(report)
+            };
+
+            if output_json {
+                return print_json(&json!({
+                    "status": "ok",
+                    "path": file_path.as_str(),
+                    "mode": mode,
+                    "keep": keep,
+                    "min_size_bytes": min_bytes,
+                    "dry_run": dry_run,
+                    "plan": plan,
+                    "reclaimable": plan.reclaimable(),
+                    "report": report,
+                    "skipped": result.skipped,
+                    "interrupted": result.interrupted
+                        || report.as_ref().is_some_and(|r| r.interrupted)
+                }));
+            }
+
+            println!("{}", t!("duplicates.dedupe_title").bold().bright_cyan());
+            if dry_run {
+                println!("{}", t!("common.dry_run").yellow());
+            }
+            if result.interrupted || report.as_ref().is_some_and(|r| r.interrupted) {
+                println!("{}", t!("common.interrupted").yellow());
+            }
+            println!("{}", t!("duplicates.path", path = file_path.as_str()));
+            if mode == DedupeMode::Hardlink {
+                println!("{}", t!("duplicates.hardlink_note").yellow());
+            }
+            if plan.actions.is_empty() && plan.skipped.is_empty() {
+                println!("\n{}", t!("duplicates.none"));
+            }
+
+            let (replaced, skipped) = match &report {
+                Some(report) => (
+                    &report.replaced,
+                    plan.skipped
+                        .iter()
+                        .chain(&report.skipped)
+                        .collect::<Vec<_>>(),
+                ),
+                None => (&plan.actions, plan.skipped.iter().collect()),
+            };
+            if !replaced.is_empty() {
+                println!();
+            }
+            for action in replaced {
+                println!(
+                    "  {} {} {} ({})",
+                    action.copy,
+                    "->".dimmed(),
+                    action.original,
+                    format_size(action.freed, DECIMAL)
+                );
+            }
+            if !skipped.is_empty() {
+                println!("\n{}", t!("duplicates.dedupe_left_alone").bold());
+            }
+            for skip in skipped {
+                println!("  {} - {}", skip.path, skip_reason(&skip.reason).dimmed());
+            }
+            print_skipped(&result.skipped);
+
+            let key = match (&report, mode) {
+                (None, DedupeMode::Clone) => "duplicates.would_clone",
+                (None, DedupeMode::Hardlink) => "duplicates.would_link",
+                (Some(_), DedupeMode::Clone) => "duplicates.cloned",
+                (Some(_), DedupeMode::Hardlink) => "duplicates.linked",
+            };
+            let reclaimed = report
+                .as_ref()
+                .map_or(plan.reclaimable(), |report| report.reclaimed);
+            if !replaced.is_empty() {
+                println!(
+                    "\n{}",
+                    t!(
+                        key,
+                        count = replaced.len(),
+                        size = format_size(reclaimed, DECIMAL).bold()
+                    )
+                    .green()
+                );
+            }
+        }
+        DuplicatesCommand::Images {
+            path,
+            threshold,
+            min_size,
+            walk,
+            json: cmd_json,
+        } => {
+            let output_json = json || cmd_json;
+            let min_bytes = min_bytes(min_size.as_deref())?;
+            let spinner = (!output_json).then(|| {
+                let spinner = create_spinner(t!("duplicates.images_scanning"));
+                spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+                spinner
+            });
+            breadcrumb(
+                "scan",
+                "Similar image scan started",
+                &[("path", breadcrumb_path(&path))],
+            );
+            let result = SimilarityDetector::new()
+                .with_threshold(threshold)
+                .find_similar(&path, min_bytes, &walk.scan_options(), cancel)
+                .context("Failed to search for similar images");
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+            let result = result?;
+
+            if output_json {
+                return print_json(&json!({
+                    "status": "ok",
+                    "path": path,
+                    "threshold": threshold,
+                    "min_size_bytes": min_bytes,
+                    "images": result.images,
+                    "group_count": result.groups.len(),
+                    "groups": result.groups,
+                    "undecodable": result.undecodable,
+                    "skipped": result.skipped,
+                    "interrupted": result.interrupted
+                }));
+            }
+
+            println!("{}", t!("duplicates.images_title").bold().bright_cyan());
+            if result.interrupted {
+                println!("{}", t!("common.interrupted").yellow());
+            }
+            println!("{}", t!("duplicates.path", path = path.display()));
+            println!(
+                "{}",
+                t!(
+                    "duplicates.images_summary",
+                    images = result.images,
+                    groups = result.groups.len()
+                )
+            );
+            if result.groups.is_empty() {
+                println!("\n{}", t!("duplicates.images_none"));
+            }
+            for (i, group) in result.groups.iter().enumerate() {
+                println!(
+                    "\n{:3}. {}",
+                    i + 1,
+                    t!("duplicates.images_group", count = group.images.len()).bold()
+                );
+                for (j, image) in group.images.iter().enumerate() {
+                    let distance = match (j, image.distance) {
+                        (0, _) => t!("duplicates.images_reference").to_string(),
+                        (_, 0) => t!("duplicates.images_identical").to_string(),
+                        (_, bits) => t!("duplicates.images_distance", bits = bits).to_string(),
+                    };
+                    println!(
+                        "     {} {} {}",
+                        image.path,
+                        format!("({})", format_size(image.size, DECIMAL)).dimmed(),
+                        distance.dimmed()
+                    );
                 }
+            }
+            if !result.undecodable.is_empty() {
                 println!(
                     "\n{}",
-                    "This is an MVP stub. Full implementation coming soon.".dimmed()
+                    t!(
+                        "duplicates.images_undecodable",
+                        count = result.undecodable.len()
+                    )
+                    .yellow()
                 );
             }
+            print_skipped(&result.skipped);
         }
         DuplicatesCommand::Stats {
             path,
+            min_size,
+            no_cache,
+            walk,
             json: cmd_json,
         } => {
             let output_json = json || cmd_json;
+            let min_bytes = min_bytes(min_size.as_deref())?;
+            let file_path = FilePath::new(path.to_string_lossy().to_string());
+            let result = detect(
+                std::slice::from_ref(&file_path),
+                min_bytes,
+                DetectionStrategy::Full,
+                &walk.scan_options(),
+                !no_cache,
+                false,
+                false,
+                false,
+                !output_json,
+                cancel,
+            )
+            .await?;
+            let stats = DuplicateStats::from_groups(&result.duplicates);
+
             if output_json {
+                return print_json(&json!({
+                    "status": "ok",
+                    "path": file_path.as_str(),
+                    "min_size_bytes": min_bytes,
+                    "stats": stats,
+                    "skipped": result.skipped,
+                    "interrupted": result.interrupted
+                }));
+            }
+
+            println!("{}", t!("duplicates.stats_title").bold().bright_cyan());
+            if result.interrupted {
+                println!("{}", t!("common.interrupted").yellow());
+            }
+            println!("{}", t!("duplicates.path", path = file_path.as_str()));
+            if let Some(ref ms) = min_size {
+                println!("{}", t!("duplicates.min_size", size = ms));
+            }
+            let Some(largest) = &stats.largest_group else {
+                println!("\n{}", t!("duplicates.none"));
+                print_skipped(&result.skipped);
+                return Ok(());
+            };
+            println!(
+                "\n{}",
+                t!(
+                    "duplicates.stats_totals",
+                    groups = stats.group_count,
+                    files = stats.duplicate_files,
+                    size = format_size(stats.wasted_bytes, DECIMAL).bold()
+                )
+            );
+            println!(
+                "{}",
+                t!(
+                    "duplicates.stats_largest",
+                    size = format_size(largest.wasted, DECIMAL),
+                    copies = largest.copies,
+                    path = largest.path
+                )
+            );
+
+            println!("\n{}", t!("duplicates.stats_by_type").bold());
+            for usage in &stats.by_type {
+                let extension = if usage.extension.is_empty() {
+                    t!("duplicates.stats_no_extension").to_string()
+                } else {
+                    format!(".{}", usage.extension)
+                };
+                println!(
+                    "  {:<12} {:>10}  {}",
+                    extension,
+                    format_size(usage.wasted, DECIMAL),
+                    t!("duplicates.stats_groups", count = usage.groups).dimmed()
+                );
+            }
+
+            println!("\n{}", t!("duplicates.stats_most_duplicated").bold());
+            for (i, summary) in stats.most_duplicated.iter().enumerate() {
                 println!(
-                    r#"{{"status":"ok","message":"Duplicate statistics (MVP stub)","path":"{}"}}"#,
-                    path.display()
+                    "{:3}. {} x {} - {}",
+                    i + 1,
+                    summary.copies,
+                    format_size(summary.size, DECIMAL),
+                    summary.path
                 );
+            }
+            print_skipped(&result.skipped);
+        }
+        DuplicatesCommand::Empties {
+            path,
+            delete,
+            json: cmd_json,
+        } => {
+            let output_json = json || cmd_json;
+            let spinner = (!output_json).then(|| create_spinner(t!("duplicates.empties_scanning")));
+            breadcrumb(
+                "scan",
+                "Empty file scan started",
+                &[("path", breadcrumb_path(&path))],
+            );
+            let report = EmptyDirFinder::new()
+                .with_files(true)
+                .find(&path, cancel)
+                .await
+                .context("Failed to search for empty files and folders")?;
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+
+            // Files first, then each tree deepest first, so every folder is
+            // empty by the time it is archived
+            let remove: Vec<PathBuf> = report
+                .files
+                .iter()
+                .filter(|file| !file.protection.is_protected())
+                .map(|file| file.path.clone())
+                .chain(
+                    report
+                        .trees
+                        .iter()
+                        .filter(|tree| !tree.protection.is_protected())
+                        .flat_map(|tree| tree_contents(&tree.path)),
+                )
+                .collect();
+            let delete = delete && !report.interrupted;
+            let recovery = if delete && !dry_run && !remove.is_empty() {
+                Some(archive_to_recovery(
+                    remove.iter().map(PathBuf::as_path),
+                    "empty",
+                    "duplicates empties",
+                )?)
             } else {
-                println!("{}", "Duplicate Statistics".bold().bright_cyan());
-                println!("Path: {}", path.display());
+                None
+            };
+
+            if output_json {
+                return print_json(&json!({
+                    "status": "ok",
+                    "path": report.root,
+                    "files": report.files,
+                    "trees": report.trees,
+                    "dry_run": dry_run,
+                    "removed": recovery.as_ref().map(|(_, moved)| moved),
+                    "recovery_id": recovery.as_ref().map(|(id, _)| id),
+                    "skipped": report.skipped,
+                    "interrupted": report.interrupted
+                }));
+            }
+
+            println!("{}", t!("duplicates.empties_title").bold().bright_cyan());
+            if delete && dry_run {
+                println!("{}", t!("common.dry_run").yellow());
+            }
+            if report.interrupted {
+                println!("{}", t!("common.interrupted").yellow());
+            }
+            println!("{}", t!("duplicates.path", path = report.root.display()));
+            if report.files.is_empty() && report.trees.is_empty() {
+                println!("\n{}", t!("duplicates.empties_none"));
+                print_skipped(&report.skipped);
+                return Ok(());
+            }
+            let marker = |protection: PathProtection| {
+                if protection.is_protected() {
+                    format!(" {}", t!("common.protected")).yellow().to_string()
+                } else {
+                    String::new()
+                }
+            };
+            if !report.files.is_empty() {
+                println!(
+                    "\n{}",
+                    t!("duplicates.empties_files", count = report.files.len()).bold()
+                );
+                for file in &report.files {
+                    println!("  {}{}", file.path.display(), marker(file.protection));
+                }
+            }
+            if !report.trees.is_empty() {
+                println!(
+                    "\n{}",
+                    t!("duplicates.empties_trees", count = report.trees.len()).bold()
+                );
+                for tree in &report.trees {
+                    let line = t!(
+                        "duplicates.empties_tree",
+                        path = tree.path.display(),
+                        dirs = tree.dirs,
+                        depth = tree.depth
+                    );
+                    println!("  {}{}", line, marker(tree.protection));
+                }
+            }
+            if let Some((recovery_id, moved)) = recovery {
                 println!(
                     "\n{}",
-                    "This is an MVP stub. Full implementation coming soon.".dimmed()
+                    t!("duplicates.empties_moved", count = moved, id = recovery_id).green()
                 );
+            } else if delete && dry_run {
+                println!("\n{}", t!("disk.would_archive", count = remove.len()));
+            } else if !delete && !report.interrupted && !remove.is_empty() {
+                println!("\n{}", t!("duplicates.empties_hint").dimmed());
             }
+            print_skipped(&report.skipped);
         }
     }
     Ok(())
 }
+
+/// `top` and everything in it, deepest first; entries that can't be read
+/// are left out, so archiving stops short of folders holding them
+fn tree_contents(top: &Path) -> Vec<PathBuf> {
+    let mut contents: Vec<PathBuf> = std::fs::read_dir(top)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .flat_map(|entry| match entry.file_type() {
+            Ok(kind) if kind.is_dir() => tree_contents(&entry.path()),
+            _ => vec![entry.path()],
+        })
+        .collect();
+    contents.push(top.to_path_buf());
+    contents
+}
+
+/// Smallest file size to consider; empty files are all alike and free
+/// nothing, so they're left out unless asked for
+fn min_bytes(min_size: Option<&str>) -> Result<u64> {
+    match min_size {
+        Some(min_size) => {
+            parse_size(min_size).with_context(|| format!("Invalid size format: {}", min_size))
+        }
+        None => Ok(1),
+    }
+}
+
+/// Run the detector, reusing cached hashes if `cache` is set, comparing
+/// groups byte by byte if `verify` is, media without their tags if `media`
+/// is, listing files from the size index if
+/// `incremental` is, and with a progress bar if `spinner` is
+///
+/// The bar spins while files are found, then fills with the bytes of each
+/// hashing phase and estimates the time left.
+#[allow(clippy::too_many_arguments)]
+async fn detect(
+    roots: &[FilePath],
+    min_bytes: u64,
+    strategy: DetectionStrategy,
+    options: &ScanOptions,
+    cache: bool,
+    verify: bool,
+    media: bool,
+    incremental: bool,
+    spinner: bool,
+    cancel: &CancellationToken,
+) -> Result<DuplicateResult> {
+    let listed = if incremental {
+        Some(indexed_files(roots, min_bytes, options, !spinner, cancel)?)
+    } else {
+        None
+    };
+    let spinner = spinner.then(|| {
+        let spinner = create_spinner(t!("duplicates.scanning"));
+        spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+        spinner
+    });
+    breadcrumb(
+        "scan",
+        "Duplicate scan started",
+        &[("path", breadcrumb_path(Path::new(roots[0].as_str())))],
+    );
+    let mut detector = DuplicateDetector::new()
+        .with_strategy(strategy)
+        .with_verification(verify)
+        .with_media(media)
+        .with_protected(protected_paths()?);
+    if let Some(spinner) = &spinner {
+        detector = detector.with_progress(Arc::new(progress_reporter(spinner.clone())));
+    }
+    if cache {
+        // Without the cache the scan is slower, not wrong
+        match HashCache::open(&HashCache::default_dir()) {
+            Ok(cache) => detector = detector.with_cache(Arc::new(cache)),
+            Err(e) => tracing::warn!(error = %e, "Failed to open the hash cache"),
+        }
+    }
+    let result = match listed {
+        Some((files, interrupted)) => {
+            detector
+                .find_duplicates_among(files, cancel)
+                .await
+                .map(|mut result| {
+                    result.interrupted |= interrupted;
+                    result
+                })
+        }
+        None => {
+            detector
+                .find_duplicates_in_roots(roots, min_bytes, options, cancel)
+                .await
+        }
+    }
+    .context("Failed to search for duplicates");
+    if let Some(spinner) = spinner {
+        spinner.finish_and_clear();
+    }
+    result
+}
+
+/// Reporter showing a scan's phase and progress on `bar`
+fn progress_reporter(bar: ProgressBar) -> impl Fn(&ProgressUpdate) + Send + Sync {
+    let current = Mutex::new(None);
+    move |update: &ProgressUpdate| {
+        let mut current = current.lock().unwrap_or_else(|e| e.into_inner());
+        if *current != update.phase {
+            *current = update.phase;
+            if let Some(total) = update.bytes_total {
+                show_byte_progress(&bar, total);
+            }
+        }
+        let label = update.phase.map(phase_label).unwrap_or_default();
+        if update.bytes_total.is_some() {
+            bar.set_position(update.bytes_processed);
+            bar.set_message(label);
+        } else {
+            bar.set_message(format!("{label}: {}", scan_progress_message(update)));
+        }
+    }
+}
+
+/// What the user sees for a detector [`phase`]
+fn phase_label(name: &str) -> &str {
+    match name {
+        phase::WALK => t!("duplicates.phase_walk"),
+        phase::PARTIAL_HASH => t!("duplicates.phase_partial_hash"),
+        phase::FULL_HASH => t!("duplicates.phase_full_hash"),
+        phase::VERIFY => t!("duplicates.phase_verify"),
+        phase::MEDIA_HASH => t!("duplicates.phase_media_hash"),
+        other => other,
+    }
+}
+
+/// Files of at least `min_bytes` under `roots` from the size index, after
+/// bringing it up to date, and whether a refresh was cancelled
+///
+/// Indexed paths are canonical. With several roots each file is marked with
+/// the first one it lies under.
+fn indexed_files(
+    roots: &[FilePath],
+    min_bytes: u64,
+    options: &ScanOptions,
+    json: bool,
+    cancel: &CancellationToken,
+) -> Result<(Vec<FileEntity>, bool)> {
+    let mut files = Vec::new();
+    let mut interrupted = false;
+    let mut seen = HashSet::new();
+    for (index, root) in roots.iter().enumerate() {
+        let (analysis, refresh) = incremental_analysis(Path::new(root.as_str()), json, cancel)?;
+        if let (Some(refresh), false) = (refresh, json) {
+            print_refresh(&refresh);
+        }
+        interrupted |= analysis.interrupted;
+        files.extend(
+            analysis
+                .files
+                .into_iter()
+                .filter(|f| f.size >= min_bytes && !options.is_excluded(&f.path))
+                .filter(|f| seen.insert(f.path.clone()))
+                .map(|mut f| {
+                    f.root = (roots.len() > 1).then_some(index);
+                    f
+                }),
+        );
+    }
+    Ok((files, interrupted))
+}
+
+/// Locations duplicate scans keep, from `duplicates_protect` in
+/// `~/.dragonfly/config` or the built-in list
+///
+/// A list that can't be read is an error, so a typo never exposes a Photos
+/// library to deletion.
+fn protected_paths() -> Result<ProtectedPaths> {
+    let Some(home) = dirs::home_dir() else {
+        return Ok(ProtectedPaths::default());
+    };
+    let path = home.join(".dragonfly").join("config");
+    let config = match std::fs::read_to_string(&path) {
+        Ok(config) => config,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    ProtectedPaths::from_config(&config, &home.to_string_lossy())
+        .with_context(|| format!("Invalid duplicates_protect list in {}", path.display()))
+}
+
+/// Formats of `--export`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExportFormat {
+    Csv,
+    Ndjson,
+}
+
+/// Export format for the extension of `path`
+fn export_format(path: &Path) -> Result<ExportFormat> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    Ok(match extension.as_str() {
+        "csv" => ExportFormat::Csv,
+        "ndjson" | "jsonl" => ExportFormat::Ndjson,
+        _ => bail!(t!("duplicates.export_extension")),
+    })
+}
+
+/// Write the report of `groups`, keeping the copies in `kept`, to `path`
+fn write_report(
+    path: &Path,
+    format: ExportFormat,
+    groups: &[DuplicateGroup],
+    kept: &[Option<usize>],
+) -> Result<()> {
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut out = std::io::BufWriter::new(file);
+    match format {
+        ExportFormat::Csv => write_duplicates_csv(&mut out, groups, kept),
+        ExportFormat::Ndjson => write_duplicates_ndjson(&mut out, groups, kept),
+    }
+    .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Detection strategy named on the command line
+///
+/// Partial matches only compared the ends of files, so removing copies on
+/// their word needs `--verify` to compare the rest first.
+fn detection_strategy(name: &str, deleting: bool, verify: bool) -> Result<DetectionStrategy> {
+    let strategy = match name {
+        "full" => DetectionStrategy::Full,
+        "partial" => DetectionStrategy::Partial,
+        other => bail!("{}", t!("duplicates.unknown_strategy", strategy = other)),
+    };
+    if strategy == DetectionStrategy::Partial && deleting && !verify {
+        bail!(t!("duplicates.partial_delete"));
+    }
+    Ok(strategy)
+}
+
+/// Keep strategy named on the command line
+fn keep_strategy(keep: &str) -> Result<KeepStrategy> {
+    Ok(match keep {
+        "oldest" => KeepStrategy::Oldest,
+        "newest" => KeepStrategy::Newest,
+        "shortest-path" => KeepStrategy::ShortestPath,
+        "first" => KeepStrategy::First,
+        "primary" => KeepStrategy::Primary,
+        other => bail!("{}", t!("duplicates.unknown_keep", keep = other)),
+    })
+}
+
+/// Explanation of why dedupe left a copy alone
+fn skip_reason(reason: &SkipReason) -> String {
+    match reason {
+        SkipReason::Protected => t!("duplicates.skip_protected").to_string(),
+        SkipReason::AlreadyShared => t!("duplicates.skip_already_shared").to_string(),
+        SkipReason::OtherVolume => t!("duplicates.skip_other_volume").to_string(),
+        SkipReason::DifferentMetadata => t!("duplicates.skip_different_metadata").to_string(),
+        SkipReason::Changed => t!("duplicates.skip_changed").to_string(),
+        SkipReason::Failed(error) => error.clone(),
+    }
+}
+
+/// Space the copies beyond the first take
+fn wasted(group: &DuplicateGroup) -> u64 {
+    DuplicateDetector::calculate_savings(std::slice::from_ref(group))
+}
+
+/// Order groups by the space they waste and their files by path
+fn sort_groups(result: &mut DuplicateResult) {
+    for group in &mut result.duplicates {
+        group.files.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+    result.duplicates.sort_by(|a, b| {
+        wasted(b)
+            .cmp(&wasted(a))
+            .then_with(|| a.files[0].path.cmp(&b.files[0].path))
+    });
+}
+
+/// Answer to the question which copy of a group to keep
+enum Choice {
+    /// Keep the copy at this index, remove the others
+    Keep(usize),
+    /// Keep every copy
+    KeepAll,
+    /// Leave this and the remaining groups alone
+    Stop,
+}
+
+/// Unprotected copies of each group other than the one `kept`
+fn redundant_copies<'a>(groups: &'a [DuplicateGroup], kept: &[Option<usize>]) -> Vec<&'a Path> {
+    groups
+        .iter()
+        .zip(kept)
+        .filter_map(|(group, keep)| keep.map(|keep| (group, keep)))
+        .flat_map(|(group, keep)| {
+            group
+                .files
+                .iter()
+                .enumerate()
+                .filter(move |&(i, f)| i != keep && !f.protection.is_protected())
+                .map(|(_, f)| Path::new(&f.path))
+        })
+        .collect()
+}
+
+fn print_group(number: usize, group: &DuplicateGroup, keep: Option<usize>) {
+    let partial = match group.confirmed_by {
+        DetectionStage::PartialHash => format!(" {}", t!("duplicates.partial_match").yellow()),
+        DetectionStage::MediaHash => format!(" {}", t!("duplicates.media_match").yellow()),
+        DetectionStage::FullHash | DetectionStage::ByteComparison => String::new(),
+    };
+    println!(
+        "\n{:3}. {} x {} - {}{}",
+        number,
+        group.files.len(),
+        format_size(group.files[0].size, DECIMAL),
+        t!(
+            "duplicates.wasted",
+            size = format_size(wasted(group), DECIMAL)
+        )
+        .bold(),
+        partial
+    );
+    for (i, file) in group.files.iter().enumerate() {
+        let marker = if file.protection.is_protected() {
+            format!(" {}", t!("common.protected")).yellow().to_string()
+        } else if keep == Some(i) {
+            format!(" {}", t!("duplicates.keep_marker"))
+                .green()
+                .to_string()
+        } else {
+            String::new()
+        };
+        println!("     [{}] {}{}", i + 1, file.path, marker);
+    }
+    if let Some(verification) = &group.verification {
+        if !verification.mismatched.is_empty() {
+            println!(
+                "     {}",
+                t!(
+                    "duplicates.verify_mismatch",
+                    count = verification.mismatched.len()
+                )
+                .yellow()
+            );
+        }
+        if !verification.unreadable.is_empty() {
+            println!(
+                "     {}",
+                t!(
+                    "duplicates.verify_unreadable",
+                    count = verification.unreadable.len()
+                )
+                .yellow()
+            );
+        }
+    }
+}
+
+/// Ask which copy of `group` to keep, `suggested` if the answer is empty
+fn ask_keep(group: &DuplicateGroup, suggested: usize) -> Result<Choice> {
+    let stdin = std::io::stdin();
+    loop {
+        print!(
+            "{} ",
+            t!(
+                "duplicates.keep_prompt",
+                count = group.files.len(),
+                suggested = suggested + 1
+            )
+            .cyan()
+        );
+        std::io::stdout().flush()?;
+        let mut answer = String::new();
+        if stdin.lock().read_line(&mut answer)? == 0 {
+            return Ok(Choice::Stop);
+        }
+        match answer.trim() {
+            "" => return Ok(Choice::Keep(suggested)),
+            "s" => return Ok(Choice::KeepAll),
+            "q" => return Ok(Choice::Stop),
+            number => match number.parse::<usize>() {
+                Ok(keep) if (1..=group.files.len()).contains(&keep) => {
+                    return Ok(Choice::Keep(keep - 1))
+                }
+                _ => println!("{}", t!("duplicates.keep_invalid").yellow()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(paths: &[&str]) -> DuplicateGroup {
+        DuplicateGroup {
+            files: paths
+                .iter()
+                .map(|path| FileEntity::new((*path).to_string(), 10))
+                .collect(),
+            hash: "hash".to_string(),
+            confirmed_by: DetectionStage::FullHash,
+            verification: None,
+        }
+    }
+
+    #[test]
+    fn test_redundant_copies_skip_kept_protected_and_untouched_groups() {
+        let mut preserved = group(&["/data/a.txt", "/data/b.txt", "/data/c.txt"]);
+        preserved.files[2].protection = PathProtection::Preserved;
+        let groups = vec![
+            preserved,
+            group(&["/data/d.txt", "/data/e.txt"]),
+            group(&["/data/f.txt", "/data/g.txt"]),
+        ];
+
+        let remove = redundant_copies(&groups, &[Some(0), None, Some(1)]);
+        assert_eq!(
+            remove,
+            vec![Path::new("/data/b.txt"), Path::new("/data/f.txt")]
+        );
+        assert!(redundant_copies(&groups, &[None, None, None]).is_empty());
+    }
+
+    #[test]
+    fn test_keep_strategy_rejects_unknown_names() {
+        assert_eq!(keep_strategy("oldest").unwrap(), KeepStrategy::Oldest);
+        assert_eq!(
+            keep_strategy("shortest-path").unwrap(),
+            KeepStrategy::ShortestPath
+        );
+        assert!(keep_strategy("largest").is_err());
+        assert!(keep_strategy("").is_err());
+    }
+
+    #[test]
+    fn test_partial_strategy_needs_verify_to_delete() {
+        assert_eq!(
+            detection_strategy("partial", false, false).unwrap(),
+            DetectionStrategy::Partial
+        );
+        assert!(detection_strategy("partial", true, false).is_err());
+        assert_eq!(
+            detection_strategy("partial", true, true).unwrap(),
+            DetectionStrategy::Partial
+        );
+        assert_eq!(
+            detection_strategy("full", true, false).unwrap(),
+            DetectionStrategy::Full
+        );
+        assert!(detection_strategy("quick", false, false).is_err());
+    }
+
+    #[test]
+    fn test_export_format_by_extension() {
+        assert_eq!(
+            export_format(Path::new("report.CSV")).unwrap(),
+            ExportFormat::Csv
+        );
+        assert_eq!(
+            export_format(Path::new("report.jsonl")).unwrap(),
+            ExportFormat::Ndjson
+        );
+        assert!(export_format(Path::new("report.json")).is_err());
+        assert!(export_format(Path::new("report")).is_err());
+    }
+}
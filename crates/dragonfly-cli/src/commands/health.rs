@@ -1,11 +1,24 @@
 //! System health check command handler
 
+use crate::commands::monitor;
+use crate::ui::print_json;
 use anyhow::Result;
 use colored::Colorize;
-use dragonfly_monitor::{MetricsCollector, SystemMetrics};
+use dragonfly_core::domain::protection::{PRIVACY_PROTECTED_LOCATIONS, SIP_PROTECTED_ROOTS};
+use dragonfly_core::t;
+use dragonfly_monitor::{
+    ChargingState, MemoryBreakdown, MemoryPressure, MetricsCollector, SystemMetrics, TopProcesses,
+    CPU_SAMPLE_INTERVAL,
+};
 use humansize::{format_size, DECIMAL};
 use serde_json::json;
 
+/// Processes listed in JSON output with `--recommend`
+const PROCESSES_SHOWN: usize = 5;
+
+/// Charge cycles most laptop batteries are rated for before they wear out
+const BATTERY_CYCLE_LIMIT: u32 = 1000;
+
 /// Health status for a component
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum HealthStatus {
@@ -39,112 +52,176 @@ impl ComponentHealth {
     }
 }
 
-/// Check CPU health
-fn check_cpu(metrics: &SystemMetrics) -> ComponentHealth {
+/// `advice`, led by `culprit` naming the process behind the problem if one
+/// is known
+fn advise(culprit: Option<String>, advice: &str) -> String {
+    match culprit {
+        Some(culprit) => format!("{culprit}. {advice}"),
+        None => advice.to_string(),
+    }
+}
+
+/// "Name is using 250% CPU" for the busiest process, and how to follow it
+fn busiest(top: Option<&TopProcesses>) -> Option<String> {
+    let process = top?.by_cpu.first()?;
+    Some(t!(
+        "health.busiest",
+        name = process.name,
+        percent = format!("{:.0}", process.cpu_usage_percent),
+        pid = process.pid
+    ))
+}
+
+/// "Name is using 6 GB" for the largest process
+fn largest(top: Option<&TopProcesses>) -> Option<String> {
+    let process = top?.by_memory.first()?;
+    Some(t!(
+        "health.largest",
+        name = process.name,
+        size = format_size(process.rss_bytes, DECIMAL)
+    ))
+}
+
+/// Check CPU health, naming the busiest of `top` in recommendations
+fn check_cpu(metrics: &SystemMetrics, top: Option<&TopProcesses>) -> ComponentHealth {
     let usage = metrics.cpu_usage_percent;
     if usage > 90.0 {
         ComponentHealth::new(
-            "CPU".to_string(),
+            t!("monitor.cpu").to_string(),
             HealthStatus::Critical,
-            format!("CPU usage is critically high: {:.1}%", usage),
+            t!("health.cpu_critical", usage = format!("{:.1}", usage)),
         )
-        .with_recommendation("Check for runaway processes or high system load".to_string())
+        .with_recommendation(advise(busiest(top), t!("health.cpu_critical_advice")))
     } else if usage > 70.0 {
         ComponentHealth::new(
-            "CPU".to_string(),
+            t!("monitor.cpu").to_string(),
             HealthStatus::Warning,
-            format!("CPU usage is high: {:.1}%", usage),
+            t!("health.cpu_high", usage = format!("{:.1}", usage)),
         )
-        .with_recommendation("Monitor CPU-intensive processes".to_string())
+        .with_recommendation(advise(busiest(top), t!("health.cpu_high_advice")))
     } else {
         ComponentHealth::new(
-            "CPU".to_string(),
+            t!("monitor.cpu").to_string(),
             HealthStatus::Healthy,
-            format!("CPU usage is normal: {:.1}%", usage),
+            t!("health.cpu_normal", usage = format!("{:.1}", usage)),
         )
     }
 }
 
-/// Check memory health
-fn check_memory(metrics: &SystemMetrics) -> ComponentHealth {
+/// Check memory health, naming the largest of `top` in recommendations
+fn check_memory(metrics: &SystemMetrics, top: Option<&TopProcesses>) -> ComponentHealth {
+    if let Some(breakdown) = metrics.memory_breakdown {
+        return check_memory_pressure(breakdown, top);
+    }
     let usage = metrics.memory_usage_percent();
     if usage > 95.0 {
         ComponentHealth::new(
-            "Memory".to_string(),
+            t!("monitor.memory").to_string(),
             HealthStatus::Critical,
-            format!(
-                "Memory usage is critically high: {:.1}% ({}/{})",
-                usage,
-                format_size(metrics.memory_used_bytes, DECIMAL),
-                format_size(metrics.memory_total_bytes, DECIMAL)
+            t!(
+                "health.memory_critical",
+                usage = format!("{:.1}", usage),
+                used = format_size(metrics.memory_used_bytes, DECIMAL),
+                total = format_size(metrics.memory_total_bytes, DECIMAL)
             ),
         )
-        .with_recommendation("Close applications or restart to free memory".to_string())
+        .with_recommendation(advise(largest(top), t!("health.memory_critical_advice")))
     } else if usage > 85.0 {
         ComponentHealth::new(
-            "Memory".to_string(),
+            t!("monitor.memory").to_string(),
             HealthStatus::Warning,
-            format!(
-                "Memory usage is high: {:.1}% ({}/{})",
-                usage,
-                format_size(metrics.memory_used_bytes, DECIMAL),
-                format_size(metrics.memory_total_bytes, DECIMAL)
+            t!(
+                "health.memory_high",
+                usage = format!("{:.1}", usage),
+                used = format_size(metrics.memory_used_bytes, DECIMAL),
+                total = format_size(metrics.memory_total_bytes, DECIMAL)
             ),
         )
-        .with_recommendation("Consider closing unused applications".to_string())
+        .with_recommendation(advise(largest(top), t!("health.memory_high_advice")))
     } else {
         ComponentHealth::new(
-            "Memory".to_string(),
+            t!("monitor.memory").to_string(),
             HealthStatus::Healthy,
-            format!(
-                "Memory usage is normal: {:.1}% ({}/{})",
-                usage,
-                format_size(metrics.memory_used_bytes, DECIMAL),
-                format_size(metrics.memory_total_bytes, DECIMAL)
+            t!(
+                "health.memory_normal",
+                usage = format!("{:.1}", usage),
+                used = format_size(metrics.memory_used_bytes, DECIMAL),
+                total = format_size(metrics.memory_total_bytes, DECIMAL)
             ),
         )
     }
 }
 
+/// Check memory health by the kernel's pressure level, which unlike used
+/// memory leaves out caches given back on demand
+fn check_memory_pressure(
+    breakdown: MemoryBreakdown,
+    top: Option<&TopProcesses>,
+) -> ComponentHealth {
+    let detail = t!(
+        "health.memory_breakdown",
+        app = format_size(breakdown.app_bytes, DECIMAL),
+        wired = format_size(breakdown.wired_bytes, DECIMAL),
+        compressed = format_size(breakdown.compressed_bytes, DECIMAL),
+        cached = format_size(breakdown.cached_bytes, DECIMAL)
+    );
+    match breakdown.pressure {
+        MemoryPressure::Critical => ComponentHealth::new(
+            t!("monitor.memory").to_string(),
+            HealthStatus::Critical,
+            t!("health.pressure_critical", detail = detail),
+        )
+        .with_recommendation(advise(largest(top), t!("health.memory_critical_advice"))),
+        MemoryPressure::Warning => ComponentHealth::new(
+            t!("monitor.memory").to_string(),
+            HealthStatus::Warning,
+            t!("health.pressure_elevated", detail = detail),
+        )
+        .with_recommendation(advise(largest(top), t!("health.memory_high_advice"))),
+        MemoryPressure::Normal => ComponentHealth::new(
+            t!("monitor.memory").to_string(),
+            HealthStatus::Healthy,
+            t!("health.pressure_normal", detail = detail),
+        ),
+    }
+}
+
 /// Check disk health
 fn check_disk(metrics: &SystemMetrics) -> ComponentHealth {
     let usage = metrics.disk_usage_percent();
     if usage > 95.0 {
         ComponentHealth::new(
-            "Disk".to_string(),
+            t!("monitor.disk").to_string(),
             HealthStatus::Critical,
-            format!(
-                "Disk space is critically low: {:.1}% used ({}/{})",
-                usage,
-                format_size(metrics.disk_used_bytes, DECIMAL),
-                format_size(metrics.disk_total_bytes, DECIMAL)
+            t!(
+                "health.disk_critical",
+                usage = format!("{:.1}", usage),
+                used = format_size(metrics.disk_used_bytes, DECIMAL),
+                total = format_size(metrics.disk_total_bytes, DECIMAL)
             ),
         )
-        .with_recommendation(
-            "Free up disk space immediately - run 'dragonfly disk analyze' to find large files"
-                .to_string(),
-        )
+        .with_recommendation(t!("health.disk_critical_advice").to_string())
     } else if usage > 85.0 {
         ComponentHealth::new(
-            "Disk".to_string(),
+            t!("monitor.disk").to_string(),
             HealthStatus::Warning,
-            format!(
-                "Disk space is low: {:.1}% used ({}/{})",
-                usage,
-                format_size(metrics.disk_used_bytes, DECIMAL),
-                format_size(metrics.disk_total_bytes, DECIMAL)
+            t!(
+                "health.disk_low",
+                usage = format!("{:.1}", usage),
+                used = format_size(metrics.disk_used_bytes, DECIMAL),
+                total = format_size(metrics.disk_total_bytes, DECIMAL)
             ),
         )
-        .with_recommendation("Consider cleaning up files - run 'dragonfly clean --dry-run' to see what can be cleaned".to_string())
+        .with_recommendation(t!("health.disk_low_advice").to_string())
     } else {
         ComponentHealth::new(
-            "Disk".to_string(),
+            t!("monitor.disk").to_string(),
             HealthStatus::Healthy,
-            format!(
-                "Disk space is adequate: {:.1}% used ({}/{})",
-                usage,
-                format_size(metrics.disk_used_bytes, DECIMAL),
-                format_size(metrics.disk_total_bytes, DECIMAL)
+            t!(
+                "health.disk_adequate",
+                usage = format!("{:.1}", usage),
+                used = format_size(metrics.disk_used_bytes, DECIMAL),
+                total = format_size(metrics.disk_total_bytes, DECIMAL)
             ),
         )
     }
@@ -154,51 +231,153 @@ fn check_disk(metrics: &SystemMetrics) -> ComponentHealth {
 fn check_swap(metrics: &SystemMetrics) -> ComponentHealth {
     if metrics.swap_total_bytes == 0 {
         return ComponentHealth::new(
-            "Swap".to_string(),
+            t!("monitor.swap").to_string(),
             HealthStatus::Healthy,
-            "Swap is not configured".to_string(),
+            t!("health.swap_none").to_string(),
         );
     }
 
     let usage = (metrics.swap_used_bytes as f32 / metrics.swap_total_bytes as f32) * 100.0;
     if usage > 50.0 {
         ComponentHealth::new(
-            "Swap".to_string(),
+            t!("monitor.swap").to_string(),
             HealthStatus::Warning,
-            format!(
-                "Swap usage is high: {:.1}% ({}/{})",
-                usage,
-                format_size(metrics.swap_used_bytes, DECIMAL),
-                format_size(metrics.swap_total_bytes, DECIMAL)
+            t!(
+                "health.swap_high",
+                usage = format!("{:.1}", usage),
+                used = format_size(metrics.swap_used_bytes, DECIMAL),
+                total = format_size(metrics.swap_total_bytes, DECIMAL)
+            ),
+        )
+        .with_recommendation(t!("health.swap_high_advice").to_string())
+    } else {
+        ComponentHealth::new(
+            t!("monitor.swap").to_string(),
+            HealthStatus::Healthy,
+            t!(
+                "health.swap_normal",
+                usage = format!("{:.1}", usage),
+                used = format_size(metrics.swap_used_bytes, DECIMAL),
+                total = format_size(metrics.swap_total_bytes, DECIMAL)
+            ),
+        )
+    }
+}
+
+/// Check battery wear; `None` when there's no battery
+fn check_battery(metrics: &SystemMetrics) -> Option<ComponentHealth> {
+    let battery = metrics.battery?;
+    let state = match battery.state {
+        ChargingState::Charging => t!("monitor.charging"),
+        ChargingState::Discharging => t!("monitor.on_battery"),
+        ChargingState::Full => t!("health.fully_charged"),
+        ChargingState::NotCharging => t!("health.plugged_in"),
+    };
+    let cycles = battery
+        .cycle_count
+        .map(|count| t!("health.battery_cycles", count = count))
+        .unwrap_or_default();
+    let summary = t!(
+        "health.battery_summary",
+        charge = format!("{:.0}", battery.charge_percent),
+        state = state,
+        cycles = cycles
+    );
+    let health = battery.health_percent.unwrap_or(100.0);
+    let worn_out = battery.cycle_count.unwrap_or(0) >= BATTERY_CYCLE_LIMIT;
+
+    let check = if health < 60.0 {
+        ComponentHealth::new(
+            t!("monitor.battery").to_string(),
+            HealthStatus::Critical,
+            t!(
+                "health.battery_critical",
+                health = format!("{:.0}", health),
+                summary = summary
             ),
         )
-        .with_recommendation(
-            "High swap usage indicates memory pressure - consider adding more RAM".to_string(),
+        .with_recommendation(t!("health.battery_critical_advice").to_string())
+    } else if health < 80.0 || worn_out {
+        ComponentHealth::new(
+            t!("monitor.battery").to_string(),
+            HealthStatus::Warning,
+            t!(
+                "health.battery_degraded",
+                health = format!("{:.0}", health),
+                summary = summary
+            ),
         )
+        .with_recommendation(t!("health.battery_degraded_advice").to_string())
     } else {
         ComponentHealth::new(
-            "Swap".to_string(),
+            t!("monitor.battery").to_string(),
             HealthStatus::Healthy,
-            format!(
-                "Swap usage is normal: {:.1}% ({}/{})",
-                usage,
-                format_size(metrics.swap_used_bytes, DECIMAL),
-                format_size(metrics.swap_total_bytes, DECIMAL)
+            match battery.health_percent {
+                Some(health) => t!(
+                    "health.battery_healthy",
+                    health = format!("{:.0}", health),
+                    summary = summary
+                ),
+                None => t!("health.battery_state", summary = summary),
+            },
+        )
+    };
+    Some(check)
+}
+
+/// Check which protected locations DragonFly can't read, and explain why
+fn check_access() -> ComponentHealth {
+    let home = dirs::home_dir().unwrap_or_default();
+    let unreadable: Vec<&str> = PRIVACY_PROTECTED_LOCATIONS
+        .iter()
+        .copied()
+        .filter(|location| {
+            let path = home.join(location);
+            path.exists()
+                && matches!(
+                    std::fs::read_dir(&path),
+                    Err(ref e) if e.kind() == std::io::ErrorKind::PermissionDenied
+                )
+        })
+        .collect();
+
+    let sip_note = t!("health.sip_note", roots = SIP_PROTECTED_ROOTS.join(", "));
+
+    if unreadable.is_empty() {
+        ComponentHealth::new(
+            t!("health.access").to_string(),
+            HealthStatus::Healthy,
+            t!("health.access_readable", note = sip_note),
+        )
+    } else {
+        ComponentHealth::new(
+            t!("health.access").to_string(),
+            HealthStatus::Warning,
+            t!(
+                "health.access_unreadable",
+                count = unreadable.len(),
+                locations = unreadable.join(", ~/"),
+                note = sip_note
             ),
         )
+        .with_recommendation(t!("health.access_advice").to_string())
     }
 }
 
 /// Run health checks for all components
-fn run_health_checks(metrics: &SystemMetrics, component: Option<&str>) -> Vec<ComponentHealth> {
+fn run_health_checks(
+    metrics: &SystemMetrics,
+    top: Option<&TopProcesses>,
+    component: Option<&str>,
+) -> Vec<ComponentHealth> {
     let mut checks = Vec::new();
 
     match component {
-        Some("cpu") | None => checks.push(check_cpu(metrics)),
+        Some("cpu") | None => checks.push(check_cpu(metrics, top)),
         _ => {}
     }
     match component {
-        Some("memory") | None => checks.push(check_memory(metrics)),
+        Some("memory") | None => checks.push(check_memory(metrics, top)),
         _ => {}
     }
     match component {
@@ -209,6 +388,22 @@ fn run_health_checks(metrics: &SystemMetrics, component: Option<&str>) -> Vec<Co
         Some("swap") | None => checks.push(check_swap(metrics)),
         _ => {}
     }
+    match component {
+        Some("battery") => checks.push(check_battery(metrics).unwrap_or_else(|| {
+            ComponentHealth::new(
+                t!("monitor.battery").to_string(),
+                HealthStatus::Healthy,
+                t!("health.no_battery").to_string(),
+            )
+        })),
+        // Desktops have no battery to report on
+        None => checks.extend(check_battery(metrics)),
+        _ => {}
+    }
+    match component {
+        Some("access") | None => checks.push(check_access()),
+        _ => {}
+    }
 
     checks
 }
@@ -221,10 +416,24 @@ pub async fn handle_health(
 ) -> Result<()> {
     let output_json = json || global_json;
     let mut collector = MetricsCollector::new();
+    let component_filter = component.as_deref();
+    // Recommendations name the heaviest processes, whose CPU usage is
+    // measured between two refreshes
+    let wants_processes =
+        recommend && matches!(component_filter, None | Some("cpu") | Some("memory"));
+    if wants_processes {
+        collector.collect_processes(PROCESSES_SHOWN).await?;
+        tokio::time::sleep(CPU_SAMPLE_INTERVAL).await;
+    }
     let metrics = collector.collect().await?;
+    monitor::record_history(&metrics);
+    let top = if wants_processes {
+        Some(collector.collect_processes(PROCESSES_SHOWN).await?)
+    } else {
+        None
+    };
 
-    let component_filter = component.as_deref();
-    let health_checks = run_health_checks(&metrics, component_filter);
+    let health_checks = run_health_checks(&metrics, top.as_ref(), component_filter);
 
     if output_json {
         let checks_json: Vec<serde_json::Value> = health_checks
@@ -269,18 +478,21 @@ pub async fn handle_health(
                 "memory_usage_percent": metrics.memory_usage_percent(),
                 "disk_usage_percent": metrics.disk_usage_percent(),
                 "timestamp": metrics.timestamp
-            }
+            },
+            "host": metrics.host,
+            "top_processes": top
         });
-        println!("{}", serde_json::to_string_pretty(&json_output)?);
+        print_json(&json_output)?;
         return Ok(());
     }
 
     // Human-readable output
-    println!("{}", "System Health Check".bold().bright_cyan());
+    println!("{}", t!("health.title").bold().bright_cyan());
+    println!("{}", monitor::host_line(&metrics.host).dimmed());
     if let Some(ref comp) = component {
-        println!("Component: {}", comp);
+        println!("{}", t!("health.component", component = comp));
     } else {
-        println!("Component: All");
+        println!("{}", t!("health.component", component = t!("health.all")));
     }
     println!();
 
@@ -292,16 +504,16 @@ pub async fn handle_health(
             HealthStatus::Critical => "❌".red(),
         };
         let status_text = match check.status {
-            HealthStatus::Healthy => "Healthy".green(),
-            HealthStatus::Warning => "Warning".yellow(),
-            HealthStatus::Critical => "Critical".red(),
+            HealthStatus::Healthy => t!("health.healthy").green(),
+            HealthStatus::Warning => t!("health.warning").yellow(),
+            HealthStatus::Critical => t!("health.critical").red(),
         };
 
         println!("{} {}: {}", status_icon, check.name.bold(), status_text);
         println!("   {}", check.message.dimmed());
         if recommend {
             if let Some(ref rec) = check.recommendation {
-                println!("   {} {}", "💡 Recommendation:".cyan(), rec);
+                println!("   {} {}", t!("health.recommendation").cyan(), rec);
             }
         }
         println!();
@@ -312,12 +524,9 @@ pub async fn handle_health(
     }
 
     if !has_issues {
-        println!("{}", "All systems operational!".green().bold());
+        println!("{}", t!("health.all_ok").green().bold());
     } else if recommend {
-        println!(
-            "{}",
-            "Run with --recommend to see suggestions for improving system health.".dimmed()
-        );
+        println!("{}", t!("health.recommend_hint").dimmed());
     }
 
     Ok(())
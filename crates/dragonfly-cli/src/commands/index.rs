@@ -0,0 +1,267 @@
+//! Size index command handler
+
+use crate::ui::{create_spinner, print_json};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use dragonfly_core::domain::scan_options::ScanOptions;
+use dragonfly_core::ports::CancellationToken;
+use dragonfly_core::t;
+use dragonfly_disk::{platform_watcher, GrowthMonitor, SizeIndex};
+use humansize::{format_size, DECIMAL};
+use serde_json::json;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// How long FSEvents gathers changes before delivering them
+const WATCH_LATENCY: Duration = Duration::from_secs(2);
+
+/// How often the daemon checks for Ctrl-C while waiting for changes
+const POLL_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Build, inspect, or maintain the size index
+///
+/// With no flags the given roots are (re)indexed once. `daemon` keeps them
+/// current until interrupted, `status` lists what is indexed, and `remove`
+/// drops the roots from the index.
+pub async fn handle_index(
+    roots: Vec<PathBuf>,
+    daemon: bool,
+    status: bool,
+    remove: bool,
+    json: bool,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let index_path = SizeIndex::default_path();
+    let mut index = SizeIndex::open(&index_path).context("Failed to open size index")?;
+
+    if status {
+        return print_status(&index, json);
+    }
+
+    let roots = if roots.is_empty() {
+        vec![dirs::home_dir().context("Could not determine home directory")?]
+    } else {
+        roots
+    };
+    // Watchers report canonical paths, so the index stores them that way too
+    let roots = roots
+        .iter()
+        .map(|root| {
+            std::fs::canonicalize(root).with_context(|| format!("Invalid root: {}", root.display()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if remove {
+        for root in &roots {
+            index
+                .remove_root(root)
+                .context("Failed to update size index")?;
+        }
+        if json {
+            print_json(&json!({ "status": "ok", "removed": roots }))?;
+        } else {
+            for root in &roots {
+                println!("{}", t!("index.removed", path = root.display()));
+            }
+        }
+        return Ok(());
+    }
+
+    let options = ScanOptions::default();
+    let mut indexed = Vec::new();
+    for root in &roots {
+        let spinner = (!json).then(|| create_spinner(&t!("index.indexing", path = root.display())));
+        let count = index
+            .index_root(root, &options, cancel)
+            .context("Failed to index root")?;
+        if let Some(spinner) = spinner {
+            spinner.finish_and_clear();
+        }
+        if cancel.is_cancelled() {
+            break;
+        }
+        if !json {
+            println!(
+                "{}",
+                t!("index.indexed", count = count, path = root.display())
+            );
+        }
+        indexed.push(json!({ "root": root, "files": count }));
+    }
+
+    if !daemon || cancel.is_cancelled() {
+        if json {
+            let json_output = json!({
+                "status": "ok",
+                "index": index_path,
+                "roots": indexed,
+                "interrupted": cancel.is_cancelled()
+            });
+            print_json(&json_output)?;
+        }
+        return Ok(());
+    }
+
+    if !json {
+        println!("{}", t!("index.watching").dimmed());
+    }
+    let cancel = cancel.clone();
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        let mut watcher = platform_watcher(&roots, WATCH_LATENCY)?;
+        while !cancel.is_cancelled() {
+            let changes = watcher.next_changes(POLL_TIMEOUT)?;
+            if changes.is_empty() {
+                continue;
+            }
+            let applied = index.apply_changes(&changes, &options)?;
+            if json {
+                println!(
+                    "{}",
+                    json!({ "event": "updated", "changes": applied, "at": chrono::Utc::now() })
+                );
+            } else {
+                tracing::debug!(changes = applied, "Applied changes to size index");
+            }
+        }
+        Ok(())
+    })
+    .await
+    .context("Index daemon stopped unexpectedly")??;
+
+    Ok(())
+}
+
+/// Keep the size index current for `root` and report directories `depth`
+/// levels below it that grow by at least `threshold` bytes, until interrupted
+///
+/// With `json` each report is a line of JSON (NDJSON) for other tools to
+/// follow.
+pub async fn watch_growth(
+    root: PathBuf,
+    depth: usize,
+    threshold: u64,
+    json: bool,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    // Watchers report canonical paths, so the index stores them that way too
+    let root = std::fs::canonicalize(&root)
+        .with_context(|| format!("Invalid path: {}", root.display()))?;
+    let mut index =
+        SizeIndex::open(&SizeIndex::default_path()).context("Failed to open size index")?;
+    let options = ScanOptions::default();
+    let spinner = (!json).then(|| create_spinner(&t!("index.indexing", path = root.display())));
+    let refreshed = index
+        .refresh(&root, &options, cancel)
+        .context("Failed to update size index")?;
+    if let Some(spinner) = spinner {
+        spinner.finish_and_clear();
+    }
+    if refreshed.is_none() {
+        return Ok(());
+    }
+
+    if !json {
+        let (path, threshold) = (root.display().to_string(), format_size(threshold, DECIMAL));
+        println!(
+            "{}",
+            t!("disk.watch_started", path = path, threshold = threshold).dimmed()
+        );
+    }
+    let cancel = cancel.clone();
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        let mut watcher = platform_watcher(std::slice::from_ref(&root), WATCH_LATENCY)?;
+        let mut monitor = GrowthMonitor::new(root, depth, threshold);
+        while !cancel.is_cancelled() {
+            let changes = watcher.next_changes(POLL_TIMEOUT)?;
+            if changes.is_empty() {
+                continue;
+            }
+            let now = chrono::Local::now();
+            for grown in monitor.apply(&mut index, &changes, &options)? {
+                if json {
+                    println!(
+                        "{}",
+                        json!({
+                            "event": "grew",
+                            "path": grown.path,
+                            "before": grown.before,
+                            "after": grown.after,
+                            "growth": grown.growth(),
+                            "at": now.with_timezone(&chrono::Utc)
+                        })
+                    );
+                } else {
+                    let path = grown.path.display().to_string();
+                    let growth = format_size(grown.growth().unsigned_abs(), DECIMAL);
+                    let size = format_size(grown.after, DECIMAL);
+                    println!(
+                        "{} {}",
+                        now.format("%H:%M:%S").to_string().dimmed(),
+                        t!("disk.watch_grew", path = path, growth = growth, size = size)
+                    );
+                }
+            }
+        }
+        Ok(())
+    })
+    .await
+    .context("Disk watch stopped unexpectedly")??;
+
+    Ok(())
+}
+
+fn print_status(index: &SizeIndex, json: bool) -> Result<()> {
+    let roots = index.roots().context("Failed to read size index")?;
+    let mut summaries = Vec::new();
+    for root in &roots {
+        let (files, bytes) = index.totals(&root.path)?;
+        summaries.push((root, files, bytes));
+    }
+
+    if json {
+        let json_output = json!({
+            "status": "ok",
+            "index": SizeIndex::default_path(),
+            "roots": summaries.iter().map(|(root, files, bytes)| json!({
+                "root": root.path,
+                "files": files,
+                "total_size": bytes,
+                "indexed_at": root.indexed_at,
+                "updated_at": root.updated_at
+            })).collect::<Vec<_>>()
+        });
+        print_json(&json_output)?;
+        return Ok(());
+    }
+
+    println!("{}", t!("index.title").bold().bright_cyan());
+    println!(
+        "{}",
+        t!("index.file", path = SizeIndex::default_path().display())
+    );
+    if summaries.is_empty() {
+        println!("{}", t!("index.empty"));
+        return Ok(());
+    }
+    println!();
+    for (root, files, bytes) in summaries {
+        println!(
+            "{}",
+            t!(
+                "index.root",
+                path = root.path.display().to_string().bold(),
+                count = files,
+                size = format_size(bytes, DECIMAL)
+            )
+        );
+        println!(
+            "   {}",
+            t!(
+                "index.dates",
+                indexed = root.indexed_at.format("%Y-%m-%d %H:%M"),
+                updated = root.updated_at.format("%Y-%m-%d %H:%M")
+            )
+        );
+    }
+    Ok(())
+}
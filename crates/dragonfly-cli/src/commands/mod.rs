@@ -4,21 +4,45 @@
 //! between the user interface and domain layer.
 
 pub mod analyze;
+pub mod apps;
+pub mod audit;
+pub mod capabilities;
 pub mod clean;
+pub mod doctor;
 pub mod duplicates;
 pub mod health;
+pub mod index;
 pub mod monitor;
 pub mod recover;
+pub mod snapshots;
+pub mod time_machine;
+pub mod trash;
+pub mod trends;
+pub mod watch;
 
+#[cfg(feature = "web")]
+pub mod serve;
 #[cfg(feature = "skills")]
 pub mod skills;
 
 pub use analyze::handle_disk;
+pub use apps::handle_apps;
+pub use audit::handle_audit_list;
+pub use capabilities::handle_capabilities;
 pub use clean::handle_clean;
+pub use doctor::handle_doctor;
 pub use duplicates::handle_duplicates;
 pub use health::handle_health;
+pub use index::handle_index;
 pub use monitor::handle_monitor;
 pub use recover::*;
+pub use snapshots::handle_snapshots;
+pub use time_machine::handle_time_machine_delete;
+pub use trash::{handle_trash_empty, handle_trash_status};
+pub use trends::handle_trends;
+pub use watch::handle_watch;
 
+#[cfg(feature = "web")]
+pub use serve::handle_serve;
 #[cfg(feature = "skills")]
 pub use skills::handle_skills;
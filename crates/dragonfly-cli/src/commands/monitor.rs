@@ -1,32 +1,91 @@
 //! System monitoring command handler
 
-use anyhow::Result;
+use crate::notify::{BellNotifier, DesktopNotifier, ScriptNotifier};
+use crate::ui::{print_json, sparkline, sparkline_between};
+use anyhow::{bail, Context, Result};
+use chrono::{Local, TimeZone, Utc};
 use colored::Colorize;
-use dragonfly_monitor::{MetricsCollector, SystemMetrics};
+use dragonfly_core::domain::config::quoted_list;
+use dragonfly_core::ports::Notifier;
+use dragonfly_core::t;
+use dragonfly_monitor::{
+    AlertEvent, AlertRule, AlertTransition, Alerts, ChargingState, CollectorOverhead, Extras,
+    GpuMetrics, HistorySummary, HostInfo, MemoryPressure, MetricsCollector, MetricsHistory,
+    MetricsSample, ProcessMetrics, ProcessSample, RecentSamples, Series, SystemMetrics,
+    TopProcesses, CPU_SAMPLE_INTERVAL,
+};
 use humansize::{format_size, DECIMAL};
 use serde_json::json;
 use std::io::{self, Write};
 use tokio::time::{sleep, Duration};
 
+/// Widest sparkline printed by `monitor history`, in characters
+const SPARKLINE_WIDTH: usize = 40;
+
+/// Title of alert notifications
+const ALERT_TITLE: &str = "Dragonfly alert";
+
+/// Record `metrics` in the default metrics history
+///
+/// History is a side record, so failing to write it is only a warning.
+pub fn record_history(metrics: &SystemMetrics) {
+    let recorded = MetricsHistory::open(&MetricsHistory::default_path())
+        .and_then(|history| history.record(metrics));
+    if let Err(e) = recorded {
+        tracing::warn!(error = %e, "Failed to record metrics history");
+    }
+}
+
 /// Display metrics in a formatted table
-fn display_metrics(metrics: &SystemMetrics) {
+fn display_metrics(
+    metrics: &SystemMetrics,
+    top: Option<&TopProcesses>,
+    per_core: bool,
+    alerts: &Alerts,
+    recent: &RecentSamples,
+    overhead: CollectorOverhead,
+) {
     print!("\x1B[2J\x1B[1;1H"); // Clear screen and move cursor to top
-    println!("{}", "System Monitor".bold().bright_cyan());
+    println!("{}", t!("monitor.title").bold().bright_cyan());
+    println!("{}", host_line(&metrics.host).dimmed());
     println!("{}", "=".repeat(50).dimmed());
     println!();
 
     // CPU
-    let cpu_color = if metrics.cpu_usage_percent > 80.0 {
-        "red"
-    } else if metrics.cpu_usage_percent > 50.0 {
-        "yellow"
-    } else {
-        "green"
-    };
     println!(
-        "CPU:    {:>6.1}% {}",
+        "{}{:>6.1}% {}{}",
+        label("monitor.cpu"),
         metrics.cpu_usage_percent,
-        format_bar(metrics.cpu_usage_percent / 100.0, cpu_color)
+        format_bar(
+            metrics.cpu_usage_percent / 100.0,
+            cpu_color(metrics.cpu_usage_percent)
+        ),
+        trend(recent, |s| f64::from(s.cpu_usage_percent))
+    );
+    if per_core {
+        let width = metrics
+            .cpu_per_core_percent
+            .len()
+            .saturating_sub(1)
+            .to_string()
+            .len();
+        for (core, usage) in metrics.cpu_per_core_percent.iter().enumerate() {
+            println!(
+                "  {:<width$} {:>6.1}% {}",
+                core,
+                usage,
+                format_bar(usage / 100.0, cpu_color(*usage)),
+                width = width
+            );
+        }
+    }
+    println!(
+        "{}{:.2} {:.2} {:.2} {}",
+        label("monitor.load"),
+        metrics.load_average.one,
+        metrics.load_average.five,
+        metrics.load_average.fifteen,
+        t!("monitor.load_windows").dimmed()
     );
 
     // Memory
@@ -39,12 +98,33 @@ fn display_metrics(metrics: &SystemMetrics) {
         "green"
     };
     println!(
-        "Memory: {:>6.1}% {} ({}/{})",
+        "{}{:>6.1}% {}{} ({}/{})",
+        label("monitor.memory"),
         mem_percent,
         format_bar(mem_percent / 100.0, mem_color),
+        trend(recent, MetricsSample::memory_usage_percent),
         format_size(metrics.memory_used_bytes, DECIMAL),
         format_size(metrics.memory_total_bytes, DECIMAL)
     );
+    if let Some(breakdown) = metrics.memory_breakdown {
+        let pressure = match breakdown.pressure {
+            MemoryPressure::Normal => t!("monitor.pressure_normal").green(),
+            MemoryPressure::Warning => t!("monitor.pressure_warning").yellow(),
+            MemoryPressure::Critical => t!("monitor.pressure_critical").red(),
+        };
+        println!(
+            "        {} {}",
+            t!("monitor.pressure", level = pressure),
+            t!(
+                "monitor.breakdown",
+                app = format_size(breakdown.app_bytes, DECIMAL),
+                wired = format_size(breakdown.wired_bytes, DECIMAL),
+                compressed = format_size(breakdown.compressed_bytes, DECIMAL),
+                cached = format_size(breakdown.cached_bytes, DECIMAL)
+            )
+            .dimmed()
+        );
+    }
 
     // Swap
     if metrics.swap_total_bytes > 0 {
@@ -56,7 +136,8 @@ fn display_metrics(metrics: &SystemMetrics) {
             "green"
         };
         println!(
-            "Swap:   {:>6.1}% {} ({}/{})",
+            "{}{:>6.1}% {} ({}/{})",
+            label("monitor.swap"),
             swap_percent,
             format_bar(swap_percent / 100.0, swap_color),
             format_size(metrics.swap_used_bytes, DECIMAL),
@@ -74,18 +155,186 @@ fn display_metrics(metrics: &SystemMetrics) {
         "green"
     };
     println!(
-        "Disk:   {:>6.1}% {} ({}/{})",
+        "{}{:>6.1}% {}{} ({}/{})",
+        label("monitor.disk"),
         disk_percent,
         format_bar(disk_percent / 100.0, disk_color),
+        trend(recent, MetricsSample::disk_usage_percent),
         format_size(metrics.disk_used_bytes, DECIMAL),
         format_size(metrics.disk_total_bytes, DECIMAL)
     );
 
+    // Battery
+    if let Some(battery) = metrics.battery {
+        let battery_color = if battery.charge_percent < 10.0 {
+            "red"
+        } else if battery.charge_percent < 20.0 {
+            "yellow"
+        } else {
+            "green"
+        };
+        let state = match battery.state {
+            ChargingState::Charging => t!("monitor.charging"),
+            ChargingState::Discharging => t!("monitor.on_battery"),
+            ChargingState::Full => t!("monitor.full"),
+            ChargingState::NotCharging => t!("monitor.not_charging"),
+        };
+        println!(
+            "{}{:>6.0}% {} ({})",
+            label("monitor.battery"),
+            battery.charge_percent,
+            format_bar(battery.charge_percent / 100.0, battery_color),
+            state
+        );
+    }
+
+    // GPU
+    if let Some(gpu) = &metrics.gpu {
+        println!("{}", gpu_row(gpu));
+    }
+
+    let firing: Vec<String> = alerts
+        .firing()
+        .map(|rule| match rule.metric.value(metrics) {
+            Some(value) => describe_alert(rule, value),
+            None => rule.to_string(),
+        })
+        .collect();
+    if !firing.is_empty() {
+        println!();
+        println!("{}", t!("monitor.alerts").bold().red());
+        for alert in firing {
+            println!("  {} {}", "⚠".red(), alert);
+        }
+    }
+
+    if let Some(top) = top {
+        println!();
+        println!("{}", t!("monitor.top_cpu").bold());
+        for process in &top.by_cpu {
+            print_process(process);
+        }
+        println!();
+        println!("{}", t!("monitor.top_memory").bold());
+        for process in &top.by_memory {
+            print_process(process);
+        }
+    }
+
     println!();
-    println!("{}", "Press Ctrl+C to exit".dimmed());
+    println!(
+        "{}",
+        t!(
+            "monitor.overhead",
+            last = overhead.last_micros,
+            mean = overhead.mean_micros
+        )
+        .dimmed()
+    );
+    println!("{}", t!("monitor.exit_hint").dimmed());
     io::stdout().flush().unwrap();
 }
 
+/// OS, model, kernel and uptime on one line, leaving out what the platform
+/// doesn't report
+pub fn host_line(host: &HostInfo) -> String {
+    let mut parts: Vec<String> = [
+        host.os_version.clone(),
+        host.hardware_model.clone(),
+        host.kernel_version
+            .as_ref()
+            .map(|kernel| t!("monitor.kernel", version = kernel)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let mut up = t!("monitor.up", uptime = format_uptime(host.uptime_seconds));
+    if let Some(booted) = Local.timestamp_opt(host.boot_time as i64, 0).single() {
+        up.push(' ');
+        up.push_str(&t!(
+            "monitor.booted",
+            time = booted.format("%Y-%m-%d %H:%M")
+        ));
+    }
+    parts.push(up);
+    parts.join(" · ")
+}
+
+/// Uptime to the two largest units, e.g. `3d 4h`, `5h 12m` or `7m`
+fn format_uptime(seconds: u64) -> String {
+    let (days, hours, minutes) = (seconds / 86_400, seconds / 3600 % 24, seconds / 60 % 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+/// Sparkline of a percentage over the recent samples on a 0-100 scale,
+/// padded to the window so what follows it lines up; empty with no window
+fn trend(recent: &RecentSamples, percent: impl Fn(&MetricsSample) -> f64) -> String {
+    let width = recent.capacity();
+    if width == 0 {
+        return String::new();
+    }
+    let values: Vec<u64> = recent
+        .iter()
+        .map(|sample| percent(sample).round() as u64)
+        .collect();
+    let line = sparkline_between(&values, width, 0, 100);
+    format!(
+        " {}",
+        format!("{:<width$}", line, width = width).bright_cyan()
+    )
+}
+
+/// Bar color for a CPU usage percentage
+fn cpu_color(usage_percent: f32) -> &'static str {
+    if usage_percent > 80.0 {
+        "red"
+    } else if usage_percent > 50.0 {
+        "yellow"
+    } else {
+        "green"
+    }
+}
+
+/// The monitor's GPU line: utilisation with a bar, and memory in use
+fn gpu_row(gpu: &GpuMetrics) -> String {
+    format!(
+        "{}{:>6.1}% {} {}",
+        label("monitor.gpu"),
+        gpu.utilization_percent,
+        format_bar(
+            gpu.utilization_percent / 100.0,
+            cpu_color(gpu.utilization_percent)
+        ),
+        t!(
+            "monitor.gpu_in_use",
+            size = format_size(gpu.memory_in_use_bytes, DECIMAL)
+        )
+    )
+}
+
+/// A dashboard row label such as `CPU:`, padded so the figures line up
+fn label(key: &str) -> String {
+    format!("{:<8}", format!("{}:", t!(key)))
+}
+
+/// One line of a process table: pid, name, CPU and resident memory
+fn print_process(process: &ProcessMetrics) {
+    let name: String = process.name.chars().take(28).collect();
+    println!(
+        "  {:>7}  {:<28} {:>6.1}%  {:>10}",
+        process.pid,
+        name,
+        process.cpu_usage_percent,
+        format_size(process.rss_bytes, DECIMAL)
+    );
+}
+
 /// Format a progress bar
 fn format_bar(value: f32, color: &str) -> String {
     let width: usize = 20;
@@ -99,46 +348,454 @@ fn format_bar(value: f32, color: &str) -> String {
     }
 }
 
-pub async fn handle_monitor(interval: u64, json: bool) -> Result<()> {
+/// `rule` and the figure that set it off, e.g. `disk>90 (disk at 93.1%)`
+fn describe_alert(rule: &AlertRule, value: f64) -> String {
+    t!(
+        "monitor.alert_value",
+        rule = rule,
+        metric = rule.metric.name(),
+        value = format!("{:.1}{}", value, rule.metric.unit())
+    )
+}
+
+/// Alert rules and the notifiers they fire, from the command line or else
+/// `alerts` and `alert_actions` in `~/.dragonfly/config`
+///
+/// Actions are `bell`, `notify` (a desktop notification) and
+/// `exec:<command>`; without any, alerts post desktop notifications.
+fn alert_settings(
+    rules: Vec<AlertRule>,
+    actions: Vec<String>,
+) -> Result<(Alerts, Vec<Box<dyn Notifier>>)> {
+    let config = match dirs::home_dir() {
+        Some(home) => {
+            let path = home.join(".dragonfly").join("config");
+            match std::fs::read_to_string(&path) {
+                Ok(config) => config,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to read {}", path.display()))
+                }
+            }
+        }
+        None => String::new(),
+    };
+    let config_list = |key: &str| {
+        quoted_list(&config, key)
+            .with_context(|| format!("Invalid {} list in ~/.dragonfly/config", key))
+    };
+
+    let rules = if rules.is_empty() {
+        config_list("alerts")?
+            .iter()
+            .map(|expression| {
+                expression
+                    .parse::<AlertRule>()
+                    .context("Invalid alert in ~/.dragonfly/config")
+            })
+            .collect::<Result<_>>()?
+    } else {
+        rules
+    };
+    let mut actions = if actions.is_empty() {
+        config_list("alert_actions")?
+    } else {
+        actions
+    };
+    if actions.is_empty() {
+        actions.push("notify".to_string());
+    }
+
+    let notifiers = actions
+        .iter()
+        .map(|action| -> Result<Box<dyn Notifier>> {
+            Ok(match action.as_str() {
+                "bell" => Box::new(BellNotifier::new()),
+                "notify" => Box::new(DesktopNotifier::new()),
+                _ => match action.strip_prefix("exec:").map(str::trim) {
+                    Some(command) if !command.is_empty() => Box::new(ScriptNotifier::new(command)),
+                    _ => bail!("{}", t!("monitor.unknown_alert_action", action = action)),
+                },
+            })
+        })
+        .collect::<Result<_>>()?;
+    Ok((Alerts::new(rules), notifiers))
+}
+
+/// Run every notifier for the alerts that fired in `events`
+///
+/// A notifier that fails is only a warning; the others still run.
+async fn fire_alerts(events: &[AlertEvent], notifiers: &[Box<dyn Notifier>]) {
+    for event in events {
+        if event.transition != AlertTransition::Fired {
+            continue;
+        }
+        let message = describe_alert(&event.rule, event.value);
+        for notifier in notifiers {
+            if let Err(e) = notifier.notify(ALERT_TITLE, &message).await {
+                tracing::warn!(error = %e, alert = %event.rule, "Alert action failed");
+            }
+        }
+    }
+}
+
+pub async fn handle_monitor(
+    interval: u64,
+    top: usize,
+    per_core: bool,
+    sparkline_width: usize,
+    alerts: Vec<AlertRule>,
+    alert_actions: Vec<String>,
+    json: bool,
+) -> Result<()> {
+    let (mut alerts, notifiers) = alert_settings(alerts, alert_actions)?;
     let mut collector = MetricsCollector::new();
+    // Process CPU usage is measured between refreshes, so take a baseline
+    if top > 0 {
+        collector.collect_processes(top).await?;
+    }
 
     if json {
         // JSON mode: output single snapshot and exit
+        if top > 0 {
+            sleep(CPU_SAMPLE_INTERVAL).await;
+        }
         let metrics = collector.collect().await?;
+        record_history(&metrics);
+        let events = alerts.evaluate(&metrics);
+        fire_alerts(&events, &notifiers).await;
+        let firing: Vec<_> = events
+            .iter()
+            .map(|event| {
+                json!({
+                    "rule": event.rule.to_string(),
+                    "value": event.value,
+                })
+            })
+            .collect();
+        let processes = if top > 0 {
+            Some(collector.collect_processes(top).await?)
+        } else {
+            None
+        };
         let json_output = json!({
             "status": "ok",
             "cpu_usage_percent": metrics.cpu_usage_percent,
+            "cpu_per_core_percent": metrics.cpu_per_core_percent,
+            "load_average": metrics.load_average,
             "memory_total_bytes": metrics.memory_total_bytes,
             "memory_used_bytes": metrics.memory_used_bytes,
             "memory_available_bytes": metrics.memory_available_bytes,
+            "memory_breakdown": metrics.memory_breakdown,
             "swap_total_bytes": metrics.swap_total_bytes,
             "swap_used_bytes": metrics.swap_used_bytes,
             "disk_total_bytes": metrics.disk_total_bytes,
             "disk_used_bytes": metrics.disk_used_bytes,
             "disk_available_bytes": metrics.disk_available_bytes,
+            "battery": metrics.battery,
+            "gpu": metrics.gpu,
+            "host": metrics.host,
             "network_rx_bytes": metrics.network_rx_bytes,
             "network_tx_bytes": metrics.network_tx_bytes,
-            "timestamp": metrics.timestamp
+            "timestamp": metrics.timestamp,
+            "collector_overhead": collector.overhead(),
+            "top_processes": processes,
+            "alerts": firing
         });
-        println!("{}", serde_json::to_string_pretty(&json_output)?);
+        print_json(&json_output)?;
         return Ok(());
     }
 
     // Interactive mode: continuous monitoring
-    println!("{}", "System Monitor".bold().bright_cyan());
-    println!("Update interval: {} seconds", interval);
-    println!("{}", "Press Ctrl+C to exit".dimmed());
+    println!("{}", t!("monitor.title").bold().bright_cyan());
+    println!("{}", t!("monitor.interval", seconds = interval));
+    println!("{}", t!("monitor.exit_hint").dimmed());
     sleep(Duration::from_secs(1)).await;
 
+    let mut recent = RecentSamples::new(sparkline_width);
     loop {
+        let processes = if top > 0 {
+            collector.collect_processes(top).await.ok()
+        } else {
+            None
+        };
         match collector.collect().await {
             Ok(metrics) => {
-                display_metrics(&metrics);
+                record_history(&metrics);
+                recent.push(&metrics);
+                let events = alerts.evaluate(&metrics);
+                display_metrics(
+                    &metrics,
+                    processes.as_ref(),
+                    per_core,
+                    &alerts,
+                    &recent,
+                    collector.overhead(),
+                );
+                fire_alerts(&events, &notifiers).await;
             }
             Err(e) => {
-                eprintln!("Error collecting metrics: {}", e);
+                eprintln!("{}", t!("monitor.collect_error", error = e));
+            }
+        }
+        sleep(Duration::from_secs(interval)).await;
+    }
+}
+
+/// Summarise the metrics recorded over the last `since`
+pub fn handle_history(since: chrono::Duration, json: bool) -> Result<()> {
+    let history = MetricsHistory::open(&MetricsHistory::default_path())?;
+    let samples = history.samples(Utc::now() - since)?;
+    let summary = HistorySummary::of(&samples);
+
+    if json {
+        let json_output = json!({
+            "status": "ok",
+            "since_seconds": since.num_seconds(),
+            "summary": summary,
+            "samples": samples
+        });
+        print_json(&json_output)?;
+        return Ok(());
+    }
+
+    println!("{}", t!("monitor.history_title").bold().bright_cyan());
+    let Some(summary) = summary else {
+        println!("\n{}", t!("monitor.history_empty"));
+        return Ok(());
+    };
+    println!(
+        "{}",
+        t!(
+            "monitor.history_range",
+            count = summary.samples,
+            from = summary.from.format("%Y-%m-%d %H:%M"),
+            to = summary.to.format("%Y-%m-%d %H:%M")
+        )
+        .dimmed()
+    );
+    println!();
+
+    let line = |values: Vec<f64>| {
+        let scaled: Vec<u64> = values.iter().map(|value| (value * 100.0) as u64).collect();
+        sparkline(&scaled, SPARKLINE_WIDTH)
+    };
+    let cpu = line(
+        samples
+            .iter()
+            .map(|s| f64::from(s.cpu_usage_percent))
+            .collect(),
+    );
+    let memory = line(samples.iter().map(|s| s.memory_usage_percent()).collect());
+    let disk = line(samples.iter().map(|s| s.disk_usage_percent()).collect());
+    print_series(t!("monitor.cpu"), &cpu, &summary.cpu_percent);
+    print_series(t!("monitor.memory"), &memory, &summary.memory_percent);
+    print_series(t!("monitor.disk"), &disk, &summary.disk_percent);
+
+    if let Some(per_day) = summary.disk_used_bytes.per_day {
+        let sign = if per_day < 0.0 { '-' } else { '+' };
+        println!(
+            "        {}",
+            t!(
+                "monitor.history_per_day",
+                change = format!(
+                    "{}{}",
+                    sign,
+                    format_size(per_day.abs().round() as u64, DECIMAL)
+                )
+            )
+            .dimmed()
+        );
+    }
+    Ok(())
+}
+
+/// One metric's sparkline, range, and change per day
+fn print_series(label: &str, sparkline: &str, series: &Series) {
+    let rate = match series.per_day {
+        Some(per_day) => t!("monitor.history_rate", rate = format!("{:+.1}", per_day)),
+        None => String::new(),
+    };
+    let percent = |value: f64| format!("{:.1}", value);
+    println!(
+        "{:<7} {}",
+        format!("{}:", label),
+        t!(
+            "monitor.history_series",
+            sparkline = sparkline.bright_cyan(),
+            first = percent(series.first),
+            last = percent(series.last),
+            mean = percent(series.mean),
+            min = percent(series.min),
+            max = percent(series.max),
+            rate = rate
+        )
+    );
+}
+
+/// Resolve `target` to a pid: a number is one, anything else must name
+/// exactly one running process
+async fn resolve_process(collector: &mut MetricsCollector, target: &str) -> Result<u32> {
+    if let Ok(pid) = target.parse() {
+        return Ok(pid);
+    }
+    let found = collector.find_processes(target).await?;
+    match found.as_slice() {
+        [] => bail!("{}", t!("monitor.no_process", name = target)),
+        [process] => Ok(process.pid),
+        several => {
+            let pids: Vec<String> = several.iter().map(|p| p.pid.to_string()).collect();
+            bail!(
+                "{}",
+                t!(
+                    "monitor.several_processes",
+                    count = several.len(),
+                    name = target,
+                    pids = pids.join(", ")
+                )
+            )
+        }
+    }
+}
+
+/// One row of the `monitor process` table
+fn print_process_sample(sample: &ProcessSample) {
+    let count = |value: Option<u32>| value.map_or_else(|| "-".to_string(), |v| v.to_string());
+    println!(
+        "{}  {:>6.1}%  {:>10}  {:>7}  {:>10}",
+        chrono::Local::now().format("%H:%M:%S"),
+        sample.cpu_usage_percent,
+        format_size(sample.rss_bytes, DECIMAL),
+        count(sample.threads),
+        count(sample.open_files)
+    );
+}
+
+/// Follow one process until it exits, or for `count` samples
+pub async fn handle_process(
+    target: &str,
+    interval: u64,
+    count: Option<usize>,
+    json: bool,
+) -> Result<()> {
+    let mut collector = MetricsCollector::with_extras(Extras {
+        disk: false,
+        processes: false,
+        battery: false,
+        gpu: false,
+    });
+    let pid = resolve_process(&mut collector, target).await?;
+    // The first sample is the baseline CPU usage is measured from
+    let Some(first) = collector.collect_process(pid).await? else {
+        bail!("{}", t!("monitor.no_pid", pid = pid));
+    };
+
+    if !json {
+        println!(
+            "{} {}",
+            t!("monitor.following").bold().bright_cyan(),
+            t!(
+                "monitor.following_detail",
+                name = first.name.bold(),
+                pid = pid,
+                seconds = interval
+            )
+        );
+        println!(
+            "{}",
+            format!(
+                "{:<8}  {:>7}  {:>10}  {:>7}  {:>10}",
+                t!("monitor.column_time"),
+                t!("monitor.cpu"),
+                t!("monitor.memory"),
+                t!("monitor.column_threads"),
+                t!("monitor.column_open_files")
+            )
+            .dimmed()
+        );
+    }
+    sleep(CPU_SAMPLE_INTERVAL).await;
+
+    let mut taken = 0;
+    loop {
+        let Some(sample) = collector.collect_process(pid).await? else {
+            if json {
+                let line = json!({
+                    "event": "exited",
+                    "pid": pid,
+                    "timestamp": Utc::now().timestamp()
+                });
+                println!("{}", line);
+            } else {
+                println!(
+                    "{}",
+                    t!("monitor.process_exited", name = first.name, pid = pid).yellow()
+                );
             }
+            return Ok(());
+        };
+        if json {
+            let mut line = serde_json::to_value(&sample)?;
+            line["event"] = json!("sample");
+            println!("{}", line);
+        } else {
+            print_process_sample(&sample);
+        }
+        taken += 1;
+        if count.is_some_and(|count| taken >= count) {
+            return Ok(());
         }
         sleep(Duration::from_secs(interval)).await;
     }
 }
+
+/// Serve metrics for Prometheus on `listen` until Ctrl-C
+#[cfg(feature = "exporter")]
+pub async fn handle_serve(listen: std::net::SocketAddr) -> Result<()> {
+    use anyhow::Context;
+    use dragonfly_monitor::exporter::{self, METRICS_PATH};
+
+    let listener = tokio::net::TcpListener::bind(listen)
+        .await
+        .with_context(|| format!("Failed to bind {}", listen))?;
+
+    println!("{}", t!("monitor.exporter_title").bold().bright_cyan());
+    println!(
+        "{}",
+        t!(
+            "monitor.exporter_metrics",
+            url = format!("http://{}{}", listen, METRICS_PATH)
+        )
+    );
+    if listen.ip().is_loopback() {
+        println!("{}", t!("monitor.exporter_local").dimmed());
+    } else {
+        println!(
+            "{}",
+            t!("monitor.exporter_exposed", address = listen.ip()).yellow()
+        );
+    }
+
+    exporter::serve(listener, MetricsCollector::new(), async {
+        let _ = tokio::signal::ctrl_c().await;
+    })
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gpu_row() {
+        let row = gpu_row(&GpuMetrics {
+            utilization_percent: 50.0,
+            memory_in_use_bytes: 1_500_000_000,
+            memory_allocated_bytes: 4_000_000_000,
+        });
+        assert!(row.starts_with("GPU:      50.0% "), "{row}");
+        assert!(row.contains(&format!("{}{}", "█".repeat(10), "░".repeat(10))));
+        assert!(row.ends_with("(1.50 GB in use)"), "{row}");
+    }
+}
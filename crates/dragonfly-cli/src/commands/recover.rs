@@ -1,39 +1,169 @@
 //! Recovery command handler for restoring cleaned files
 
+use crate::commands::audit;
+use crate::error_tracking::breadcrumb;
+use crate::notify::PromptNotifier;
+use crate::ui::print_json;
 use anyhow::Result;
 use colored::Colorize;
-use dragonfly_cleaner::RecoveryManager;
+use dragonfly_cleaner::{
+    AuditAction, AuditEntry, IntegrityProblem, IntegrityReport, RecoveryItem, RecoveryManager,
+    RecoveryManifest,
+};
+use dragonfly_core::domain::scan_options::matches_pattern;
+use dragonfly_core::ports::Notifier;
+use dragonfly_core::t;
+use serde_json::json;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 
-/// List available recoveries
-pub async fn handle_recover_list(json: bool) -> Result<()> {
+/// Record a completed restore of `items` in the audit log
+fn audit_restore(
+    manifest: &RecoveryManifest,
+    items: &[&RecoveryItem],
+    restored_count: usize,
+    restored_size: u64,
+) {
+    audit::record(
+        AuditEntry::new(AuditAction::Restore, audit::command_line())
+            .with_paths(
+                items
+                    .iter()
+                    .map(|item| item.original_path.clone())
+                    .collect(),
+            )
+            .with_items(restored_count)
+            .with_bytes(restored_size)
+            .with_recovery_id(manifest.id.clone()),
+    );
+}
+
+/// Describe an integrity problem in the installed language
+fn describe_problem(problem: &IntegrityProblem) -> String {
+    match problem {
+        IntegrityProblem::Unsealed => t!("recover.problem_unsealed").to_string(),
+        IntegrityProblem::SealMismatch => t!("recover.problem_seal_mismatch").to_string(),
+        IntegrityProblem::MissingKey => t!("recover.problem_missing_key").to_string(),
+        IntegrityProblem::Unreadable { error } => t!("recover.problem_unreadable", error = error),
+        IntegrityProblem::MissingManifest => t!("recover.problem_missing_manifest").to_string(),
+        IntegrityProblem::NotIndexed => t!("recover.problem_not_indexed").to_string(),
+        IntegrityProblem::MissingItem { path } => {
+            t!("recover.problem_missing_item", path = path.display())
+        }
+        IntegrityProblem::SizeMismatch {
+            path,
+            expected,
+            actual,
+        } => t!(
+            "recover.problem_size_mismatch",
+            path = path.display(),
+            expected = expected,
+            actual = actual
+        ),
+        IntegrityProblem::ChecksumMismatch { path } => {
+            t!("recover.problem_checksum_mismatch", path = path.display())
+        }
+    }
+}
+
+/// Print the problems found by `recover list --verify`
+fn print_integrity_report(report: &IntegrityReport) {
+    println!("{}", t!("recover.verify_title").bold().bright_cyan());
+    let print_problems = |label: &str, problems: &[IntegrityProblem]| {
+        for problem in problems {
+            let line = format!("  {}: {}", label, describe_problem(problem));
+            if problem.is_damage() {
+                println!("{}", line.red());
+            } else {
+                println!("{}", line.yellow());
+            }
+        }
+    };
+    print_problems(t!("recover.verify_index"), &report.index);
+    for (id, problems) in &report.recoveries {
+        print_problems(id, problems);
+    }
+    if report.is_intact() {
+        println!(
+            "{}",
+            t!("recover.verify_intact", count = report.checked).green()
+        );
+    } else {
+        println!("{}", t!("recover.verify_damaged").red().bold());
+    }
+}
+
+/// List available recoveries, optionally checking the store's integrity
+pub async fn handle_recover_list(json: bool, verify: bool) -> Result<()> {
     let recovery_dir = RecoveryManager::default_dir();
     let manager = RecoveryManager::new(recovery_dir);
     manager.initialize()?;
 
     let recoveries = manager.list_recoveries()?;
+    let report = if verify {
+        Some(manager.verify()?)
+    } else {
+        None
+    };
 
     if json {
-        println!("{}", serde_json::to_string_pretty(&recoveries)?);
+        match &report {
+            Some(report) => {
+                let json_output = json!({
+                    "status": "ok",
+                    "recoveries": recoveries,
+                    "integrity": {
+                        "intact": report.is_intact(),
+                        "checked": report.checked,
+                        "index": report.index,
+                        "recoveries": report.recoveries,
+                    },
+                });
+                print_json(&json_output)?;
+            }
+            None => print_json(&recoveries)?,
+        }
     } else {
-        println!("{}", "Available Recoveries".bold().bright_cyan());
+        println!("{}", t!("recover.list_title").bold().bright_cyan());
         println!();
         if recoveries.is_empty() {
-            println!("No recoveries available.");
+            println!("{}", t!("recover.list_none"));
         } else {
             for recovery in recoveries {
-                println!("ID: {}", recovery.id);
-                println!("Date: {}", recovery.timestamp.format("%Y-%m-%d %H:%M:%S"));
-                println!("Size: {} bytes", recovery.total_size);
-                println!("Items: {}", recovery.items.len());
+                println!("{}", t!("recover.id", id = recovery.id));
                 println!(
-                    "Retention until: {}",
-                    recovery.retention_until.format("%Y-%m-%d %H:%M:%S")
+                    "{}",
+                    t!(
+                        "recover.restore_date",
+                        date = recovery.timestamp.format("%Y-%m-%d %H:%M:%S")
+                    )
+                );
+                println!("{}", t!("recover.size_bytes", bytes = recovery.total_size));
+                println!("{}", t!("recover.items", count = recovery.items.len()));
+                if let Some(ratio) = recovery.compression_ratio {
+                    println!(
+                        "{}",
+                        t!("recover.compression", ratio = format!("{:.1}", ratio))
+                    );
+                }
+                println!(
+                    "{}",
+                    t!(
+                        "recover.retention",
+                        date = recovery.retention_until.format("%Y-%m-%d %H:%M:%S")
+                    )
                 );
                 println!();
             }
         }
+        if let Some(report) = &report {
+            print_integrity_report(report);
+        }
     }
 
+    if report.is_some_and(|report| !report.is_intact()) {
+        anyhow::bail!("{}", t!("recover.verify_damaged"));
+    }
     Ok(())
 }
 
@@ -46,65 +176,205 @@ pub async fn handle_recover_show(recovery_id: String, json: bool) -> Result<()>
     let manifest = manager.load_manifest(&recovery_id)?;
 
     if json {
-        println!("{}", serde_json::to_string_pretty(&manifest)?);
+        print_json(&manifest)?;
     } else {
-        println!("{}", "Recovery Details".bold().bright_cyan());
-        println!("ID: {}", manifest.id);
-        println!("Date: {}", manifest.timestamp.format("%Y-%m-%d %H:%M:%S"));
-        println!("Total Size: {} bytes", manifest.total_size);
-        println!("Items: {}", manifest.items.len());
+        println!("{}", t!("recover.show_title").bold().bright_cyan());
+        println!("{}", t!("recover.id", id = manifest.id));
+        println!(
+            "{}",
+            t!(
+                "recover.restore_date",
+                date = manifest.timestamp.format("%Y-%m-%d %H:%M:%S")
+            )
+        );
+        println!(
+            "{}",
+            t!("recover.total_size_bytes", bytes = manifest.total_size)
+        );
+        println!("{}", t!("recover.items", count = manifest.items.len()));
+        if let Some(ratio) = manifest.compression_ratio {
+            println!(
+                "{}",
+                t!("recover.compression", ratio = format!("{:.1}", ratio))
+            );
+        }
         println!(
-            "Retention until: {}",
-            manifest.retention_until.format("%Y-%m-%d %H:%M:%S")
+            "{}",
+            t!(
+                "recover.retention",
+                date = manifest.retention_until.format("%Y-%m-%d %H:%M:%S")
+            )
         );
         println!();
-        println!("Items:");
+        println!("{}", t!("recover.items_heading"));
         for item in manifest.items {
             println!("  - {}", item.original_path.display());
-            println!("    Size: {} bytes", item.size);
-            println!("    Category: {}", item.category);
-            println!("    Source: {}", item.source);
+            println!("    {}", t!("recover.size_bytes", bytes = item.size));
+            println!(
+                "    {}",
+                t!("recover.item_category", category = item.category)
+            );
+            println!("    {}", t!("recover.item_source", source = item.source));
         }
     }
 
     Ok(())
 }
 
-/// Restore a recovery
-pub async fn handle_recover_restore(recovery_id: String, json: bool) -> Result<()> {
+/// Restore a recovery, or the items of it matching `patterns` or chosen
+/// interactively
+pub async fn handle_recover_restore(
+    recovery_id: String,
+    patterns: Vec<String>,
+    interactive: bool,
+    json: bool,
+    dry_run: bool,
+) -> Result<()> {
     use humansize::{format_size, DECIMAL};
 
+    // Clap only sees the subcommand's own --json; the global one comes
+    // through `json` too
+    if interactive && json {
+        anyhow::bail!("{}", t!("recover.interactive_json"));
+    }
+
     let recovery_dir = RecoveryManager::default_dir();
     let manager = RecoveryManager::new(recovery_dir);
     manager.initialize()?;
 
+    // Never restore to paths that were changed behind our back
+    if let Some(problem) = manager
+        .verify_recovery(&recovery_id)?
+        .iter()
+        .find(|problem| problem.is_damage())
+    {
+        anyhow::bail!(
+            "{}",
+            t!(
+                "recover.damaged",
+                id = recovery_id,
+                problem = describe_problem(problem)
+            )
+        );
+    }
+
     // Load manifest to show what will be restored
     let manifest = manager.load_manifest(&recovery_id)?;
+    let mut selected: Vec<&RecoveryItem> = matching_items(&manifest, &patterns);
+    if selected.is_empty() && !manifest.items.is_empty() {
+        anyhow::bail!(
+            "{}",
+            t!(
+                "recover.no_match",
+                id = recovery_id,
+                patterns = patterns.join(", ")
+            )
+        );
+    }
+    if interactive {
+        selected = choose_items(&selected).await?;
+        if selected.is_empty() {
+            println!("{}", t!("recover.interactive_none"));
+            return Ok(());
+        }
+    }
+    let size: u64 = selected.iter().map(|item| item.size).sum();
+    let left_archived = manifest.items.len() - selected.len();
+    breadcrumb(
+        "recover",
+        "Restore began",
+        &[
+            ("recovery_id", recovery_id.as_str().into()),
+            ("items", selected.len().into()),
+        ],
+    );
+
+    if dry_run {
+        if json {
+            let json_output = json!({
+                "status": "ok",
+                "dry_run": true,
+                "recovery_id": recovery_id,
+                "files_restored": selected.len(),
+                "bytes_restored": size,
+                "items_left_archived": left_archived,
+                "paths": selected.iter().map(|item| &item.original_path).collect::<Vec<_>>()
+            });
+            print_json(&json_output)?;
+        } else {
+            println!("{}", t!("recover.restore_title").bold().bright_cyan());
+            println!("{}", t!("common.dry_run").yellow());
+            println!(
+                "{}",
+                t!(
+                    "recover.would_restore",
+                    count = selected.len(),
+                    size = format_size(size, DECIMAL)
+                )
+            );
+            for item in &selected {
+                println!("  - {}", item.original_path.display());
+            }
+        }
+        return Ok(());
+    }
+
+    let wanted: HashSet<&Path> = selected
+        .iter()
+        .map(|item| item.archive_path.as_path())
+        .collect();
+    let restore = || {
+        manager.restore_items(&recovery_id, |item| {
+            wanted.contains(item.archive_path.as_path())
+        })
+    };
 
     if json {
-        let (restored_count, restored_size) = manager.restore_recovery(&recovery_id)?;
-        println!(
-            r#"{{"status":"ok","recovery_id":"{}","files_restored":{},"bytes_restored":{}}}"#,
-            recovery_id, restored_count, restored_size
-        );
+        let (restored_count, restored_size) = restore()?;
+        audit_restore(&manifest, &selected, restored_count, restored_size);
+        print_json(&json!({
+            "status": "ok",
+            "recovery_id": recovery_id,
+            "files_restored": restored_count,
+            "bytes_restored": restored_size,
+            "items_left_archived": left_archived
+        }))?;
         return Ok(());
     }
 
-    println!("{}", "Recovery Restore".bold().bright_cyan());
-    println!("Recovery ID: {}", recovery_id);
-    println!("Date: {}", manifest.timestamp.format("%Y-%m-%d %H:%M:%S"));
-    println!("Items to restore: {}", manifest.items.len());
+    if !interactive {
+        println!("{}", t!("recover.restore_title").bold().bright_cyan());
+    }
+    println!("{}", t!("recover.restore_id", id = recovery_id));
+    println!(
+        "{}",
+        t!(
+            "recover.restore_date",
+            date = manifest.timestamp.format("%Y-%m-%d %H:%M:%S")
+        )
+    );
+    println!("{}", t!("recover.restore_count", count = selected.len()));
     println!();
 
     // Restore files
-    match manager.restore_recovery(&recovery_id) {
+    match restore() {
         Ok((restored_count, restored_size)) => {
-            println!("{}", "Restore completed successfully!".green().bold());
-            println!("Files restored: {}", restored_count);
+            audit_restore(&manifest, &selected, restored_count, restored_size);
+            println!("{}", t!("recover.restored").green().bold());
+            println!("{}", t!("recover.restored_files", count = restored_count));
             println!(
-                "Size restored: {}",
-                format_size(restored_size, DECIMAL).bold()
+                "{}",
+                t!(
+                    "recover.restored_size",
+                    size = format_size(restored_size, DECIMAL).bold()
+                )
             );
+            if left_archived > 0 {
+                println!(
+                    "{}",
+                    t!("recover.left_archived", count = left_archived).dimmed()
+                );
+            }
         }
         Err(e) => {
             return Err(anyhow::anyhow!("Failed to restore recovery: {}", e));
@@ -114,22 +384,182 @@ pub async fn handle_recover_restore(recovery_id: String, json: bool) -> Result<(
     Ok(())
 }
 
+/// Items of `manifest` whose original path matches one of `patterns`, or
+/// all of them without patterns
+///
+/// `~/` at the start of a pattern stands for the home folder, as it would
+/// unquoted in the shell.
+fn matching_items<'a>(
+    manifest: &'a RecoveryManifest,
+    patterns: &[String],
+) -> Vec<&'a RecoveryItem> {
+    let home = dirs::home_dir().unwrap_or_default();
+    let patterns: Vec<String> = patterns
+        .iter()
+        .map(|pattern| match pattern.strip_prefix("~/") {
+            Some(rest) => home.join(rest).to_string_lossy().to_string(),
+            None => pattern.clone(),
+        })
+        .collect();
+    manifest
+        .items
+        .iter()
+        .filter(|item| {
+            let path = item.original_path.to_string_lossy();
+            patterns.is_empty()
+                || patterns
+                    .iter()
+                    .any(|pattern| matches_pattern(pattern, &path))
+        })
+        .collect()
+}
+
+/// Offer each folder among `items`, asking whether to restore it
+async fn choose_items<'a>(items: &[&'a RecoveryItem]) -> Result<Vec<&'a RecoveryItem>> {
+    use humansize::{format_size, DECIMAL};
+
+    let groups = group_items(items);
+    println!("{}", t!("recover.restore_title").bold().bright_cyan());
+    println!("{}", t!("recover.interactive_intro", count = groups.len()));
+    println!();
+    let notifier = PromptNotifier::new();
+    let mut chosen = Vec::new();
+    for (path, members) in groups {
+        if notifier.has_quit() {
+            break;
+        }
+        let prompt = t!(
+            "recover.interactive_prompt",
+            path = path.display(),
+            count = members.len(),
+            size = format_size(members.iter().map(|item| item.size).sum::<u64>(), DECIMAL)
+        );
+        if notifier.confirm(&prompt).await? {
+            chosen.extend(members);
+        }
+    }
+    println!();
+    Ok(chosen)
+}
+
+/// Split `items` into the folders an interactive restore offers
+///
+/// Items are grouped by category first, so a clean's caches and logs stay
+/// apart, then by the file or folder directly inside the folder all items
+/// of the category share: one group per app for a clean of
+/// `~/Library/Caches`. Groups keep the order their first items have.
+fn group_items<'a>(items: &[&'a RecoveryItem]) -> Vec<(PathBuf, Vec<&'a RecoveryItem>)> {
+    let mut categories: Vec<(&str, Vec<&'a RecoveryItem>)> = Vec::new();
+    for &item in items {
+        match categories
+            .iter_mut()
+            .find(|(category, _)| *category == item.category)
+        {
+            Some((_, members)) => members.push(item),
+            None => categories.push((&item.category, vec![item])),
+        }
+    }
+
+    let mut groups: Vec<(PathBuf, Vec<&'a RecoveryItem>)> = Vec::new();
+    for (_, members) in categories {
+        let root = common_ancestor(members.iter().map(|item| item.original_path.as_path()));
+        let first_group = groups.len();
+        for item in members {
+            let key = match item
+                .original_path
+                .strip_prefix(&root)
+                .ok()
+                .and_then(|rest| rest.components().next())
+            {
+                Some(child) => root.join(child),
+                None => item.original_path.clone(),
+            };
+            match groups[first_group..]
+                .iter_mut()
+                .find(|(path, _)| *path == key)
+            {
+                Some((_, group)) => group.push(item),
+                None => groups.push((key, vec![item])),
+            }
+        }
+    }
+    groups
+}
+
+/// The deepest path all of `paths` are inside of, or are
+fn common_ancestor<'a>(mut paths: impl Iterator<Item = &'a Path>) -> PathBuf {
+    let Some(first) = paths.next() else {
+        return PathBuf::new();
+    };
+    let mut common = first.to_path_buf();
+    for path in paths {
+        while !path.starts_with(&common) {
+            if !common.pop() {
+                break;
+            }
+        }
+    }
+    common
+}
+
 /// Clean up expired recoveries
-pub async fn handle_recover_cleanup(json: bool) -> Result<()> {
+pub async fn handle_recover_cleanup(json: bool, dry_run: bool) -> Result<()> {
+    use humansize::{format_size, DECIMAL};
+
     let recovery_dir = RecoveryManager::default_dir();
     let manager = RecoveryManager::new(recovery_dir);
     manager.initialize()?;
 
-    let cleaned = manager.cleanup_expired()?;
+    let expired = manager.expired_recoveries()?;
+    let size: u64 = expired.iter().map(|manifest| manifest.total_size).sum();
+    let locations: Vec<_> = expired
+        .iter()
+        .map(|manifest| manager.archive_location(manifest))
+        .collect();
+    let cleaned = if dry_run {
+        expired.into_iter().map(|manifest| manifest.id).collect()
+    } else {
+        manager.cleanup_expired()?
+    };
+
+    if !dry_run && !cleaned.is_empty() {
+        audit::record(
+            AuditEntry::new(AuditAction::RecoveryPurge, audit::command_line())
+                .with_paths(locations)
+                .with_items(cleaned.len())
+                .with_bytes(size),
+        );
+    }
 
     if json {
-        println!(r#"{{"status":"ok","cleaned":{}}}"#, cleaned.len());
+        let json_output = json!({
+            "status": "ok",
+            "dry_run": dry_run,
+            "cleaned": cleaned.len(),
+            "recoveries": cleaned,
+            "bytes": size
+        });
+        print_json(&json_output)?;
     } else {
-        println!("{}", "Recovery Cleanup".bold().bright_cyan());
+        println!("{}", t!("recover.clean_title").bold().bright_cyan());
+        if dry_run {
+            println!("{}", t!("common.dry_run").yellow());
+        }
         if cleaned.is_empty() {
-            println!("No expired recoveries to clean.");
+            println!("{}", t!("recover.clean_none"));
         } else {
-            println!("Cleaned {} expired recoveries:", cleaned.len());
+            let size = format_size(size, DECIMAL);
+            if dry_run {
+                println!(
+                    "{}",
+                    t!("recover.would_clean", count = cleaned.len(), size = size)
+                );
+            } else {
+                println!(
+                    "{}",
+                    t!("recover.cleaned", count = cleaned.len(), size = size)
+                );
+            }
             for id in cleaned {
                 println!("  - {}", id);
             }
@@ -138,3 +568,50 @@ pub async fn handle_recover_cleanup(json: bool) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(path: &str, category: &str) -> RecoveryItem {
+        RecoveryItem {
+            original_path: PathBuf::from(path),
+            archive_path: PathBuf::from(path.trim_start_matches('/')),
+            size: 1,
+            checksum: String::new(),
+            category: category.to_string(),
+            source: "clean".to_string(),
+            can_regenerate: false,
+        }
+    }
+
+    #[test]
+    fn test_group_items_by_category_and_folder() {
+        let items = [
+            item("/u/Library/Caches/com.a/db", "cache"),
+            item("/u/Library/Caches/com.b/x/1", "cache"),
+            item("/u/Library/Caches/com.a/img/2", "cache"),
+            item("/u/Library/Logs/app.log", "logs"),
+        ];
+        let refs: Vec<&RecoveryItem> = items.iter().collect();
+        let groups: Vec<(PathBuf, usize)> = group_items(&refs)
+            .into_iter()
+            .map(|(path, members)| (path, members.len()))
+            .collect();
+        assert_eq!(
+            groups,
+            [
+                (PathBuf::from("/u/Library/Caches/com.a"), 2),
+                (PathBuf::from("/u/Library/Caches/com.b"), 1),
+                (PathBuf::from("/u/Library/Logs/app.log"), 1),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_interactive_restore_refuses_json() {
+        let result = handle_recover_restore("missing".to_string(), Vec::new(), true, true, false);
+        let error = result.await.unwrap_err();
+        assert_eq!(error.to_string(), t!("recover.interactive_json"));
+    }
+}
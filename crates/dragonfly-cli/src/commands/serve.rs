@@ -0,0 +1,289 @@
+//! Local web dashboard
+//!
+//! `dragonfly serve` exposes live metrics, the last disk scan, and clean
+//! estimates as JSON under `/api`. With `--web` it also serves a small
+//! dashboard whose assets are compiled into the binary. The server only ever
+//! binds to the loopback interface and rejects requests addressed to any other
+//! host, so a malicious web page can't reach it through DNS rebinding.
+
+use anyhow::{Context, Result};
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use colored::Colorize;
+use dragonfly_cleaner::{CleanTarget, SystemCleaner};
+use dragonfly_core::domain::is_local_host;
+use dragonfly_core::domain::value_objects::FilePath;
+use dragonfly_core::t;
+use dragonfly_disk::DiskAnalyzer;
+use dragonfly_monitor::MetricsCollector;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock};
+
+const INDEX_HTML: &str = include_str!("../../assets/web/index.html");
+const APP_JS: &str = include_str!("../../assets/web/app.js");
+const STYLE_CSS: &str = include_str!("../../assets/web/style.css");
+
+/// How deep the scan treemap goes below the scanned root
+const TREEMAP_DEPTH: usize = 3;
+
+/// Children kept per treemap node; the rest are folded into one entry
+const TREEMAP_CHILDREN: usize = 24;
+
+/// Shared server state
+struct ServerState {
+    collector: Mutex<MetricsCollector>,
+    scan_root: PathBuf,
+    last_scan: RwLock<Option<Value>>,
+    scan_lock: Mutex<()>,
+}
+
+/// Run the local API server, with the dashboard if `web` is set
+pub async fn handle_serve(web: bool, port: u16, path: PathBuf) -> Result<()> {
+    let scan_root = expand_home(&path);
+    let state = Arc::new(ServerState {
+        collector: Mutex::new(MetricsCollector::new()),
+        scan_root,
+        last_scan: RwLock::new(None),
+        scan_lock: Mutex::new(()),
+    });
+
+    let mut app = Router::new()
+        .route("/api/metrics", get(metrics))
+        .route("/api/scan", get(last_scan).post(run_scan))
+        .route("/api/clean-estimates", get(clean_estimates));
+    if web {
+        app = app
+            .route("/", get(|| asset("text/html; charset=utf-8", INDEX_HTML)))
+            .route(
+                "/app.js",
+                get(|| asset("text/javascript; charset=utf-8", APP_JS)),
+            )
+            .route(
+                "/style.css",
+                get(|| asset("text/css; charset=utf-8", STYLE_CSS)),
+            );
+    }
+    let app = app
+        .layer(middleware::from_fn(require_local_host))
+        .with_state(state.clone());
+
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to bind {}", addr))?;
+
+    println!("{}", t!("serve.title").bold().bright_cyan());
+    if web {
+        println!(
+            "{}",
+            t!("serve.dashboard", url = format!("http://{}/", addr).bold())
+        );
+    }
+    println!("{}", t!("serve.api", url = format!("http://{}/api", addr)));
+    println!(
+        "{}",
+        t!("serve.scan_root", path = state.scan_root.display())
+    );
+    println!("{}", t!("serve.listening").dimmed());
+
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await
+        .context("Server error")?;
+
+    Ok(())
+}
+
+/// Serve an embedded asset
+async fn asset(content_type: &'static str, body: &'static str) -> Response {
+    ([(header::CONTENT_TYPE, content_type)], body).into_response()
+}
+
+/// Reject requests whose Host header isn't a loopback name
+async fn require_local_host(headers: HeaderMap, request: Request, next: Next) -> Response {
+    let host = headers
+        .get(header::HOST)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or_default();
+    if is_local_host(host) {
+        next.run(request).await
+    } else {
+        (StatusCode::FORBIDDEN, "Forbidden").into_response()
+    }
+}
+
+async fn metrics(State(state): State<Arc<ServerState>>) -> Response {
+    let mut collector = state.collector.lock().await;
+    match collector.collect().await {
+        Ok(metrics) => Json(json!({
+            "status": "ok",
+            "metrics": metrics,
+            "collector_overhead": collector.overhead(),
+            "memory_usage_percent": metrics.memory_usage_percent(),
+            "disk_usage_percent": metrics.disk_usage_percent()
+        }))
+        .into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+async fn last_scan(State(state): State<Arc<ServerState>>) -> Json<Value> {
+    Json(json!({
+        "status": "ok",
+        "root": state.scan_root,
+        "scan": *state.last_scan.read().await
+    }))
+}
+
+async fn run_scan(State(state): State<Arc<ServerState>>) -> Response {
+    // One scan at a time; a second request waits and then rescans
+    let _guard = state.scan_lock.lock().await;
+
+    let root = FilePath::new(state.scan_root.to_string_lossy().to_string());
+    let result = match DiskAnalyzer::new().analyze(&root).await {
+        Ok(result) => result,
+        Err(e) => return error_response(e),
+    };
+
+    let mut tree = TreeNode::default();
+    for file in &result.files {
+        let Ok(relative) = Path::new(&file.path).strip_prefix(&state.scan_root) else {
+            continue;
+        };
+        tree.insert(relative, file.size, TREEMAP_DEPTH);
+    }
+
+    let scan = json!({
+        "scanned_at": chrono::Utc::now(),
+        "total_size": result.total_size,
+        "total_files": result.files.len(),
+        "skipped": result.skipped.len(),
+        "tree": tree.to_json(&state.scan_root.display().to_string(), TREEMAP_CHILDREN)
+    });
+    *state.last_scan.write().await = Some(scan.clone());
+
+    Json(json!({ "status": "ok", "root": state.scan_root, "scan": scan })).into_response()
+}
+
+async fn clean_estimates() -> Response {
+    let cleaner = SystemCleaner::new();
+    let mut estimates = Vec::new();
+    for target in [CleanTarget::Caches, CleanTarget::Logs, CleanTarget::Temp] {
+        match cleaner.clean(target, true).await {
+            Ok(result) => estimates.push(CleanEstimate {
+                target: format!("{:?}", target),
+                files: result.files_found.len(),
+                bytes: result.bytes_freed,
+            }),
+            Err(e) => return error_response(e),
+        }
+    }
+
+    let total: u64 = estimates.iter().map(|e| e.bytes).sum();
+    Json(json!({ "status": "ok", "estimates": estimates, "total_bytes": total })).into_response()
+}
+
+#[derive(Serialize)]
+struct CleanEstimate {
+    target: String,
+    files: usize,
+    bytes: u64,
+}
+
+fn error_response(error: impl std::fmt::Display) -> Response {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({ "status": "error", "message": error.to_string() })),
+    )
+        .into_response()
+}
+
+/// Directory sizes aggregated for the treemap
+#[derive(Debug, Default)]
+struct TreeNode {
+    size: u64,
+    children: BTreeMap<String, TreeNode>,
+}
+
+impl TreeNode {
+    /// Add a file's size to every directory on its path, up to `depth` levels
+    fn insert(&mut self, relative: &Path, size: u64, depth: usize) {
+        self.size += size;
+        let mut node = self;
+        for component in relative.iter().take(depth) {
+            node = node
+                .children
+                .entry(component.to_string_lossy().to_string())
+                .or_default();
+            node.size += size;
+        }
+    }
+
+    /// Largest `limit` children, with the remainder folded into one entry
+    fn to_json(&self, name: &str, limit: usize) -> Value {
+        let mut children: Vec<_> = self.children.iter().collect();
+        children.sort_by_key(|(_, child)| std::cmp::Reverse(child.size));
+
+        let mut json_children: Vec<Value> = children
+            .iter()
+            .take(limit)
+            .map(|(child_name, child)| child.to_json(child_name, limit))
+            .collect();
+        let rest: u64 = children.iter().skip(limit).map(|(_, c)| c.size).sum();
+        if rest > 0 {
+            json_children.push(json!({
+                "name": t!("serve.more", count = children.len() - limit),
+                "size": rest,
+                "children": []
+            }));
+        }
+
+        json!({ "name": name, "size": self.size, "children": json_children })
+    }
+}
+
+/// Expand a leading `~` to the home directory
+fn expand_home(path: &Path) -> PathBuf {
+    match path.strip_prefix("~") {
+        Ok(rest) => dirs::home_dir()
+            .map(|home| home.join(rest))
+            .unwrap_or_else(|| path.to_path_buf()),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tree_node_aggregates_and_folds() {
+        let mut tree = TreeNode::default();
+        tree.insert(Path::new("a/x/file1"), 10, TREEMAP_DEPTH);
+        tree.insert(Path::new("a/file2"), 5, TREEMAP_DEPTH);
+        tree.insert(Path::new("b"), 1, TREEMAP_DEPTH);
+        tree.insert(Path::new("c"), 2, TREEMAP_DEPTH);
+
+        assert_eq!(tree.size, 18);
+        assert_eq!(tree.children["a"].size, 15);
+        assert_eq!(tree.children["a"].children["x"].size, 10);
+
+        let json = tree.to_json("root", 2);
+        let children = json["children"].as_array().unwrap();
+        assert_eq!(children[0]["name"], "a");
+        assert_eq!(children[1]["name"], "c");
+        assert_eq!(children[2]["name"], "(1 more)");
+        assert_eq!(children[2]["size"], 1);
+    }
+}
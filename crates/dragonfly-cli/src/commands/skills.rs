@@ -2,6 +2,7 @@
 //!
 //! This command provides a quick reference guide for common DragonFly workflows.
 
+use crate::ui::print_json;
 use anyhow::Result;
 use colored::Colorize;
 
@@ -62,13 +63,18 @@ pub async fn handle_skills(json: bool) -> Result<()> {
                 "safe_clean": "dragonfly clean --dry-run"
             }
         });
-        println!("{}", serde_json::to_string_pretty(&json_output)?);
+        print_json(&json_output)?;
         return Ok(());
     }
 
     // Human-friendly formatted output
     println!();
-    println!("{}", "🐉 DragonFly Skills - Workflow Cheat Sheet".bold().bright_cyan());
+    println!(
+        "{}",
+        "🐉 DragonFly Skills - Workflow Cheat Sheet"
+            .bold()
+            .bright_cyan()
+    );
     println!("{}", "===========================================".dimmed());
     println!();
 
@@ -83,8 +89,14 @@ pub async fn handle_skills(json: bool) -> Result<()> {
 
     // Safety Rules
     println!("{}", "Safety Rules:".bold().bright_yellow());
-    println!("  • Prefer {} when available before deletion/cleanup", "--dry-run".green());
-    println!("  • For duplicates, use {} before deleting anything", "--interactive".green());
+    println!(
+        "  • Prefer {} when available before deletion/cleanup",
+        "--dry-run".green()
+    );
+    println!(
+        "  • For duplicates, use {} before deleting anything",
+        "--interactive".green()
+    );
     println!("  • Avoid scanning system-critical folders: /System, /private, etc.");
     println!();
 
@@ -94,12 +106,24 @@ pub async fn handle_skills(json: bool) -> Result<()> {
     println!();
     println!("   Commands:");
     println!("   {}", "dragonfly disk analyze ~/".green());
-    println!("   {}", "dragonfly disk analyze ~/ --json > report.json".green());
-    println!("   {}", "dragonfly disk analyze ~/ --min-size 500MB".green());
+    println!(
+        "   {}",
+        "dragonfly disk analyze ~/ --json > report.json".green()
+    );
+    println!(
+        "   {}",
+        "dragonfly disk analyze ~/ --min-size 500MB".green()
+    );
     println!();
     println!("   Notes:");
-    println!("   • Use {} for automation/ingestion into other tooling", "--json".cyan());
-    println!("   • Use {} to focus on big items first", "--min-size".cyan());
+    println!(
+        "   • Use {} for automation/ingestion into other tooling",
+        "--json".cyan()
+    );
+    println!(
+        "   • Use {} to focus on big items first",
+        "--min-size".cyan()
+    );
     println!();
 
     // Duplicate Scan
@@ -108,11 +132,17 @@ pub async fn handle_skills(json: bool) -> Result<()> {
     println!();
     println!("   Commands:");
     println!("   {}", "dragonfly duplicates scan ~/Pictures".green());
-    println!("   {}", "dragonfly duplicates scan ~/Documents --interactive".green());
+    println!(
+        "   {}",
+        "dragonfly duplicates scan ~/Documents --interactive".green()
+    );
     println!("   {}", "dragonfly duplicates scan ~/ --dry-run".green());
     println!();
     println!("   Notes:");
-    println!("   • Prefer {} for human confirmation", "--interactive".cyan());
+    println!(
+        "   • Prefer {} for human confirmation",
+        "--interactive".cyan()
+    );
     println!("   • Prefer {} to preview actions", "--dry-run".cyan());
     println!();
 
@@ -135,23 +165,41 @@ pub async fn handle_skills(json: bool) -> Result<()> {
     println!("   {}", "dragonfly clean --dry-run".green());
     println!();
     println!("   Notes:");
-    println!("   • Run without {} only after verifying what will be removed", "--dry-run".cyan());
+    println!(
+        "   • Run without {} only after verifying what will be removed",
+        "--dry-run".cyan()
+    );
     println!();
 
     // macOS-specific tips
     println!("{}", "macOS-Specific Path Tips:".bold().bright_yellow());
-    println!("   • External disks: usually under {}", "/Volumes/<DiskName>/...".cyan());
-    println!("   • Photos library: {}", "~/Pictures/Photos Library.photoslibrary".cyan());
-    println!("   • iOS backups: {}", "~/Library/Application Support/MobileSync/Backup/".cyan());
+    println!(
+        "   • External disks: usually under {}",
+        "/Volumes/<DiskName>/...".cyan()
+    );
+    println!(
+        "   • Photos library: {}",
+        "~/Pictures/Photos Library.photoslibrary".cyan()
+    );
+    println!(
+        "   • iOS backups: {}",
+        "~/Library/Application Support/MobileSync/Backup/".cyan()
+    );
     println!();
 
     // Quick Recipes
     println!("{}", "Quick Recipes:".bold().bright_magenta());
     println!("   • What's huge in Downloads?");
-    println!("     {}", "dragonfly disk analyze ~/Downloads --min-size 200MB".green());
+    println!(
+        "     {}",
+        "dragonfly disk analyze ~/Downloads --min-size 200MB".green()
+    );
     println!();
     println!("   • Find duplicates in Pictures safely:");
-    println!("     {}", "dragonfly duplicates scan ~/Pictures --dry-run".green());
+    println!(
+        "     {}",
+        "dragonfly duplicates scan ~/Pictures --dry-run".green()
+    );
     println!();
     println!("   • Clean safely:");
     println!("     {}", "dragonfly clean --dry-run".green());
@@ -159,7 +207,10 @@ pub async fn handle_skills(json: bool) -> Result<()> {
 
     // Output tips
     println!("{}", "Output for Automation:".bold());
-    println!("   • JSON output: {}", "dragonfly disk analyze <path> --json > report.json".cyan());
+    println!(
+        "   • JSON output: {}",
+        "dragonfly disk analyze <path> --json > report.json".cyan()
+    );
     println!("   • Keep reports in: {}", "~/dragonfly-reports/".cyan());
     println!();
 
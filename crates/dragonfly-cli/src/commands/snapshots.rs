@@ -0,0 +1,165 @@
+//! Saved scan snapshots command handler
+
+use super::analyze::parse_size;
+use crate::types::SnapshotsCommand;
+use crate::ui::print_json;
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use dragonfly_core::t;
+use dragonfly_disk::{SavedFile, SavedScan};
+use humansize::{format_size, DECIMAL};
+use serde_json::json;
+use std::cmp::Reverse;
+use std::path::{Path, PathBuf};
+
+/// File a saved scan argument refers to: a bare name (no directory or
+/// extension) is a scan in `~/.dragonfly/scans`, anything else a file path
+pub(crate) fn saved_scan_file(value: &Path) -> Result<PathBuf> {
+    if value.components().count() == 1 && value.extension().is_none() {
+        Ok(SavedScan::named(
+            &SavedScan::default_dir(),
+            &value.to_string_lossy(),
+        )?)
+    } else {
+        Ok(value.to_path_buf())
+    }
+}
+
+/// The file of the scan saved as `name`, which must exist
+fn existing_scan(name: &str) -> Result<PathBuf> {
+    let file = SavedScan::named(&SavedScan::default_dir(), name)?;
+    if !file.exists() {
+        bail!(t!("disk.snapshot_not_found", name = name));
+    }
+    Ok(file)
+}
+
+/// List, show, or delete scans saved by name
+pub fn handle_snapshots(command: SnapshotsCommand, json: bool, dry_run: bool) -> Result<()> {
+    match command {
+        SnapshotsCommand::List { json: cmd_json } => {
+            let scans =
+                SavedScan::list(&SavedScan::default_dir()).context("Failed to list saved scans")?;
+            if json || cmd_json {
+                return print_json(&json!({
+                    "status": "ok",
+                    "scans": scans
+                }));
+            }
+            println!("{}", t!("disk.snapshots_title").bold().bright_cyan());
+            if scans.is_empty() {
+                println!("{}", t!("disk.snapshots_none"));
+                return Ok(());
+            }
+            for scan in &scans {
+                println!(
+                    "  {:<20} {}  {:>10}  {}  {}",
+                    scan.name.bold(),
+                    scan.scanned_at
+                        .with_timezone(&chrono::Local)
+                        .format("%Y-%m-%d %H:%M"),
+                    format_size(scan.total_size, DECIMAL),
+                    t!("disk.tree_files", count = scan.file_count).dimmed(),
+                    scan.root.display()
+                );
+            }
+        }
+        SnapshotsCommand::Show {
+            name,
+            top,
+            min_size,
+            sort,
+            json: cmd_json,
+        } => {
+            let file = existing_scan(&name)?;
+            let scan = SavedScan::load(&file)
+                .with_context(|| format!("Failed to read saved scan {}", file.display()))?;
+            let min_bytes = min_size.as_deref().map(parse_size).transpose()?;
+            let mut files: Vec<&SavedFile> = scan
+                .files
+                .iter()
+                .filter(|f| min_bytes.map_or(true, |min| f.size >= min))
+                .collect();
+            match sort.as_str() {
+                "size" => files.sort_by_key(|f| (Reverse(f.size), &f.path)),
+                "path" => files.sort_by_key(|f| &f.path),
+                other => bail!("{}", t!("disk.unknown_sort", sort = other)),
+            }
+            let matching = files.len();
+            files.truncate(top);
+
+            if json || cmd_json {
+                return print_json(&json!({
+                    "status": "ok",
+                    "name": name,
+                    "root": scan.root,
+                    "scanned_at": scan.scanned_at,
+                    "total_size": scan.total_size,
+                    "file_count": scan.files.len(),
+                    "matching_files": matching,
+                    "files": files.iter().map(|f| json!({
+                        "path": scan.root.join(&f.path),
+                        "size": f.size
+                    })).collect::<Vec<_>>()
+                }));
+            }
+            println!(
+                "{}",
+                t!("disk.snapshot_title", name = name).bold().bright_cyan()
+            );
+            println!("{}", t!("disk.path", path = scan.root.display()));
+            println!(
+                "{}",
+                t!(
+                    "disk.snapshot_scanned",
+                    date = scan
+                        .scanned_at
+                        .with_timezone(&chrono::Local)
+                        .format("%Y-%m-%d %H:%M")
+                )
+            );
+            println!(
+                "{}\n",
+                t!(
+                    "disk.tree_total",
+                    size = format_size(scan.total_size, DECIMAL),
+                    count = scan.files.len()
+                )
+            );
+            for (i, f) in files.iter().enumerate() {
+                println!(
+                    "{:3}. {} - {}",
+                    i + 1,
+                    format_size(f.size, DECIMAL).bold(),
+                    scan.root.join(&f.path).display()
+                );
+            }
+        }
+        SnapshotsCommand::Delete {
+            name,
+            json: cmd_json,
+        } => {
+            let file = existing_scan(&name)?;
+            if !dry_run {
+                std::fs::remove_file(&file)
+                    .with_context(|| format!("Failed to delete {}", file.display()))?;
+            }
+            if json || cmd_json {
+                return print_json(&json!({
+                    "status": "ok",
+                    "name": name,
+                    "path": file,
+                    "deleted": !dry_run,
+                    "dry_run": dry_run
+                }));
+            }
+            if dry_run {
+                println!("{}", t!("common.dry_run").yellow());
+                println!("{}", t!("disk.snapshot_would_delete", name = name));
+            } else {
+                println!("{}", t!("disk.snapshot_deleted", name = name).green());
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,154 @@
+//! Time Machine snapshot command handler
+
+use crate::commands::audit;
+use crate::ui::print_json;
+use anyhow::{bail, Result};
+use colored::Colorize;
+use dragonfly_cleaner::{AuditAction, AuditEntry, TimeMachineManager};
+use dragonfly_core::t;
+use serde_json::json;
+
+/// Delete a single snapshot, or all snapshots older than a number of days
+pub async fn handle_time_machine_delete(
+    id: Option<String>,
+    older_than: Option<u32>,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    let deleted = match (id, older_than) {
+        (Some(id), None) => vec![id],
+        (None, Some(days)) => TimeMachineManager::old_snapshots(days)?,
+        _ => bail!("{}", t!("time_machine.delete_target")),
+    };
+
+    if !dry_run {
+        // Audit only what tmutil actually removed, even when a later one fails
+        let mut removed = Vec::new();
+        let mut failure = None;
+        for id in &deleted {
+            match TimeMachineManager::delete_snapshot(id) {
+                Ok(()) => removed.push(id.clone()),
+                Err(e) => {
+                    failure = Some(e);
+                    break;
+                }
+            }
+        }
+        if !removed.is_empty() {
+            audit::record(
+                AuditEntry::new(AuditAction::SnapshotDelete, audit::command_line())
+                    .with_paths(removed.iter().map(Into::into).collect())
+                    .with_items(removed.len()),
+            );
+        }
+        if let Some(e) = failure {
+            return Err(e.into());
+        }
+    }
+
+    if json {
+        let json_output = json!({
+            "status": "ok",
+            "dry_run": dry_run,
+            "snapshots": deleted,
+            "count": deleted.len()
+        });
+        print_json(&json_output)?;
+        return Ok(());
+    }
+
+    println!("{}", t!("time_machine.delete_title").bold().bright_cyan());
+    if dry_run {
+        println!("{}", t!("common.dry_run").yellow());
+    }
+    println!();
+
+    if deleted.is_empty() {
+        println!("{}", t!("time_machine.delete_none"));
+    } else {
+        let key = if dry_run {
+            "time_machine.would_delete"
+        } else {
+            "time_machine.deleted"
+        };
+        println!("{}", t!(key, count = deleted.len()));
+        for id in &deleted {
+            println!("  - {}", id);
+        }
+    }
+
+    Ok(())
+}
+
+/// Have macOS thin local snapshots until `bytes` are free, or all it can
+/// without a limit
+pub fn handle_time_machine_thin(bytes: Option<u64>, dry_run: bool, json: bool) -> Result<()> {
+    let target = bytes.unwrap_or(THIN_ALL);
+    let thinned = if dry_run {
+        // macOS decides which snapshots go; show every candidate
+        TimeMachineManager::list_snapshots()?
+            .into_iter()
+            .map(|snapshot| snapshot.id)
+            .collect()
+    } else {
+        TimeMachineManager::thin_snapshots(target)?
+    };
+
+    if !dry_run && !thinned.is_empty() {
+        audit::record(
+            AuditEntry::new(AuditAction::SnapshotDelete, audit::command_line())
+                .with_paths(thinned.iter().map(Into::into).collect())
+                .with_items(thinned.len()),
+        );
+    }
+
+    if json {
+        let json_output = json!({
+            "status": "ok",
+            "dry_run": dry_run,
+            "bytes": bytes,
+            "snapshots": thinned,
+            "count": thinned.len()
+        });
+        print_json(&json_output)?;
+        return Ok(());
+    }
+
+    println!("{}", t!("time_machine.thin_title").bold().bright_cyan());
+    if dry_run {
+        println!("{}", t!("common.dry_run").yellow());
+    }
+    println!();
+
+    if thinned.is_empty() {
+        println!("{}", t!("time_machine.thin_none"));
+        return Ok(());
+    }
+    let key = if dry_run {
+        "time_machine.thin_candidates"
+    } else {
+        "time_machine.thinned"
+    };
+    println!("{}", t!(key, count = thinned.len()));
+    for id in &thinned {
+        println!("  - {}", id);
+    }
+    Ok(())
+}
+
+/// Purge amount asking `tmutil` to thin every snapshot it can
+const THIN_ALL: u64 = i64::MAX as u64;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_delete_needs_an_id_or_age() {
+        for (id, older_than) in [(None, None), (Some("id".to_string()), Some(30))] {
+            let result = handle_time_machine_delete(id, older_than, true, true);
+            let error = result.await.unwrap_err();
+            assert_eq!(error.to_string(), t!("time_machine.delete_target"));
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! Trash command handlers
+
+use crate::commands::audit;
+use crate::notify::PromptNotifier;
+use crate::ui::{print_json, print_skipped};
+use anyhow::{bail, Result};
+use colored::Colorize;
+use dragonfly_cleaner::{AuditAction, AuditEntry, Trash, TrashEmptied};
+use dragonfly_core::ports::Notifier;
+use dragonfly_core::t;
+use humansize::{format_size, DECIMAL};
+use serde_json::json;
+
+/// Show the size of every trash
+pub fn handle_trash_status(json: bool) -> Result<()> {
+    let trashes: Vec<(Trash, usize, u64)> = Trash::all()
+        .into_iter()
+        .map(|trash| {
+            let items = trash.items();
+            let bytes = items.iter().map(|item| item.bytes).sum();
+            (trash, items.len(), bytes)
+        })
+        .collect();
+    let items: usize = trashes.iter().map(|(_, items, _)| items).sum();
+    let bytes: u64 = trashes.iter().map(|(_, _, bytes)| bytes).sum();
+
+    if json {
+        print_json(&json!({
+            "status": "ok",
+            "trashes": trashes.iter().map(|(trash, items, bytes)| json!({
+                "path": trash.path,
+                "volume": trash.volume,
+                "items": items,
+                "bytes": bytes
+            })).collect::<Vec<_>>(),
+            "items": items,
+            "bytes": bytes
+        }))?;
+        return Ok(());
+    }
+
+    println!("{}", t!("trash.title").bold().bright_cyan());
+    println!();
+    if items == 0 {
+        println!("{}", t!("trash.none"));
+        return Ok(());
+    }
+    for (trash, items, bytes) in &trashes {
+        println!(
+            "  {}",
+            t!(
+                "trash.line",
+                name = trash_name(trash),
+                size = format_size(*bytes, DECIMAL),
+                count = items
+            )
+        );
+    }
+    println!(
+        "{}",
+        t!(
+            "trash.total",
+            size = format_size(bytes, DECIMAL).bold(),
+            count = items
+        )
+    );
+    println!("{}", t!("trash.empty_hint").dimmed());
+    Ok(())
+}
+
+/// Permanently delete what's in every trash, or only what was trashed at
+/// least `older_than` ago, once the user confirms or `yes` is given
+pub async fn handle_trash_empty(
+    older_than: Option<chrono::Duration>,
+    dry_run: bool,
+    yes: bool,
+    json: bool,
+) -> Result<()> {
+    let ask = !dry_run && !yes;
+    if ask && json {
+        bail!(t!("trash.empty_json"));
+    }
+    let cutoff = older_than.map(|age| chrono::Utc::now() - age);
+    if ask {
+        let pending = empty_all(cutoff, true);
+        if pending.removed.is_empty() {
+            return report(cutoff, pending, false, json);
+        }
+        let prompt = t!(
+            "trash.empty_confirm",
+            count = pending.removed.len(),
+            size = format_size(pending.bytes(), DECIMAL)
+        );
+        if !PromptNotifier::new().confirm(&prompt).await? {
+            println!("{}", t!("trash.empty_declined").yellow());
+            return Ok(());
+        }
+    }
+    let emptied = empty_all(cutoff, dry_run);
+
+    if !dry_run {
+        audit::record_refused(&emptied.skipped);
+        if !emptied.removed.is_empty() {
+            audit::record(
+                AuditEntry::new(AuditAction::Delete, audit::command_line())
+                    .with_paths(
+                        emptied
+                            .removed
+                            .iter()
+                            .map(|item| item.path.clone())
+                            .collect(),
+                    )
+                    .with_items(emptied.removed.len())
+                    .with_bytes(emptied.bytes()),
+            );
+        }
+    }
+    report(cutoff, emptied, dry_run, json)
+}
+
+/// Empty every trash, or with `dry_run` only list what would go
+fn empty_all(cutoff: Option<chrono::DateTime<chrono::Utc>>, dry_run: bool) -> TrashEmptied {
+    let mut emptied = TrashEmptied::default();
+    for trash in Trash::all() {
+        let trash = trash.empty(cutoff, dry_run);
+        emptied.removed.extend(trash.removed);
+        emptied.kept += trash.kept;
+        emptied.skipped.extend(trash.skipped);
+    }
+    emptied
+}
+
+/// Print what emptying the trash removed, or would remove
+fn report(
+    cutoff: Option<chrono::DateTime<chrono::Utc>>,
+    emptied: TrashEmptied,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    if json {
+        print_json(&json!({
+            "status": "ok",
+            "dry_run": dry_run,
+            "trashed_before": cutoff,
+            "removed": emptied.removed,
+            "items": emptied.removed.len(),
+            "bytes": emptied.bytes(),
+            "kept": emptied.kept,
+            "skipped": emptied.skipped
+        }))?;
+        return Ok(());
+    }
+
+    println!("{}", t!("trash.title").bold().bright_cyan());
+    if dry_run {
+        println!("{}", t!("common.dry_run").yellow());
+    }
+    if let Some(cutoff) = cutoff {
+        println!(
+            "{}",
+            t!(
+                "trash.older_than",
+                date = cutoff
+                    .with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M")
+            )
+        );
+    }
+    println!();
+
+    let key = if dry_run {
+        "trash.would_delete"
+    } else {
+        "trash.deleted"
+    };
+    println!(
+        "{}",
+        t!(
+            key,
+            count = emptied.removed.len(),
+            size = format_size(emptied.bytes(), DECIMAL).bold()
+        )
+    );
+    if emptied.kept > 0 {
+        println!("{}", t!("trash.kept", count = emptied.kept).dimmed());
+    }
+    print_skipped(&emptied.skipped);
+    Ok(())
+}
+
+/// The volume a trash belongs to, or the home folder
+fn trash_name(trash: &Trash) -> String {
+    match &trash.volume {
+        Some(volume) => volume.display().to_string(),
+        None => t!("trash.home").to_string(),
+    }
+}
@@ -0,0 +1,216 @@
+//! Usage trends command handler and history recording helpers
+
+use crate::ui::{print_json, sparkline};
+use anyhow::Result;
+use chrono::{Duration, NaiveDate, Utc};
+use colored::Colorize;
+use dragonfly_cleaner::{AuditAction, AuditLog};
+use dragonfly_core::t;
+use dragonfly_disk::{mount_point, History, LargestFiles, Trend, VolumeSpace};
+use humansize::{format_size, DECIMAL};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Widest sparkline printed, in characters
+const SPARKLINE_WIDTH: usize = 60;
+
+/// Record the totals of a complete scan in the default history
+///
+/// Like the audit log, history is a side record of something that already
+/// happened, so failing to write it is only a warning.
+pub fn record_scan(path: &Path, total_size: u64, file_count: u64) {
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let recorded = History::open(&History::default_path())
+        .and_then(|history| history.record_scan(&path, total_size, file_count));
+    if let Err(e) = recorded {
+        tracing::warn!(error = %e, "Failed to record scan history");
+    }
+}
+
+/// Keep the largest of `files` (paths relative to `path`, and sizes) from a
+/// complete scan of `path`, returning those kept from the scan before
+pub fn track_largest_files(path: &Path, files: &[(String, u64)]) -> Option<LargestFiles> {
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let tracked = History::open(&History::default_path()).and_then(|history| {
+        let previous = history.largest_files(&path)?;
+        history.record_largest_files(&path, files)?;
+        Ok(previous)
+    });
+    tracked.unwrap_or_else(|e| {
+        tracing::warn!(error = %e, "Failed to track largest files");
+        None
+    })
+}
+
+/// Record the current free space of the volume holding `path` in the
+/// default history
+pub fn record_free_space(path: &Path) {
+    let measured = VolumeSpace::of(path).and_then(|space| Ok((mount_point(path)?, space)));
+    let (volume, space) = match measured {
+        Ok(measured) => measured,
+        Err(e) => {
+            tracing::warn!(error = %e, path = %path.display(), "Failed to measure free space for history");
+            return;
+        }
+    };
+    let recorded = History::open(&History::default_path())
+        .and_then(|history| history.record_free_space(&volume, space.available));
+    if let Err(e) = recorded {
+        tracing::warn!(error = %e, "Failed to record free space history");
+    }
+}
+
+/// Chart scanned folder size, free space, and bytes cleaned over time
+///
+/// Sizes come from `disk analyze` runs, free space from analyses, cleans, and
+/// the free space watch, and cleaned bytes from the audit log.
+pub async fn handle_trends(path: Option<PathBuf>, days: u32, json: bool) -> Result<()> {
+    let since = Utc::now() - Duration::days(i64::from(days));
+    let history = History::open(&History::default_path())?;
+
+    let path = path.map(|path| std::fs::canonicalize(&path).unwrap_or(path));
+    let (scanned, volumes) = match path {
+        Some(ref path) => (vec![path.clone()], vec![mount_point(path)?]),
+        None => (history.scanned_paths(since)?, history.volumes(since)?),
+    };
+    let mut free_space = Vec::new();
+    for volume in volumes {
+        let trend = Trend::of(&history.free_space(&volume, since)?);
+        free_space.push((volume, trend));
+    }
+    let mut scans = Vec::new();
+    for path in scanned {
+        let trend = Trend::of(&history.scan_sizes(&path, since)?);
+        scans.push((path, trend));
+    }
+
+    // Bytes cleaned per day, from deletions in the audit log
+    let mut cleaned: BTreeMap<NaiveDate, u64> = BTreeMap::new();
+    let mut operations = 0;
+    for entry in AuditLog::new(AuditLog::default_path()).entries()? {
+        if entry.action == AuditAction::Delete && entry.timestamp >= since {
+            *cleaned.entry(entry.timestamp.date_naive()).or_default() += entry.bytes;
+            operations += 1;
+        }
+    }
+    let cleaned_total: u64 = cleaned.values().sum();
+
+    if json {
+        let series = |key: &str, entries: &[(PathBuf, Option<Trend>)]| {
+            entries
+                .iter()
+                .map(|(path, trend)| {
+                    json!({
+                        key: path,
+                        "trend": trend,
+                        "per_day": trend.as_ref().map(Trend::per_day),
+                        "accelerating": trend.as_ref().map(Trend::accelerating)
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+        let json_output = json!({
+            "status": "ok",
+            "days": days,
+            "free_space": series("volume", &free_space),
+            "scans": series("path", &scans),
+            "cleaned": {
+                "bytes": cleaned_total,
+                "operations": operations,
+                "daily": cleaned
+                    .iter()
+                    .map(|(day, bytes)| json!({ "date": day, "bytes": bytes }))
+                    .collect::<Vec<_>>()
+            }
+        });
+        print_json(&json_output)?;
+        return Ok(());
+    }
+
+    println!("{}", t!("trends.title", days = days).bold().bright_cyan());
+    if free_space.is_empty() && scans.is_empty() && cleaned.is_empty() {
+        println!("\n{}", t!("trends.empty"));
+        return Ok(());
+    }
+
+    for (volume, trend) in &free_space {
+        println!("\n{}  {}", t!("trends.free_space").bold(), volume.display());
+        print_trend(trend.as_ref());
+    }
+    for (path, trend) in &scans {
+        println!("\n{}  {}", t!("trends.scanned").bold(), path.display());
+        print_trend(trend.as_ref());
+    }
+
+    println!("\n{}", t!("trends.cleaned").bold());
+    if cleaned.is_empty() {
+        println!("  {}", t!("trends.nothing_cleaned").dimmed());
+    } else {
+        // One bar per day of the period, including days without cleaning
+        let first = since.date_naive();
+        let daily: Vec<u64> = (0..=i64::from(days))
+            .map(|offset| {
+                cleaned
+                    .get(&(first + Duration::days(offset)))
+                    .copied()
+                    .unwrap_or(0)
+            })
+            .collect();
+        println!(
+            "  {}  {}",
+            sparkline(&daily, SPARKLINE_WIDTH).green(),
+            t!(
+                "trends.cleaned_total",
+                size = format_size(cleaned_total, DECIMAL),
+                count = operations
+            )
+        );
+    }
+    Ok(())
+}
+
+/// Print the sparkline, change, and pace of one series
+fn print_trend(trend: Option<&Trend>) {
+    let Some(trend) = trend else {
+        println!("  {}", t!("trends.not_enough").dimmed());
+        return;
+    };
+    let values: Vec<u64> = trend.daily.iter().map(|sample| sample.value).collect();
+    let first = values.first().copied().unwrap_or(0);
+    let last = values.last().copied().unwrap_or(0);
+    println!(
+        "  {}  {} → {}  ({}, {})",
+        sparkline(&values, SPARKLINE_WIDTH).bright_cyan(),
+        format_size(first, DECIMAL),
+        format_size(last, DECIMAL),
+        signed_size(trend.change as f64),
+        t!("trends.per_day", rate = signed_size(trend.per_day()))
+    );
+
+    let recent = signed_size(trend.recent_per_day);
+    let earlier = signed_size(trend.earlier_per_day);
+    let pace = if !trend.accelerating() {
+        t!("trends.steady", recent = recent, earlier = earlier).normal()
+    } else if trend.recent_per_day > 0.0 {
+        t!("trends.growing_faster", recent = recent, earlier = earlier).yellow()
+    } else {
+        t!(
+            "trends.shrinking_faster",
+            recent = recent,
+            earlier = earlier
+        )
+        .yellow()
+    };
+    println!("  {}", pace);
+}
+
+/// Format a byte change with an explicit sign
+fn signed_size(bytes: f64) -> String {
+    let sign = if bytes < 0.0 { '-' } else { '+' };
+    format!(
+        "{}{}",
+        sign,
+        format_size(bytes.abs().round() as u64, DECIMAL)
+    )
+}
@@ -0,0 +1,349 @@
+//! Free space watch command handler
+
+use super::analyze::parse_size;
+use super::trends;
+use crate::notify::DesktopNotifier;
+use crate::ui::print_json;
+use anyhow::{Context, Result};
+use chrono::Local;
+use colored::Colorize;
+use dragonfly_core::ports::{CancellationToken, Notifier};
+use dragonfly_core::t;
+use dragonfly_disk::VolumeSpace;
+use humansize::{format_size, DECIMAL};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+/// launchd label of the background agent
+const AGENT_LABEL: &str = "com.dragonfly.watch";
+
+/// How often the watch checks for Ctrl-C between measurements
+const POLL_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// After an alert, free space has to climb this fraction above the threshold
+/// before the next drop alerts again, so hovering around it doesn't spam
+const REARM_MARGIN: f64 = 0.05;
+
+/// Watch free space, or install or remove the background agent doing so
+///
+/// A notification is sent each time free space on the volume holding `path`
+/// drops below `free_below`, not on every check while it stays low.
+pub async fn handle_watch(
+    free_below: Option<String>,
+    path: Option<PathBuf>,
+    interval: u64,
+    install: bool,
+    uninstall: bool,
+    json: bool,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let agent = dirs::home_dir()
+        .context("Could not determine home directory")?
+        .join("Library/LaunchAgents")
+        .join(format!("{}.plist", AGENT_LABEL));
+    if uninstall {
+        return uninstall_agent(&agent, json);
+    }
+
+    let free_below = free_below.context("--free-below is required")?;
+    let threshold = parse_size(&free_below)?;
+    let path = match path {
+        Some(path) => path,
+        None => dirs::home_dir().context("Could not determine home directory")?,
+    };
+    let path = std::fs::canonicalize(&path)
+        .with_context(|| format!("Invalid path: {}", path.display()))?;
+    let interval = Duration::from_secs(interval.max(1));
+
+    if install {
+        return install_agent(&agent, &free_below, &path, interval, json);
+    }
+
+    if !json {
+        println!(
+            "{}",
+            t!(
+                "watch.started",
+                path = path.display(),
+                threshold = format_size(threshold, DECIMAL),
+                interval = interval.as_secs()
+            )
+            .dimmed()
+        );
+    }
+    let notifier = DesktopNotifier::new();
+    let mut alarm = Alarm::new(threshold);
+    while !cancel.is_cancelled() {
+        let available = VolumeSpace::of(&path)
+            .with_context(|| format!("Failed to measure free space on {}", path.display()))?
+            .available;
+        trends::record_free_space(&path);
+        let free = format_size(available, DECIMAL);
+        let limit = format_size(threshold, DECIMAL);
+
+        match alarm.check(available) {
+            Some(Crossing::Below) => {
+                report_event(json, "below", available, threshold, || {
+                    t!("watch.below", free = free, threshold = limit).yellow()
+                });
+                let body = t!("watch.notify_body", free = free, threshold = limit);
+                if let Err(e) = notifier.notify(t!("watch.notify_title"), &body).await {
+                    tracing::warn!(error = %e, "Failed to send notification");
+                }
+            }
+            Some(Crossing::Recovered) => {
+                report_event(json, "recovered", available, threshold, || {
+                    t!("watch.recovered", free = free).green()
+                });
+            }
+            None => {}
+        }
+
+        let deadline = Instant::now() + interval;
+        while !cancel.is_cancelled() {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            tokio::time::sleep(POLL_TIMEOUT.min(deadline - now)).await;
+        }
+    }
+    Ok(())
+}
+
+/// A change of free space across the threshold worth reporting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Crossing {
+    /// Dropped below the threshold
+    Below,
+    /// Climbed back above it by the re-arm margin
+    Recovered,
+}
+
+/// Tracks whether a low-space alert is outstanding, so a drop is reported
+/// once rather than on every check while space stays low
+struct Alarm {
+    threshold: u64,
+    rearm_above: u64,
+    alerted: bool,
+}
+
+impl Alarm {
+    fn new(threshold: u64) -> Self {
+        Self {
+            threshold,
+            rearm_above: threshold + (threshold as f64 * REARM_MARGIN) as u64,
+            alerted: false,
+        }
+    }
+
+    /// The crossing a measurement of `available` bytes makes, if any
+    fn check(&mut self, available: u64) -> Option<Crossing> {
+        if available < self.threshold && !self.alerted {
+            self.alerted = true;
+            Some(Crossing::Below)
+        } else if available >= self.rearm_above && self.alerted {
+            self.alerted = false;
+            Some(Crossing::Recovered)
+        } else {
+            None
+        }
+    }
+}
+
+/// Print a threshold crossing, as a JSON line or a timestamped message
+fn report_event(
+    json: bool,
+    event: &str,
+    available: u64,
+    threshold: u64,
+    message: impl FnOnce() -> colored::ColoredString,
+) {
+    let now = Local::now();
+    if json {
+        let line = json!({
+            "event": event,
+            "time": now.to_rfc3339(),
+            "available": available,
+            "threshold": threshold
+        });
+        println!("{}", line);
+    } else {
+        println!("{}  {}", now.format("%Y-%m-%d %H:%M"), message());
+    }
+}
+
+/// Write the launchd agent and load it, so the watch runs at every login
+fn install_agent(
+    agent: &Path,
+    free_below: &str,
+    path: &Path,
+    interval: Duration,
+    json: bool,
+) -> Result<()> {
+    let exe = std::env::current_exe().context("Could not locate the dragonfly executable")?;
+    let log = dirs::home_dir()
+        .context("Could not determine home directory")?
+        .join(".dragonfly/watch.log");
+    let args = [
+        exe.to_string_lossy().to_string(),
+        "watch".to_string(),
+        "--free-below".to_string(),
+        free_below.to_string(),
+        "--interval".to_string(),
+        interval.as_secs().to_string(),
+        "--path".to_string(),
+        path.to_string_lossy().to_string(),
+    ];
+
+    if let Some(parent) = agent.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if let Some(parent) = log.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // Replace an agent installed earlier with other settings
+    if agent.exists() {
+        let _ = launchctl("unload", agent);
+    }
+    std::fs::write(agent, launch_agent_plist(&args, &log))
+        .with_context(|| format!("Failed to write {}", agent.display()))?;
+    launchctl("load", agent)?;
+
+    if json {
+        let json_output = json!({
+            "status": "ok",
+            "installed": agent,
+            "free_below": free_below,
+            "path": path,
+            "interval_secs": interval.as_secs()
+        });
+        print_json(&json_output)?;
+    } else {
+        println!("{}", t!("watch.installed", agent = agent.display()));
+        println!("{}", t!("watch.uninstall_hint").dimmed());
+    }
+    Ok(())
+}
+
+/// Unload and delete the launchd agent
+fn uninstall_agent(agent: &Path, json: bool) -> Result<()> {
+    let installed = agent.exists();
+    if installed {
+        let _ = launchctl("unload", agent);
+        std::fs::remove_file(agent)
+            .with_context(|| format!("Failed to remove {}", agent.display()))?;
+    }
+
+    if json {
+        let json_output = json!({ "status": "ok", "removed": installed.then_some(agent) });
+        print_json(&json_output)?;
+    } else if installed {
+        println!("{}", t!("watch.uninstalled"));
+    } else {
+        println!("{}", t!("watch.not_installed"));
+    }
+    Ok(())
+}
+
+fn launchctl(action: &str, agent: &Path) -> Result<()> {
+    let status = Command::new("launchctl")
+        .args([action, "-w"])
+        .arg(agent)
+        .status()
+        .context("Failed to run launchctl")?;
+    anyhow::ensure!(
+        status.success(),
+        "launchctl {} failed with {}",
+        action,
+        status
+    );
+    Ok(())
+}
+
+/// Property list for a launchd agent that keeps `args` running
+fn launch_agent_plist(args: &[String], log: &Path) -> String {
+    let arguments: String = args
+        .iter()
+        .map(|arg| format!("        <string>{}</string>\n", xml_escape(arg)))
+        .collect();
+    let log = xml_escape(&log.to_string_lossy());
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{AGENT_LABEL}</string>
+    <key>ProgramArguments</key>
+    <array>
+{arguments}    </array>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <true/>
+    <key>ProcessType</key>
+    <string>Background</string>
+    <key>StandardOutPath</key>
+    <string>{log}</string>
+    <key>StandardErrorPath</key>
+    <string>{log}</string>
+</dict>
+</plist>
+"#
+    )
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alarm_reports_each_drop_once() {
+        let mut alarm = Alarm::new(1000);
+        assert_eq!(alarm.check(2000), None);
+        assert_eq!(alarm.check(999), Some(Crossing::Below));
+        assert_eq!(alarm.check(500), None);
+        assert_eq!(alarm.check(999), None);
+        // Back above the threshold but inside the margin: still low
+        assert_eq!(alarm.check(1040), None);
+        assert_eq!(alarm.check(900), None);
+        assert_eq!(alarm.check(1050), Some(Crossing::Recovered));
+        assert_eq!(alarm.check(2000), None);
+        assert_eq!(alarm.check(10), Some(Crossing::Below));
+    }
+
+    #[test]
+    fn test_launch_agent_plist() {
+        let args = [
+            "/usr/local/bin/dragonfly".to_string(),
+            "watch".to_string(),
+            "--path".to_string(),
+            "/Volumes/R&D <2>".to_string(),
+        ];
+        let plist = launch_agent_plist(&args, Path::new("/Users/me/.dragonfly/watch.log"));
+
+        assert!(plist.contains("<string>com.dragonfly.watch</string>"));
+        assert!(plist.contains(
+            "    <array>\n        <string>/usr/local/bin/dragonfly</string>\n        \
+             <string>watch</string>\n        <string>--path</string>\n        \
+             <string>/Volumes/R&amp;D &lt;2&gt;</string>\n    </array>"
+        ));
+        assert_eq!(
+            plist
+                .matches("<string>/Users/me/.dragonfly/watch.log</string>")
+                .count(),
+            2
+        );
+        assert!(plist.contains("<key>RunAtLoad</key>\n    <true/>"));
+        assert!(plist.trim_end().ends_with("</plist>"));
+    }
+}
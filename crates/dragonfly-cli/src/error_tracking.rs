@@ -6,9 +6,11 @@
 //! Since GlitchTip is Sentry API-compatible, we use the Sentry SDK,
 //! but configured to only work with local/self-hosted GlitchTip instances.
 
+use sentry::protocol::{Breadcrumb, Map, Value};
 use sentry::{init, ClientInitGuard};
 use std::borrow::Cow;
 use std::env;
+use std::path::Path;
 
 /// Error tracking configuration
 #[derive(Debug, Clone)]
@@ -114,6 +116,55 @@ pub fn load_config() -> ErrorTrackingConfig {
     }
 }
 
+/// Record a command lifecycle stage as a breadcrumb
+///
+/// Breadcrumbs are attached to the next error report, so a report from a failed
+/// clean shows which target was being cleaned and how far it got. When error
+/// tracking is disabled there is no active client and this does nothing, not
+/// even build the breadcrumb.
+pub fn breadcrumb(category: &str, message: &str, data: &[(&str, Value)]) {
+    sentry::add_breadcrumb(|| Breadcrumb {
+        category: Some(category.to_string()),
+        message: Some(message.to_string()),
+        data: data
+            .iter()
+            .map(|(key, value)| ((*key).to_string(), value.clone()))
+            .collect::<Map<_, _>>(),
+        ..Default::default()
+    });
+}
+
+/// Path for a breadcrumb, with the home directory replaced by `~`
+///
+/// Reports are sent with `send_default_pii` off, so the user name embedded in
+/// home paths shouldn't leak through breadcrumbs either.
+pub fn breadcrumb_path(path: &Path) -> Value {
+    let path = match dirs::home_dir().and_then(|home| path.strip_prefix(home).ok()) {
+        Some(relative) => Path::new("~").join(relative),
+        None => path.to_path_buf(),
+    };
+    Value::String(path.display().to_string())
+}
+
+/// Check whether a DSN points at this machine
+///
+/// Only a loopback or `.local` host keeps error reports on the local network
+/// segment; anything else sends them elsewhere.
+pub fn is_local_dsn(dsn: &str) -> bool {
+    let Some((_, rest)) = dsn.split_once("://") else {
+        return false;
+    };
+    let authority = rest.split('/').next().unwrap_or_default();
+    let host_port = authority.rsplit('@').next().unwrap_or_default();
+    let host = if let Some(bracketed) = host_port.strip_prefix('[') {
+        bracketed.split(']').next().unwrap_or_default()
+    } else {
+        host_port.split(':').next().unwrap_or_default()
+    };
+
+    matches!(host, "localhost" | "127.0.0.1" | "::1") || host.ends_with(".local")
+}
+
 /// Extract DSN from config file content
 ///
 /// Supports `.glitchtiprc` format:
@@ -181,6 +232,65 @@ mod tests {
         );
     }
 
+    #[derive(Default)]
+    struct CapturingTransport(std::sync::Mutex<Vec<sentry::Envelope>>);
+
+    impl sentry::Transport for CapturingTransport {
+        fn send_envelope(&self, envelope: sentry::Envelope) {
+            self.0.lock().unwrap().push(envelope);
+        }
+    }
+
+    #[test]
+    fn test_breadcrumbs_attach_to_reports() {
+        let transport = std::sync::Arc::new(CapturingTransport::default());
+        let client = sentry::Client::from(sentry::ClientOptions {
+            dsn: "http://key@localhost:8000/1".parse().ok(),
+            transport: Some(std::sync::Arc::new(transport.clone())),
+            ..Default::default()
+        });
+        let hub = std::sync::Arc::new(sentry::Hub::new(
+            Some(std::sync::Arc::new(client)),
+            std::sync::Arc::new(sentry::Scope::default()),
+        ));
+
+        sentry::Hub::run(hub, || {
+            breadcrumb("clean", "Deletion began", &[("target", "Caches".into())]);
+            sentry::capture_message("failed", sentry::Level::Error);
+        });
+
+        let envelopes = transport.0.lock().unwrap();
+        let event = envelopes[0].event().unwrap();
+        assert_eq!(event.breadcrumbs.len(), 1);
+        assert_eq!(event.breadcrumbs[0].category.as_deref(), Some("clean"));
+        assert_eq!(event.breadcrumbs[0].data["target"], "Caches");
+    }
+
+    #[test]
+    fn test_breadcrumb_path_hides_home() {
+        if let Some(home) = dirs::home_dir() {
+            assert_eq!(
+                breadcrumb_path(&home.join("Library/Caches")),
+                Value::String("~/Library/Caches".to_string())
+            );
+        }
+        assert_eq!(
+            breadcrumb_path(Path::new("/tmp")),
+            Value::String("/tmp".to_string())
+        );
+    }
+
+    #[test]
+    fn test_is_local_dsn() {
+        assert!(is_local_dsn("http://key@localhost:8000/1"));
+        assert!(is_local_dsn("http://key@127.0.0.1/1"));
+        assert!(is_local_dsn("http://key@[::1]:8000/1"));
+        assert!(is_local_dsn("https://key@glitchtip.local/1"));
+        assert!(!is_local_dsn("https://key@glitchtip.example.com/1"));
+        assert!(!is_local_dsn("https://localhost@evil.example.com/1"));
+        assert!(!is_local_dsn("not a dsn"));
+    }
+
     #[test]
     fn test_mask_dsn() {
         let dsn = "https://abc123def456@localhost:8000/789";
@@ -0,0 +1,32 @@
+//! Ctrl-C handling for long-running scans and cleans
+//!
+//! The first Ctrl-C asks the running operation to stop; it finishes the item
+//! in progress, reports what it has so far, and the process exits with
+//! [`EXIT_INTERRUPTED`]. A second Ctrl-C exits immediately.
+
+use dragonfly_core::ports::CancellationToken;
+
+/// Exit code used when an operation was interrupted (128 + SIGINT)
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+/// Install the Ctrl-C handler and return the token it cancels
+pub fn install() -> CancellationToken {
+    let token = CancellationToken::new();
+    let handler_token = token.clone();
+
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        eprintln!(
+            "\nInterrupted - finishing up and reporting partial results (Ctrl-C again to abort)"
+        );
+        handler_token.cancel();
+
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(EXIT_INTERRUPTED);
+        }
+    });
+
+    token
+}
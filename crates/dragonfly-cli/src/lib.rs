@@ -5,10 +5,19 @@
 
 pub mod commands;
 pub mod error_tracking;
+pub mod interrupt;
+pub mod locale;
+pub mod nice;
+pub mod notify;
+pub mod policy;
+pub mod redact;
 pub mod types;
 pub mod ui;
 
-pub use types::{DiskCommand, DuplicatesCommand, RecoverCommand, TimeMachineCommand};
+pub use types::{
+    AuditCommand, CleanCommand, DiskCommand, DuplicatesCommand, MonitorCommand, RecoverCommand,
+    SnapshotsCommand, TimeMachineCommand, TrashCommand,
+};
 
 /// CLI version
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -0,0 +1,76 @@
+//! Locale selection for CLI and TUI output
+//!
+//! The language is taken from the first of these that is set:
+//! 1. `DRAGONFLY_LANG` environment variable
+//! 2. `locale = <name>` in `~/.dragonfly/config`
+//! 3. The standard `LC_ALL`, `LC_MESSAGES`, and `LANG` variables
+//!
+//! Catalogs in `~/.dragonfly/locales` (`de.catalog`, `pt_BR.catalog`, ...)
+//! add translations or override built-in ones.
+
+use dragonfly_core::i18n::{self, fallback_chain, normalize_locale, Catalog, FALLBACK_LOCALE};
+use std::path::PathBuf;
+
+/// Variables consulted after `DRAGONFLY_LANG` and the config file
+const SYSTEM_LOCALE_VARS: &[&str] = &["LC_ALL", "LC_MESSAGES", "LANG"];
+
+/// Select the locale and install its catalog for the rest of the process
+pub fn init() {
+    let locale = select_locale(|name| std::env::var(name).ok(), config_locale());
+    let mut catalog = Catalog::built_in(&locale);
+    if let Some(dir) = dragonfly_dir().map(|dir| dir.join("locales")) {
+        for candidate in fallback_chain(&locale) {
+            let path = dir.join(format!("{}.catalog", candidate));
+            let Ok(source) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            if let Err(e) = catalog.merge(&source) {
+                tracing::warn!(error = %e, path = %path.display(), "Ignoring malformed message catalog");
+            }
+        }
+    }
+    i18n::install(catalog);
+}
+
+/// First usable locale from the environment or config, English otherwise
+fn select_locale(env: impl Fn(&str) -> Option<String>, config: Option<String>) -> String {
+    env("DRAGONFLY_LANG")
+        .or(config)
+        .into_iter()
+        .chain(SYSTEM_LOCALE_VARS.iter().filter_map(|name| env(name)))
+        .find_map(|value| normalize_locale(&value))
+        .unwrap_or_else(|| FALLBACK_LOCALE.to_string())
+}
+
+/// `locale` setting from `~/.dragonfly/config`
+fn config_locale() -> Option<String> {
+    let config = std::fs::read_to_string(dragonfly_dir()?.join("config")).ok()?;
+    config.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        (key.trim() == "locale").then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+fn dragonfly_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".dragonfly"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_select_locale_precedence() {
+        let vars = HashMap::from([("LANG", "fr_FR.UTF-8"), ("LC_ALL", "C")]);
+        let env = |name: &str| vars.get(name).map(|value| value.to_string());
+
+        assert_eq!(select_locale(env, None), "fr_FR");
+        assert_eq!(select_locale(env, Some("de".to_string())), "de");
+        assert_eq!(select_locale(|_| None, None), "en");
+
+        let vars = HashMap::from([("DRAGONFLY_LANG", "ja"), ("LANG", "fr_FR")]);
+        let env = |name: &str| vars.get(name).map(|value| value.to_string());
+        assert_eq!(select_locale(env, Some("de".to_string())), "ja");
+    }
+}
@@ -9,11 +9,24 @@ use clap::{Parser, Subcommand};
 use colored::Colorize;
 use tracing_subscriber::EnvFilter;
 
-use dragonfly_cli::commands::{analyze, clean, duplicates, health, monitor, recover};
+use dragonfly_cli::commands::{
+    analyze, apps, audit, capabilities, clean, doctor, duplicates, health, index, monitor,
+    recover, time_machine, trash, trends, watch,
+};
+#[cfg(feature = "web")]
+use dragonfly_cli::commands::serve;
 #[cfg(feature = "skills")]
 use dragonfly_cli::commands::skills;
-use dragonfly_cli::error_tracking::{init_error_tracking, load_config};
-use dragonfly_cli::{DiskCommand, DuplicatesCommand, RecoverCommand, TimeMachineCommand};
+use dragonfly_cli::error_tracking::{breadcrumb, init_error_tracking, load_config};
+use dragonfly_cli::ui::print_json;
+use dragonfly_cli::{interrupt, locale, nice, policy, redact};
+use dragonfly_core::domain::throttle::{self, Throttle};
+use dragonfly_core::t;
+use dragonfly_monitor::AlertRule;
+use dragonfly_cli::{
+    AuditCommand, CleanCommand, DiskCommand, DuplicatesCommand, MonitorCommand, RecoverCommand,
+    TimeMachineCommand, TrashCommand,
+};
 
 #[derive(Parser)]
 #[command(
@@ -42,6 +55,31 @@ struct Cli {
     #[arg(global = true, long)]
     json: bool,
 
+    /// Preview destructive commands: report what would change, change nothing
+    #[arg(global = true, long)]
+    dry_run: bool,
+
+    /// Replace your username and configured path segments in JSON output
+    #[arg(global = true, long)]
+    redact_paths: bool,
+
+    /// Run with low CPU and disk priority and fewer threads
+    #[arg(global = true, long)]
+    nice: bool,
+
+    /// Read at most this many filesystem entries per second (2000 with
+    /// `--throttle` alone), on top of the low priority of `--nice`
+    #[arg(
+        global = true,
+        long,
+        value_name = "ENTRIES_PER_SEC",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "2000",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    throttle: Option<u32>,
+
     /// Enable error tracking (GlitchTip only) - sends errors to local/self-hosted server
     #[arg(global = true, long)]
     enable_error_tracking: bool,
@@ -64,23 +102,54 @@ enum Commands {
     },
 
     /// Monitor system in real-time
-    #[command(about = "Monitor CPU, memory, disk, and network usage")]
+    #[command(
+        about = "Monitor CPU, memory, disk, and network usage",
+        args_conflicts_with_subcommands = true
+    )]
     Monitor {
+        #[command(subcommand)]
+        command: Option<MonitorCommand>,
+
         /// Update interval in seconds
         #[arg(short, long, default_value = "5")]
         interval: u64,
 
+        /// Show this many processes using the most CPU and memory (0 hides them)
+        #[arg(long, default_value = "5")]
+        top: usize,
+
+        /// Show a usage bar for each CPU core
+        #[arg(long)]
+        per_core: bool,
+
+        /// Samples charted in the sparklines beside the CPU, memory and disk
+        /// bars (0 hides them)
+        #[arg(long = "sparkline", value_name = "SAMPLES", default_value = "30")]
+        sparkline: usize,
+
+        /// Alert when a figure crosses a threshold, e.g. "disk>90", "mem>95"
+        /// or "battery<20" (cpu, mem, swap, disk, load, battery; repeatable)
+        #[arg(long = "alert", value_name = "EXPR")]
+        alerts: Vec<AlertRule>,
+
+        /// What a firing alert does: bell, notify or exec:<command>
+        /// (repeatable; default notify)
+        #[arg(long = "alert-action", value_name = "ACTION")]
+        alert_actions: Vec<String>,
+
         /// Run in JSON output mode
         #[arg(long)]
         json: bool,
     },
 
     /// Clean caches and temporary files
-    #[command(about = "Clean system caches and temporary files")]
+    #[command(
+        about = "Clean system caches and temporary files",
+        args_conflicts_with_subcommands = true
+    )]
     Clean {
-        /// Perform a dry run (don't actually delete)
-        #[arg(long)]
-        dry_run: bool,
+        #[command(subcommand)]
+        command: Option<CleanCommand>,
 
         /// Clean all (caches, logs, temps)
         #[arg(long)]
@@ -98,9 +167,47 @@ enum Commands {
         #[arg(long)]
         temp: bool,
 
-        /// Interactive mode (confirm each deletion)
+        /// Clean Xcode DerivedData, Archives, iOS DeviceSupport for older
+        /// versions, and simulators whose runtime is gone
+        #[arg(long)]
+        xcode: bool,
+
+        /// Show how much Docker's stopped containers, dangling images and
+        /// unused build cache take; nothing is removed without --prune
+        #[arg(long, conflicts_with_all = ["all", "caches", "logs", "temp", "xcode"])]
+        docker: bool,
+
+        /// With --docker, prune what it reports after asking for confirmation
+        #[arg(long, requires = "docker")]
+        prune: bool,
+
+        /// List iPhone and iPad backups with their device, date and size, and
+        /// with --interactive choose stale ones to delete
+        #[arg(
+            long,
+            conflicts_with_all = ["all", "caches", "logs", "temp", "xcode", "docker", "scope"]
+        )]
+        ios_backups: bool,
+
+        /// Ask before cleaning each folder; with --dry-run, list the files
+        /// that would be cleaned
         #[arg(short, long)]
         interactive: bool,
+
+        /// Only locations under your home (user) or only shared ones like
+        /// /Library/Caches and /var/log (system, needs sudo); default both
+        #[arg(long, value_parser = ["user", "system"])]
+        scope: Option<String>,
+
+        /// Only clean files last modified at least this many days ago, keeping
+        /// recent logs and caches
+        #[arg(long, value_name = "DAYS")]
+        older_than: Option<u32>,
+
+        /// Delete files outright instead of moving them to recovery, so the
+        /// space is freed now but the clean can't be undone
+        #[arg(long)]
+        permanent: bool,
     },
 
     /// System health check
@@ -114,11 +221,113 @@ enum Commands {
         #[arg(short, long)]
         recommend: bool,
 
-        /// Check specific component (disk, memory, cpu)
+        /// Check specific component (disk, memory, cpu, swap, battery, access)
         #[arg(short, long)]
         component: Option<String>,
     },
 
+    /// Installed applications
+    #[command(about = "List installed apps by size and last use, highlighting large unused ones")]
+    Apps {
+        /// Highlight apps not opened for this many days
+        #[arg(long, default_value = "90")]
+        unused_days: i64,
+
+        /// Only highlight apps at least this large, bundle and data together
+        #[arg(long, default_value = "500MB")]
+        min_size: String,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Persistent size index
+    #[command(about = "Index file sizes so disk queries are answered without rescanning")]
+    Index {
+        /// Folders to index (default: home directory)
+        roots: Vec<std::path::PathBuf>,
+
+        /// Keep the index current by watching for changes until Ctrl-C
+        #[arg(long, conflicts_with_all = ["status", "remove"])]
+        daemon: bool,
+
+        /// List indexed folders and when they were last updated
+        #[arg(long, conflicts_with = "remove")]
+        status: bool,
+
+        /// Remove the folders from the index
+        #[arg(long)]
+        remove: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Free space watch
+    #[command(about = "Notify when free disk space drops below a threshold, now or at every login")]
+    Watch {
+        /// Notify when free space drops below this, e.g. 20GB
+        #[arg(long, value_name = "SIZE", required_unless_present = "uninstall")]
+        free_below: Option<String>,
+
+        /// Volume to watch, given by any path on it (default: home directory)
+        #[arg(long)]
+        path: Option<std::path::PathBuf>,
+
+        /// Seconds between checks
+        #[arg(long, default_value = "300")]
+        interval: u64,
+
+        /// Run the watch in the background at every login (launchd agent)
+        #[arg(long, conflicts_with = "uninstall")]
+        install: bool,
+
+        /// Remove the background watch
+        #[arg(long)]
+        uninstall: bool,
+
+        /// Output as JSON (one line per event while watching)
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Usage trends
+    #[command(about = "Chart folder growth, free space, and bytes cleaned over time")]
+    Trends {
+        /// Only show this scanned folder and its volume (default: everything recorded)
+        path: Option<std::path::PathBuf>,
+
+        /// How many days back to chart
+        #[arg(long, default_value = "90")]
+        days: u32,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Self-checks of the installation
+    #[command(about = "Check the installation, e.g. attest that nothing can use the network")]
+    Doctor {
+        /// Audit features and configuration for anything that could send data off this machine
+        #[arg(long)]
+        privacy: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Report what this build and machine support
+    #[command(about = "Report compiled features, subsystems, and platform abilities")]
+    Capabilities {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
     /// Recover cleaned files
     #[command(about = "Manage and restore cleaned files")]
     Recover {
@@ -133,6 +342,20 @@ enum Commands {
         command: TimeMachineCommand,
     },
 
+    /// Trash on the startup and external volumes
+    #[command(about = "Show how much the trash holds and empty it, optionally only older items")]
+    Trash {
+        #[command(subcommand)]
+        command: TrashCommand,
+    },
+
+    /// Audit log of destructive operations
+    #[command(about = "Review everything DragonFly has deleted, restored, or removed")]
+    Audit {
+        #[command(subcommand)]
+        command: AuditCommand,
+    },
+
     /// Display workflow cheat sheet
     #[cfg(feature = "skills")]
     #[command(about = "Display DragonFly workflow cheat sheet and quick reference")]
@@ -142,6 +365,23 @@ enum Commands {
         json: bool,
     },
 
+    /// Local web dashboard
+    #[cfg(feature = "web")]
+    #[command(about = "Serve metrics and scan results on localhost, optionally with a browser dashboard")]
+    Serve {
+        /// Also serve the browser dashboard
+        #[arg(long)]
+        web: bool,
+
+        /// Port to listen on (bound to localhost only)
+        #[arg(short, long, default_value = "7878")]
+        port: u16,
+
+        /// Directory scanned from the dashboard
+        #[arg(long, default_value = "~")]
+        path: std::path::PathBuf,
+    },
+
     /// Retro defrag-style TUI for disk cleanup
     #[cfg(feature = "tui")]
     #[command(about = "Launch retro defrag-style terminal UI for disk scanning and cleanup")]
@@ -149,6 +389,10 @@ enum Commands {
         /// Path to scan
         #[arg(default_value = "~")]
         path: String,
+
+        /// Search for duplicate files instead of sizing the tree
+        #[arg(long)]
+        duplicates: bool,
     },
 }
 
@@ -168,38 +412,206 @@ async fn main() -> Result<()> {
     // Initialize logging
     init_logging(cli.debug)?;
 
+    // Select the output language; logging is up so a bad catalog is reported
+    locale::init();
+
+    // Every destructive command honors the user's protected locations
+    policy::init()?;
+    redact::init(cli.redact_paths)?;
+    // Throttling also lowers priority, or the rate limit alone would still
+    // compete with interactive work at full priority
+    nice::init(cli.nice || cli.throttle.is_some());
+    if let Some(rate) = cli.throttle {
+        throttle::install(Throttle::new(rate));
+    }
+
+    // Only the subcommand name; arguments may contain paths
+    let command_name = std::env::args()
+        .skip(1)
+        .find(|arg| !arg.starts_with('-'))
+        .unwrap_or_default();
+    breadcrumb(
+        "cli",
+        "Arguments parsed",
+        &[("command", command_name.into()), ("json", cli.json.into())],
+    );
+
     // Print header
-    if !cli.json {
+    if !cli.json && !writes_export(&cli.command) {
         print_header();
     }
 
+    // Scans and cleans stop gracefully on Ctrl-C; other commands keep the
+    // default behavior
+    let cancel = match cli.command {
+        Commands::Disk { .. }
+        | Commands::Duplicates { .. }
+        | Commands::Clean { .. }
+        | Commands::Apps { .. }
+        | Commands::Index { .. }
+        | Commands::Watch { .. } => Some(interrupt::install()),
+        _ => None,
+    };
+    let token = cancel.clone().unwrap_or_default();
+
     let result = match cli.command {
-        Commands::Disk { command } => analyze::handle_disk(command, cli.json).await,
-        Commands::Duplicates { command } => duplicates::handle_duplicates(command, cli.json).await,
-        Commands::Monitor { interval, json } => monitor::handle_monitor(interval, json).await,
+        Commands::Disk { command } => {
+            analyze::handle_disk(command, cli.json, cli.dry_run, &token).await
+        }
+        Commands::Duplicates { command } => {
+            duplicates::handle_duplicates(command, cli.json, cli.dry_run, &token).await
+        }
+        Commands::Monitor {
+            command: Some(MonitorCommand::History { since, json }),
+            ..
+        } => monitor::handle_history(since, json || cli.json),
+        Commands::Monitor {
+            command:
+                Some(MonitorCommand::Process {
+                    target,
+                    interval,
+                    count,
+                    json,
+                }),
+            ..
+        } => monitor::handle_process(&target, interval, count, json || cli.json).await,
+        #[cfg(feature = "exporter")]
+        Commands::Monitor {
+            command: Some(MonitorCommand::Serve { listen }),
+            ..
+        } => monitor::handle_serve(listen).await,
+        Commands::Monitor {
+            command: None,
+            interval,
+            top,
+            per_core,
+            sparkline,
+            alerts,
+            alert_actions,
+            json,
+        } => {
+            monitor::handle_monitor(
+                interval,
+                top,
+                per_core,
+                sparkline,
+                alerts,
+                alert_actions,
+                json,
+            )
+            .await
+        }
+        Commands::Clean {
+            command: Some(CleanCommand::Orphans { interactive, json }),
+            ..
+        } => clean::handle_orphans(cli.dry_run, interactive, json || cli.json, &token).await,
+        Commands::Clean {
+            command: Some(CleanCommand::History { json }),
+            ..
+        } => clean::handle_history(json || cli.json),
         Commands::Clean {
-            dry_run,
+            command: None,
             all,
             caches,
             logs,
             temp,
+            xcode,
+            docker,
+            prune,
+            ios_backups,
             interactive,
-        } => clean::handle_clean(dry_run, all, caches, logs, temp, interactive, cli.json).await,
+            scope,
+            older_than,
+            permanent,
+        } => {
+            clean::handle_clean(
+                cli.dry_run,
+                all,
+                caches,
+                logs,
+                temp,
+                xcode,
+                docker,
+                prune,
+                ios_backups,
+                interactive,
+                scope.as_deref(),
+                older_than,
+                permanent,
+                cli.json,
+                &token,
+            )
+            .await
+        }
         Commands::Health {
             json,
             recommend,
             component,
         } => health::handle_health(json, recommend, component, cli.json).await,
+        Commands::Apps {
+            unused_days,
+            min_size,
+            json,
+        } => apps::handle_apps(unused_days, min_size, json || cli.json, &token).await,
+        Commands::Index {
+            roots,
+            daemon,
+            status,
+            remove,
+            json,
+        } => index::handle_index(roots, daemon, status, remove, json || cli.json, &token).await,
+        Commands::Watch {
+            free_below,
+            path,
+            interval,
+            install,
+            uninstall,
+            json,
+        } => {
+            watch::handle_watch(
+                free_below,
+                path,
+                interval,
+                install,
+                uninstall,
+                json || cli.json,
+                &token,
+            )
+            .await
+        }
+        Commands::Trends { path, days, json } => {
+            trends::handle_trends(path, days, json || cli.json).await
+        }
+        Commands::Doctor { privacy, json } => {
+            doctor::handle_doctor(cli.enable_error_tracking, privacy, json || cli.json).await
+        }
+        Commands::Capabilities { json } => {
+            capabilities::handle_capabilities(json || cli.json).await
+        }
         Commands::Recover { command } => match command {
-            RecoverCommand::List { json } => recover::handle_recover_list(json || cli.json).await,
+            RecoverCommand::List { verify, json } => {
+                recover::handle_recover_list(json || cli.json, verify).await
+            }
             RecoverCommand::Show { id, json } => {
                 recover::handle_recover_show(id, json || cli.json).await
             }
-            RecoverCommand::Restore { id, json } => {
-                recover::handle_recover_restore(id, json || cli.json).await
+            RecoverCommand::Restore {
+                id,
+                items,
+                interactive,
+                json,
+            } => {
+                recover::handle_recover_restore(
+                    id,
+                    items,
+                    interactive,
+                    json || cli.json,
+                    cli.dry_run,
+                )
+                .await
             }
             RecoverCommand::Cleanup { json } => {
-                recover::handle_recover_cleanup(json || cli.json).await
+                recover::handle_recover_cleanup(json || cli.json, cli.dry_run).await
             }
         },
         Commands::TimeMachine { command } => match command {
@@ -219,36 +631,66 @@ async fn main() -> Result<()> {
                         })).collect::<Vec<_>>(),
                         "count": snapshots.len()
                     });
-                    println!("{}", serde_json::to_string_pretty(&json_output)?);
+                    print_json(&json_output)?;
                 } else {
-                    println!("{}", "Time Machine Snapshots".bold().bright_cyan());
+                    println!("{}", t!("time_machine.title").bold().bright_cyan());
                     println!();
                     if snapshots.is_empty() {
-                        println!("No local snapshots found.");
+                        println!("{}", t!("time_machine.none"));
                     } else {
-                        println!("Found {} local snapshot(s):\n", snapshots.len());
+                        println!("{}\n", t!("time_machine.found", count = snapshots.len()));
                         for (i, snapshot) in snapshots.iter().enumerate() {
                             println!("{}. {}", i + 1, snapshot.id);
-                            println!("   Date: {}", snapshot.date);
+                            println!("   {}", t!("time_machine.date", date = snapshot.date));
                             if let Some(size) = snapshot.size {
-                                println!("   Size: {}", format_size(size, DECIMAL));
+                                println!(
+                                    "   {}",
+                                    t!("time_machine.size", size = format_size(size, DECIMAL))
+                                );
                             }
                             println!();
                         }
-                        println!(
-                            "{}",
-                            "Note: Use 'tmutil deletelocalsnapshot <id>' to delete snapshots"
-                                .dimmed()
-                        );
+                        println!("{}", t!("time_machine.delete_hint").dimmed());
                     }
                 }
                 Ok(())
             }
+            TimeMachineCommand::Delete {
+                id,
+                older_than,
+                json,
+            } => {
+                time_machine::handle_time_machine_delete(
+                    id,
+                    older_than,
+                    cli.dry_run,
+                    json || cli.json,
+                )
+                .await
+            }
+            TimeMachineCommand::Thin { bytes, json } => {
+                time_machine::handle_time_machine_thin(bytes, cli.dry_run, json || cli.json)
+            }
+        },
+        Commands::Trash { command } => match command {
+            TrashCommand::Status { json } => trash::handle_trash_status(json || cli.json),
+            TrashCommand::Empty {
+                older_than,
+                yes,
+                json,
+            } => trash::handle_trash_empty(older_than, cli.dry_run, yes, json || cli.json).await,
+        },
+        Commands::Audit { command } => match command {
+            AuditCommand::List { limit, json } => {
+                audit::handle_audit_list(limit, json || cli.json).await
+            }
         },
         #[cfg(feature = "skills")]
         Commands::Skills { json } => skills::handle_skills(json || cli.json).await,
+        #[cfg(feature = "web")]
+        Commands::Serve { web, port, path } => serve::handle_serve(web, port, path).await,
         #[cfg(feature = "tui")]
-        Commands::Defrag { path } => {
+        Commands::Defrag { path, duplicates } => {
             // Expand ~ to home directory
             let expanded_path = if path.starts_with('~') {
                 if let Some(home) = dirs::home_dir() {
@@ -259,7 +701,12 @@ async fn main() -> Result<()> {
             } else {
                 path
             };
-            dragonfly_tui::run_app(expanded_path).await
+            let kind = if duplicates {
+                dragonfly_tui::ScanKind::Duplicates
+            } else {
+                dragonfly_tui::ScanKind::Disk
+            };
+            dragonfly_tui::run_app(expanded_path, kind).await
         },
     };
 
@@ -276,6 +723,10 @@ async fn main() -> Result<()> {
         }
     }
 
+    if result.is_ok() && cancel.is_some_and(|c| c.is_cancelled()) {
+        std::process::exit(interrupt::EXIT_INTERRUPTED);
+    }
+
     result
 }
 
@@ -294,6 +745,19 @@ fn init_logging(debug: bool) -> Result<()> {
     Ok(())
 }
 
+/// Whether the command writes an export or an event stream for another tool
+/// to stdout, which must not start with the header
+fn writes_export(command: &Commands) -> bool {
+    matches!(
+        command,
+        Commands::Disk {
+            command: DiskCommand::Analyze { format: Some(_), .. }
+                | DiskCommand::Large { format: Some(_), .. }
+                | DiskCommand::Watch { json: true, .. },
+        }
+    )
+}
+
 fn print_header() {
     println!();
     println!(
@@ -302,6 +766,6 @@ fn print_header() {
         "DragonFly".bold().bright_cyan()
     );
     println!("{}", format!("v{}", env!("CARGO_PKG_VERSION")).dimmed());
-    println!("{}", "Privacy-first macOS maintenance utility".dimmed());
+    println!("{}", t!("app.tagline").dimmed());
     println!();
 }
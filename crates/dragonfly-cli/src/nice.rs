@@ -0,0 +1,113 @@
+//! Background priority for `--nice`
+//!
+//! Lowers CPU and disk priority and shrinks the worker pool that every
+//! filesystem walk shares, so a scan or clean can run during work hours
+//! without the rest of the machine feeling it. On macOS both priorities apply
+//! to the whole process; on Linux they apply to the main thread and the walk
+//! workers, which is where scans do their work.
+
+/// CPU niceness for background runs (0 is normal, 19 the lowest)
+const NICENESS: libc::c_int = 10;
+
+/// Turn on background priority for the rest of the process when `enabled`
+///
+/// Must run before the first scan, while the shared worker pool can still be
+/// sized. Anything that can't be lowered is logged and the command carries
+/// on at normal priority.
+pub fn init(enabled: bool) {
+    if !enabled {
+        return;
+    }
+    lower_current_thread();
+    let cores = std::thread::available_parallelism().map_or(1, usize::from);
+    let threads = background_threads(cores);
+    if let Err(e) = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|i| format!("dragonfly-nice-{i}"))
+        .start_handler(|_| lower_current_thread())
+        .build_global()
+    {
+        tracing::warn!("Could not limit scan threads: {e}");
+    }
+    tracing::debug!(threads, "Running with background priority");
+}
+
+/// Worker threads to use in background mode on a machine with `cores`
+fn background_threads(cores: usize) -> usize {
+    (cores / 4).max(1)
+}
+
+/// Lower CPU and disk priority for the calling thread (the whole process on
+/// macOS)
+fn lower_current_thread() {
+    // SAFETY: plain syscall with no pointers; 0 means the caller
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, NICENESS) } != 0 {
+        tracing::warn!(
+            "Could not lower CPU priority: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    if let Err(e) = lower_io_priority() {
+        tracing::warn!("Could not lower disk priority: {e}");
+    }
+}
+
+/// Throttle disk I/O behind other processes' requests
+#[cfg(target_os = "macos")]
+fn lower_io_priority() -> std::io::Result<()> {
+    const IOPOL_TYPE_DISK: libc::c_int = 0;
+    const IOPOL_SCOPE_PROCESS: libc::c_int = 0;
+    const IOPOL_THROTTLE: libc::c_int = 3;
+    extern "C" {
+        fn setiopolicy_np(
+            iotype: libc::c_int,
+            scope: libc::c_int,
+            policy: libc::c_int,
+        ) -> libc::c_int;
+    }
+    // SAFETY: plain syscall wrapper with no pointers
+    if unsafe { setiopolicy_np(IOPOL_TYPE_DISK, IOPOL_SCOPE_PROCESS, IOPOL_THROTTLE) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Move disk I/O to the lowest best-effort level
+#[cfg(target_os = "linux")]
+fn lower_io_priority() -> std::io::Result<()> {
+    const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+    const IOPRIO_CLASS_BE: libc::c_long = 2;
+    const IOPRIO_CLASS_SHIFT: libc::c_long = 13;
+    const LOWEST_LEVEL: libc::c_long = 7;
+    // SAFETY: plain syscall with no pointers; 0 means the caller
+    let result = unsafe {
+        libc::syscall(
+            libc::SYS_ioprio_set,
+            IOPRIO_WHO_PROCESS,
+            0,
+            (IOPRIO_CLASS_BE << IOPRIO_CLASS_SHIFT) | LOWEST_LEVEL,
+        )
+    };
+    if result != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// No disk priority control on this platform; CPU priority still applies
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn lower_io_priority() -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_background_threads() {
+        assert_eq!(background_threads(1), 1);
+        assert_eq!(background_threads(8), 2);
+        assert_eq!(background_threads(16), 4);
+    }
+}
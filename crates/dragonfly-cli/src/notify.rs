@@ -0,0 +1,258 @@
+//! Notifications
+//!
+//! Adapters for the [`Notifier`] port: [`DesktopNotifier`] posts to
+//! Notification Center via `osascript` on macOS, and through `notify-send`
+//! on other desktops; [`BellNotifier`] rings the terminal bell;
+//! [`ScriptNotifier`] runs a user's command; and [`PromptNotifier`] asks
+//! questions on the terminal.
+
+use async_trait::async_trait;
+use colored::Colorize;
+use dragonfly_core::error::{Error, Result};
+use dragonfly_core::ports::Notifier;
+use std::io::{BufRead, Write};
+use std::process::Command;
+use std::sync::Mutex;
+
+/// Posts notifications to the desktop
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DesktopNotifier;
+
+impl DesktopNotifier {
+    /// Create a desktop notifier
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl Notifier for DesktopNotifier {
+    async fn notify(&self, title: &str, message: &str) -> Result<()> {
+        let mut command = if cfg!(target_os = "macos") {
+            let script = format!(
+                "display notification {} with title {}",
+                applescript_string(message),
+                applescript_string(title)
+            );
+            let mut command = Command::new("osascript");
+            command.args(["-e", &script]);
+            command
+        } else {
+            let mut command = Command::new("notify-send");
+            command.args([title, message]);
+            command
+        };
+        let status = command.status().map_err(|e| {
+            Error::NotSupported(format!("Desktop notifications unavailable: {}", e))
+        })?;
+        if !status.success() {
+            return Err(Error::Internal(format!(
+                "Notification command failed with {}",
+                status
+            )));
+        }
+        Ok(())
+    }
+
+    /// Progress isn't worth a notification; it is dropped
+    async fn progress(&self, _current: usize, _total: usize, _message: &str) -> Result<()> {
+        Ok(())
+    }
+
+    async fn confirm(&self, _message: &str) -> Result<bool> {
+        Err(Error::NotSupported(
+            "Desktop notifications can't ask for confirmation".to_string(),
+        ))
+    }
+}
+
+/// Rings the terminal bell
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BellNotifier;
+
+impl BellNotifier {
+    /// Create a bell notifier
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl Notifier for BellNotifier {
+    async fn notify(&self, _title: &str, _message: &str) -> Result<()> {
+        // stderr, so the bell never lands in JSON on stdout
+        let mut stderr = std::io::stderr();
+        stderr.write_all(b"\x07")?;
+        stderr.flush()?;
+        Ok(())
+    }
+
+    async fn progress(&self, _current: usize, _total: usize, _message: &str) -> Result<()> {
+        Ok(())
+    }
+
+    async fn confirm(&self, _message: &str) -> Result<bool> {
+        Err(Error::NotSupported(
+            "The terminal bell can't ask for confirmation".to_string(),
+        ))
+    }
+}
+
+/// Runs a shell command for each notification
+///
+/// The command gets the title and message as `$1` and `$2`, and in
+/// `DRAGONFLY_TITLE` and `DRAGONFLY_MESSAGE`. It runs to completion before
+/// `notify` returns.
+#[derive(Debug, Clone)]
+pub struct ScriptNotifier {
+    command: String,
+}
+
+impl ScriptNotifier {
+    /// Create a notifier running `command` through `sh -c`
+    pub fn new(command: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for ScriptNotifier {
+    async fn notify(&self, title: &str, message: &str) -> Result<()> {
+        let status = Command::new("sh")
+            .args(["-c", &self.command, "dragonfly", title, message])
+            .env("DRAGONFLY_TITLE", title)
+            .env("DRAGONFLY_MESSAGE", message)
+            .status()
+            .map_err(|e| Error::Internal(format!("Failed to run '{}': {}", self.command, e)))?;
+        if !status.success() {
+            return Err(Error::Internal(format!(
+                "'{}' failed with {}",
+                self.command, status
+            )));
+        }
+        Ok(())
+    }
+
+    async fn progress(&self, _current: usize, _total: usize, _message: &str) -> Result<()> {
+        Ok(())
+    }
+
+    async fn confirm(&self, _message: &str) -> Result<bool> {
+        Err(Error::NotSupported(
+            "A notification script can't ask for confirmation".to_string(),
+        ))
+    }
+}
+
+/// Prints to the terminal and reads confirmations from stdin
+///
+/// Each confirmation takes `y` (yes), `n` (no, the default), `a` (yes to
+/// this and every later one) or `q` (no to this and every later one). The
+/// end of input counts as `q`.
+#[derive(Debug, Default)]
+pub struct PromptNotifier {
+    /// The answer to every later confirmation, once `a` or `q` was given
+    standing_answer: Mutex<Option<bool>>,
+}
+
+impl PromptNotifier {
+    /// Create a prompt notifier
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the user quit, so later confirmations are all refused
+    pub fn has_quit(&self) -> bool {
+        self.standing_answer() == Some(false)
+    }
+
+    fn standing_answer(&self) -> Option<bool> {
+        *self
+            .standing_answer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// An answer to a [`PromptNotifier`] confirmation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Answer {
+    Yes,
+    No,
+    All,
+    Quit,
+}
+
+impl Answer {
+    /// Parse a line of input; anything unrecognized is no
+    fn parse(input: &str) -> Self {
+        match input.trim().to_lowercase().as_str() {
+            "y" | "yes" => Self::Yes,
+            "a" | "all" => Self::All,
+            "q" | "quit" => Self::Quit,
+            _ => Self::No,
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for PromptNotifier {
+    async fn notify(&self, title: &str, message: &str) -> Result<()> {
+        println!("{} {}", title.bold(), message);
+        Ok(())
+    }
+
+    async fn progress(&self, current: usize, total: usize, message: &str) -> Result<()> {
+        println!("[{}/{}] {}", current, total, message);
+        Ok(())
+    }
+
+    async fn confirm(&self, message: &str) -> Result<bool> {
+        if let Some(answer) = self.standing_answer() {
+            return Ok(answer);
+        }
+        print!("{} ", format!("{} [y/N/a/q]", message).cyan());
+        std::io::stdout().flush()?;
+        let mut input = String::new();
+        let answer = if std::io::stdin().lock().read_line(&mut input)? == 0 {
+            println!();
+            Answer::Quit
+        } else {
+            Answer::parse(&input)
+        };
+        let standing = match answer {
+            Answer::All => Some(true),
+            Answer::Quit => Some(false),
+            Answer::Yes | Answer::No => None,
+        };
+        if standing.is_some() {
+            *self
+                .standing_answer
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()) = standing;
+        }
+        Ok(matches!(answer, Answer::Yes | Answer::All))
+    }
+}
+
+/// Quote `text` as an AppleScript string literal
+fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_answer_parse() {
+        assert_eq!(Answer::parse("y\n"), Answer::Yes);
+        assert_eq!(Answer::parse("YES"), Answer::Yes);
+        assert_eq!(Answer::parse("a"), Answer::All);
+        assert_eq!(Answer::parse(" q "), Answer::Quit);
+        assert_eq!(Answer::parse(""), Answer::No);
+        assert_eq!(Answer::parse("sure"), Answer::No);
+    }
+}
@@ -0,0 +1,29 @@
+//! Protection policy loading
+//!
+//! `never_touch`, `protect_patterns` and `allow_roots` in
+//! `~/.dragonfly/config` extend the built-in protected locations for every
+//! destructive command.
+
+use anyhow::{Context, Result};
+use dragonfly_core::domain::policy::{self, ProtectionPolicy};
+
+/// Read the protection policy from the config file and install it for the
+/// rest of the process
+///
+/// A policy that can't be read is an error rather than a warning, so a typo
+/// never silently drops the user's protected locations.
+pub fn init() -> Result<()> {
+    let Some(home) = dirs::home_dir() else {
+        return Ok(());
+    };
+    let path = home.join(".dragonfly").join("config");
+    let config = match std::fs::read_to_string(&path) {
+        Ok(config) => config,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let policy = ProtectionPolicy::from_config(&config, &home.to_string_lossy())
+        .with_context(|| format!("Invalid protection policy in {}", path.display()))?;
+    policy::install(policy);
+    Ok(())
+}
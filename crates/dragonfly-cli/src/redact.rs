@@ -0,0 +1,31 @@
+//! Path redaction loading
+//!
+//! `--redact-paths` hides the account name and the `redact_segments` listed
+//! in `~/.dragonfly/config` from JSON output.
+
+use anyhow::{Context, Result};
+use dragonfly_core::domain::redaction::{self, PathRedactor};
+
+/// Turn on redaction for the rest of the process when `enabled`
+///
+/// The config is only read when redaction is asked for; a list that can't be
+/// read is an error, so a typo never leaks the names it was meant to hide.
+pub fn init(enabled: bool) -> Result<()> {
+    if !enabled {
+        return Ok(());
+    }
+    let Some(home) = dirs::home_dir() else {
+        redaction::install(PathRedactor::default());
+        return Ok(());
+    };
+    let path = home.join(".dragonfly").join("config");
+    let config = match std::fs::read_to_string(&path) {
+        Ok(config) => config,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let redactor = PathRedactor::from_config(&config, &home.to_string_lossy())
+        .with_context(|| format!("Invalid redaction list in {}", path.display()))?;
+    redaction::install(redactor);
+    Ok(())
+}
@@ -1,15 +1,51 @@
 //! Command type definitions
 
-use clap::Subcommand;
+use clap::{Args, Subcommand};
+use dragonfly_core::domain::scan_options::ScanOptions;
 use std::path::PathBuf;
 
+/// Where a disk scan is allowed to go
+#[derive(Args, Debug, Clone, Copy, Default)]
+pub struct WalkArgs {
+    /// Descend at most this many levels below the path (1 = only the
+    /// entries directly inside it)
+    #[arg(long, value_name = "LEVELS")]
+    pub max_depth: Option<usize>,
+
+    /// Follow symbolic links to directories (links back up the tree are
+    /// skipped, so loops end)
+    #[arg(long)]
+    pub follow_symlinks: bool,
+
+    /// Stay on the path's filesystem, skipping mounted volumes and network
+    /// shares below it
+    #[arg(long)]
+    pub one_filesystem: bool,
+}
+
+impl WalkArgs {
+    /// Scan options for these flags
+    pub fn scan_options(self) -> ScanOptions {
+        ScanOptions::new()
+            .with_max_depth(self.max_depth)
+            .with_follow_symlinks(self.follow_symlinks)
+            .with_one_filesystem(self.one_filesystem)
+    }
+
+    /// Whether any flag differs from a full walk
+    pub fn is_set(self) -> bool {
+        self.max_depth.is_some() || self.follow_symlinks || self.one_filesystem
+    }
+}
+
 #[derive(Subcommand)]
 pub enum DiskCommand {
     /// Analyze disk usage
     Analyze {
-        /// Path to analyze
+        /// Paths to analyze; several are scanned at the same time and merged,
+        /// with a subtotal for each
         #[arg(default_value = ".")]
-        path: PathBuf,
+        paths: Vec<PathBuf>,
 
         /// Minimum file size to consider (e.g., 100MB, 1GB)
         #[arg(short, long)]
@@ -19,6 +55,72 @@ pub enum DiskCommand {
         #[arg(short, long, default_value = "10")]
         top: usize,
 
+        /// Also show the largest directories, with sizes including
+        /// everything below them
+        #[arg(long, conflicts_with = "format")]
+        dirs: bool,
+
+        /// Answer from the size index when it covers the path (see
+        /// `dragonfly index`), scanning otherwise
+        #[arg(long)]
+        index: bool,
+
+        /// How to measure: `deep` walks every file, `quick` estimates the
+        /// total from random samples (seconds on huge volumes, no file list),
+        /// `incremental` updates the size index by re-reading only
+        /// directories that changed since the last run (files that grew in
+        /// place are caught by `dragonfly index --daemon` or a deep scan)
+        #[arg(long, value_parser = ["deep", "quick", "incremental"], default_value = "deep", conflicts_with = "index")]
+        strategy: String,
+
+        /// Report time spent per scan phase (walk, stat, hash, aggregation)
+        /// and throughput
+        #[arg(long)]
+        profile: bool,
+
+        /// Show size on disk (allocated blocks) instead of logical size, as
+        /// Finder and `df` do; sparse and compressed files count for less
+        #[arg(long)]
+        physical: bool,
+
+        /// Write the scan in another format instead: `treemap-json` nests
+        /// directories with their sizes for d3-flamegraph and other treemap
+        /// viewers, `ncdu` is ncdu's export format (open with `ncdu -f`),
+        /// `csv` and `tsv` list the files shown with their logical and
+        /// allocated size, modification time, and kind for spreadsheets
+        #[arg(long, value_parser = ["treemap-json", "ncdu", "csv", "tsv"], conflicts_with = "index")]
+        format: Option<String>,
+
+        /// How to list macOS packages (`.app`, `.photoslibrary`,
+        /// `.framework`...): `collapse` shows each as one item with the size
+        /// of everything inside, as Finder does; `expand` lists their files
+        #[arg(long, value_parser = ["collapse", "expand"], default_value = "expand")]
+        package_mode: String,
+
+        /// Only list files modified after this date (2024-01-31) or more
+        /// recently than this long ago (30d, 12h, 2w, 6mo, 1y)
+        #[arg(long, value_name = "WHEN")]
+        modified_after: Option<String>,
+
+        /// Only list files modified before this date or longer ago than this
+        #[arg(long, value_name = "WHEN")]
+        modified_before: Option<String>,
+
+        /// Also save every file of the scan, for comparing with a later scan
+        /// (see `disk diff`): a bare name is kept in `~/.dragonfly/scans`
+        /// (see `disk snapshots`), anything else is a file path
+        #[arg(long, value_name = "NAME|FILE")]
+        save: Option<PathBuf>,
+
+        /// Continue the last interrupted deep scan from its checkpoint
+        /// (`~/.dragonfly/scan-checkpoint.json`) instead of starting over;
+        /// needs the same path and walk options
+        #[arg(long, conflicts_with_all = ["index", "format"])]
+        resume: bool,
+
+        #[command(flatten)]
+        walk: WalkArgs,
+
         /// Output as JSON
         #[arg(long)]
         json: bool,
@@ -34,6 +136,353 @@ pub enum DiskCommand {
         #[arg(short, long, default_value = "100MB")]
         min_size: String,
 
+        /// Query the Spotlight index instead of walking (falls back to a walk
+        /// when the volume isn't indexed)
+        #[arg(long, conflicts_with = "index")]
+        spotlight: bool,
+
+        /// Answer from the size index when it covers the path (see
+        /// `dragonfly index`), scanning otherwise
+        #[arg(long)]
+        index: bool,
+
+        /// Only list files modified after this date (2024-01-31) or more
+        /// recently than this long ago (30d, 12h, 2w, 6mo, 1y)
+        #[arg(long, value_name = "WHEN")]
+        modified_after: Option<String>,
+
+        /// Only list files modified before this date or longer ago than this
+        #[arg(long, value_name = "WHEN")]
+        modified_before: Option<String>,
+
+        /// Report time spent per scan phase (walk, stat, hash, aggregation)
+        /// and throughput
+        #[arg(long)]
+        profile: bool,
+
+        /// List the files for spreadsheets instead, with their logical and
+        /// allocated size, modification time, and kind
+        #[arg(long, value_parser = ["csv", "tsv"])]
+        format: Option<String>,
+
+        #[command(flatten)]
+        walk: WalkArgs,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show the heaviest directories, with sizes including everything below
+    /// them
+    Tree {
+        /// Path to analyze
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// Number of directories to show
+        #[arg(short, long, default_value = "10")]
+        top: usize,
+
+        /// Only consider directories at most this many levels below the path
+        #[arg(long)]
+        depth: Option<usize>,
+
+        #[command(flatten)]
+        walk: WalkArgs,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Break down space by kind of file (video, images, archives, code,
+    /// caches...)
+    Types {
+        /// Path to analyze
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        #[command(flatten)]
+        walk: WalkArgs,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Break down space by the user owning the files, for Macs shared by
+    /// several accounts
+    ByOwner {
+        /// Path to analyze
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        #[command(flatten)]
+        walk: WalkArgs,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Find rebuildable developer artifacts (node_modules, Cargo target,
+    /// virtualenvs, Gradle/Maven/CocoaPods caches, DerivedData) per project
+    DevJunk {
+        /// Path to search
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// Number of projects to show
+        #[arg(short, long, default_value = "20")]
+        top: usize,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Find files not modified or opened in a long time
+    Stale {
+        /// Path to search
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// Only include files untouched for at least this many days
+        #[arg(long, default_value = "365")]
+        days: u32,
+
+        /// Minimum file size (e.g., 100MB, 1GB)
+        #[arg(short, long, default_value = "100MB")]
+        min_size: String,
+
+        /// Number of files to show
+        #[arg(short, long, default_value = "20")]
+        top: usize,
+
+        #[command(flatten)]
+        walk: WalkArgs,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Scan several volumes concurrently and merge the results
+    ScanVolumes {
+        /// Volume roots to scan (defaults to every volume `disk volumes` lists)
+        volumes: Vec<PathBuf>,
+
+        /// Worker threads per device
+        #[arg(long, default_value = "4")]
+        threads: usize,
+
+        /// Number of top items to show
+        #[arg(short, long, default_value = "10")]
+        top: usize,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List mounted volumes with their capacity, used, free, and purgeable
+    /// space
+    Volumes {
+        /// Include system and pseudo filesystems hidden from Finder
+        #[arg(long)]
+        all: bool,
+
+        /// Also scan each volume and report the size of its files
+        #[arg(long)]
+        scan: bool,
+
+        /// Worker threads per device when scanning
+        #[arg(long, default_value = "4", requires = "scan")]
+        threads: usize,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Break down Mail storage by account and mailbox (read-only)
+    Mail {
+        /// Mail folder to inspect (defaults to ~/Library/Mail)
+        #[arg(long)]
+        path: Option<PathBuf>,
+
+        /// Number of mailboxes to show per account
+        #[arg(short, long, default_value = "5")]
+        top: usize,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Break down Photos library storage (read-only)
+    Photos {
+        /// Library package to inspect (defaults to the system Photos library)
+        #[arg(long)]
+        library: Option<PathBuf>,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Find symlinks (and optionally Finder aliases) whose targets are gone
+    BrokenLinks {
+        /// Path to search
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// Also check Finder alias files (slower)
+        #[arg(long)]
+        aliases: bool,
+
+        /// Move the broken links to a recovery archive
+        #[arg(long)]
+        remove: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Find empty directory trees left behind by apps and tools
+    EmptyDirs {
+        /// Path to search
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// Only show trees nested at least this deep
+        #[arg(long, default_value = "1")]
+        min_depth: usize,
+
+        /// Remove the trees (protected locations are left alone)
+        #[arg(long)]
+        prune: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Find old screenshots and screen recordings
+    Screenshots {
+        /// Folders to search (defaults to the Desktop and the screenshot location)
+        #[arg(long = "folder")]
+        folders: Vec<PathBuf>,
+
+        /// Only include screenshots older than this many days
+        #[arg(long, default_value = "30")]
+        older_than: i64,
+
+        /// Move them into this folder
+        #[arg(long, value_name = "DIR", conflicts_with = "delete")]
+        move_to: Option<PathBuf>,
+
+        /// Delete them, keeping a recovery archive
+        #[arg(long)]
+        delete: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show what grew since the latest local APFS snapshot (needs sudo)
+    SnapshotDiff {
+        /// Directory to compare (defaults to the home directory)
+        path: Option<PathBuf>,
+
+        /// Snapshot to compare against (defaults to the most recent)
+        #[arg(long)]
+        snapshot: Option<String>,
+
+        /// Group changes this many levels below the path
+        #[arg(short, long, default_value = "3")]
+        depth: usize,
+
+        /// Number of paths to show
+        #[arg(short, long, default_value = "20")]
+        top: usize,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Keep the size index current from filesystem events (FSEvents on
+    /// macOS) and report directories that grow past a threshold, until Ctrl-C
+    Watch {
+        /// Folder to watch
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// Report a directory each time it grows by this much (e.g. 1GB)
+        #[arg(long, default_value = "1GB")]
+        threshold: String,
+
+        /// Watch directories this many levels below the path (0 watches the
+        /// path as a whole)
+        #[arg(long, default_value = "1")]
+        depth: usize,
+
+        /// Print each report as a line of JSON (NDJSON)
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Compare two scans saved with `disk analyze --save`: directories that
+    /// grew or shrank the most, new large files, and deleted files
+    Diff {
+        /// Earlier saved scan (name or file)
+        before: PathBuf,
+
+        /// Later saved scan (name or file)
+        after: PathBuf,
+
+        /// Group directory changes this many levels below the scanned path
+        #[arg(long, default_value = "2")]
+        depth: usize,
+
+        /// Only list new and deleted files of at least this size (e.g. 100MB)
+        #[arg(short, long, default_value = "100MB")]
+        min_size: String,
+
+        /// Number of entries to show per list
+        #[arg(short, long, default_value = "10")]
+        top: usize,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List, show, and delete scans saved by name with `disk analyze
+    /// --save <NAME>`
+    Snapshots {
+        #[command(subcommand)]
+        command: SnapshotsCommand,
+    },
+
+    /// Show how much removing other architectures from universal apps would save
+    Thinning {
+        /// Folders holding apps (defaults to /Applications and ~/Applications)
+        #[arg(long = "app-dir")]
+        app_dirs: Vec<PathBuf>,
+
+        /// Only include apps whose name contains this text
+        #[arg(long)]
+        app: Option<String>,
+
+        /// Thin the binaries, keeping the originals in a recovery archive
+        #[arg(long)]
+        thin: bool,
+
         /// Output as JSON
         #[arg(long)]
         json: bool,
@@ -44,33 +493,223 @@ pub enum DiskCommand {
 pub enum DuplicatesCommand {
     /// Find duplicate files
     Scan {
+        /// Paths to scan; with several, copies are also found across them
+        /// and the first is the primary one for `--keep primary`
+        #[arg(default_value = ".")]
+        paths: Vec<PathBuf>,
+
+        /// Minimum file size to consider (e.g., 1MB); empty files are left
+        /// out unless this is 0
+        #[arg(short, long)]
+        min_size: Option<String>,
+
+        /// Interactive mode: pick the copy to keep in each group, the others
+        /// are moved to recovery; --keep chooses the suggested copy
+        #[arg(short, long)]
+        interactive: bool,
+
+        /// Keep one copy of each group, chosen by --keep, and move the
+        /// others to recovery
+        #[arg(long)]
+        delete: bool,
+
+        /// Copy to keep with --delete or --interactive: `oldest` or `newest`
+        /// by modification time, `shortest-path`, `first` in path order, or
+        /// `primary`, the one under the first path given
+        #[arg(long, value_parser = ["oldest", "newest", "shortest-path", "first", "primary"], default_value = "first")]
+        keep: String,
+
+        /// How to compare files of the same size: `full` hashes every byte,
+        /// `partial` only the first and last 64 KB (much faster on large
+        /// files, but files differing only in the middle count as duplicates;
+        /// deleting with it needs --verify)
+        #[arg(long, value_parser = ["full", "partial"], default_value = "full")]
+        strategy: String,
+
+        /// Report time spent per scan phase (walk, stat, hash, aggregation)
+        /// and throughput
+        #[arg(long)]
+        profile: bool,
+
+        /// Hash every file again instead of reusing hashes of unchanged
+        /// files from ~/.dragonfly/hash-cache
+        #[arg(long)]
+        no_cache: bool,
+
+        /// Keep the file list in the size index (~/.dragonfly/index.db) and
+        /// only re-read folders changed since the last run; with the hash
+        /// cache, only new or modified files are hashed. A file edited in
+        /// place is noticed once its size matches another file's
+        #[arg(long)]
+        incremental: bool,
+
+        /// Leave out paths matching this glob (`*` and `?`); without a `/` it
+        /// matches any path component, e.g. `node_modules` or `*.tmp`.
+        /// Repeat for several
+        #[arg(long = "exclude", value_name = "GLOB")]
+        excludes: Vec<String>,
+
+        /// Also write the report to this file, one row per duplicate file
+        /// with its group, hash, size, path, and suggested action (keep,
+        /// remove, protected): CSV for `.csv`, JSON Lines for `.ndjson` or
+        /// `.jsonl`
+        #[arg(long, value_name = "FILE")]
+        export: Option<PathBuf>,
+
+        /// Compare the files of each group byte by byte before treating them
+        /// as duplicates, guarding against hash collisions and read errors
+        #[arg(long)]
+        verify: bool,
+
+        /// Match music, photos and videos (MP3, FLAC, JPEG, MP4/MOV) by their
+        /// audio, image or video data, so copies with different ID3 tags or
+        /// EXIF still count as duplicates
+        #[arg(long)]
+        media: bool,
+
+        #[command(flatten)]
+        walk: WalkArgs,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Replace redundant copies with APFS clones or hard links, freeing
+    /// their space while every path keeps working
+    Dedupe {
         /// Path to scan
         #[arg(default_value = ".")]
         path: PathBuf,
 
-        /// Minimum file size to consider
+        /// `clone` makes copy-on-write clones (APFS only), each still a
+        /// separate file; `hardlink` makes every path name the same file
+        #[arg(long, value_parser = ["clone", "hardlink"], default_value = "clone")]
+        mode: String,
+
+        /// Copy the others are made from: `oldest` or `newest` by
+        /// modification time, `shortest-path`, or `first` in path order
+        #[arg(long, value_parser = ["oldest", "newest", "shortest-path", "first"], default_value = "first")]
+        keep: String,
+
+        /// Minimum file size to consider (e.g., 1MB)
         #[arg(short, long)]
         min_size: Option<String>,
 
-        /// Dry run (don't delete)
+        /// Hash every file again instead of reusing hashes of unchanged
+        /// files from ~/.dragonfly/hash-cache
         #[arg(long)]
-        dry_run: bool,
+        no_cache: bool,
+
+        /// Leave out paths matching this glob (`*` and `?`); without a `/` it
+        /// matches any path component, e.g. `node_modules` or `*.tmp`.
+        /// Repeat for several
+        #[arg(long = "exclude", value_name = "GLOB")]
+        excludes: Vec<String>,
+
+        #[command(flatten)]
+        walk: WalkArgs,
 
-        /// Interactive mode (select which files to delete)
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Find visually similar images (resized, re-encoded, screenshots)
+    /// that byte comparison misses
+    Images {
+        /// Path to scan
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// Largest difference, in bits of the 64-bit perceptual hash, between
+        /// similar images; lower is stricter
+        #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(0..=64))]
+        threshold: u32,
+
+        /// Minimum file size to consider (e.g., 100KB)
         #[arg(short, long)]
-        interactive: bool,
+        min_size: Option<String>,
+
+        #[command(flatten)]
+        walk: WalkArgs,
 
         /// Output as JSON
         #[arg(long)]
         json: bool,
     },
 
-    /// Show duplicate statistics
+    /// Show duplicate statistics: wasted space, the largest group, space
+    /// by file type, and the most duplicated files
     Stats {
         /// Path to analyze
         #[arg(default_value = ".")]
         path: PathBuf,
 
+        /// Minimum file size to consider (e.g., 1MB); empty files are left
+        /// out unless this is 0
+        #[arg(short, long)]
+        min_size: Option<String>,
+
+        /// Hash every file again instead of reusing hashes of unchanged
+        /// files from ~/.dragonfly/hash-cache
+        #[arg(long)]
+        no_cache: bool,
+
+        #[command(flatten)]
+        walk: WalkArgs,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List zero-byte files and empty folder trees
+    Empties {
+        /// Path to search
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// Move them to a recovery archive (protected locations are left
+        /// alone)
+        #[arg(long)]
+        delete: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SnapshotsCommand {
+    /// List saved scans, newest first
+    List {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show the files of a saved scan without scanning again
+    Show {
+        /// Name the scan was saved under
+        name: String,
+        /// Number of files to show
+        #[arg(short, long, default_value = "20")]
+        top: usize,
+        /// Only list files of at least this size (e.g. 100MB)
+        #[arg(short, long)]
+        min_size: Option<String>,
+        /// Order files by `size` (largest first) or `path`
+        #[arg(long, value_parser = ["size", "path"], default_value = "size")]
+        sort: String,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Delete a saved scan
+    Delete {
+        /// Name the scan was saved under
+        name: String,
         /// Output as JSON
         #[arg(long)]
         json: bool,
@@ -81,6 +720,9 @@ pub enum DuplicatesCommand {
 pub enum RecoverCommand {
     /// List all recoveries
     List {
+        /// Check the integrity seals and archived items of every recovery
+        #[arg(long)]
+        verify: bool,
         /// Output as JSON
         #[arg(long)]
         json: bool,
@@ -97,6 +739,14 @@ pub enum RecoverCommand {
     Restore {
         /// Recovery ID
         id: String,
+        /// Only restore items whose original path matches; a pattern without
+        /// `/` matches any file or folder name, and `*` and `?` are wildcards
+        #[arg(long = "item", value_name = "PATH_GLOB")]
+        items: Vec<String>,
+        /// Ask about each folder in the recovery and restore only the chosen
+        /// ones
+        #[arg(short, long, conflicts_with = "json")]
+        interactive: bool,
         /// Output as JSON
         #[arg(long)]
         json: bool,
@@ -117,4 +767,158 @@ pub enum TimeMachineCommand {
         #[arg(long)]
         json: bool,
     },
+    /// Delete local snapshots
+    Delete {
+        /// Snapshot ID to delete
+        #[arg(conflicts_with = "older_than", required_unless_present = "older_than")]
+        id: Option<String>,
+        /// Delete all snapshots older than this many days
+        #[arg(long)]
+        older_than: Option<u32>,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Have macOS thin local snapshots, oldest first, until enough space is
+    /// free
+    Thin {
+        /// Bytes to free on the startup volume (defaults to as much as the
+        /// snapshots hold)
+        #[arg(long)]
+        bytes: Option<u64>,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CleanCommand {
+    /// Find data in ~/Library left behind by apps no longer installed,
+    /// grouped by bundle identifier
+    Orphans {
+        /// Ask about each app's leftovers and move the chosen ones to recovery
+        #[arg(short, long)]
+        interactive: bool,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// List past cleans with their target, files, bytes and whether they
+    /// were dry runs
+    History {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TrashCommand {
+    /// Show the size of your trash and of each external volume's
+    Status {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Permanently delete what's in the trash
+    Empty {
+        /// Only delete items trashed at least this long ago, e.g. 30d or 2w
+        #[arg(long, value_name = "AGE", value_parser = parse_period)]
+        older_than: Option<chrono::Duration>,
+        /// Delete without asking first (required with --json)
+        #[arg(short, long)]
+        yes: bool,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum AuditCommand {
+    /// List recorded destructive operations (newest first)
+    List {
+        /// Show at most this many entries
+        #[arg(short, long)]
+        limit: Option<usize>,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum MonitorCommand {
+    /// Show how CPU, memory and disk use developed over a recent period,
+    /// from samples recorded by `monitor` and `health`
+    History {
+        /// How far back to look, e.g. 90m, 24h, 7d or 2w
+        #[arg(long, default_value = "24h", value_parser = parse_period)]
+        since: chrono::Duration,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Follow one process's CPU, memory, threads and open files over time
+    Process {
+        /// Process ID, or the exact name of a single running process
+        target: String,
+        /// Seconds between samples
+        #[arg(short, long, default_value = "2")]
+        interval: u64,
+        /// Stop after this many samples instead of when the process exits
+        #[arg(long)]
+        count: Option<usize>,
+        /// Stream a JSON object per sample, one per line
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Expose live metrics for Prometheus to scrape at /metrics
+    #[cfg(feature = "exporter")]
+    Serve {
+        /// Address to listen on; keep it on localhost unless other machines
+        /// must scrape this one
+        #[arg(long, default_value = "127.0.0.1:9095")]
+        listen: std::net::SocketAddr,
+    },
+}
+
+/// Parse a period of minutes, hours, days or weeks such as `90m`, `24h`,
+/// `7d` or `2w`
+pub fn parse_period(value: &str) -> Result<chrono::Duration, String> {
+    let split = value.len().saturating_sub(1);
+    let (count, unit) = value.split_at(split);
+    let count: i64 = count
+        .parse()
+        .ok()
+        .filter(|&count| count > 0)
+        .ok_or_else(|| {
+            format!("expected a positive number followed by m, h, d or w, got '{value}'")
+        })?;
+    match unit {
+        "m" => Ok(chrono::Duration::minutes(count)),
+        "h" => Ok(chrono::Duration::hours(count)),
+        "d" => Ok(chrono::Duration::days(count)),
+        "w" => Ok(chrono::Duration::weeks(count)),
+        _ => Err(format!("unknown unit in '{value}'; use m, h, d or w")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_period() {
+        assert_eq!(parse_period("24h"), Ok(chrono::Duration::hours(24)));
+        assert_eq!(parse_period("90m"), Ok(chrono::Duration::minutes(90)));
+        assert_eq!(parse_period("2w"), Ok(chrono::Duration::days(14)));
+        assert!(parse_period("0d").is_err());
+        assert!(parse_period("5y").is_err());
+        assert!(parse_period("h").is_err());
+        assert!(parse_period("").is_err());
+    }
 }
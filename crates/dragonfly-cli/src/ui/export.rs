@@ -0,0 +1,340 @@
+//! Scan exports for other tools
+
+use chrono::SecondsFormat;
+use dragonfly_core::domain::entities::FileEntity;
+use dragonfly_disk::{DirectoryNode, FileCategory};
+use dragonfly_duplicates::DuplicateGroup;
+use serde_json::{json, Map, Value};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Version of ncdu's export format that [`ncdu_json`] writes
+const NCDU_FORMAT: (u32, u32) = (1, 2);
+
+/// `node` as nested `{name, value, children}` objects, the hierarchy
+/// d3-flamegraph and similar treemap viewers read
+///
+/// `value` is the size of everything below a directory; whatever its
+/// children don't account for is the files directly inside it.
+pub fn treemap_json(node: &DirectoryNode) -> Value {
+    let name = node
+        .path
+        .file_name()
+        .unwrap_or(node.path.as_os_str())
+        .to_string_lossy();
+    let mut object = Map::new();
+    object.insert("name".into(), json!(name));
+    object.insert("path".into(), json!(node.path));
+    object.insert("value".into(), json!(node.size));
+    object.insert("files".into(), json!(node.file_count));
+    if !node.children.is_empty() {
+        let children: Vec<Value> = node.children.iter().map(treemap_json).collect();
+        object.insert("children".into(), Value::Array(children));
+    }
+    Value::Object(object)
+}
+
+/// A directory being assembled for [`ncdu_json`]
+#[derive(Default)]
+struct NcduDir<'a> {
+    files: Vec<(String, &'a FileEntity)>,
+    dirs: BTreeMap<String, NcduDir<'a>>,
+}
+
+impl NcduDir<'_> {
+    /// ncdu's form of a directory: its info object followed by its entries,
+    /// subdirectories being nested arrays
+    ///
+    /// Files carry no `nlink`: ncdu matches hard links by `ino`, which a scan
+    /// doesn't record, and a scan already lists each linked file only once.
+    fn to_value(&self, name: &str) -> Value {
+        let mut entries = vec![json!({ "name": name })];
+        for (name, file) in &self.files {
+            entries.push(json!({
+                "name": name,
+                "asize": file.size,
+                "dsize": file.size_on_disk(),
+            }));
+        }
+        entries.extend(self.dirs.iter().map(|(name, dir)| dir.to_value(name)));
+        Value::Array(entries)
+    }
+}
+
+/// The files found under `root` in ncdu's JSON export format, which ncdu
+/// opens with `ncdu -f`
+///
+/// Only directories holding files appear, since a walk doesn't report empty
+/// ones. `timestamp` is the scan time in seconds since the Unix epoch.
+pub fn ncdu_json(root: &Path, files: &[FileEntity], timestamp: i64) -> Value {
+    let mut tree = NcduDir::default();
+    for file in files {
+        let Ok(relative) = Path::new(&file.path).strip_prefix(root) else {
+            continue;
+        };
+        let mut components: Vec<String> = relative
+            .iter()
+            .map(|c| c.to_string_lossy().into_owned())
+            .collect();
+        let Some(name) = components.pop() else {
+            continue;
+        };
+        let dir = components.into_iter().fold(&mut tree, |dir, component| {
+            dir.dirs.entry(component).or_default()
+        });
+        dir.files.push((name, file));
+    }
+    let root_name = std::fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+    json!([
+        NCDU_FORMAT.0,
+        NCDU_FORMAT.1,
+        {
+            "progname": "dragonfly",
+            "progver": env!("CARGO_PKG_VERSION"),
+            "timestamp": timestamp,
+        },
+        tree.to_value(&root_name.to_string_lossy()),
+    ])
+}
+
+/// Columns of [`write_delimited`]
+const DELIMITED_COLUMNS: [&str; 5] = ["path", "size", "allocated_size", "modified", "category"];
+
+/// `files` as a spreadsheet table, one row per file after a header row, with
+/// fields separated by `separator` (`,` for CSV, a tab for TSV)
+///
+/// Fields holding the separator, a quote, or a line break are quoted, quotes
+/// doubled, as RFC 4180 has it. Sizes are in bytes and modification times
+/// RFC 3339 in UTC; unknown values are left empty.
+pub fn write_delimited(
+    out: &mut impl Write,
+    files: &[FileEntity],
+    separator: char,
+) -> io::Result<()> {
+    write_row(out, &DELIMITED_COLUMNS.map(Cow::from), separator)?;
+    for file in files {
+        let row = [
+            Cow::from(file.path.as_str()),
+            file.size.to_string().into(),
+            file.allocated_size
+                .map(|size| size.to_string())
+                .unwrap_or_default()
+                .into(),
+            file.modified
+                .map(|modified| modified.to_rfc3339_opts(SecondsFormat::Secs, true))
+                .unwrap_or_default()
+                .into(),
+            FileCategory::of(&file.path).as_str().into(),
+        ];
+        write_row(out, &row, separator)?;
+    }
+    out.flush()
+}
+
+/// Columns of [`write_duplicates_csv`]
+const DUPLICATE_COLUMNS: [&str; 5] = ["group", "hash", "size", "path", "action"];
+
+/// Suggested action for file `index` of `group` when copy `keep` is kept:
+/// `keep`, `remove`, or `protected` for a copy that is never removed
+///
+/// With no copy chosen every file is kept.
+pub fn duplicate_action(group: &DuplicateGroup, index: usize, keep: Option<usize>) -> &'static str {
+    match keep {
+        Some(keep) if keep != index => {
+            if group.files[index].protection.is_protected() {
+                "protected"
+            } else {
+                "remove"
+            }
+        }
+        _ => "keep",
+    }
+}
+
+/// `groups` as CSV, one row per file after a header row, with the copy of
+/// each group in `kept` marked for keeping and the others for removal
+///
+/// Groups are numbered from 1 in the order given, as the text output lists
+/// them.
+pub fn write_duplicates_csv(
+    out: &mut impl Write,
+    groups: &[DuplicateGroup],
+    kept: &[Option<usize>],
+) -> io::Result<()> {
+    write_row(out, &DUPLICATE_COLUMNS.map(Cow::from), ',')?;
+    for (number, (group, &keep)) in groups.iter().zip(kept).enumerate() {
+        for (i, file) in group.files.iter().enumerate() {
+            let row = [
+                Cow::from((number + 1).to_string()),
+                group.hash.as_str().into(),
+                file.size.to_string().into(),
+                file.path.as_str().into(),
+                duplicate_action(group, i, keep).into(),
+            ];
+            write_row(out, &row, ',')?;
+        }
+    }
+    out.flush()
+}
+
+/// The rows of [`write_duplicates_csv`] as JSON Lines (NDJSON), one object
+/// per file, for `jq` and other line-oriented tools
+pub fn write_duplicates_ndjson(
+    out: &mut impl Write,
+    groups: &[DuplicateGroup],
+    kept: &[Option<usize>],
+) -> io::Result<()> {
+    for (number, (group, &keep)) in groups.iter().zip(kept).enumerate() {
+        for (i, file) in group.files.iter().enumerate() {
+            let row = json!({
+                "group": number + 1,
+                "hash": group.hash,
+                "size": file.size,
+                "path": file.path,
+                "action": duplicate_action(group, i, keep),
+            });
+            writeln!(out, "{row}")?;
+        }
+    }
+    out.flush()
+}
+
+/// Write one row of [`write_delimited`]
+fn write_row(out: &mut impl Write, fields: &[Cow<str>], separator: char) -> io::Result<()> {
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            write!(out, "{separator}")?;
+        }
+        if field.contains([separator, '"', '\n', '\r']) {
+            write!(out, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            out.write_all(field.as_bytes())?;
+        }
+    }
+    out.write_all(b"\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn test_treemap_json_nests_directories() {
+        let direct: HashMap<PathBuf, (u64, u64)> = [("/data", (5, 1)), ("/data/a/b", (100, 2))]
+            .into_iter()
+            .map(|(dir, sizes)| (PathBuf::from(dir), sizes))
+            .collect();
+        let tree = DirectoryNode::from_direct(Path::new("/data"), &direct);
+
+        let treemap = treemap_json(&tree);
+        assert_eq!(treemap["name"], "data");
+        assert_eq!(treemap["value"], 105);
+        assert_eq!(treemap["children"][0]["name"], "a");
+        assert_eq!(treemap["children"][0]["children"][0]["value"], 100);
+        assert!(treemap["children"][0]["children"][0]
+            .get("children")
+            .is_none());
+    }
+
+    #[test]
+    fn test_ncdu_json_nests_directories() {
+        let mut linked = FileEntity::new("/data/a/b/linked.bin".into(), 100);
+        linked.allocated_size = Some(4096);
+        linked.hardlink_count = Some(2);
+        let files = [
+            FileEntity::new("/data/top.txt".into(), 5),
+            linked,
+            FileEntity::new("/data/a/mid.txt".into(), 7),
+            FileEntity::new("/elsewhere/skipped.txt".into(), 1),
+        ];
+
+        let export = ncdu_json(Path::new("/data"), &files, 1_700_000_000);
+        assert_eq!((export[0].clone(), export[1].clone()), (json!(1), json!(2)));
+        assert_eq!(export[2]["progname"], "dragonfly");
+        assert_eq!(export[2]["timestamp"], 1_700_000_000);
+
+        // [{root}, top.txt, [{a}, mid.txt, [{b}, linked.bin]]]
+        let root = export[3].as_array().unwrap();
+        assert_eq!(root.len(), 3);
+        assert_eq!(root[0]["name"], "/data");
+        assert_eq!(root[1], json!({"name": "top.txt", "asize": 5, "dsize": 5}));
+        let a = root[2].as_array().unwrap();
+        assert_eq!(a[0], json!({"name": "a"}));
+        assert_eq!(a[1]["name"], "mid.txt");
+        let b = a[2].as_array().unwrap();
+        assert_eq!(b[0], json!({"name": "b"}));
+        assert_eq!(
+            b[1],
+            json!({"name": "linked.bin", "asize": 100, "dsize": 4096})
+        );
+        assert_eq!(b.len(), 2);
+    }
+
+    #[test]
+    fn test_write_delimited_quotes_fields() {
+        let mut plain = FileEntity::new("/data/movie.mp4".into(), 10);
+        plain.allocated_size = Some(4096);
+        let quoted = FileEntity::new("/data/a, \"b\".txt".into(), 5);
+
+        let mut csv = Vec::new();
+        write_delimited(&mut csv, &[plain.clone(), quoted], ',').unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "path,size,allocated_size,modified,category\n\
+             /data/movie.mp4,10,4096,,video\n\
+             \"/data/a, \"\"b\"\".txt\",5,,,documents\n"
+        );
+
+        let mut tsv = Vec::new();
+        write_delimited(&mut tsv, &[plain], '\t').unwrap();
+        assert!(String::from_utf8(tsv)
+            .unwrap()
+            .ends_with("/data/movie.mp4\t10\t4096\t\tvideo\n"));
+    }
+
+    #[test]
+    fn test_duplicate_report_rows() {
+        let mut protected = FileEntity::new("/data/Photos.photoslibrary/a.jpg".into(), 3);
+        protected.protection = dragonfly_core::domain::PathProtection::Preserved;
+        let group = DuplicateGroup {
+            files: vec![
+                FileEntity::new("/data/a.jpg".into(), 3),
+                FileEntity::new("/data/copy of a.jpg".into(), 3),
+                protected,
+            ],
+            hash: "abc".into(),
+            confirmed_by: dragonfly_duplicates::DetectionStage::FullHash,
+            verification: None,
+        };
+        let groups = [group.clone(), group];
+
+        let mut csv = Vec::new();
+        write_duplicates_csv(&mut csv, &groups, &[Some(0), None]).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "group,hash,size,path,action");
+        assert_eq!(lines[1], "1,abc,3,/data/a.jpg,keep");
+        assert_eq!(lines[2], "1,abc,3,/data/copy of a.jpg,remove");
+        assert_eq!(
+            lines[3],
+            "1,abc,3,/data/Photos.photoslibrary/a.jpg,protected"
+        );
+        assert_eq!(lines[4], "2,abc,3,/data/a.jpg,keep");
+        assert_eq!(lines.len(), 7);
+
+        let mut ndjson = Vec::new();
+        write_duplicates_ndjson(&mut ndjson, &groups[..1], &[Some(0)]).unwrap();
+        let rows: Vec<Value> = String::from_utf8(ndjson)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[1]["action"], "remove");
+        assert_eq!(rows[1]["group"], 1);
+    }
+}
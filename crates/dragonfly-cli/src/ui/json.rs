@@ -0,0 +1,15 @@
+//! JSON report output
+
+use anyhow::Result;
+use dragonfly_core::domain::redaction;
+use serde::Serialize;
+
+/// Print `value` as pretty JSON, redacting paths if `--redact-paths` is on
+pub fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<()> {
+    let json = serde_json::to_string_pretty(value)?;
+    match redaction::redactor() {
+        Some(redactor) => println!("{}", redactor.redact(&json)),
+        None => println!("{}", json),
+    }
+    Ok(())
+}
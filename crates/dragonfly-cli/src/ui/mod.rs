@@ -1,9 +1,19 @@
 //! User interface components for the CLI
 
 pub mod colors;
+pub mod export;
+pub mod json;
+pub mod profile;
 pub mod progress;
+pub mod skipped;
+pub mod sparkline;
 pub mod table;
 
 pub use colors::*;
+pub use export::*;
+pub use json::*;
+pub use profile::*;
 pub use progress::*;
+pub use skipped::*;
+pub use sparkline::*;
 pub use table::*;
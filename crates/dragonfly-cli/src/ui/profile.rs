@@ -0,0 +1,78 @@
+//! Scan profile output for `--profile`
+
+use colored::Colorize;
+use dragonfly_core::domain::scan_profile::ScanProfile;
+use dragonfly_core::t;
+use serde_json::{json, Value};
+
+/// `profile` as JSON, with throughput alongside the raw timings
+pub fn profile_json(profile: &ScanProfile) -> Value {
+    json!({
+        "elapsed_micros": profile.elapsed_micros,
+        "walk_micros": profile.walk_micros,
+        "stat_micros": profile.stat_micros,
+        "hash_micros": profile.hash_micros,
+        "aggregate_micros": profile.aggregate_micros,
+        "files": profile.files,
+        "bytes": profile.bytes,
+        "hashed_bytes": profile.hashed_bytes,
+        "files_per_second": profile.files_per_second(),
+        "megabytes_per_second": profile.megabytes_per_second(),
+        "hash_megabytes_per_second": profile.hash_megabytes_per_second(),
+    })
+}
+
+/// Print per-phase timings and throughput
+pub fn print_profile(profile: &ScanProfile) {
+    println!();
+    println!("{}", t!("profile.title").bold());
+    let phases = [
+        (t!("profile.walk"), profile.walk_micros),
+        (t!("profile.stat"), profile.stat_micros),
+        (t!("profile.hash"), profile.hash_micros),
+        (t!("profile.aggregate"), profile.aggregate_micros),
+        (t!("profile.elapsed"), profile.elapsed_micros),
+    ];
+    for (phase, micros) in phases {
+        println!("  {:<12} {:>10}", phase, format_micros(micros));
+    }
+    println!(
+        "  {}",
+        t!(
+            "profile.throughput",
+            files = format!("{:.0}", profile.files_per_second()),
+            megabytes = format!("{:.1}", profile.megabytes_per_second())
+        )
+    );
+    if profile.hashed_bytes > 0 {
+        println!(
+            "  {}",
+            t!(
+                "profile.hash_throughput",
+                megabytes = format!("{:.1}", profile.hash_megabytes_per_second())
+            )
+        );
+    }
+    println!("  {}", t!("profile.threads_note").dimmed());
+}
+
+/// Human-readable duration, from microseconds
+fn format_micros(micros: u64) -> String {
+    if micros >= 1_000_000 {
+        format!("{:.2} s", micros as f64 / 1_000_000.0)
+    } else {
+        format!("{:.1} ms", micros as f64 / 1_000.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_micros() {
+        assert_eq!(format_micros(0), "0.0 ms");
+        assert_eq!(format_micros(12_345), "12.3 ms");
+        assert_eq!(format_micros(2_500_000), "2.50 s");
+    }
+}
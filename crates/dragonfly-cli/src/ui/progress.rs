@@ -1,7 +1,13 @@
 //! Progress bar and spinner utilities
 
+use dragonfly_core::ports::ProgressUpdate;
+use dragonfly_core::t;
+use humansize::{format_size, DECIMAL};
 use indicatif::{ProgressBar, ProgressStyle};
 
+/// Characters of the current path shown next to a scan's running totals
+const PATH_WIDTH: usize = 50;
+
 pub fn create_spinner(msg: &str) -> ProgressBar {
     let pb = ProgressBar::new_spinner();
     pb.set_style(
@@ -25,3 +31,51 @@ pub fn create_progress_bar(total: u64, msg: &str) -> ProgressBar {
     pb.set_message(msg.to_string());
     pb
 }
+
+/// Switch `pb` to a bar of `total` bytes with a remaining-time estimate,
+/// starting over from zero
+pub fn show_byte_progress(pb: &ProgressBar, total: u64) {
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} {msg} [{bar:30.cyan/blue}] {decimal_bytes}/{decimal_total_bytes} ({eta})")
+            .unwrap()
+            .progress_chars("█▓░"),
+    );
+    pb.reset();
+    pb.set_length(total);
+}
+
+/// Spinner message for a scan in progress: running totals and the latest
+/// file, shortened from the left so the line doesn't wrap
+pub fn scan_progress_message(update: &ProgressUpdate) -> String {
+    let totals = t!(
+        "common.scan_progress",
+        files = update.items_processed,
+        size = format_size(update.bytes_processed, DECIMAL)
+    );
+    match &update.current_path {
+        Some(path) => format!("{totals} - {}", shorten_left(path, PATH_WIDTH)),
+        None => totals,
+    }
+}
+
+/// The last `width` characters of `text`, with an ellipsis if cut
+fn shorten_left(text: &str, width: usize) -> String {
+    let count = text.chars().count();
+    if count <= width {
+        return text.to_string();
+    }
+    let tail: String = text.chars().skip(count - width + 1).collect();
+    format!("…{tail}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shorten_left() {
+        assert_eq!(shorten_left("/tmp/a", 10), "/tmp/a");
+        assert_eq!(shorten_left("/Users/me/Library/file", 8), "…ry/file");
+    }
+}
@@ -0,0 +1,41 @@
+//! Reporting of paths skipped by scans and cleans
+
+use colored::*;
+use dragonfly_core::domain::protection::{SkipReason, SkippedPath};
+use dragonfly_core::t;
+
+/// Print a summary of skipped paths grouped by reason
+pub fn print_skipped(skipped: &[SkippedPath]) {
+    if skipped.is_empty() {
+        return;
+    }
+
+    println!(
+        "\n{}",
+        t!("skipped.summary", count = skipped.len()).yellow()
+    );
+
+    for reason in [
+        SkipReason::SipProtected,
+        SkipReason::SystemCritical,
+        SkipReason::PrivacyProtected,
+        SkipReason::PermissionDenied,
+        SkipReason::PolicyProtected,
+        SkipReason::InUse,
+    ] {
+        let paths: Vec<&SkippedPath> = skipped.iter().filter(|s| s.reason == reason).collect();
+        if paths.is_empty() {
+            continue;
+        }
+        println!("  {} - {}", paths.len(), reason.explanation());
+        for skipped_path in paths.iter().take(3) {
+            println!("    {}", skipped_path.path.dimmed());
+        }
+        if paths.len() > 3 {
+            println!(
+                "    {}",
+                t!("skipped.more", count = paths.len() - 3).dimmed()
+            );
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! Sparklines for charting a series in a single line of text
+
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Chart `values` as block characters, at most `width` of them
+///
+/// Longer series are split into `width` buckets charted by their largest
+/// value, so peaks stay visible.
+pub fn sparkline(values: &[u64], width: usize) -> String {
+    let buckets: Vec<u64> = if values.len() > width && width > 0 {
+        (0..width)
+            .map(|i| {
+                let start = i * values.len() / width;
+                let end = ((i + 1) * values.len() / width).max(start + 1);
+                values[start..end].iter().copied().max().unwrap_or(0)
+            })
+            .collect()
+    } else {
+        values.to_vec()
+    };
+
+    let low = buckets.iter().copied().min().unwrap_or(0);
+    let high = buckets.iter().copied().max().unwrap_or(0);
+    chart(&buckets, low, high)
+}
+
+/// Chart `values` as block characters on a fixed scale from `low` to `high`,
+/// at most `width` of them, keeping the latest if there are more
+///
+/// Unlike [`sparkline`], a flat series stays at its level on the scale, so
+/// a steady 20% reads low rather than mid-height.
+pub fn sparkline_between(values: &[u64], width: usize, low: u64, high: u64) -> String {
+    let latest = &values[values.len().saturating_sub(width)..];
+    let clamped: Vec<u64> = latest.iter().map(|v| (*v).clamp(low, high)).collect();
+    chart(&clamped, low, high)
+}
+
+/// Block characters for `values`, all between `low` and `high`
+fn chart(values: &[u64], low: u64, high: u64) -> String {
+    values
+        .iter()
+        .map(|&value| {
+            if high == low {
+                return BARS[BARS.len() / 2];
+            }
+            let level = (value - low) as f64 / (high - low) as f64;
+            BARS[(level * (BARS.len() - 1) as f64).round() as usize]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparkline_scales_and_buckets() {
+        assert_eq!(sparkline(&[0, 7, 14], 10), "▁▅█");
+        assert_eq!(sparkline(&[5, 5], 10), "▅▅");
+        assert_eq!(sparkline(&[1, 9, 1, 1], 2), "█▁");
+        assert_eq!(sparkline(&[], 10), "");
+    }
+
+    #[test]
+    fn test_sparkline_between_keeps_scale_and_latest() {
+        assert_eq!(sparkline_between(&[20, 20], 10, 0, 100), "▂▂");
+        assert_eq!(sparkline_between(&[0, 150, 50, 100], 3, 0, 100), "█▅█");
+    }
+}
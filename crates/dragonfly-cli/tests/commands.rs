@@ -0,0 +1,232 @@
+//! JSON output and refusals of the command handlers
+//!
+//! Like the dry-run tests, each test runs the binary with `HOME` pointed at a
+//! temporary directory, which holds the index, saved scans, history, audit
+//! log and trash it reads and writes.
+
+use assert_cmd::Command;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+use tempfile::TempDir;
+
+/// Run `dragonfly <args>` with `home` as the home folder
+fn run(home: &Path, args: &[&str]) -> Output {
+    Command::cargo_bin("dragonfly")
+        .unwrap()
+        .env("HOME", home)
+        .env("RUST_BACKTRACE", "0")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Run `dragonfly --json <args>` and return its JSON output
+fn json_output(home: &Path, args: &[&str]) -> Value {
+    let output = run(home, &[&["--json"], args].concat());
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+/// Run `dragonfly <args>`, which must fail, and return its error output
+fn refusal(home: &Path, args: &[&str]) -> String {
+    let output = run(home, args);
+    assert!(!output.status.success(), "{:?} succeeded", args);
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+/// A folder under `home` holding `files`, by canonical path as the index and
+/// history store it
+fn folder(home: &Path, files: &[(&str, &[u8])]) -> PathBuf {
+    let dir = home.join("data");
+    fs::create_dir_all(&dir).unwrap();
+    for (name, contents) in files {
+        fs::write(dir.join(name), contents).unwrap();
+    }
+    fs::canonicalize(dir).unwrap()
+}
+
+/// The home folder's trash
+fn trash(home: &Path) -> PathBuf {
+    if cfg!(target_os = "macos") {
+        home.join(".Trash")
+    } else {
+        home.join(".local/share/Trash/files")
+    }
+}
+
+#[test]
+fn index_builds_lists_and_removes_roots() {
+    let home = TempDir::new().unwrap();
+    let dir = folder(home.path(), &[("a.bin", b"aaaa"), ("b.bin", b"bb")]);
+    let root = dir.to_str().unwrap();
+
+    let built = json_output(home.path(), &["index", root]);
+    assert_eq!(built["roots"], json!([{ "root": root, "files": 2 }]));
+    assert_eq!(built["interrupted"], false);
+
+    let status = json_output(home.path(), &["index", "--status"]);
+    assert_eq!(status["roots"][0]["root"], root);
+    assert_eq!(status["roots"][0]["files"], 2);
+    assert_eq!(status["roots"][0]["total_size"], 6);
+
+    let removed = json_output(home.path(), &["index", root, "--remove"]);
+    assert_eq!(removed["removed"], json!([root]));
+    let status = json_output(home.path(), &["index", "--status"]);
+    assert_eq!(status["roots"], json!([]));
+}
+
+#[test]
+fn analyze_refuses_walk_options_with_index() {
+    let home = TempDir::new().unwrap();
+    let dir = folder(home.path(), &[("a.bin", b"a")]);
+    let root = dir.to_str().unwrap();
+
+    for args in [
+        ["--index", "--max-depth", "1"],
+        ["--strategy", "incremental", "--one-filesystem"],
+    ] {
+        let error = refusal(
+            home.path(),
+            &[&["disk", "analyze", root], &args[..]].concat(),
+        );
+        assert!(error.contains("size index covers whole trees"), "{error}");
+    }
+}
+
+#[test]
+fn trash_empty_asks_unless_yes_and_is_audited() {
+    let home = TempDir::new().unwrap();
+    let trash = trash(home.path());
+    fs::create_dir_all(&trash).unwrap();
+    fs::write(trash.join("old.txt"), b"junk!").unwrap();
+
+    let status = json_output(home.path(), &["trash", "status"]);
+    assert_eq!(status["items"], 1);
+    assert_eq!(status["bytes"], 5);
+    assert_eq!(status["trashes"][0]["volume"], Value::Null);
+
+    // JSON output can't answer the question, so it needs --yes
+    let error = refusal(home.path(), &["--json", "trash", "empty"]);
+    assert!(error.contains("--yes"), "{error}");
+    assert!(trash.join("old.txt").exists());
+
+    let emptied = json_output(home.path(), &["trash", "empty", "--yes"]);
+    assert_eq!(emptied["dry_run"], false);
+    assert_eq!(emptied["items"], 1);
+    assert_eq!(emptied["bytes"], 5);
+    assert_eq!(emptied["kept"], 0);
+    assert!(!trash.join("old.txt").exists());
+
+    let entries = json_output(home.path(), &["audit", "list"]);
+    let entries = entries.as_array().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["action"], "delete");
+    assert_eq!(entries[0]["items"], 1);
+    assert_eq!(entries[0]["bytes"], 5);
+    assert_eq!(entries[0]["recovery_id"], Value::Null);
+}
+
+#[test]
+fn audit_list_is_empty_without_a_log() {
+    let home = TempDir::new().unwrap();
+    assert_eq!(json_output(home.path(), &["audit", "list"]), json!([]));
+}
+
+#[test]
+fn apps_reports_bundles_in_home_applications() {
+    let home = TempDir::new().unwrap();
+    let binary = home
+        .path()
+        .join("Applications/Editor.app/Contents/MacOS/Editor");
+    fs::create_dir_all(binary.parent().unwrap()).unwrap();
+    fs::write(&binary, [0; 100]).unwrap();
+
+    let output = json_output(home.path(), &["apps", "--min-size", "1"]);
+    assert_eq!(output["min_size_bytes"], 1);
+    assert_eq!(output["unused_days"], 90);
+    let editor = output["apps"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|app| app["name"] == "Editor")
+        .unwrap();
+    assert_eq!(editor["bundle_size"], 100);
+    assert_eq!(editor["data_size"], 0);
+    assert_eq!(editor["total_size"], 100);
+
+    let error = refusal(home.path(), &["apps", "--min-size", "lots"]);
+    assert!(error.contains("Invalid size format"), "{error}");
+}
+
+#[test]
+fn snapshots_list_show_and_delete_saved_scans() {
+    let home = TempDir::new().unwrap();
+    let dir = folder(home.path(), &[("big.bin", &[0; 64]), ("small.bin", b"s")]);
+    let root = dir.to_str().unwrap();
+    json_output(home.path(), &["disk", "analyze", root, "--save", "before"]);
+
+    let list = json_output(home.path(), &["disk", "snapshots", "list"]);
+    assert_eq!(list["scans"][0]["name"], "before");
+    assert_eq!(list["scans"][0]["file_count"], 2);
+    assert_eq!(list["scans"][0]["total_size"], 65);
+
+    let show = json_output(
+        home.path(),
+        &["disk", "snapshots", "show", "before", "--min-size", "2"],
+    );
+    assert_eq!(show["root"], root);
+    assert_eq!(show["file_count"], 2);
+    assert_eq!(show["matching_files"], 1);
+    assert_eq!(
+        show["files"],
+        json!([{ "path": dir.join("big.bin"), "size": 64 }])
+    );
+
+    let file = home.path().join(".dragonfly/scans/before.json");
+    let dry_run = json_output(
+        home.path(),
+        &["--dry-run", "disk", "snapshots", "delete", "before"],
+    );
+    assert_eq!(dry_run["deleted"], false);
+    assert!(file.exists());
+    let deleted = json_output(home.path(), &["disk", "snapshots", "delete", "before"]);
+    assert_eq!(deleted["deleted"], true);
+    assert!(!file.exists());
+
+    let error = refusal(home.path(), &["disk", "snapshots", "show", "before"]);
+    assert!(error.contains("No saved scan named before"), "{error}");
+}
+
+#[test]
+fn trends_chart_scans_and_cleaned_bytes() {
+    let home = TempDir::new().unwrap();
+    let empty = json_output(home.path(), &["trends"]);
+    assert_eq!(empty["days"], 90);
+    assert_eq!(empty["scans"], json!([]));
+    assert_eq!(
+        empty["cleaned"],
+        json!({ "bytes": 0, "operations": 0, "daily": [] })
+    );
+
+    let dir = folder(home.path(), &[("a.bin", b"aaaa")]);
+    json_output(home.path(), &["disk", "analyze", dir.to_str().unwrap()]);
+    let trash = trash(home.path());
+    fs::create_dir_all(&trash).unwrap();
+    fs::write(trash.join("old.txt"), b"junk!").unwrap();
+    json_output(home.path(), &["trash", "empty", "--yes"]);
+
+    let trends = json_output(home.path(), &["trends", "--days", "7"]);
+    assert_eq!(trends["days"], 7);
+    assert_eq!(trends["scans"][0]["path"], dir.to_str().unwrap());
+    // One scan is too few points for a trend
+    assert_eq!(trends["scans"][0]["trend"], Value::Null);
+    assert_eq!(trends["cleaned"]["bytes"], 5);
+    assert_eq!(trends["cleaned"]["operations"], 1);
+    assert_eq!(trends["cleaned"]["daily"][0]["bytes"], 5);
+}
@@ -0,0 +1,117 @@
+//! The global `--dry-run` must leave the filesystem alone for every
+//! destructive command
+//!
+//! Each test runs the binary with `HOME` pointed at a temporary directory, so
+//! the caches, recoveries and audit log it touches are all inside it.
+
+use assert_cmd::Command;
+use dragonfly_cleaner::RecoveryManager;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+/// Run `dragonfly --dry-run --json <args>` with `home` as the home folder and
+/// return its JSON output
+fn dry_run(home: &Path, args: &[&str]) -> Value {
+    let output = Command::cargo_bin("dragonfly")
+        .unwrap()
+        .env("HOME", home)
+        .args(["--dry-run", "--json"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+/// Nothing was archived: no recovery holds any item
+fn assert_no_recoveries(home: &Path) {
+    let recovery = RecoveryManager::new(home.join(".dragonfly/recovery"));
+    let recoveries = recovery.list_recoveries().unwrap_or_default();
+    assert!(recoveries.is_empty(), "{:?}", recoveries);
+}
+
+#[test]
+fn clean_leaves_caches_in_place() {
+    let home = TempDir::new().unwrap();
+    let cache = home.path().join("Library/Caches/com.example.app/blob.bin");
+    fs::create_dir_all(cache.parent().unwrap()).unwrap();
+    fs::write(&cache, b"cached").unwrap();
+
+    let output = dry_run(home.path(), &["clean", "--caches", "--scope", "user"]);
+    assert_eq!(output["dry_run"], true);
+    assert_eq!(output["files_found"], 1);
+    assert!(output["recovery_id"].is_null());
+    assert_eq!(fs::read(&cache).unwrap(), b"cached");
+    assert_no_recoveries(home.path());
+}
+
+#[test]
+fn duplicates_delete_keeps_every_copy() {
+    let home = TempDir::new().unwrap();
+    let dir = home.path().join("photos");
+    fs::create_dir_all(&dir).unwrap();
+    for name in ["a.jpg", "b.jpg", "c.jpg"] {
+        fs::write(dir.join(name), b"the same picture").unwrap();
+    }
+
+    let output = dry_run(
+        home.path(),
+        &[
+            "duplicates",
+            "scan",
+            dir.to_str().unwrap(),
+            "--min-size",
+            "1",
+            "--delete",
+            "--no-cache",
+        ],
+    );
+    assert_eq!(output["dry_run"], true);
+    assert_eq!(output["group_count"], 1);
+    assert_eq!(output["delete"]["files"].as_array().unwrap().len(), 2);
+    assert_eq!(output["delete"]["removed"], 0);
+    assert!(output["delete"]["recovery_id"].is_null());
+    for name in ["a.jpg", "b.jpg", "c.jpg"] {
+        assert!(dir.join(name).exists());
+    }
+    assert_no_recoveries(home.path());
+}
+
+#[test]
+fn time_machine_delete_removes_nothing() {
+    let home = TempDir::new().unwrap();
+    let id = "com.apple.TimeMachine.2025-01-20-143000.local";
+
+    let output = dry_run(home.path(), &["time-machine", "delete", id]);
+    assert_eq!(output["dry_run"], true);
+    assert_eq!(output["snapshots"], serde_json::json!([id]));
+    // A real deletion is audited; a dry run leaves no trace
+    assert!(!home.path().join(".dragonfly/audit.jsonl").exists());
+}
+
+#[test]
+fn recover_cleanup_keeps_expired_recoveries() {
+    let home = TempDir::new().unwrap();
+    let recovery = RecoveryManager::new(home.path().join(".dragonfly/recovery"));
+    recovery.initialize().unwrap();
+    let file = home.path().join("old.log");
+    fs::write(&file, b"log").unwrap();
+    let mut manifest = recovery.create_manifest(30);
+    recovery
+        .archive_item(&mut manifest, &file, "log", "test")
+        .unwrap();
+    manifest.retention_until = chrono::Utc::now() - chrono::Duration::days(1);
+    recovery.save_manifest(&manifest).unwrap();
+
+    let output = dry_run(home.path(), &["recover", "cleanup"]);
+    assert_eq!(output["dry_run"], true);
+    assert_eq!(output["recoveries"], serde_json::json!([manifest.id]));
+    assert_eq!(recovery.list_recoveries().unwrap().len(), 1);
+    assert!(recovery.archive_location(&manifest).exists());
+}
@@ -0,0 +1,58 @@
+//! Config file lists - `key = ["a", "b"]` settings in `~/.dragonfly/config`
+
+use crate::error::{Error, Result};
+
+/// Whether `source` sets `key` at all, even to an empty list
+#[must_use]
+pub fn is_set(source: &str, key: &str) -> bool {
+    source.lines().any(|line| {
+        line.split_once('=')
+            .is_some_and(|(name, _)| name.trim() == key)
+    })
+}
+
+/// Entries of the list `key = [...]` in `source`, empty if it isn't set
+///
+/// Lists may span several lines; entries must be double-quoted.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidInput`] for a value that isn't a list, a list
+/// that isn't closed, and entries that aren't quoted.
+pub fn quoted_list(source: &str, key: &str) -> Result<Vec<String>> {
+    let mut lines = source.lines();
+    let Some(first) = lines.by_ref().find_map(|line| {
+        let (name, value) = line.split_once('=')?;
+        (name.trim() == key).then_some(value)
+    }) else {
+        return Ok(Vec::new());
+    };
+
+    let mut list = first.trim().to_string();
+    if !list.starts_with('[') {
+        return Err(Error::InvalidInput(format!(
+            "{key} must be a list, e.g. {key} = [\"~/Documents\"]"
+        )));
+    }
+    while !list.ends_with(']') {
+        let Some(line) = lines.next() else {
+            return Err(Error::InvalidInput(format!(
+                "{key} list is missing a closing ]"
+            )));
+        };
+        list.push_str(line.trim());
+    }
+
+    list[1..list.len() - 1]
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            entry
+                .strip_prefix('"')
+                .and_then(|entry| entry.strip_suffix('"'))
+                .map(str::to_string)
+                .ok_or_else(|| Error::InvalidInput(format!("{key} entry {entry} must be quoted")))
+        })
+        .collect()
+}
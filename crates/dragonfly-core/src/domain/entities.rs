@@ -1,5 +1,7 @@
 //! Domain entities - Objects with identity
 
+use super::protection::PathProtection;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 /// Health status enumeration
@@ -20,6 +22,73 @@ pub struct FileEntity {
     pub path: String,
     /// File size in bytes
     pub size: u64,
+    /// Space the file occupies on disk (its allocated blocks) in bytes, when
+    /// known; smaller than `size` for sparse and compressed files
+    #[serde(default)]
+    pub allocated_size: Option<u64>,
+    /// Number of names (hard links) the file has, when known
+    #[serde(default)]
+    pub hardlink_count: Option<u64>,
+    /// Number of files inside, when the entry stands for a whole package
+    /// (a bundle directory such as an `.app` that Finder shows as one item)
+    #[serde(default)]
+    pub package_files: Option<u64>,
+    /// User id of the file's owner, when known
+    #[serde(default)]
+    pub owner_uid: Option<u32>,
+    /// When the file was last modified, when known
+    #[serde(default)]
+    pub modified: Option<DateTime<Utc>>,
+    /// Whether the file lies in a protected system location
+    #[serde(default)]
+    pub protection: PathProtection,
+    /// Index of the scan root the file was found under, when a scan was
+    /// given several
+    #[serde(default)]
+    pub root: Option<usize>,
+}
+
+impl FileEntity {
+    /// Create a new file entity, classifying its protection from the path
+    #[must_use]
+    pub fn new(path: String, size: u64) -> Self {
+        let protection = PathProtection::classify(&path);
+        Self {
+            path,
+            size,
+            allocated_size: None,
+            hardlink_count: None,
+            package_files: None,
+            owner_uid: None,
+            modified: None,
+            protection,
+            root: None,
+        }
+    }
+
+    /// Allocated size when known, logical size otherwise
+    #[must_use]
+    pub fn size_on_disk(&self) -> u64 {
+        self.allocated_size.unwrap_or(self.size)
+    }
+
+    /// Whether the file was last modified after `after` and before `before`
+    /// (each bound optional); a file whose modification time is unknown only
+    /// matches when there are no bounds
+    #[must_use]
+    pub fn modified_between(
+        &self,
+        after: Option<DateTime<Utc>>,
+        before: Option<DateTime<Utc>>,
+    ) -> bool {
+        if after.is_none() && before.is_none() {
+            return true;
+        }
+        self.modified.is_some_and(|modified| {
+            after.map_or(true, |after| modified >= after)
+                && before.map_or(true, |before| modified < before)
+        })
+    }
 }
 
 /// Directory entity (MVP stub)
@@ -35,3 +104,22 @@ pub struct SystemSnapshot {
     /// Timestamp of the snapshot
     pub timestamp: u64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_modified_between() {
+        let at = |day| Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap();
+        let mut file = FileEntity::new("/data/a.txt".into(), 1);
+        assert!(file.modified_between(None, None));
+        assert!(!file.modified_between(Some(at(1)), None));
+
+        file.modified = Some(at(10));
+        assert!(file.modified_between(Some(at(10)), Some(at(11))));
+        assert!(!file.modified_between(Some(at(11)), None));
+        assert!(!file.modified_between(None, Some(at(10))));
+    }
+}
@@ -0,0 +1,31 @@
+//! Loopback hosts - Which `Host` headers local-only HTTP endpoints accept
+//!
+//! The metrics exporter and the local API answer only requests addressed to
+//! this machine by name, so a web page that rebinds its own domain to
+//! 127.0.0.1 still can't read them.
+
+/// Whether a `Host` header value names this machine's loopback interface
+#[must_use]
+pub fn is_local_host(host: &str) -> bool {
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    };
+    matches!(name, "localhost" | "127.0.0.1" | "[::1]")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_local_host() {
+        assert!(is_local_host("localhost:7878"));
+        assert!(is_local_host("127.0.0.1:9095"));
+        assert!(is_local_host("[::1]:9095"));
+        assert!(is_local_host("localhost"));
+        assert!(!is_local_host("attacker.example:7878"));
+        assert!(!is_local_host("localhost.attacker.example"));
+        assert!(!is_local_host(""));
+    }
+}
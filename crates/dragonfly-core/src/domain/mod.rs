@@ -6,21 +6,48 @@
 //!
 //! ## Module Organization
 //!
+//! - [`config`]: `key = [...]` list settings in `~/.dragonfly/config`
 //! - [`entities`]: Domain entities with identity (File, Directory, System)
 //! - [`value_objects`]: Immutable value objects (FileSize, FilePath, Percentage)
 //! - [`events`]: Domain events that capture important business occurrences
+//! - [`loopback`]: Which `Host` headers local-only HTTP endpoints accept
+//! - [`policy`]: User-configured protected and allowed locations
+//! - [`protection`]: SIP-protected, system-critical, and privacy-protected locations
+//! - [`redaction`]: Placeholders for user names and segments in shareable reports
+//! - [`scan_options`]: Behavior flags shared by every filesystem walker
+//! - [`scan_profile`]: Per-phase timings and throughput of a scan
+//! - [`throttle`]: A process-wide pace for filesystem walks
 
+pub mod config;
 pub mod entities;
 pub mod events;
+pub mod loopback;
+pub mod policy;
+pub mod protection;
+pub mod redaction;
+pub mod scan_options;
+pub mod scan_profile;
+pub mod throttle;
 pub mod value_objects;
 
 pub use entities::{DirectoryEntity, FileEntity, HealthStatus, SystemSnapshot};
 pub use events::DomainEvent;
+pub use loopback::is_local_host;
+pub use policy::ProtectionPolicy;
+pub use protection::{PathProtection, SkipReason, SkippedPath};
+pub use redaction::PathRedactor;
+pub use scan_options::ScanOptions;
+pub use scan_profile::{PhaseTimer, ScanProfile};
+pub use throttle::Throttle;
 pub use value_objects::{FilePath, FileSize, Percentage};
 
 /// Re-export commonly used domain types
 pub mod prelude {
     pub use super::entities::*;
     pub use super::events::*;
+    pub use super::policy::*;
+    pub use super::protection::*;
+    pub use super::scan_options::*;
+    pub use super::scan_profile::*;
     pub use super::value_objects::*;
 }
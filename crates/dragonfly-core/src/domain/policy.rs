@@ -0,0 +1,285 @@
+//! Protection policy - The built-in protected locations plus the user's own
+//!
+//! Users extend the built-in denylist in `~/.dragonfly/config`:
+//!
+//! ```text
+//! never_touch = ["~/Documents", "/Volumes/Backup"]
+//! protect_patterns = ["*.sqlite", "~/Library/Caches/*/keep"]
+//! allow_roots = ["~/Library/Caches", "~/Downloads", "/tmp"]
+//! ```
+//!
+//! `never_touch` adds locations nothing may modify, and `protect_patterns`
+//! adds the paths matching a pattern, with the rules of
+//! [`matches_pattern`]. A non-empty `allow_roots`
+//! turns the policy into an allowlist: only paths beneath one of the roots may
+//! be modified. Neither can loosen the built-in protection of system
+//! locations.
+//!
+//! Every destructive operation asks [`policy`] before touching a path, so the
+//! cleaner, recovery archiving, and anything built on them agree.
+
+use super::config::quoted_list;
+use super::protection::{is_within, normalize, PathProtection, SkipReason};
+use super::scan_options::matches_pattern;
+use crate::error::{Error, Result};
+use std::sync::OnceLock;
+
+/// Config key listing locations that must never be modified
+pub const NEVER_TOUCH_KEY: &str = "never_touch";
+
+/// Config key listing patterns of paths that must never be modified
+pub const PROTECT_PATTERNS_KEY: &str = "protect_patterns";
+
+/// Config key listing the only locations that may be modified
+pub const ALLOW_ROOTS_KEY: &str = "allow_roots";
+
+static POLICY: OnceLock<ProtectionPolicy> = OnceLock::new();
+
+/// Which paths destructive operations may modify
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProtectionPolicy {
+    never_touch: Vec<String>,
+    protect_patterns: Vec<String>,
+    allow_roots: Vec<String>,
+}
+
+impl ProtectionPolicy {
+    /// Policy with the given user lists; `~` must already be expanded
+    #[must_use]
+    pub fn new(never_touch: Vec<String>, allow_roots: Vec<String>) -> Self {
+        let clean = |paths: Vec<String>| {
+            paths
+                .iter()
+                .map(|path| normalize(path).to_string())
+                .collect()
+        };
+        Self {
+            never_touch: clean(never_touch),
+            protect_patterns: Vec::new(),
+            allow_roots: clean(allow_roots),
+        }
+    }
+
+    /// Also protect the paths matching any of `patterns`; `~` must already
+    /// be expanded
+    #[must_use]
+    pub fn with_protect_patterns(mut self, patterns: Vec<String>) -> Self {
+        self.protect_patterns = patterns;
+        self
+    }
+
+    /// Read `never_touch`, `protect_patterns` and `allow_roots` from
+    /// config file text
+    ///
+    /// Other settings are ignored. Lists may span several lines, and `~`
+    /// at the start of an entry stands for `home`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidInput`] for a list that isn't closed, entries
+    /// that aren't quoted, and paths that aren't absolute. Patterns without
+    /// a `/` apply to any file or folder name and needn't be absolute.
+    pub fn from_config(source: &str, home: &str) -> Result<Self> {
+        let patterns = quoted_list(source, PROTECT_PATTERNS_KEY)?
+            .into_iter()
+            .map(|pattern| {
+                if !pattern.contains('/') {
+                    return Ok(pattern);
+                }
+                let pattern = expand_home(pattern, home);
+                if pattern.starts_with('/') {
+                    Ok(pattern)
+                } else {
+                    Err(Error::InvalidInput(format!(
+                        "{PROTECT_PATTERNS_KEY} entry {pattern} must be a name or an absolute path"
+                    )))
+                }
+            })
+            .collect::<Result<Vec<String>>>()?;
+        Ok(Self::new(
+            config_list(source, NEVER_TOUCH_KEY, home)?,
+            config_list(source, ALLOW_ROOTS_KEY, home)?,
+        )
+        .with_protect_patterns(patterns))
+    }
+
+    /// Locations the user protected
+    #[must_use]
+    pub fn never_touch(&self) -> &[String] {
+        &self.never_touch
+    }
+
+    /// Patterns of paths the user protected
+    #[must_use]
+    pub fn protect_patterns(&self) -> &[String] {
+        &self.protect_patterns
+    }
+
+    /// Locations the user limited modifications to (empty for no limit)
+    #[must_use]
+    pub fn allow_roots(&self) -> &[String] {
+        &self.allow_roots
+    }
+
+    /// Why `path` must not be modified, or `None` if it may be
+    #[must_use]
+    pub fn check(&self, path: &str) -> Option<SkipReason> {
+        if let Some(reason) = PathProtection::classify(path).skip_reason() {
+            return Some(reason);
+        }
+        let path = normalize(path);
+        let denied = self.never_touch.iter().any(|root| is_within(path, root))
+            || self.matches_protect_pattern(path);
+        let outside_allowed = !self.allow_roots.is_empty()
+            && !self.allow_roots.iter().any(|root| is_within(path, root));
+        (denied || outside_allowed).then_some(SkipReason::PolicyProtected)
+    }
+
+    /// Whether the user's lists leave nothing beneath `path` to modify
+    ///
+    /// Lets a walk skip a refused tree as a whole instead of refusing each
+    /// file in it.
+    #[must_use]
+    pub fn refuses_tree(&self, path: &str) -> bool {
+        let path = normalize(path);
+        self.never_touch.iter().any(|root| is_within(path, root))
+            || self.matches_protect_pattern(path)
+            || (!self.allow_roots.is_empty()
+                && !self
+                    .allow_roots
+                    .iter()
+                    .any(|root| is_within(path, root) || is_within(root, path)))
+    }
+
+    /// Whether `path`, or a folder it lies in, matches a protect pattern
+    fn matches_protect_pattern(&self, path: &str) -> bool {
+        self.protect_patterns
+            .iter()
+            .any(|pattern| matches_pattern(pattern, path))
+    }
+}
+
+/// Install the process-wide policy; later calls are ignored
+pub fn install(policy: ProtectionPolicy) {
+    let _ = POLICY.set(policy);
+}
+
+/// The process-wide policy (built-in protection only until one is installed)
+pub fn policy() -> &'static ProtectionPolicy {
+    POLICY.get_or_init(ProtectionPolicy::default)
+}
+
+/// Entries of the path list `key = [...]` in `source`, with `~` expanded
+fn config_list(source: &str, key: &str, home: &str) -> Result<Vec<String>> {
+    quoted_list(source, key)?
+        .into_iter()
+        .map(|path| {
+            let path = expand_home(path, home);
+            if path.starts_with('/') {
+                Ok(path)
+            } else {
+                Err(Error::InvalidInput(format!(
+                    "{key} entry {path} must be an absolute path"
+                )))
+            }
+        })
+        .collect()
+}
+
+/// `path` with a leading `~` replaced by `home`
+fn expand_home(path: String, home: &str) -> String {
+    match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => format!("{home}{rest}"),
+        _ => path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_config() {
+        let config = "locale = de\nnever_touch = [\"~/Documents\", \"/Volumes/Backup/\"]\nallow_roots = [\n    \"~/Library/Caches\",\n    \"/tmp\",\n]\n";
+        let policy = ProtectionPolicy::from_config(config, "/Users/me").unwrap();
+        assert_eq!(
+            policy.never_touch(),
+            ["/Users/me/Documents", "/Volumes/Backup"]
+        );
+        assert_eq!(policy.allow_roots(), ["/Users/me/Library/Caches", "/tmp"]);
+        let policy = ProtectionPolicy::from_config(
+            "protect_patterns = [\"*.sqlite\", \"~/Library/Caches/*/keep\"]",
+            "/Users/me",
+        )
+        .unwrap();
+        assert_eq!(
+            policy.protect_patterns(),
+            ["*.sqlite", "/Users/me/Library/Caches/*/keep"]
+        );
+
+        assert_eq!(
+            ProtectionPolicy::from_config("locale = de", "/Users/me").unwrap(),
+            ProtectionPolicy::default()
+        );
+        for invalid in [
+            "never_touch = \"~/Documents\"",
+            "never_touch = [\"~/Documents\"",
+            "never_touch = [~/Documents]",
+            "allow_roots = [\"Downloads\"]",
+            "protect_patterns = [\"Caches/*.db\"]",
+        ] {
+            assert!(ProtectionPolicy::from_config(invalid, "/Users/me").is_err());
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let policy = ProtectionPolicy::new(
+            vec!["/Users/me/Library/Caches/keep".to_string()],
+            vec!["/Users/me/Library/Caches".to_string(), "/tmp".to_string()],
+        );
+        assert_eq!(policy.check("/Users/me/Library/Caches/app/a.bin"), None);
+        assert_eq!(
+            policy.check("/Users/me/Library/Caches/keep/a.bin"),
+            Some(SkipReason::PolicyProtected)
+        );
+        assert_eq!(
+            policy.check("/Users/me/Documents/a.txt"),
+            Some(SkipReason::PolicyProtected)
+        );
+        // The built-in protection still applies inside allowed roots
+        assert_eq!(policy.check("/tmp"), Some(SkipReason::SystemCritical));
+        assert_eq!(
+            ProtectionPolicy::default().check("/System/Library/a"),
+            Some(SkipReason::SipProtected)
+        );
+        assert_eq!(
+            ProtectionPolicy::default().check("/Users/me/Documents/a.txt"),
+            None
+        );
+
+        assert!(policy.refuses_tree("/Users/me/Library/Caches/keep"));
+        assert!(policy.refuses_tree("/Users/me/Library/Logs"));
+        assert!(!policy.refuses_tree("/Users/me/Library"));
+        assert!(!policy.refuses_tree("/Users/me/Library/Caches/app"));
+    }
+
+    #[test]
+    fn test_check_protect_patterns() {
+        let policy = ProtectionPolicy::default().with_protect_patterns(vec![
+            "*.sqlite".to_string(),
+            "/Users/me/Library/Caches/*/keep".to_string(),
+        ]);
+        assert_eq!(
+            policy.check("/Users/me/Library/Caches/app/index.sqlite"),
+            Some(SkipReason::PolicyProtected)
+        );
+        assert_eq!(
+            policy.check("/Users/me/Library/Caches/app/keep/a.bin"),
+            Some(SkipReason::PolicyProtected)
+        );
+        assert_eq!(policy.check("/Users/me/Library/Caches/app/a.bin"), None);
+        assert!(policy.refuses_tree("/Users/me/Library/Caches/app/keep"));
+        assert!(!policy.refuses_tree("/Users/me/Library/Caches/app"));
+    }
+}
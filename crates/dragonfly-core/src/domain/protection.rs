@@ -0,0 +1,313 @@
+//! Protected locations - Paths `DragonFly` must never modify or cannot read
+//!
+//! macOS guards parts of the filesystem in two different ways:
+//! - **System Integrity Protection (SIP)** makes the OS itself read-only,
+//!   even for root. Other locations are not SIP-protected but are still
+//!   critical to a working system.
+//! - **Privacy protection (TCC)** hides user data such as Mail or Messages
+//!   from any process that hasn't been granted Full Disk Access.
+//!
+//! Classification here is purely path-based so every scanner and the cleaner
+//! agree on what is protected.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Locations protected by System Integrity Protection
+pub const SIP_PROTECTED_ROOTS: &[&str] = &["/System", "/usr", "/bin", "/sbin"];
+
+/// SIP-protected subtrees that are writable by design
+pub const SIP_EXCEPTIONS: &[&str] = &["/usr/local"];
+
+/// Locations that are not SIP-protected but must never be modified
+pub const SYSTEM_CRITICAL_ROOTS: &[&str] = &[
+    "/etc",
+    "/private/etc",
+    "/dev",
+    "/var/db",
+    "/private/var/db",
+    "/var/vm",
+    "/private/var/vm",
+    "/Library/Keychains",
+    "/Library/Preferences/SystemConfiguration",
+];
+
+/// Directories that are critical themselves, though their contents may not be
+pub const SYSTEM_CRITICAL_DIRECTORIES: &[&str] = &[
+    "/",
+    "/Applications",
+    "/Library",
+    "/Users",
+    "/Volumes",
+    "/private",
+    "/private/var",
+    "/tmp",
+    "/var",
+];
+
+/// Home-relative locations that require Full Disk Access to read
+pub const PRIVACY_PROTECTED_LOCATIONS: &[&str] = &[
+    "Library/Mail",
+    "Library/Messages",
+    "Library/Safari",
+    "Library/Cookies",
+    "Library/Calendars",
+    "Library/HomeKit",
+    "Library/Suggestions",
+    "Library/Application Support/AddressBook",
+    "Library/Application Support/CallHistoryDB",
+    "Library/Application Support/com.apple.TCC",
+    "Library/Containers/com.apple.mail",
+];
+
+/// Home-relative locations that are system-critical for the user account
+const USER_CRITICAL_LOCATIONS: &[&str] = &["Library/Keychains"];
+
+/// Protection level of a path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PathProtection {
+    /// No protection - the path may be modified
+    #[default]
+    None,
+    /// Protected by System Integrity Protection
+    Sip,
+    /// Not SIP-protected, but modifying it could break the system
+    SystemCritical,
+    /// Left alone by the operation at hand, such as a Photos library during
+    /// a duplicate scan; never assigned by [`PathProtection::classify`]
+    Preserved,
+}
+
+impl PathProtection {
+    /// Classify a path
+    #[must_use]
+    pub fn classify(path: &str) -> Self {
+        let path = normalize(path);
+
+        if SIP_PROTECTED_ROOTS.iter().any(|root| is_within(path, root))
+            && !SIP_EXCEPTIONS.iter().any(|root| is_within(path, root))
+        {
+            return Self::Sip;
+        }
+
+        if SYSTEM_CRITICAL_ROOTS
+            .iter()
+            .any(|root| is_within(path, root))
+            || SYSTEM_CRITICAL_DIRECTORIES.contains(&path)
+            || is_home_directory(path)
+            || USER_CRITICAL_LOCATIONS
+                .iter()
+                .any(|location| is_within_home_location(path, location))
+        {
+            return Self::SystemCritical;
+        }
+
+        Self::None
+    }
+
+    /// Whether the path must not be modified
+    #[must_use]
+    pub fn is_protected(self) -> bool {
+        self != Self::None
+    }
+
+    /// Reason to report when an operation skips a path with this protection
+    #[must_use]
+    pub fn skip_reason(self) -> Option<SkipReason> {
+        match self {
+            Self::None => None,
+            Self::Sip => Some(SkipReason::SipProtected),
+            Self::SystemCritical => Some(SkipReason::SystemCritical),
+            Self::Preserved => Some(SkipReason::PolicyProtected),
+        }
+    }
+}
+
+/// Check whether a path lies in a location guarded by macOS privacy controls
+#[must_use]
+pub fn is_privacy_protected(path: &str) -> bool {
+    let path = normalize(path);
+    PRIVACY_PROTECTED_LOCATIONS
+        .iter()
+        .any(|location| is_within_home_location(path, location))
+}
+
+/// Why a path was skipped by a scan or clean
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// Protected by System Integrity Protection
+    SipProtected,
+    /// Critical to the operating system
+    SystemCritical,
+    /// Hidden by macOS privacy controls (needs Full Disk Access)
+    PrivacyProtected,
+    /// Any other permission error
+    PermissionDenied,
+    /// Protected by the user's `never_touch` or `protect_patterns`, or
+    /// outside their `allow_roots`
+    PolicyProtected,
+    /// Open in a running process, which may still be writing to it
+    InUse,
+}
+
+impl SkipReason {
+    /// Reason for a permission error encountered at `path`
+    #[must_use]
+    pub fn for_permission_error(path: &str) -> Self {
+        if is_privacy_protected(path) {
+            Self::PrivacyProtected
+        } else {
+            match PathProtection::classify(path).skip_reason() {
+                Some(reason) => reason,
+                None => Self::PermissionDenied,
+            }
+        }
+    }
+
+    /// Whether `DragonFly` refused the path itself, rather than the OS
+    /// denying access
+    ///
+    /// Files skipped for being open aren't refusals: they are left alone
+    /// only until the process holding them lets go.
+    #[must_use]
+    pub fn is_refusal(self) -> bool {
+        matches!(
+            self,
+            Self::SipProtected | Self::SystemCritical | Self::PolicyProtected
+        )
+    }
+
+    /// Human-readable explanation of the skip, in the installed language
+    #[must_use]
+    pub fn explanation(self) -> &'static str {
+        crate::i18n::catalog().message(match self {
+            Self::SipProtected => "skip.sip_protected",
+            Self::SystemCritical => "skip.system_critical",
+            Self::PrivacyProtected => "skip.privacy_protected",
+            Self::PermissionDenied => "skip.permission_denied",
+            Self::PolicyProtected => "skip.policy_protected",
+            Self::InUse => "skip.in_use",
+        })
+    }
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.explanation())
+    }
+}
+
+/// A path that was skipped instead of failing the whole operation
+///
+/// Scans and cleanups collect these in the `skipped` field of their report,
+/// so one unreadable or protected folder doesn't abort the rest; the
+/// [`SkipReason`] says what was in the way.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedPath {
+    /// Skipped path
+    pub path: String,
+    /// Why it was skipped
+    pub reason: SkipReason,
+}
+
+impl SkippedPath {
+    /// Create a new skipped path record
+    #[must_use]
+    pub fn new(path: String, reason: SkipReason) -> Self {
+        Self { path, reason }
+    }
+
+    /// Record for an I/O error at `path`, if the error was a permission error
+    ///
+    /// Other errors (e.g. a file vanishing mid-scan) are not reported as skips.
+    #[must_use]
+    pub fn from_io_error(path: &str, error: &std::io::Error) -> Option<Self> {
+        (error.kind() == std::io::ErrorKind::PermissionDenied)
+            .then(|| Self::new(path.to_string(), SkipReason::for_permission_error(path)))
+    }
+}
+
+/// Strip trailing separators (but keep the root)
+pub(crate) fn normalize(path: &str) -> &str {
+    let trimmed = path.trim_end_matches('/');
+    if trimmed.is_empty() && path.starts_with('/') {
+        "/"
+    } else {
+        trimmed
+    }
+}
+
+/// Check whether `path` is `root` or lies beneath it
+pub(crate) fn is_within(path: &str, root: &str) -> bool {
+    path.strip_prefix(root)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Check whether `path` is exactly a user home directory (`/Users/<name>`)
+fn is_home_directory(path: &str) -> bool {
+    path.strip_prefix("/Users/")
+        .is_some_and(|name| !name.is_empty() && !name.contains('/'))
+}
+
+/// Check whether `path` lies within `<home>/<location>` for any user home
+fn is_within_home_location(path: &str, location: &str) -> bool {
+    let needle = format!("/{location}");
+    path.match_indices(&needle).any(|(index, _)| {
+        let rest = &path[index + needle.len()..];
+        let home = &path[..index];
+        (rest.is_empty() || rest.starts_with('/')) && !home.is_empty()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("/System/Library/CoreServices", PathProtection::Sip)]
+    #[case("/usr/bin/true", PathProtection::Sip)]
+    #[case("/usr/local/bin/brew", PathProtection::None)]
+    #[case("/usrlocal", PathProtection::None)]
+    #[case("/private/var/db/dslocal", PathProtection::SystemCritical)]
+    #[case(
+        "/Users/me/Library/Keychains/login.keychain-db",
+        PathProtection::SystemCritical
+    )]
+    #[case("/Users/me", PathProtection::SystemCritical)]
+    #[case("/Users/me/", PathProtection::SystemCritical)]
+    #[case("/", PathProtection::SystemCritical)]
+    #[case("/Users/me/Library/Caches/com.example", PathProtection::None)]
+    #[case("/tmp/scratch.txt", PathProtection::None)]
+    fn test_classify(#[case] path: &str, #[case] expected: PathProtection) {
+        assert_eq!(PathProtection::classify(path), expected);
+    }
+
+    #[test]
+    fn test_privacy_protected() {
+        assert!(is_privacy_protected("/Users/me/Library/Mail"));
+        assert!(is_privacy_protected(
+            "/Users/me/Library/Mail/V10/INBOX.mbox"
+        ));
+        assert!(!is_privacy_protected("/Users/me/Library/Mailbox"));
+        assert!(!is_privacy_protected("/Users/me/Library/Caches"));
+    }
+
+    #[test]
+    fn test_skip_reason_for_permission_error() {
+        assert_eq!(
+            SkipReason::for_permission_error("/Users/me/Library/Messages/chat.db"),
+            SkipReason::PrivacyProtected
+        );
+        assert_eq!(
+            SkipReason::for_permission_error("/System/Volumes/Data"),
+            SkipReason::SipProtected
+        );
+        assert_eq!(
+            SkipReason::for_permission_error("/opt/secret"),
+            SkipReason::PermissionDenied
+        );
+    }
+}
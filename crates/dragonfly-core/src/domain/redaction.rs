@@ -0,0 +1,145 @@
+//! Path redaction - Placeholders for personal names in shareable reports
+//!
+//! Reports are full of paths like `/Users/alice/Clients/Acme/...`. With
+//! redaction on, the account name becomes [`USER_PLACEHOLDER`] and every
+//! segment the user lists in `~/.dragonfly/config` becomes
+//! [`SEGMENT_PLACEHOLDER`]:
+//!
+//! ```text
+//! redact_segments = ["Acme", "secret-project"]
+//! ```
+//!
+//! Only whole path segments are replaced, so a file that merely contains the
+//! name (`alice-notes.txt`) keeps it.
+
+use super::config::quoted_list;
+use crate::error::Result;
+use std::sync::OnceLock;
+
+/// Config key listing path segments to redact
+pub const REDACT_SEGMENTS_KEY: &str = "redact_segments";
+
+/// Replacement for the account name
+pub const USER_PLACEHOLDER: &str = "<user>";
+
+/// Replacement for user-listed segments
+pub const SEGMENT_PLACEHOLDER: &str = "<redacted>";
+
+static REDACTOR: OnceLock<PathRedactor> = OnceLock::new();
+
+/// Replaces sensitive path segments with placeholders
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PathRedactor {
+    user: Option<String>,
+    segments: Vec<String>,
+}
+
+impl PathRedactor {
+    /// Redactor for the account whose home directory is `home`, plus
+    /// `segments`
+    #[must_use]
+    pub fn new(home: &str, segments: Vec<String>) -> Self {
+        let user = home
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .filter(|name| !name.is_empty())
+            .map(str::to_string);
+        Self {
+            user,
+            segments: segments
+                .into_iter()
+                .map(|segment| segment.trim_matches('/').to_string())
+                .filter(|segment| !segment.is_empty())
+                .collect(),
+        }
+    }
+
+    /// Read `redact_segments` from config file text
+    ///
+    /// # Errors
+    ///
+    /// Returns [`crate::Error::InvalidInput`] for a list that isn't closed or
+    /// entries that aren't quoted.
+    pub fn from_config(source: &str, home: &str) -> Result<Self> {
+        Ok(Self::new(home, quoted_list(source, REDACT_SEGMENTS_KEY)?))
+    }
+
+    /// Segments the user listed
+    #[must_use]
+    pub fn segments(&self) -> &[String] {
+        &self.segments
+    }
+
+    /// `text` with every redacted segment replaced by its placeholder
+    #[must_use]
+    pub fn redact(&self, text: &str) -> String {
+        let mut redacted = text.to_string();
+        if let Some(user) = &self.user {
+            redacted = replace_segment(&redacted, user, USER_PLACEHOLDER);
+        }
+        for segment in &self.segments {
+            redacted = replace_segment(&redacted, segment, SEGMENT_PLACEHOLDER);
+        }
+        redacted
+    }
+}
+
+/// Install the process-wide redactor; later calls are ignored
+pub fn install(redactor: PathRedactor) {
+    let _ = REDACTOR.set(redactor);
+}
+
+/// The process-wide redactor, if redaction was turned on
+pub fn redactor() -> Option<&'static PathRedactor> {
+    REDACTOR.get()
+}
+
+/// Replace occurrences of `segment` that form whole path segments
+fn replace_segment(text: &str, segment: &str, placeholder: &str) -> String {
+    let mut redacted = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(index) = rest.find(segment) {
+        let (before, after) = (&rest[..index], &rest[index + segment.len()..]);
+        let whole = before.ends_with('/') && after.chars().next().map_or(true, ends_segment);
+        redacted.push_str(before);
+        redacted.push_str(if whole { placeholder } else { segment });
+        rest = after;
+    }
+    redacted.push_str(rest);
+    redacted
+}
+
+/// Whether `c` can end a path segment embedded in report text
+fn ends_segment(c: char) -> bool {
+    matches!(c, '/' | '"' | '\'' | '`' | ',' | ':' | ')' | ']') || c.is_whitespace()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        let redactor = PathRedactor::from_config(
+            "redact_segments = [\"Acme\", \"/secret-project/\"]",
+            "/Users/alice",
+        )
+        .unwrap();
+        assert_eq!(redactor.segments(), ["Acme", "secret-project"]);
+        assert_eq!(
+            redactor.redact(r#"{"path": "/Users/alice/Clients/Acme/secret-project"}"#),
+            r#"{"path": "/Users/<user>/Clients/<redacted>/<redacted>"}"#
+        );
+        // Partial matches are left alone
+        assert_eq!(
+            redactor.redact("/Users/alice/alice-notes.txt /tmp/Acmeish"),
+            "/Users/<user>/alice-notes.txt /tmp/Acmeish"
+        );
+        assert_eq!(
+            PathRedactor::default().redact("/Users/alice"),
+            "/Users/alice"
+        );
+        assert!(PathRedactor::from_config("redact_segments = [Acme]", "/Users/alice").is_err());
+    }
+}
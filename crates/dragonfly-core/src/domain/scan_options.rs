@@ -0,0 +1,195 @@
+//! Scan options - Behavior flags shared by every filesystem walker
+//!
+//! The disk analyzer, duplicate detector, and cleaner each walk the
+//! filesystem. They all take a [`ScanOptions`] so that flags like excludes or
+//! staying on one filesystem mean the same thing everywhere.
+
+use serde::{Deserialize, Serialize};
+
+/// Options controlling how a directory tree is walked
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanOptions {
+    /// Patterns for paths to leave out (see [`ScanOptions::is_excluded`])
+    pub excludes: Vec<String>,
+    /// Follow symbolic links to directories
+    pub follow_symlinks: bool,
+    /// Don't cross into other mounted filesystems
+    pub one_filesystem: bool,
+    /// Maximum depth below the root (the root itself is depth 0)
+    pub max_depth: Option<usize>,
+    /// Include hidden (dot) files and directories
+    pub include_hidden: bool,
+    /// Worker threads for the walk (`None` uses the shared default pool)
+    pub threads: Option<usize>,
+}
+
+impl ScanOptions {
+    /// Create options with default behavior
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an exclude pattern
+    #[must_use]
+    pub fn exclude(mut self, pattern: impl Into<String>) -> Self {
+        self.excludes.push(pattern.into());
+        self
+    }
+
+    /// Add several exclude patterns
+    #[must_use]
+    pub fn with_excludes<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.excludes.extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Set whether symbolic links are followed
+    #[must_use]
+    pub fn with_follow_symlinks(mut self, follow: bool) -> Self {
+        self.follow_symlinks = follow;
+        self
+    }
+
+    /// Set whether the walk stays on the root's filesystem
+    #[must_use]
+    pub fn with_one_filesystem(mut self, one_filesystem: bool) -> Self {
+        self.one_filesystem = one_filesystem;
+        self
+    }
+
+    /// Limit how deep the walk descends
+    #[must_use]
+    pub fn with_max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Set whether hidden files are included
+    #[must_use]
+    pub fn with_hidden(mut self, include_hidden: bool) -> Self {
+        self.include_hidden = include_hidden;
+        self
+    }
+
+    /// Set the number of worker threads
+    #[must_use]
+    pub fn with_threads(mut self, threads: Option<usize>) -> Self {
+        self.threads = threads.filter(|&n| n > 0);
+        self
+    }
+
+    /// Check whether a path matches any exclude pattern
+    ///
+    /// Patterns support `*` (any run of characters) and `?` (one character).
+    /// A pattern without a `/` matches any single path component, so `node_modules`
+    /// or `*.tmp` apply at every level. A pattern containing `/` is matched
+    /// against the whole path and also excludes everything beneath a match.
+    #[must_use]
+    pub fn is_excluded(&self, path: &str) -> bool {
+        self.excludes
+            .iter()
+            .any(|pattern| matches_pattern(pattern, path))
+    }
+}
+
+/// Check whether `path` matches an exclude-style `pattern`
+///
+/// Follows the rules of [`ScanOptions::is_excluded`], for other lists of
+/// path patterns.
+#[must_use]
+pub fn matches_pattern(pattern: &str, path: &str) -> bool {
+    if pattern.contains('/') {
+        let pattern = pattern.trim_end_matches('/');
+        let mut prefix_end = path.len();
+        loop {
+            if wildcard_match(pattern, &path[..prefix_end]) {
+                return true;
+            }
+            match path[..prefix_end].rfind('/') {
+                Some(index) if index > 0 => prefix_end = index,
+                _ => return false,
+            }
+        }
+    } else {
+        path.split('/')
+            .any(|component| wildcard_match(pattern, component))
+    }
+}
+
+/// Match `text` against a pattern with `*` and `?` wildcards
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("*.tmp", "a.tmp", true)]
+    #[case("*.tmp", "a.tmp.bak", false)]
+    #[case("cache?", "cache1", true)]
+    #[case("cache?", "cache", false)]
+    #[case("*", "", true)]
+    #[case("a*b*c", "axxbyyc", true)]
+    #[case("a*b*c", "axxbyy", false)]
+    fn test_wildcard_match(#[case] pattern: &str, #[case] text: &str, #[case] expected: bool) {
+        assert_eq!(wildcard_match(pattern, text), expected);
+    }
+
+    #[test]
+    fn test_component_patterns_match_at_any_level() {
+        let options = ScanOptions::new().exclude("node_modules").exclude("*.log");
+        assert!(options.is_excluded("/Users/me/project/node_modules"));
+        assert!(options.is_excluded("/Users/me/project/node_modules/pkg/index.js"));
+        assert!(options.is_excluded("/var/log/system.log"));
+        assert!(!options.is_excluded("/Users/me/project/src/main.rs"));
+    }
+
+    #[test]
+    fn test_path_patterns_exclude_subtrees() {
+        let options = ScanOptions::new().with_excludes(["/Users/*/Library/Caches/"]);
+        assert!(options.is_excluded("/Users/me/Library/Caches"));
+        assert!(options.is_excluded("/Users/me/Library/Caches/com.example/data"));
+        assert!(!options.is_excluded("/Users/me/Library/Logs"));
+    }
+
+    #[test]
+    fn test_defaults() {
+        let options = ScanOptions::new();
+        assert!(!options.is_excluded("/anything"));
+        assert!(!options.follow_symlinks);
+        assert!(!options.include_hidden);
+        assert_eq!(options.max_depth, None);
+        assert_eq!(ScanOptions::new().with_threads(Some(0)).threads, None);
+    }
+}
@@ -0,0 +1,130 @@
+//! Scan profile - Where a filesystem scan spent its time
+//!
+//! Scans run on many threads at once, so phase times are summed over every
+//! thread that worked on the phase. On a busy scan the phases together can
+//! exceed the wall-clock `elapsed` time; compare them with each other rather
+//! than with `elapsed`.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Time spent per phase of a scan, in microseconds, and what it processed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanProfile {
+    /// Wall-clock duration of the whole scan
+    pub elapsed_micros: u64,
+    /// Waiting for directory entries from the walk
+    pub walk_micros: u64,
+    /// Reading file metadata (`stat`)
+    pub stat_micros: u64,
+    /// Hashing file contents (0 for scans that don't hash)
+    pub hash_micros: u64,
+    /// Building results from the files found
+    pub aggregate_micros: u64,
+    /// Files processed
+    pub files: u64,
+    /// Combined size of those files in bytes
+    pub bytes: u64,
+    /// Bytes read for hashing
+    pub hashed_bytes: u64,
+}
+
+impl ScanProfile {
+    /// Files processed per second of wall-clock time
+    #[must_use]
+    pub fn files_per_second(&self) -> f64 {
+        per_second(self.files, self.elapsed_micros)
+    }
+
+    /// Megabytes (10^6 bytes) of files processed per second of wall-clock time
+    #[must_use]
+    pub fn megabytes_per_second(&self) -> f64 {
+        per_second(self.bytes, self.elapsed_micros) / 1_000_000.0
+    }
+
+    /// Megabytes hashed per second of hashing time
+    #[must_use]
+    pub fn hash_megabytes_per_second(&self) -> f64 {
+        per_second(self.hashed_bytes, self.hash_micros) / 1_000_000.0
+    }
+}
+
+#[allow(clippy::cast_precision_loss)] // Rates don't need every bit
+fn per_second(count: u64, micros: u64) -> f64 {
+    if micros == 0 {
+        0.0
+    } else {
+        count as f64 * 1_000_000.0 / micros as f64
+    }
+}
+
+/// Accumulates the time spent in one phase, from any number of threads
+#[derive(Debug, Default)]
+pub struct PhaseTimer {
+    nanos: AtomicU64,
+}
+
+impl PhaseTimer {
+    /// Create a timer with nothing recorded
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `f`, adding the time it takes to the phase
+    pub fn time<T>(&self, f: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let result = f();
+        self.add(started.elapsed());
+        result
+    }
+
+    /// Add `duration` to the phase
+    pub fn add(&self, duration: Duration) {
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        self.nanos.fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    /// Time recorded so far, in microseconds
+    #[must_use]
+    pub fn micros(&self) -> u64 {
+        self.nanos.load(Ordering::Relaxed) / 1_000
+    }
+}
+
+/// Microseconds since `started`
+#[must_use]
+pub fn micros_since(started: Instant) -> u64 {
+    u64::try_from(started.elapsed().as_micros()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throughput() {
+        let profile = ScanProfile {
+            elapsed_micros: 2_000_000,
+            hash_micros: 500_000,
+            files: 1_000,
+            bytes: 8_000_000,
+            hashed_bytes: 4_000_000,
+            ..ScanProfile::default()
+        };
+        assert!((profile.files_per_second() - 500.0).abs() < f64::EPSILON);
+        assert!((profile.megabytes_per_second() - 4.0).abs() < f64::EPSILON);
+        assert!((profile.hash_megabytes_per_second() - 8.0).abs() < f64::EPSILON);
+        assert!(ScanProfile::default().files_per_second().abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_phase_timer_accumulates() {
+        let timer = PhaseTimer::new();
+        timer.add(Duration::from_micros(1_500));
+        assert_eq!(timer.time(|| 42), 42);
+        timer.add(Duration::from_micros(500));
+        assert!(timer.micros() >= 2_000);
+    }
+}
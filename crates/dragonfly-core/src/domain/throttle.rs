@@ -0,0 +1,81 @@
+//! Throttle - A process-wide pace for filesystem walks
+//!
+//! With `--throttle`, every walker reports the directory entries it reads
+//! here and sleeps whenever the process gets ahead of the configured rate, so
+//! a background scan leaves the disk to interactive work. The pace is shared
+//! by all walks in the process: scanning several volumes at once doesn't
+//! multiply it.
+
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::time::{Duration, Instant};
+
+static THROTTLE: OnceLock<Throttle> = OnceLock::new();
+
+/// Limits how many filesystem entries are read per second
+#[derive(Debug)]
+pub struct Throttle {
+    /// Time one entry is allowed to take
+    interval: Duration,
+    /// When the next entry may be read
+    next: Mutex<Option<Instant>>,
+}
+
+impl Throttle {
+    /// Throttle to `entries_per_second` (at least one)
+    #[must_use]
+    pub fn new(entries_per_second: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / entries_per_second.max(1),
+            next: Mutex::new(None),
+        }
+    }
+
+    /// Account for `entries` just read, sleeping if the process is ahead of
+    /// the rate
+    pub fn pace(&self, entries: usize) {
+        let wait = self.reserve(entries, Instant::now());
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+    }
+
+    /// Book `entries` at the earliest free time after `now` and return how
+    /// long the caller has to wait for it
+    fn reserve(&self, entries: usize, now: Instant) -> Duration {
+        let mut next = self.next.lock().unwrap_or_else(PoisonError::into_inner);
+        let start = next.map_or(now, |next| next.max(now));
+        let cost = self
+            .interval
+            .saturating_mul(u32::try_from(entries).unwrap_or(u32::MAX));
+        *next = Some(start + cost);
+        start - now
+    }
+}
+
+/// Install the process-wide throttle; later calls are ignored
+pub fn install(throttle: Throttle) {
+    let _ = THROTTLE.set(throttle);
+}
+
+/// The process-wide throttle, if throttling was turned on
+pub fn throttle() -> Option<&'static Throttle> {
+    THROTTLE.get()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserve_spaces_out_entries() {
+        let throttle = Throttle::new(100);
+        let now = Instant::now();
+        // The first batch goes right away and books 50 entries' worth of time
+        assert_eq!(throttle.reserve(50, now), Duration::ZERO);
+        assert_eq!(throttle.reserve(10, now), Duration::from_millis(500));
+        assert_eq!(throttle.reserve(1, now), Duration::from_millis(600));
+        // Time spent elsewhere counts towards the wait
+        let later = now + Duration::from_secs(2);
+        assert_eq!(throttle.reserve(1, later), Duration::ZERO);
+    }
+}
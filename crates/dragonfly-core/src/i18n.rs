@@ -0,0 +1,248 @@
+//! Message catalogs for user-facing text
+//!
+//! Every message shown to users has a key, such as `clean.title`, and its
+//! text comes from a [`Catalog`]. English is built in and is the fallback for
+//! every key, so a translation can cover as much or as little as it likes.
+//!
+//! Catalogs are plain text, one message per line:
+//!
+//! ```text
+//! # Comments start with `#`
+//! clean.title = Cache Cleaner
+//! clean.found = Found {count} files
+//! ```
+//!
+//! `{name}` placeholders are filled in by [`Catalog::format`] and `\n` starts
+//! a new line. Finding and reading catalog files is left to the frontends;
+//! this module only parses and looks up.
+//!
+//! The frontends install one catalog per process with [`install`], after which
+//! [`t!`](crate::t) returns text in the selected language.
+
+use crate::error::{Error, Result};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+/// Locale every other one falls back to
+pub const FALLBACK_LOCALE: &str = "en";
+
+/// English messages, compiled in
+const ENGLISH: &str = include_str!("../locales/en.catalog");
+
+/// Catalogs shipped with `DragonFly`, by locale
+const BUILT_IN: &[(&str, &str)] = &[(FALLBACK_LOCALE, ENGLISH)];
+
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+/// Messages for one locale
+#[derive(Debug, Clone)]
+pub struct Catalog {
+    locale: String,
+    messages: HashMap<String, String>,
+}
+
+impl Catalog {
+    /// The built-in English catalog
+    ///
+    /// # Panics
+    ///
+    /// Never in practice: the built-in catalog is checked by the tests.
+    #[must_use]
+    pub fn english() -> Self {
+        Self {
+            locale: FALLBACK_LOCALE.to_string(),
+            messages: parse(ENGLISH).expect("built-in English catalog is valid"),
+        }
+    }
+
+    /// English overlaid with the built-in translations for `locale`
+    ///
+    /// A regional locale such as `pt_BR` picks up the language's catalog
+    /// (`pt`) first and then the regional one, so each only needs to hold
+    /// what differs.
+    #[must_use]
+    pub fn built_in(locale: &str) -> Self {
+        let mut catalog = Self::english();
+        catalog.locale = locale.to_string();
+        for candidate in fallback_chain(locale) {
+            if let Some((_, source)) = BUILT_IN
+                .iter()
+                .find(|(name, _)| *name == candidate && *name != FALLBACK_LOCALE)
+            {
+                // Built-in catalogs are checked by the tests
+                let _ = catalog.merge(source);
+            }
+        }
+        catalog
+    }
+
+    /// Overlay the messages in `source`, replacing ones with the same key
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidInput`] naming the first malformed line;
+    /// nothing is merged in that case.
+    pub fn merge(&mut self, source: &str) -> Result<()> {
+        self.messages.extend(parse(source)?);
+        Ok(())
+    }
+
+    /// Locale the catalog was built for
+    #[must_use]
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    /// Text of `key`, or the key itself if no catalog has it
+    #[must_use]
+    pub fn message<'a>(&'a self, key: &'a str) -> &'a str {
+        self.messages.get(key).map_or(key, String::as_str)
+    }
+
+    /// Text of `key` with each `{name}` placeholder replaced by its value
+    #[must_use]
+    pub fn format(&self, key: &str, args: &[(&str, &dyn Display)]) -> String {
+        let mut text = self.message(key).to_string();
+        for (name, value) in args {
+            text = text.replace(&format!("{{{name}}}"), &value.to_string());
+        }
+        text
+    }
+}
+
+impl Default for Catalog {
+    fn default() -> Self {
+        Self::english()
+    }
+}
+
+/// Install the process-wide catalog used by [`t!`](crate::t)
+///
+/// Only the first call has an effect; until then English is used.
+pub fn install(catalog: Catalog) {
+    let _ = CATALOG.set(catalog);
+}
+
+/// The process-wide catalog
+pub fn catalog() -> &'static Catalog {
+    CATALOG.get_or_init(Catalog::english)
+}
+
+/// Canonical form of a locale setting such as `de_DE.UTF-8` (`de_DE`)
+///
+/// Returns `None` for empty values and for `C` and `POSIX`, which select no
+/// language in particular.
+#[must_use]
+pub fn normalize_locale(value: &str) -> Option<String> {
+    let name = value
+        .split(['.', '@'])
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .replace('-', "_");
+    if name.is_empty() || name == "C" || name == "POSIX" {
+        return None;
+    }
+    Some(name)
+}
+
+/// Locales to try for `locale`, most general first
+///
+/// `pt_BR` yields `pt` then `pt_BR`.
+#[must_use]
+pub fn fallback_chain(locale: &str) -> Vec<String> {
+    let language = locale.split('_').next().unwrap_or(locale);
+    let mut chain = vec![language.to_string()];
+    if language != locale {
+        chain.push(locale.to_string());
+    }
+    chain
+}
+
+/// Parse catalog source into key-message pairs
+fn parse(source: &str) -> Result<HashMap<String, String>> {
+    let mut messages = HashMap::new();
+    for (number, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, text)) = line.split_once('=') else {
+            return Err(Error::InvalidInput(format!(
+                "line {}: expected `key = message`",
+                number + 1
+            )));
+        };
+        let key = key.trim();
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            return Err(Error::InvalidInput(format!(
+                "line {}: invalid message key `{key}`",
+                number + 1
+            )));
+        }
+        messages.insert(key.to_string(), text.trim().replace("\\n", "\n"));
+    }
+    Ok(messages)
+}
+
+/// Look up a message in the installed catalog
+///
+/// `t!("clean.title")` returns the text as `&'static str`;
+/// `t!("clean.found", count = n)` fills in placeholders and returns a `String`.
+#[macro_export]
+macro_rules! t {
+    ($key:expr) => {
+        $crate::i18n::catalog().message($key)
+    };
+    ($key:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::catalog().format(
+            $key,
+            &[$((stringify!($name), &$value as &dyn ::std::fmt::Display)),+],
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_built_in_catalogs_parse() {
+        for (locale, source) in BUILT_IN {
+            assert!(parse(source).is_ok(), "catalog {locale} is malformed");
+        }
+        assert_eq!(Catalog::english().message("clean.title"), "Cache Cleaner");
+    }
+
+    #[test]
+    fn test_merge_overrides_and_falls_back() {
+        let mut catalog = Catalog::built_in("de_DE");
+        catalog
+            .merge("# German\nclean.title = Cache-Bereinigung\nclean.found = {count} Dateien gefunden\n")
+            .unwrap();
+
+        assert_eq!(catalog.locale(), "de_DE");
+        assert_eq!(catalog.message("clean.title"), "Cache-Bereinigung");
+        assert_eq!(
+            catalog.format("clean.found", &[("count", &3)]),
+            "3 Dateien gefunden"
+        );
+        // Untranslated keys fall back to English, unknown keys to the key
+        assert_eq!(catalog.message("clean.freed"), "Freed: {size}");
+        assert_eq!(catalog.message("no.such.key"), "no.such.key");
+
+        assert!(catalog.merge("clean.title Cache").is_err());
+        assert_eq!(catalog.message("clean.title"), "Cache-Bereinigung");
+    }
+
+    #[test]
+    fn test_normalize_locale() {
+        assert_eq!(normalize_locale("de_DE.UTF-8"), Some("de_DE".to_string()));
+        assert_eq!(normalize_locale("pt-BR"), Some("pt_BR".to_string()));
+        assert_eq!(normalize_locale("C"), None);
+        assert_eq!(normalize_locale(""), None);
+        assert_eq!(fallback_chain("pt_BR"), vec!["pt", "pt_BR"]);
+        assert_eq!(fallback_chain("fr"), vec!["fr"]);
+    }
+}
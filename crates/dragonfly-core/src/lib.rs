@@ -63,10 +63,7 @@
 //! let path = FilePath::new("/Users/me/large_file.dat".to_string());
 //!
 //! // Create domain entity
-//! let file = FileEntity {
-//!     path: path.as_str().to_string(),
-//!     size: size.bytes(),
-//! };
+//! let file = FileEntity::new(path.as_str().to_string(), size.bytes());
 //!
 //! // Use value objects
 //! assert_eq!(size.bytes(), 104_857_600);
@@ -79,6 +76,7 @@
 //! - [`ports`]: Port traits (interfaces) for dependency inversion
 //! - [`use_cases`]: Business use cases and application logic
 //! - [`error`]: Domain-specific error types
+//! - [`i18n`]: Message catalogs for user-facing text
 //!
 //! ## Testing Philosophy
 //!
@@ -132,6 +130,12 @@ pub mod domain;
 /// Errors are strongly typed and provide context for debugging.
 pub mod error;
 
+/// Message catalogs for user-facing text
+///
+/// Frontends look up their text here by key, so output can be translated
+/// without touching the code that prints it.
+pub mod i18n;
+
 /// Port traits (interfaces) for dependency inversion
 ///
 /// Ports define the boundaries of the application. External systems
@@ -154,6 +158,8 @@ pub use error::{Error, Result};
 // Re-export domain types
 pub use domain::{
     entities::{DirectoryEntity, FileEntity, HealthStatus, SystemSnapshot},
+    protection::{PathProtection, SkipReason, SkippedPath},
+    scan_options::ScanOptions,
     value_objects::{FilePath, FileSize, Percentage},
     DomainEvent,
 };
@@ -0,0 +1,53 @@
+//! Filesystem change notifications
+
+use crate::error::Result;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// A change somewhere in a watched tree
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FsChange {
+    /// File or directory that changed
+    pub path: PathBuf,
+    /// Whether everything beneath `path` may have changed
+    ///
+    /// Set when the watcher can't say exactly what changed, for example after
+    /// dropping events under load. Consumers should rescan the whole subtree.
+    pub subtree: bool,
+}
+
+impl FsChange {
+    /// A change to a single file or directory entry
+    #[must_use]
+    pub fn path(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            subtree: false,
+        }
+    }
+
+    /// A change anywhere beneath `path`
+    #[must_use]
+    pub fn subtree(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            subtree: true,
+        }
+    }
+}
+
+/// Source of filesystem change notifications (Driven Port)
+///
+/// Adapters watch a set of roots chosen when they are created, e.g. through
+/// `FSEvents` on macOS, and hand changes out in batches. Changes may be
+/// coalesced, so a consumer should re-read the current state of each changed
+/// path rather than assume what happened to it.
+pub trait FileSystemWatcher: Send {
+    /// Wait up to `timeout` for changes, returning an empty batch if none came
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the watcher has stopped and can deliver no more
+    /// changes.
+    fn next_changes(&mut self, timeout: Duration) -> Result<Vec<FsChange>>;
+}
@@ -5,6 +5,19 @@
 //!
 //! - **Driving Ports** (Primary): Called by external actors to drive the application
 //! - **Driven Ports** (Secondary): Called by the application to interact with external systems
+//!
+//! Ports that walk the filesystem take a [`ProgressReporter`] and a
+//! [`CancellationToken`], so every adapter observes and stops long operations
+//! the same way. A [`FileSystemWatcher`] reports changes to watched trees so
+//! that results can be kept up to date without walking again.
+
+mod cancellation;
+mod fs_watch;
+mod progress;
+
+pub use cancellation::CancellationToken;
+pub use fs_watch::{FileSystemWatcher, FsChange};
+pub use progress::{NoProgress, ProgressReporter, ProgressUpdate};
 
 use crate::domain::entities::{DirectoryEntity, FileEntity, SystemSnapshot};
 use crate::domain::value_objects::FilePath;
@@ -19,7 +32,15 @@ use std::collections::HashMap;
 #[async_trait]
 pub trait FileRepository: Send + Sync {
     /// Scan a directory and return all files
-    async fn scan_directory(&self, path: &FilePath) -> Result<Vec<FileEntity>>;
+    ///
+    /// Reports progress as files are found and returns the files seen so far
+    /// once `cancel` fires.
+    async fn scan_directory(
+        &self,
+        path: &FilePath,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<FileEntity>>;
 
     /// Get file metadata
     async fn get_file_metadata(&self, path: &FilePath) -> Result<FileEntity>;
@@ -44,10 +65,20 @@ pub trait FileRepository: Send + Sync {
 #[async_trait]
 pub trait DirectoryRepository: Send + Sync {
     /// Analyze a directory and return summary
-    async fn analyze_directory(&self, path: &FilePath) -> Result<DirectoryEntity>;
+    async fn analyze_directory(
+        &self,
+        path: &FilePath,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> Result<DirectoryEntity>;
 
     /// Get directory size recursively
-    async fn get_directory_size(&self, path: &FilePath) -> Result<u64>;
+    async fn get_directory_size(
+        &self,
+        path: &FilePath,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> Result<u64>;
 
     /// Delete a directory and all contents
     async fn delete_directory(&self, path: &FilePath) -> Result<()>;
@@ -170,18 +201,34 @@ pub trait ConfigService: Send + Sync {
 #[async_trait]
 pub trait AnalyzeDiskUseCase: Send + Sync {
     /// Analyze disk usage in a directory
-    async fn analyze(&self, path: &FilePath) -> Result<DirectoryEntity>;
+    async fn analyze(
+        &self,
+        path: &FilePath,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> Result<DirectoryEntity>;
 
     /// Find large files in a directory
-    async fn find_large_files(&self, path: &FilePath, min_size: u64) -> Result<Vec<FileEntity>>;
+    async fn find_large_files(
+        &self,
+        path: &FilePath,
+        min_size: u64,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<FileEntity>>;
 }
 
 /// Use case for duplicate detection (Driving Port)
 #[async_trait]
 pub trait FindDuplicatesUseCase: Send + Sync {
     /// Find duplicate files in a directory
-    async fn find_duplicates(&self, path: &FilePath, min_size: u64)
-        -> Result<Vec<Vec<FileEntity>>>;
+    async fn find_duplicates(
+        &self,
+        path: &FilePath,
+        min_size: u64,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<Vec<FileEntity>>>;
 
     /// Calculate potential space savings from duplicates
     async fn calculate_savings(&self, duplicates: &[Vec<FileEntity>]) -> Result<u64>;
@@ -236,4 +283,67 @@ mod tests {
         // Actual implementation tests will be in adapter tests
         assert!(std::any::type_name::<dyn FileRepository>().contains("FileRepository"));
     }
+
+    /// Directory repository over a fixed list of file sizes
+    struct FixedDirectory(Vec<u64>);
+
+    #[async_trait]
+    impl DirectoryRepository for FixedDirectory {
+        async fn analyze_directory(
+            &self,
+            path: &FilePath,
+            _progress: &dyn ProgressReporter,
+            _cancel: &CancellationToken,
+        ) -> Result<DirectoryEntity> {
+            Ok(DirectoryEntity {
+                path: path.as_str().to_string(),
+            })
+        }
+
+        async fn get_directory_size(
+            &self,
+            _path: &FilePath,
+            progress: &dyn ProgressReporter,
+            cancel: &CancellationToken,
+        ) -> Result<u64> {
+            let mut total = 0;
+            for (index, size) in (1u64..).zip(&self.0) {
+                if cancel.is_cancelled() {
+                    break;
+                }
+                total += size;
+                progress.report(&ProgressUpdate::new(index, total));
+                if index == 2 {
+                    cancel.cancel();
+                }
+            }
+            Ok(total)
+        }
+
+        async fn delete_directory(&self, _path: &FilePath) -> Result<()> {
+            Ok(())
+        }
+
+        async fn list_directories(&self, _path: &FilePath) -> Result<Vec<FilePath>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_driven_port_reports_progress_and_honors_cancellation() {
+        let repository = FixedDirectory(vec![10, 20, 30, 40]);
+        let updates = std::sync::Mutex::new(Vec::new());
+        let reporter = |update: &ProgressUpdate| updates.lock().unwrap().push(update.clone());
+        let cancel = CancellationToken::new();
+
+        let size = repository
+            .get_directory_size(&FilePath::new("/data".to_string()), &reporter, &cancel)
+            .await
+            .unwrap();
+
+        assert_eq!(size, 30);
+        let updates = updates.into_inner().unwrap();
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[1].bytes_processed, 30);
+    }
 }
@@ -0,0 +1,141 @@
+//! Progress reporting for long-running operations
+
+/// Snapshot of how far a long-running operation has got
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProgressUpdate {
+    /// Items (files, directories, hashes...) processed so far
+    pub items_processed: u64,
+    /// Total items, when known up front
+    pub items_total: Option<u64>,
+    /// Bytes processed so far
+    pub bytes_processed: u64,
+    /// Total bytes, when known up front
+    pub bytes_total: Option<u64>,
+    /// Item currently being processed, if any
+    pub current_path: Option<String>,
+    /// Stage of a multi-stage operation, if it has several
+    pub phase: Option<&'static str>,
+}
+
+impl ProgressUpdate {
+    /// Create an update for the given counts
+    #[must_use]
+    pub fn new(items_processed: u64, bytes_processed: u64) -> Self {
+        Self {
+            items_processed,
+            bytes_processed,
+            ..Self::default()
+        }
+    }
+
+    /// Set the total number of items
+    #[must_use]
+    pub fn with_total(mut self, items_total: u64) -> Self {
+        self.items_total = Some(items_total);
+        self
+    }
+
+    /// Set the total number of bytes
+    #[must_use]
+    pub fn with_bytes_total(mut self, bytes_total: u64) -> Self {
+        self.bytes_total = Some(bytes_total);
+        self
+    }
+
+    /// Set the stage the operation is in
+    #[must_use]
+    pub fn with_phase(mut self, phase: &'static str) -> Self {
+        self.phase = Some(phase);
+        self
+    }
+
+    /// Set the item currently being processed
+    #[must_use]
+    pub fn with_current_path(mut self, path: impl Into<String>) -> Self {
+        self.current_path = Some(path.into());
+        self
+    }
+
+    /// Completed fraction between 0 and 1, when the total is known
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn fraction(&self) -> Option<f64> {
+        self.items_total.map(|total| {
+            if total == 0 {
+                1.0
+            } else {
+                (self.items_processed as f64 / total as f64).min(1.0)
+            }
+        })
+    }
+}
+
+/// Observer of progress updates (Driven Port)
+///
+/// Operations call [`ProgressReporter::report`] periodically from whichever
+/// thread is doing the work, so implementations must be cheap and thread-safe.
+/// Adapters render the updates however suits them: a CLI progress bar, a TUI
+/// gauge, or events pushed to an API client.
+pub trait ProgressReporter: Send + Sync {
+    /// Receive a progress update
+    fn report(&self, update: &ProgressUpdate);
+}
+
+/// Reporter that discards every update
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoProgress;
+
+impl ProgressReporter for NoProgress {
+    fn report(&self, _update: &ProgressUpdate) {}
+}
+
+impl<F> ProgressReporter for F
+where
+    F: Fn(&ProgressUpdate) + Send + Sync,
+{
+    fn report(&self, update: &ProgressUpdate) {
+        self(update);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_closures_are_reporters() {
+        let seen = Mutex::new(Vec::new());
+        let reporter = |update: &ProgressUpdate| seen.lock().unwrap().push(update.clone());
+
+        reporter.report(&ProgressUpdate::new(1, 10).with_current_path("/tmp/a"));
+        NoProgress.report(&ProgressUpdate::new(2, 20));
+
+        let seen = seen.into_inner().unwrap();
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].current_path.as_deref(), Some("/tmp/a"));
+    }
+
+    #[test]
+    fn test_fraction() {
+        assert_eq!(ProgressUpdate::new(5, 0).fraction(), None);
+        assert_eq!(
+            ProgressUpdate::new(5, 0).with_total(10).fraction(),
+            Some(0.5)
+        );
+        assert_eq!(
+            ProgressUpdate::new(0, 0).with_total(0).fraction(),
+            Some(1.0)
+        );
+    }
+
+    #[test]
+    fn test_phase_and_byte_total() {
+        let update = ProgressUpdate::new(1, 512)
+            .with_bytes_total(1024)
+            .with_phase("hash");
+        assert_eq!(update.bytes_total, Some(1024));
+        assert_eq!(update.phase, Some("hash"));
+        assert_eq!(update.fraction(), None);
+    }
+}
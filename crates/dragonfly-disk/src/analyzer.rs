@@ -1,11 +1,34 @@
 //! Disk analysis orchestration
 
+use crate::checkpoint::{self, ScanCheckpoint, CHECKPOINT_INTERVAL};
+use crate::file_types::{self, CategoryUsage};
+use crate::owners::{self, OwnerUsage};
+use crate::packages;
+use crate::sampling::{self, QuickEstimate};
+use crate::spotlight;
+use crate::stale::{self, StaleFilesReport};
+use crate::strategies::AnalysisStrategy;
+use crate::tree::{DirectoryNode, DirectoryTree};
+use crate::walk::{
+    allocated_size, inode_links, owner_uid, skipped_from_walk_error, walker, PROGRESS_INTERVAL,
+};
+use chrono::DateTime;
 use dragonfly_core::domain::entities::FileEntity;
+use dragonfly_core::domain::protection::SkippedPath;
+use dragonfly_core::domain::scan_options::ScanOptions;
+use dragonfly_core::domain::scan_profile::{micros_since, PhaseTimer, ScanProfile};
 use dragonfly_core::domain::value_objects::FilePath;
 use dragonfly_core::error::Result;
-use jwalk::WalkDir;
+use dragonfly_core::ports::{CancellationToken, NoProgress, ProgressReporter, ProgressUpdate};
 use rayon::prelude::*;
-use std::path::Path;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Most roots [`DiskAnalyzer::analyze_roots`] walks at the same time
+pub const MAX_CONCURRENT_ROOTS: usize = 4;
 
 /// Disk analyzer orchestrates disk analysis operations
 #[derive(Debug, Clone, Copy)]
@@ -16,8 +39,82 @@ pub struct DiskAnalyzer;
 pub struct AnalysisResult {
     /// Total size in bytes
     pub total_size: u64,
+    /// Space the files occupy on disk in bytes, when the strategy can tell
+    pub allocated_size: Option<u64>,
     /// Files found
     pub files: Vec<FileEntity>,
+    /// Paths the walk couldn't read; their sizes are missing from the totals
+    pub skipped: Vec<SkippedPath>,
+    /// Whether the scan was cancelled before completing (results are partial)
+    pub interrupted: bool,
+    /// Strategy that produced the result
+    pub strategy: AnalysisStrategy,
+    /// Where the scan spent its time
+    pub profile: ScanProfile,
+}
+
+impl AnalysisResult {
+    /// File count and total size per kind of file (video, images, caches...),
+    /// largest first
+    pub fn by_category(&self) -> Vec<CategoryUsage> {
+        file_types::by_category(&self.files)
+    }
+
+    /// File count and total size per owning user, largest first
+    pub fn by_owner(&self) -> Vec<OwnerUsage> {
+        owners::by_owner(&self.files)
+    }
+
+    /// Report each package below `root` (an `.app`, `.photoslibrary`,
+    /// `.framework`...) as one entry instead of the files inside it
+    pub fn collapse_packages(&mut self, root: &Path) {
+        let files = std::mem::take(&mut self.files);
+        self.files = packages::collapse(root, files);
+    }
+}
+
+/// Running totals of an analysis in progress
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AnalysisTotals {
+    /// Files seen so far
+    pub file_count: u64,
+    /// Combined size of those files in bytes
+    pub total_size: u64,
+    /// Space those files occupy on disk in bytes (their logical size where
+    /// the platform can't tell)
+    pub allocated_size: u64,
+}
+
+/// Subtotal of one root of a multi-root analysis
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RootTotals {
+    /// Root as given
+    pub root: PathBuf,
+    /// Files and sizes found below it
+    pub totals: AnalysisTotals,
+}
+
+/// Merged result of analyzing several roots
+#[derive(Debug, Clone)]
+pub struct MultiRootAnalysis {
+    /// Files of every root, with the combined totals
+    pub result: AnalysisResult,
+    /// Subtotal of each root, in the order the roots were given
+    pub roots: Vec<RootTotals>,
+}
+
+/// Outcome of a streaming analysis; the files themselves went to the visitor
+#[derive(Debug, Clone, Default)]
+pub struct StreamSummary {
+    /// Totals over every file visited
+    pub totals: AnalysisTotals,
+    /// Paths the walk couldn't read, which the visitor never saw
+    pub skipped: Vec<SkippedPath>,
+    /// Whether the scan was cancelled before completing (totals are partial)
+    pub interrupted: bool,
+    /// Where the scan spent its time; `aggregate_micros` is time spent in the
+    /// visitor
+    pub profile: ScanProfile,
 }
 
 impl DiskAnalyzer {
@@ -28,6 +125,359 @@ impl DiskAnalyzer {
 
     /// Analyze a directory and return file sizes
     pub async fn analyze(&self, path: &FilePath) -> Result<AnalysisResult> {
+        self.analyze_with_options(path, &ScanOptions::default(), &CancellationToken::new())
+            .await
+    }
+
+    /// Analyze a directory with the given scan options
+    ///
+    /// Stops early with partial results if `cancel` fires. Every file is kept
+    /// in memory; use [`Self::analyze_streaming`] for very large trees.
+    pub async fn analyze_with_options(
+        &self,
+        path: &FilePath,
+        options: &ScanOptions,
+        cancel: &CancellationToken,
+    ) -> Result<AnalysisResult> {
+        self.analyze_with_progress(path, options, &NoProgress, cancel)
+            .await
+    }
+
+    /// Analyze a directory, telling `progress` how far the scan has got
+    ///
+    /// `progress` hears the files seen, bytes counted, and the latest file
+    /// every thousand files, from whichever walk thread found it, and once
+    /// more when the walk is done.
+    pub async fn analyze_with_progress(
+        &self,
+        path: &FilePath,
+        options: &ScanOptions,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> Result<AnalysisResult> {
+        let files = Mutex::new(Vec::new());
+        let summary = self
+            .analyze_streaming(path, options, cancel, |file, totals| {
+                if totals.file_count % PROGRESS_INTERVAL == 0 {
+                    progress.report(
+                        &ProgressUpdate::new(totals.file_count, totals.total_size)
+                            .with_current_path(file.path.as_str()),
+                    );
+                }
+                files.lock().unwrap().push(file);
+            })
+            .await?;
+        progress.report(&ProgressUpdate::new(
+            summary.totals.file_count,
+            summary.totals.total_size,
+        ));
+
+        Ok(AnalysisResult {
+            total_size: summary.totals.total_size,
+            allocated_size: Some(summary.totals.allocated_size),
+            files: files.into_inner().unwrap(),
+            skipped: summary.skipped,
+            interrupted: summary.interrupted,
+            strategy: AnalysisStrategy::Deep,
+            profile: summary.profile,
+        })
+    }
+
+    /// Analyze a directory in parts, writing the parts finished so far to
+    /// `checkpoint` every [`CHECKPOINT_INTERVAL`] and when `cancel` fires
+    ///
+    /// With `resume`, the parts it holds are taken from it instead of being
+    /// scanned again; it must be of the same directory scanned with the same
+    /// options. A checkpoint this scan wrote or resumed from is removed once
+    /// it completes. A file hard linked from two parts counts once in each.
+    pub async fn analyze_checkpointed(
+        &self,
+        path: &FilePath,
+        options: &ScanOptions,
+        checkpoint: &Path,
+        resume: Option<ScanCheckpoint>,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> Result<AnalysisResult> {
+        let base = Path::new(path.as_str());
+        if !base.exists() {
+            return Err(dragonfly_core::error::Error::NotFound(format!(
+                "Path does not exist: {}",
+                path.as_str()
+            )));
+        }
+        let root = std::fs::canonicalize(base)?;
+
+        let started = Instant::now();
+        // Only a checkpoint of this scan is removed when it completes
+        let mut owns_checkpoint = resume.is_some();
+        let (mut files, mut completed, mut skipped) = match resume {
+            Some(resumed) if resumed.covers(&root, options) => {
+                let files = resumed
+                    .files
+                    .into_iter()
+                    .map(|mut file| {
+                        file.path = base.join(&file.path).to_string_lossy().into_owned();
+                        file
+                    })
+                    .collect();
+                (files, resumed.completed, resumed.skipped)
+            }
+            Some(resumed) => {
+                return Err(dragonfly_core::error::Error::InvalidInput(format!(
+                    "Checkpoint is of a scan of {} with other options",
+                    resumed.root.display()
+                )));
+            }
+            None => (Vec::new(), BTreeSet::new(), Vec::new()),
+        };
+        let mut totals = AnalysisTotals {
+            file_count: files.len() as u64,
+            total_size: files.iter().map(|f: &FileEntity| f.size).sum(),
+            allocated_size: files.iter().map(FileEntity::size_on_disk).sum(),
+        };
+        let mut profile = ScanProfile::default();
+        let mut partial = Vec::new();
+        let mut last_saved = Instant::now();
+
+        for part in checkpoint::split(base, options) {
+            let key = part
+                .dir
+                .strip_prefix(base)
+                .unwrap_or(&part.dir)
+                .to_path_buf();
+            if completed.contains(&key) {
+                continue;
+            }
+            if cancel.is_cancelled() {
+                break;
+            }
+            // Gone since the tree was split
+            if !part.dir.exists() {
+                completed.insert(key);
+                continue;
+            }
+            let before = totals;
+            let found = Mutex::new(Vec::new());
+            let part_path = FilePath::new(part.dir.to_string_lossy().into_owned());
+            let summary = self
+                .analyze_streaming(
+                    &part_path,
+                    &part.walk_options(options),
+                    cancel,
+                    |file, part_totals| {
+                        if part_totals.file_count % PROGRESS_INTERVAL == 0 {
+                            progress.report(
+                                &ProgressUpdate::new(
+                                    before.file_count + part_totals.file_count,
+                                    before.total_size + part_totals.total_size,
+                                )
+                                .with_current_path(file.path.as_str()),
+                            );
+                        }
+                        found.lock().unwrap().push(file);
+                    },
+                )
+                .await?;
+            totals.file_count += summary.totals.file_count;
+            totals.total_size += summary.totals.total_size;
+            totals.allocated_size += summary.totals.allocated_size;
+            profile.walk_micros += summary.profile.walk_micros;
+            profile.stat_micros += summary.profile.stat_micros;
+            profile.aggregate_micros += summary.profile.aggregate_micros;
+
+            // A part cut short is scanned again in full when resuming
+            if summary.interrupted {
+                partial = found.into_inner().unwrap();
+                break;
+            }
+            files.extend(found.into_inner().unwrap());
+            skipped.extend(summary.skipped);
+            completed.insert(key);
+            if last_saved.elapsed() >= CHECKPOINT_INTERVAL {
+                ScanCheckpoint::capture(base, &root, options, &completed, &files, &skipped)
+                    .save(checkpoint)?;
+                owns_checkpoint = true;
+                last_saved = Instant::now();
+            }
+        }
+
+        let interrupted = cancel.is_cancelled();
+        if interrupted {
+            ScanCheckpoint::capture(base, &root, options, &completed, &files, &skipped)
+                .save(checkpoint)?;
+        } else if owns_checkpoint && checkpoint.exists() {
+            std::fs::remove_file(checkpoint)?;
+        }
+        files.extend(partial);
+        progress.report(&ProgressUpdate::new(totals.file_count, totals.total_size));
+
+        Ok(AnalysisResult {
+            total_size: totals.total_size,
+            allocated_size: Some(totals.allocated_size),
+            files,
+            skipped,
+            interrupted,
+            strategy: AnalysisStrategy::Deep,
+            profile: ScanProfile {
+                elapsed_micros: micros_since(started),
+                files: totals.file_count,
+                bytes: totals.total_size,
+                ..profile
+            },
+        })
+    }
+
+    /// Analyze several directories at once and merge what they hold
+    ///
+    /// Up to [`MAX_CONCURRENT_ROOTS`] roots are walked at the same time, each
+    /// on a thread of its own; the rest wait for a free one. `progress` hears
+    /// the combined count. Roots must not overlap, since the files of a root
+    /// inside another would count twice; a file hard linked into two roots
+    /// counts once in each.
+    pub async fn analyze_roots(
+        &self,
+        paths: &[FilePath],
+        options: &ScanOptions,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> Result<MultiRootAnalysis> {
+        let mut canonical = Vec::with_capacity(paths.len());
+        for path in paths {
+            if !Path::new(path.as_str()).exists() {
+                return Err(dragonfly_core::error::Error::NotFound(format!(
+                    "Path does not exist: {}",
+                    path.as_str()
+                )));
+            }
+            canonical.push(std::fs::canonicalize(path.as_str())?);
+        }
+        for (i, root) in canonical.iter().enumerate() {
+            if let Some(j) =
+                (0..canonical.len()).find(|&j| j != i && root.starts_with(&canonical[j]))
+            {
+                return Err(dragonfly_core::error::Error::InvalidInput(format!(
+                    "{} is inside {}; scan it once",
+                    paths[i].as_str(),
+                    paths[j].as_str()
+                )));
+            }
+        }
+
+        let started = Instant::now();
+        let next = AtomicUsize::new(0);
+        let seen_files = AtomicU64::new(0);
+        let seen_bytes = AtomicU64::new(0);
+        let files = Mutex::new(Vec::new());
+        let on_file = |file: FileEntity, _: AnalysisTotals| {
+            let count = seen_files.fetch_add(1, Ordering::Relaxed) + 1;
+            let bytes = seen_bytes.fetch_add(file.size, Ordering::Relaxed) + file.size;
+            if count % PROGRESS_INTERVAL == 0 {
+                progress.report(
+                    &ProgressUpdate::new(count, bytes).with_current_path(file.path.as_str()),
+                );
+            }
+            files.lock().unwrap().push(file);
+        };
+        let mut summaries: Vec<(usize, Result<StreamSummary>)> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..paths.len().min(MAX_CONCURRENT_ROOTS))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut done = Vec::new();
+                        loop {
+                            let index = next.fetch_add(1, Ordering::Relaxed);
+                            let Some(path) = paths.get(index) else {
+                                return done;
+                            };
+                            done.push((index, self.stream(path, options, cancel, on_file)));
+                        }
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| {
+                    worker
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+                })
+                .collect()
+        });
+        summaries.sort_by_key(|(index, _)| *index);
+
+        let mut totals = AnalysisTotals::default();
+        let mut skipped = Vec::new();
+        let mut profile = ScanProfile::default();
+        let mut roots = Vec::with_capacity(paths.len());
+        for (index, summary) in summaries {
+            let summary = summary?;
+            totals.file_count += summary.totals.file_count;
+            totals.total_size += summary.totals.total_size;
+            totals.allocated_size += summary.totals.allocated_size;
+            profile.walk_micros += summary.profile.walk_micros;
+            profile.stat_micros += summary.profile.stat_micros;
+            profile.aggregate_micros += summary.profile.aggregate_micros;
+            skipped.extend(summary.skipped);
+            roots.push(RootTotals {
+                root: PathBuf::from(paths[index].as_str()),
+                totals: summary.totals,
+            });
+        }
+        progress.report(&ProgressUpdate::new(totals.file_count, totals.total_size));
+
+        Ok(MultiRootAnalysis {
+            result: AnalysisResult {
+                total_size: totals.total_size,
+                allocated_size: Some(totals.allocated_size),
+                files: files.into_inner().unwrap(),
+                skipped,
+                interrupted: cancel.is_cancelled(),
+                strategy: AnalysisStrategy::Deep,
+                profile: ScanProfile {
+                    elapsed_micros: micros_since(started),
+                    files: totals.file_count,
+                    bytes: totals.total_size,
+                    ..profile
+                },
+            },
+            roots,
+        })
+    }
+
+    /// Analyze a directory, handing each file to `on_file` as it is found
+    ///
+    /// Nothing is collected, so memory stays flat however many files the tree
+    /// holds. `on_file` also receives the running totals including that file.
+    /// Files are visited from several threads at once and in no particular
+    /// order. Stops early if `cancel` fires.
+    ///
+    /// A file with several hard links is visited and counted once, under
+    /// whichever of its names the walk reaches first. APFS clones are separate
+    /// files as far as the walk can tell, so each copy counts in full.
+    pub async fn analyze_streaming<F>(
+        &self,
+        path: &FilePath,
+        options: &ScanOptions,
+        cancel: &CancellationToken,
+        on_file: F,
+    ) -> Result<StreamSummary>
+    where
+        F: Fn(FileEntity, AnalysisTotals) + Sync,
+    {
+        self.stream(path, options, cancel, on_file)
+    }
+
+    /// The walk behind [`Self::analyze_streaming`], on the calling thread
+    fn stream<F>(
+        &self,
+        path: &FilePath,
+        options: &ScanOptions,
+        cancel: &CancellationToken,
+        on_file: F,
+    ) -> Result<StreamSummary>
+    where
+        F: Fn(FileEntity, AnalysisTotals) + Sync,
+    {
         let path_str = path.as_str();
         let base_path = Path::new(path_str);
 
@@ -38,29 +488,214 @@ impl DiskAnalyzer {
             )));
         }
 
-        let files: Vec<FileEntity> = WalkDir::new(base_path)
-            .into_iter()
+        let started = Instant::now();
+        let (walk, stat, aggregate) = (PhaseTimer::new(), PhaseTimer::new(), PhaseTimer::new());
+        let file_count = AtomicU64::new(0);
+        let total_size = AtomicU64::new(0);
+        let allocated = AtomicU64::new(0);
+        // Only inodes with more than one name, so this stays small
+        let linked_inodes = Mutex::new(HashSet::new());
+        let skipped = Mutex::new(Vec::new());
+        let skip = |err: &jwalk::Error| {
+            if let Some(path) = skipped_from_walk_error(err) {
+                skipped.lock().unwrap().push(path);
+            }
+        };
+
+        let mut entries = walker(base_path, options).into_iter();
+        std::iter::from_fn(|| walk.time(|| entries.next()))
+            .take_while(|_| !cancel.is_cancelled())
             .par_bridge()
-            .filter_map(|entry| {
-                let entry = entry.ok()?;
-                let metadata = entry.metadata().ok()?;
+            .for_each(|entry| {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(err) => return skip(&err),
+                };
+                let metadata = match stat.time(|| entry.metadata()) {
+                    Ok(metadata) => metadata,
+                    Err(err) => return skip(&err),
+                };
 
                 if metadata.is_file() {
+                    let links = inode_links(&metadata);
+                    if let Some((inode, count)) = links {
+                        if count > 1 && !linked_inodes.lock().unwrap().insert(inode) {
+                            return;
+                        }
+                    }
                     let size = metadata.len();
+                    let on_disk = allocated_size(&metadata);
+                    let counted = on_disk.unwrap_or(size);
+                    let totals = AnalysisTotals {
+                        file_count: file_count.fetch_add(1, Ordering::Relaxed) + 1,
+                        total_size: total_size.fetch_add(size, Ordering::Relaxed) + size,
+                        allocated_size: allocated.fetch_add(counted, Ordering::Relaxed) + counted,
+                    };
                     let path_str = entry.path().to_string_lossy().to_string();
-                    Some(FileEntity {
-                        path: path_str,
-                        size,
-                    })
-                } else {
-                    None
+                    let mut file = FileEntity::new(path_str, size);
+                    file.allocated_size = on_disk;
+                    file.hardlink_count = links.map(|(_, count)| count);
+                    file.owner_uid = owner_uid(&metadata);
+                    file.modified = metadata.modified().ok().map(DateTime::from);
+                    aggregate.time(|| on_file(file, totals));
                 }
+            });
+
+        let totals = AnalysisTotals {
+            file_count: file_count.into_inner(),
+            total_size: total_size.into_inner(),
+            allocated_size: allocated.into_inner(),
+        };
+        Ok(StreamSummary {
+            totals,
+            skipped: skipped.into_inner().unwrap(),
+            interrupted: cancel.is_cancelled(),
+            profile: ScanProfile {
+                elapsed_micros: micros_since(started),
+                walk_micros: walk.micros(),
+                stat_micros: stat.micros(),
+                aggregate_micros: aggregate.micros(),
+                files: totals.file_count,
+                bytes: totals.total_size,
+                ..ScanProfile::default()
+            },
+        })
+    }
+
+    /// Estimate the size of a directory tree from `probes` random probes
+    /// instead of walking all of it ([`AnalysisStrategy::Quick`])
+    ///
+    /// Only a fraction of the directories are read, so huge volumes come back
+    /// in seconds; the estimate carries a 95% confidence interval (see
+    /// [`crate::sampling`]). Stops early if `cancel` fires.
+    pub async fn estimate_quick(
+        &self,
+        path: &FilePath,
+        options: &ScanOptions,
+        probes: usize,
+        cancel: &CancellationToken,
+    ) -> Result<QuickEstimate> {
+        let base_path = Path::new(path.as_str());
+        if !base_path.is_dir() {
+            return Err(dragonfly_core::error::Error::NotFound(format!(
+                "Directory does not exist: {}",
+                path.as_str()
+            )));
+        }
+        Ok(sampling::estimate(
+            base_path,
+            options,
+            probes,
+            fastrand::u64(..),
+            cancel,
+        )?)
+    }
+
+    /// Analyze a directory and total the file sizes of every directory below
+    /// it
+    ///
+    /// Only per-directory totals are kept, not the files themselves. Stops
+    /// early with partial sizes if `cancel` fires.
+    pub async fn analyze_tree(
+        &self,
+        path: &FilePath,
+        options: &ScanOptions,
+        cancel: &CancellationToken,
+    ) -> Result<DirectoryTree> {
+        let direct: Mutex<HashMap<PathBuf, (u64, u64)>> = Mutex::new(HashMap::new());
+        let summary = self
+            .analyze_streaming(path, options, cancel, |file, _| {
+                let Some(dir) = Path::new(&file.path).parent() else {
+                    return;
+                };
+                let mut direct = direct.lock().unwrap();
+                let (size, count) = direct.entry(dir.to_path_buf()).or_default();
+                *size += file.size;
+                *count += 1;
             })
-            .collect();
+            .await?;
+
+        let root =
+            DirectoryNode::from_direct(Path::new(path.as_str()), &direct.into_inner().unwrap());
+        Ok(DirectoryTree {
+            root,
+            skipped: summary.skipped,
+            interrupted: summary.interrupted,
+        })
+    }
 
-        let total_size: u64 = files.iter().map(|f| f.size).sum();
+    /// Find files of at least `min_size_bytes` using the given strategy
+    ///
+    /// With [`AnalysisStrategy::Spotlight`] the Spotlight index is queried
+    /// instead of walking, which is near-instant on indexed volumes. If the
+    /// volume isn't indexed or the query fails, this falls back to a walk; the
+    /// result's `strategy` says which was used. Only the matching files are
+    /// returned and `total_size` is their combined size.
+    pub async fn find_large_files_with_strategy(
+        &self,
+        path: &FilePath,
+        min_size_bytes: u64,
+        strategy: AnalysisStrategy,
+        options: &ScanOptions,
+        cancel: &CancellationToken,
+    ) -> Result<AnalysisResult> {
+        if strategy == AnalysisStrategy::Spotlight {
+            let started = Instant::now();
+            let root = Path::new(path.as_str());
+            if let Some(files) =
+                spotlight::find_files_larger_than(root, min_size_bytes, options, cancel)
+            {
+                let total_size = files.iter().map(|f| f.size).sum();
+                return Ok(AnalysisResult {
+                    total_size,
+                    allocated_size: None,
+                    profile: ScanProfile {
+                        elapsed_micros: micros_since(started),
+                        files: files.len() as u64,
+                        bytes: total_size,
+                        ..ScanProfile::default()
+                    },
+                    files,
+                    skipped: Vec::new(),
+                    interrupted: cancel.is_cancelled(),
+                    strategy: AnalysisStrategy::Spotlight,
+                });
+            }
+            tracing::debug!(
+                path = path.as_str(),
+                "Spotlight unavailable, walking instead"
+            );
+        }
 
-        Ok(AnalysisResult { total_size, files })
+        let mut result = self.analyze_with_options(path, options, cancel).await?;
+        result.files.retain(|f| f.size >= min_size_bytes);
+        result.total_size = result.files.iter().map(|f| f.size).sum();
+        result.allocated_size = Some(result.files.iter().map(FileEntity::size_on_disk).sum());
+        Ok(result)
+    }
+
+    /// Find files of at least `min_size` bytes that haven't been modified or
+    /// read in `older_than_days` days, largest first
+    ///
+    /// Stops early with partial results if `cancel` fires. Access times are
+    /// only as good as the filesystem keeps them (see [`crate::stale`]).
+    pub async fn find_stale_files(
+        &self,
+        path: &FilePath,
+        older_than_days: u32,
+        min_size: u64,
+        options: &ScanOptions,
+        cancel: &CancellationToken,
+    ) -> Result<StaleFilesReport> {
+        let base_path = Path::new(path.as_str());
+        if !base_path.exists() {
+            return Err(dragonfly_core::error::Error::NotFound(format!(
+                "Path does not exist: {}",
+                path.as_str()
+            )));
+        }
+        let cutoff = chrono::Utc::now() - chrono::Duration::days(i64::from(older_than_days));
+        Ok(stale::find(base_path, cutoff, min_size, options, cancel))
     }
 
     /// Find large files above a minimum size
@@ -89,9 +724,339 @@ impl Default for DiskAnalyzer {
 mod tests {
     use super::*;
 
+    use std::fs;
+    use tempfile::TempDir;
+
     #[test]
     fn test_analyzer_creation() {
         let analyzer = DiskAnalyzer::new();
         assert_eq!(std::mem::size_of_val(&analyzer), 0);
     }
+
+    #[tokio::test]
+    async fn test_analyze_sums_file_sizes() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("a.bin"), vec![0u8; 100]).unwrap();
+        fs::create_dir(temp_dir.path().join("sub")).unwrap();
+        fs::write(temp_dir.path().join("sub").join("b.bin"), vec![0u8; 50]).unwrap();
+
+        let analyzer = DiskAnalyzer::new();
+        let path = FilePath::new(temp_dir.path().to_string_lossy().to_string());
+        let result = analyzer.analyze(&path).await.unwrap();
+
+        assert_eq!(result.files.len(), 2);
+        assert_eq!(result.total_size, 150);
+        assert!(result.skipped.is_empty());
+        assert!(result.files.iter().all(|f| !f.protection.is_protected()));
+        assert!(!result.interrupted);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_analyze_reports_allocated_size_of_sparse_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let sparse = fs::File::create(temp_dir.path().join("sparse.img")).unwrap();
+        sparse.set_len(64 * 1024 * 1024).unwrap();
+        fs::write(temp_dir.path().join("dense.bin"), vec![1u8; 8192]).unwrap();
+
+        let path = FilePath::new(temp_dir.path().to_string_lossy().to_string());
+        let result = DiskAnalyzer::new().analyze(&path).await.unwrap();
+
+        let sparse = result
+            .files
+            .iter()
+            .find(|f| f.path.ends_with("sparse.img"))
+            .unwrap();
+        assert_eq!(sparse.size, 64 * 1024 * 1024);
+        assert!(sparse.allocated_size.unwrap() < sparse.size);
+        let dense = result
+            .files
+            .iter()
+            .find(|f| f.path.ends_with("dense.bin"))
+            .unwrap();
+        assert!(dense.allocated_size.unwrap() >= 8192);
+        assert!(result.allocated_size.unwrap() < result.total_size);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_analyze_counts_hardlinked_files_once() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("original.bin"), vec![0u8; 100]).unwrap();
+        fs::create_dir(temp_dir.path().join("sub")).unwrap();
+        fs::hard_link(
+            temp_dir.path().join("original.bin"),
+            temp_dir.path().join("sub/link.bin"),
+        )
+        .unwrap();
+        fs::write(temp_dir.path().join("other.bin"), vec![0u8; 10]).unwrap();
+
+        let path = FilePath::new(temp_dir.path().to_string_lossy().to_string());
+        let result = DiskAnalyzer::new().analyze(&path).await.unwrap();
+
+        assert_eq!(result.total_size, 110);
+        assert_eq!(result.files.len(), 2);
+        let linked = result.files.iter().find(|f| f.size == 100).unwrap();
+        assert_eq!(linked.hardlink_count, Some(2));
+        let other = result.files.iter().find(|f| f.size == 10).unwrap();
+        assert_eq!(other.hardlink_count, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_analyze_roots_merges_with_subtotals() {
+        let temp_dir = TempDir::new().unwrap();
+        let roots: Vec<PathBuf> = ["a", "b", "c"]
+            .iter()
+            .map(|name| temp_dir.path().join(name))
+            .collect();
+        for (i, root) in roots.iter().enumerate() {
+            fs::create_dir(root).unwrap();
+            fs::write(root.join("file.bin"), vec![0u8; 10 * (i + 1)]).unwrap();
+        }
+        let paths: Vec<FilePath> = roots
+            .iter()
+            .map(|root| FilePath::new(root.to_string_lossy().to_string()))
+            .collect();
+
+        let analysis = DiskAnalyzer::new()
+            .analyze_roots(
+                &paths,
+                &ScanOptions::default(),
+                &NoProgress,
+                &CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(analysis.result.total_size, 60);
+        assert_eq!(analysis.result.files.len(), 3);
+        let subtotals: Vec<(PathBuf, u64)> = analysis
+            .roots
+            .into_iter()
+            .map(|root| (root.root, root.totals.total_size))
+            .collect();
+        assert_eq!(
+            subtotals,
+            [
+                (roots[0].clone(), 10),
+                (roots[1].clone(), 20),
+                (roots[2].clone(), 30)
+            ]
+        );
+
+        let nested = [
+            paths[0].clone(),
+            FilePath::new(temp_dir.path().to_string_lossy().to_string()),
+        ];
+        let overlap = DiskAnalyzer::new()
+            .analyze_roots(
+                &nested,
+                &ScanOptions::default(),
+                &NoProgress,
+                &CancellationToken::new(),
+            )
+            .await;
+        assert!(matches!(
+            overlap,
+            Err(dragonfly_core::error::Error::InvalidInput(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_analyze_streaming_visits_every_file() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("a.bin"), vec![0u8; 100]).unwrap();
+        fs::create_dir(temp_dir.path().join("sub")).unwrap();
+        fs::write(temp_dir.path().join("sub").join("b.bin"), vec![0u8; 50]).unwrap();
+
+        let path = FilePath::new(temp_dir.path().to_string_lossy().to_string());
+        let visited = Mutex::new(Vec::new());
+        let summary = DiskAnalyzer::new()
+            .analyze_streaming(
+                &path,
+                &ScanOptions::default(),
+                &CancellationToken::new(),
+                |file, totals| visited.lock().unwrap().push((file.size, totals)),
+            )
+            .await
+            .unwrap();
+
+        let mut visited = visited.into_inner().unwrap();
+        visited.sort_by_key(|(_, totals)| totals.file_count);
+        assert_eq!(visited.len(), 2);
+        assert_eq!(visited[1].1.file_count, 2);
+        assert_eq!(visited[1].1.total_size, 150);
+        assert_eq!(
+            summary.totals,
+            AnalysisTotals {
+                file_count: 2,
+                total_size: 150,
+                allocated_size: summary.totals.allocated_size,
+            }
+        );
+        assert!(!summary.interrupted);
+        assert_eq!((summary.profile.files, summary.profile.bytes), (2, 150));
+        assert_eq!(summary.profile.hash_micros, 0);
+    }
+
+    #[tokio::test]
+    async fn test_analyze_with_progress_reports_while_scanning() {
+        let temp_dir = TempDir::new().unwrap();
+        for i in 0..PROGRESS_INTERVAL + 1 {
+            fs::write(temp_dir.path().join(format!("{i}.bin")), [0u8; 2]).unwrap();
+        }
+
+        let path = FilePath::new(temp_dir.path().to_string_lossy().to_string());
+        let updates = Mutex::new(Vec::new());
+        let reporter = |update: &ProgressUpdate| updates.lock().unwrap().push(update.clone());
+        DiskAnalyzer::new()
+            .analyze_with_progress(
+                &path,
+                &ScanOptions::default(),
+                &reporter,
+                &CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+
+        let updates = updates.into_inner().unwrap();
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].items_processed, PROGRESS_INTERVAL);
+        assert_eq!(updates[0].bytes_processed, 2 * PROGRESS_INTERVAL);
+        assert!(updates[0].current_path.is_some());
+        assert_eq!(
+            updates[1],
+            ProgressUpdate::new(PROGRESS_INTERVAL + 1, 2 * (PROGRESS_INTERVAL + 1))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_analyze_tree_totals_directories() {
+        let temp_dir = TempDir::new().unwrap();
+        let nested = temp_dir.path().join("a/b");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::create_dir(temp_dir.path().join("empty")).unwrap();
+        std::fs::write(temp_dir.path().join("top.bin"), vec![0u8; 10]).unwrap();
+        std::fs::write(temp_dir.path().join("a/one.bin"), vec![0u8; 20]).unwrap();
+        std::fs::write(nested.join("two.bin"), vec![0u8; 30]).unwrap();
+
+        let path = FilePath::new(temp_dir.path().to_string_lossy().to_string());
+        let tree = DiskAnalyzer::new()
+            .analyze_tree(&path, &ScanOptions::default(), &CancellationToken::new())
+            .await
+            .unwrap();
+
+        assert_eq!((tree.root.size, tree.root.file_count), (60, 3));
+        assert_eq!(tree.root.children.len(), 1);
+        let a = &tree.root.children[0];
+        assert_eq!((a.path.clone(), a.size), (temp_dir.path().join("a"), 50));
+        assert_eq!(a.children[0].size, 30);
+        assert!(!tree.interrupted);
+    }
+
+    #[tokio::test]
+    async fn test_find_large_files_with_strategy_filters_and_totals() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("big.bin"), vec![0u8; 100]).unwrap();
+        fs::write(temp_dir.path().join("small.bin"), vec![0u8; 10]).unwrap();
+
+        let path = FilePath::new(temp_dir.path().to_string_lossy().to_string());
+        let result = DiskAnalyzer::new()
+            .find_large_files_with_strategy(
+                &path,
+                50,
+                AnalysisStrategy::Deep,
+                &ScanOptions::default(),
+                &CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(result.files.len(), 1);
+        assert_eq!(result.total_size, 100);
+        assert_eq!(result.strategy, AnalysisStrategy::Deep);
+    }
+
+    #[tokio::test]
+    async fn test_analyze_cancelled_returns_partial_result() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("a.bin"), vec![0u8; 100]).unwrap();
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        let analyzer = DiskAnalyzer::new();
+        let path = FilePath::new(temp_dir.path().to_string_lossy().to_string());
+        let result = analyzer
+            .analyze_with_options(&path, &ScanOptions::default(), &cancel)
+            .await
+            .unwrap();
+
+        assert!(result.interrupted);
+        assert!(result.files.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_analyze_checkpointed_resumes_finished_parts() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("root");
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::write(root.join("top.bin"), vec![0u8; 10]).unwrap();
+        fs::write(root.join("a/b/deep.bin"), vec![0u8; 20]).unwrap();
+        let checkpoint = temp_dir.path().join("checkpoint.json");
+        let analyzer = DiskAnalyzer::new();
+        let path = FilePath::new(root.to_string_lossy().to_string());
+        let options = ScanOptions::default();
+
+        // Stopped before anything was scanned
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let result = analyzer
+            .analyze_checkpointed(&path, &options, &checkpoint, None, &NoProgress, &cancel)
+            .await
+            .unwrap();
+        assert!(result.interrupted);
+        let mut saved = ScanCheckpoint::load(&checkpoint).unwrap();
+        assert!(saved.completed.is_empty());
+
+        // A finished part is taken from the checkpoint, not scanned again
+        saved.completed.insert(PathBuf::from("a/b"));
+        saved
+            .files
+            .push(FileEntity::new("a/b/deep.bin".into(), 1000));
+        let result = analyzer
+            .analyze_checkpointed(
+                &path,
+                &options,
+                &checkpoint,
+                Some(saved),
+                &NoProgress,
+                &CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        assert!(!result.interrupted);
+        assert_eq!(result.files.len(), 2);
+        assert_eq!(result.total_size, 1010);
+        assert!(!checkpoint.exists());
+
+        let other = ScanCheckpoint::capture(
+            &root,
+            Path::new("/elsewhere"),
+            &options,
+            &BTreeSet::new(),
+            &[],
+            &[],
+        );
+        assert!(analyzer
+            .analyze_checkpointed(
+                &path,
+                &options,
+                &checkpoint,
+                Some(other),
+                &NoProgress,
+                &CancellationToken::new(),
+            )
+            .await
+            .is_err());
+    }
 }
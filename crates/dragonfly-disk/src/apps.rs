@@ -0,0 +1,270 @@
+//! Installed application inventory
+//!
+//! An app's footprint is more than its bundle: most apps also keep support
+//! files, caches, containers, and saved state under `~/Library`, usually named
+//! after the app or its bundle identifier. This module sizes both parts and
+//! asks Spotlight when each app was last opened, which together show the large
+//! apps that haven't been used in months.
+
+use crate::spotlight;
+use crate::walk::walker;
+use chrono::{DateTime, Utc};
+use dragonfly_core::domain::scan_options::ScanOptions;
+use dragonfly_core::ports::CancellationToken;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Folders under `~/Library` where apps keep data named after the app
+const DATA_BY_NAME: &[&str] = &["Application Support", "Logs"];
+
+/// Folders under `~/Library` where apps keep data named after the bundle id
+const DATA_BY_BUNDLE_ID: &[&str] = &[
+    "Application Support",
+    "Caches",
+    "Containers",
+    "HTTPStorages",
+    "Logs",
+    "WebKit",
+];
+
+/// An installed application and the space it uses
+#[derive(Debug, Clone, Serialize)]
+pub struct AppReport {
+    /// Display name (bundle name without `.app`)
+    pub name: String,
+    /// Bundle path
+    pub path: PathBuf,
+    /// Bundle identifier, when it could be read
+    pub bundle_id: Option<String>,
+    /// Size of the bundle in bytes
+    pub bundle_size: u64,
+    /// Size of the app's data under `~/Library` in bytes
+    pub data_size: u64,
+    /// Data locations that exist
+    pub data_paths: Vec<PathBuf>,
+    /// When the app was last opened, if Spotlight knows
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+impl AppReport {
+    /// Bundle and data size together
+    pub fn total_size(&self) -> u64 {
+        self.bundle_size + self.data_size
+    }
+
+    /// Whether the app is known not to have been opened since `cutoff`
+    ///
+    /// Apps Spotlight has no record of are not considered unused, since that
+    /// usually means the volume isn't indexed rather than that the app was
+    /// never opened.
+    pub fn unused_since(&self, cutoff: DateTime<Utc>) -> bool {
+        self.last_used.is_some_and(|last_used| last_used < cutoff)
+    }
+}
+
+/// Inventory of the apps installed in a set of folders
+#[derive(Debug, Clone)]
+pub struct AppInventory {
+    app_dirs: Vec<PathBuf>,
+    library: PathBuf,
+}
+
+impl AppInventory {
+    /// Inventory apps in `app_dirs`, with their data under `library`
+    pub fn new(app_dirs: Vec<PathBuf>, library: PathBuf) -> Self {
+        Self { app_dirs, library }
+    }
+
+    /// Size every app, largest first
+    ///
+    /// App folders that don't exist are skipped. Stops early with the apps
+    /// sized so far if `cancel` fires.
+    pub async fn scan(&self, cancel: &CancellationToken) -> Vec<AppReport> {
+        let mut apps = Vec::new();
+        for bundle in app_bundles(&self.app_dirs) {
+            if cancel.is_cancelled() {
+                break;
+            }
+            apps.push(self.inspect(&bundle, cancel));
+        }
+        apps.sort_by_key(|app| std::cmp::Reverse(app.total_size()));
+        apps
+    }
+
+    fn inspect(&self, bundle: &Path, cancel: &CancellationToken) -> AppReport {
+        let name = bundle
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let bundle_id = bundle_identifier(bundle);
+        let data_paths = data_locations(&self.library, &name, bundle_id.as_deref());
+        let data_size = data_paths.iter().map(|path| size_of(path, cancel)).sum();
+
+        AppReport {
+            bundle_size: size_of(bundle, cancel),
+            data_size,
+            data_paths,
+            last_used: spotlight::last_used_date(bundle),
+            path: bundle.to_path_buf(),
+            bundle_id,
+            name,
+        }
+    }
+}
+
+/// `.app` bundles directly inside `app_dirs`, sorted by path
+pub(crate) fn app_bundles(app_dirs: &[PathBuf]) -> Vec<PathBuf> {
+    let mut bundles: Vec<PathBuf> = app_dirs
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.flatten())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "app") && path.is_dir())
+        .collect();
+    bundles.sort();
+    bundles
+}
+
+/// Existing data locations for an app under `library`
+fn data_locations(library: &Path, name: &str, bundle_id: Option<&str>) -> Vec<PathBuf> {
+    let mut candidates: Vec<PathBuf> = DATA_BY_NAME
+        .iter()
+        .map(|folder| library.join(folder).join(name))
+        .collect();
+    if let Some(id) = bundle_id {
+        candidates.extend(
+            DATA_BY_BUNDLE_ID
+                .iter()
+                .map(|folder| library.join(folder).join(id)),
+        );
+        candidates.push(library.join("Preferences").join(format!("{}.plist", id)));
+        candidates.push(
+            library
+                .join("Saved Application State")
+                .join(format!("{}.savedState", id)),
+        );
+    }
+
+    let mut locations: Vec<PathBuf> = candidates
+        .into_iter()
+        .filter(|path| path.exists())
+        .collect();
+    locations.sort();
+    locations.dedup();
+    locations
+}
+
+/// Total size of a file, or of the files under a directory
+pub(crate) fn size_of(path: &Path, cancel: &CancellationToken) -> u64 {
+    if path.is_file() {
+        return std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    }
+    walker(path, &ScanOptions::new().with_hidden(true))
+        .into_iter()
+        .take_while(|_| !cancel.is_cancelled())
+        .flatten()
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Read `CFBundleIdentifier` from a bundle's Info.plist
+///
+/// XML property lists are read directly. Binary ones are converted with
+/// `plutil`, which is only available on macOS.
+pub(crate) fn bundle_identifier(bundle: &Path) -> Option<String> {
+    let info = bundle.join("Contents/Info.plist");
+    let content = std::fs::read(&info).ok()?;
+    if content.starts_with(b"bplist") {
+        let output = Command::new("plutil")
+            .args(["-extract", "CFBundleIdentifier", "raw", "-o", "-"])
+            .arg(&info)
+            .output()
+            .ok()?;
+        let id = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        return (output.status.success() && !id.is_empty()).then_some(id);
+    }
+    plist_string(&String::from_utf8_lossy(&content), "CFBundleIdentifier")
+}
+
+/// Value of a top-level string key in an XML property list
+fn plist_string(xml: &str, key: &str) -> Option<String> {
+    let after_key = xml.split_once(&format!("<key>{}</key>", key))?.1;
+    let value = after_key.trim_start().strip_prefix("<string>")?;
+    let value = value.split_once("</string>")?.0.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn write(root: &Path, relative: &str, contents: &[u8]) {
+        let path = root.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_plist_string() {
+        let xml = "<dict>\n\t<key>CFBundleName</key>\n\t<string>Editor</string>\n\
+                   \t<key>CFBundleIdentifier</key>\n\t<string>com.example.editor</string>\n</dict>";
+        assert_eq!(
+            plist_string(xml, "CFBundleIdentifier").as_deref(),
+            Some("com.example.editor")
+        );
+        assert_eq!(plist_string(xml, "CFBundleVersion"), None);
+    }
+
+    #[tokio::test]
+    async fn test_scan_sizes_bundles_and_data() {
+        let temp_dir = TempDir::new().unwrap();
+        let apps = temp_dir.path().join("Applications");
+        let library = temp_dir.path().join("Library");
+        write(
+            &apps,
+            "Editor.app/Contents/Info.plist",
+            b"<key>CFBundleIdentifier</key><string>com.example.editor</string>",
+        );
+        write(&apps, "Editor.app/Contents/MacOS/Editor", &[0; 100]);
+        write(&apps, "Tiny.app/Contents/MacOS/Tiny", &[0; 10]);
+        write(&apps, "notes.txt", b"not an app");
+        write(&library, "Application Support/Editor/state.db", &[0; 30]);
+        write(&library, "Caches/com.example.editor/cache.bin", &[0; 20]);
+        write(&library, "Preferences/com.example.editor.plist", &[0; 5]);
+
+        let reports = AppInventory::new(vec![apps], library)
+            .scan(&CancellationToken::new())
+            .await;
+
+        assert_eq!(reports.len(), 2);
+        let editor = &reports[0];
+        assert_eq!(editor.name, "Editor");
+        assert_eq!(editor.bundle_id.as_deref(), Some("com.example.editor"));
+        assert_eq!(editor.data_size, 55);
+        assert_eq!(editor.data_paths.len(), 3);
+        assert_eq!(reports[1].name, "Tiny");
+        assert_eq!(reports[1].data_size, 0);
+    }
+
+    #[test]
+    fn test_unused_since() {
+        let now = Utc::now();
+        let mut report = AppReport {
+            name: "App".to_string(),
+            path: PathBuf::from("/Applications/App.app"),
+            bundle_id: None,
+            bundle_size: 0,
+            data_size: 0,
+            data_paths: Vec::new(),
+            last_used: None,
+        };
+        assert!(!report.unused_since(now));
+        report.last_used = Some(now - chrono::Duration::days(200));
+        assert!(report.unused_since(now - chrono::Duration::days(90)));
+    }
+}
@@ -0,0 +1,277 @@
+//! Scans that survive being interrupted
+//!
+//! A deep scan of a whole disk can run for an hour, and stopping it used to
+//! throw all of that away. [`DiskAnalyzer::analyze_checkpointed`] scans the
+//! tree in parts (the files directly in the top directories, then every
+//! directory two levels down as a whole) and every so often writes the parts
+//! finished so far to a [`ScanCheckpoint`]. A later scan handed that
+//! checkpoint only scans the parts that are missing.
+//!
+//! [`DiskAnalyzer::analyze_checkpointed`]: crate::DiskAnalyzer::analyze_checkpointed
+
+use crate::walk::{device_id, leads_back, walk_includes};
+use chrono::{DateTime, Utc};
+use dragonfly_core::domain::entities::FileEntity;
+use dragonfly_core::domain::protection::SkippedPath;
+use dragonfly_core::domain::scan_options::ScanOptions;
+use dragonfly_core::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Version of the checkpoint format, bumped on incompatible changes
+const FORMAT_VERSION: u32 = 1;
+
+/// How many levels below the root the tree is split into parts
+const SPLIT_DEPTH: usize = 2;
+
+/// Least time between two checkpoint writes
+pub const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The finished parts of an interrupted scan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanCheckpoint {
+    version: u32,
+    /// Scanned directory
+    pub root: PathBuf,
+    /// Options the scan ran with
+    pub options: ScanOptions,
+    /// When the checkpoint was written
+    pub saved_at: DateTime<Utc>,
+    /// Finished parts, as directories relative to the root
+    pub completed: BTreeSet<PathBuf>,
+    /// Files of the finished parts, with paths relative to the root
+    pub files: Vec<FileEntity>,
+    /// Paths the finished parts could not read, with the reason
+    pub skipped: Vec<SkippedPath>,
+}
+
+impl ScanCheckpoint {
+    /// Default checkpoint location (`~/.dragonfly/scan-checkpoint.json`)
+    pub fn default_path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_else(|| PathBuf::from("~"))
+            .join(".dragonfly")
+            .join("scan-checkpoint.json")
+    }
+
+    /// Checkpoint of the `completed` parts of a scan of `base`, whose files
+    /// are `files` with paths as scanned
+    pub(crate) fn capture(
+        base: &Path,
+        root: &Path,
+        options: &ScanOptions,
+        completed: &BTreeSet<PathBuf>,
+        files: &[FileEntity],
+        skipped: &[SkippedPath],
+    ) -> Self {
+        let files = files
+            .iter()
+            .map(|file| {
+                let mut file = file.clone();
+                if let Ok(relative) = Path::new(&file.path).strip_prefix(base) {
+                    file.path = relative.to_string_lossy().into_owned();
+                }
+                file
+            })
+            .collect();
+        Self {
+            version: FORMAT_VERSION,
+            root: root.to_path_buf(),
+            options: options.clone(),
+            saved_at: Utc::now(),
+            completed: completed.clone(),
+            files,
+            skipped: skipped.to_vec(),
+        }
+    }
+
+    /// Whether this checkpoint is of a scan of the canonical `root` with
+    /// `options`; the number of threads doesn't matter
+    pub fn covers(&self, root: &Path, options: &ScanOptions) -> bool {
+        let unthreaded = |options: &ScanOptions| ScanOptions {
+            threads: None,
+            ..options.clone()
+        };
+        self.root == root && unthreaded(&self.options) == unthreaded(options)
+    }
+
+    /// Write the checkpoint to `path` as JSON, replacing any earlier one
+    /// whole so an interruption while writing can't leave half a file
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_vec(self)
+            .map_err(|e| Error::Internal(format!("Failed to encode checkpoint: {}", e)))?;
+        let partial = path.with_extension("json.partial");
+        std::fs::write(&partial, json)?;
+        std::fs::rename(&partial, path)?;
+        Ok(())
+    }
+
+    /// Read a checkpoint written by [`ScanCheckpoint::save`]
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read(path)?;
+        let checkpoint: Self = serde_json::from_slice(&content).map_err(|e| {
+            Error::InvalidInput(format!("Not a scan checkpoint: {}: {}", path.display(), e))
+        })?;
+        if checkpoint.version != FORMAT_VERSION {
+            return Err(Error::NotSupported(format!(
+                "Scan checkpoint {} has format version {}, expected {}",
+                path.display(),
+                checkpoint.version,
+                FORMAT_VERSION
+            )));
+        }
+        Ok(checkpoint)
+    }
+}
+
+/// A part of a scan that is finished (and checkpointed) as a whole
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ScanPart {
+    /// Directory the part covers
+    pub dir: PathBuf,
+    /// Levels below the root
+    depth: usize,
+    /// Whether the part is everything below `dir` rather than only the files
+    /// directly in it
+    whole_tree: bool,
+}
+
+impl ScanPart {
+    /// Options that walk exactly this part of a scan with `options`
+    pub(crate) fn walk_options(&self, options: &ScanOptions) -> ScanOptions {
+        let max_depth = if self.whole_tree {
+            options.max_depth.map(|max| max.saturating_sub(self.depth))
+        } else {
+            Some(1)
+        };
+        options.clone().with_max_depth(max_depth)
+    }
+}
+
+/// The parts a scan of `root` with `options` is made of, in scan order
+///
+/// Directories the scan would leave out (hidden, excluded, on another
+/// filesystem, or a link leading back up) are left out here too.
+pub(crate) fn split(root: &Path, options: &ScanOptions) -> Vec<ScanPart> {
+    let device = if options.one_filesystem {
+        device_id(root)
+    } else {
+        None
+    };
+    let mut parts = Vec::new();
+    split_dir(root, root, 0, options, device, &mut parts);
+    parts
+}
+
+fn split_dir(
+    root: &Path,
+    dir: &Path,
+    depth: usize,
+    options: &ScanOptions,
+    device: Option<u64>,
+    parts: &mut Vec<ScanPart>,
+) {
+    let whole = ScanPart {
+        dir: dir.to_path_buf(),
+        depth,
+        whole_tree: true,
+    };
+    if depth >= SPLIT_DEPTH || options.max_depth.is_some_and(|max| depth + 1 >= max) {
+        return parts.push(whole);
+    }
+    // The walk of the part reports why it couldn't be read
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return parts.push(whole);
+    };
+    let mut children: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|entry| {
+            let Ok(file_type) = entry.file_type() else {
+                return false;
+            };
+            if file_type.is_symlink() {
+                options.follow_symlinks && entry.path().is_dir() && !leads_back(dir, &entry.path())
+            } else {
+                file_type.is_dir()
+            }
+        })
+        .map(|entry| entry.path())
+        .filter(|path| walk_includes(root, path, options))
+        .filter(|path| device.map_or(true, |device| device_id(path) == Some(device)))
+        .collect();
+    children.sort();
+
+    parts.push(ScanPart {
+        whole_tree: false,
+        ..whole
+    });
+    for child in children {
+        split_dir(root, &child, depth + 1, options, device, parts);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_split_into_parts() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("a/b/c")).unwrap();
+        fs::create_dir_all(root.join("a/d")).unwrap();
+        fs::create_dir_all(root.join(".hidden/x")).unwrap();
+        fs::write(root.join("file.txt"), "x").unwrap();
+
+        let parts: Vec<(PathBuf, bool)> = split(root, &ScanOptions::default())
+            .into_iter()
+            .map(|part| (part.dir, part.whole_tree))
+            .collect();
+        assert_eq!(
+            parts,
+            [
+                (root.to_path_buf(), false),
+                (root.join("a"), false),
+                (root.join("a/b"), true),
+                (root.join("a/d"), true),
+            ]
+        );
+
+        let shallow = ScanOptions::default().with_max_depth(Some(1));
+        let parts = split(root, &shallow);
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].walk_options(&shallow).max_depth, Some(1));
+    }
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("checkpoint.json");
+        let options = ScanOptions::default();
+        let files = [FileEntity::new("/data/a/b.bin".into(), 10)];
+        let completed = BTreeSet::from([PathBuf::from("a")]);
+        let checkpoint = ScanCheckpoint::capture(
+            Path::new("/data"),
+            Path::new("/data"),
+            &options,
+            &completed,
+            &files,
+            &[],
+        );
+        checkpoint.save(&path).unwrap();
+
+        let loaded = ScanCheckpoint::load(&path).unwrap();
+        assert_eq!(loaded.files[0].path, "a/b.bin");
+        assert_eq!(loaded.completed, completed);
+        assert!(loaded.covers(Path::new("/data"), &options.clone().with_threads(Some(2))));
+        assert!(!loaded.covers(Path::new("/data"), &options.with_hidden(true)));
+        assert!(!loaded.covers(Path::new("/other"), &ScanOptions::default()));
+    }
+}
@@ -0,0 +1,281 @@
+//! Rebuildable developer artifacts
+//!
+//! Dependency folders, build output, and package caches often outweigh the
+//! source code they belong to, and every one of them can be restored by
+//! building or installing again. This module finds them across a tree and
+//! attributes each to the project it was built for, so the space they take
+//! can be weighed project by project.
+//!
+//! Build directories with generic names (`target`, `build`, `Pods`) only
+//! count next to the file that marks their build tool, so an unrelated folder
+//! that happens to share the name is left out.
+
+use crate::apps::size_of;
+use dragonfly_core::domain::protection::{PathProtection, SkippedPath};
+use dragonfly_core::error::{Error, Result};
+use dragonfly_core::ports::CancellationToken;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Kind of rebuildable artifact
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DevArtifactKind {
+    /// npm, Yarn, or pnpm dependencies (`node_modules`)
+    NodeModules,
+    /// Cargo build output (`target` next to `Cargo.toml`)
+    CargoTarget,
+    /// Python virtual environment (a folder holding `pyvenv.cfg`)
+    PythonVirtualenv,
+    /// Gradle build output and project cache (`build`, `.gradle`)
+    GradleBuild,
+    /// Gradle's shared download cache (`~/.gradle/caches`)
+    GradleCache,
+    /// Maven build output (`target` next to `pom.xml`)
+    MavenTarget,
+    /// Maven's shared repository (`~/.m2/repository`)
+    MavenRepository,
+    /// CocoaPods dependencies (`Pods` next to `Podfile`)
+    CocoaPods,
+    /// CocoaPods' shared cache (`~/Library/Caches/CocoaPods`)
+    CocoaPodsCache,
+    /// Xcode build products and indexes, one folder per project
+    XcodeDerivedData,
+}
+
+/// A folder of rebuildable artifacts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DevArtifact {
+    /// The artifact folder
+    pub path: PathBuf,
+    /// What it holds
+    pub kind: DevArtifactKind,
+    /// Project it was built for; `None` for caches shared by all projects
+    pub project: Option<PathBuf>,
+    /// Size of its files in bytes
+    pub size: u64,
+    /// Protection level of the path
+    pub protection: PathProtection,
+}
+
+/// Space the artifacts of one project take
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProjectArtifacts {
+    /// Project folder; `None` for caches shared by all projects
+    pub project: Option<PathBuf>,
+    /// Combined size of the project's artifacts in bytes
+    pub total_size: u64,
+    /// The artifacts, largest first
+    pub artifacts: Vec<DevArtifact>,
+}
+
+/// Rebuildable artifacts found under a directory
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DevArtifactsReport {
+    /// Directory that was searched
+    pub root: PathBuf,
+    /// Artifacts grouped by project, largest first
+    pub projects: Vec<ProjectArtifacts>,
+    /// Combined size of every artifact in bytes, all of it reclaimable by
+    /// deleting the folders and rebuilding when needed
+    pub total_size: u64,
+    /// Project folders the search wasn't allowed to enter
+    pub skipped: Vec<SkippedPath>,
+    /// Whether the search was cancelled before completing (results are partial)
+    pub interrupted: bool,
+}
+
+/// Finds rebuildable developer artifacts
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DevArtifactFinder;
+
+impl DevArtifactFinder {
+    /// Create a new developer artifact finder
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Search `root` for artifact folders and measure them
+    ///
+    /// Artifact folders are not searched further, so dependencies nested in
+    /// `node_modules` count once, with their outermost folder. Symlinks are
+    /// not followed.
+    pub async fn find(
+        &self,
+        root: &Path,
+        cancel: &CancellationToken,
+    ) -> Result<DevArtifactsReport> {
+        if !root.is_dir() {
+            return Err(Error::NotFound(format!(
+                "Directory does not exist: {}",
+                root.display()
+            )));
+        }
+
+        let mut report = DevArtifactsReport {
+            root: root.to_path_buf(),
+            ..Default::default()
+        };
+        let mut found = Vec::new();
+        let mut pending = vec![root.to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            if cancel.is_cancelled() {
+                break;
+            }
+            let entries = match std::fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(err) => {
+                    report
+                        .skipped
+                        .extend(SkippedPath::from_io_error(&dir.to_string_lossy(), &err));
+                    continue;
+                }
+            };
+            for entry in entries.flatten() {
+                if !entry.file_type().is_ok_and(|t| t.is_dir()) {
+                    continue;
+                }
+                let path = entry.path();
+                match classify(&path) {
+                    Some((kind, project)) => found.push((path, kind, project)),
+                    None => pending.push(path),
+                }
+            }
+        }
+
+        // Each measurement walks in parallel already; jwalk gives up when
+        // started from threads of a busy rayon pool
+        let artifacts: Vec<DevArtifact> = found
+            .into_iter()
+            .map(|(path, kind, project)| DevArtifact {
+                size: size_of(&path, cancel),
+                protection: PathProtection::classify(&path.to_string_lossy()),
+                path,
+                kind,
+                project,
+            })
+            .collect();
+        report.total_size = artifacts.iter().map(|a| a.size).sum();
+        report.projects = by_project(artifacts);
+        report.interrupted = cancel.is_cancelled();
+        Ok(report)
+    }
+}
+
+/// What the folder at `dir` holds and the project it belongs to, if it is an
+/// artifact folder
+fn classify(dir: &Path) -> Option<(DevArtifactKind, Option<PathBuf>)> {
+    use DevArtifactKind::*;
+
+    let name = dir.file_name()?.to_str()?;
+    let parent = dir.parent()?;
+    let parent_name = parent.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let beside = |file: &str| parent.join(file).is_file();
+    let gradle_project = || {
+        [
+            "build.gradle",
+            "build.gradle.kts",
+            "settings.gradle",
+            "settings.gradle.kts",
+        ]
+        .iter()
+        .any(|file| beside(file))
+    };
+
+    let kind = match name {
+        "node_modules" => NodeModules,
+        "target" if beside("Cargo.toml") => CargoTarget,
+        "target" if beside("pom.xml") => MavenTarget,
+        "build" | ".gradle" if gradle_project() => GradleBuild,
+        "Pods" if beside("Podfile") => CocoaPods,
+        "caches" if parent_name == ".gradle" => return Some((GradleCache, None)),
+        "repository" if parent_name == ".m2" => return Some((MavenRepository, None)),
+        "CocoaPods" if parent_name == "Caches" => return Some((CocoaPodsCache, None)),
+        _ if parent_name == "DerivedData" => return Some((XcodeDerivedData, None)),
+        _ if dir.join("pyvenv.cfg").is_file() => PythonVirtualenv,
+        _ => return None,
+    };
+    Some((kind, Some(parent.to_path_buf())))
+}
+
+/// Group artifacts by project, largest project and artifact first
+fn by_project(artifacts: Vec<DevArtifact>) -> Vec<ProjectArtifacts> {
+    let mut projects: HashMap<Option<PathBuf>, Vec<DevArtifact>> = HashMap::new();
+    for artifact in artifacts {
+        projects
+            .entry(artifact.project.clone())
+            .or_default()
+            .push(artifact);
+    }
+    let mut projects: Vec<ProjectArtifacts> = projects
+        .into_iter()
+        .map(|(project, mut artifacts)| {
+            artifacts.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+            ProjectArtifacts {
+                project,
+                total_size: artifacts.iter().map(|a| a.size).sum(),
+                artifacts,
+            }
+        })
+        .collect();
+    projects.sort_by(|a, b| {
+        b.total_size
+            .cmp(&a.total_size)
+            .then_with(|| a.project.cmp(&b.project))
+    });
+    projects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn write(path: &Path, size: usize) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, vec![0u8; size]).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_finds_artifacts_per_project() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        write(&root.join("web/package.json"), 10);
+        write(
+            &root.join("web/node_modules/a/node_modules/b/index.js"),
+            300,
+        );
+        write(&root.join("web/node_modules/a/index.js"), 200);
+        write(&root.join("tool/Cargo.toml"), 10);
+        write(&root.join("tool/target/debug/tool"), 1000);
+        write(&root.join("tool/.venv/pyvenv.cfg"), 5);
+        // Not next to a build file, so not a build directory
+        write(&root.join("notes/target/plan.txt"), 50);
+        write(&root.join("home/.gradle/caches/jars/x.jar"), 400);
+
+        let report = DevArtifactFinder::new()
+            .find(root, &CancellationToken::new())
+            .await
+            .unwrap();
+        assert_eq!(report.total_size, 1905);
+        assert_eq!(report.projects.len(), 3);
+
+        let tool = &report.projects[0];
+        assert_eq!(tool.project.as_deref(), Some(root.join("tool").as_path()));
+        assert_eq!(tool.total_size, 1005);
+        assert_eq!(tool.artifacts[0].kind, DevArtifactKind::CargoTarget);
+        assert_eq!(tool.artifacts[1].kind, DevArtifactKind::PythonVirtualenv);
+
+        let web = &report.projects[1];
+        assert_eq!(web.artifacts.len(), 1);
+        assert_eq!(web.artifacts[0].size, 500);
+
+        assert_eq!(report.projects[2].project, None);
+        assert_eq!(
+            report.projects[2].artifacts[0].kind,
+            DevArtifactKind::GradleCache
+        );
+    }
+}
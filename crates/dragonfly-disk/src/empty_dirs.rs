@@ -0,0 +1,280 @@
+//! Empty directory detection
+//!
+//! Uninstallers, build tools, and sync clients often leave behind folder
+//! scaffolding with nothing in it. Such trees take no space but clutter scan
+//! results and file pickers. This module finds the topmost directory of each
+//! empty tree; a directory counts as empty when it holds nothing but empty
+//! directories and Finder's `.DS_Store` files. It can also list zero-byte
+//! files, the other kind of clutter that takes no space.
+
+use crate::walk::{skipped_from_walk_error, walker};
+use dragonfly_core::domain::protection::{PathProtection, SkippedPath};
+use dragonfly_core::domain::scan_options::ScanOptions;
+use dragonfly_core::error::{Error, Result};
+use dragonfly_core::ports::CancellationToken;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Files that don't keep a directory from counting as empty
+const IGNORED_FILES: &[&str] = &[".DS_Store"];
+
+/// Empty files that mean something by being there, such as a Python
+/// package's `__init__.py` or a `.gitkeep` holding a folder in a repository
+const MARKER_FILES: &[&str] = &[
+    "__init__.py",
+    "py.typed",
+    ".gitkeep",
+    ".keep",
+    ".nojekyll",
+    ".localized",
+    ".metadata_never_index",
+];
+
+/// A file holding no bytes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EmptyFile {
+    /// Path of the file
+    pub path: PathBuf,
+    /// Protection level of the file
+    pub protection: PathProtection,
+}
+
+/// A directory tree containing no files
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EmptyTree {
+    /// Topmost empty directory
+    pub path: PathBuf,
+    /// Number of directories in the tree, including the top one
+    pub dirs: usize,
+    /// Levels of nesting, 1 for a single empty directory
+    pub depth: usize,
+    /// Protection level of the top directory
+    pub protection: PathProtection,
+}
+
+/// Empty directory trees found under a directory
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EmptyDirsReport {
+    /// Directory that was searched
+    pub root: PathBuf,
+    /// Empty trees, deepest first
+    pub trees: Vec<EmptyTree>,
+    /// Zero-byte files, by path, if they were asked for
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<EmptyFile>,
+    /// Directories that couldn't be listed, and so aren't reported as empty
+    pub skipped: Vec<SkippedPath>,
+    /// Whether the search was cancelled before completing (results are partial)
+    pub interrupted: bool,
+}
+
+/// Finds empty directory trees
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EmptyDirFinder {
+    /// Whether zero-byte files are listed too
+    files: bool,
+}
+
+impl EmptyDirFinder {
+    /// Create a new empty directory finder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also list zero-byte files, other than [`MARKER_FILES`] and the
+    /// `.DS_Store` files empty trees may hold
+    pub fn with_files(mut self, files: bool) -> Self {
+        self.files = files;
+        self
+    }
+
+    /// Search `root` for empty directory trees
+    ///
+    /// `root` itself is never reported. Hidden files count as content, and
+    /// directories that can't be read are assumed not to be empty.
+    pub async fn find(&self, root: &Path, cancel: &CancellationToken) -> Result<EmptyDirsReport> {
+        if !root.is_dir() {
+            return Err(Error::NotFound(format!(
+                "Directory does not exist: {}",
+                root.display()
+            )));
+        }
+
+        let mut report = EmptyDirsReport {
+            root: root.to_path_buf(),
+            ..Default::default()
+        };
+        let mut dirs = Vec::new();
+        let mut occupied = HashSet::new();
+        let options = ScanOptions::new().with_hidden(true);
+        for entry in walker(root, &options) {
+            if cancel.is_cancelled() {
+                report.interrupted = true;
+                break;
+            }
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    report.skipped.extend(skipped_from_walk_error(&err));
+                    if let Some(path) = err.path() {
+                        mark_occupied(&mut occupied, root, path);
+                    }
+                    continue;
+                }
+            };
+            if entry.depth() == 0 {
+                continue;
+            }
+
+            let path = entry.path();
+            if entry.file_type().is_dir() {
+                dirs.push(path);
+            } else if !is_ignored(&path) {
+                if let Some(parent) = path.parent() {
+                    mark_occupied(&mut occupied, root, parent);
+                }
+                if self.files
+                    && entry.file_type().is_file()
+                    && !is_marker(&path)
+                    && entry.metadata().is_ok_and(|metadata| metadata.len() == 0)
+                {
+                    report.files.push(EmptyFile {
+                        protection: PathProtection::classify(&path.to_string_lossy()),
+                        path,
+                    });
+                }
+            }
+        }
+        // A partial walk can't tell empty directories from unvisited ones
+        if report.interrupted {
+            return Ok(report);
+        }
+
+        let mut trees: HashMap<&Path, (usize, usize)> = HashMap::new();
+        for dir in dirs.iter().filter(|dir| !occupied.contains(*dir)) {
+            let mut top = dir.as_path();
+            let mut depth = 1;
+            while let Some(parent) = top
+                .parent()
+                .filter(|p| *p != root && !occupied.contains(*p))
+            {
+                top = parent;
+                depth += 1;
+            }
+            let tree = trees.entry(top).or_default();
+            tree.0 += 1;
+            tree.1 = tree.1.max(depth);
+        }
+
+        report.trees = trees
+            .into_iter()
+            .map(|(path, (dirs, depth))| EmptyTree {
+                protection: PathProtection::classify(&path.to_string_lossy()),
+                path: path.to_path_buf(),
+                dirs,
+                depth,
+            })
+            .collect();
+        report
+            .trees
+            .sort_by(|a, b| b.depth.cmp(&a.depth).then_with(|| a.path.cmp(&b.path)));
+        report.files.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(report)
+    }
+}
+
+/// Whether a file doesn't count as directory content
+fn is_ignored(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| IGNORED_FILES.iter().any(|ignored| name == *ignored))
+}
+
+/// Whether an empty file is there on purpose
+fn is_marker(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| MARKER_FILES.iter().any(|marker| name == *marker))
+}
+
+/// Mark `dir` and its ancestors up to `root` as having content
+fn mark_occupied(occupied: &mut HashSet<PathBuf>, root: &Path, dir: &Path) {
+    for ancestor in dir.ancestors() {
+        if !ancestor.starts_with(root) || !occupied.insert(ancestor.to_path_buf()) {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_find_reports_topmost_empty_trees() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("old/build/intermediates/debug")).unwrap();
+        fs::create_dir_all(root.join("old/build/outputs")).unwrap();
+        fs::write(root.join("old/.DS_Store"), b"finder").unwrap();
+        fs::create_dir_all(root.join("project/src/empty")).unwrap();
+        fs::write(root.join("project/src/main.rs"), b"fn main() {}").unwrap();
+        fs::create_dir_all(root.join("config/.hidden")).unwrap();
+        fs::write(root.join("config/.hidden/settings"), b"x").unwrap();
+
+        let report = EmptyDirFinder::new()
+            .find(root, &CancellationToken::new())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            report.trees,
+            vec![
+                EmptyTree {
+                    path: root.join("old"),
+                    dirs: 5,
+                    depth: 4,
+                    protection: PathProtection::None,
+                },
+                EmptyTree {
+                    path: root.join("project/src/empty"),
+                    dirs: 1,
+                    depth: 1,
+                    protection: PathProtection::None,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_lists_zero_byte_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("pkg")).unwrap();
+        fs::write(root.join("pkg/__init__.py"), b"").unwrap();
+        fs::write(root.join("pkg/empty.log"), b"").unwrap();
+        fs::write(root.join("notes.txt"), b"x").unwrap();
+        fs::write(root.join(".DS_Store"), b"").unwrap();
+
+        let report = EmptyDirFinder::new()
+            .find(root, &CancellationToken::new())
+            .await
+            .unwrap();
+        assert!(report.files.is_empty());
+
+        let report = EmptyDirFinder::new()
+            .with_files(true)
+            .find(root, &CancellationToken::new())
+            .await
+            .unwrap();
+        assert_eq!(
+            report.files,
+            vec![EmptyFile {
+                path: root.join("pkg/empty.log"),
+                protection: PathProtection::None,
+            }]
+        );
+        assert!(report.trees.is_empty());
+    }
+}
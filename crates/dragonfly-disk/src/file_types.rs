@@ -0,0 +1,191 @@
+//! Grouping of files by kind
+//!
+//! A list of the largest files doesn't show that a folder is mostly videos
+//! or that caches add up to gigabytes across thousands of small files. This
+//! module sorts files into a handful of broad categories, by where they live
+//! (caches) and otherwise by extension, and totals each category.
+
+use dragonfly_core::domain::entities::FileEntity;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Directory names whose contents are caches, wherever they appear
+const CACHE_DIRS: &[&str] = &[
+    "Caches",
+    ".cache",
+    "cache",
+    "__pycache__",
+    "DerivedData",
+    ".gradle",
+    ".npm",
+];
+
+/// Broad kind of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileCategory {
+    /// Movies and screen recordings
+    Video,
+    /// Photos, pictures, and raw camera files
+    Images,
+    /// Music, podcasts, and recordings
+    Audio,
+    /// Compressed archives
+    Archives,
+    /// Disk images and installers
+    DiskImages,
+    /// Text documents, spreadsheets, presentations, and PDFs
+    Documents,
+    /// Source code and project files
+    Code,
+    /// Anything inside a cache directory, whatever its extension
+    Caches,
+    /// Everything else
+    Other,
+}
+
+impl FileCategory {
+    /// Categorize a file from its path
+    pub fn of(path: &str) -> Self {
+        let path = Path::new(path);
+        let in_cache = path
+            .parent()
+            .into_iter()
+            .flat_map(Path::iter)
+            .any(|component| CACHE_DIRS.iter().any(|dir| component == *dir));
+        if in_cache {
+            return Self::Caches;
+        }
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "mp4" | "mov" | "m4v" | "mkv" | "avi" | "wmv" | "webm" | "mpg" | "mpeg" | "flv" => {
+                Self::Video
+            }
+            "jpg" | "jpeg" | "png" | "gif" | "heic" | "heif" | "tif" | "tiff" | "bmp" | "webp"
+            | "raw" | "cr2" | "cr3" | "nef" | "arw" | "dng" | "psd" | "svg" => Self::Images,
+            "mp3" | "m4a" | "aac" | "wav" | "flac" | "aiff" | "aif" | "ogg" | "opus" | "alac" => {
+                Self::Audio
+            }
+            "zip" | "tar" | "gz" | "tgz" | "bz2" | "xz" | "zst" | "7z" | "rar" | "xip" => {
+                Self::Archives
+            }
+            "dmg" | "iso" | "img" | "pkg" | "sparseimage" | "sparsebundle" | "vmdk" | "qcow2" => {
+                Self::DiskImages
+            }
+            "pdf" | "doc" | "docx" | "xls" | "xlsx" | "ppt" | "pptx" | "pages" | "numbers"
+            | "key" | "txt" | "rtf" | "md" | "odt" | "ods" | "epub" => Self::Documents,
+            "rs" | "c" | "h" | "cpp" | "hpp" | "m" | "mm" | "swift" | "py" | "js" | "ts"
+            | "tsx" | "jsx" | "go" | "java" | "kt" | "rb" | "php" | "cs" | "sh" | "json"
+            | "toml" | "yaml" | "yml" | "html" | "css" | "sql" => Self::Code,
+            _ => Self::Other,
+        }
+    }
+
+    /// Identifier of the category, as it appears in JSON output
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Video => "video",
+            Self::Images => "images",
+            Self::Audio => "audio",
+            Self::Archives => "archives",
+            Self::DiskImages => "disk_images",
+            Self::Documents => "documents",
+            Self::Code => "code",
+            Self::Caches => "caches",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// Files of one category and the space they use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CategoryUsage {
+    /// Category
+    pub category: FileCategory,
+    /// Number of files
+    pub file_count: u64,
+    /// Combined size in bytes
+    pub total_size: u64,
+}
+
+/// Total `files` per category, largest first
+pub(crate) fn by_category(files: &[FileEntity]) -> Vec<CategoryUsage> {
+    let mut totals: HashMap<FileCategory, (u64, u64)> = HashMap::new();
+    for file in files {
+        let (count, size) = totals.entry(FileCategory::of(&file.path)).or_default();
+        *count += 1;
+        *size += file.size;
+    }
+    let mut usage: Vec<CategoryUsage> = totals
+        .into_iter()
+        .map(|(category, (file_count, total_size))| CategoryUsage {
+            category,
+            file_count,
+            total_size,
+        })
+        .collect();
+    usage.sort_by(|a, b| {
+        b.total_size
+            .cmp(&a.total_size)
+            .then_with(|| b.file_count.cmp(&a.file_count))
+    });
+    usage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_categories() {
+        assert_eq!(
+            FileCategory::of("/Users/me/Movies/trip.MOV"),
+            FileCategory::Video
+        );
+        assert_eq!(
+            FileCategory::of("/Users/me/a.tar.gz"),
+            FileCategory::Archives
+        );
+        assert_eq!(
+            FileCategory::of("/Users/me/src/main.rs"),
+            FileCategory::Code
+        );
+        assert_eq!(
+            FileCategory::of("/Users/me/Library/Caches/app/thumb.png"),
+            FileCategory::Caches
+        );
+        assert_eq!(FileCategory::of("/Users/me/README"), FileCategory::Other);
+    }
+
+    #[test]
+    fn test_as_str_matches_json() {
+        let category = FileCategory::DiskImages;
+        assert_eq!(serde_json::to_value(category).unwrap(), category.as_str());
+    }
+
+    #[test]
+    fn test_by_category_sorts_by_size() {
+        let files = [
+            FileEntity::new("/a/one.mp4".into(), 500),
+            FileEntity::new("/a/two.mkv".into(), 700),
+            FileEntity::new("/a/photo.jpg".into(), 100),
+            FileEntity::new("/a/.cache/blob".into(), 300),
+        ];
+        let usage = by_category(&files);
+        assert_eq!(usage.len(), 3);
+        assert_eq!(
+            usage[0],
+            CategoryUsage {
+                category: FileCategory::Video,
+                file_count: 2,
+                total_size: 1200
+            }
+        );
+        assert_eq!(usage[1].category, FileCategory::Caches);
+        assert_eq!(usage[2].category, FileCategory::Images);
+    }
+}
@@ -0,0 +1,191 @@
+//! Directories growing past a threshold while they are watched
+//!
+//! `dragonfly disk watch` keeps the size index current from the changes a
+//! [`FileSystemWatcher`] reports, and [`GrowthMonitor`] tells which of the
+//! directories a few levels below the watched root grew by more than a set
+//! amount since they were first touched. Each report moves that directory's
+//! baseline up, so steady growth is reported once per threshold crossed
+//! rather than on every change.
+//!
+//! [`FileSystemWatcher`]: dragonfly_core::ports::FileSystemWatcher
+
+use crate::index::SizeIndex;
+use crate::snapshot_diff::PathGrowth;
+use dragonfly_core::domain::scan_options::ScanOptions;
+use dragonfly_core::error::Result;
+use dragonfly_core::ports::FsChange;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Reports directories whose size grew by at least a threshold
+#[derive(Debug)]
+pub struct GrowthMonitor {
+    root: PathBuf,
+    depth: usize,
+    threshold: u64,
+    baselines: HashMap<PathBuf, u64>,
+}
+
+impl GrowthMonitor {
+    /// Watch directories `depth` levels below `root` (`0` for the root
+    /// itself) for growth of at least `threshold` bytes
+    ///
+    /// `root` must be canonical, as watchers report canonical paths.
+    pub fn new(root: PathBuf, depth: usize, threshold: u64) -> Self {
+        Self {
+            root,
+            depth,
+            threshold: threshold.max(1),
+            baselines: HashMap::new(),
+        }
+    }
+
+    /// Apply `changes` to `index` and return the directories that have now
+    /// grown by the threshold, largest growth first
+    ///
+    /// `before` is the size the growth is measured from: the size when the
+    /// directory was first touched, when it was last reported, or the
+    /// smallest it has been since, whichever came last.
+    pub fn apply(
+        &mut self,
+        index: &mut SizeIndex,
+        changes: &[FsChange],
+        options: &ScanOptions,
+    ) -> Result<Vec<PathGrowth>> {
+        let mut touched = BTreeSet::new();
+        for change in changes {
+            let Ok(relative) = change.path.strip_prefix(&self.root) else {
+                continue;
+            };
+            let levels = relative.components().count();
+            // Anything below may have changed, so every watched directory
+            // there is checked, including ones that are gone now
+            if change.subtree && levels < self.depth {
+                touched.extend(dirs_below(&change.path, self.depth - levels));
+                touched.extend(
+                    self.baselines
+                        .keys()
+                        .filter(|dir| dir.starts_with(&change.path))
+                        .cloned(),
+                );
+            }
+            touched.extend(self.directory_of(&change.path));
+        }
+        for dir in &touched {
+            if !self.baselines.contains_key(dir) {
+                let (_, size) = index.totals(dir)?;
+                self.baselines.insert(dir.clone(), size);
+            }
+        }
+        index.apply_changes(changes, options)?;
+
+        let mut grown = Vec::new();
+        for dir in touched {
+            let (_, after) = index.totals(&dir)?;
+            let baseline = self.baselines.entry(dir.clone()).or_default();
+            if after >= baseline.saturating_add(self.threshold) {
+                grown.push(PathGrowth {
+                    path: dir,
+                    before: *baseline,
+                    after,
+                });
+                *baseline = after;
+            } else if after < *baseline {
+                *baseline = after;
+            }
+        }
+        grown.sort_by_key(|change| std::cmp::Reverse(change.growth()));
+        Ok(grown)
+    }
+
+    /// The watched directory a changed path belongs to
+    ///
+    /// Changes above the watched depth, such as a file directly in
+    /// `~/Downloads` when watching two levels below `~`, count towards the
+    /// directory holding them. The root itself is only watched at depth `0`,
+    /// since its size would repeat the growth of everything below it.
+    fn directory_of(&self, path: &Path) -> Option<PathBuf> {
+        let relative = path.strip_prefix(&self.root).ok()?;
+        let components: Vec<_> = relative.components().collect();
+        let depth = if components.len() > self.depth {
+            self.depth
+        } else {
+            components.len().saturating_sub(1)
+        };
+        if depth == 0 && self.depth > 0 {
+            return None;
+        }
+        Some(
+            self.root
+                .join(components[..depth].iter().collect::<PathBuf>()),
+        )
+    }
+}
+
+/// Directories exactly `levels` below `path`, not following symlinks
+fn dirs_below(path: &Path, levels: usize) -> Vec<PathBuf> {
+    if levels == 0 {
+        return vec![path.to_path_buf()];
+    }
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .flat_map(|entry| dirs_below(&entry.path(), levels - 1))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dragonfly_core::ports::CancellationToken;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_reports_growth_past_threshold() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = fs::canonicalize(temp_dir.path()).unwrap().join("watched");
+        fs::create_dir_all(root.join("downloads")).unwrap();
+        fs::create_dir_all(root.join("docs")).unwrap();
+        let options = ScanOptions::default();
+        let mut index = SizeIndex::open(&temp_dir.path().join("index.db")).unwrap();
+        index
+            .index_root(&root, &options, &CancellationToken::new())
+            .unwrap();
+        let mut monitor = GrowthMonitor::new(root.clone(), 1, 1000);
+
+        fs::write(root.join("downloads/a.bin"), vec![0u8; 600]).unwrap();
+        fs::write(root.join("docs/note.txt"), vec![0u8; 10]).unwrap();
+        let changes = [
+            FsChange::path(root.join("downloads/a.bin")),
+            FsChange::path(root.join("docs/note.txt")),
+        ];
+        let grown = monitor.apply(&mut index, &changes, &options).unwrap();
+        assert!(grown.is_empty());
+
+        fs::write(root.join("downloads/b.bin"), vec![0u8; 600]).unwrap();
+        let changes = [FsChange::path(root.join("downloads/b.bin"))];
+        let grown = monitor.apply(&mut index, &changes, &options).unwrap();
+        assert_eq!(grown.len(), 1);
+        assert_eq!(grown[0].path, root.join("downloads"));
+        assert_eq!((grown[0].before, grown[0].after), (0, 1200));
+
+        // The baseline moved up with the report
+        fs::write(root.join("downloads/c.bin"), vec![0u8; 600]).unwrap();
+        let changes = [FsChange::path(root.join("downloads/c.bin"))];
+        assert!(monitor
+            .apply(&mut index, &changes, &options)
+            .unwrap()
+            .is_empty());
+
+        // A change to the whole tree checks every watched directory
+        fs::write(root.join("docs/big.bin"), vec![0u8; 2000]).unwrap();
+        let changes = [FsChange::subtree(&root)];
+        let grown = monitor.apply(&mut index, &changes, &options).unwrap();
+        assert_eq!(grown.len(), 1);
+        assert_eq!(grown[0].path, root.join("docs"));
+    }
+}
@@ -0,0 +1,445 @@
+//! Usage history
+//!
+//! Scans and free space measurements are recorded in
+//! `~/.dragonfly/history.db` as they happen, so growth can be charted over
+//! weeks and months without anything running in the background. [`Trend`]
+//! turns a series of measurements into daily points and tells whether growth
+//! is speeding up. The largest files of the latest scan of each path are kept
+//! too, so the next scan can say which of them are new or changed
+//! ([`LargestFiles`]).
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use dragonfly_core::error::{Error, Result};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS scans (
+        recorded_at TEXT NOT NULL,
+        path TEXT NOT NULL,
+        total_size INTEGER NOT NULL,
+        file_count INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS scans_by_path ON scans (path, recorded_at);
+    CREATE TABLE IF NOT EXISTS free_space (
+        recorded_at TEXT NOT NULL,
+        volume TEXT NOT NULL,
+        available INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS free_space_by_volume ON free_space (volume, recorded_at);
+    CREATE TABLE IF NOT EXISTS largest_files (
+        recorded_at TEXT NOT NULL,
+        path TEXT NOT NULL,
+        file TEXT NOT NULL,
+        size INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS largest_files_by_path ON largest_files (path);
+";
+
+/// Files kept per scanned path for [`LargestFiles`]
+pub const TRACKED_FILES: usize = 1000;
+
+/// Recent growth has to exceed earlier growth by this factor to count as
+/// accelerating, so day-to-day noise doesn't raise the alarm
+const ACCELERATION_FACTOR: f64 = 1.25;
+
+/// A measurement at a point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Sample {
+    /// When the value was measured
+    pub at: DateTime<Utc>,
+    /// Measured bytes
+    pub value: u64,
+}
+
+/// Recorded scans and free space measurements
+#[derive(Debug)]
+pub struct History {
+    conn: Connection,
+}
+
+impl History {
+    /// Default history location (`~/.dragonfly/history.db`)
+    pub fn default_path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_else(|| PathBuf::from("~"))
+            .join(".dragonfly")
+            .join("history.db")
+    }
+
+    /// Open the history at `path`, creating it if needed
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path).map_err(db_error)?;
+        conn.execute_batch(SCHEMA).map_err(db_error)?;
+        Ok(Self { conn })
+    }
+
+    /// Record the result of a complete scan of `path`
+    pub fn record_scan(&self, path: &Path, total_size: u64, file_count: u64) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO scans (recorded_at, path, total_size, file_count) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    timestamp(Utc::now()),
+                    path.to_string_lossy(),
+                    total_size as i64,
+                    file_count as i64
+                ],
+            )
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    /// Record the free space of the volume mounted at `volume`
+    pub fn record_free_space(&self, volume: &Path, available: u64) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO free_space (recorded_at, volume, available) VALUES (?1, ?2, ?3)",
+                params![
+                    timestamp(Utc::now()),
+                    volume.to_string_lossy(),
+                    available as i64
+                ],
+            )
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    /// Paths scanned since `since`, most recently scanned first
+    pub fn scanned_paths(&self, since: DateTime<Utc>) -> Result<Vec<PathBuf>> {
+        self.strings(
+            "SELECT path FROM scans WHERE recorded_at >= ?1
+             GROUP BY path ORDER BY MAX(recorded_at) DESC",
+            since,
+        )
+        .map(|paths| paths.into_iter().map(PathBuf::from).collect())
+    }
+
+    /// Volumes measured since `since`, most recently measured first
+    pub fn volumes(&self, since: DateTime<Utc>) -> Result<Vec<PathBuf>> {
+        self.strings(
+            "SELECT volume FROM free_space WHERE recorded_at >= ?1
+             GROUP BY volume ORDER BY MAX(recorded_at) DESC",
+            since,
+        )
+        .map(|volumes| volumes.into_iter().map(PathBuf::from).collect())
+    }
+
+    /// Total size of `path` at each scan since `since`, oldest first
+    pub fn scan_sizes(&self, path: &Path, since: DateTime<Utc>) -> Result<Vec<Sample>> {
+        self.samples(
+            "SELECT recorded_at, total_size FROM scans
+             WHERE path = ?1 AND recorded_at >= ?2 ORDER BY recorded_at",
+            path,
+            since,
+        )
+    }
+
+    /// Free space of `volume` at each measurement since `since`, oldest first
+    pub fn free_space(&self, volume: &Path, since: DateTime<Utc>) -> Result<Vec<Sample>> {
+        self.samples(
+            "SELECT recorded_at, available FROM free_space
+             WHERE volume = ?1 AND recorded_at >= ?2 ORDER BY recorded_at",
+            volume,
+            since,
+        )
+    }
+
+    /// Replace the largest files kept for `path` with the largest
+    /// [`TRACKED_FILES`] of `files` (file name and size)
+    pub fn record_largest_files(&self, path: &Path, files: &[(String, u64)]) -> Result<()> {
+        let mut largest: Vec<&(String, u64)> = files.iter().collect();
+        largest.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+        largest.truncate(TRACKED_FILES);
+
+        let path = path.to_string_lossy();
+        let recorded_at = timestamp(Utc::now());
+        let tx = self.conn.unchecked_transaction().map_err(db_error)?;
+        tx.execute("DELETE FROM largest_files WHERE path = ?1", params![path])
+            .map_err(db_error)?;
+        {
+            let mut insert = tx
+                .prepare(
+                    "INSERT INTO largest_files (recorded_at, path, file, size) VALUES (?1, ?2, ?3, ?4)",
+                )
+                .map_err(db_error)?;
+            for (file, size) in largest {
+                insert
+                    .execute(params![recorded_at, path, file, *size as i64])
+                    .map_err(db_error)?;
+            }
+        }
+        tx.commit().map_err(db_error)
+    }
+
+    /// Largest files kept from the latest scan of `path`, if it was scanned
+    pub fn largest_files(&self, path: &Path) -> Result<Option<LargestFiles>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT recorded_at, file, size FROM largest_files WHERE path = ?1")
+            .map_err(db_error)?;
+        let rows = statement
+            .query_map(params![path.to_string_lossy()], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            })
+            .map_err(db_error)?;
+
+        let mut recorded_at = None;
+        let mut sizes = HashMap::new();
+        for row in rows {
+            let (at, file, size) = row.map_err(db_error)?;
+            if recorded_at.is_none() {
+                recorded_at = Some(parse_timestamp(&at)?);
+            }
+            sizes.insert(file, size.max(0) as u64);
+        }
+        Ok(recorded_at.map(|recorded_at| LargestFiles { recorded_at, sizes }))
+    }
+
+    fn strings(&self, sql: &str, since: DateTime<Utc>) -> Result<Vec<String>> {
+        let mut statement = self.conn.prepare(sql).map_err(db_error)?;
+        let rows = statement
+            .query_map(params![timestamp(since)], |row| row.get::<_, String>(0))
+            .map_err(db_error)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(db_error)
+    }
+
+    fn samples(&self, sql: &str, key: &Path, since: DateTime<Utc>) -> Result<Vec<Sample>> {
+        let mut statement = self.conn.prepare(sql).map_err(db_error)?;
+        let rows = statement
+            .query_map(params![key.to_string_lossy(), timestamp(since)], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })
+            .map_err(db_error)?;
+
+        let mut samples = Vec::new();
+        for row in rows {
+            let (at, value) = row.map_err(db_error)?;
+            samples.push(Sample {
+                at: parse_timestamp(&at)?,
+                value: value.max(0) as u64,
+            });
+        }
+        Ok(samples)
+    }
+}
+
+/// How a file changed since the previous scan
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileChange {
+    /// Not among the largest files last time
+    New,
+    /// Bigger than last time
+    Grown,
+    /// Smaller than last time
+    Shrunk,
+    /// Same size as last time
+    Unchanged,
+}
+
+/// The largest files of an earlier scan, to compare a new scan against
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LargestFiles {
+    /// When the earlier scan finished
+    pub recorded_at: DateTime<Utc>,
+    sizes: HashMap<String, u64>,
+}
+
+impl LargestFiles {
+    /// Size of `file` in the earlier scan, if it was among the largest
+    pub fn previous_size(&self, file: &str) -> Option<u64> {
+        self.sizes.get(file).copied()
+    }
+
+    /// How `file`, now `size` bytes, changed since the earlier scan
+    pub fn change(&self, file: &str, size: u64) -> FileChange {
+        match self.previous_size(file) {
+            None => FileChange::New,
+            Some(previous) if size > previous => FileChange::Grown,
+            Some(previous) if size < previous => FileChange::Shrunk,
+            Some(_) => FileChange::Unchanged,
+        }
+    }
+}
+
+/// How a measured value developed over a period
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Trend {
+    /// Last measurement of each day, oldest first
+    pub daily: Vec<Sample>,
+    /// Change from the first to the last measurement in bytes
+    pub change: i64,
+    /// Average change per day in the first half of the period
+    pub earlier_per_day: f64,
+    /// Average change per day in the second half of the period
+    pub recent_per_day: f64,
+}
+
+impl Trend {
+    /// Trend of `samples` (oldest first), or `None` with fewer than two days
+    /// of data
+    pub fn of(samples: &[Sample]) -> Option<Self> {
+        let daily = daily(samples);
+        let (first, last) = (daily.first()?, daily.last()?);
+        if daily.len() < 2 {
+            return None;
+        }
+        let middle = &daily[daily.len() / 2];
+        Some(Self {
+            change: last.value as i64 - first.value as i64,
+            earlier_per_day: per_day(first, middle),
+            recent_per_day: per_day(middle, last),
+            daily,
+        })
+    }
+
+    /// Average change per day over the whole period
+    pub fn per_day(&self) -> f64 {
+        match (self.daily.first(), self.daily.last()) {
+            (Some(first), Some(last)) => per_day(first, last),
+            _ => 0.0,
+        }
+    }
+
+    /// Whether the value is moving in the same direction faster than before
+    ///
+    /// Needs at least three days of data so each half has its own span.
+    pub fn accelerating(&self) -> bool {
+        self.daily.len() >= 3
+            && self.recent_per_day.signum() == self.earlier_per_day.signum()
+            && self.recent_per_day.abs() > self.earlier_per_day.abs() * ACCELERATION_FACTOR
+    }
+}
+
+/// Last sample of each calendar day (UTC)
+fn daily(samples: &[Sample]) -> Vec<Sample> {
+    let mut daily: Vec<Sample> = Vec::new();
+    for sample in samples {
+        let day = sample.at.date_naive();
+        match daily.last_mut() {
+            Some(last) if last.at.date_naive() == day => *last = *sample,
+            _ => daily.push(*sample),
+        }
+    }
+    daily
+}
+
+fn per_day(from: &Sample, to: &Sample) -> f64 {
+    let days = (to.at - from.at).num_seconds() as f64 / 86_400.0;
+    if days <= 0.0 {
+        return 0.0;
+    }
+    (to.value as f64 - from.value as f64) / days
+}
+
+fn timestamp(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn parse_timestamp(value: &str) -> Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|date| date.with_timezone(&Utc))
+        .map_err(|e| Error::Internal(format!("Invalid history timestamp {}: {}", value, e)))
+}
+
+fn db_error(error: rusqlite::Error) -> Error {
+    Error::Internal(format!("History database error: {}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use tempfile::TempDir;
+
+    fn sample(days_ago: i64, value: u64) -> Sample {
+        Sample {
+            at: Utc::now() - Duration::days(days_ago),
+            value,
+        }
+    }
+
+    #[test]
+    fn test_record_and_query() {
+        let temp_dir = TempDir::new().unwrap();
+        let history = History::open(&temp_dir.path().join("state/history.db")).unwrap();
+        let home = Path::new("/Users/me");
+        history.record_scan(home, 1000, 10).unwrap();
+        history.record_scan(home, 1500, 12).unwrap();
+        history.record_free_space(Path::new("/"), 5000).unwrap();
+
+        let since = Utc::now() - Duration::days(1);
+        assert_eq!(history.scanned_paths(since).unwrap(), vec![home]);
+        let sizes: Vec<u64> = history
+            .scan_sizes(home, since)
+            .unwrap()
+            .iter()
+            .map(|s| s.value)
+            .collect();
+        assert_eq!(sizes, vec![1000, 1500]);
+        assert_eq!(history.free_space(Path::new("/"), since).unwrap().len(), 1);
+        assert!(history
+            .scan_sizes(home, Utc::now() + Duration::days(1))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_largest_files_change() {
+        let temp_dir = TempDir::new().unwrap();
+        let history = History::open(&temp_dir.path().join("history.db")).unwrap();
+        let home = Path::new("/Users/me");
+        assert!(history.largest_files(home).unwrap().is_none());
+
+        let files = |entries: &[(&str, u64)]| -> Vec<(String, u64)> {
+            entries.iter().map(|(f, s)| (f.to_string(), *s)).collect()
+        };
+        history
+            .record_largest_files(home, &files(&[("a", 100), ("b", 50), ("c", 10)]))
+            .unwrap();
+        let previous = history.largest_files(home).unwrap().unwrap();
+        assert_eq!(previous.change("a", 150), FileChange::Grown);
+        assert_eq!(previous.change("b", 20), FileChange::Shrunk);
+        assert_eq!(previous.change("c", 10), FileChange::Unchanged);
+        assert_eq!(previous.change("d", 10), FileChange::New);
+        assert_eq!(previous.previous_size("a"), Some(100));
+
+        // A new scan replaces the kept files
+        history
+            .record_largest_files(home, &files(&[("d", 10)]))
+            .unwrap();
+        let latest = history.largest_files(home).unwrap().unwrap();
+        assert_eq!(latest.previous_size("a"), None);
+        assert_eq!(latest.change("d", 10), FileChange::Unchanged);
+    }
+
+    #[test]
+    fn test_trend_detects_acceleration() {
+        // Two samples on the same day collapse into the later one
+        let samples = [
+            sample(10, 100),
+            sample(6, 140),
+            sample(5, 150),
+            sample(5, 150),
+            sample(0, 400),
+        ];
+        let trend = Trend::of(&samples).unwrap();
+        assert_eq!(trend.daily.len(), 4);
+        assert_eq!(trend.change, 300);
+        assert!(trend.accelerating());
+
+        let steady = [sample(4, 100), sample(2, 120), sample(0, 140)];
+        assert!(!Trend::of(&steady).unwrap().accelerating());
+        assert!(Trend::of(&[sample(0, 1)]).is_none());
+    }
+}
@@ -0,0 +1,707 @@
+//! Persistent size index
+//!
+//! The index stores the size of every file under a set of roots in
+//! `~/.dragonfly/index.db`, so analysis commands can answer from it instead of
+//! walking the tree again. `dragonfly index --daemon` keeps it current by
+//! applying the changes a [`FileSystemWatcher`] reports.
+//!
+//! Without the daemon, [`SizeIndex::refresh`] catches up incrementally: it
+//! keeps each directory's modification time and only re-reads directories
+//! whose time changed, which happens when entries are added, removed, or
+//! renamed in them. A file that grows in place doesn't change its directory's
+//! time, so such growth waits for the daemon or the next full walk.
+//!
+//! [`FileSystemWatcher`]: dragonfly_core::ports::FileSystemWatcher
+
+use crate::analyzer::AnalysisResult;
+use crate::strategies::AnalysisStrategy;
+use crate::walk::{device_id, leads_back, skipped_from_walk_error, walk_includes, walker};
+use chrono::{DateTime, Utc};
+use dragonfly_core::domain::entities::FileEntity;
+use dragonfly_core::domain::scan_options::ScanOptions;
+use dragonfly_core::domain::scan_profile::{micros_since, ScanProfile};
+use dragonfly_core::error::{Error, Result};
+use dragonfly_core::ports::{CancellationToken, FsChange};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS roots (
+        path TEXT PRIMARY KEY,
+        indexed_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS files (
+        path TEXT PRIMARY KEY,
+        size INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS files_by_size ON files (size);
+    CREATE TABLE IF NOT EXISTS dirs (
+        path TEXT PRIMARY KEY,
+        mtime INTEGER NOT NULL
+    );
+";
+
+/// A root covered by the index
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IndexedRoot {
+    /// Root directory
+    pub path: PathBuf,
+    /// When the root was last fully walked
+    pub indexed_at: DateTime<Utc>,
+    /// When the root's entries last changed
+    pub updated_at: DateTime<Utc>,
+}
+
+/// What [`SizeIndex::refresh`] had to do
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct RefreshStats {
+    /// Directories read again because they changed or are new
+    pub dirs_rescanned: u64,
+    /// Unchanged directories whose files came from the index
+    pub dirs_reused: u64,
+    /// Directories that no longer exist
+    pub dirs_removed: u64,
+    /// Whether nothing was known about the path yet, so it was walked in full
+    pub full_walk: bool,
+}
+
+/// On-disk index of file sizes under a set of roots
+#[derive(Debug)]
+pub struct SizeIndex {
+    conn: Connection,
+}
+
+impl SizeIndex {
+    /// Default index location (`~/.dragonfly/index.db`)
+    pub fn default_path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_else(|| PathBuf::from("~"))
+            .join(".dragonfly")
+            .join("index.db")
+    }
+
+    /// Open the index at `path`, creating it if needed
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path).map_err(db_error)?;
+        // WAL lets commands read while the daemon writes
+        conn.pragma_update(None, "journal_mode", "WAL")
+            .map_err(db_error)?;
+        conn.execute_batch(SCHEMA).map_err(db_error)?;
+        Ok(Self { conn })
+    }
+
+    /// Walk `root` and replace everything the index holds for it
+    ///
+    /// Returns the number of files indexed. If `cancel` fires the index is
+    /// left as it was.
+    pub fn index_root(
+        &mut self,
+        root: &Path,
+        options: &ScanOptions,
+        cancel: &CancellationToken,
+    ) -> Result<u64> {
+        let walked = walk_entries(root, options, cancel);
+        if cancel.is_cancelled() {
+            return Ok(0);
+        }
+
+        let now = Utc::now().to_rfc3339();
+        let tx = self.conn.transaction().map_err(db_error)?;
+        delete_subtree(&tx, root)?;
+        insert_files(&tx, &walked.files)?;
+        insert_dirs(&tx, &walked.dirs)?;
+        tx.execute(
+            "INSERT INTO roots (path, indexed_at, updated_at) VALUES (?1, ?2, ?2)
+             ON CONFLICT (path) DO UPDATE SET indexed_at = ?2, updated_at = ?2",
+            params![root.to_string_lossy(), now],
+        )
+        .map_err(db_error)?;
+        tx.commit().map_err(db_error)?;
+        Ok(walked.files.len() as u64)
+    }
+
+    /// Bring `root` up to date by re-reading only the directories that
+    /// changed since they were last read
+    ///
+    /// Every directory is still looked at, but unchanged ones keep their
+    /// indexed files without statting them. A path the index knows nothing
+    /// about is walked in full, and becomes a root if no indexed root covers
+    /// it. If `cancel` fires the index is left as it was and `None` is
+    /// returned.
+    pub fn refresh(
+        &mut self,
+        root: &Path,
+        options: &ScanOptions,
+        cancel: &CancellationToken,
+    ) -> Result<Option<RefreshStats>> {
+        let stored = self.dir_mtimes(root)?;
+        if stored.is_empty() {
+            self.index_root(root, options, cancel)?;
+            if cancel.is_cancelled() {
+                return Ok(None);
+            }
+            return Ok(Some(RefreshStats {
+                dirs_rescanned: self.dir_mtimes(root)?.len() as u64,
+                full_walk: true,
+                ..RefreshStats::default()
+            }));
+        }
+
+        let mut children: HashMap<&Path, Vec<&Path>> = HashMap::new();
+        for dir in stored.keys().filter(|dir| dir.as_path() != root) {
+            if let Some(parent) = dir.parent() {
+                children.entry(parent).or_default().push(dir);
+            }
+        }
+        let device = if options.one_filesystem {
+            device_id(root)
+        } else {
+            None
+        };
+
+        let covering = self
+            .covering_root(root)?
+            .map_or_else(|| root.to_path_buf(), |indexed| indexed.path);
+
+        let mut stats = RefreshStats::default();
+        let tx = self.conn.transaction().map_err(db_error)?;
+        let mut pending = vec![root.to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            if cancel.is_cancelled() {
+                return Ok(None);
+            }
+            let Some(mtime) = dir_mtime(&dir, options) else {
+                delete_subtree(&tx, &dir)?;
+                stats.dirs_removed += 1;
+                continue;
+            };
+            let known: &[&Path] = children.get(dir.as_path()).map_or(&[], Vec::as_slice);
+            if stored.get(&dir) == Some(&mtime) {
+                stats.dirs_reused += 1;
+                pending.extend(known.iter().map(|child| child.to_path_buf()));
+                continue;
+            }
+
+            stats.dirs_rescanned += 1;
+            let (files, subdirs) = read_dir_entries(root, &dir, options, device);
+            delete_direct_files(&tx, &dir)?;
+            insert_files(&tx, &files)?;
+            insert_dirs(&tx, &[(dir.to_string_lossy().to_string(), mtime)])?;
+            for gone in known
+                .iter()
+                .filter(|child| !subdirs.iter().any(|s| s == *child))
+            {
+                delete_subtree(&tx, gone)?;
+                stats.dirs_removed += 1;
+            }
+            pending.extend(subdirs);
+        }
+
+        let now = Utc::now().to_rfc3339();
+        tx.execute(
+            "INSERT INTO roots (path, indexed_at, updated_at) VALUES (?1, ?2, ?2)
+             ON CONFLICT (path) DO UPDATE SET updated_at = ?2",
+            params![covering.to_string_lossy(), now],
+        )
+        .map_err(db_error)?;
+        tx.commit().map_err(db_error)?;
+        Ok(Some(stats))
+    }
+
+    /// Recorded modification times of `path` and the directories beneath it
+    fn dir_mtimes(&self, path: &Path) -> Result<HashMap<PathBuf, i64>> {
+        let (lower, upper) = subtree_bounds(path);
+        let mut statement = self
+            .conn
+            .prepare(
+                "SELECT path, mtime FROM dirs
+                 WHERE path = ?1 OR (path >= ?2 AND path < ?3)",
+            )
+            .map_err(db_error)?;
+        let rows = statement
+            .query_map(params![path.to_string_lossy(), lower, upper], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })
+            .map_err(db_error)?;
+        let mut mtimes = HashMap::new();
+        for row in rows {
+            let (path, mtime) = row.map_err(db_error)?;
+            mtimes.insert(PathBuf::from(path), mtime);
+        }
+        Ok(mtimes)
+    }
+
+    /// Bring the index up to date with reported changes
+    ///
+    /// Each changed path is re-read from disk: missing paths are dropped,
+    /// files are re-sized, and directories (or subtree changes) are walked
+    /// again. Changes outside every indexed root are ignored. Returns the
+    /// number of distinct paths applied.
+    pub fn apply_changes(&mut self, changes: &[FsChange], options: &ScanOptions) -> Result<usize> {
+        let roots = self.roots()?;
+        let changes: HashSet<&FsChange> = changes.iter().collect();
+        let mut touched_roots = BTreeSet::new();
+        let mut applied = 0;
+
+        let tx = self.conn.transaction().map_err(db_error)?;
+        for change in changes {
+            let Some(root) = roots
+                .iter()
+                .filter(|r| change.path.starts_with(&r.path))
+                .max_by_key(|r| r.path.as_os_str().len())
+            else {
+                continue;
+            };
+            if !walk_includes(&root.path, &change.path, options) {
+                continue;
+            }
+
+            match std::fs::symlink_metadata(&change.path) {
+                Ok(metadata) if metadata.is_file() && !change.subtree => {
+                    insert_files(
+                        &tx,
+                        &[FileEntity::new(
+                            change.path.to_string_lossy().to_string(),
+                            metadata.len(),
+                        )],
+                    )?;
+                }
+                Ok(metadata) if metadata.is_dir() => {
+                    let walked = walk_entries(&change.path, options, &CancellationToken::new());
+                    delete_subtree(&tx, &change.path)?;
+                    insert_files(&tx, &walked.files)?;
+                    insert_dirs(&tx, &walked.dirs)?;
+                }
+                _ => delete_subtree(&tx, &change.path)?,
+            }
+            touched_roots.insert(root.path.to_string_lossy().to_string());
+            applied += 1;
+        }
+
+        let now = Utc::now().to_rfc3339();
+        for root in touched_roots {
+            tx.execute(
+                "UPDATE roots SET updated_at = ?2 WHERE path = ?1",
+                params![root, now],
+            )
+            .map_err(db_error)?;
+        }
+        tx.commit().map_err(db_error)?;
+        Ok(applied)
+    }
+
+    /// Roots the index covers
+    pub fn roots(&self) -> Result<Vec<IndexedRoot>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT path, indexed_at, updated_at FROM roots ORDER BY path")
+            .map_err(db_error)?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })
+            .map_err(db_error)?;
+
+        let mut roots = Vec::new();
+        for row in rows {
+            let (path, indexed_at, updated_at) = row.map_err(db_error)?;
+            roots.push(IndexedRoot {
+                path: PathBuf::from(path),
+                indexed_at: parse_timestamp(&indexed_at)?,
+                updated_at: parse_timestamp(&updated_at)?,
+            });
+        }
+        Ok(roots)
+    }
+
+    /// The indexed root containing `path`, if any
+    pub fn covering_root(&self, path: &Path) -> Result<Option<IndexedRoot>> {
+        Ok(self
+            .roots()?
+            .into_iter()
+            .filter(|root| path.starts_with(&root.path))
+            .max_by_key(|root| root.path.as_os_str().len()))
+    }
+
+    /// Files under `path` of at least `min_size` bytes, largest first
+    ///
+    /// Returns `None` if no indexed root contains `path`. The result carries
+    /// [`AnalysisStrategy::Incremental`] since it comes from stored results.
+    pub fn analysis(&self, path: &Path, min_size: u64) -> Result<Option<AnalysisResult>> {
+        let started = std::time::Instant::now();
+        if self.covering_root(path)?.is_none() {
+            return Ok(None);
+        }
+
+        let (lower, upper) = subtree_bounds(path);
+        let mut statement = self
+            .conn
+            .prepare(
+                "SELECT path, size FROM files
+                 WHERE (path = ?1 OR (path >= ?2 AND path < ?3)) AND size >= ?4
+                 ORDER BY size DESC",
+            )
+            .map_err(db_error)?;
+        let rows = statement
+            .query_map(
+                params![path.to_string_lossy(), lower, upper, min_size as i64],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)),
+            )
+            .map_err(db_error)?;
+
+        let mut files = Vec::new();
+        for row in rows {
+            let (path, size) = row.map_err(db_error)?;
+            files.push(FileEntity::new(path, size as u64));
+        }
+        let total_size = files.iter().map(|f| f.size).sum();
+        Ok(Some(AnalysisResult {
+            total_size,
+            allocated_size: None,
+            profile: ScanProfile {
+                elapsed_micros: micros_since(started),
+                files: files.len() as u64,
+                bytes: total_size,
+                ..ScanProfile::default()
+            },
+            files,
+            skipped: Vec::new(),
+            interrupted: false,
+            strategy: AnalysisStrategy::Incremental,
+        }))
+    }
+
+    /// Number of files and their combined size under `path`
+    pub fn totals(&self, path: &Path) -> Result<(u64, u64)> {
+        let (lower, upper) = subtree_bounds(path);
+        self.conn
+            .query_row(
+                "SELECT COUNT(*), COALESCE(SUM(size), 0) FROM files
+                 WHERE path = ?1 OR (path >= ?2 AND path < ?3)",
+                params![path.to_string_lossy(), lower, upper],
+                |row| Ok((row.get::<_, i64>(0)? as u64, row.get::<_, i64>(1)? as u64)),
+            )
+            .map_err(db_error)
+    }
+
+    /// Stop indexing `root` and drop its entries
+    pub fn remove_root(&mut self, root: &Path) -> Result<()> {
+        let tx = self.conn.transaction().map_err(db_error)?;
+        delete_subtree(&tx, root)?;
+        tx.execute(
+            "DELETE FROM roots WHERE path = ?1",
+            params![root.to_string_lossy()],
+        )
+        .map_err(db_error)?;
+        tx.commit().map_err(db_error)
+    }
+}
+
+/// Regular files and directories found by a walk
+#[derive(Debug, Default)]
+struct Walked {
+    files: Vec<FileEntity>,
+    /// Directory paths with their modification times
+    dirs: Vec<(String, i64)>,
+}
+
+/// Regular files and directories under `root` as the walker sees them
+fn walk_entries(root: &Path, options: &ScanOptions, cancel: &CancellationToken) -> Walked {
+    let mut walked = Walked::default();
+    let entries = walker(root, options)
+        .into_iter()
+        .take_while(|_| !cancel.is_cancelled())
+        .filter_map(|entry| match entry {
+            Ok(entry) => Some(entry),
+            Err(err) => {
+                if let Some(skipped) = skipped_from_walk_error(&err) {
+                    tracing::debug!(path = %skipped.path, "Skipping unreadable path");
+                }
+                None
+            }
+        });
+    for entry in entries {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let path = entry.path().to_string_lossy().to_string();
+        if metadata.is_file() {
+            walked.files.push(FileEntity::new(path, metadata.len()));
+        } else if metadata.is_dir() {
+            walked.dirs.push((path, mtime_nanos(&metadata)));
+        }
+    }
+    walked
+}
+
+/// Files and subdirectories directly inside `dir` that a walk of `root` would
+/// visit
+fn read_dir_entries(
+    root: &Path,
+    dir: &Path,
+    options: &ScanOptions,
+    device: Option<u64>,
+) -> (Vec<FileEntity>, Vec<PathBuf>) {
+    let (mut files, mut subdirs) = (Vec::new(), Vec::new());
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            tracing::debug!(path = %dir.display(), error = %err, "Skipping unreadable directory");
+            return (files, subdirs);
+        }
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !walk_includes(root, &path, options) {
+            continue;
+        }
+        let metadata = if options.follow_symlinks {
+            std::fs::metadata(&path)
+        } else {
+            entry.metadata()
+        };
+        let Ok(metadata) = metadata else {
+            continue;
+        };
+        if metadata.is_dir()
+            && entry.file_type().is_ok_and(|t| t.is_symlink())
+            && leads_back(dir, &path)
+        {
+            continue;
+        }
+        if metadata.is_file() {
+            files.push(FileEntity::new(
+                path.to_string_lossy().to_string(),
+                metadata.len(),
+            ));
+        } else if metadata.is_dir() && device.map_or(true, |d| device_id(&path) == Some(d)) {
+            subdirs.push(path);
+        }
+    }
+    (files, subdirs)
+}
+
+/// Modification time of the directory `dir`, or `None` if it's gone (or no
+/// longer a directory)
+fn dir_mtime(dir: &Path, options: &ScanOptions) -> Option<i64> {
+    let metadata = if options.follow_symlinks {
+        std::fs::metadata(dir)
+    } else {
+        std::fs::symlink_metadata(dir)
+    };
+    metadata
+        .ok()
+        .filter(std::fs::Metadata::is_dir)
+        .map(|metadata| mtime_nanos(&metadata))
+}
+
+/// Modification time in nanoseconds since the Unix epoch (0 if unknown)
+fn mtime_nanos(metadata: &std::fs::Metadata) -> i64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |elapsed| {
+            i64::try_from(elapsed.as_nanos()).unwrap_or(i64::MAX)
+        })
+}
+
+fn insert_files(conn: &Connection, files: &[FileEntity]) -> Result<()> {
+    let mut statement = conn
+        .prepare_cached("INSERT OR REPLACE INTO files (path, size) VALUES (?1, ?2)")
+        .map_err(db_error)?;
+    for file in files {
+        statement
+            .execute(params![file.path, file.size as i64])
+            .map_err(db_error)?;
+    }
+    Ok(())
+}
+
+fn insert_dirs(conn: &Connection, dirs: &[(String, i64)]) -> Result<()> {
+    let mut statement = conn
+        .prepare_cached("INSERT OR REPLACE INTO dirs (path, mtime) VALUES (?1, ?2)")
+        .map_err(db_error)?;
+    for (path, mtime) in dirs {
+        statement.execute(params![path, mtime]).map_err(db_error)?;
+    }
+    Ok(())
+}
+
+/// Delete `path` and everything beneath it
+fn delete_subtree(conn: &Connection, path: &Path) -> Result<()> {
+    let (lower, upper) = subtree_bounds(path);
+    for table in ["files", "dirs"] {
+        conn.execute(
+            &format!("DELETE FROM {table} WHERE path = ?1 OR (path >= ?2 AND path < ?3)"),
+            params![path.to_string_lossy(), lower, upper],
+        )
+        .map_err(db_error)?;
+    }
+    Ok(())
+}
+
+/// Delete the files directly inside `dir`, keeping those in subdirectories
+fn delete_direct_files(conn: &Connection, dir: &Path) -> Result<()> {
+    let (lower, upper) = subtree_bounds(dir);
+    conn.execute(
+        "DELETE FROM files WHERE path >= ?1 AND path < ?2
+         AND instr(substr(path, length(?1) + 1), '/') = 0",
+        params![lower, upper],
+    )
+    .map_err(db_error)?;
+    Ok(())
+}
+
+/// Key range holding every path strictly beneath `path`
+///
+/// Descendants start with `path/`, and `0` is the character after `/`, so
+/// they sort in `[path/, path0)`. Range queries use the primary key index,
+/// unlike `LIKE`.
+fn subtree_bounds(path: &Path) -> (String, String) {
+    let path = path.to_string_lossy();
+    let base = path.trim_end_matches('/');
+    (format!("{}/", base), format!("{}0", base))
+}
+
+fn parse_timestamp(value: &str) -> Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|date| date.with_timezone(&Utc))
+        .map_err(|e| Error::Internal(format!("Invalid index timestamp {}: {}", value, e)))
+}
+
+fn db_error(error: rusqlite::Error) -> Error {
+    Error::Internal(format!("Index database error: {}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn open(temp_dir: &TempDir) -> SizeIndex {
+        SizeIndex::open(&temp_dir.path().join("state/index.db")).unwrap()
+    }
+
+    #[test]
+    fn test_subtree_bounds() {
+        assert_eq!(
+            subtree_bounds(Path::new("/a/b")),
+            ("/a/b/".to_string(), "/a/b0".to_string())
+        );
+        assert_eq!(
+            subtree_bounds(Path::new("/")),
+            ("/".to_string(), "0".to_string())
+        );
+    }
+
+    #[test]
+    fn test_index_and_query() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("data");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::create_dir_all(root.join("sub-sibling")).unwrap();
+        fs::write(root.join("big.bin"), vec![0u8; 100]).unwrap();
+        fs::write(root.join("sub/small.bin"), vec![0u8; 10]).unwrap();
+        fs::write(root.join("sub-sibling/other.bin"), vec![0u8; 20]).unwrap();
+
+        let mut index = open(&temp_dir);
+        let count = index
+            .index_root(&root, &ScanOptions::default(), &CancellationToken::new())
+            .unwrap();
+        assert_eq!(count, 3);
+
+        let all = index.analysis(&root, 0).unwrap().unwrap();
+        assert_eq!(all.total_size, 130);
+        assert_eq!(index.totals(&root).unwrap(), (3, 130));
+        assert_eq!(all.files[0].size, 100);
+        assert_eq!(all.strategy, AnalysisStrategy::Incremental);
+
+        // A sibling sharing the prefix isn't part of the subtree
+        let sub = index.analysis(&root.join("sub"), 0).unwrap().unwrap();
+        assert_eq!(sub.files.len(), 1);
+
+        let large = index.analysis(&root, 50).unwrap().unwrap();
+        assert_eq!(large.files.len(), 1);
+
+        assert!(index.analysis(temp_dir.path(), 0).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_apply_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("data");
+        fs::create_dir_all(root.join("dir")).unwrap();
+        fs::write(root.join("grows.bin"), vec![0u8; 10]).unwrap();
+        fs::write(root.join("gone.bin"), vec![0u8; 10]).unwrap();
+
+        let mut index = open(&temp_dir);
+        let options = ScanOptions::default();
+        index
+            .index_root(&root, &options, &CancellationToken::new())
+            .unwrap();
+
+        fs::write(root.join("grows.bin"), vec![0u8; 50]).unwrap();
+        fs::remove_file(root.join("gone.bin")).unwrap();
+        fs::write(root.join("dir/new.bin"), vec![0u8; 5]).unwrap();
+        let applied = index
+            .apply_changes(
+                &[
+                    FsChange::path(root.join("grows.bin")),
+                    FsChange::path(root.join("gone.bin")),
+                    FsChange::subtree(root.join("dir")),
+                    FsChange::path("/elsewhere/file"),
+                ],
+                &options,
+            )
+            .unwrap();
+        assert_eq!(applied, 3);
+
+        let result = index.analysis(&root, 0).unwrap().unwrap();
+        assert_eq!(result.total_size, 55);
+        assert_eq!(result.files.len(), 2);
+    }
+
+    #[test]
+    fn test_refresh_rereads_changed_directories() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("data");
+        for dir in ["same", "changes", "goes/away"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(root.join("same/a.bin"), vec![0u8; 10]).unwrap();
+        fs::write(root.join("changes/b.bin"), vec![0u8; 20]).unwrap();
+        fs::write(root.join("goes/away/c.bin"), vec![0u8; 30]).unwrap();
+
+        let mut index = open(&temp_dir);
+        let options = ScanOptions::default();
+        let cancel = CancellationToken::new();
+        let first = index.refresh(&root, &options, &cancel).unwrap().unwrap();
+        assert!(first.full_walk);
+        assert_eq!(first.dirs_rescanned, 5);
+        assert_eq!(index.totals(&root).unwrap(), (3, 60));
+
+        // Make sure the changed directories get a different time
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        fs::write(root.join("changes/new.bin"), vec![0u8; 5]).unwrap();
+        fs::remove_dir_all(root.join("goes")).unwrap();
+        let second = index.refresh(&root, &options, &cancel).unwrap().unwrap();
+        assert!(!second.full_walk);
+        // The root (lost `goes`) and `changes` (gained a file)
+        assert_eq!(second.dirs_rescanned, 2);
+        assert_eq!(second.dirs_reused, 1);
+        assert_eq!(second.dirs_removed, 1);
+        assert_eq!(index.totals(&root).unwrap(), (3, 35));
+    }
+}
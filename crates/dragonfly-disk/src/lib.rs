@@ -10,10 +10,73 @@
 )]
 
 pub mod analyzer;
+pub mod apps;
+pub mod checkpoint;
+pub mod dev_artifacts;
+pub mod empty_dirs;
+pub mod file_types;
+pub mod growth;
+pub mod history;
+pub mod index;
+pub mod links;
+pub mod mail;
+pub mod multi_volume;
+pub mod orphans;
+pub mod owners;
+pub mod packages;
+pub mod photos;
+pub mod sampling;
+pub mod saved_scan;
+pub mod screenshots;
+pub mod snapshot_diff;
+pub mod space;
+pub mod spotlight;
+pub mod stale;
 pub mod strategies;
+pub mod thinning;
+pub mod tree;
+pub mod walk;
+pub mod watch;
 
-pub use analyzer::{AnalysisResult, DiskAnalyzer};
+pub use analyzer::{
+    AnalysisResult, AnalysisTotals, DiskAnalyzer, MultiRootAnalysis, RootTotals, StreamSummary,
+    MAX_CONCURRENT_ROOTS,
+};
+pub use apps::{AppInventory, AppReport};
+pub use checkpoint::{ScanCheckpoint, CHECKPOINT_INTERVAL};
+pub use dev_artifacts::{
+    DevArtifact, DevArtifactFinder, DevArtifactKind, DevArtifactsReport, ProjectArtifacts,
+};
+pub use empty_dirs::{EmptyDirFinder, EmptyDirsReport, EmptyFile, EmptyTree};
+pub use file_types::{CategoryUsage, FileCategory};
+pub use growth::GrowthMonitor;
+pub use history::{FileChange, History, LargestFiles, Sample, Trend};
+pub use index::{IndexedRoot, RefreshStats, SizeIndex};
+pub use links::{BrokenLink, BrokenLinkFinder, BrokenLinksReport, LinkKind};
+pub use mail::{
+    MailAccountUsage, MailAnalyzer, MailReport, MailboxUsage, DEFAULT_MAIL_DIR,
+    DEFAULT_MAIL_DOWNLOADS,
+};
+pub use multi_volume::{MultiVolumeReport, VolumeProgress, VolumeReport, VolumeScanCoordinator};
+pub use orphans::{Leftover, OrphanFinder, OrphanGroup};
+pub use owners::{user_name, OwnerUsage};
+pub use photos::{
+    PhotosCategory, PhotosLibraryAnalyzer, PhotosLibraryReport, DEFAULT_PHOTOS_LIBRARY,
+};
+pub use sampling::{QuickEstimate, DEFAULT_PROBES};
+pub use saved_scan::{SavedFile, SavedScan, SavedScanInfo, ScanDiffReport};
+pub use screenshots::{Screenshot, ScreenshotFinder, ScreenshotReport};
+pub use snapshot_diff::{
+    snapshot_volume, PathGrowth, SnapshotDiff, SnapshotDiffReport, SnapshotMount,
+};
+pub use space::{
+    mount_point, mounted_volumes, supports_clones, MountedVolume, ReclaimCheck, VolumeSpace,
+};
+pub use stale::{StaleFile, StaleFilesReport};
 pub use strategies::AnalysisStrategy;
+pub use thinning::{AppThinning, Slice, ThinningAnalyzer, ThinningReport, UniversalBinary};
+pub use tree::{DirectoryNode, DirectoryTree};
+pub use watch::{platform_watcher, PollingWatcher};
 
 /// Module version
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
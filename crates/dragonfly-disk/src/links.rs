@@ -0,0 +1,311 @@
+//! Broken link detection
+//!
+//! Symlinks outlive the files they point at: uninstalled tools, moved
+//! projects, and old Homebrew prefixes leave links behind that resolve to
+//! nothing. Finder aliases break the same way. This module finds both so they
+//! can be reviewed and removed.
+
+use crate::walk::{skipped_from_walk_error, walker};
+use dragonfly_core::domain::protection::{PathProtection, SkippedPath};
+use dragonfly_core::domain::scan_options::ScanOptions;
+use dragonfly_core::error::{Error, Result};
+use dragonfly_core::ports::CancellationToken;
+use serde::Serialize;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Alias files are small; anything larger is not worth opening
+const MAX_ALIAS_SIZE: u64 = 64 * 1024;
+
+/// Data fork signature of a Finder alias file
+const ALIAS_MAGIC: &[u8; 16] = b"book\0\0\0\0mark\0\0\0\0";
+
+/// Bookmark item holding the target's path components
+const BOOKMARK_PATH_KEY: u32 = 0x1004;
+
+/// Bookmark item types used when reading the target path
+const BOOKMARK_TYPE_STRING: u32 = 0x0101;
+const BOOKMARK_TYPE_ARRAY: u32 = 0x0601;
+
+/// Kind of link
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkKind {
+    /// Symbolic link
+    Symlink,
+    /// Finder alias file
+    Alias,
+}
+
+/// A link whose target does not exist
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BrokenLink {
+    /// Path of the link itself
+    pub path: PathBuf,
+    /// Where the link points, as stored in the link
+    pub target: PathBuf,
+    /// Symlink or alias
+    pub kind: LinkKind,
+    /// Protection level of the link's path
+    pub protection: PathProtection,
+}
+
+/// Broken links found under a directory
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BrokenLinksReport {
+    /// Directory that was searched
+    pub root: PathBuf,
+    /// Broken links, sorted by path
+    pub links: Vec<BrokenLink>,
+    /// Directories that couldn't be listed, whose links weren't checked
+    pub skipped: Vec<SkippedPath>,
+    /// Whether the search was cancelled before completing (results are partial)
+    pub interrupted: bool,
+}
+
+/// Finds symlinks and, optionally, Finder aliases with missing targets
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BrokenLinkFinder {
+    include_aliases: bool,
+}
+
+impl BrokenLinkFinder {
+    /// Create a finder that checks symlinks only
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also check Finder alias files
+    ///
+    /// This opens every small regular file to look for the alias signature,
+    /// so it is noticeably slower on large trees.
+    pub fn with_aliases(mut self, include_aliases: bool) -> Self {
+        self.include_aliases = include_aliases;
+        self
+    }
+
+    /// Search `root` for broken links
+    pub async fn find(
+        &self,
+        root: &Path,
+        options: &ScanOptions,
+        cancel: &CancellationToken,
+    ) -> Result<BrokenLinksReport> {
+        if !root.is_dir() {
+            return Err(Error::NotFound(format!(
+                "Directory does not exist: {}",
+                root.display()
+            )));
+        }
+
+        let mut report = BrokenLinksReport {
+            root: root.to_path_buf(),
+            ..Default::default()
+        };
+        // Links are inspected, never followed, whatever the options say
+        let options = options.clone().with_follow_symlinks(false);
+        for entry in walker(root, &options) {
+            if cancel.is_cancelled() {
+                report.interrupted = true;
+                break;
+            }
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    report.skipped.extend(skipped_from_walk_error(&err));
+                    continue;
+                }
+            };
+
+            let path = entry.path();
+            let file_type = entry.file_type();
+            let broken = if file_type.is_symlink() {
+                broken_symlink(&path)
+            } else if self.include_aliases && file_type.is_file() {
+                broken_alias(&path)
+            } else {
+                None
+            };
+            report.links.extend(broken);
+        }
+
+        report.links.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(report)
+    }
+}
+
+/// The symlink at `path`, if its target doesn't exist
+fn broken_symlink(path: &Path) -> Option<BrokenLink> {
+    match std::fs::metadata(path) {
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Some(BrokenLink {
+            target: std::fs::read_link(path).ok()?,
+            kind: LinkKind::Symlink,
+            protection: PathProtection::classify(&path.to_string_lossy()),
+            path: path.to_path_buf(),
+        }),
+        _ => None,
+    }
+}
+
+/// The alias file at `path`, if it is one and its target doesn't exist
+///
+/// Aliases whose target path can't be read are left alone rather than
+/// reported, so nothing that might still work is ever offered for removal.
+fn broken_alias(path: &Path) -> Option<BrokenLink> {
+    let metadata = std::fs::metadata(path).ok()?;
+    if metadata.len() < ALIAS_MAGIC.len() as u64 || metadata.len() > MAX_ALIAS_SIZE {
+        return None;
+    }
+    let mut file = std::fs::File::open(path).ok()?;
+    let mut magic = [0u8; 16];
+    file.read_exact(&mut magic).ok()?;
+    if &magic != ALIAS_MAGIC {
+        return None;
+    }
+
+    let mut data = magic.to_vec();
+    file.read_to_end(&mut data).ok()?;
+    let target = alias_target(&data)?;
+    (!target.exists()).then(|| BrokenLink {
+        path: path.to_path_buf(),
+        target,
+        kind: LinkKind::Alias,
+        protection: PathProtection::classify(&path.to_string_lossy()),
+    })
+}
+
+/// Target path recorded in an alias file's bookmark data
+///
+/// The header is followed by a table of contents mapping item keys to
+/// offsets; the path is stored as an array of offsets to its components.
+/// All offsets are relative to the end of the header.
+fn alias_target(data: &[u8]) -> Option<PathBuf> {
+    let header_size = read_u32(data, 16)? as usize;
+    let toc = header_size.checked_add(read_u32(data, header_size)? as usize)?;
+    // TOC header: size, magic, id, next TOC, entry count
+    let count = read_u32(data, toc.checked_add(16)?)? as usize;
+    // Each entry is key, offset, reserved
+    let path_offset = (0..count.min(data.len() / 12)).find_map(|n| {
+        let entry = toc + 20 + n * 12;
+        if read_u32(data, entry)? == BOOKMARK_PATH_KEY {
+            read_u32(data, entry + 4)
+        } else {
+            None
+        }
+    })?;
+
+    let (kind, components) = bookmark_item(data, header_size, path_offset)?;
+    if kind != BOOKMARK_TYPE_ARRAY {
+        return None;
+    }
+    let mut path = PathBuf::from("/");
+    for chunk in components.chunks_exact(4) {
+        let offset = u32::from_le_bytes(chunk.try_into().ok()?);
+        let (kind, name) = bookmark_item(data, header_size, offset)?;
+        if kind != BOOKMARK_TYPE_STRING {
+            return None;
+        }
+        path.push(std::str::from_utf8(name).ok()?);
+    }
+    (path.as_os_str().len() > 1).then_some(path)
+}
+
+/// Type and payload of the bookmark item at `offset`
+fn bookmark_item(data: &[u8], header_size: usize, offset: u32) -> Option<(u32, &[u8])> {
+    let start = header_size.checked_add(offset as usize)?;
+    let length = read_u32(data, start)? as usize;
+    let kind = read_u32(data, start + 4)?;
+    let payload = data.get(start + 8..start.checked_add(8 + length)?)?;
+    Some((kind, payload))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// Minimal alias file pointing at `/` joined with `components`
+    fn alias_bytes(components: &[&str]) -> Vec<u8> {
+        const HEADER: usize = 0x38;
+        let mut body = vec![0u8; 4]; // TOC offset, patched below
+        let mut offsets = Vec::new();
+        for component in components {
+            offsets.push(body.len() as u32);
+            body.extend((component.len() as u32).to_le_bytes());
+            body.extend(BOOKMARK_TYPE_STRING.to_le_bytes());
+            body.extend(component.as_bytes());
+            while body.len() % 4 != 0 {
+                body.push(0);
+            }
+        }
+        let array = body.len() as u32;
+        body.extend((offsets.len() as u32 * 4).to_le_bytes());
+        body.extend(BOOKMARK_TYPE_ARRAY.to_le_bytes());
+        for offset in offsets {
+            body.extend(offset.to_le_bytes());
+        }
+        let toc = body.len() as u32;
+        body[..4].copy_from_slice(&toc.to_le_bytes());
+        for value in [32, 0xffff_fffe, 1, 0, 1, BOOKMARK_PATH_KEY, array, 0] {
+            body.extend(u32::to_le_bytes(value));
+        }
+
+        let mut data = ALIAS_MAGIC.to_vec();
+        data.extend((HEADER as u32).to_le_bytes());
+        data.resize(HEADER, 0);
+        data.extend(body);
+        data
+    }
+
+    #[test]
+    fn test_alias_target() {
+        let data = alias_bytes(&["Users", "me", "Projects"]);
+        assert_eq!(
+            alias_target(&data),
+            Some(PathBuf::from("/Users/me/Projects"))
+        );
+        assert_eq!(alias_target(&data[..40]), None);
+    }
+
+    #[tokio::test]
+    async fn test_find_broken_symlinks_and_aliases() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(root.join("real.txt"), b"data").unwrap();
+        std::os::unix::fs::symlink(root.join("real.txt"), root.join("ok")).unwrap();
+        std::os::unix::fs::symlink("missing.txt", root.join("dangling")).unwrap();
+        fs::write(
+            root.join("Old Project alias"),
+            alias_bytes(&["nonexistent-dragonfly-test", "Project"]),
+        )
+        .unwrap();
+
+        let finder = BrokenLinkFinder::new();
+        let cancel = CancellationToken::new();
+        let report = finder
+            .find(root, &ScanOptions::default(), &cancel)
+            .await
+            .unwrap();
+        assert_eq!(report.links.len(), 1);
+        assert_eq!(report.links[0].path, root.join("dangling"));
+        assert_eq!(report.links[0].target, PathBuf::from("missing.txt"));
+
+        let report = finder
+            .with_aliases(true)
+            .find(root, &ScanOptions::default(), &cancel)
+            .await
+            .unwrap();
+        assert_eq!(report.links.len(), 2);
+        assert_eq!(report.links[0].kind, LinkKind::Alias);
+        assert_eq!(
+            report.links[0].target,
+            PathBuf::from("/nonexistent-dragonfly-test/Project")
+        );
+    }
+}
@@ -0,0 +1,339 @@
+//! Mail storage analysis
+//!
+//! Apple Mail keeps a local copy of every message it has downloaded under
+//! `~/Library/Mail/V<n>/<account>/<mailbox>.mbox`, with decoded attachments in
+//! `Attachments` folders next to the messages. Attachments opened from Mail are
+//! also copied to its Mail Downloads folder. This module sizes each account and
+//! mailbox and the share taken by attachments, so it's clear whether clearing
+//! Mail Downloads or re-syncing an account would win anything back.
+//!
+//! Nothing is modified; the Mail folders are only read.
+
+use crate::walk::{skipped_from_walk_error, walker};
+use dragonfly_core::domain::protection::SkippedPath;
+use dragonfly_core::domain::scan_options::ScanOptions;
+use dragonfly_core::error::{Error, Result};
+use dragonfly_core::ports::CancellationToken;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+/// Location of Mail's data, relative to the home directory
+pub const DEFAULT_MAIL_DIR: &str = "Library/Mail";
+
+/// Location of Mail's downloaded attachments, relative to the home directory
+pub const DEFAULT_MAIL_DOWNLOADS: &str =
+    "Library/Containers/com.apple.mail/Data/Library/Mail Downloads";
+
+/// Folder Mail keeps account metadata in rather than messages
+const MAIL_DATA_DIR: &str = "MailData";
+
+/// Storage used by one mailbox
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MailboxUsage {
+    /// Mailbox path within the account, e.g. `Archive/2023`
+    pub name: String,
+    /// Total size in bytes
+    pub total_size: u64,
+    /// Size of attachments in bytes
+    pub attachments_size: u64,
+    /// Number of messages stored locally
+    pub message_count: u64,
+}
+
+/// Storage used by one account
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MailAccountUsage {
+    /// Account folder name
+    pub name: String,
+    /// Total size in bytes
+    pub total_size: u64,
+    /// Size of attachments in bytes
+    pub attachments_size: u64,
+    /// Mailboxes, largest first
+    pub mailboxes: Vec<MailboxUsage>,
+}
+
+/// Storage breakdown of Mail's data
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MailReport {
+    /// Mail data folder that was inspected
+    pub root: PathBuf,
+    /// Total size of all accounts in bytes
+    pub total_size: u64,
+    /// Size of attachments across all accounts in bytes
+    pub attachments_size: u64,
+    /// Accounts, largest first
+    pub accounts: Vec<MailAccountUsage>,
+    /// Size of the Mail Downloads folder in bytes
+    pub downloads_size: u64,
+    /// Mailboxes and attachment folders that couldn't be read, typically
+    /// without Full Disk Access
+    pub skipped: Vec<SkippedPath>,
+    /// Whether the scan was cancelled before completing (results are partial)
+    pub interrupted: bool,
+}
+
+impl MailReport {
+    /// Share of the stored mail taken by attachments, between 0 and 1
+    pub fn attachment_share(&self) -> f64 {
+        if self.total_size == 0 {
+            0.0
+        } else {
+            self.attachments_size as f64 / self.total_size as f64
+        }
+    }
+}
+
+/// Read-only analyzer for Apple Mail storage
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MailAnalyzer;
+
+impl MailAnalyzer {
+    /// Create a new Mail analyzer
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Break down the storage under `mail_root` and the `downloads` folder
+    ///
+    /// `mail_root` is normally `~/Library/Mail`; the newest `V<n>` folder in
+    /// it is the one Mail is using. A folder without version subfolders is
+    /// treated as the version folder itself.
+    pub async fn analyze(
+        &self,
+        mail_root: &Path,
+        downloads: Option<&Path>,
+        cancel: &CancellationToken,
+    ) -> Result<MailReport> {
+        if !mail_root.is_dir() {
+            return Err(Error::NotFound(format!(
+                "Mail folder does not exist: {}",
+                mail_root.display()
+            )));
+        }
+
+        let root = current_version_dir(mail_root).unwrap_or_else(|| mail_root.to_path_buf());
+        let mut report = MailReport {
+            root: root.clone(),
+            ..Default::default()
+        };
+        let mut accounts: BTreeMap<String, BTreeMap<String, MailboxUsage>> = BTreeMap::new();
+
+        let options = ScanOptions::new().with_hidden(true);
+        for (path, size) in files(&root, &options, cancel, &mut report.skipped) {
+            let Ok(relative) = path.strip_prefix(&root) else {
+                continue;
+            };
+            let mut components = relative.components();
+            let Some(Component::Normal(account)) = components.next() else {
+                continue;
+            };
+            let account = account.to_string_lossy();
+            if account == MAIL_DATA_DIR || components.as_path().as_os_str().is_empty() {
+                continue;
+            }
+
+            let location = classify(components.as_path());
+            let mailbox = accounts
+                .entry(account.to_string())
+                .or_default()
+                .entry(location.mailbox)
+                .or_default();
+            mailbox.total_size += size;
+            if location.attachment {
+                mailbox.attachments_size += size;
+            }
+            if location.message {
+                mailbox.message_count += 1;
+            }
+        }
+
+        for (name, mailboxes) in accounts {
+            let mut mailboxes: Vec<MailboxUsage> = mailboxes
+                .into_iter()
+                .map(|(name, usage)| MailboxUsage { name, ..usage })
+                .collect();
+            mailboxes.sort_by_key(|m| std::cmp::Reverse(m.total_size));
+            let account = MailAccountUsage {
+                name,
+                total_size: mailboxes.iter().map(|m| m.total_size).sum(),
+                attachments_size: mailboxes.iter().map(|m| m.attachments_size).sum(),
+                mailboxes,
+            };
+            report.total_size += account.total_size;
+            report.attachments_size += account.attachments_size;
+            report.accounts.push(account);
+        }
+        report
+            .accounts
+            .sort_by_key(|a| std::cmp::Reverse(a.total_size));
+
+        if let Some(downloads) = downloads.filter(|d| d.is_dir()) {
+            report.downloads_size = files(downloads, &options, cancel, &mut report.skipped)
+                .map(|(_, size)| size)
+                .sum();
+        }
+
+        report.interrupted = cancel.is_cancelled();
+        Ok(report)
+    }
+}
+
+/// Where a file sits within an account
+#[derive(Debug, PartialEq, Eq)]
+struct MailLocation {
+    mailbox: String,
+    attachment: bool,
+    message: bool,
+}
+
+/// Classify a path relative to its account folder
+///
+/// The mailbox is the chain of `.mbox` folders leading to the file, so nested
+/// mailboxes are reported separately from their parents. Files outside any
+/// mailbox belong to the account itself.
+fn classify(relative: &Path) -> MailLocation {
+    let mut mailbox = Vec::new();
+    let mut attachment = false;
+    for component in relative.iter() {
+        let component = component.to_string_lossy();
+        if let Some(name) = component.strip_suffix(".mbox") {
+            mailbox.push(name.to_string());
+        } else if component == "Attachments" {
+            attachment = true;
+        }
+    }
+    // Partially downloaded messages are `<id>.partial.emlx`
+    let message = !attachment && relative.extension().is_some_and(|ext| ext == "emlx");
+
+    MailLocation {
+        mailbox: if mailbox.is_empty() {
+            "(account data)".to_string()
+        } else {
+            mailbox.join("/")
+        },
+        attachment,
+        message,
+    }
+}
+
+/// Newest `V<n>` folder under the Mail folder
+fn current_version_dir(mail_root: &Path) -> Option<PathBuf> {
+    std::fs::read_dir(mail_root)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let version: u32 = entry
+                .file_name()
+                .to_str()?
+                .strip_prefix('V')?
+                .parse()
+                .ok()?;
+            entry
+                .file_type()
+                .ok()?
+                .is_dir()
+                .then(|| (version, entry.path()))
+        })
+        .max_by_key(|(version, _)| *version)
+        .map(|(_, path)| path)
+}
+
+/// Regular files under `root` with their sizes, recording unreadable paths
+fn files<'a>(
+    root: &Path,
+    options: &ScanOptions,
+    cancel: &'a CancellationToken,
+    skipped: &'a mut Vec<SkippedPath>,
+) -> impl Iterator<Item = (PathBuf, u64)> + 'a {
+    walker(root, options)
+        .into_iter()
+        .take_while(|_| !cancel.is_cancelled())
+        .filter_map(move |entry| {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    skipped.extend(skipped_from_walk_error(&err));
+                    return None;
+                }
+            };
+            match entry.metadata() {
+                Ok(metadata) if metadata.is_file() => Some((entry.path(), metadata.len())),
+                Ok(_) => None,
+                Err(err) => {
+                    skipped.extend(skipped_from_walk_error(&err));
+                    None
+                }
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn write(root: &Path, relative: &str, size: usize) {
+        let path = root.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, vec![0u8; size]).unwrap();
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            classify(Path::new("INBOX.mbox/ID/Data/1/Messages/1.emlx")),
+            MailLocation {
+                mailbox: "INBOX".to_string(),
+                attachment: false,
+                message: true,
+            }
+        );
+        assert_eq!(
+            classify(Path::new(
+                "Archive.mbox/2023.mbox/ID/Data/Attachments/1/2/a.pdf"
+            )),
+            MailLocation {
+                mailbox: "Archive/2023".to_string(),
+                attachment: true,
+                message: false,
+            }
+        );
+        assert_eq!(classify(Path::new("Info.plist")).mailbox, "(account data)");
+    }
+
+    #[tokio::test]
+    async fn test_analyze_reports_accounts_and_attachments() {
+        let temp_dir = TempDir::new().unwrap();
+        let mail = temp_dir.path().join("Mail");
+        write(&mail, "V9/OLD/INBOX.mbox/Messages/1.emlx", 1000);
+        write(&mail, "V10/MailData/Envelope Index", 500);
+        write(&mail, "V10/WORK/INBOX.mbox/ID/Data/Messages/1.emlx", 10);
+        write(&mail, "V10/WORK/INBOX.mbox/ID/Data/Messages/2.emlx", 10);
+        write(
+            &mail,
+            "V10/WORK/INBOX.mbox/ID/Data/Attachments/1/2/a.pdf",
+            80,
+        );
+        write(&mail, "V10/HOME/Sent.mbox/ID/Data/Messages/3.emlx", 5);
+        let downloads = temp_dir.path().join("Mail Downloads");
+        write(&downloads, "ABC/a.pdf", 80);
+
+        let report = MailAnalyzer::new()
+            .analyze(&mail, Some(&downloads), &CancellationToken::new())
+            .await
+            .unwrap();
+
+        assert!(report.root.ends_with("V10"));
+        assert_eq!(report.total_size, 105);
+        assert_eq!(report.attachments_size, 80);
+        assert_eq!(report.downloads_size, 80);
+        assert_eq!(report.accounts[0].name, "WORK");
+        assert_eq!(report.accounts[0].mailboxes[0].name, "INBOX");
+        assert_eq!(report.accounts[0].mailboxes[0].message_count, 2);
+        assert_eq!(report.accounts[1].name, "HOME");
+        assert!((report.attachment_share() - 80.0 / 105.0).abs() < f64::EPSILON);
+    }
+}
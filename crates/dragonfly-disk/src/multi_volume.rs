@@ -0,0 +1,385 @@
+//! Concurrent scanning of several mounted volumes
+//!
+//! Walking drives one after another leaves all but one of them idle. The
+//! coordinator groups the requested roots by the device they live on and gives
+//! each device its own worker pool, so an internal SSD and a couple of external
+//! disks are scanned at the same time without their walks competing for the
+//! same threads. Each walk stays on its root's filesystem, so a volume mounted
+//! beneath another root is not counted twice.
+
+use crate::walk::{device_id, skipped_from_walk_error, walker, PROGRESS_INTERVAL};
+use dragonfly_core::domain::entities::FileEntity;
+use dragonfly_core::domain::protection::SkippedPath;
+use dragonfly_core::domain::scan_options::ScanOptions;
+use dragonfly_core::ports::CancellationToken;
+use jwalk::Parallelism;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Worker threads per device when the scan options don't set a count
+const DEFAULT_THREADS_PER_DEVICE: usize = 4;
+
+/// Progress of a single volume scan
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumeProgress {
+    /// Root being scanned
+    pub root: PathBuf,
+    /// Files counted so far
+    pub files_scanned: u64,
+    /// Bytes counted so far
+    pub bytes_scanned: u64,
+    /// Whether the scan of this root has finished
+    pub finished: bool,
+}
+
+/// Result of scanning a single volume
+#[derive(Debug, Clone)]
+pub struct VolumeReport {
+    /// Root that was scanned
+    pub root: PathBuf,
+    /// Total size of files under the root in bytes
+    pub total_size: u64,
+    /// Number of files under the root
+    pub file_count: u64,
+    /// Paths under the root that couldn't be read and aren't in the totals
+    pub skipped: Vec<SkippedPath>,
+    /// Error that prevented scanning the root, if any
+    pub error: Option<String>,
+}
+
+/// Merged result of a multi-volume scan
+#[derive(Debug, Clone)]
+pub struct MultiVolumeReport {
+    /// Per-volume results, in the order the roots were given
+    pub volumes: Vec<VolumeReport>,
+    /// Total size across all volumes in bytes
+    pub total_size: u64,
+    /// Total number of files across all volumes
+    pub file_count: u64,
+    /// Largest files across all volumes, largest first
+    pub largest_files: Vec<FileEntity>,
+    /// Whether the scan was cancelled before completing (results are partial)
+    pub interrupted: bool,
+}
+
+/// Scans several volumes concurrently, one worker pool per device
+///
+/// The thread count in the scan options applies to each device's pool. Walks
+/// always stay on their root's filesystem, whatever `one_filesystem` says.
+#[derive(Debug, Clone)]
+pub struct VolumeScanCoordinator {
+    options: ScanOptions,
+    top_files: usize,
+}
+
+impl VolumeScanCoordinator {
+    /// Create a coordinator with default settings
+    pub fn new() -> Self {
+        Self {
+            options: ScanOptions::default(),
+            top_files: 10,
+        }
+    }
+
+    /// Set the scan options used for every volume
+    pub fn with_options(mut self, options: ScanOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Set how many of the largest files to keep in the merged report
+    pub fn with_top_files(mut self, top_files: usize) -> Self {
+        self.top_files = top_files;
+        self
+    }
+
+    /// Scan all roots, reporting per-volume progress to `on_progress`
+    ///
+    /// Roots on the same device are scanned one after another on that
+    /// device's pool; different devices are scanned concurrently. A root that
+    /// cannot be scanned is reported with an error instead of failing the
+    /// whole scan.
+    pub async fn scan<F>(
+        &self,
+        roots: &[PathBuf],
+        cancel: &CancellationToken,
+        on_progress: F,
+    ) -> MultiVolumeReport
+    where
+        F: Fn(&VolumeProgress) + Sync,
+    {
+        let mut devices: BTreeMap<DeviceKey, Vec<(usize, &Path)>> = BTreeMap::new();
+        for (index, root) in roots.iter().enumerate() {
+            // Roots whose device cannot be determined get a group of their own
+            let key = match device_id(root) {
+                Some(device) => (Some(device), 0),
+                None => (None, index),
+            };
+            devices.entry(key).or_default().push((index, root));
+        }
+
+        let on_progress = &on_progress;
+        let mut scans: Vec<(usize, VolumeScan)> = std::thread::scope(|scope| {
+            let workers: Vec<_> = devices
+                .into_values()
+                .map(|group| {
+                    scope.spawn(move || {
+                        let pool =
+                            build_pool(self.options.threads.unwrap_or(DEFAULT_THREADS_PER_DEVICE));
+                        group
+                            .into_iter()
+                            .map(|(index, root)| {
+                                (
+                                    index,
+                                    self.scan_root(root, pool.clone(), cancel, on_progress),
+                                )
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap_or_default())
+                .collect()
+        });
+        scans.sort_by_key(|(index, _)| *index);
+
+        let mut largest = BinaryHeap::new();
+        let mut volumes = Vec::with_capacity(scans.len());
+        for (_, scan) in scans {
+            for entry in scan.largest {
+                push_bounded(&mut largest, entry, self.top_files);
+            }
+            volumes.push(scan.report);
+        }
+
+        let largest_files = largest
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((size, path))| FileEntity::new(path, size))
+            .collect();
+
+        MultiVolumeReport {
+            total_size: volumes.iter().map(|v| v.total_size).sum(),
+            file_count: volumes.iter().map(|v| v.file_count).sum(),
+            volumes,
+            largest_files,
+            interrupted: cancel.is_cancelled(),
+        }
+    }
+
+    /// Walk a single root on the given pool
+    fn scan_root<F>(
+        &self,
+        root: &Path,
+        pool: Option<Arc<rayon::ThreadPool>>,
+        cancel: &CancellationToken,
+        on_progress: &F,
+    ) -> VolumeScan
+    where
+        F: Fn(&VolumeProgress) + Sync,
+    {
+        let mut progress = VolumeProgress {
+            root: root.to_path_buf(),
+            files_scanned: 0,
+            bytes_scanned: 0,
+            finished: false,
+        };
+        let mut scan = VolumeScan {
+            report: VolumeReport {
+                root: root.to_path_buf(),
+                total_size: 0,
+                file_count: 0,
+                skipped: Vec::new(),
+                error: None,
+            },
+            largest: BinaryHeap::new(),
+        };
+
+        if let Err(e) = std::fs::metadata(root) {
+            scan.report.error = Some(e.to_string());
+            progress.finished = true;
+            on_progress(&progress);
+            return scan;
+        }
+
+        let parallelism = match pool {
+            Some(pool) => Parallelism::RayonExistingPool {
+                pool,
+                busy_timeout: None,
+            },
+            None => Parallelism::Serial,
+        };
+        let options = self.options.clone().with_one_filesystem(true);
+        let walk = walker(root, &options).parallelism(parallelism);
+
+        for entry in walk {
+            if cancel.is_cancelled() {
+                break;
+            }
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    scan.report.skipped.extend(skipped_from_walk_error(&err));
+                    continue;
+                }
+            };
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let size = match entry.metadata() {
+                Ok(metadata) => metadata.len(),
+                Err(err) => {
+                    scan.report.skipped.extend(skipped_from_walk_error(&err));
+                    continue;
+                }
+            };
+
+            progress.files_scanned += 1;
+            progress.bytes_scanned += size;
+            push_bounded(
+                &mut scan.largest,
+                Reverse((size, entry.path().to_string_lossy().to_string())),
+                self.top_files,
+            );
+
+            if progress.files_scanned % PROGRESS_INTERVAL == 0 {
+                on_progress(&progress);
+            }
+        }
+
+        scan.report.file_count = progress.files_scanned;
+        scan.report.total_size = progress.bytes_scanned;
+        progress.finished = true;
+        on_progress(&progress);
+
+        scan
+    }
+}
+
+impl Default for VolumeScanCoordinator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Grouping key: the device, or the root's index when the device is unknown
+type DeviceKey = (Option<u64>, usize);
+
+/// Min-heap entry of (size, path), so the smallest kept file is on top
+type LargestEntry = Reverse<(u64, String)>;
+
+/// Scan of one root along with its largest files
+#[derive(Debug)]
+struct VolumeScan {
+    report: VolumeReport,
+    largest: BinaryHeap<LargestEntry>,
+}
+
+/// Keep at most `limit` of the largest entries in the heap
+fn push_bounded(heap: &mut BinaryHeap<LargestEntry>, entry: LargestEntry, limit: usize) {
+    if limit == 0 {
+        return;
+    }
+    if heap.len() < limit {
+        heap.push(entry);
+    } else if heap.peek().is_some_and(|smallest| entry < *smallest) {
+        heap.pop();
+        heap.push(entry);
+    }
+}
+
+/// Build the worker pool for one device
+///
+/// Falls back to walking on the calling thread if the pool can't be created.
+fn build_pool(threads: usize) -> Option<Arc<rayon::ThreadPool>> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map(Arc::new)
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_scan_merges_volume_reports() {
+        let first = TempDir::new().unwrap();
+        let second = TempDir::new().unwrap();
+        fs::write(first.path().join("a.bin"), vec![0u8; 300]).unwrap();
+        fs::write(first.path().join("b.bin"), vec![0u8; 100]).unwrap();
+        fs::write(second.path().join("c.bin"), vec![0u8; 200]).unwrap();
+
+        let roots = vec![first.path().to_path_buf(), second.path().to_path_buf()];
+        let progress = Mutex::new(Vec::new());
+        let report = VolumeScanCoordinator::new()
+            .with_top_files(2)
+            .scan(&roots, &CancellationToken::new(), |p| {
+                progress.lock().unwrap().push(p.clone());
+            })
+            .await;
+
+        assert_eq!(report.volumes.len(), 2);
+        assert_eq!(report.volumes[0].root, roots[0]);
+        assert_eq!(report.volumes[0].total_size, 400);
+        assert_eq!(report.volumes[1].file_count, 1);
+        assert_eq!(report.total_size, 600);
+        assert_eq!(report.file_count, 3);
+        assert!(!report.interrupted);
+
+        let largest: Vec<u64> = report.largest_files.iter().map(|f| f.size).collect();
+        assert_eq!(largest, vec![300, 200]);
+
+        let finished = progress.lock().unwrap();
+        assert_eq!(finished.iter().filter(|p| p.finished).count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_missing_root_is_reported_not_fatal() {
+        let present = TempDir::new().unwrap();
+        fs::write(present.path().join("a.bin"), b"abc").unwrap();
+        let roots = vec![present.path().join("missing"), present.path().to_path_buf()];
+
+        let report = VolumeScanCoordinator::new()
+            .scan(&roots, &CancellationToken::new(), |_| {})
+            .await;
+
+        assert!(report.volumes[0].error.is_some());
+        assert_eq!(report.volumes[1].total_size, 3);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_scan_is_marked_interrupted() {
+        let root = TempDir::new().unwrap();
+        fs::write(root.path().join("a.bin"), b"abc").unwrap();
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let report = VolumeScanCoordinator::new()
+            .scan(&[root.path().to_path_buf()], &cancel, |_| {})
+            .await;
+
+        assert!(report.interrupted);
+        assert_eq!(report.file_count, 0);
+    }
+
+    #[test]
+    fn test_push_bounded_keeps_largest() {
+        let mut heap = BinaryHeap::new();
+        for size in [5, 1, 9, 3] {
+            push_bounded(&mut heap, Reverse((size, size.to_string())), 2);
+        }
+        let sizes: Vec<u64> = heap.into_iter().map(|Reverse((s, _))| s).collect();
+        assert_eq!(sizes.len(), 2);
+        assert!(sizes.contains(&9) && sizes.contains(&5));
+    }
+}
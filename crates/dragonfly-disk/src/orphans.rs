@@ -0,0 +1,227 @@
+//! Data left behind by uninstalled applications
+//!
+//! Dragging an app to the trash leaves its support files, caches,
+//! preferences and sandbox container in `~/Library`, each named after its
+//! bundle identifier. Any such entry whose identifier matches none of the
+//! installed apps is a leftover. Helpers and updaters use the identifier of
+//! their app with a suffix (`com.example.editor.ShipIt`), so an entry counts
+//! as belonging to an app when either identifier extends the other. Apple's
+//! own identifiers are never reported: many belong to parts of the system
+//! that aren't apps.
+
+use crate::apps::{app_bundles, bundle_identifier, size_of};
+use dragonfly_core::ports::CancellationToken;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Folders under `~/Library` holding one entry per bundle identifier
+const DATA_BY_BUNDLE_ID: &[&str] = &["Application Support", "Caches", "Containers"];
+
+/// Folder under `~/Library` holding `<bundle id>.plist` files
+const PREFERENCES: &str = "Preferences";
+
+/// Identifier prefixes that are never leftovers
+const SYSTEM_PREFIXES: &[&str] = &["com.apple."];
+
+/// A folder or file an uninstalled app left behind
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Leftover {
+    /// Where it is
+    pub path: PathBuf,
+    /// Bytes it takes
+    pub size: u64,
+}
+
+/// Leftover data of one uninstalled app
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OrphanGroup {
+    /// Bundle identifier the data is named after
+    pub bundle_id: String,
+    /// Folders and files holding it, sorted by path
+    pub leftovers: Vec<Leftover>,
+    /// Bytes they take together
+    pub size: u64,
+}
+
+/// Finds data under a Library folder that no installed app owns
+#[derive(Debug, Clone)]
+pub struct OrphanFinder {
+    app_dirs: Vec<PathBuf>,
+    library: PathBuf,
+}
+
+impl OrphanFinder {
+    /// Look for leftovers under `library` of apps missing from `app_dirs`
+    pub fn new(app_dirs: Vec<PathBuf>, library: PathBuf) -> Self {
+        Self { app_dirs, library }
+    }
+
+    /// Leftovers grouped by bundle identifier, largest first
+    ///
+    /// Stops early with the groups found so far if `cancel` fires.
+    pub fn scan(&self, cancel: &CancellationToken) -> Vec<OrphanGroup> {
+        let installed: Vec<String> = app_bundles(&self.app_dirs)
+            .iter()
+            .filter_map(|bundle| bundle_identifier(bundle))
+            .map(|id| id.to_lowercase())
+            .collect();
+
+        let mut groups: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+        for (bundle_id, path) in self.candidates() {
+            if !is_installed(&bundle_id, &installed) {
+                groups.entry(bundle_id).or_default().push(path);
+            }
+        }
+
+        let mut orphans = Vec::new();
+        for (bundle_id, mut paths) in groups {
+            if cancel.is_cancelled() {
+                break;
+            }
+            paths.sort();
+            let leftovers: Vec<Leftover> = paths
+                .into_iter()
+                .map(|path| Leftover {
+                    size: size_of(&path, cancel),
+                    path,
+                })
+                .collect();
+            orphans.push(OrphanGroup {
+                bundle_id,
+                size: leftovers.iter().map(|leftover| leftover.size).sum(),
+                leftovers,
+            });
+        }
+        orphans.sort_by(|a, b| b.size.cmp(&a.size).then(a.bundle_id.cmp(&b.bundle_id)));
+        orphans
+    }
+
+    /// Entries named after a bundle identifier, with that identifier
+    fn candidates(&self) -> Vec<(String, PathBuf)> {
+        let entries = |folder: &str| {
+            std::fs::read_dir(self.library.join(folder))
+                .into_iter()
+                .flat_map(|entries| entries.flatten())
+                .map(|entry| {
+                    (
+                        entry.file_name().to_string_lossy().to_string(),
+                        entry.path(),
+                    )
+                })
+        };
+        let mut candidates: Vec<(String, PathBuf)> = DATA_BY_BUNDLE_ID
+            .iter()
+            .flat_map(|folder| entries(folder))
+            .filter(|(name, path)| looks_like_bundle_id(name) && path.is_dir())
+            .collect();
+        candidates.extend(entries(PREFERENCES).filter_map(|(name, path)| {
+            let id = name.strip_suffix(".plist")?;
+            looks_like_bundle_id(id).then(|| (id.to_string(), path))
+        }));
+        candidates
+            .into_iter()
+            .filter(|(id, _)| {
+                let id = id.to_lowercase();
+                !SYSTEM_PREFIXES.iter().any(|prefix| id.starts_with(prefix))
+            })
+            .collect()
+    }
+}
+
+/// Whether `name` is a reverse-DNS identifier such as `com.example.editor`
+fn looks_like_bundle_id(name: &str) -> bool {
+    let parts: Vec<&str> = name.split('.').collect();
+    parts.len() >= 3
+        && parts.iter().all(|part| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+        && parts[0].chars().all(|c| c.is_ascii_alphabetic())
+}
+
+/// Whether `id` belongs to one of the `installed` identifiers, which are
+/// lowercase
+///
+/// Identifiers are compared case-insensitively, and one that extends the
+/// other at a dot counts as the same app.
+fn is_installed(id: &str, installed: &[String]) -> bool {
+    let id = id.to_lowercase();
+    let extends = |longer: &str, shorter: &str| {
+        longer
+            .strip_prefix(shorter)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+    };
+    installed
+        .iter()
+        .any(|app| extends(&id, app) || extends(app, &id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    fn write(root: &Path, relative: &str, contents: &[u8]) {
+        let path = root.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_is_installed() {
+        let installed = vec!["com.example.editor".to_string()];
+        assert!(is_installed("com.example.editor", &installed));
+        assert!(is_installed("com.Example.Editor.ShipIt", &installed));
+        assert!(!is_installed("com.example.editorial", &installed));
+        assert!(!is_installed("org.other.app", &installed));
+        assert!(looks_like_bundle_id("com.example.editor"));
+        assert!(!looks_like_bundle_id("Editor"));
+        assert!(!looks_like_bundle_id("notes.backup.txt "));
+        assert!(!looks_like_bundle_id("2024.01.export"));
+    }
+
+    #[test]
+    fn test_scan_groups_leftovers_by_bundle_id() {
+        let temp_dir = TempDir::new().unwrap();
+        let apps = temp_dir.path().join("Applications");
+        let library = temp_dir.path().join("Library");
+        write(
+            &apps,
+            "Editor.app/Contents/Info.plist",
+            b"<key>CFBundleIdentifier</key><string>com.example.editor</string>",
+        );
+        write(&library, "Caches/com.example.editor/cache.bin", &[0; 10]);
+        write(&library, "Caches/com.example.editor.ShipIt/log", &[0; 10]);
+        write(&library, "Caches/com.gone.player/cache.bin", &[0; 40]);
+        write(&library, "Containers/com.gone.player/Data/db", &[0; 50]);
+        write(&library, "Preferences/com.gone.player.plist", &[0; 5]);
+        write(
+            &library,
+            "Application Support/org.left.tool/state",
+            &[0; 20],
+        );
+        write(&library, "Application Support/Editor/state", &[0; 20]);
+        write(&library, "Caches/com.apple.Safari/cache.db", &[0; 30]);
+
+        let orphans =
+            OrphanFinder::new(vec![apps], library.clone()).scan(&CancellationToken::new());
+        let ids: Vec<&str> = orphans.iter().map(|o| o.bundle_id.as_str()).collect();
+        assert_eq!(ids, ["com.gone.player", "org.left.tool"]);
+        assert_eq!(orphans[0].size, 95);
+        assert_eq!(orphans[0].leftovers[1].size, 50);
+        let paths: Vec<&PathBuf> = orphans[0].leftovers.iter().map(|l| &l.path).collect();
+        assert_eq!(
+            paths,
+            [
+                &library.join("Caches/com.gone.player"),
+                &library.join("Containers/com.gone.player"),
+                &library.join("Preferences/com.gone.player.plist"),
+            ]
+        );
+    }
+}
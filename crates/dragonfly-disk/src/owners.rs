@@ -0,0 +1,115 @@
+//! Space used per owning user
+//!
+//! On a Mac shared by several people, one account's photo library or
+//! downloads can fill the disk for everyone. Totalling files by the user that
+//! owns them shows who is using the space; user names are looked up once per
+//! user rather than once per file.
+
+use dragonfly_core::domain::entities::FileEntity;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Files of one owner and the space they use
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OwnerUsage {
+    /// User id of the owner; `None` when the platform doesn't tell
+    pub uid: Option<u32>,
+    /// Account name, when the user id belongs to a known account
+    pub user: Option<String>,
+    /// Number of files
+    pub file_count: u64,
+    /// Combined size in bytes
+    pub total_size: u64,
+}
+
+/// Total `files` per owner, largest first
+pub(crate) fn by_owner(files: &[FileEntity]) -> Vec<OwnerUsage> {
+    let mut totals: HashMap<Option<u32>, (u64, u64)> = HashMap::new();
+    for file in files {
+        let (count, size) = totals.entry(file.owner_uid).or_default();
+        *count += 1;
+        *size += file.size;
+    }
+    let mut usage: Vec<OwnerUsage> = totals
+        .into_iter()
+        .map(|(uid, (file_count, total_size))| OwnerUsage {
+            uid,
+            user: uid.and_then(user_name),
+            file_count,
+            total_size,
+        })
+        .collect();
+    usage.sort_by(|a, b| {
+        b.total_size
+            .cmp(&a.total_size)
+            .then_with(|| a.uid.cmp(&b.uid))
+    });
+    usage
+}
+
+/// Account name of the user with id `uid`, if there is one
+#[cfg(unix)]
+pub fn user_name(uid: u32) -> Option<String> {
+    let mut buf = vec![0 as libc::c_char; 1024];
+    loop {
+        // SAFETY: `passwd` is plain data that getpwuid_r fills in
+        let mut entry: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut found = std::ptr::null_mut();
+        // SAFETY: every pointer is valid for the duration of the call, and
+        // `buf.len()` is the size of the buffer the strings are written to
+        let rc =
+            unsafe { libc::getpwuid_r(uid, &mut entry, buf.as_mut_ptr(), buf.len(), &mut found) };
+        if rc == libc::ERANGE && buf.len() < 1 << 20 {
+            buf.resize(buf.len() * 2, 0);
+            continue;
+        }
+        if rc != 0 || found.is_null() || entry.pw_name.is_null() {
+            return None;
+        }
+        // SAFETY: on success `pw_name` points to a NUL-terminated string in `buf`
+        let name = unsafe { std::ffi::CStr::from_ptr(entry.pw_name) };
+        return Some(name.to_string_lossy().into_owned());
+    }
+}
+
+/// Account name of the user with id `uid`, if there is one
+#[cfg(not(unix))]
+pub fn user_name(_uid: u32) -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owned(path: &str, size: u64, uid: Option<u32>) -> FileEntity {
+        let mut file = FileEntity::new(path.into(), size);
+        file.owner_uid = uid;
+        file
+    }
+
+    #[test]
+    fn test_by_owner_sorts_by_size() {
+        let files = [
+            owned("/Users/a/movie.mov", 700, Some(501)),
+            owned("/Users/b/photo.jpg", 300, Some(502)),
+            owned("/Users/b/song.mp3", 600, Some(502)),
+            owned("/Volumes/x/file", 10, None),
+        ];
+        let usage = by_owner(&files);
+        assert_eq!(usage.len(), 3);
+        assert_eq!(
+            (usage[0].uid, usage[0].file_count, usage[0].total_size),
+            (Some(502), 2, 900)
+        );
+        assert_eq!(usage[1].uid, Some(501));
+        assert_eq!(usage[2].uid, None);
+        assert_eq!(usage[2].user, None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_user_name_of_root() {
+        assert_eq!(user_name(0).as_deref(), Some("root"));
+    }
+}
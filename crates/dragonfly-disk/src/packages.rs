@@ -0,0 +1,151 @@
+//! macOS packages reported as single items
+//!
+//! Finder shows bundle directories such as `.app`, `.photoslibrary`, and
+//! `.framework` as one file, but a walk sees the thousands of small files
+//! inside them. Collapsing a file list folds everything inside a package into
+//! one entry for the package, so a top-N list says "Photos
+//! Library.photoslibrary - 180 GB" instead of naming its internals.
+
+use dragonfly_core::domain::entities::FileEntity;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Directory extensions (lowercase) that make a directory a package
+const PACKAGE_EXTENSIONS: &[&str] = &[
+    "app",
+    "appex",
+    "bundle",
+    "framework",
+    "plugin",
+    "kext",
+    "xpc",
+    "systemextension",
+    "prefpane",
+    "qlgenerator",
+    "mdimporter",
+    "saver",
+    "photoslibrary",
+    "photolibrary",
+    "aplibrary",
+    "musiclibrary",
+    "tvlibrary",
+    "imovielibrary",
+    "fcpbundle",
+    "logicx",
+    "band",
+    "xcarchive",
+    "xcodeproj",
+    "xcworkspace",
+    "playground",
+    "dsym",
+    "docset",
+    "sparsebundle",
+    "rtfd",
+    "pages",
+    "numbers",
+    "key",
+];
+
+/// Whether a directory with this name is a package
+pub fn is_package(name: &Path) -> bool {
+    name.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .is_some_and(|e| PACKAGE_EXTENSIONS.contains(&e.as_str()))
+}
+
+/// Outermost package below `root` that holds `file`, if any
+///
+/// The file's own name doesn't count, and neither do packages at or above
+/// `root`, so analyzing a package directly still lists what is inside it.
+fn package_of(root: &Path, file: &Path) -> Option<PathBuf> {
+    let relative = file.strip_prefix(root).ok()?;
+    let mut package = root.to_path_buf();
+    for component in relative.parent()?.components() {
+        package.push(component);
+        if is_package(Path::new(component.as_os_str())) {
+            return Some(package);
+        }
+    }
+    None
+}
+
+/// Replace the files inside packages below `root` with one entry per package
+///
+/// File paths may be given as scanned or resolved (as the size index keeps
+/// them).
+pub(crate) fn collapse(root: &Path, files: Vec<FileEntity>) -> Vec<FileEntity> {
+    let canonical = std::fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+    let mut packages: BTreeMap<PathBuf, Vec<FileEntity>> = BTreeMap::new();
+    let mut collapsed = Vec::with_capacity(files.len());
+    for file in files {
+        let path = Path::new(&file.path);
+        let package = package_of(root, path).or_else(|| package_of(&canonical, path));
+        match package {
+            Some(package) => packages.entry(package).or_default().push(file),
+            None => collapsed.push(file),
+        }
+    }
+    collapsed.extend(packages.into_iter().map(|(package, inner)| {
+        let mut entry = FileEntity::new(
+            package.to_string_lossy().into_owned(),
+            inner.iter().map(|f| f.size).sum(),
+        );
+        entry.allocated_size = inner.iter().map(|f| f.allocated_size).sum();
+        entry.modified = inner.iter().filter_map(|f| f.modified).max();
+        entry.package_files = Some(inner.len() as u64);
+        entry
+    }));
+    collapsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collapse_folds_outermost_package() {
+        let mut inner = FileEntity::new("/data/Xcode.app/Contents/MacOS/Xcode".into(), 100);
+        inner.allocated_size = Some(4096);
+        let files = vec![
+            inner,
+            FileEntity::new(
+                "/data/Xcode.app/Contents/Frameworks/A.framework/A".into(),
+                50,
+            ),
+            FileEntity::new(
+                "/data/Photos Library.photoslibrary/database/Photos.sqlite".into(),
+                7,
+            ),
+            FileEntity::new("/data/notes.txt".into(), 3),
+            FileEntity::new("/data/Keynote.key".into(), 9),
+        ];
+
+        let mut collapsed = collapse(Path::new("/data"), files);
+        collapsed.sort_by(|a, b| a.path.cmp(&b.path));
+        let paths: Vec<&str> = collapsed.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "/data/Keynote.key",
+                "/data/Photos Library.photoslibrary",
+                "/data/Xcode.app",
+                "/data/notes.txt"
+            ]
+        );
+        let app = &collapsed[2];
+        assert_eq!((app.size, app.package_files), (150, Some(2)));
+        // One inner file's allocation is unknown, so the package's is too
+        assert_eq!(app.allocated_size, None);
+        assert_eq!(collapsed[0].package_files, None);
+    }
+
+    #[test]
+    fn test_collapse_keeps_contents_of_analyzed_package() {
+        let root = Path::new("/Users/me/Pictures/Photos Library.photoslibrary");
+        let files = vec![FileEntity::new(
+            format!("{}/originals/0/IMG.HEIC", root.display()),
+            10,
+        )];
+        assert_eq!(collapse(root, files)[0].package_files, None);
+    }
+}
@@ -0,0 +1,284 @@
+//! Photos library storage analysis
+//!
+//! The Photos library is a package directory that is often the single largest
+//! item on a Mac. Only part of it is the user's photos: Photos also keeps
+//! thumbnails, previews, and rendered edits that it can regenerate, plus
+//! analysis caches and its database. This module sizes those parts separately
+//! and estimates whether iCloud "Optimize Mac Storage" has offloaded originals.
+//!
+//! The library is only ever read. Nothing inside it is opened for writing,
+//! moved, or deleted; Photos manages that itself.
+
+use crate::walk::{skipped_from_walk_error, walker};
+use dragonfly_core::domain::protection::SkippedPath;
+use dragonfly_core::domain::scan_options::ScanOptions;
+use dragonfly_core::error::{Error, Result};
+use dragonfly_core::ports::CancellationToken;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Location of the system Photos library, relative to the home directory
+pub const DEFAULT_PHOTOS_LIBRARY: &str = "Pictures/Photos Library.photoslibrary";
+
+/// Part of a Photos library a file belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PhotosCategory {
+    /// Original photos and videos as imported
+    Originals,
+    /// Thumbnails, previews, and rendered edits Photos can regenerate
+    Derivatives,
+    /// Analysis, sync, and other caches
+    Caches,
+    /// The library database
+    Database,
+    /// Anything else
+    Other,
+}
+
+impl PhotosCategory {
+    /// Categorize a path relative to the library root
+    pub fn from_relative(relative: &Path) -> Self {
+        let mut components = relative.iter().map(|c| c.to_string_lossy());
+        let first = components.next().unwrap_or_default();
+        let second = components.next().unwrap_or_default();
+        match (first.as_ref(), second.as_ref()) {
+            // Photos 5 and later use originals/, earlier versions Masters/
+            ("originals" | "Masters", _) => Self::Originals,
+            ("resources", "derivatives" | "renders" | "proxies" | "media") => Self::Derivatives,
+            ("Thumbnails" | "Previews", _) => Self::Derivatives,
+            ("resources", _) | ("private" | "scopes", _) => Self::Caches,
+            ("database", _) => Self::Database,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// Storage breakdown of a Photos library
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PhotosLibraryReport {
+    /// Library package that was inspected
+    pub library: PathBuf,
+    /// Total size of the package in bytes
+    pub total_size: u64,
+    /// Size of original photos and videos in bytes
+    pub originals_size: u64,
+    /// Size of regenerable thumbnails, previews, and renders in bytes
+    pub derivatives_size: u64,
+    /// Size of caches in bytes
+    pub caches_size: u64,
+    /// Size of the library database in bytes
+    pub database_size: u64,
+    /// Size of everything else in bytes
+    pub other_size: u64,
+    /// Number of photos and videos in the library
+    pub item_count: u64,
+    /// Number of items whose original is stored on this Mac
+    pub local_originals: u64,
+    /// Whether the library syncs with iCloud Photos
+    pub icloud_photos: bool,
+    /// Whether originals appear to have been offloaded to iCloud
+    pub optimized_storage: bool,
+    /// Parts of the library that couldn't be read, typically without Full
+    /// Disk Access; their size is missing from the breakdown
+    pub skipped: Vec<SkippedPath>,
+    /// Whether the scan was cancelled before completing (results are partial)
+    pub interrupted: bool,
+}
+
+/// Read-only analyzer for Photos library packages
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PhotosLibraryAnalyzer;
+
+impl PhotosLibraryAnalyzer {
+    /// Create a new Photos library analyzer
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Break down the storage used by the library at `library`
+    ///
+    /// Items are counted by asset identifier across originals and derivatives:
+    /// with optimized storage, Photos keeps previews of every item but only
+    /// some originals, so fewer local originals than items on an iCloud
+    /// library means originals have been offloaded.
+    pub async fn analyze(
+        &self,
+        library: &Path,
+        cancel: &CancellationToken,
+    ) -> Result<PhotosLibraryReport> {
+        if !library.is_dir() {
+            return Err(Error::NotFound(format!(
+                "Photos library does not exist: {}",
+                library.display()
+            )));
+        }
+
+        let mut report = PhotosLibraryReport {
+            library: library.to_path_buf(),
+            icloud_photos: library.join("resources/cpl").is_dir(),
+            ..Default::default()
+        };
+        let mut items = HashSet::new();
+        let mut originals = HashSet::new();
+
+        // Hidden files are part of the package's footprint too
+        let options = ScanOptions::new().with_hidden(true);
+        for entry in walker(library, &options)
+            .into_iter()
+            .take_while(|_| !cancel.is_cancelled())
+        {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    report.skipped.extend(skipped_from_walk_error(&err));
+                    continue;
+                }
+            };
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(err) => {
+                    report.skipped.extend(skipped_from_walk_error(&err));
+                    continue;
+                }
+            };
+            if !metadata.is_file() {
+                continue;
+            }
+
+            let path = entry.path();
+            let Ok(relative) = path.strip_prefix(library) else {
+                continue;
+            };
+            let size = metadata.len();
+            report.total_size += size;
+
+            let category = PhotosCategory::from_relative(relative);
+            match category {
+                PhotosCategory::Originals => report.originals_size += size,
+                PhotosCategory::Derivatives => report.derivatives_size += size,
+                PhotosCategory::Caches => report.caches_size += size,
+                PhotosCategory::Database => report.database_size += size,
+                PhotosCategory::Other => report.other_size += size,
+            }
+
+            if matches!(
+                category,
+                PhotosCategory::Originals | PhotosCategory::Derivatives
+            ) {
+                if let Some(asset) = asset_id(&path) {
+                    if category == PhotosCategory::Originals {
+                        originals.insert(asset.clone());
+                    }
+                    items.insert(asset);
+                }
+            }
+        }
+
+        report.item_count = items.len() as u64;
+        report.local_originals = originals.len() as u64;
+        report.optimized_storage =
+            report.icloud_photos && report.local_originals < report.item_count;
+        report.interrupted = cancel.is_cancelled();
+        Ok(report)
+    }
+}
+
+/// Asset identifier embedded in a library file name
+///
+/// Originals are stored as `<UUID>.<ext>` and derivatives as
+/// `<UUID>_<variant>.<ext>`; Photos' own sidecar files (`.aae` edit
+/// descriptions, `.plist` metadata) don't name assets. Libraries from before
+/// Photos 5 keep camera file names under `Masters/`, so their counts are
+/// approximate.
+fn asset_id(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    if matches!(extension.as_str(), "aae" | "plist" | "db" | "sqlite") {
+        return None;
+    }
+    let name = path.file_name()?.to_string_lossy();
+    let id = name.split(['_', '.']).next()?;
+    (!id.is_empty()).then(|| id.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn write(root: &Path, relative: &str, size: usize) {
+        let path = root.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, vec![0u8; size]).unwrap();
+    }
+
+    #[test]
+    fn test_categories() {
+        let cases = [
+            ("originals/A/ABC.heic", PhotosCategory::Originals),
+            ("Masters/2019/IMG_1.JPG", PhotosCategory::Originals),
+            (
+                "resources/derivatives/A/ABC_1_105_c.jpeg",
+                PhotosCategory::Derivatives,
+            ),
+            ("resources/cpl/cloudsync.noindex/x", PhotosCategory::Caches),
+            ("database/Photos.sqlite", PhotosCategory::Database),
+            ("Info.plist", PhotosCategory::Other),
+        ];
+        for (relative, expected) in cases {
+            assert_eq!(
+                PhotosCategory::from_relative(Path::new(relative)),
+                expected,
+                "{}",
+                relative
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_analyze_breaks_down_library() {
+        let temp_dir = TempDir::new().unwrap();
+        let library = temp_dir.path().join("Photos Library.photoslibrary");
+        write(&library, "originals/A/AAA.heic", 100);
+        write(&library, "originals/A/AAA.aae", 1);
+        write(&library, "resources/derivatives/A/AAA_1_105_c.jpeg", 10);
+        write(&library, "resources/derivatives/B/BBB_1_105_c.jpeg", 10);
+        write(&library, "resources/caches/compute/x.bin", 5);
+        write(&library, "database/Photos.sqlite", 20);
+
+        let report = PhotosLibraryAnalyzer::new()
+            .analyze(&library, &CancellationToken::new())
+            .await
+            .unwrap();
+
+        assert_eq!(report.total_size, 146);
+        assert_eq!(report.originals_size, 101);
+        assert_eq!(report.derivatives_size, 20);
+        assert_eq!(report.caches_size, 5);
+        assert_eq!(report.database_size, 20);
+        assert_eq!(report.item_count, 2);
+        assert_eq!(report.local_originals, 1);
+        assert!(!report.icloud_photos);
+        assert!(!report.optimized_storage);
+
+        // The same library synced with iCloud has an original offloaded
+        fs::create_dir_all(library.join("resources/cpl")).unwrap();
+        let report = PhotosLibraryAnalyzer::new()
+            .analyze(&library, &CancellationToken::new())
+            .await
+            .unwrap();
+        assert!(report.icloud_photos);
+        assert!(report.optimized_storage);
+    }
+
+    #[tokio::test]
+    async fn test_missing_library() {
+        let temp_dir = TempDir::new().unwrap();
+        let result = PhotosLibraryAnalyzer::new()
+            .analyze(&temp_dir.path().join("missing"), &CancellationToken::new())
+            .await;
+        assert!(matches!(result, Err(Error::NotFound(_))));
+    }
+}
@@ -0,0 +1,381 @@
+//! Sample-based size estimation for [`AnalysisStrategy::Quick`]
+//!
+//! Walking every file of a multi-terabyte volume takes minutes. Instead, the
+//! top few levels are read exactly and each directory below them is probed a
+//! few times along random paths: a probe descends into one randomly chosen
+//! child directory after another, and weighs the files it sees by how many
+//! siblings it skipped on the way down (Knuth's tree-size estimator). The
+//! probes' average is an unbiased estimate of the directory's size, and their
+//! spread gives a confidence interval.
+//!
+//! Trees whose siblings are alike (photo libraries, caches, build outputs)
+//! estimate well; a single huge file deep inside one of many directories is
+//! the worst case. Such a file usually shows up as a wide interval, but when
+//! no probe happens to pass it the interval is too narrow, so treat it as a
+//! guide rather than a guarantee.
+//!
+//! [`AnalysisStrategy::Quick`]: crate::strategies::AnalysisStrategy::Quick
+
+use crate::walk::{device_id, leads_back};
+use dragonfly_core::domain::protection::SkippedPath;
+use dragonfly_core::domain::scan_options::ScanOptions;
+use dragonfly_core::ports::CancellationToken;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Probes spread across the tree by default
+pub const DEFAULT_PROBES: usize = 4000;
+
+/// Probes per sampled directory, however many there are, so each one gets a
+/// spread to measure
+const MIN_PROBES_PER_DIR: usize = 8;
+
+/// Standard normal quantile for a two-sided 95% interval
+const Z_95: f64 = 1.96;
+
+/// Approximate size of a directory tree
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QuickEstimate {
+    /// Estimated combined size of every file, in bytes
+    pub total_size: u64,
+    /// Half-width of the 95% confidence interval around `total_size`
+    pub margin: u64,
+    /// Estimated number of files
+    pub file_count: u64,
+    /// Random probes taken
+    pub probes: usize,
+    /// Directories read while probing
+    pub directories_read: u64,
+    /// Directories a probe couldn't read; the estimate leaves them out
+    pub skipped: Vec<SkippedPath>,
+    /// Whether sampling was cancelled before completing (the estimate covers
+    /// fewer probes, or misses subdirectories)
+    pub interrupted: bool,
+}
+
+impl QuickEstimate {
+    /// Lower and upper bound of the 95% confidence interval
+    pub fn range(&self) -> (u64, u64) {
+        (
+            self.total_size.saturating_sub(self.margin),
+            self.total_size.saturating_add(self.margin),
+        )
+    }
+}
+
+/// Files and subdirectories directly inside a directory
+#[derive(Debug, Default)]
+struct Listing {
+    bytes: u64,
+    files: u64,
+    subdirs: Vec<PathBuf>,
+}
+
+/// What sampling needs to know to read directories like a walk would
+struct Sampler<'a> {
+    options: &'a ScanOptions,
+    device: Option<u64>,
+}
+
+impl Sampler<'_> {
+    /// Read `dir`, which is `level` levels below the root
+    fn list(&self, dir: &Path, level: usize) -> std::io::Result<Listing> {
+        let mut listing = Listing::default();
+        let files_included = self.options.max_depth.map_or(true, |max| level < max);
+        let descend = self.options.max_depth.map_or(true, |max| level + 1 < max);
+        for entry in std::fs::read_dir(dir)? {
+            let Ok(entry) = entry else {
+                continue;
+            };
+            let path = entry.path();
+            if !self.options.include_hidden && entry.file_name().to_string_lossy().starts_with('.')
+            {
+                continue;
+            }
+            if !self.options.excludes.is_empty()
+                && self.options.is_excluded(&path.to_string_lossy())
+            {
+                continue;
+            }
+            let metadata = if self.options.follow_symlinks {
+                std::fs::metadata(&path)
+            } else {
+                entry.metadata()
+            };
+            let Ok(metadata) = metadata else {
+                continue;
+            };
+            if metadata.is_dir()
+                && entry.file_type().is_ok_and(|t| t.is_symlink())
+                && leads_back(dir, &path)
+            {
+                continue;
+            }
+            if metadata.is_file() && files_included {
+                listing.bytes += metadata.len();
+                listing.files += 1;
+            } else if metadata.is_dir() && descend && self.same_device(&path) {
+                listing.subdirs.push(path);
+            }
+        }
+        // Read order varies between filesystems; sorting keeps a seed's
+        // choices reproducible
+        listing.subdirs.sort();
+        Ok(listing)
+    }
+
+    fn same_device(&self, path: &Path) -> bool {
+        self.device
+            .map_or(true, |device| device_id(path).map_or(true, |d| d == device))
+    }
+
+    /// One random descent from `start` (at `level`): estimated bytes and
+    /// files of the tree under `start`
+    fn probe(
+        &self,
+        start: &Path,
+        level: usize,
+        rng: &mut fastrand::Rng,
+        stats: &mut StratumStats,
+    ) -> (f64, f64) {
+        let (mut bytes, mut files) = (0.0, 0.0);
+        let mut weight = 1.0;
+        let mut dir = start.to_path_buf();
+        let mut level = level;
+        let mut at_start = true;
+        loop {
+            stats.directories_read += 1;
+            let listing = match self.list(&dir, level) {
+                Ok(listing) => listing,
+                Err(err) => {
+                    stats
+                        .skipped
+                        .extend(SkippedPath::from_io_error(&dir.to_string_lossy(), &err));
+                    break;
+                }
+            };
+            bytes += weight * listing.bytes as f64;
+            files += weight * listing.files as f64;
+            if listing.subdirs.is_empty() {
+                break;
+            }
+            if at_start {
+                stats.leaf_start = false;
+                at_start = false;
+            }
+            weight *= listing.subdirs.len() as f64;
+            dir = listing.subdirs[rng.usize(..listing.subdirs.len())].clone();
+            level += 1;
+        }
+        (bytes, files)
+    }
+}
+
+/// Probing results for one directory below the exactly read levels
+#[derive(Debug, Default)]
+struct StratumStats {
+    bytes: f64,
+    bytes_variance: f64,
+    files: f64,
+    probes: usize,
+    directories_read: u64,
+    skipped: Vec<SkippedPath>,
+    /// Whether the first probe found no subdirectories below the start, in
+    /// which case that one probe is exact
+    leaf_start: bool,
+}
+
+/// Estimate the size of the tree under `root` from `probes` random probes
+///
+/// The top of the tree is read exactly, a level at a time, for as long as
+/// that stays within `probes` directories; the probes then start from the
+/// directories below the exactly read levels. Big trees usually fan out near
+/// the top, so this splits them into many small, similar parts and narrows
+/// the interval considerably.
+///
+/// `seed` fixes the random choices, so the same tree gives the same estimate.
+pub(crate) fn estimate(
+    root: &Path,
+    options: &ScanOptions,
+    probes: usize,
+    seed: u64,
+    cancel: &CancellationToken,
+) -> std::io::Result<QuickEstimate> {
+    let sampler = Sampler {
+        options,
+        device: if options.one_filesystem {
+            device_id(root)
+        } else {
+            None
+        },
+    };
+    let top = sampler.list(root, 0)?;
+    let mut estimate = QuickEstimate {
+        directories_read: 1,
+        ..QuickEstimate::default()
+    };
+    let (mut bytes, mut files) = (top.bytes as f64, top.files as f64);
+
+    // Read whole levels while the next one leaves enough probes per directory
+    let max_strata = (probes / MIN_PROBES_PER_DIR).max(1);
+    let (mut strata, mut level) = (top.subdirs, 1);
+    while !strata.is_empty()
+        && !cancel.is_cancelled()
+        && estimate.directories_read + strata.len() as u64 <= probes as u64
+    {
+        let listings: Vec<(&PathBuf, std::io::Result<Listing>)> = strata
+            .par_iter()
+            .map(|dir| (dir, sampler.list(dir, level)))
+            .collect();
+        let below: usize = listings
+            .iter()
+            .filter_map(|(_, listing)| listing.as_ref().ok())
+            .map(|listing| listing.subdirs.len())
+            .sum();
+        if below > max_strata {
+            break;
+        }
+        let mut next = Vec::with_capacity(below);
+        for (dir, listing) in listings {
+            estimate.directories_read += 1;
+            match listing {
+                Ok(listing) => {
+                    bytes += listing.bytes as f64;
+                    files += listing.files as f64;
+                    next.extend(listing.subdirs);
+                }
+                Err(err) => estimate
+                    .skipped
+                    .extend(SkippedPath::from_io_error(&dir.to_string_lossy(), &err)),
+            }
+        }
+        strata = next;
+        level += 1;
+    }
+
+    let per_dir = (probes / strata.len().max(1)).max(MIN_PROBES_PER_DIR);
+    let sampled: Vec<StratumStats> = strata
+        .par_iter()
+        .enumerate()
+        .map(|(index, dir)| {
+            let mut rng = fastrand::Rng::with_seed(seed.wrapping_add(index as u64));
+            sample_stratum(&sampler, dir, level, per_dir, &mut rng, cancel)
+        })
+        .collect();
+
+    let mut variance = 0.0;
+    for stratum in sampled {
+        bytes += stratum.bytes;
+        files += stratum.files;
+        variance += stratum.bytes_variance;
+        estimate.probes += stratum.probes;
+        estimate.directories_read += stratum.directories_read;
+        estimate.skipped.extend(stratum.skipped);
+    }
+    estimate.total_size = bytes.round() as u64;
+    estimate.file_count = files.round() as u64;
+    estimate.margin = (Z_95 * variance.sqrt()).round() as u64;
+    estimate.interrupted = cancel.is_cancelled();
+    Ok(estimate)
+}
+
+/// Probe `dir` (at `level`) up to `probes` times: mean estimate and variance
+/// of the mean
+fn sample_stratum(
+    sampler: &Sampler<'_>,
+    dir: &Path,
+    level: usize,
+    probes: usize,
+    rng: &mut fastrand::Rng,
+    cancel: &CancellationToken,
+) -> StratumStats {
+    let mut stats = StratumStats {
+        leaf_start: true,
+        ..StratumStats::default()
+    };
+    let mut samples = Vec::with_capacity(probes);
+    while samples.len() < probes && !cancel.is_cancelled() {
+        samples.push(sampler.probe(dir, level, rng, &mut stats));
+        if stats.leaf_start {
+            // Nothing to choose between: one look is exact
+            break;
+        }
+    }
+
+    stats.probes = samples.len();
+    if samples.is_empty() {
+        return stats;
+    }
+    let n = samples.len() as f64;
+    stats.bytes = samples.iter().map(|(bytes, _)| bytes).sum::<f64>() / n;
+    stats.files = samples.iter().map(|(_, files)| files).sum::<f64>() / n;
+    if samples.len() > 1 {
+        let squares: f64 = samples
+            .iter()
+            .map(|(bytes, _)| (bytes - stats.bytes).powi(2))
+            .sum();
+        stats.bytes_variance = squares / (n - 1.0) / n;
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_uniform_tree_is_estimated_exactly() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(root.join("top.bin"), vec![0u8; 7]).unwrap();
+        for a in ["a", "b", "c"] {
+            for b in ["x", "y"] {
+                let dir = root.join(a).join(b);
+                fs::create_dir_all(&dir).unwrap();
+                fs::write(dir.join("file.bin"), vec![0u8; 100]).unwrap();
+            }
+            fs::write(root.join(a).join("side.bin"), vec![0u8; 10]).unwrap();
+        }
+
+        let estimate = estimate(
+            root,
+            &ScanOptions::default(),
+            DEFAULT_PROBES,
+            1,
+            &CancellationToken::new(),
+        )
+        .unwrap();
+        assert_eq!(estimate.total_size, 7 + 3 * (10 + 2 * 100));
+        assert_eq!(estimate.file_count, 1 + 3 * 3);
+        assert_eq!(estimate.margin, 0);
+        assert!(!estimate.interrupted);
+    }
+
+    #[test]
+    fn test_uneven_tree_reports_a_margin() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        for (name, size) in [("small", 10), ("big", 10_000)] {
+            let dir = root.join("data").join(name);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("file.bin"), vec![0u8; size]).unwrap();
+        }
+
+        // Too few probes to read `data` exactly, so it is sampled
+        let estimate = estimate(
+            root,
+            &ScanOptions::default(),
+            4,
+            7,
+            &CancellationToken::new(),
+        )
+        .unwrap();
+        assert!(estimate.margin > 0);
+        let (low, high) = estimate.range();
+        assert!(low <= 10_010 && 10_010 <= high);
+        assert_eq!(estimate.probes, MIN_PROBES_PER_DIR);
+    }
+}
@@ -0,0 +1,357 @@
+//! Scan results saved to a file, and the differences between two of them
+//!
+//! `disk analyze --save` writes every file of a scan with its size. Comparing
+//! two such files later answers "what ate 40 GB since last week?" for any
+//! folder, on any filesystem, without snapshots or anything running in
+//! between: directories that grew or shrank the most, new large files, and
+//! files that are gone.
+//!
+//! Scans saved under a name live in `~/.dragonfly/scans/<name>.json`, where
+//! `disk snapshots` lists them and queries them again without rescanning.
+
+use crate::analyzer::AnalysisResult;
+use crate::snapshot_diff::PathGrowth;
+use chrono::{DateTime, Utc};
+use dragonfly_core::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Version of the saved scan format, bumped on incompatible changes
+const FORMAT_VERSION: u32 = 1;
+
+/// A file in a saved scan
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedFile {
+    /// Path relative to the scanned root
+    pub path: PathBuf,
+    /// Size in bytes
+    pub size: u64,
+}
+
+/// Every file of a complete scan, as written by `disk analyze --save`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedScan {
+    version: u32,
+    /// Scanned directory
+    pub root: PathBuf,
+    /// When the scan finished
+    pub scanned_at: DateTime<Utc>,
+    /// Combined size of the files in bytes
+    pub total_size: u64,
+    /// Files found
+    pub files: Vec<SavedFile>,
+}
+
+impl SavedScan {
+    /// The files `result` found under `root`, scanned now
+    ///
+    /// File paths may be given as scanned or resolved (as the size index
+    /// keeps them); either way they are saved relative to `root`.
+    pub fn from_result(root: &Path, result: &AnalysisResult) -> Self {
+        let canonical = std::fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+        let files = result
+            .files
+            .iter()
+            .map(|file| {
+                let path = Path::new(&file.path);
+                let relative = path
+                    .strip_prefix(root)
+                    .or_else(|_| path.strip_prefix(&canonical))
+                    .unwrap_or(path);
+                SavedFile {
+                    path: relative.to_path_buf(),
+                    size: file.size,
+                }
+            })
+            .collect();
+        Self {
+            version: FORMAT_VERSION,
+            root: canonical,
+            scanned_at: Utc::now(),
+            total_size: result.total_size,
+            files,
+        }
+    }
+
+    /// Directory scans saved by name are kept in (`~/.dragonfly/scans`)
+    pub fn default_dir() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_else(|| PathBuf::from("~"))
+            .join(".dragonfly")
+            .join("scans")
+    }
+
+    /// File of the scan saved as `name` in `dir`
+    ///
+    /// Names are letters, digits, `-`, `_`, and `.`, not starting with a dot,
+    /// so they can't point outside `dir`.
+    pub fn named(dir: &Path, name: &str) -> Result<PathBuf> {
+        let valid = !name.is_empty()
+            && !name.starts_with('.')
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if !valid {
+            return Err(Error::InvalidInput(format!(
+                "Invalid scan name: {} (use letters, digits, -, _ and .)",
+                name
+            )));
+        }
+        Ok(dir.join(format!("{}.json", name)))
+    }
+
+    /// Every scan saved by name in `dir`, newest first
+    ///
+    /// Files that aren't saved scans of this version are left out.
+    pub fn list(dir: &Path) -> Result<Vec<SavedScanInfo>> {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut scans: Vec<SavedScanInfo> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| {
+                let scan = Self::load(&path)
+                    .map_err(|e| tracing::debug!("Skipping {}: {}", path.display(), e))
+                    .ok()?;
+                Some(SavedScanInfo {
+                    name: path.file_stem()?.to_string_lossy().into_owned(),
+                    root: scan.root,
+                    scanned_at: scan.scanned_at,
+                    total_size: scan.total_size,
+                    file_count: scan.files.len(),
+                    path,
+                })
+            })
+            .collect();
+        scans.sort_by_key(|scan| std::cmp::Reverse(scan.scanned_at));
+        Ok(scans)
+    }
+
+    /// Write the scan to `path` as JSON
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_vec(self)
+            .map_err(|e| Error::Internal(format!("Failed to encode scan: {}", e)))?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// Read a scan written by [`SavedScan::save`]
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read(path)?;
+        let scan: Self = serde_json::from_slice(&content).map_err(|e| {
+            Error::InvalidInput(format!("Not a saved scan: {}: {}", path.display(), e))
+        })?;
+        if scan.version != FORMAT_VERSION {
+            return Err(Error::NotSupported(format!(
+                "Saved scan {} has format version {}, expected {}",
+                path.display(),
+                scan.version,
+                FORMAT_VERSION
+            )));
+        }
+        Ok(scan)
+    }
+
+    /// What changed between this scan and a `later` one
+    ///
+    /// Directory changes are grouped `depth` levels below the root, so growth
+    /// spread over many small files still shows up in one place. Only new and
+    /// deleted files of at least `min_size` bytes are listed.
+    pub fn diff(&self, later: &SavedScan, depth: usize, min_size: u64) -> ScanDiffReport {
+        let before_dirs = directory_sizes(&self.files, depth);
+        let after_dirs = directory_sizes(&later.files, depth);
+        let keys: BTreeSet<&PathBuf> = before_dirs.keys().chain(after_dirs.keys()).collect();
+        let (mut grown, mut shrunk): (Vec<PathGrowth>, Vec<PathGrowth>) = keys
+            .into_iter()
+            .map(|key| PathGrowth {
+                path: later.root.join(key),
+                before: before_dirs.get(key).copied().unwrap_or(0),
+                after: after_dirs.get(key).copied().unwrap_or(0),
+            })
+            .filter(|change| change.growth() != 0)
+            .partition(|change| change.growth() > 0);
+        grown.sort_by_key(|change| std::cmp::Reverse(change.growth()));
+        shrunk.sort_by_key(PathGrowth::growth);
+
+        ScanDiffReport {
+            root: later.root.clone(),
+            scanned_before: self.scanned_at,
+            scanned_after: later.scanned_at,
+            size_before: self.total_size,
+            size_after: later.total_size,
+            grown,
+            shrunk,
+            new_files: missing_from(&later.files, &self.files, min_size),
+            deleted_files: missing_from(&self.files, &later.files, min_size),
+        }
+    }
+}
+
+/// A scan saved by name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SavedScanInfo {
+    /// Name it was saved under
+    pub name: String,
+    /// File it is stored in
+    pub path: PathBuf,
+    /// Scanned directory
+    pub root: PathBuf,
+    /// When the scan finished
+    pub scanned_at: DateTime<Utc>,
+    /// Combined size of the files in bytes
+    pub total_size: u64,
+    /// Number of files
+    pub file_count: usize,
+}
+
+/// Differences between two saved scans
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanDiffReport {
+    /// Directory of the later scan
+    pub root: PathBuf,
+    /// When the earlier scan finished
+    pub scanned_before: DateTime<Utc>,
+    /// When the later scan finished
+    pub scanned_after: DateTime<Utc>,
+    /// Total size in the earlier scan in bytes
+    pub size_before: u64,
+    /// Total size in the later scan in bytes
+    pub size_after: u64,
+    /// Directories that grew, largest growth first
+    pub grown: Vec<PathGrowth>,
+    /// Directories that shrank, largest decrease first
+    pub shrunk: Vec<PathGrowth>,
+    /// Files only in the later scan, largest first
+    pub new_files: Vec<SavedFile>,
+    /// Files only in the earlier scan, largest first
+    pub deleted_files: Vec<SavedFile>,
+}
+
+impl ScanDiffReport {
+    /// Net change of the whole tree in bytes
+    pub fn net_change(&self) -> i64 {
+        self.size_after as i64 - self.size_before as i64
+    }
+}
+
+/// File sizes summed per directory `depth` levels below the root; files
+/// directly in the root belong to no directory
+fn directory_sizes(files: &[SavedFile], depth: usize) -> HashMap<PathBuf, u64> {
+    let mut sizes = HashMap::new();
+    for file in files {
+        let Some(parent) = file.path.parent() else {
+            continue;
+        };
+        let key: PathBuf = parent.components().take(depth.max(1)).collect();
+        if !key.as_os_str().is_empty() {
+            *sizes.entry(key).or_default() += file.size;
+        }
+    }
+    sizes
+}
+
+/// Files of `files` of at least `min_size` bytes that `other` doesn't have,
+/// largest first
+fn missing_from(files: &[SavedFile], other: &[SavedFile], min_size: u64) -> Vec<SavedFile> {
+    let present: BTreeSet<&Path> = other.iter().map(|file| file.path.as_path()).collect();
+    let mut missing: Vec<SavedFile> = files
+        .iter()
+        .filter(|file| file.size >= min_size && !present.contains(file.path.as_path()))
+        .cloned()
+        .collect();
+    missing.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+    missing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn scan(files: &[(&str, u64)]) -> SavedScan {
+        SavedScan {
+            version: FORMAT_VERSION,
+            root: PathBuf::from("/data"),
+            scanned_at: Utc::now(),
+            total_size: files.iter().map(|(_, size)| size).sum(),
+            files: files
+                .iter()
+                .map(|(path, size)| SavedFile {
+                    path: PathBuf::from(path),
+                    size: *size,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("scan.json");
+        let saved = scan(&[("a/b.bin", 10)]);
+        saved.save(&path).unwrap();
+        assert_eq!(SavedScan::load(&path).unwrap(), saved);
+
+        std::fs::write(&path, "{}").unwrap();
+        assert!(SavedScan::load(&path).is_err());
+    }
+
+    #[test]
+    fn test_named_scans() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().join("scans");
+        assert!(SavedScan::list(&dir).unwrap().is_empty());
+        assert!(SavedScan::named(&dir, "../escape").is_err());
+        assert!(SavedScan::named(&dir, ".hidden").is_err());
+
+        let mut older = scan(&[("a.bin", 10)]);
+        older.scanned_at -= chrono::Duration::days(1);
+        older
+            .save(&SavedScan::named(&dir, "monday").unwrap())
+            .unwrap();
+        scan(&[("a.bin", 10), ("b.bin", 5)])
+            .save(&SavedScan::named(&dir, "tuesday").unwrap())
+            .unwrap();
+        std::fs::write(dir.join("notes.json"), "{}").unwrap();
+
+        let scans = SavedScan::list(&dir).unwrap();
+        let names: Vec<&str> = scans.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["tuesday", "monday"]);
+        assert_eq!((scans[0].file_count, scans[0].total_size), (2, 15));
+    }
+
+    #[test]
+    fn test_diff() {
+        let before = scan(&[
+            ("videos/old.mov", 500),
+            ("videos/kept.mov", 100),
+            ("docs/a.txt", 300),
+            ("top.bin", 50),
+        ]);
+        let after = scan(&[
+            ("videos/kept.mov", 100),
+            ("videos/new.mov", 2000),
+            ("docs/a.txt", 100),
+            ("top.bin", 50),
+        ]);
+
+        let report = before.diff(&after, 1, 200);
+        assert_eq!(report.net_change(), 1300);
+        assert_eq!(report.grown.len(), 1);
+        assert_eq!(report.grown[0].path, Path::new("/data/videos"));
+        assert_eq!(report.grown[0].growth(), 1500);
+        assert_eq!(report.shrunk[0].path, Path::new("/data/docs"));
+        assert_eq!(report.shrunk[0].growth(), -200);
+        assert_eq!(report.new_files[0].path, Path::new("videos/new.mov"));
+        assert_eq!(report.deleted_files.len(), 1);
+        assert_eq!(report.deleted_files[0].path, Path::new("videos/old.mov"));
+    }
+}
@@ -0,0 +1,241 @@
+//! Old screenshot detection
+//!
+//! Screenshots pile up on the Desktop (or wherever the screenshot location
+//! points) and are rarely looked at again after a few days. They are found by
+//! their default names and, where Spotlight is available, by the
+//! `kMDItemIsScreenCapture` flag macOS sets on every capture, which also
+//! catches localized and renamed ones.
+
+use crate::spotlight;
+use crate::walk::{skipped_from_walk_error, walker};
+use chrono::{DateTime, Utc};
+use dragonfly_core::domain::protection::{PathProtection, SkippedPath};
+use dragonfly_core::domain::scan_options::ScanOptions;
+use dragonfly_core::ports::CancellationToken;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Name prefixes used by macOS and common capture tools
+const NAME_PREFIXES: &[&str] = &[
+    "Screenshot ",
+    "Screen Shot ",
+    "Screen Recording ",
+    "CleanShot ",
+];
+
+/// Extensions screenshots and screen recordings are saved with
+const EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "heic", "tiff", "gif", "pdf", "mov", "mp4",
+];
+
+/// A screenshot or screen recording
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Screenshot {
+    /// File path
+    pub path: PathBuf,
+    /// Size in bytes
+    pub size: u64,
+    /// When the file was created
+    pub taken: DateTime<Utc>,
+    /// Protection level of the path
+    pub protection: PathProtection,
+}
+
+/// Screenshots older than the cutoff
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScreenshotReport {
+    /// Folders that were searched
+    pub folders: Vec<PathBuf>,
+    /// Screenshots, oldest first
+    pub screenshots: Vec<Screenshot>,
+    /// Combined size in bytes
+    pub total_size: u64,
+    /// Screenshot folders that couldn't be read
+    pub skipped: Vec<SkippedPath>,
+    /// Whether the search was cancelled before completing (results are partial)
+    pub interrupted: bool,
+}
+
+/// Finds screenshots taken before a cutoff
+#[derive(Debug, Clone)]
+pub struct ScreenshotFinder {
+    folders: Vec<PathBuf>,
+}
+
+impl ScreenshotFinder {
+    /// Search `folders` (and their subfolders)
+    pub fn new(folders: Vec<PathBuf>) -> Self {
+        Self { folders }
+    }
+
+    /// The Desktop plus the configured screenshot location, if different
+    pub fn default_folders() -> Vec<PathBuf> {
+        let mut folders: Vec<PathBuf> = dirs::desktop_dir().into_iter().collect();
+        if let Some(location) = screenshot_location() {
+            if !folders.contains(&location) {
+                folders.push(location);
+            }
+        }
+        folders
+    }
+
+    /// Screenshots created before `cutoff`
+    ///
+    /// Folders that don't exist are skipped.
+    pub async fn find(
+        &self,
+        cutoff: DateTime<Utc>,
+        cancel: &CancellationToken,
+    ) -> ScreenshotReport {
+        let mut report = ScreenshotReport {
+            folders: self.folders.clone(),
+            ..Default::default()
+        };
+        let mut seen = HashSet::new();
+        let options = ScanOptions::default();
+
+        for folder in self.folders.iter().filter(|folder| folder.is_dir()) {
+            let captures: HashSet<PathBuf> = spotlight::screen_captures(folder)
+                .unwrap_or_default()
+                .into_iter()
+                .collect();
+            for entry in walker(folder, &options) {
+                if cancel.is_cancelled() {
+                    report.interrupted = true;
+                    return finish(report);
+                }
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(err) => {
+                        report.skipped.extend(skipped_from_walk_error(&err));
+                        continue;
+                    }
+                };
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+                let path = entry.path();
+                if !(captures.contains(&path) || is_screenshot_name(&path)) {
+                    continue;
+                }
+                let Ok(metadata) = entry.metadata() else {
+                    continue;
+                };
+                let Some(taken) = metadata.created().or_else(|_| metadata.modified()).ok() else {
+                    continue;
+                };
+                let taken = DateTime::<Utc>::from(taken);
+                if taken < cutoff && seen.insert(path.clone()) {
+                    report.screenshots.push(Screenshot {
+                        protection: PathProtection::classify(&path.to_string_lossy()),
+                        size: metadata.len(),
+                        path,
+                        taken,
+                    });
+                }
+            }
+        }
+        finish(report)
+    }
+}
+
+fn finish(mut report: ScreenshotReport) -> ScreenshotReport {
+    report
+        .screenshots
+        .sort_by_key(|screenshot| screenshot.taken);
+    report.total_size = report.screenshots.iter().map(|s| s.size).sum();
+    report
+}
+
+/// Whether a file has a default screenshot name, e.g.
+/// `Screenshot 2024-05-01 at 10.22.33.png`
+fn is_screenshot_name(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    let has_extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+    has_extension
+        && NAME_PREFIXES.iter().any(|prefix| {
+            name.strip_prefix(prefix).is_some_and(|rest| {
+                let date = rest.as_bytes();
+                date.len() >= 5 && date[..4].iter().all(u8::is_ascii_digit) && date[4] == b'-'
+            })
+        })
+}
+
+/// Folder screenshots are saved to, if it was changed from the Desktop
+fn screenshot_location() -> Option<PathBuf> {
+    let output = Command::new("defaults")
+        .args(["read", "com.apple.screencapture", "location"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let location = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    match location.strip_prefix("~/") {
+        Some(relative) => Some(dirs::home_dir()?.join(relative)),
+        None => (!location.is_empty()).then(|| PathBuf::from(location)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_is_screenshot_name() {
+        assert!(is_screenshot_name(Path::new(
+            "Screenshot 2024-05-01 at 10.22.33.png"
+        )));
+        assert!(is_screenshot_name(Path::new(
+            "/Users/me/Desktop/Screen Shot 2019-01-02 at 9.00.00 AM.PNG"
+        )));
+        assert!(is_screenshot_name(Path::new(
+            "Screen Recording 2024-05-01 at 10.22.33.mov"
+        )));
+        assert!(!is_screenshot_name(Path::new("Screenshot ideas.png")));
+        assert!(!is_screenshot_name(Path::new(
+            "Screenshot 2024-05-01 at 10.22.33.txt"
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_find_applies_cutoff() {
+        let temp_dir = TempDir::new().unwrap();
+        let desktop = temp_dir.path();
+        fs::write(
+            desktop.join("Screenshot 2024-05-01 at 10.22.33.png"),
+            [0; 10],
+        )
+        .unwrap();
+        fs::create_dir(desktop.join("Sorted")).unwrap();
+        fs::write(
+            desktop.join("Sorted/Screen Shot 2019-01-02 at 9.00.00.png"),
+            [0; 5],
+        )
+        .unwrap();
+        fs::write(desktop.join("notes.png"), [0; 10]).unwrap();
+
+        let finder = ScreenshotFinder::new(vec![desktop.to_path_buf()]);
+        let cancel = CancellationToken::new();
+
+        let report = finder
+            .find(Utc::now() + chrono::Duration::days(1), &cancel)
+            .await;
+        assert_eq!(report.screenshots.len(), 2);
+        assert_eq!(report.total_size, 15);
+
+        let report = finder
+            .find(Utc::now() - chrono::Duration::days(1), &cancel)
+            .await;
+        assert!(report.screenshots.is_empty());
+    }
+}
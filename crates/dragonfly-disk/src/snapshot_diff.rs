@@ -0,0 +1,320 @@
+//! Change detection against APFS snapshots
+//!
+//! A local APFS snapshot is a read-only view of a volume as it was when the
+//! snapshot was taken. Comparing the newest one with the live filesystem shows
+//! where space went since then, which answers "what ate 30 GB since
+//! yesterday" without anything having been recorded beforehand.
+//!
+//! Mounting a snapshot goes through `mount_apfs` and needs root.
+
+use crate::walk::{skipped_from_walk_error, walker};
+use dragonfly_core::domain::protection::SkippedPath;
+use dragonfly_core::domain::scan_options::ScanOptions;
+use dragonfly_core::error::{Error, Result};
+use dragonfly_core::ports::CancellationToken;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+/// Volume holding user data on macOS; `/Users` and friends are firmlinked here
+const DATA_VOLUME: &str = "/System/Volumes/Data";
+
+/// How a path's size changed since the snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PathGrowth {
+    /// Live path
+    pub path: PathBuf,
+    /// Size in the snapshot in bytes (0 if the path didn't exist)
+    pub before: u64,
+    /// Size now in bytes (0 if the path was removed)
+    pub after: u64,
+}
+
+impl PathGrowth {
+    /// Change in bytes; negative if the path shrank
+    pub fn growth(&self) -> i64 {
+        self.after as i64 - self.before as i64
+    }
+}
+
+/// Result of comparing a live tree with its snapshot
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SnapshotDiffReport {
+    /// Live directory that was compared
+    pub root: PathBuf,
+    /// Size of the tree in the snapshot in bytes
+    pub size_before: u64,
+    /// Size of the tree now in bytes
+    pub size_after: u64,
+    /// Paths that grew, largest growth first
+    pub grown: Vec<PathGrowth>,
+    /// Live paths that couldn't be read and count as empty in `size_after`
+    pub skipped: Vec<SkippedPath>,
+    /// Whether the comparison was cancelled before completing (results are partial)
+    pub interrupted: bool,
+}
+
+impl SnapshotDiffReport {
+    /// Net change of the whole tree in bytes
+    pub fn net_change(&self) -> i64 {
+        self.size_after as i64 - self.size_before as i64
+    }
+}
+
+/// Compares a live directory tree with the same tree in a snapshot
+#[derive(Debug, Clone, Copy)]
+pub struct SnapshotDiff {
+    depth: usize,
+}
+
+impl SnapshotDiff {
+    /// Report changes grouped `depth` levels below the compared root
+    ///
+    /// Files nested deeper count towards their ancestor at that depth, so
+    /// growth spread over many small files still shows up in one place.
+    pub fn new(depth: usize) -> Self {
+        Self {
+            depth: depth.max(1),
+        }
+    }
+
+    /// Compare the live tree at `live` with its snapshot copy at `before`
+    ///
+    /// A `before` that doesn't exist means the whole tree is new. Other
+    /// filesystems mounted inside the tree, including the snapshot itself,
+    /// are not descended into.
+    pub async fn compare(
+        &self,
+        live: &Path,
+        before: &Path,
+        cancel: &CancellationToken,
+    ) -> Result<SnapshotDiffReport> {
+        if !live.is_dir() {
+            return Err(Error::NotFound(format!(
+                "Directory does not exist: {}",
+                live.display()
+            )));
+        }
+
+        let mut report = SnapshotDiffReport {
+            root: live.to_path_buf(),
+            ..Default::default()
+        };
+        let after_sizes = self.sizes(live, cancel, &mut report.skipped);
+        let before_sizes = if before.is_dir() {
+            self.sizes(before, cancel, &mut report.skipped)
+        } else {
+            HashMap::new()
+        };
+
+        report.size_before = before_sizes.values().sum();
+        report.size_after = after_sizes.values().sum();
+        let keys: BTreeSet<&PathBuf> = after_sizes.keys().chain(before_sizes.keys()).collect();
+        report.grown = keys
+            .into_iter()
+            .map(|key| PathGrowth {
+                path: live.join(key),
+                before: before_sizes.get(key).copied().unwrap_or(0),
+                after: after_sizes.get(key).copied().unwrap_or(0),
+            })
+            .filter(|change| change.growth() > 0)
+            .collect();
+        report
+            .grown
+            .sort_by_key(|change| std::cmp::Reverse(change.growth()));
+        report.interrupted = cancel.is_cancelled();
+        Ok(report)
+    }
+
+    /// File sizes under `root`, summed per path `depth` levels down
+    fn sizes(
+        &self,
+        root: &Path,
+        cancel: &CancellationToken,
+        skipped: &mut Vec<SkippedPath>,
+    ) -> HashMap<PathBuf, u64> {
+        let options = ScanOptions::new()
+            .with_hidden(true)
+            .with_one_filesystem(true);
+        let mut sizes = HashMap::new();
+        for entry in walker(root, &options)
+            .into_iter()
+            .take_while(|_| !cancel.is_cancelled())
+        {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    skipped.extend(skipped_from_walk_error(&err));
+                    continue;
+                }
+            };
+            let metadata = match entry.metadata() {
+                Ok(metadata) if metadata.is_file() => metadata,
+                Ok(_) => continue,
+                Err(err) => {
+                    skipped.extend(skipped_from_walk_error(&err));
+                    continue;
+                }
+            };
+            let path = entry.path();
+            let Ok(relative) = path.strip_prefix(root) else {
+                continue;
+            };
+            let key: PathBuf = relative.components().take(self.depth).collect();
+            *sizes.entry(key).or_default() += metadata.len();
+        }
+        sizes
+    }
+}
+
+/// The volume a path lives on and the path relative to the volume root
+///
+/// Paths under `/Volumes/<name>` belong to that volume. Everything else is
+/// taken to be on the Data volume, which is where `/Users`, `/Applications`,
+/// and the other writable top-level folders are firmlinked to.
+pub fn snapshot_volume(path: &Path) -> (PathBuf, PathBuf) {
+    if let Ok(relative) = path.strip_prefix(DATA_VOLUME) {
+        return (PathBuf::from(DATA_VOLUME), relative.to_path_buf());
+    }
+    if let Ok(rest) = path.strip_prefix("/Volumes") {
+        let mut components = rest.components();
+        if let Some(Component::Normal(name)) = components.next() {
+            return (
+                Path::new("/Volumes").join(name),
+                components.as_path().to_path_buf(),
+            );
+        }
+    }
+    let relative = path.strip_prefix("/").unwrap_or(path);
+    (PathBuf::from(DATA_VOLUME), relative.to_path_buf())
+}
+
+/// A snapshot mounted read-only, unmounted again on drop
+#[derive(Debug)]
+pub struct SnapshotMount {
+    mount_point: PathBuf,
+}
+
+impl SnapshotMount {
+    /// Mount `snapshot` of `volume` in a private temporary folder
+    pub fn mount(volume: &Path, snapshot: &str) -> Result<Self> {
+        let mount_point =
+            std::env::temp_dir().join(format!("dragonfly-snapshot-{}", std::process::id()));
+        std::fs::create_dir_all(&mount_point)?;
+
+        let output = Command::new("mount_apfs")
+            .args(["-o", "rdonly,nobrowse", "-s", snapshot])
+            .arg(volume)
+            .arg(&mount_point)
+            .output()
+            .map_err(|e| Error::NotSupported(format!("Failed to run mount_apfs: {}", e)))?;
+        if !output.status.success() {
+            let _ = std::fs::remove_dir(&mount_point);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if stderr.contains("not permitted") || stderr.contains("Permission denied") {
+                return Err(Error::PermissionDenied(
+                    "mounting snapshots requires root; re-run with sudo".to_string(),
+                ));
+            }
+            return Err(Error::FileSystem(format!(
+                "Failed to mount snapshot {}: {}",
+                snapshot,
+                stderr.trim()
+            )));
+        }
+        Ok(Self { mount_point })
+    }
+
+    /// Where the snapshot's volume root is mounted
+    pub fn path(&self) -> &Path {
+        &self.mount_point
+    }
+}
+
+impl Drop for SnapshotMount {
+    fn drop(&mut self) {
+        let unmounted = Command::new("umount")
+            .arg(&self.mount_point)
+            .status()
+            .is_ok_and(|status| status.success());
+        if unmounted {
+            let _ = std::fs::remove_dir(&self.mount_point);
+        } else {
+            tracing::warn!(
+                "Failed to unmount snapshot at {}",
+                self.mount_point.display()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn write(root: &Path, relative: &str, size: usize) {
+        let path = root.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, vec![0u8; size]).unwrap();
+    }
+
+    #[test]
+    fn test_snapshot_volume() {
+        assert_eq!(
+            snapshot_volume(Path::new("/Users/me/Downloads")),
+            (
+                PathBuf::from(DATA_VOLUME),
+                PathBuf::from("Users/me/Downloads")
+            )
+        );
+        assert_eq!(
+            snapshot_volume(Path::new("/System/Volumes/Data/Users/me")),
+            (PathBuf::from(DATA_VOLUME), PathBuf::from("Users/me"))
+        );
+        assert_eq!(
+            snapshot_volume(Path::new("/Volumes/Backup/Projects")),
+            (PathBuf::from("/Volumes/Backup"), PathBuf::from("Projects"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_compare_reports_growth_by_depth() {
+        let temp_dir = TempDir::new().unwrap();
+        let before = temp_dir.path().join("before");
+        let live = temp_dir.path().join("live");
+        write(&before, "Movies/a.mov", 100);
+        write(&before, "Documents/old.txt", 50);
+        write(&before, "notes.txt", 10);
+        write(&live, "Movies/a.mov", 100);
+        write(&live, "Movies/Exports/b.mov", 400);
+        write(&live, "Library/Caches/x/blob", 70);
+        write(&live, "notes.txt", 5);
+
+        let report = SnapshotDiff::new(1)
+            .compare(&live, &before, &CancellationToken::new())
+            .await
+            .unwrap();
+
+        assert_eq!(report.size_before, 160);
+        assert_eq!(report.size_after, 575);
+        assert_eq!(report.net_change(), 415);
+        assert_eq!(
+            report.grown,
+            vec![
+                PathGrowth {
+                    path: live.join("Movies"),
+                    before: 100,
+                    after: 500,
+                },
+                PathGrowth {
+                    path: live.join("Library"),
+                    before: 0,
+                    after: 70,
+                },
+            ]
+        );
+    }
+}
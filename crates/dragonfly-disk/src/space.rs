@@ -0,0 +1,465 @@
+//! Volume free space measurement
+//!
+//! Deleting a file doesn't always give its bytes back. If a local APFS
+//! snapshot still references the file, the blocks stay allocated and only
+//! become "purgeable", space macOS reclaims later by thinning snapshots when
+//! it needs room. Measuring free space before and after a clean shows how much
+//! was actually reclaimed, and how much of the rest is waiting on snapshots.
+//!
+//! [`mounted_volumes`] lists every mounted volume with its capacity, so space
+//! on external drives and network shares is accounted for as well.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Fraction of the reported bytes that may go missing before a shortfall is
+/// flagged; other processes write to the volume while a clean runs
+const SHORTFALL_TOLERANCE: f64 = 0.1;
+
+/// Free space on a volume at one point in time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct VolumeSpace {
+    /// Bytes available for new files right now
+    pub available: u64,
+    /// Bytes the system could free on demand, such as space held by local
+    /// snapshots (0 where this isn't reported)
+    pub purgeable: u64,
+}
+
+impl VolumeSpace {
+    /// Measure the volume holding `path`
+    ///
+    /// # Errors
+    ///
+    /// Returns the underlying error if the volume can't be queried.
+    pub fn of(path: &Path) -> std::io::Result<Self> {
+        let available = available_bytes(path)?;
+        let purgeable =
+            important_usage_bytes(path).map_or(0, |important| important.saturating_sub(available));
+        Ok(Self {
+            available,
+            purgeable,
+        })
+    }
+}
+
+/// A mounted volume and how full it is
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MountedVolume {
+    /// Where the volume is mounted
+    pub mount_point: PathBuf,
+    /// Device or share the volume comes from, e.g. `/dev/disk3s1`
+    pub source: String,
+    /// Filesystem type, e.g. `apfs`
+    pub filesystem: String,
+    /// Size of the volume in bytes
+    pub capacity: u64,
+    /// Bytes in use, including space held by local snapshots
+    pub used: u64,
+    /// Free space, and how much more the system could free on demand
+    #[serde(flatten)]
+    pub space: VolumeSpace,
+    /// Whether the volume is mounted read-only
+    pub read_only: bool,
+    /// Whether the volume is one users see and store files on, as opposed to
+    /// system and pseudo filesystems (on macOS, those not hidden from Finder)
+    pub browsable: bool,
+}
+
+/// Every mounted volume, in mount order
+///
+/// Volumes that can't be measured, such as a network share that went away,
+/// are left out.
+///
+/// # Errors
+///
+/// Returns the underlying error if the mount table can't be read.
+#[cfg(target_os = "macos")]
+pub fn mounted_volumes() -> std::io::Result<Vec<MountedVolume>> {
+    /// Hidden from Finder (`nobrowse`); not exported by the libc crate
+    const MNT_DONTBROWSE: u32 = 0x0010_0000;
+
+    let mut mounts: *mut libc::statfs = std::ptr::null_mut();
+    // SAFETY: getmntinfo points `mounts` at a buffer it owns, valid until the
+    // next call, holding the returned number of entries
+    let stats = unsafe {
+        let count = libc::getmntinfo(&mut mounts, libc::MNT_NOWAIT);
+        if count <= 0 || mounts.is_null() {
+            return Err(std::io::Error::last_os_error());
+        }
+        std::slice::from_raw_parts(mounts, count as usize).to_vec()
+    };
+
+    Ok(stats
+        .iter()
+        .filter_map(|stat| {
+            let mount_point = PathBuf::from(c_string(&stat.f_mntonname));
+            let block_size = u64::from(stat.f_bsize);
+            Some(MountedVolume {
+                space: VolumeSpace::of(&mount_point).ok()?,
+                source: c_string(&stat.f_mntfromname),
+                filesystem: c_string(&stat.f_fstypename),
+                capacity: stat.f_blocks * block_size,
+                used: stat.f_blocks.saturating_sub(stat.f_bfree) * block_size,
+                read_only: stat.f_flags & libc::MNT_RDONLY as u32 != 0,
+                browsable: stat.f_flags & MNT_DONTBROWSE == 0,
+                mount_point,
+            })
+        })
+        .collect())
+}
+
+/// Every mounted volume, in mount order
+///
+/// Volumes that can't be measured, such as a network share that went away,
+/// are left out.
+///
+/// # Errors
+///
+/// Returns the underlying error if the mount table can't be read.
+#[cfg(not(target_os = "macos"))]
+#[allow(clippy::useless_conversion)] // statvfs field widths vary across platforms
+pub fn mounted_volumes() -> std::io::Result<Vec<MountedVolume>> {
+    let table = std::fs::read_to_string("/proc/self/mounts")?;
+    Ok(parse_mounts(&table)
+        .into_iter()
+        .filter_map(|mount| {
+            let c_path = c_path(&mount.mount_point).ok()?;
+            // SAFETY: `stat` is plain data and `c_path` is a valid C string
+            let stat = unsafe {
+                let mut stat: libc::statvfs = std::mem::zeroed();
+                if libc::statvfs(c_path.as_ptr(), &mut stat) != 0 {
+                    return None;
+                }
+                stat
+            };
+            let block_size = u64::from(stat.f_frsize);
+            let blocks = u64::from(stat.f_blocks);
+            Some(MountedVolume {
+                space: VolumeSpace::of(&mount.mount_point).ok()?,
+                capacity: blocks * block_size,
+                used: blocks.saturating_sub(u64::from(stat.f_bfree)) * block_size,
+                // Pseudo filesystems have no backing device path, and
+                // containers bind-mount single files
+                browsable: (mount.source.starts_with('/') && mount.mount_point.is_dir())
+                    || mount.mount_point == Path::new("/"),
+                ..mount
+            })
+        })
+        .collect())
+}
+
+/// Entries of a `/proc/self/mounts` table, without sizes
+#[cfg(not(target_os = "macos"))]
+fn parse_mounts(table: &str) -> Vec<MountedVolume> {
+    // Spaces and other separators in fields are written as octal escapes
+    let unescape = |field: &str| {
+        field
+            .replace("\\040", " ")
+            .replace("\\011", "\t")
+            .replace("\\012", "\n")
+            .replace("\\134", "\\")
+    };
+    table
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let (source, target, filesystem, options) = (
+                fields.next()?,
+                fields.next()?,
+                fields.next()?,
+                fields.next()?,
+            );
+            Some(MountedVolume {
+                mount_point: PathBuf::from(unescape(target)),
+                source: unescape(source),
+                filesystem: filesystem.to_string(),
+                capacity: 0,
+                used: 0,
+                space: VolumeSpace::default(),
+                read_only: options.split(',').any(|option| option == "ro"),
+                browsable: false,
+            })
+        })
+        .collect()
+}
+
+#[cfg(target_os = "macos")]
+fn c_string(chars: &[libc::c_char]) -> String {
+    let bytes: Vec<u8> = chars
+        .iter()
+        .take_while(|&&c| c != 0)
+        .map(|&c| c as u8)
+        .collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Mount point of the volume holding `path`
+///
+/// Walks up from `path` until the parent is on another device, so every path
+/// on a volume maps to the same key.
+///
+/// # Errors
+///
+/// Returns the underlying error if `path` can't be resolved.
+pub fn mount_point(path: &Path) -> std::io::Result<PathBuf> {
+    use std::os::unix::fs::MetadataExt;
+
+    let path = std::fs::canonicalize(path)?;
+    let device = std::fs::metadata(&path)?.dev();
+    let mut mount = path.as_path();
+    while let Some(parent) = mount.parent() {
+        match std::fs::metadata(parent) {
+            Ok(metadata) if metadata.dev() == device => mount = parent,
+            _ => break,
+        }
+    }
+    Ok(mount.to_path_buf())
+}
+
+/// Reported freed bytes compared with what the volume actually gained
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ReclaimCheck {
+    /// Bytes the operation reported as freed
+    pub reported: u64,
+    /// Free space before the operation
+    pub before: VolumeSpace,
+    /// Free space after the operation
+    pub after: VolumeSpace,
+}
+
+impl ReclaimCheck {
+    /// Compare a measurement taken before an operation with the volume now
+    ///
+    /// # Errors
+    ///
+    /// Returns the underlying error if the volume can't be queried.
+    pub fn measure(path: &Path, before: VolumeSpace, reported: u64) -> std::io::Result<Self> {
+        Ok(Self {
+            reported,
+            before,
+            after: VolumeSpace::of(path)?,
+        })
+    }
+
+    /// Change in available bytes; negative if the volume filled up meanwhile
+    pub fn reclaimed(&self) -> i64 {
+        self.after.available as i64 - self.before.available as i64
+    }
+
+    /// Change in purgeable bytes
+    pub fn purgeable_growth(&self) -> i64 {
+        self.after.purgeable as i64 - self.before.purgeable as i64
+    }
+
+    /// Reported bytes that didn't show up as available space
+    pub fn shortfall(&self) -> u64 {
+        (self.reported as i64 - self.reclaimed()).max(0) as u64
+    }
+
+    /// Whether the shortfall is too large to be noise from other writers
+    pub fn is_short(&self) -> bool {
+        self.shortfall() as f64 > self.reported as f64 * SHORTFALL_TOLERANCE
+    }
+
+    /// Whether the missing bytes turned into purgeable space, which is what
+    /// happens when snapshots still reference the deleted files
+    pub fn pinned_by_snapshots(&self) -> bool {
+        self.is_short() && self.purgeable_growth() > 0
+    }
+}
+
+/// Bytes available to unprivileged users on the volume holding `path`
+#[cfg(target_os = "macos")]
+fn available_bytes(path: &Path) -> std::io::Result<u64> {
+    let c_path = c_path(path)?;
+    // SAFETY: `stat` is plain data and `c_path` is a valid C string
+    unsafe {
+        let mut stat: libc::statfs = std::mem::zeroed();
+        if libc::statfs(c_path.as_ptr(), &mut stat) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(stat.f_bavail * u64::from(stat.f_bsize))
+    }
+}
+
+/// Bytes available to unprivileged users on the volume holding `path`
+#[cfg(not(target_os = "macos"))]
+#[allow(clippy::useless_conversion)] // statvfs field widths vary across platforms
+fn available_bytes(path: &Path) -> std::io::Result<u64> {
+    let c_path = c_path(path)?;
+    // SAFETY: `stat` is plain data and `c_path` is a valid C string
+    unsafe {
+        let mut stat: libc::statvfs = std::mem::zeroed();
+        if libc::statvfs(c_path.as_ptr(), &mut stat) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(u64::from(stat.f_bavail) * u64::from(stat.f_frsize))
+    }
+}
+
+/// Whether the volume holding `path` can clone files (copy-on-write copies
+/// that share storage until modified), which only APFS supports
+///
+/// # Errors
+///
+/// Returns an error if `path` can't be examined.
+#[cfg(target_os = "macos")]
+pub fn supports_clones(path: &Path) -> std::io::Result<bool> {
+    let c_path = c_path(path)?;
+    // SAFETY: `stat` is plain data and `c_path` is a valid C string
+    let stat = unsafe {
+        let mut stat: libc::statfs = std::mem::zeroed();
+        if libc::statfs(c_path.as_ptr(), &mut stat) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        stat
+    };
+    Ok(c_string(&stat.f_fstypename) == "apfs")
+}
+
+/// Whether the volume holding `path` can clone files (copy-on-write copies
+/// that share storage until modified), which only APFS supports
+///
+/// # Errors
+///
+/// Returns an error if `path` can't be examined.
+#[cfg(not(target_os = "macos"))]
+pub fn supports_clones(path: &Path) -> std::io::Result<bool> {
+    std::fs::metadata(path).map(|_| false)
+}
+
+fn c_path(path: &Path) -> std::io::Result<std::ffi::CString> {
+    use std::os::unix::ffi::OsStrExt;
+    std::ffi::CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))
+}
+
+/// Available bytes including purgeable space, as Finder reports them
+#[cfg(target_os = "macos")]
+fn important_usage_bytes(path: &Path) -> Option<u64> {
+    use core_foundation_sys::base::{kCFAllocatorDefault, Boolean, CFIndex, CFRelease, CFTypeRef};
+    use core_foundation_sys::error::CFErrorRef;
+    use core_foundation_sys::number::{kCFNumberSInt64Type, CFNumberGetValue, CFNumberRef};
+    use core_foundation_sys::string::CFStringRef;
+    use core_foundation_sys::url::{CFURLCreateFromFileSystemRepresentation, CFURLRef};
+    use std::ffi::c_void;
+    use std::os::unix::ffi::OsStrExt;
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        static kCFURLVolumeAvailableCapacityForImportantUsageKey: CFStringRef;
+        fn CFURLCopyResourcePropertyForKey(
+            url: CFURLRef,
+            key: CFStringRef,
+            value: *mut c_void,
+            error: *mut CFErrorRef,
+        ) -> Boolean;
+    }
+
+    let bytes = path.as_os_str().as_bytes();
+    // SAFETY: every object created here is released before returning
+    unsafe {
+        let url = CFURLCreateFromFileSystemRepresentation(
+            kCFAllocatorDefault,
+            bytes.as_ptr(),
+            bytes.len() as CFIndex,
+            1,
+        );
+        if url.is_null() {
+            return None;
+        }
+        let mut value: CFTypeRef = std::ptr::null();
+        let found = CFURLCopyResourcePropertyForKey(
+            url,
+            kCFURLVolumeAvailableCapacityForImportantUsageKey,
+            (&mut value as *mut CFTypeRef).cast(),
+            std::ptr::null_mut(),
+        );
+        CFRelease(url.cast());
+        if found == 0 || value.is_null() {
+            return None;
+        }
+        let mut capacity: i64 = 0;
+        let converted = CFNumberGetValue(
+            value as CFNumberRef,
+            kCFNumberSInt64Type,
+            (&mut capacity as *mut i64).cast(),
+        );
+        CFRelease(value);
+        converted.then(|| capacity.max(0) as u64)
+    }
+}
+
+/// Purgeable space is a macOS concept; elsewhere there is none to report
+#[cfg(not(target_os = "macos"))]
+fn important_usage_bytes(_path: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn space(available: u64, purgeable: u64) -> VolumeSpace {
+        VolumeSpace {
+            available,
+            purgeable,
+        }
+    }
+
+    #[test]
+    fn test_reclaim_check_flags_snapshot_pinned_space() {
+        let check = ReclaimCheck {
+            reported: 1000,
+            before: space(5000, 0),
+            after: space(5980, 0),
+        };
+        assert_eq!(check.reclaimed(), 980);
+        assert_eq!(check.shortfall(), 20);
+        assert!(!check.is_short());
+
+        let check = ReclaimCheck {
+            reported: 1000,
+            before: space(5000, 200),
+            after: space(5100, 1100),
+        };
+        assert_eq!(check.shortfall(), 900);
+        assert!(check.is_short());
+        assert!(check.pinned_by_snapshots());
+    }
+
+    #[test]
+    fn test_volume_space_of_existing_path() {
+        let space = VolumeSpace::of(Path::new("/")).unwrap();
+        assert!(space.available > 0);
+        assert!(VolumeSpace::of(Path::new("/nonexistent-dragonfly-volume")).is_err());
+        assert_eq!(mount_point(Path::new("/")).unwrap(), Path::new("/"));
+    }
+
+    #[test]
+    fn test_mounted_volumes_include_root() {
+        let volumes = mounted_volumes().unwrap();
+        let root = volumes
+            .iter()
+            .find(|volume| volume.mount_point == Path::new("/"))
+            .unwrap();
+        assert!(root.browsable);
+        assert!(root.capacity >= root.used);
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn test_parse_mounts() {
+        let mounts = parse_mounts(
+            "/dev/sda1 / ext4 rw,relatime 0 0\n\
+             proc /proc proc rw,nosuid 0 0\n\
+             /dev/sdb1 /media/My\\040Disk vfat ro,noatime 0 0\n",
+        );
+        assert_eq!(mounts.len(), 3);
+        assert_eq!(mounts[2].mount_point, Path::new("/media/My Disk"));
+        assert!(mounts[2].read_only);
+        assert!(!mounts[0].read_only);
+        assert_eq!(mounts[1].filesystem, "proc");
+    }
+}
@@ -0,0 +1,128 @@
+//! Spotlight metadata queries
+//!
+//! Spotlight already knows things a filesystem walk can't cheaply find out,
+//! such as when an app was last opened or which files are over a given size.
+//! Queries go through the `mdls`, `mdfind`, and `mdutil` command line tools;
+//! where Spotlight isn't available (other platforms, unindexed volumes) they
+//! return `None` and callers fall back to walking the filesystem.
+
+use crate::walk::walk_includes;
+use chrono::{DateTime, Utc};
+use dragonfly_core::domain::entities::FileEntity;
+use dragonfly_core::domain::scan_options::ScanOptions;
+use dragonfly_core::ports::CancellationToken;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// When the item at `path` was last opened, according to Spotlight
+pub fn last_used_date(path: &Path) -> Option<DateTime<Utc>> {
+    let output = Command::new("mdls")
+        .args(["-raw", "-name", "kMDItemLastUsedDate"])
+        .arg(path)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_mdls_date(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Whether Spotlight indexing is enabled for the volume holding `path`
+pub fn is_indexed(path: &Path) -> bool {
+    Command::new("mdutil")
+        .arg("-s")
+        .arg(path)
+        .output()
+        .is_ok_and(|output| {
+            output.status.success()
+                && String::from_utf8_lossy(&output.stdout).contains("Indexing enabled")
+        })
+}
+
+/// Files under `root` of at least `min_size` bytes, according to Spotlight
+///
+/// Returns `None` when the volume isn't indexed or the query fails, so the
+/// caller can walk instead. Each hit is checked against the filesystem, which
+/// drops files deleted since they were indexed and gives their current size,
+/// and against the scan options so results match what a walk would return.
+/// Spotlight doesn't index some system and hidden locations, so those files
+/// are missing from the results.
+pub fn find_files_larger_than(
+    root: &Path,
+    min_size: u64,
+    options: &ScanOptions,
+    cancel: &CancellationToken,
+) -> Option<Vec<FileEntity>> {
+    let paths = query(root, &format!("kMDItemFSSize >= {}", min_size))?;
+    let files = paths
+        .iter()
+        .take_while(|_| !cancel.is_cancelled())
+        .filter(|path| walk_includes(root, Path::new(path), options))
+        .filter_map(|path| {
+            let metadata = std::fs::symlink_metadata(path).ok()?;
+            (metadata.is_file() && metadata.len() >= min_size).then(|| {
+                let mut file = FileEntity::new(path.to_string(), metadata.len());
+                file.modified = metadata.modified().ok().map(DateTime::from);
+                file
+            })
+        })
+        .collect();
+    Some(files)
+}
+
+/// Files under `root` that macOS recorded as screenshots or screen recordings
+///
+/// This catches captures whose names are localized or were changed with
+/// `defaults write com.apple.screencapture name`. Returns `None` when the
+/// volume isn't indexed or the query fails.
+pub fn screen_captures(root: &Path) -> Option<Vec<PathBuf>> {
+    let paths = query(root, "kMDItemIsScreenCapture == 1")?;
+    Some(paths.into_iter().map(PathBuf::from).collect())
+}
+
+/// Paths under `root` matching a Spotlight query
+fn query(root: &Path, query: &str) -> Option<Vec<String>> {
+    if !is_indexed(root) {
+        return None;
+    }
+    let output = Command::new("mdfind")
+        .arg("-0")
+        .arg("-onlyin")
+        .arg(root)
+        .arg(query)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Some(
+        stdout
+            .split('\0')
+            .filter(|path| !path.is_empty())
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+/// Parse a date printed by `mdls -raw`, e.g. `2024-05-01 10:22:33 +0000`
+///
+/// Attributes that aren't set are printed as `(null)`.
+fn parse_mdls_date(raw: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_str(raw.trim(), "%Y-%m-%d %H:%M:%S %z")
+        .ok()
+        .map(|date| date.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mdls_date() {
+        let date = parse_mdls_date("2024-05-01 10:22:33 +0000\n").unwrap();
+        assert_eq!(date.to_rfc3339(), "2024-05-01T10:22:33+00:00");
+        assert_eq!(parse_mdls_date("(null)"), None);
+    }
+}
@@ -0,0 +1,149 @@
+//! Files nobody has touched in a long time
+//!
+//! A file counts as last used at the later of its modification and access
+//! times. Volumes mounted with `noatime` (and macOS, which only updates access
+//! times lazily) can report an access time older than the real last read, so
+//! a file that is read but never written may show up here; check before
+//! deleting.
+
+use crate::walk::{skipped_from_walk_error, walker};
+use chrono::{DateTime, Utc};
+use dragonfly_core::domain::protection::{PathProtection, SkippedPath};
+use dragonfly_core::domain::scan_options::ScanOptions;
+use dragonfly_core::ports::CancellationToken;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// A file not used since before the cutoff
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StaleFile {
+    /// File path
+    pub path: PathBuf,
+    /// Size in bytes
+    pub size: u64,
+    /// Later of the modification and access times
+    pub last_used: DateTime<Utc>,
+    /// When the file was last modified
+    pub modified: DateTime<Utc>,
+    /// When the file was last read, if the filesystem records it
+    pub accessed: Option<DateTime<Utc>>,
+    /// Protection level of the path
+    pub protection: PathProtection,
+}
+
+/// Files not used since a cutoff
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StaleFilesReport {
+    /// Stale files, largest first
+    pub files: Vec<StaleFile>,
+    /// Combined size in bytes
+    pub total_size: u64,
+    /// Directories that couldn't be listed, whose files weren't checked
+    pub skipped: Vec<SkippedPath>,
+    /// Whether the search was cancelled before completing (results are partial)
+    pub interrupted: bool,
+}
+
+/// Files under `root` of at least `min_size` bytes last used before `cutoff`
+pub(crate) fn find(
+    root: &Path,
+    cutoff: DateTime<Utc>,
+    min_size: u64,
+    options: &ScanOptions,
+    cancel: &CancellationToken,
+) -> StaleFilesReport {
+    let files = Mutex::new(Vec::new());
+    let skipped = Mutex::new(Vec::new());
+    walker(root, options)
+        .into_iter()
+        .take_while(|_| !cancel.is_cancelled())
+        .par_bridge()
+        .for_each(|entry| {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    skipped
+                        .lock()
+                        .unwrap()
+                        .extend(skipped_from_walk_error(&err));
+                    return;
+                }
+            };
+            let Ok(metadata) = entry.metadata() else {
+                return;
+            };
+            if !metadata.is_file() || metadata.len() < min_size {
+                return;
+            }
+            let Ok(modified) = metadata.modified().map(DateTime::<Utc>::from) else {
+                return;
+            };
+            let accessed = metadata.accessed().ok().map(DateTime::<Utc>::from);
+            let last_used = accessed.map_or(modified, |accessed| accessed.max(modified));
+            if last_used >= cutoff {
+                return;
+            }
+            let path = entry.path();
+            files.lock().unwrap().push(StaleFile {
+                protection: PathProtection::classify(&path.to_string_lossy()),
+                size: metadata.len(),
+                path,
+                last_used,
+                modified,
+                accessed,
+            });
+        });
+
+    let mut files = files.into_inner().unwrap();
+    files.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+    StaleFilesReport {
+        total_size: files.iter().map(|f| f.size).sum(),
+        files,
+        skipped: skipped.into_inner().unwrap(),
+        interrupted: cancel.is_cancelled(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::time::{Duration, SystemTime};
+    use tempfile::TempDir;
+
+    fn age(path: &Path, days: u64) {
+        let time = SystemTime::now() - Duration::from_secs(days * 24 * 60 * 60);
+        let file = fs::File::options().write(true).open(path).unwrap();
+        file.set_times(fs::FileTimes::new().set_modified(time).set_accessed(time))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_finds_old_large_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        for (name, size, days) in [
+            ("old.bin", 500, 400),
+            ("old_small.bin", 5, 400),
+            ("recent.bin", 500, 10),
+        ] {
+            fs::write(root.join(name), vec![0u8; size]).unwrap();
+            age(&root.join(name), days);
+        }
+
+        let cutoff = Utc::now() - chrono::Duration::days(365);
+        let report = find(
+            root,
+            cutoff,
+            100,
+            &ScanOptions::default(),
+            &CancellationToken::new(),
+        );
+        assert_eq!(report.files.len(), 1);
+        assert!(report.files[0].path.ends_with("old.bin"));
+        assert_eq!(report.total_size, 500);
+        assert!(report.files[0].last_used < cutoff);
+    }
+}
@@ -12,6 +12,8 @@ pub enum AnalysisStrategy {
     Quick,
     /// Incremental - use cached results
     Incremental,
+    /// Spotlight - query the Spotlight index instead of walking
+    Spotlight,
 }
 
 #[cfg(test)]
@@ -0,0 +1,335 @@
+//! Universal binary analysis
+//!
+//! Universal ("fat") Mach-O binaries carry a complete copy of their code for
+//! each architecture, so on an Apple silicon Mac the Intel slice of every app
+//! is dead weight, and vice versa. This module reads the fat headers of the
+//! binaries inside app bundles and reports, per app, how much the slices for
+//! other architectures take up.
+//!
+//! Nothing is modified here; thinning itself is a separate, explicit step.
+
+use crate::apps::app_bundles;
+use crate::walk::{skipped_from_walk_error, walker};
+use dragonfly_core::domain::protection::{PathProtection, SkippedPath};
+use dragonfly_core::domain::scan_options::ScanOptions;
+use dragonfly_core::ports::CancellationToken;
+use serde::Serialize;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Fat header magic numbers (big-endian on disk)
+const FAT_MAGIC: u32 = 0xcafe_babe;
+const FAT_MAGIC_64: u32 = 0xcafe_babf;
+
+/// Java class files share `FAT_MAGIC`; their version field reads as a much
+/// larger slice count than any real universal binary has
+const MAX_SLICES: u32 = 20;
+
+/// An architecture slice in a universal binary
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Slice {
+    /// Architecture name as `lipo` spells it, e.g. `arm64`
+    pub arch: String,
+    /// Offset of the slice in the file
+    pub offset: u64,
+    /// Size of the slice in bytes
+    pub size: u64,
+}
+
+/// A universal binary and what thinning it would save
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UniversalBinary {
+    /// Path of the binary
+    pub path: PathBuf,
+    /// Size of the file in bytes
+    pub size: u64,
+    /// Architecture slices
+    pub slices: Vec<Slice>,
+    /// Bytes taken by slices other than the native one
+    pub reclaimable: u64,
+}
+
+/// Universal binaries in one app
+#[derive(Debug, Clone, Serialize)]
+pub struct AppThinning {
+    /// Display name (bundle name without `.app`)
+    pub name: String,
+    /// Bundle path
+    pub path: PathBuf,
+    /// Universal binaries that contain the native architecture
+    pub binaries: Vec<UniversalBinary>,
+    /// Bytes reclaimable across all of the app's binaries
+    pub reclaimable: u64,
+    /// Protection level of the bundle
+    pub protection: PathProtection,
+}
+
+/// Thinning potential of a set of apps
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ThinningReport {
+    /// Architecture that would be kept
+    pub native_arch: String,
+    /// Apps with something to reclaim, most reclaimable first
+    pub apps: Vec<AppThinning>,
+    /// Bytes reclaimable across all apps
+    pub total_reclaimable: u64,
+    /// Folders under the apps that couldn't be read, so their binaries
+    /// weren't measured
+    pub skipped: Vec<SkippedPath>,
+    /// Whether the analysis was cancelled before completing (results are partial)
+    pub interrupted: bool,
+}
+
+/// Measures what thinning universal binaries would reclaim
+#[derive(Debug, Clone)]
+pub struct ThinningAnalyzer {
+    native_arch: String,
+}
+
+impl ThinningAnalyzer {
+    /// Analyzer keeping the architecture this program runs on
+    pub fn new() -> Self {
+        Self::for_arch(native_arch())
+    }
+
+    /// Analyzer keeping `arch` (as `lipo` spells it)
+    pub fn for_arch(arch: impl Into<String>) -> Self {
+        Self {
+            native_arch: arch.into(),
+        }
+    }
+
+    /// Analyze the `.app` bundles directly inside `app_dirs`
+    pub async fn analyze(
+        &self,
+        app_dirs: &[PathBuf],
+        cancel: &CancellationToken,
+    ) -> ThinningReport {
+        let mut report = ThinningReport {
+            native_arch: self.native_arch.clone(),
+            ..Default::default()
+        };
+        for bundle in app_bundles(app_dirs) {
+            if cancel.is_cancelled() {
+                report.interrupted = true;
+                break;
+            }
+            let app = self.analyze_app(&bundle, cancel, &mut report.skipped);
+            if app.reclaimable > 0 {
+                report.apps.push(app);
+            }
+        }
+        report
+            .apps
+            .sort_by_key(|app| std::cmp::Reverse(app.reclaimable));
+        report.total_reclaimable = report.apps.iter().map(|app| app.reclaimable).sum();
+        report
+    }
+
+    fn analyze_app(
+        &self,
+        bundle: &Path,
+        cancel: &CancellationToken,
+        skipped: &mut Vec<SkippedPath>,
+    ) -> AppThinning {
+        let mut binaries = Vec::new();
+        for entry in walker(bundle, &ScanOptions::new().with_hidden(true)) {
+            if cancel.is_cancelled() {
+                break;
+            }
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    skipped.extend(skipped_from_walk_error(&err));
+                    continue;
+                }
+            };
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let path = entry.path();
+            let Some(slices) = read_slices(&path) else {
+                continue;
+            };
+            // Without a native slice the binary can't be thinned to it
+            if !slices.iter().any(|slice| slice.arch == self.native_arch) {
+                continue;
+            }
+            let reclaimable = slices
+                .iter()
+                .filter(|slice| slice.arch != self.native_arch)
+                .map(|slice| slice.size)
+                .sum();
+            binaries.push(UniversalBinary {
+                size: entry.metadata().map(|m| m.len()).unwrap_or(0),
+                path,
+                slices,
+                reclaimable,
+            });
+        }
+
+        AppThinning {
+            name: bundle
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            path: bundle.to_path_buf(),
+            reclaimable: binaries.iter().map(|binary| binary.reclaimable).sum(),
+            binaries,
+            protection: PathProtection::classify(&bundle.to_string_lossy()),
+        }
+    }
+}
+
+impl Default for ThinningAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Architecture of the running program, as `lipo` spells it
+pub fn native_arch() -> &'static str {
+    match std::env::consts::ARCH {
+        "aarch64" => "arm64",
+        other => other,
+    }
+}
+
+/// Slices of the universal binary at `path`, or `None` if it isn't one
+pub fn read_slices(path: &Path) -> Option<Vec<Slice>> {
+    let mut file = std::fs::File::open(path).ok()?;
+    let mut header = [0u8; 8];
+    file.read_exact(&mut header).ok()?;
+    let magic = u32::from_be_bytes(header[..4].try_into().ok()?);
+    let count = u32::from_be_bytes(header[4..].try_into().ok()?);
+    let entry_size = match magic {
+        FAT_MAGIC => 20,
+        FAT_MAGIC_64 => 32,
+        _ => return None,
+    };
+    if count == 0 || count > MAX_SLICES {
+        return None;
+    }
+
+    let mut entries = vec![0u8; entry_size * count as usize];
+    file.read_exact(&mut entries).ok()?;
+    let slices = entries
+        .chunks_exact(entry_size)
+        .map(|entry| parse_slice(entry, magic == FAT_MAGIC_64))
+        .collect::<Option<Vec<_>>>()?;
+    Some(slices)
+}
+
+/// Parse a `fat_arch` or `fat_arch_64` entry
+fn parse_slice(entry: &[u8], is_64: bool) -> Option<Slice> {
+    let be_u32 = |at: usize| Some(u32::from_be_bytes(entry.get(at..at + 4)?.try_into().ok()?));
+    let be_u64 = |at: usize| Some(u64::from_be_bytes(entry.get(at..at + 8)?.try_into().ok()?));
+    let cpu_type = be_u32(0)?;
+    let cpu_subtype = be_u32(4)?;
+    let (offset, size) = if is_64 {
+        (be_u64(8)?, be_u64(16)?)
+    } else {
+        (u64::from(be_u32(8)?), u64::from(be_u32(12)?))
+    };
+    Some(Slice {
+        arch: arch_name(cpu_type, cpu_subtype),
+        offset,
+        size,
+    })
+}
+
+/// `lipo` name for a Mach-O CPU type
+fn arch_name(cpu_type: u32, cpu_subtype: u32) -> String {
+    const CPU_I386: u32 = 0x0000_0007;
+    const CPU_X86_64: u32 = 0x0100_0007;
+    const CPU_ARM: u32 = 0x0000_000c;
+    const CPU_ARM64: u32 = 0x0100_000c;
+    const CPU_ARM64_32: u32 = 0x0200_000c;
+    const CPU_PPC: u32 = 0x0000_0012;
+    // The top byte of the subtype holds capability flags
+    match (cpu_type, cpu_subtype & 0x00ff_ffff) {
+        (CPU_I386, _) => "i386".to_string(),
+        (CPU_X86_64, 8) => "x86_64h".to_string(),
+        (CPU_X86_64, _) => "x86_64".to_string(),
+        (CPU_ARM64, 2) => "arm64e".to_string(),
+        (CPU_ARM64, _) => "arm64".to_string(),
+        (CPU_ARM64_32, _) => "arm64_32".to_string(),
+        (CPU_ARM, _) => "arm".to_string(),
+        (CPU_PPC, _) => "ppc".to_string(),
+        (other, subtype) => format!("cpu{}.{}", other, subtype),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// Fat binary with the given (cpu type, size) slices
+    fn fat_binary(slices: &[(u32, u32)]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend(FAT_MAGIC.to_be_bytes());
+        data.extend((slices.len() as u32).to_be_bytes());
+        let mut offset = 0x1000;
+        for &(cpu_type, size) in slices {
+            for value in [cpu_type, 0, offset, size, 12] {
+                data.extend(value.to_be_bytes());
+            }
+            offset += size;
+        }
+        data.resize(offset as usize, 0);
+        data
+    }
+
+    #[test]
+    fn test_read_slices() {
+        let temp_dir = TempDir::new().unwrap();
+        let binary = temp_dir.path().join("tool");
+        fs::write(
+            &binary,
+            fat_binary(&[(0x0100_0007, 300), (0x0100_000c, 200)]),
+        )
+        .unwrap();
+
+        let slices = read_slices(&binary).unwrap();
+        assert_eq!(slices.len(), 2);
+        assert_eq!(slices[0].arch, "x86_64");
+        assert_eq!(slices[0].size, 300);
+        assert_eq!(slices[1].arch, "arm64");
+
+        // A Java class file: same magic, version where the count would be
+        let class = temp_dir.path().join("Main.class");
+        fs::write(&class, [0xca, 0xfe, 0xba, 0xbe, 0, 0, 0, 52]).unwrap();
+        assert!(read_slices(&class).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_analyze_reports_reclaimable_per_app() {
+        let temp_dir = TempDir::new().unwrap();
+        let apps = temp_dir.path();
+        let macos = apps.join("Editor.app/Contents/MacOS");
+        fs::create_dir_all(&macos).unwrap();
+        fs::write(
+            macos.join("Editor"),
+            fat_binary(&[(0x0100_0007, 300), (0x0100_000c, 200)]),
+        )
+        .unwrap();
+        fs::write(macos.join("helper"), fat_binary(&[(0x0100_0007, 100)])).unwrap();
+        fs::create_dir_all(apps.join("Native.app/Contents/MacOS")).unwrap();
+        fs::write(
+            apps.join("Native.app/Contents/MacOS/Native"),
+            b"\xcf\xfa\xed\xfe",
+        )
+        .unwrap();
+
+        let report = ThinningAnalyzer::for_arch("arm64")
+            .analyze(&[apps.to_path_buf()], &CancellationToken::new())
+            .await;
+
+        assert_eq!(report.apps.len(), 1);
+        assert_eq!(report.apps[0].name, "Editor");
+        assert_eq!(report.apps[0].binaries.len(), 1);
+        assert_eq!(report.total_reclaimable, 300);
+    }
+}
@@ -0,0 +1,176 @@
+//! Per-directory size aggregation
+//!
+//! A flat file list says which files are big but not which folders are. The
+//! tree built here gives every directory holding files its cumulative size,
+//! including everything below it, so the heaviest branches stand out.
+//! Directories without any files (at any depth) are left out.
+
+use dragonfly_core::domain::protection::SkippedPath;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// A directory and the space used below it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DirectoryNode {
+    /// Directory path
+    pub path: PathBuf,
+    /// Combined size of every file below the directory, in bytes
+    pub size: u64,
+    /// Number of files below the directory
+    pub file_count: u64,
+    /// Subdirectories holding files, largest first
+    pub children: Vec<DirectoryNode>,
+}
+
+impl DirectoryNode {
+    /// Build the tree under `root` from files directly inside each directory
+    ///
+    /// `direct` maps a directory to the size and count of the files it holds
+    /// itself. Directories outside `root` are ignored; ancestors between
+    /// `root` and a directory are filled in.
+    pub fn from_direct(root: &Path, direct: &HashMap<PathBuf, (u64, u64)>) -> Self {
+        let mut children: HashMap<&Path, Vec<&Path>> = HashMap::new();
+        for dir in direct.keys().filter(|dir| dir.starts_with(root)) {
+            let mut dir = dir.as_path();
+            while let Some(parent) = dir.parent().filter(|_| dir != root) {
+                let siblings = children.entry(parent).or_default();
+                if siblings.contains(&dir) {
+                    break;
+                }
+                siblings.push(dir);
+                dir = parent;
+            }
+        }
+        build(root, direct, &children)
+    }
+
+    /// Build the tree under `root` from `(path, size)` pairs of files
+    ///
+    /// Files outside `root` are ignored, as for [`Self::from_direct`].
+    pub fn from_files<'a>(root: &Path, files: impl IntoIterator<Item = (&'a str, u64)>) -> Self {
+        let mut direct: HashMap<PathBuf, (u64, u64)> = HashMap::new();
+        for (path, size) in files {
+            let Some(dir) = Path::new(path).parent() else {
+                continue;
+            };
+            let (total, count) = direct.entry(dir.to_path_buf()).or_default();
+            *total += size;
+            *count += 1;
+        }
+        Self::from_direct(root, &direct)
+    }
+
+    /// The `count` largest directories below this one, largest first
+    ///
+    /// With `max_depth`, only directories at most that many levels below this
+    /// one are considered (1 for direct children).
+    pub fn heaviest(&self, count: usize, max_depth: Option<usize>) -> Vec<(&DirectoryNode, usize)> {
+        let mut found = Vec::new();
+        let mut pending: Vec<(&DirectoryNode, usize)> =
+            self.children.iter().map(|child| (child, 1)).collect();
+        while let Some((node, depth)) = pending.pop() {
+            found.push((node, depth));
+            if max_depth.map_or(true, |max| depth < max) {
+                pending.extend(node.children.iter().map(|child| (child, depth + 1)));
+            }
+        }
+        found.sort_by(|(a, _), (b, _)| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+        found.truncate(count);
+        found
+    }
+}
+
+/// Build the node for `dir` and everything below it
+fn build(
+    dir: &Path,
+    direct: &HashMap<PathBuf, (u64, u64)>,
+    children: &HashMap<&Path, Vec<&Path>>,
+) -> DirectoryNode {
+    let (mut size, mut file_count) = direct.get(dir).copied().unwrap_or_default();
+    let mut nodes: Vec<DirectoryNode> = children
+        .get(dir)
+        .into_iter()
+        .flatten()
+        .map(|child| build(child, direct, children))
+        .collect();
+    for node in &nodes {
+        size += node.size;
+        file_count += node.file_count;
+    }
+    nodes.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+    DirectoryNode {
+        path: dir.to_path_buf(),
+        size,
+        file_count,
+        children: nodes,
+    }
+}
+
+/// Directory sizes under a scanned root
+#[derive(Debug, Clone, Serialize)]
+pub struct DirectoryTree {
+    /// The scanned directory, with everything below it
+    pub root: DirectoryNode,
+    /// Directories that couldn't be read; their size is missing from
+    /// their parents
+    pub skipped: Vec<SkippedPath>,
+    /// Whether the scan was cancelled before completing (sizes are partial)
+    pub interrupted: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sizes_are_cumulative() {
+        let root = Path::new("/data");
+        let direct: HashMap<PathBuf, (u64, u64)> = [
+            ("/data", (5, 1)),
+            ("/data/a/b/c", (100, 2)),
+            ("/data/a", (10, 1)),
+            ("/data/d", (50, 1)),
+            ("/elsewhere", (1000, 1)),
+        ]
+        .into_iter()
+        .map(|(dir, sizes)| (PathBuf::from(dir), sizes))
+        .collect();
+
+        let tree = DirectoryNode::from_direct(root, &direct);
+        assert_eq!((tree.size, tree.file_count), (165, 5));
+        let names: Vec<_> = tree.children.iter().map(|c| c.path.clone()).collect();
+        assert_eq!(names, [PathBuf::from("/data/a"), PathBuf::from("/data/d")]);
+        assert_eq!(tree.children[0].size, 110);
+        assert_eq!(tree.children[0].children[0].size, 100);
+
+        let heaviest: Vec<_> = tree
+            .heaviest(3, None)
+            .into_iter()
+            .map(|(node, depth)| (node.path.to_string_lossy().into_owned(), depth))
+            .collect();
+        assert_eq!(
+            heaviest,
+            [
+                ("/data/a".to_string(), 1),
+                ("/data/a/b".to_string(), 2),
+                ("/data/a/b/c".to_string(), 3)
+            ]
+        );
+        assert_eq!(tree.heaviest(10, Some(1)).len(), 2);
+    }
+
+    #[test]
+    fn test_from_files_groups_by_parent() {
+        let files = [
+            ("/data/a/one.bin", 10),
+            ("/data/a/two.bin", 20),
+            ("/data/b/c/three.bin", 5),
+            ("/elsewhere/four.bin", 1000),
+        ];
+        let tree = DirectoryNode::from_files(Path::new("/data"), files);
+        assert_eq!((tree.size, tree.file_count), (35, 3));
+        assert_eq!(tree.children[0].path, PathBuf::from("/data/a"));
+        assert_eq!(tree.children[1].children[0].size, 5);
+    }
+}
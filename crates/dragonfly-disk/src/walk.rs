@@ -0,0 +1,288 @@
+//! Filesystem walking configured from [`ScanOptions`]
+//!
+//! The one walker every scanner uses, including the cleaner and the
+//! duplicate finder, so the walk flags mean the same thing everywhere.
+
+use dragonfly_core::domain::protection::SkippedPath;
+use dragonfly_core::domain::scan_options::ScanOptions;
+use dragonfly_core::domain::throttle;
+use jwalk::{Parallelism, WalkDir};
+use std::path::{Component, Path};
+
+/// How many files are counted between progress notifications
+pub(crate) const PROGRESS_INTERVAL: u64 = 1_000;
+
+/// Build a walker for `root` that honors the scan options
+///
+/// Excluded entries and, with `one_filesystem`, directories on other devices
+/// are pruned before they are read, so their contents are never visited.
+/// With `follow_symlinks`, links to a directory the walk is already inside
+/// are pruned too, so link loops end. With a process-wide [`throttle`],
+/// reading pauses to keep to its rate.
+pub fn walker(root: &Path, options: &ScanOptions) -> WalkDir {
+    let mut walk = WalkDir::new(root)
+        .follow_links(options.follow_symlinks)
+        .skip_hidden(!options.include_hidden);
+    if let Some(max_depth) = options.max_depth {
+        walk = walk.max_depth(max_depth);
+    }
+    if let Some(threads) = options.threads {
+        walk = walk.parallelism(Parallelism::RayonNewPool(threads));
+    }
+
+    let device = if options.one_filesystem {
+        device_id(root)
+    } else {
+        None
+    };
+    let throttle = throttle::throttle();
+    if options.excludes.is_empty()
+        && device.is_none()
+        && !options.follow_symlinks
+        && throttle.is_none()
+    {
+        return walk;
+    }
+
+    let options = options.clone();
+    walk.process_read_dir(move |_, parent, _, children| {
+        if let Some(throttle) = throttle {
+            throttle.pace(children.len());
+        }
+        children.retain(|child| {
+            let Ok(entry) = child else {
+                return true;
+            };
+            if !options.excludes.is_empty() && options.is_excluded(&entry.path().to_string_lossy())
+            {
+                return false;
+            }
+            if options.follow_symlinks
+                && entry.path_is_symlink()
+                && entry.file_type().is_dir()
+                && leads_back(parent, &entry.path())
+            {
+                return false;
+            }
+            match device {
+                Some(device) if entry.file_type().is_dir() => {
+                    entry_device(entry).map_or(true, |d| d == device)
+                }
+                _ => true,
+            }
+        });
+    })
+}
+
+/// Whether the directory the link at `link` points to contains `parent`, the
+/// directory being read, so following it would walk in circles
+///
+/// Both the path the walk took (through earlier links) and where `parent`
+/// really is count, which catches links pointing at each other as well as
+/// links pointing up.
+pub fn leads_back(parent: &Path, link: &Path) -> bool {
+    let Ok(target) = std::fs::canonicalize(link) else {
+        return false;
+    };
+    let real_parent = std::fs::canonicalize(parent).ok();
+    real_parent.iter().any(|real| real.starts_with(&target))
+        || parent
+            .ancestors()
+            .filter_map(|dir| std::fs::canonicalize(dir).ok())
+            .any(|dir| dir == target)
+}
+
+/// Whether a walk of `root` with `options` would visit `path`
+pub fn walk_includes(root: &Path, path: &Path, options: &ScanOptions) -> bool {
+    let Ok(relative) = path.strip_prefix(root) else {
+        return false;
+    };
+    if let Some(max_depth) = options.max_depth {
+        if relative.components().count() > max_depth {
+            return false;
+        }
+    }
+    if !options.include_hidden
+        && relative.components().any(|component| {
+            matches!(component, Component::Normal(name) if name.to_string_lossy().starts_with('.'))
+        })
+    {
+        return false;
+    }
+    options.excludes.is_empty() || !options.is_excluded(&path.to_string_lossy())
+}
+
+/// Convert a walk error into a skip record (permission errors only)
+pub fn skipped_from_walk_error(err: &jwalk::Error) -> Option<SkippedPath> {
+    let path = err.path()?.to_string_lossy();
+    SkippedPath::from_io_error(&path, err.io_error()?)
+}
+
+/// Device a path lives on
+#[cfg(unix)]
+pub fn device_id(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::metadata(path).ok().map(|m| m.dev())
+}
+
+/// Device a path lives on
+#[cfg(not(unix))]
+pub fn device_id(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Space a file occupies on disk, from its allocated 512-byte blocks
+#[cfg(unix)]
+pub(crate) fn allocated_size(metadata: &std::fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.blocks() * 512)
+}
+
+/// Space a file occupies on disk
+#[cfg(not(unix))]
+pub(crate) fn allocated_size(_metadata: &std::fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// A file's inode as (device, inode number), with how many names it has
+#[cfg(unix)]
+pub(crate) fn inode_links(metadata: &std::fs::Metadata) -> Option<((u64, u64), u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some(((metadata.dev(), metadata.ino()), metadata.nlink()))
+}
+
+/// A file's inode, with how many names it has
+#[cfg(not(unix))]
+pub(crate) fn inode_links(_metadata: &std::fs::Metadata) -> Option<((u64, u64), u64)> {
+    None
+}
+
+/// User id of a file's owner
+#[cfg(unix)]
+pub(crate) fn owner_uid(metadata: &std::fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.uid())
+}
+
+/// User id of a file's owner
+#[cfg(not(unix))]
+pub(crate) fn owner_uid(_metadata: &std::fs::Metadata) -> Option<u32> {
+    None
+}
+
+/// Device a walked entry lives on
+#[cfg(unix)]
+fn entry_device(entry: &jwalk::DirEntry<((), ())>) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    entry.metadata().ok().map(|m| m.dev())
+}
+
+/// Device a walked entry lives on
+#[cfg(not(unix))]
+fn entry_device(_entry: &jwalk::DirEntry<((), ())>) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn walked_files(root: &Path, options: &ScanOptions) -> Vec<String> {
+        let mut names: Vec<String> = walker(root, options)
+            .into_iter()
+            .flatten()
+            .filter(|e| e.file_type().is_file())
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_walker_honors_options() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("keep/deep")).unwrap();
+        fs::create_dir_all(root.join("node_modules")).unwrap();
+        fs::write(root.join("top.txt"), b"").unwrap();
+        fs::write(root.join(".hidden"), b"").unwrap();
+        fs::write(root.join("keep/deep/nested.txt"), b"").unwrap();
+        fs::write(root.join("node_modules/dep.js"), b"").unwrap();
+
+        assert_eq!(
+            walked_files(root, &ScanOptions::new()),
+            vec!["dep.js", "nested.txt", "top.txt"]
+        );
+        assert_eq!(
+            walked_files(root, &ScanOptions::new().exclude("node_modules")),
+            vec!["nested.txt", "top.txt"]
+        );
+        assert_eq!(
+            walked_files(root, &ScanOptions::new().with_max_depth(Some(1))),
+            vec!["top.txt"]
+        );
+        assert_eq!(
+            walked_files(root, &ScanOptions::new().with_hidden(true)),
+            vec![".hidden", "dep.js", "nested.txt", "top.txt"]
+        );
+        assert_eq!(
+            walked_files(root, &ScanOptions::new().with_one_filesystem(true)).len(),
+            3
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_followed_symlink_loops_end() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::create_dir_all(root.join("c")).unwrap();
+        fs::write(root.join("a/b/deep.txt"), b"").unwrap();
+        fs::write(root.join("c/other.txt"), b"").unwrap();
+        std::os::unix::fs::symlink(root, root.join("a/up")).unwrap();
+        std::os::unix::fs::symlink(root.join("c"), root.join("a/b/to_c")).unwrap();
+        std::os::unix::fs::symlink(root.join("a"), root.join("c/to_a")).unwrap();
+
+        // `c` is seen directly and once more through `to_c`; its link back
+        // to `a` isn't followed from inside `a`
+        let options = ScanOptions::new().with_follow_symlinks(true);
+        assert_eq!(
+            walked_files(root, &options),
+            vec!["deep.txt", "deep.txt", "other.txt", "other.txt"]
+        );
+    }
+
+    #[test]
+    fn test_walk_includes() {
+        let root = Path::new("/Users/me");
+        let options = ScanOptions::new().exclude("node_modules");
+        assert!(walk_includes(
+            root,
+            Path::new("/Users/me/a/big.iso"),
+            &options
+        ));
+        assert!(!walk_includes(
+            root,
+            Path::new("/Users/me/.cache/big"),
+            &options
+        ));
+        assert!(!walk_includes(
+            root,
+            Path::new("/Users/me/node_modules/x"),
+            &options
+        ));
+        assert!(!walk_includes(
+            root,
+            Path::new("/Volumes/Other/big"),
+            &options
+        ));
+        assert!(!walk_includes(
+            root,
+            Path::new("/Users/me/a/big.iso"),
+            &ScanOptions::new().with_max_depth(Some(1))
+        ));
+    }
+}
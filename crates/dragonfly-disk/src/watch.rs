@@ -0,0 +1,327 @@
+//! Filesystem watchers
+//!
+//! On macOS changes come from FSEvents, which reports the paths that changed
+//! shortly after the fact at very little cost. Elsewhere a polling watcher
+//! stands in: it reports every root as changed at a fixed interval, so
+//! consumers rescan instead of updating incrementally.
+
+use dragonfly_core::error::Result;
+use dragonfly_core::ports::{FileSystemWatcher, FsChange};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// How often the polling fallback reports its roots; each report means a
+/// full rescan, so this is much longer than an FSEvents latency
+#[cfg(not(target_os = "macos"))]
+const POLL_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Create the best watcher available on this platform
+///
+/// `latency` is how long changes are gathered before they are delivered;
+/// longer latencies coalesce bursts of changes to the same paths. It is
+/// ignored by the polling fallback.
+pub fn platform_watcher(
+    roots: &[PathBuf],
+    latency: Duration,
+) -> Result<Box<dyn FileSystemWatcher>> {
+    #[cfg(target_os = "macos")]
+    {
+        Ok(Box::new(fsevents::FsEventsWatcher::new(roots, latency)?))
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = latency;
+        Ok(Box::new(PollingWatcher::new(roots.to_vec(), POLL_INTERVAL)))
+    }
+}
+
+/// Watcher that reports every root as changed once per interval
+#[derive(Debug)]
+pub struct PollingWatcher {
+    roots: Vec<PathBuf>,
+    interval: Duration,
+    next_poll: Instant,
+}
+
+impl PollingWatcher {
+    /// Report `roots` as changed every `interval`, starting one interval from now
+    pub fn new(roots: Vec<PathBuf>, interval: Duration) -> Self {
+        Self {
+            roots,
+            interval,
+            next_poll: Instant::now() + interval,
+        }
+    }
+}
+
+impl FileSystemWatcher for PollingWatcher {
+    fn next_changes(&mut self, timeout: Duration) -> Result<Vec<FsChange>> {
+        let now = Instant::now();
+        if now < self.next_poll {
+            let wait = (self.next_poll - now).min(timeout);
+            std::thread::sleep(wait);
+            if Instant::now() < self.next_poll {
+                return Ok(Vec::new());
+            }
+        }
+        self.next_poll = Instant::now() + self.interval;
+        Ok(self.roots.iter().cloned().map(FsChange::subtree).collect())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod fsevents {
+    //! Minimal FSEvents bindings
+    //!
+    //! The stream delivers events on a private dispatch queue; the callback
+    //! forwards them through a channel that [`FsEventsWatcher::next_changes`]
+    //! reads from.
+
+    use core_foundation_sys::array::{kCFTypeArrayCallBacks, CFArrayCreate, CFArrayRef};
+    use core_foundation_sys::base::{kCFAllocatorDefault, CFAllocatorRef, CFIndex, CFRelease};
+    use core_foundation_sys::string::{
+        kCFStringEncodingUTF8, CFStringCreateWithBytes, CFStringRef,
+    };
+    use dragonfly_core::error::{Error, Result};
+    use dragonfly_core::ports::{FileSystemWatcher, FsChange};
+    use std::ffi::{c_char, c_void, CStr};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::PathBuf;
+    use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+    use std::time::Duration;
+
+    type FSEventStreamRef = *mut c_void;
+    type DispatchQueue = *mut c_void;
+
+    type FSEventStreamCallback = extern "C" fn(
+        stream: *const c_void,
+        info: *mut c_void,
+        num_events: usize,
+        event_paths: *mut c_void,
+        event_flags: *const u32,
+        event_ids: *const u64,
+    );
+
+    #[repr(C)]
+    struct FSEventStreamContext {
+        version: CFIndex,
+        info: *mut c_void,
+        retain: Option<extern "C" fn(*const c_void) -> *const c_void>,
+        release: Option<extern "C" fn(*const c_void)>,
+        copy_description: Option<extern "C" fn(*const c_void) -> CFStringRef>,
+    }
+
+    const EVENT_ID_SINCE_NOW: u64 = u64::MAX;
+    const CREATE_FLAG_NO_DEFER: u32 = 0x0000_0002;
+    const CREATE_FLAG_FILE_EVENTS: u32 = 0x0000_0010;
+    const EVENT_FLAG_MUST_SCAN_SUBDIRS: u32 = 0x0000_0001;
+    const EVENT_FLAG_USER_DROPPED: u32 = 0x0000_0002;
+    const EVENT_FLAG_KERNEL_DROPPED: u32 = 0x0000_0004;
+    const EVENT_FLAG_ROOT_CHANGED: u32 = 0x0000_0020;
+
+    #[link(name = "CoreServices", kind = "framework")]
+    extern "C" {
+        fn FSEventStreamCreate(
+            allocator: CFAllocatorRef,
+            callback: FSEventStreamCallback,
+            context: *const FSEventStreamContext,
+            paths_to_watch: CFArrayRef,
+            since_when: u64,
+            latency: f64,
+            flags: u32,
+        ) -> FSEventStreamRef;
+        fn FSEventStreamSetDispatchQueue(stream: FSEventStreamRef, queue: DispatchQueue);
+        fn FSEventStreamStart(stream: FSEventStreamRef) -> u8;
+        fn FSEventStreamStop(stream: FSEventStreamRef);
+        fn FSEventStreamInvalidate(stream: FSEventStreamRef);
+        fn FSEventStreamRelease(stream: FSEventStreamRef);
+    }
+
+    extern "C" {
+        fn dispatch_queue_create(label: *const c_char, attr: *mut c_void) -> DispatchQueue;
+        fn dispatch_release(object: *mut c_void);
+    }
+
+    /// Watcher backed by an FSEvents stream
+    pub(super) struct FsEventsWatcher {
+        stream: FSEventStreamRef,
+        queue: DispatchQueue,
+        sender: *mut Sender<Vec<FsChange>>,
+        receiver: Receiver<Vec<FsChange>>,
+    }
+
+    // The stream and queue are only touched through thread-safe FSEvents and
+    // dispatch calls, and the sender is only used by the callback until drop.
+    unsafe impl Send for FsEventsWatcher {}
+
+    impl FsEventsWatcher {
+        pub(super) fn new(roots: &[PathBuf], latency: Duration) -> Result<Self> {
+            let (sender, receiver) = channel();
+            let sender = Box::into_raw(Box::new(sender));
+
+            // SAFETY: every pointer passed below is valid for the duration of
+            // the call; the CF objects created here are released before
+            // returning, and the stream copies the paths it needs.
+            unsafe {
+                let paths: Vec<CFStringRef> = roots
+                    .iter()
+                    .map(|root| {
+                        let bytes = root.as_os_str().as_bytes();
+                        CFStringCreateWithBytes(
+                            kCFAllocatorDefault,
+                            bytes.as_ptr(),
+                            bytes.len() as CFIndex,
+                            kCFStringEncodingUTF8,
+                            0,
+                        )
+                    })
+                    .collect();
+                let array = CFArrayCreate(
+                    kCFAllocatorDefault,
+                    paths.as_ptr().cast(),
+                    paths.len() as CFIndex,
+                    &kCFTypeArrayCallBacks,
+                );
+                for path in &paths {
+                    CFRelease(path.cast());
+                }
+
+                let context = FSEventStreamContext {
+                    version: 0,
+                    info: sender.cast(),
+                    retain: None,
+                    release: None,
+                    copy_description: None,
+                };
+                let stream = FSEventStreamCreate(
+                    kCFAllocatorDefault,
+                    callback,
+                    &context,
+                    array,
+                    EVENT_ID_SINCE_NOW,
+                    latency.as_secs_f64(),
+                    CREATE_FLAG_NO_DEFER | CREATE_FLAG_FILE_EVENTS,
+                );
+                CFRelease(array.cast());
+                if stream.is_null() {
+                    drop(Box::from_raw(sender));
+                    return Err(Error::Internal(
+                        "Failed to create FSEvents stream".to_string(),
+                    ));
+                }
+
+                let queue = dispatch_queue_create(
+                    b"com.dragonfly.fsevents\0".as_ptr().cast(),
+                    std::ptr::null_mut(),
+                );
+                FSEventStreamSetDispatchQueue(stream, queue);
+                if FSEventStreamStart(stream) == 0 {
+                    FSEventStreamInvalidate(stream);
+                    FSEventStreamRelease(stream);
+                    dispatch_release(queue);
+                    drop(Box::from_raw(sender));
+                    return Err(Error::Internal(
+                        "Failed to start FSEvents stream".to_string(),
+                    ));
+                }
+
+                Ok(Self {
+                    stream,
+                    queue,
+                    sender,
+                    receiver,
+                })
+            }
+        }
+    }
+
+    impl FileSystemWatcher for FsEventsWatcher {
+        fn next_changes(&mut self, timeout: Duration) -> Result<Vec<FsChange>> {
+            let mut changes = match self.receiver.recv_timeout(timeout) {
+                Ok(changes) => changes,
+                Err(RecvTimeoutError::Timeout) => return Ok(Vec::new()),
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(Error::Internal("FSEvents stream stopped".to_string()))
+                }
+            };
+            while let Ok(more) = self.receiver.try_recv() {
+                changes.extend(more);
+            }
+            Ok(changes)
+        }
+    }
+
+    impl Drop for FsEventsWatcher {
+        fn drop(&mut self) {
+            // SAFETY: stopping and invalidating the stream guarantees the
+            // callback won't run again, so the sender can be freed after.
+            unsafe {
+                FSEventStreamStop(self.stream);
+                FSEventStreamInvalidate(self.stream);
+                FSEventStreamRelease(self.stream);
+                dispatch_release(self.queue);
+                drop(Box::from_raw(self.sender));
+            }
+        }
+    }
+
+    extern "C" fn callback(
+        _stream: *const c_void,
+        info: *mut c_void,
+        num_events: usize,
+        event_paths: *mut c_void,
+        event_flags: *const u32,
+        _event_ids: *const u64,
+    ) {
+        // SAFETY: FSEvents passes `num_events` C strings and flags, and `info`
+        // is the sender installed in `FsEventsWatcher::new`.
+        let (sender, paths, flags) = unsafe {
+            (
+                &*info.cast::<Sender<Vec<FsChange>>>(),
+                std::slice::from_raw_parts(event_paths.cast::<*const c_char>(), num_events),
+                std::slice::from_raw_parts(event_flags, num_events),
+            )
+        };
+
+        let changes = paths
+            .iter()
+            .zip(flags)
+            .map(|(&path, &flags)| {
+                // SAFETY: each entry is a NUL-terminated path string
+                let path = unsafe { CStr::from_ptr(path) };
+                let path = PathBuf::from(std::ffi::OsStr::from_bytes(path.to_bytes()));
+                let rescan = EVENT_FLAG_MUST_SCAN_SUBDIRS
+                    | EVENT_FLAG_USER_DROPPED
+                    | EVENT_FLAG_KERNEL_DROPPED
+                    | EVENT_FLAG_ROOT_CHANGED;
+                if flags & rescan != 0 {
+                    FsChange::subtree(path)
+                } else {
+                    FsChange::path(path)
+                }
+            })
+            .collect();
+        let _ = sender.send(changes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_polling_watcher_reports_roots_each_interval() {
+        let roots = vec![PathBuf::from("/a"), PathBuf::from("/b")];
+        let mut watcher = PollingWatcher::new(roots, Duration::from_millis(20));
+
+        assert!(watcher
+            .next_changes(Duration::from_millis(1))
+            .unwrap()
+            .is_empty());
+        let changes = watcher.next_changes(Duration::from_millis(100)).unwrap();
+        assert_eq!(
+            changes,
+            vec![FsChange::subtree("/a"), FsChange::subtree("/b")]
+        );
+    }
+}
@@ -0,0 +1,187 @@
+//! Persistent cache of file hashes
+//!
+//! Hashing dominates a duplicate scan, and between two scans of the same tree
+//! most files haven't changed. [`HashCache`] keeps the hashes in
+//! `~/.dragonfly/hash-cache/hashes.db`, keyed by the file's device, inode,
+//! size, and modification time, so a file is only read again once one of
+//! those changes. Entries expire after [`MAX_AGE_DAYS`] so hashes of files
+//! that are long gone don't pile up.
+
+use crate::hasher::HashAlgorithm;
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use dragonfly_core::error::{Error, Result};
+use dragonfly_core::ports::CacheService;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::fs::Metadata;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+/// Days after which a cached hash is dropped and computed again
+pub const MAX_AGE_DAYS: i64 = 90;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS hashes (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL,
+        stored_at INTEGER NOT NULL
+    );
+";
+
+/// On-disk cache of file hashes, behind the [`CacheService`] port
+#[derive(Debug)]
+pub struct HashCache {
+    conn: Mutex<Connection>,
+}
+
+impl HashCache {
+    /// Default cache directory (`~/.dragonfly/hash-cache`)
+    pub fn default_dir() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_else(|| PathBuf::from("~"))
+            .join(".dragonfly")
+            .join("hash-cache")
+    }
+
+    /// Open the cache in `dir`, creating it if needed, and drop expired
+    /// entries
+    pub fn open(dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let conn = Connection::open(dir.join("hashes.db")).map_err(db_error)?;
+        // WAL lets concurrent scans share the cache; NORMAL skips the sync on
+        // every insert, and a lost entry only means hashing the file again
+        conn.pragma_update(None, "journal_mode", "WAL")
+            .map_err(db_error)?;
+        conn.pragma_update(None, "synchronous", "NORMAL")
+            .map_err(db_error)?;
+        conn.execute_batch(SCHEMA).map_err(db_error)?;
+        let expired = (Utc::now() - Duration::days(MAX_AGE_DAYS)).timestamp();
+        conn.execute("DELETE FROM hashes WHERE stored_at < ?1", params![expired])
+            .map_err(db_error)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[async_trait]
+impl CacheService for HashCache {
+    async fn set(&self, key: &str, value: serde_json::Value) -> Result<()> {
+        self.conn()
+            .execute(
+                "INSERT OR REPLACE INTO hashes (key, value, stored_at) VALUES (?1, ?2, ?3)",
+                params![key, value.to_string(), Utc::now().timestamp()],
+            )
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<serde_json::Value>> {
+        let value: Option<String> = self
+            .conn()
+            .query_row(
+                "SELECT value FROM hashes WHERE key = ?1",
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(db_error)?;
+        value
+            .map(|value| {
+                serde_json::from_str(&value)
+                    .map_err(|e| Error::Internal(format!("Malformed hash cache entry: {e}")))
+            })
+            .transpose()
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.conn()
+            .execute("DELETE FROM hashes WHERE key = ?1", params![key])
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    async fn clear(&self) -> Result<()> {
+        self.conn()
+            .execute("DELETE FROM hashes", [])
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        Ok(self.get(key).await?.is_some())
+    }
+}
+
+/// Key of the `part` hash (`partial` or `full`) of the file with `metadata`
+///
+/// Any change to the file's content moves its modification time, which
+/// makes the key miss; a file replaced by another gets a new inode.
+pub(crate) fn cache_key(metadata: &Metadata, algorithm: HashAlgorithm, part: &str) -> String {
+    format!(
+        "{part}:{algorithm}:{}:{}:{}:{}.{}",
+        metadata.dev(),
+        metadata.ino(),
+        metadata.len(),
+        metadata.mtime(),
+        metadata.mtime_nsec()
+    )
+}
+
+fn db_error(error: rusqlite::Error) -> Error {
+    Error::Internal(format!("Hash cache database error: {}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_cache_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let cache = HashCache::open(dir.path()).unwrap();
+        assert_eq!(cache.get("a").await.unwrap(), None);
+
+        cache.set("a", json!("abc")).await.unwrap();
+        assert_eq!(cache.get("a").await.unwrap(), Some(json!("abc")));
+        assert!(cache.exists("a").await.unwrap());
+
+        // Entries outlive the connection
+        drop(cache);
+        let cache = HashCache::open(dir.path()).unwrap();
+        assert_eq!(cache.get("a").await.unwrap(), Some(json!("abc")));
+
+        cache.delete("a").await.unwrap();
+        assert!(!cache.exists("a").await.unwrap());
+        cache.set("b", json!("def")).await.unwrap();
+        cache.clear().await.unwrap();
+        assert!(!cache.exists("b").await.unwrap());
+    }
+
+    #[test]
+    fn test_key_changes_with_content() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("file");
+        std::fs::write(&path, b"one").unwrap();
+        let before = cache_key(
+            &std::fs::metadata(&path).unwrap(),
+            HashAlgorithm::Blake3,
+            "full",
+        );
+        std::fs::write(&path, b"three").unwrap();
+        let after = cache_key(
+            &std::fs::metadata(&path).unwrap(),
+            HashAlgorithm::Blake3,
+            "full",
+        );
+        assert_ne!(before, after);
+        assert!(before.starts_with("full:BLAKE3:"));
+    }
+}
@@ -0,0 +1,458 @@
+//! Replacing redundant copies with clones or hard links
+//!
+//! Deleting duplicates breaks every path that led to a removed copy.
+//! Replacing each copy with an APFS clone of the copy that is kept (a
+//! copy-on-write file sharing its storage) or with a hard link to it frees
+//! the same space while every path stays valid.
+//!
+//! Work happens in two steps: [`Deduplicator::plan`] picks the copy to keep
+//! in each group and rules out copies that can't be replaced safely, using
+//! only their metadata, and [`Deduplicator::apply`] verifies that each copy
+//! still holds the same content as its original before replacing it. A copy
+//! is never modified in place: the clone or link is created next to it and
+//! renamed over it, so a failure leaves the copy as it was.
+
+use crate::detector::DuplicateGroup;
+use crate::hasher::{self, HashAlgorithm};
+use crate::strategy::KeepStrategy;
+use dragonfly_core::ports::CancellationToken;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+/// How redundant copies are replaced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DedupeMode {
+    /// An APFS clone: a separate file sharing storage until either is
+    /// modified. Needs an APFS volume.
+    Clone,
+    /// A hard link: another name for the kept file, so a change made
+    /// through one path shows through all of them
+    Hardlink,
+}
+
+/// A copy to be replaced by a clone of, or link to, the copy that is kept
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DedupeAction {
+    /// Copy that is kept
+    pub original: String,
+    /// Copy that is replaced
+    pub copy: String,
+    /// Size of the copy
+    pub size: u64,
+    /// Bytes freed by replacing it: none when the copy has other hard links,
+    /// which keep its data on disk
+    pub freed: u64,
+}
+
+/// Why a copy was left as it is
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// It lies in a protected location
+    Protected,
+    /// It is already a hard link to the kept copy
+    AlreadyShared,
+    /// It is on another volume than the kept copy
+    OtherVolume,
+    /// Its owner or permissions differ from the kept copy's, which a hard
+    /// link would replace, or its owner can't be given to a clone
+    DifferentMetadata,
+    /// Its content no longer matches the kept copy
+    Changed,
+    /// Replacing it failed
+    Failed(String),
+}
+
+/// A copy left as it is, and why
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DedupeSkip {
+    /// Path of the copy
+    pub path: String,
+    /// Reason it was left alone
+    pub reason: SkipReason,
+}
+
+/// Copies that can be replaced, worked out before anything is changed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DedupePlan {
+    /// Copies to replace
+    pub actions: Vec<DedupeAction>,
+    /// Copies ruled out
+    pub skipped: Vec<DedupeSkip>,
+}
+
+impl DedupePlan {
+    /// Bytes the plan would free
+    pub fn reclaimable(&self) -> u64 {
+        self.actions.iter().map(|action| action.freed).sum()
+    }
+}
+
+/// Outcome of applying a plan
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DedupeReport {
+    /// Copies replaced
+    pub replaced: Vec<DedupeAction>,
+    /// Copies left alone when it came to replacing them
+    pub skipped: Vec<DedupeSkip>,
+    /// Bytes freed
+    pub reclaimed: u64,
+    /// Whether the run was cancelled before every action was applied
+    pub interrupted: bool,
+}
+
+/// Replaces redundant copies of duplicate files with clones or hard links
+#[derive(Debug, Clone, Copy)]
+pub struct Deduplicator {
+    /// How copies are replaced
+    mode: DedupeMode,
+    /// Hash used to confirm that a copy still matches its original
+    algorithm: HashAlgorithm,
+}
+
+impl Deduplicator {
+    /// Create a deduplicator replacing copies the given way
+    pub fn new(mode: DedupeMode) -> Self {
+        Self {
+            mode,
+            algorithm: HashAlgorithm::default(),
+        }
+    }
+
+    /// How copies are replaced
+    pub fn mode(&self) -> DedupeMode {
+        self.mode
+    }
+
+    /// Pick the copy to keep in each group and the copies that can be
+    /// replaced by it, without changing anything
+    pub fn plan(&self, groups: &[DuplicateGroup], keep: KeepStrategy) -> DedupePlan {
+        let mut plan = DedupePlan::default();
+        for group in groups {
+            let Some(kept) = keep.pick(&group.files) else {
+                continue;
+            };
+            let original = &group.files[kept];
+            let Ok(original_meta) = fs::metadata(&original.path) else {
+                continue;
+            };
+            for (i, copy) in group.files.iter().enumerate() {
+                if i == kept {
+                    continue;
+                }
+                let checked = if copy.protection.is_protected() {
+                    Err(SkipReason::Protected)
+                } else {
+                    match fs::metadata(&copy.path) {
+                        Ok(meta) => match self.rule_out(&original_meta, &meta) {
+                            Some(reason) => Err(reason),
+                            None => Ok(freed_by_replacing(&meta)),
+                        },
+                        Err(e) => Err(SkipReason::Failed(e.to_string())),
+                    }
+                };
+                match checked {
+                    Err(reason) => plan.skipped.push(DedupeSkip {
+                        path: copy.path.clone(),
+                        reason,
+                    }),
+                    Ok(freed) => plan.actions.push(DedupeAction {
+                        original: original.path.clone(),
+                        copy: copy.path.clone(),
+                        size: copy.size,
+                        freed,
+                    }),
+                }
+            }
+        }
+        plan
+    }
+
+    /// Replace the copies of `actions`, confirming first that each still
+    /// holds the same bytes as its original
+    ///
+    /// Stops early if `cancel` fires; actions not reached are left out of the
+    /// report.
+    pub fn apply(&self, actions: &[DedupeAction], cancel: &CancellationToken) -> DedupeReport {
+        let mut report = DedupeReport::default();
+        for action in actions {
+            if cancel.is_cancelled() {
+                report.interrupted = true;
+                break;
+            }
+            match self.apply_one(action) {
+                Ok(Outcome::Replaced { freed }) => {
+                    report.reclaimed += freed;
+                    report.replaced.push(DedupeAction {
+                        freed,
+                        ..action.clone()
+                    });
+                }
+                Ok(Outcome::Skipped(reason)) => report.skipped.push(DedupeSkip {
+                    path: action.copy.clone(),
+                    reason,
+                }),
+                Err(e) => report.skipped.push(DedupeSkip {
+                    path: action.copy.clone(),
+                    reason: SkipReason::Failed(e.to_string()),
+                }),
+            }
+        }
+        report
+    }
+
+    /// Reason `copy` can't be replaced by a clone of or link to `original`
+    fn rule_out(&self, original: &fs::Metadata, copy: &fs::Metadata) -> Option<SkipReason> {
+        if original.dev() != copy.dev() {
+            return Some(SkipReason::OtherVolume);
+        }
+        if original.ino() == copy.ino() {
+            return Some(SkipReason::AlreadyShared);
+        }
+        if self.mode == DedupeMode::Hardlink
+            && (original.uid() != copy.uid()
+                || original.gid() != copy.gid()
+                || original.mode() != copy.mode())
+        {
+            return Some(SkipReason::DifferentMetadata);
+        }
+        None
+    }
+
+    /// Replace one copy, or say why it was left alone
+    fn apply_one(&self, action: &DedupeAction) -> io::Result<Outcome> {
+        let original = Path::new(&action.original);
+        let copy = Path::new(&action.copy);
+        let (original_meta, copy_meta) = (fs::metadata(original)?, fs::metadata(copy)?);
+        if let Some(reason) = self.rule_out(&original_meta, &copy_meta) {
+            return Ok(Outcome::Skipped(reason));
+        }
+        if original_meta.len() != copy_meta.len()
+            || copy_meta.len() != action.size
+            || hasher::hash_file(self.algorithm, original)?
+                != hasher::hash_file(self.algorithm, copy)?
+        {
+            return Ok(Outcome::Skipped(SkipReason::Changed));
+        }
+
+        let temp = temp_path(copy);
+        match self.mode {
+            DedupeMode::Hardlink => fs::hard_link(original, &temp)?,
+            DedupeMode::Clone => {
+                clone_file(original, &temp)?;
+                match take_metadata(&temp, &copy_meta) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                        let _ = fs::remove_file(&temp);
+                        return Ok(Outcome::Skipped(SkipReason::DifferentMetadata));
+                    }
+                    Err(e) => {
+                        let _ = fs::remove_file(&temp);
+                        return Err(e);
+                    }
+                }
+            }
+        }
+        if let Err(e) = fs::rename(&temp, copy) {
+            let _ = fs::remove_file(&temp);
+            return Err(e);
+        }
+        Ok(Outcome::Replaced {
+            freed: freed_by_replacing(&copy_meta),
+        })
+    }
+}
+
+/// What became of one action
+enum Outcome {
+    /// The copy was replaced, freeing this many bytes
+    Replaced { freed: u64 },
+    /// The copy was left alone
+    Skipped(SkipReason),
+}
+
+/// Bytes replacing a copy with `meta` frees: its size, unless other hard
+/// links to it keep the data
+fn freed_by_replacing(meta: &fs::Metadata) -> u64 {
+    if meta.nlink() > 1 {
+        0
+    } else {
+        meta.len()
+    }
+}
+
+/// Give the clone at `clone` the modification time, permissions and owner of
+/// the copy it replaces, rather than those of the original it was made from
+///
+/// The owner goes last, since once it changes the clone may no longer be
+/// ours to modify. Changing it needs root unless the copy is our own.
+fn take_metadata(clone: &Path, copy: &fs::Metadata) -> io::Result<()> {
+    fs::File::open(clone)?.set_modified(copy.modified()?)?;
+    fs::set_permissions(clone, copy.permissions())?;
+    let clone_meta = fs::metadata(clone)?;
+    if clone_meta.uid() != copy.uid() || clone_meta.gid() != copy.gid() {
+        std::os::unix::fs::chown(clone, Some(copy.uid()), Some(copy.gid()))?;
+    }
+    Ok(())
+}
+
+/// Name the replacement for `copy` is created under before it takes its place
+fn temp_path(copy: &Path) -> PathBuf {
+    let name = copy
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    copy.with_file_name(format!(".{name}.dragonfly-dedupe"))
+}
+
+/// Create `dst` as an APFS clone of `src`
+#[cfg(target_os = "macos")]
+fn clone_file(src: &Path, dst: &Path) -> io::Result<()> {
+    use std::ffi::{c_char, c_int, CString};
+    use std::os::unix::ffi::OsStrExt;
+
+    extern "C" {
+        fn clonefile(src: *const c_char, dst: *const c_char, flags: u32) -> c_int;
+    }
+
+    let c_path = |path: &Path| {
+        CString::new(path.as_os_str().as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+    };
+    let (src, dst) = (c_path(src)?, c_path(dst)?);
+    // SAFETY: both arguments are valid C strings
+    if unsafe { clonefile(src.as_ptr(), dst.as_ptr(), 0) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Clones are an APFS feature; elsewhere there is no way to make one
+#[cfg(not(target_os = "macos"))]
+fn clone_file(_src: &Path, _dst: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "clones need an APFS volume",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::DetectionStage;
+    use dragonfly_core::domain::entities::FileEntity;
+    use tempfile::TempDir;
+
+    fn group(dir: &Path, names: &[&str], content: &[u8]) -> DuplicateGroup {
+        DuplicateGroup {
+            files: names
+                .iter()
+                .map(|name| {
+                    let path = dir.join(name);
+                    fs::write(&path, content).unwrap();
+                    FileEntity::new(path.to_string_lossy().to_string(), content.len() as u64)
+                })
+                .collect(),
+            hash: String::new(),
+            confirmed_by: DetectionStage::FullHash,
+            verification: None,
+        }
+    }
+
+    #[test]
+    fn test_hardlink_replaces_copies() {
+        let dir = TempDir::new().unwrap();
+        let groups = [group(dir.path(), &["a", "b", "c"], b"same content")];
+        let deduplicator = Deduplicator::new(DedupeMode::Hardlink);
+
+        let plan = deduplicator.plan(&groups, KeepStrategy::First);
+        assert_eq!(plan.actions.len(), 2);
+        assert_eq!(plan.reclaimable(), 24);
+
+        let report = deduplicator.apply(&plan.actions, &CancellationToken::new());
+        assert_eq!(report.replaced.len(), 2);
+        assert_eq!(report.reclaimed, 24);
+        let inode = |name: &str| fs::metadata(dir.path().join(name)).unwrap().ino();
+        assert_eq!(inode("a"), inode("b"));
+        assert_eq!(inode("a"), inode("c"));
+        assert_eq!(fs::read(dir.path().join("c")).unwrap(), b"same content");
+
+        // Linked copies aren't planned again
+        let plan = deduplicator.plan(&groups, KeepStrategy::First);
+        assert!(plan.actions.is_empty());
+        assert_eq!(plan.skipped[0].reason, SkipReason::AlreadyShared);
+    }
+
+    #[test]
+    fn test_linked_copy_frees_nothing() {
+        let dir = TempDir::new().unwrap();
+        let groups = [group(dir.path(), &["a", "b"], b"same content")];
+        // Another name for b, outside the scan, keeps its data on disk
+        fs::hard_link(dir.path().join("b"), dir.path().join("b-elsewhere")).unwrap();
+        let deduplicator = Deduplicator::new(DedupeMode::Hardlink);
+
+        let plan = deduplicator.plan(&groups, KeepStrategy::First);
+        assert_eq!(plan.actions.len(), 1);
+        assert_eq!((plan.actions[0].size, plan.actions[0].freed), (12, 0));
+        assert_eq!(plan.reclaimable(), 0);
+
+        let report = deduplicator.apply(&plan.actions, &CancellationToken::new());
+        assert_eq!(report.replaced.len(), 1);
+        assert_eq!(report.reclaimed, 0);
+        assert_eq!(
+            fs::read(dir.path().join("b-elsewhere")).unwrap(),
+            b"same content"
+        );
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn test_clone_keeps_the_copys_modification_time() {
+        let dir = TempDir::new().unwrap();
+        let groups = [group(dir.path(), &["a", "b"], b"same content")];
+        let copy = dir.path().join("b");
+        let modified = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1 << 30);
+        fs::File::open(&copy)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        let deduplicator = Deduplicator::new(DedupeMode::Clone);
+
+        let plan = deduplicator.plan(&groups, KeepStrategy::First);
+        let report = deduplicator.apply(&plan.actions, &CancellationToken::new());
+        assert_eq!(report.replaced.len(), 1);
+        assert_eq!(report.reclaimed, 12);
+        assert_eq!(fs::metadata(&copy).unwrap().modified().unwrap(), modified);
+    }
+
+    #[test]
+    fn test_changed_copy_is_left_alone() {
+        let dir = TempDir::new().unwrap();
+        let groups = [group(dir.path(), &["a", "b"], b"same content")];
+        let deduplicator = Deduplicator::new(DedupeMode::Hardlink);
+        let plan = deduplicator.plan(&groups, KeepStrategy::First);
+
+        fs::write(dir.path().join("b"), b"other bytes!").unwrap();
+        let report = deduplicator.apply(&plan.actions, &CancellationToken::new());
+        assert!(report.replaced.is_empty());
+        assert_eq!(report.skipped[0].reason, SkipReason::Changed);
+        assert_eq!(fs::read(dir.path().join("b")).unwrap(), b"other bytes!");
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn test_clone_fails_without_apfs() {
+        let dir = TempDir::new().unwrap();
+        let groups = [group(dir.path(), &["a", "b"], b"same content")];
+        let deduplicator = Deduplicator::new(DedupeMode::Clone);
+        let plan = deduplicator.plan(&groups, KeepStrategy::First);
+        let report = deduplicator.apply(&plan.actions, &CancellationToken::new());
+        assert!(matches!(report.skipped[0].reason, SkipReason::Failed(_)));
+        assert_eq!(fs::read(dir.path().join("b")).unwrap(), b"same content");
+        assert!(!temp_path(&dir.path().join("b")).exists());
+    }
+}
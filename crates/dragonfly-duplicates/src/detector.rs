@@ -1,28 +1,109 @@
 //! Duplicate file detection orchestration
 
-use crate::hasher::HashAlgorithm;
+use crate::cache::cache_key;
+use crate::hasher::{self, HashAlgorithm};
+use crate::media::{self, MediaKind, Payload};
+use crate::protect::ProtectedPaths;
+use crate::strategy::{DetectionStage, DetectionStrategy};
+use crate::verify;
+use chrono::DateTime;
 use dragonfly_core::domain::entities::FileEntity;
+use dragonfly_core::domain::protection::SkippedPath;
+use dragonfly_core::domain::scan_options::ScanOptions;
+use dragonfly_core::domain::scan_profile::{micros_since, PhaseTimer, ScanProfile};
 use dragonfly_core::domain::value_objects::FilePath;
 use dragonfly_core::error::Result;
-use jwalk::WalkDir;
+use dragonfly_core::ports::{
+    CacheService, CancellationToken, NoProgress, ProgressReporter, ProgressUpdate,
+};
+use dragonfly_disk::walk::{skipped_from_walk_error, walker};
+use rayon::iter::Either;
 use rayon::prelude::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Files walked between progress updates
+const PROGRESS_INTERVAL: u64 = 1000;
+
+/// Stages a scan reports in [`ProgressUpdate::phase`]
+pub mod phase {
+    /// Walking the roots; counts are files found and their bytes
+    pub const WALK: &str = "walk";
+    /// Hashing the head and tail of files sharing a size
+    pub const PARTIAL_HASH: &str = "partial_hash";
+    /// Hashing whole files whose partial hashes matched
+    pub const FULL_HASH: &str = "full_hash";
+    /// Comparing the files of each group byte by byte; items are groups
+    pub const VERIFY: &str = "verify";
+    /// Hashing the audio, image or video data of media files
+    pub const MEDIA_HASH: &str = "media_hash";
+}
 
 /// Duplicate detector orchestrates finding duplicate files
-#[derive(Debug, Clone, Copy)]
+#[derive(Clone)]
 pub struct DuplicateDetector {
     /// Hash algorithm to use
     algorithm: HashAlgorithm,
+    /// How thoroughly files are compared
+    strategy: DetectionStrategy,
+    /// Where hashes of unchanged files are kept between scans
+    cache: Option<Arc<dyn CacheService>>,
+    /// Whether groups are confirmed byte by byte after hashing
+    verify: bool,
+    /// Whether media files are compared without their tags
+    media: bool,
+    /// Locations whose files are always kept
+    protected: ProtectedPaths,
+    /// Observer of the scan's progress
+    progress: Arc<dyn ProgressReporter>,
+}
+
+impl std::fmt::Debug for DuplicateDetector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DuplicateDetector")
+            .field("algorithm", &self.algorithm)
+            .field("strategy", &self.strategy)
+            .field("cache", &self.cache.is_some())
+            .field("verify", &self.verify)
+            .field("media", &self.media)
+            .field("protected", &self.protected)
+            .finish()
+    }
+}
+
+/// Files found to hold the same content
+#[derive(Debug, Clone)]
+pub struct DuplicateGroup {
+    /// The files, all of the same size unless matched by
+    /// [`DetectionStage::MediaHash`]
+    pub files: Vec<FileEntity>,
+    /// Hash the files share, of their first and last 64 KB if only that was
+    /// compared
+    pub hash: String,
+    /// Comparison that confirmed them
+    pub confirmed_by: DetectionStage,
+    /// Outcome of the byte comparison, if the group was verified
+    pub verification: Option<verify::Verification>,
 }
 
 /// Result of duplicate detection
 #[derive(Debug, Clone)]
 pub struct DuplicateResult {
-    /// Groups of duplicate files (each group contains files with same hash)
-    pub duplicates: Vec<Vec<FileEntity>>,
+    /// Groups of duplicate files
+    pub duplicates: Vec<DuplicateGroup>,
     /// Total space that could be saved by removing duplicates
     pub potential_savings: u64,
+    /// Files that couldn't be read or hashed, and so weren't compared
+    pub skipped: Vec<SkippedPath>,
+    /// Whether the scan was cancelled before completing (results are partial)
+    pub interrupted: bool,
+    /// Where the scan spent its time
+    pub profile: ScanProfile,
+    /// Hashes taken from the cache instead of reading the file
+    pub cache_hits: u64,
 }
 
 impl DuplicateDetector {
@@ -30,113 +111,600 @@ impl DuplicateDetector {
     pub fn new() -> Self {
         Self {
             algorithm: HashAlgorithm::default(),
+            strategy: DetectionStrategy::default(),
+            cache: None,
+            verify: false,
+            media: false,
+            protected: ProtectedPaths::default(),
+            progress: Arc::new(NoProgress),
         }
     }
 
     /// Create a new duplicate detector with specified algorithm
     pub fn with_algorithm(algorithm: HashAlgorithm) -> Self {
-        Self { algorithm }
+        Self {
+            algorithm,
+            strategy: DetectionStrategy::default(),
+            cache: None,
+            verify: false,
+            media: false,
+            protected: ProtectedPaths::default(),
+            progress: Arc::new(NoProgress),
+        }
+    }
+
+    /// Set how thoroughly files are compared
+    pub fn with_strategy(mut self, strategy: DetectionStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Reuse hashes of unchanged files from `cache`, and store new ones there
+    pub fn with_cache(mut self, cache: Arc<dyn CacheService>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Compare the files of every group byte by byte before reporting them,
+    /// guarding against hash collisions and files that changed or can't be
+    /// read
+    pub fn with_verification(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Compare music, photos and videos ([`MediaKind`]) by a hash of their
+    /// audio, image or video data, leaving out ID3 tags, EXIF and other
+    /// metadata, so copies that were tagged or edited differently still
+    /// group together
+    ///
+    /// Such groups are confirmed by [`DetectionStage::MediaHash`], hold
+    /// files of different sizes, and aren't byte-compared by
+    /// [`with_verification`](Self::with_verification). Files that don't
+    /// parse as the format their extension names are compared whole.
+    pub fn with_media(mut self, media: bool) -> Self {
+        self.media = media;
+        self
+    }
+
+    /// Mark files in `protected` locations so no copy there is proposed for
+    /// removal (the built-in [`ProtectedPaths`] by default)
+    pub fn with_protected(mut self, protected: ProtectedPaths) -> Self {
+        self.protected = protected;
+        self
+    }
+
+    /// Tell `progress` how far scans have got
+    ///
+    /// Updates carry the [`phase`] the scan is in. While walking they count
+    /// the files found so far; while hashing they count files and bytes
+    /// hashed against totals known up front, so a remaining time can be
+    /// estimated from them.
+    pub fn with_progress(mut self, progress: Arc<dyn ProgressReporter>) -> Self {
+        self.progress = progress;
+        self
     }
 
     /// Find duplicates in a directory
     pub async fn find_duplicates(&self, path: &FilePath, min_size: u64) -> Result<DuplicateResult> {
-        let path_str = path.as_str();
-        let base_path = Path::new(path_str);
+        self.find_duplicates_with_options(
+            path,
+            min_size,
+            &ScanOptions::default(),
+            &CancellationToken::new(),
+        )
+        .await
+    }
 
-        if !base_path.exists() {
+    /// Find duplicates with the given scan options
+    ///
+    /// Files are grouped by size first: a file no other file matches in size
+    /// can't have a duplicate, so only files sharing their size with another
+    /// are hashed. On real trees of photos and documents that skips most of
+    /// the reading. Those are then grouped by a hash of their first and last
+    /// 64 KB, and with [`DetectionStrategy::Full`] the files still grouped
+    /// are hashed whole. Files small enough for the partial hash to cover
+    /// are never read twice.
+    ///
+    /// Stops early with partial results if `cancel` fires; groups in a partial
+    /// result only cover the files hashed before cancellation.
+    pub async fn find_duplicates_with_options(
+        &self,
+        path: &FilePath,
+        min_size: u64,
+        options: &ScanOptions,
+        cancel: &CancellationToken,
+    ) -> Result<DuplicateResult> {
+        self.find_duplicates_in_roots(std::slice::from_ref(path), min_size, options, cancel)
+            .await
+    }
+
+    /// Find duplicates within and across several roots, such as a folder and
+    /// its copy on a backup drive
+    ///
+    /// With more than one root, each file's [`FileEntity::root`] is the index
+    /// of the root it was found under. A file under two overlapping roots
+    /// counts once, for the earlier root.
+    pub async fn find_duplicates_in_roots(
+        &self,
+        roots: &[FilePath],
+        min_size: u64,
+        options: &ScanOptions,
+        cancel: &CancellationToken,
+    ) -> Result<DuplicateResult> {
+        if let Some(missing) = roots.iter().find(|root| !Path::new(root.as_str()).exists()) {
             return Err(dragonfly_core::error::Error::NotFound(format!(
                 "Path does not exist: {}",
-                path_str
+                missing.as_str()
             )));
         }
 
+        let started = Instant::now();
+        let (walk, stat) = (PhaseTimer::new(), PhaseTimer::new());
+
         // Collect files meeting minimum size
-        let files: Vec<FileEntity> = WalkDir::new(base_path)
-            .into_iter()
-            .par_bridge()
-            .filter_map(|entry| {
-                let entry = entry.ok()?;
-                let metadata = entry.metadata().ok()?;
-
-                if metadata.is_file() && metadata.len() >= min_size {
-                    let size = metadata.len();
-                    let path_str = entry.path().to_string_lossy().to_string();
-                    Some(FileEntity {
-                        path: path_str,
-                        size,
+        let mut files = Vec::new();
+        let mut skipped = Vec::new();
+        let mut seen = HashSet::new();
+        let (found_files, found_bytes) = (AtomicU64::new(0), AtomicU64::new(0));
+        for (index, root) in roots.iter().enumerate() {
+            let root_index = (roots.len() > 1).then_some(index);
+            let mut entries = walker(Path::new(root.as_str()), options).into_iter();
+            let (found, denied): (Vec<FileEntity>, Vec<SkippedPath>) =
+                std::iter::from_fn(|| walk.time(|| entries.next()))
+                    .take_while(|_| !cancel.is_cancelled())
+                    .par_bridge()
+                    .filter_map(|entry| {
+                        let entry = match entry {
+                            Ok(entry) => entry,
+                            Err(err) => return skipped_from_walk_error(&err).map(Either::Right),
+                        };
+                        let metadata = match stat.time(|| entry.metadata()) {
+                            Ok(metadata) => metadata,
+                            Err(err) => return skipped_from_walk_error(&err).map(Either::Right),
+                        };
+
+                        if metadata.is_file() && metadata.len() >= min_size {
+                            let size = metadata.len();
+                            let path_str = entry.path().to_string_lossy().to_string();
+                            let mut file = FileEntity::new(path_str, size);
+                            file.modified = metadata.modified().ok().map(DateTime::from);
+                            file.root = root_index;
+                            self.protected.apply(&mut file);
+                            let bytes = found_bytes.fetch_add(size, Ordering::Relaxed) + size;
+                            let count = found_files.fetch_add(1, Ordering::Relaxed) + 1;
+                            if count % PROGRESS_INTERVAL == 0 {
+                                self.progress.report(
+                                    &ProgressUpdate::new(count, bytes)
+                                        .with_phase(phase::WALK)
+                                        .with_current_path(file.path.clone()),
+                                );
+                            }
+                            Some(Either::Left(file))
+                        } else {
+                            None
+                        }
                     })
-                } else {
-                    None
+                    .partition_map(|item| item);
+            files.extend(found.into_iter().filter(|f| seen.insert(f.path.clone())));
+            skipped.extend(denied);
+        }
+        self.progress.report(
+            &ProgressUpdate::new(
+                found_files.load(Ordering::Relaxed),
+                found_bytes.load(Ordering::Relaxed),
+            )
+            .with_phase(phase::WALK),
+        );
+        self.group_files(files, skipped, started, &walk, &stat, cancel)
+            .await
+    }
+
+    /// Find duplicates among `files` listed beforehand, such as from the size
+    /// index, instead of walking
+    ///
+    /// Only files sharing their listed size with another are looked at on
+    /// disk. Those are statted again, so one changed since it was listed is
+    /// compared by its current size, and one that is gone is dropped. A file
+    /// that grew into the size of another, unlisted one is missed until the
+    /// list catches up.
+    pub async fn find_duplicates_among(
+        &self,
+        files: Vec<FileEntity>,
+        cancel: &CancellationToken,
+    ) -> Result<DuplicateResult> {
+        let started = Instant::now();
+        let (walk, stat) = (PhaseTimer::new(), PhaseTimer::new());
+        let mut sizes: HashMap<u64, usize> = HashMap::new();
+        for file in &files {
+            *sizes.entry(file.size).or_default() += 1;
+        }
+        let files = files
+            .into_par_iter()
+            .filter_map(|mut file| {
+                if sizes[&file.size] > 1 {
+                    let metadata = stat.time(|| std::fs::metadata(&file.path)).ok()?;
+                    file.size = metadata.len();
+                    file.modified = metadata.modified().ok().map(DateTime::from);
                 }
+                self.protected.apply(&mut file);
+                Some(file)
             })
             .collect();
+        self.group_files(files, Vec::new(), started, &walk, &stat, cancel)
+            .await
+    }
+
+    /// Group `files` found by a walk that started at `started`, timed by
+    /// `walk` and `stat`, into duplicates
+    async fn group_files(
+        &self,
+        files: Vec<FileEntity>,
+        mut skipped: Vec<SkippedPath>,
+        started: Instant,
+        walk: &PhaseTimer,
+        stat: &PhaseTimer,
+        cancel: &CancellationToken,
+    ) -> Result<DuplicateResult> {
+        let hash = PhaseTimer::new();
+        let file_count = files.len() as u64;
+        let bytes: u64 = files.iter().map(|f| f.size).sum();
+        let mut hashed_bytes = 0;
+        let mut cache_hits = 0;
 
-        // Group files by hash
-        let mut hash_groups: HashMap<String, Vec<FileEntity>> = HashMap::new();
+        // Media files are compared by their content whatever their size
+        let (media_duplicates, files) = if self.media {
+            self.media_groups(
+                files,
+                &hash,
+                &mut hashed_bytes,
+                &mut cache_hits,
+                &mut skipped,
+                cancel,
+            )
+            .await
+        } else {
+            (Vec::new(), files)
+        };
 
+        // Only files of the same size can be duplicates
+        let grouping = Instant::now();
+        let mut size_groups: HashMap<u64, Vec<FileEntity>> = HashMap::new();
         for file in files {
-            let hash = self.compute_hash(&file.path)?;
-            hash_groups.entry(hash).or_default().push(file);
+            size_groups.entry(file.size).or_default().push(file);
         }
-
-        // Filter to only groups with duplicates (2+ files)
-        let duplicates: Vec<Vec<FileEntity>> = hash_groups
+        let candidates: Vec<FileEntity> = size_groups
             .into_values()
             .filter(|group| group.len() > 1)
+            .flatten()
             .collect();
+        let mut aggregate_micros = micros_since(grouping);
+        let partial_total = Progress {
+            items: candidates.len() as u64,
+            bytes: candidates
+                .iter()
+                .map(|f| f.size.min(2 * hasher::PARTIAL_HASH_BYTES))
+                .sum(),
+        };
+        let mut done = Progress::default();
 
-        // Calculate potential savings (sum of sizes minus one file per group)
-        let potential_savings: u64 = duplicates
-            .iter()
-            .map(|group| {
-                let total_size: u64 = group.iter().map(|f| f.size).sum();
-                let keep_one = group.first().map(|f| f.size).unwrap_or(0);
-                total_size - keep_one
-            })
-            .sum();
+        // Tell apart candidates of the same size by their head and tail
+        let mut partial_groups: HashMap<(u64, String), Vec<FileEntity>> = HashMap::new();
+
+        for file in candidates {
+            if cancel.is_cancelled() {
+                break;
+            }
+            self.report(phase::PARTIAL_HASH, &done, &partial_total, &file.path);
+            let hashed = self
+                .cached_hash(&file.path, "partial", || {
+                    hash.time(|| {
+                        hasher::hash_head_and_tail(self.algorithm, Path::new(&file.path), file.size)
+                    })
+                })
+                .await;
+            let Some((partial, cached)) = hashed_or_skipped(hashed, &file.path, &mut skipped)
+            else {
+                continue;
+            };
+            if cached {
+                cache_hits += 1;
+            } else {
+                hashed_bytes += file.size.min(2 * hasher::PARTIAL_HASH_BYTES);
+            }
+            done.add(file.size.min(2 * hasher::PARTIAL_HASH_BYTES));
+            let grouping = Instant::now();
+            partial_groups
+                .entry((file.size, partial))
+                .or_default()
+                .push(file);
+            aggregate_micros += micros_since(grouping);
+        }
+
+        // Confirm what the partial hash didn't read in full
+        let mut duplicates = Vec::new();
+        let full_total = if self.strategy == DetectionStrategy::Partial {
+            Progress::default()
+        } else {
+            partial_groups
+                .iter()
+                .filter(|((size, _), group)| group.len() > 1 && !hasher::covers_whole_file(*size))
+                .fold(Progress::default(), |mut total, ((size, _), group)| {
+                    total.items += group.len() as u64;
+                    total.bytes += size * group.len() as u64;
+                    total
+                })
+        };
+        let mut done = Progress::default();
+        for ((size, partial), group) in partial_groups {
+            if group.len() < 2 {
+                continue;
+            }
+            if hasher::covers_whole_file(size) {
+                duplicates.push(DuplicateGroup {
+                    files: group,
+                    hash: partial,
+                    confirmed_by: DetectionStage::FullHash,
+                    verification: None,
+                });
+                continue;
+            }
+            if self.strategy == DetectionStrategy::Partial {
+                duplicates.push(DuplicateGroup {
+                    files: group,
+                    hash: partial,
+                    confirmed_by: DetectionStage::PartialHash,
+                    verification: None,
+                });
+                continue;
+            }
+            let mut full_groups: HashMap<String, Vec<FileEntity>> = HashMap::new();
+            for file in group {
+                if cancel.is_cancelled() {
+                    break;
+                }
+                self.report(phase::FULL_HASH, &done, &full_total, &file.path);
+                let hashed = self
+                    .cached_hash(&file.path, "full", || {
+                        hash.time(|| hasher::hash_file(self.algorithm, Path::new(&file.path)))
+                    })
+                    .await;
+                let Some((full, cached)) = hashed_or_skipped(hashed, &file.path, &mut skipped)
+                else {
+                    continue;
+                };
+                if cached {
+                    cache_hits += 1;
+                } else {
+                    hashed_bytes += file.size;
+                }
+                done.add(file.size);
+                full_groups.entry(full).or_default().push(file);
+            }
+            duplicates.extend(
+                full_groups
+                    .into_iter()
+                    .filter(|(_, group)| group.len() > 1)
+                    .map(|(hash, files)| DuplicateGroup {
+                        files,
+                        hash,
+                        confirmed_by: DetectionStage::FullHash,
+                        verification: None,
+                    }),
+            );
+        }
+        if self.verify {
+            let mut verified = Vec::with_capacity(duplicates.len());
+            let total = Progress {
+                items: duplicates.len() as u64,
+                bytes: duplicates
+                    .iter()
+                    .map(|group| group.files.iter().map(|f| f.size).sum::<u64>())
+                    .sum(),
+            };
+            let mut done = Progress::default();
+            for group in duplicates {
+                if cancel.is_cancelled() {
+                    break;
+                }
+                let group_bytes = group.files.iter().map(|f| f.size).sum();
+                self.report(phase::VERIFY, &done, &total, &group.files[0].path);
+                done.add(group_bytes);
+                verified.extend(hash.time(|| verify::verify(group.files)).into_iter().map(
+                    |(files, verification)| DuplicateGroup {
+                        files,
+                        hash: group.hash.clone(),
+                        confirmed_by: DetectionStage::ByteComparison,
+                        verification: Some(verification),
+                    },
+                ));
+            }
+            duplicates = verified;
+        }
+        duplicates.extend(media_duplicates);
+        let grouping = Instant::now();
+        let potential_savings = Self::calculate_savings(&duplicates);
+        aggregate_micros += micros_since(grouping);
 
         Ok(DuplicateResult {
             duplicates,
             potential_savings,
+            skipped,
+            interrupted: cancel.is_cancelled(),
+            profile: ScanProfile {
+                elapsed_micros: micros_since(started),
+                walk_micros: walk.micros(),
+                stat_micros: stat.micros(),
+                hash_micros: hash.micros(),
+                aggregate_micros,
+                files: file_count,
+                bytes,
+                hashed_bytes,
+            },
+            cache_hits,
         })
     }
 
+    /// Split the media files off `files` and group them by a hash of their
+    /// audio, image or video data, returning the groups and the files still
+    /// to compare whole, among them media files that matched no other
+    async fn media_groups(
+        &self,
+        files: Vec<FileEntity>,
+        hash: &PhaseTimer,
+        hashed_bytes: &mut u64,
+        cache_hits: &mut u64,
+        skipped: &mut Vec<SkippedPath>,
+        cancel: &CancellationToken,
+    ) -> (Vec<DuplicateGroup>, Vec<FileEntity>) {
+        let (parsed, mut rest): (Vec<_>, Vec<_>) = files.into_par_iter().partition_map(|file| {
+            let path = Path::new(&file.path);
+            match MediaKind::from_path(path)
+                .and_then(|kind| Some((kind, hash.time(|| media::payload(kind, path)).ok()?)))
+            {
+                Some((kind, ranges)) => Either::Left((file, kind, ranges)),
+                None => Either::Right(file),
+            }
+        });
+
+        // Only media of the same kind and payload length can match
+        let mut by_length: HashMap<(MediaKind, u64), Vec<(FileEntity, Payload)>> = HashMap::new();
+        for (file, kind, ranges) in parsed {
+            let length = ranges.iter().map(|range| range.end - range.start).sum();
+            by_length
+                .entry((kind, length))
+                .or_default()
+                .push((file, ranges));
+        }
+        let (candidates, unmatched): (Vec<_>, Vec<_>) = by_length
+            .into_iter()
+            .partition(|(_, group)| group.len() > 1);
+        rest.extend(
+            unmatched
+                .into_iter()
+                .flat_map(|(_, group)| group.into_iter().map(|(file, _)| file)),
+        );
+        let total =
+            candidates
+                .iter()
+                .fold(Progress::default(), |mut total, ((_, length), group)| {
+                    total.items += group.len() as u64;
+                    total.bytes += length * group.len() as u64;
+                    total
+                });
+
+        let mut done = Progress::default();
+        let mut groups: HashMap<(MediaKind, String), Vec<FileEntity>> = HashMap::new();
+        'groups: for ((kind, length), group) in candidates {
+            for (file, ranges) in group {
+                if cancel.is_cancelled() {
+                    break 'groups;
+                }
+                self.report(phase::MEDIA_HASH, &done, &total, &file.path);
+                let hashed = self
+                    .cached_hash(&file.path, "media", || {
+                        hash.time(|| {
+                            hasher::hash_ranges(self.algorithm, Path::new(&file.path), &ranges)
+                        })
+                    })
+                    .await;
+                let Some((digest, cached)) = hashed_or_skipped(hashed, &file.path, skipped) else {
+                    continue;
+                };
+                if cached {
+                    *cache_hits += 1;
+                } else {
+                    *hashed_bytes += length;
+                }
+                done.add(length);
+                groups.entry((kind, digest)).or_default().push(file);
+            }
+        }
+        let mut duplicates = Vec::new();
+        for ((_, hash), files) in groups {
+            if files.len() > 1 {
+                duplicates.push(DuplicateGroup {
+                    files,
+                    hash,
+                    confirmed_by: DetectionStage::MediaHash,
+                    verification: None,
+                });
+            } else {
+                rest.extend(files);
+            }
+        }
+        (duplicates, rest)
+    }
+
+    /// Tell the observer `done` of `total` is through `phase`, `path` next
+    fn report(&self, phase: &'static str, done: &Progress, total: &Progress, path: &str) {
+        self.progress.report(
+            &ProgressUpdate::new(done.items, done.bytes)
+                .with_total(total.items)
+                .with_bytes_total(total.bytes)
+                .with_phase(phase)
+                .with_current_path(path),
+        );
+    }
+
     /// Calculate potential space savings from duplicate groups
-    pub fn calculate_savings(duplicates: &[Vec<FileEntity>]) -> u64 {
+    pub fn calculate_savings(duplicates: &[DuplicateGroup]) -> u64 {
         duplicates
             .iter()
             .map(|group| {
-                let total_size: u64 = group.iter().map(|f| f.size).sum();
-                let keep_one = group.first().map(|f| f.size).unwrap_or(0);
+                let total_size: u64 = group.files.iter().map(|f| f.size).sum();
+                let keep_one = group.files.first().map(|f| f.size).unwrap_or(0);
                 total_size - keep_one
             })
             .sum()
     }
 
-    /// Compute hash for a file
-    fn compute_hash(&self, file_path: &str) -> Result<String> {
-        use std::fs::File;
-        use std::io::Read;
-
-        let mut file = File::open(file_path)?;
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer)?;
-
-        let hash = match self.algorithm {
-            HashAlgorithm::Blake3 => {
-                let mut hasher = blake3::Hasher::new();
-                hasher.update(&buffer);
-                hasher.finalize().to_hex().to_string()
-            }
-            HashAlgorithm::XxHash3 => {
-                use xxhash_rust::xxh3::Xxh3;
-                let mut hasher = Xxh3::new();
-                hasher.update(&buffer);
-                format!("{:x}", hasher.digest())
-            }
+    /// The `part` hash of the file at `path`, from the cache if the file is
+    /// unchanged since it was stored, otherwise computed by `hash` and stored
+    ///
+    /// Returns the hash and whether it came from the cache. The cache only
+    /// ever saves work: when it can't be read or written the hash is
+    /// computed as if there were none.
+    async fn cached_hash(
+        &self,
+        path: &str,
+        part: &str,
+        hash: impl FnOnce() -> std::io::Result<String>,
+    ) -> std::io::Result<(String, bool)> {
+        let Some(cache) = &self.cache else {
+            return Ok((hash()?, false));
+        };
+        let Ok(metadata) = std::fs::metadata(path) else {
+            return Ok((hash()?, false));
         };
+        let key = cache_key(&metadata, self.algorithm, part);
+        match cache.get(&key).await {
+            Ok(Some(serde_json::Value::String(cached))) => return Ok((cached, true)),
+            Ok(_) => {}
+            Err(e) => tracing::warn!(error = %e, "Failed to read the hash cache"),
+        }
+        let digest = hash()?;
+        if let Err(e) = cache.set(&key, digest.clone().into()).await {
+            tracing::warn!(error = %e, "Failed to write the hash cache");
+        }
+        Ok((digest, false))
+    }
+}
 
-        Ok(hash)
+/// The hash of a candidate, or `None` if it became unreadable or was removed
+/// since the walk, noting it in `skipped` when it's a permission problem
+fn hashed_or_skipped(
+    hashed: std::io::Result<(String, bool)>,
+    path: &str,
+    skipped: &mut Vec<SkippedPath>,
+) -> Option<(String, bool)> {
+    match hashed {
+        Ok(hashed) => Some(hashed),
+        Err(e) => {
+            tracing::debug!(path, error = %e, "Skipping a file that can't be hashed");
+            skipped.extend(SkippedPath::from_io_error(path, &e));
+            None
+        }
     }
 }
 
@@ -146,9 +714,25 @@ impl Default for DuplicateDetector {
     }
 }
 
+/// Files and bytes through a phase, or due to go through it
+#[derive(Debug, Default)]
+struct Progress {
+    items: u64,
+    bytes: u64,
+}
+
+impl Progress {
+    /// Count one more file of `bytes`
+    fn add(&mut self, bytes: u64) {
+        self.items += 1;
+        self.bytes += bytes;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cache::HashCache;
     use std::fs;
     use std::io::Write;
     use tempfile::TempDir;
@@ -174,10 +758,329 @@ mod tests {
         let result = detector.find_duplicates(&path, 0).await.unwrap();
 
         assert_eq!(result.duplicates.len(), 1);
-        assert_eq!(result.duplicates[0].len(), 2);
-        assert!(result.duplicates[0].iter().any(|f| f.path == file1));
-        assert!(result.duplicates[0].iter().any(|f| f.path == file2));
-        assert!(!result.duplicates[0].iter().any(|f| f.path == file3));
+        assert_eq!(result.duplicates[0].files.len(), 2);
+        assert!(result.duplicates[0].files.iter().any(|f| f.path == file1));
+        assert!(result.duplicates[0].files.iter().any(|f| f.path == file2));
+        assert!(!result.duplicates[0].files.iter().any(|f| f.path == file3));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn should_not_follow_symlinks_back_up_the_tree() {
+        let temp_dir = TempDir::new().unwrap();
+        create_test_file(temp_dir.path(), "a.txt", b"same").unwrap();
+        create_test_file(temp_dir.path(), "b.txt", b"same").unwrap();
+        fs::create_dir(temp_dir.path().join("dir")).unwrap();
+        std::os::unix::fs::symlink(temp_dir.path(), temp_dir.path().join("dir/up")).unwrap();
+
+        let path = FilePath::new(temp_dir.path().to_string_lossy().to_string());
+        let options = ScanOptions::new().with_follow_symlinks(true);
+        let result = DuplicateDetector::new()
+            .find_duplicates_with_options(&path, 0, &options, &CancellationToken::new())
+            .await
+            .unwrap();
+
+        assert_eq!(result.duplicates.len(), 1);
+        assert_eq!(result.duplicates[0].files.len(), 2);
+    }
+
+    /// Removes a file just before it would be hashed
+    struct RemoveBeforeHashing(String);
+
+    impl ProgressReporter for RemoveBeforeHashing {
+        fn report(&self, update: &ProgressUpdate) {
+            if update.phase == Some(phase::PARTIAL_HASH)
+                && update.current_path.as_deref() == Some(self.0.as_str())
+            {
+                let _ = fs::remove_file(&self.0);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn should_skip_files_removed_after_the_walk() {
+        let temp_dir = TempDir::new().unwrap();
+        create_test_file(temp_dir.path(), "a.txt", b"same").unwrap();
+        create_test_file(temp_dir.path(), "b.txt", b"same").unwrap();
+        let gone = create_test_file(temp_dir.path(), "c.txt", b"gone").unwrap();
+
+        let detector = DuplicateDetector::new().with_progress(Arc::new(RemoveBeforeHashing(gone)));
+        let path = FilePath::new(temp_dir.path().to_string_lossy().to_string());
+        let result = detector.find_duplicates(&path, 0).await.unwrap();
+
+        assert_eq!(result.duplicates.len(), 1);
+        assert_eq!(result.duplicates[0].files.len(), 2);
+        assert!(!temp_dir.path().join("c.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn should_confirm_groups_byte_by_byte_when_verifying() {
+        let temp_dir = TempDir::new().unwrap();
+        create_test_file(temp_dir.path(), "a.txt", b"0123456789").unwrap();
+        create_test_file(temp_dir.path(), "b.txt", b"0123456789").unwrap();
+
+        let path = FilePath::new(temp_dir.path().to_string_lossy().to_string());
+        let result = DuplicateDetector::new()
+            .with_verification(true)
+            .find_duplicates(&path, 0)
+            .await
+            .unwrap();
+
+        assert_eq!(result.duplicates.len(), 1);
+        let group = &result.duplicates[0];
+        assert_eq!(group.confirmed_by, DetectionStage::ByteComparison);
+        assert_eq!(
+            group.verification.as_ref().map(|v| v.status),
+            Some(verify::VerificationStatus::Verified)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_report_progress_through_each_phase() {
+        let temp_dir = TempDir::new().unwrap();
+        let content = vec![3u8; 3 * hasher::PARTIAL_HASH_BYTES as usize];
+        create_test_file(temp_dir.path(), "a.bin", &content).unwrap();
+        create_test_file(temp_dir.path(), "b.bin", &content).unwrap();
+
+        let updates = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = Arc::clone(&updates);
+        let path = FilePath::new(temp_dir.path().to_string_lossy().to_string());
+        DuplicateDetector::new()
+            .with_verification(true)
+            .with_progress(Arc::new(move |update: &ProgressUpdate| {
+                seen.lock().unwrap().push(update.clone());
+            }))
+            .find_duplicates(&path, 0)
+            .await
+            .unwrap();
+
+        let updates = updates.lock().unwrap();
+        let phases: Vec<&str> = updates.iter().filter_map(|u| u.phase).collect();
+        assert_eq!(
+            phases,
+            [
+                phase::WALK,
+                phase::PARTIAL_HASH,
+                phase::PARTIAL_HASH,
+                phase::FULL_HASH,
+                phase::FULL_HASH,
+                phase::VERIFY
+            ]
+        );
+        let last_full = &updates[4];
+        assert_eq!(last_full.items_total, Some(2));
+        assert_eq!(last_full.bytes_processed, content.len() as u64);
+        assert_eq!(last_full.bytes_total, Some(2 * content.len() as u64));
+    }
+
+    #[tokio::test]
+    async fn should_group_media_with_different_tags() {
+        let temp_dir = TempDir::new().unwrap();
+        let frames = [0xffu8, 0xfb, 1, 2, 3, 4];
+        let tagged = [
+            b"ID3\x04\x00\x00\x00\x00\x00\x02".as_slice(),
+            b"hi",
+            &frames,
+        ]
+        .concat();
+        create_test_file(temp_dir.path(), "song.mp3", &frames).unwrap();
+        create_test_file(temp_dir.path(), "song (retagged).mp3", &tagged).unwrap();
+        create_test_file(temp_dir.path(), "a.txt", b"same").unwrap();
+        create_test_file(temp_dir.path(), "b.txt", b"same").unwrap();
+        // A song matching no other song is still compared whole
+        create_test_file(temp_dir.path(), "other.mp3", b"other song").unwrap();
+        create_test_file(temp_dir.path(), "other.mp3.bak", b"other song").unwrap();
+
+        let path = FilePath::new(temp_dir.path().to_string_lossy().to_string());
+        let plain = DuplicateDetector::new()
+            .find_duplicates(&path, 0)
+            .await
+            .unwrap();
+        assert_eq!(plain.duplicates.len(), 2);
+
+        let mut result = DuplicateDetector::new()
+            .with_media(true)
+            .with_verification(true)
+            .find_duplicates(&path, 0)
+            .await
+            .unwrap();
+        result
+            .duplicates
+            .sort_by_key(|group| group.confirmed_by == DetectionStage::MediaHash);
+        assert_eq!(result.duplicates.len(), 3);
+        assert_eq!(
+            result.duplicates[1].confirmed_by,
+            DetectionStage::ByteComparison
+        );
+        let media = &result.duplicates[2];
+        assert_eq!(media.confirmed_by, DetectionStage::MediaHash);
+        assert_eq!(media.files.len(), 2);
+        assert!(media.verification.is_none());
+    }
+
+    #[tokio::test]
+    async fn should_find_duplicates_across_roots() {
+        let primary = TempDir::new().unwrap();
+        let backup = TempDir::new().unwrap();
+        create_test_file(primary.path(), "a.txt", b"0123456789").unwrap();
+        create_test_file(backup.path(), "a.txt", b"0123456789").unwrap();
+        create_test_file(backup.path(), "b.txt", b"other").unwrap();
+
+        let roots = [primary.path(), backup.path(), backup.path()]
+            .map(|root| FilePath::new(root.to_string_lossy().to_string()));
+        let result = DuplicateDetector::new()
+            .find_duplicates_in_roots(
+                &roots,
+                0,
+                &ScanOptions::default(),
+                &CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+
+        // The repeated root adds nothing
+        assert_eq!(result.profile.files, 3);
+        assert_eq!(result.duplicates.len(), 1);
+        let mut roots: Vec<_> = result.duplicates[0].files.iter().map(|f| f.root).collect();
+        roots.sort();
+        assert_eq!(roots, [Some(0), Some(1)]);
+    }
+
+    #[tokio::test]
+    async fn should_find_duplicates_among_listed_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let a = create_test_file(temp_dir.path(), "a.txt", b"0123456789").unwrap();
+        let b = create_test_file(temp_dir.path(), "b.txt", b"0123456789").unwrap();
+        let c = create_test_file(temp_dir.path(), "c.txt", b"0123456789").unwrap();
+        // Listed at its old size, now grown
+        fs::write(&c, b"0123456789 and more").unwrap();
+        let gone = temp_dir
+            .path()
+            .join("gone.txt")
+            .to_string_lossy()
+            .to_string();
+
+        let files = [&a, &b, &c, &gone]
+            .map(|path| FileEntity::new(path.to_string(), 10))
+            .to_vec();
+        let result = DuplicateDetector::new()
+            .find_duplicates_among(files, &CancellationToken::new())
+            .await
+            .unwrap();
+
+        assert_eq!(result.profile.files, 3);
+        assert_eq!(result.duplicates.len(), 1);
+        let mut paths: Vec<_> = result.duplicates[0].files.iter().map(|f| &f.path).collect();
+        paths.sort();
+        assert_eq!(paths, [&a, &b]);
+    }
+
+    #[tokio::test]
+    async fn should_profile_every_file_hashed() {
+        let temp_dir = TempDir::new().unwrap();
+        create_test_file(temp_dir.path(), "a.txt", b"0123456789").unwrap();
+        create_test_file(temp_dir.path(), "b.txt", b"0123456789").unwrap();
+
+        let path = FilePath::new(temp_dir.path().to_string_lossy().to_string());
+        let result = DuplicateDetector::new()
+            .find_duplicates(&path, 0)
+            .await
+            .unwrap();
+
+        assert_eq!(result.profile.files, 2);
+        assert_eq!(result.profile.bytes, 20);
+        assert_eq!(result.profile.hashed_bytes, 20);
+    }
+
+    #[tokio::test]
+    async fn should_reuse_cached_hashes_of_unchanged_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_dir = TempDir::new().unwrap();
+        create_test_file(temp_dir.path(), "a.txt", b"0123456789").unwrap();
+        create_test_file(temp_dir.path(), "b.txt", b"0123456789").unwrap();
+
+        let path = FilePath::new(temp_dir.path().to_string_lossy().to_string());
+        let detector = DuplicateDetector::new()
+            .with_cache(Arc::new(HashCache::open(cache_dir.path()).unwrap()));
+        let first = detector.find_duplicates(&path, 0).await.unwrap();
+        assert_eq!(first.cache_hits, 0);
+
+        let second = detector.find_duplicates(&path, 0).await.unwrap();
+        assert_eq!(second.cache_hits, 2);
+        assert_eq!(second.profile.hashed_bytes, 0);
+        assert_eq!(second.duplicates.len(), 1);
+
+        // A changed file is hashed again
+        let changed = create_test_file(temp_dir.path(), "b.txt", b"9876543210").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(changed)
+            .unwrap()
+            .set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(10))
+            .unwrap();
+        let third = detector.find_duplicates(&path, 0).await.unwrap();
+        assert!(third.duplicates.is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_not_hash_files_of_unique_size() {
+        let temp_dir = TempDir::new().unwrap();
+        create_test_file(temp_dir.path(), "a.txt", b"0123456789").unwrap();
+        create_test_file(temp_dir.path(), "b.txt", b"0123456789").unwrap();
+        create_test_file(temp_dir.path(), "c.txt", b"a longer file").unwrap();
+
+        let path = FilePath::new(temp_dir.path().to_string_lossy().to_string());
+        let result = DuplicateDetector::new()
+            .find_duplicates(&path, 0)
+            .await
+            .unwrap();
+
+        assert_eq!(result.profile.files, 3);
+        assert_eq!(result.profile.hashed_bytes, 20);
+        assert_eq!(result.duplicates.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn should_report_the_stage_confirming_each_group() {
+        let temp_dir = TempDir::new().unwrap();
+        let len = 3 * hasher::PARTIAL_HASH_BYTES as usize;
+        let mut content = vec![1u8; len];
+        create_test_file(temp_dir.path(), "big1.bin", &content).unwrap();
+        create_test_file(temp_dir.path(), "big2.bin", &content).unwrap();
+        // Same head and tail, different middle
+        content[len / 2] = 2;
+        create_test_file(temp_dir.path(), "big3.bin", &content).unwrap();
+        create_test_file(temp_dir.path(), "small1.txt", b"small").unwrap();
+        create_test_file(temp_dir.path(), "small2.txt", b"small").unwrap();
+
+        let path = FilePath::new(temp_dir.path().to_string_lossy().to_string());
+        let group_of = |result: &DuplicateResult, size: u64| {
+            let group = result
+                .duplicates
+                .iter()
+                .find(|group| group.files[0].size == size)
+                .unwrap();
+            (group.files.len(), group.confirmed_by)
+        };
+
+        let full = DuplicateDetector::new()
+            .find_duplicates(&path, 0)
+            .await
+            .unwrap();
+        assert_eq!(group_of(&full, len as u64), (2, DetectionStage::FullHash));
+        assert_eq!(group_of(&full, 5), (2, DetectionStage::FullHash));
+
+        let partial = DuplicateDetector::new()
+            .with_strategy(DetectionStrategy::Partial)
+            .find_duplicates(&path, 0)
+            .await
+            .unwrap();
+        assert_eq!(
+            group_of(&partial, len as u64),
+            (3, DetectionStage::PartialHash)
+        );
+        assert_eq!(group_of(&partial, 5), (2, DetectionStage::FullHash));
+        assert!(partial.profile.hashed_bytes < full.profile.hashed_bytes);
     }
 
     #[tokio::test]
@@ -216,7 +1119,7 @@ mod tests {
 
         // Check that we have one group with 2 files and one group with 3 files
         // Order is non-deterministic due to HashMap iteration
-        let group_sizes: Vec<usize> = result.duplicates.iter().map(|g| g.len()).collect();
+        let group_sizes: Vec<usize> = result.duplicates.iter().map(|g| g.files.len()).collect();
         assert!(group_sizes.contains(&2), "Should have a group with 2 files");
         assert!(group_sizes.contains(&3), "Should have a group with 3 files");
     }
@@ -238,7 +1141,7 @@ mod tests {
         // With min_size = 100 bytes, should only find large duplicates
         let result = detector.find_duplicates(&path, 100).await.unwrap();
         assert_eq!(result.duplicates.len(), 1);
-        assert_eq!(result.duplicates[0].len(), 2);
+        assert_eq!(result.duplicates[0].files.len(), 2);
 
         // With min_size = 0, should find both
         let result_all = detector.find_duplicates(&path, 0).await.unwrap();
@@ -277,33 +1180,44 @@ mod tests {
         ));
     }
 
+    #[tokio::test]
+    async fn should_return_partial_result_when_cancelled() {
+        let temp_dir = TempDir::new().unwrap();
+        create_test_file(temp_dir.path(), "dup1.txt", b"same").unwrap();
+        create_test_file(temp_dir.path(), "dup2.txt", b"same").unwrap();
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        let detector = DuplicateDetector::new();
+        let path = FilePath::new(temp_dir.path().to_string_lossy().to_string());
+        let result = detector
+            .find_duplicates_with_options(&path, 0, &ScanOptions::default(), &cancel)
+            .await
+            .unwrap();
+
+        assert!(result.interrupted);
+        assert!(result.duplicates.is_empty());
+    }
+
     #[test]
     fn should_calculate_savings_correctly() {
+        let group = |files| DuplicateGroup {
+            files,
+            hash: String::new(),
+            confirmed_by: DetectionStage::FullHash,
+            verification: None,
+        };
         let duplicates = vec![
-            vec![
-                FileEntity {
-                    path: "file1.txt".to_string(),
-                    size: 1000,
-                },
-                FileEntity {
-                    path: "file2.txt".to_string(),
-                    size: 1000,
-                },
-            ],
-            vec![
-                FileEntity {
-                    path: "file3.txt".to_string(),
-                    size: 500,
-                },
-                FileEntity {
-                    path: "file4.txt".to_string(),
-                    size: 500,
-                },
-                FileEntity {
-                    path: "file5.txt".to_string(),
-                    size: 500,
-                },
-            ],
+            group(vec![
+                FileEntity::new("file1.txt".to_string(), 1000),
+                FileEntity::new("file2.txt".to_string(), 1000),
+            ]),
+            group(vec![
+                FileEntity::new("file3.txt".to_string(), 500),
+                FileEntity::new("file4.txt".to_string(), 500),
+                FileEntity::new("file5.txt".to_string(), 500),
+            ]),
         ];
 
         // Group 1: 2000 total - 1000 keep = 1000 savings
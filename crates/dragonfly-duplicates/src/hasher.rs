@@ -1,6 +1,27 @@
 //! Hash algorithm selection and utilities
+//!
+//! Files are hashed as a stream, a buffer at a time, so a multi-gigabyte
+//! video takes no more memory than a text file. Large files are
+//! memory-mapped instead, which spares copying them through a buffer. A
+//! partial hash of only the head and tail of a file tells most files of the
+//! same size apart after reading a fraction of them.
 
+use memmap2::Mmap;
 use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+use xxhash_rust::xxh3::Xxh3;
+
+/// Files at least this large are memory-mapped rather than read
+const MMAP_THRESHOLD: u64 = 16 * 1024 * 1024;
+
+/// Bytes read at a time from files below [`MMAP_THRESHOLD`]
+const BUFFER_SIZE: usize = 256 * 1024;
+
+/// Bytes a partial hash reads from each end of a file
+pub const PARTIAL_HASH_BYTES: u64 = 64 * 1024;
 
 /// Available hash algorithms
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
@@ -21,12 +42,185 @@ impl std::fmt::Display for HashAlgorithm {
     }
 }
 
+/// A hash being computed
+enum Hasher {
+    Blake3(Box<blake3::Hasher>),
+    XxHash3(Box<Xxh3>),
+}
+
+impl Hasher {
+    fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Blake3 => Self::Blake3(Box::default()),
+            HashAlgorithm::XxHash3 => Self::XxHash3(Box::default()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Blake3(hasher) => {
+                hasher.update(data);
+            }
+            Self::XxHash3(hasher) => hasher.update(data),
+        }
+    }
+
+    /// The hash as lowercase hex
+    fn finish(self) -> String {
+        match self {
+            Self::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+            Self::XxHash3(hasher) => format!("{:x}", hasher.digest()),
+        }
+    }
+}
+
+/// Hash the contents of the file at `path` as lowercase hex
+pub(crate) fn hash_file(algorithm: HashAlgorithm, path: &Path) -> io::Result<String> {
+    hash_file_mapping_from(algorithm, path, MMAP_THRESHOLD)
+}
+
+/// Hash the first and last [`PARTIAL_HASH_BYTES`] of the file at `path`,
+/// which is `len` bytes long
+///
+/// A file no longer than both ends together is hashed whole, giving the same
+/// hash as [`hash_file`].
+pub(crate) fn hash_head_and_tail(
+    algorithm: HashAlgorithm,
+    path: &Path,
+    len: u64,
+) -> io::Result<String> {
+    if covers_whole_file(len) {
+        return hash_file(algorithm, path);
+    }
+    let mut file = File::open(path)?;
+    let mut hasher = Hasher::new(algorithm);
+    let mut buffer = vec![0; PARTIAL_HASH_BYTES as usize];
+    file.read_exact(&mut buffer)?;
+    hasher.update(&buffer);
+    file.seek(SeekFrom::Start(len - PARTIAL_HASH_BYTES))?;
+    file.read_exact(&mut buffer)?;
+    hasher.update(&buffer);
+    Ok(hasher.finish())
+}
+
+/// Hash the bytes of the file at `path` within `ranges`, in order
+pub(crate) fn hash_ranges(
+    algorithm: HashAlgorithm,
+    path: &Path,
+    ranges: &[Range<u64>],
+) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Hasher::new(algorithm);
+    let mut buffer = vec![0; BUFFER_SIZE];
+    for range in ranges {
+        file.seek(SeekFrom::Start(range.start))?;
+        let mut left = range.end - range.start;
+        while left > 0 {
+            let chunk = &mut buffer[..left.min(BUFFER_SIZE as u64) as usize];
+            file.read_exact(chunk)?;
+            hasher.update(chunk);
+            left -= chunk.len() as u64;
+        }
+    }
+    Ok(hasher.finish())
+}
+
+/// Whether a partial hash of a file of `len` bytes reads all of it
+pub(crate) fn covers_whole_file(len: u64) -> bool {
+    len <= 2 * PARTIAL_HASH_BYTES
+}
+
+/// [`hash_file`], memory-mapping files of at least `mmap_threshold` bytes
+fn hash_file_mapping_from(
+    algorithm: HashAlgorithm,
+    path: &Path,
+    mmap_threshold: u64,
+) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Hasher::new(algorithm);
+    if file.metadata()?.len() >= mmap_threshold {
+        // SAFETY: the map is only read, and dropped before returning. A file
+        // another process changes meanwhile hashes wrong, as it would when
+        // read; one truncated meanwhile faults, a risk taken for speed on
+        // files big enough for it to matter.
+        if let Ok(map) = unsafe { Mmap::map(&file) } {
+            hasher.update(&map);
+            return Ok(hasher.finish());
+        }
+    }
+    let mut buffer = vec![0; BUFFER_SIZE];
+    loop {
+        match file.read(&mut buffer) {
+            Ok(0) => return Ok(hasher.finish()),
+            Ok(read) => hasher.update(&buffer[..read]),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tempfile::TempDir;
 
     #[test]
     fn test_default_algorithm() {
         assert_eq!(HashAlgorithm::default(), HashAlgorithm::Blake3);
     }
+
+    #[test]
+    fn test_streamed_and_mapped_hashes_match_whole_content() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("data.bin");
+        // Several buffers and a partial one
+        let content: Vec<u8> = (0..BUFFER_SIZE * 3 + 17).map(|i| i as u8).collect();
+        std::fs::write(&path, &content).unwrap();
+
+        let blake3 = blake3::hash(&content).to_hex().to_string();
+        assert_eq!(hash_file(HashAlgorithm::Blake3, &path).unwrap(), blake3);
+        assert_eq!(
+            hash_file_mapping_from(HashAlgorithm::Blake3, &path, 0).unwrap(),
+            blake3
+        );
+
+        let xxh3 = format!("{:x}", xxhash_rust::xxh3::xxh3_64(&content));
+        let len = content.len() as u64;
+        assert_eq!(hash_file(HashAlgorithm::XxHash3, &path).unwrap(), xxh3);
+        assert_eq!(
+            hash_file_mapping_from(HashAlgorithm::XxHash3, &path, 0).unwrap(),
+            xxh3
+        );
+        assert_ne!(
+            hash_head_and_tail(HashAlgorithm::XxHash3, &path, len).unwrap(),
+            xxh3
+        );
+        assert_eq!(
+            hash_ranges(HashAlgorithm::Blake3, &path, &[0..len / 2, len / 2..len]).unwrap(),
+            blake3
+        );
+    }
+
+    #[test]
+    fn test_partial_hash_ignores_the_middle() {
+        let temp_dir = TempDir::new().unwrap();
+        let len = 3 * PARTIAL_HASH_BYTES;
+        let mut content = vec![7u8; len as usize];
+        std::fs::write(temp_dir.path().join("a.bin"), &content).unwrap();
+        content[len as usize / 2] = 8;
+        std::fs::write(temp_dir.path().join("b.bin"), &content).unwrap();
+
+        let partial = |name: &str| {
+            hash_head_and_tail(HashAlgorithm::Blake3, &temp_dir.path().join(name), len).unwrap()
+        };
+        assert_eq!(partial("a.bin"), partial("b.bin"));
+
+        // Small files are hashed whole
+        std::fs::write(temp_dir.path().join("small.bin"), b"small").unwrap();
+        assert_eq!(
+            hash_head_and_tail(HashAlgorithm::Blake3, &temp_dir.path().join("small.bin"), 5)
+                .unwrap(),
+            blake3::hash(b"small").to_hex().to_string()
+        );
+    }
 }
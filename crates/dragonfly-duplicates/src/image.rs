@@ -0,0 +1,305 @@
+//! Just enough image decoding for perceptual hashing
+//!
+//! A perceptual hash only needs a small grayscale version of an image. BMP
+//! and binary PGM/PPM files are read directly; on macOS every other format
+//! ImageIO understands (JPEG, PNG, HEIC, TIFF, ...) is first converted to a
+//! small BMP by `sips`.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// An 8-bit grayscale image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct GrayImage {
+    pub width: usize,
+    pub height: usize,
+    /// Row-major, top row first
+    pub pixels: Vec<u8>,
+}
+
+impl GrayImage {
+    /// Shrink (or stretch) to `width` x `height`, each pixel the average of
+    /// the pixels it covers
+    pub fn resize(&self, width: usize, height: usize) -> Self {
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let (y0, y1) = span(y, height, self.height);
+            for x in 0..width {
+                let (x0, x1) = span(x, width, self.width);
+                let mut sum = 0u64;
+                for row in y0..y1 {
+                    let row = &self.pixels[row * self.width..][x0..x1];
+                    sum += row.iter().map(|&p| u64::from(p)).sum::<u64>();
+                }
+                let count = ((y1 - y0) * (x1 - x0)) as u64;
+                pixels.push((sum / count) as u8);
+            }
+        }
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+/// Source pixels covered by target pixel `i` of `target` when scaling from
+/// `source`, at least one
+fn span(i: usize, target: usize, source: usize) -> (usize, usize) {
+    let start = i * source / target;
+    let end = ((i + 1) * source / target).max(start + 1).min(source);
+    (start.min(source - 1), end)
+}
+
+/// Read the image at `path` as grayscale
+pub(crate) fn load(path: &Path) -> io::Result<GrayImage> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "bmp" => decode_bmp(&fs::read(path)?),
+        "pgm" | "ppm" | "pnm" => decode_pnm(&fs::read(path)?),
+        _ => convert(path),
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Luma of an RGB color
+fn luma(r: u8, g: u8, b: u8) -> u8 {
+    ((299 * u32::from(r) + 587 * u32::from(g) + 114 * u32::from(b)) / 1000) as u8
+}
+
+fn u16_at(data: &[u8], offset: usize) -> io::Result<u16> {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| invalid("truncated BMP header"))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> io::Result<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| invalid("truncated BMP header"))
+}
+
+/// Decode an uncompressed 24- or 32-bit BMP
+fn decode_bmp(data: &[u8]) -> io::Result<GrayImage> {
+    if !data.starts_with(b"BM") {
+        return Err(invalid("not a BMP file"));
+    }
+    let offset = u32_at(data, 10)? as usize;
+    let width = u32_at(data, 18)? as i32;
+    let height = u32_at(data, 22)? as i32;
+    let bits = u16_at(data, 28)?;
+    let compression = u32_at(data, 30)?;
+    if width <= 0 || height == 0 {
+        return Err(invalid("empty BMP image"));
+    }
+    // BI_RGB stores BGR(A); BI_BITFIELDS gives the channel masks after the
+    // basic header
+    let masks = match (bits, compression) {
+        (24 | 32, 0) => [0x00ff_0000, 0x0000_ff00, 0x0000_00ff],
+        (32, 3) => [u32_at(data, 54)?, u32_at(data, 58)?, u32_at(data, 62)?],
+        _ => return Err(invalid("unsupported BMP format")),
+    };
+    let (width, rows) = (width as usize, height.unsigned_abs() as usize);
+    let bytes_per_pixel = usize::from(bits / 8);
+    let stride = (width * bytes_per_pixel).div_ceil(4) * 4;
+    let mut pixels = Vec::with_capacity(width * rows);
+    for y in 0..rows {
+        // Rows are stored bottom-up unless the height is negative
+        let row = if height > 0 { rows - 1 - y } else { y };
+        let start = offset + row * stride;
+        let row = data
+            .get(start..start + width * bytes_per_pixel)
+            .ok_or_else(|| invalid("truncated BMP pixel data"))?;
+        for pixel in row.chunks_exact(bytes_per_pixel) {
+            let mut value = [0u8; 4];
+            value[..bytes_per_pixel].copy_from_slice(pixel);
+            let value = u32::from_le_bytes(value);
+            let [r, g, b] = masks.map(|mask| channel(value, mask));
+            pixels.push(luma(r, g, b));
+        }
+    }
+    Ok(GrayImage {
+        width,
+        height: rows,
+        pixels,
+    })
+}
+
+/// The channel of `value` selected by `mask`, scaled to 8 bits
+fn channel(value: u32, mask: u32) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+    let max = mask >> mask.trailing_zeros();
+    let raw = (value & mask) >> mask.trailing_zeros();
+    (u64::from(raw) * 255 / u64::from(max)) as u8
+}
+
+/// Decode a binary PGM (P5) or PPM (P6) image with 8-bit samples
+fn decode_pnm(data: &[u8]) -> io::Result<GrayImage> {
+    let mut fields = Vec::new();
+    let mut pos = 0;
+    while fields.len() < 4 {
+        match data.get(pos) {
+            Some(b'#') => {
+                while data.get(pos).is_some_and(|&c| c != b'\n') {
+                    pos += 1;
+                }
+            }
+            Some(c) if c.is_ascii_whitespace() => pos += 1,
+            Some(_) => {
+                let start = pos;
+                while data.get(pos).is_some_and(|c| !c.is_ascii_whitespace()) {
+                    pos += 1;
+                }
+                fields.push(String::from_utf8_lossy(&data[start..pos]).into_owned());
+            }
+            None => return Err(invalid("truncated PNM header")),
+        }
+    }
+    // A single whitespace character separates the header from the samples
+    pos += 1;
+    let number = |field: &str| {
+        field
+            .parse::<usize>()
+            .map_err(|_| invalid("malformed PNM header"))
+    };
+    let (width, height, max) = (
+        number(&fields[1])?,
+        number(&fields[2])?,
+        number(&fields[3])?,
+    );
+    let channels = match fields[0].as_str() {
+        "P5" => 1,
+        "P6" => 3,
+        _ => return Err(invalid("unsupported PNM format")),
+    };
+    if max == 0 || max > 255 || width == 0 || height == 0 {
+        return Err(invalid("unsupported PNM format"));
+    }
+    let samples = data
+        .get(pos..pos + width * height * channels)
+        .ok_or_else(|| invalid("truncated PNM pixel data"))?;
+    let scale = |sample: u8| (usize::from(sample).min(max) * 255 / max) as u8;
+    let pixels = samples
+        .chunks_exact(channels)
+        .map(|pixel| match pixel {
+            [gray] => scale(*gray),
+            [r, g, b] => luma(scale(*r), scale(*g), scale(*b)),
+            _ => unreachable!("chunks have one or three samples"),
+        })
+        .collect();
+    Ok(GrayImage {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// Have `sips` convert the image to a small BMP and decode that
+#[cfg(target_os = "macos")]
+fn convert(path: &Path) -> io::Result<GrayImage> {
+    use std::process::{Command, Stdio};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let out = std::env::temp_dir().join(format!(
+        "dragonfly-similarity-{}-{}.bmp",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    // Hashing needs a handful of pixels; -Z keeps the conversion cheap
+    let status = Command::new("sips")
+        .args(["-s", "format", "bmp", "-Z", "64"])
+        .arg(path)
+        .arg("--out")
+        .arg(&out)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    let image = if status.success() {
+        fs::read(&out).and_then(|data| decode_bmp(&data))
+    } else {
+        Err(invalid("sips could not convert the image"))
+    };
+    let _ = fs::remove_file(&out);
+    image
+}
+
+/// Without ImageIO only the formats decoded here can be read
+#[cfg(not(target_os = "macos"))]
+fn convert(_path: &Path) -> io::Result<GrayImage> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "only BMP, PGM, and PPM images can be read outside macOS",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 24-bit bottom-up BMP of the given RGB rows, top row first
+    fn bmp(width: usize, rows: &[Vec<[u8; 3]>]) -> Vec<u8> {
+        let stride = (width * 3).div_ceil(4) * 4;
+        let size = 54 + stride * rows.len();
+        let mut data = vec![0u8; 54];
+        data[..2].copy_from_slice(b"BM");
+        data[2..6].copy_from_slice(&(size as u32).to_le_bytes());
+        data[10..14].copy_from_slice(&54u32.to_le_bytes());
+        data[14..18].copy_from_slice(&40u32.to_le_bytes());
+        data[18..22].copy_from_slice(&(width as i32).to_le_bytes());
+        data[22..26].copy_from_slice(&(rows.len() as i32).to_le_bytes());
+        data[26..28].copy_from_slice(&1u16.to_le_bytes());
+        data[28..30].copy_from_slice(&24u16.to_le_bytes());
+        for row in rows.iter().rev() {
+            let mut bytes: Vec<u8> = row.iter().flat_map(|&[r, g, b]| [b, g, r]).collect();
+            bytes.resize(stride, 0);
+            data.extend(bytes);
+        }
+        data
+    }
+
+    #[test]
+    fn test_decode_bmp() {
+        let white = [255, 255, 255];
+        let black = [0, 0, 0];
+        let data = bmp(3, &[vec![white, black, white], vec![black, black, white]]);
+        let image = decode_bmp(&data).unwrap();
+        assert_eq!((image.width, image.height), (3, 2));
+        assert_eq!(image.pixels, [255, 0, 255, 0, 0, 255]);
+        assert!(decode_bmp(b"BM").is_err());
+    }
+
+    #[test]
+    fn test_decode_pnm() {
+        let image = decode_pnm(b"P5\n# comment\n2 2\n255\n\x00\x40\x80\xff").unwrap();
+        assert_eq!(image.pixels, [0, 64, 128, 255]);
+        let image = decode_pnm(b"P6 1 1 15\n\x0f\x0f\x0f").unwrap();
+        assert_eq!(image.pixels, [255]);
+        assert!(decode_pnm(b"P5 2 2 255\n\x00").is_err());
+    }
+
+    #[test]
+    fn test_resize_averages() {
+        let image = GrayImage {
+            width: 4,
+            height: 2,
+            pixels: vec![0, 100, 200, 200, 0, 100, 0, 0],
+        };
+        let small = image.resize(2, 1);
+        assert_eq!(small.pixels, [50, 100]);
+        // Stretching repeats pixels
+        assert_eq!(
+            small.resize(4, 2).pixels,
+            [50, 50, 100, 100, 50, 50, 100, 100]
+        );
+    }
+}
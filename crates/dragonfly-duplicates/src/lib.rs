@@ -9,11 +9,30 @@
     missing_copy_implementations
 )]
 
+pub mod cache;
+pub mod dedupe;
 pub mod detector;
 pub mod hasher;
+mod image;
+pub mod media;
+pub mod protect;
+pub mod similarity;
+pub mod stats;
+pub mod strategy;
+pub mod verify;
 
-pub use detector::DuplicateDetector;
-pub use hasher::HashAlgorithm;
+pub use cache::HashCache;
+pub use dedupe::{
+    DedupeAction, DedupeMode, DedupePlan, DedupeReport, DedupeSkip, Deduplicator, SkipReason,
+};
+pub use detector::{phase, DuplicateDetector, DuplicateGroup, DuplicateResult};
+pub use hasher::{HashAlgorithm, PARTIAL_HASH_BYTES};
+pub use media::MediaKind;
+pub use protect::ProtectedPaths;
+pub use similarity::{SimilarGroup, SimilarImage, SimilarityDetector, SimilarityResult};
+pub use stats::{DuplicateStats, GroupSummary, TypeUsage};
+pub use strategy::{DetectionStage, DetectionStrategy, KeepStrategy};
+pub use verify::{Verification, VerificationStatus};
 
 /// Module version
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
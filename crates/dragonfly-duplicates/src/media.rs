@@ -0,0 +1,305 @@
+//! The content of media files, without their tags
+//!
+//! Music players and photo apps write metadata into the file itself: ID3
+//! tags around an MP3's frames, blocks of Vorbis comments and cover art ahead
+//! of FLAC audio, EXIF and XMP segments in a JPEG, boxes beside an MP4's
+//! media data. Retagging a song or fixing a photo's date changes the file's
+//! bytes and size but not what it plays or shows. This module finds the byte
+//! ranges holding the audio, image or video data, so they can be hashed on
+//! their own and copies told apart only by their tags grouped together.
+
+// Most formats keep their content in one range
+#![allow(clippy::single_range_in_vec_init)]
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+
+/// Byte ranges of a file holding its content, in order
+pub type Payload = Vec<Range<u64>>;
+
+/// Media formats whose tags can be told apart from their content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MediaKind {
+    /// MPEG audio with ID3 tags
+    Mp3,
+    /// FLAC audio
+    Flac,
+    /// JPEG image
+    Jpeg,
+    /// MP4 and QuickTime audio or video
+    Mp4,
+}
+
+impl MediaKind {
+    /// The format a file's extension names, if it's one of these
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "mp3" => Some(Self::Mp3),
+            "flac" => Some(Self::Flac),
+            "jpg" | "jpeg" => Some(Self::Jpeg),
+            "mp4" | "m4a" | "m4v" | "mov" => Some(Self::Mp4),
+            _ => None,
+        }
+    }
+}
+
+/// Byte ranges of the file at `path` holding its audio, image or video data
+///
+/// # Errors
+///
+/// Fails with [`io::ErrorKind::InvalidData`] if the file isn't laid out as a
+/// `kind` file should be, and with any error reading it.
+pub fn payload(kind: MediaKind, path: &Path) -> io::Result<Payload> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    match kind {
+        MediaKind::Mp3 => mp3(&mut file, len),
+        MediaKind::Flac => flac(&mut file, len),
+        MediaKind::Jpeg => jpeg(&mut file, len),
+        MediaKind::Mp4 => mp4(&mut file, len),
+    }
+}
+
+/// The frames between any ID3v2 tags at the start and ID3v1 tags at the end
+fn mp3(file: &mut File, len: u64) -> io::Result<Payload> {
+    let mut start = 0;
+    let mut header = [0; 10];
+    while start + 10 <= len {
+        read_at(file, start, &mut header)?;
+        if &header[..3] != b"ID3" {
+            break;
+        }
+        // The size is stored 7 bits to a byte and leaves out the header
+        let size = header[6..]
+            .iter()
+            .fold(0, |size, byte| size << 7 | u64::from(byte & 0x7f));
+        let footer = if header[5] & 0x10 == 0 { 0 } else { 10 };
+        start += 10 + size + footer;
+    }
+
+    let mut end = len;
+    let mut tag = [0; 4];
+    if end >= start + 128 {
+        read_at(file, end - 128, &mut tag[..3])?;
+        if &tag[..3] == b"TAG" {
+            end -= 128;
+            if end >= start + 227 {
+                read_at(file, end - 227, &mut tag)?;
+                if &tag == b"TAG+" {
+                    end -= 227;
+                }
+            }
+        }
+    }
+    if start >= end {
+        return Err(invalid("MP3 holds nothing but tags"));
+    }
+    Ok(vec![start..end])
+}
+
+/// The audio frames after the metadata blocks
+fn flac(file: &mut File, len: u64) -> io::Result<Payload> {
+    let mut magic = [0; 4];
+    read_at(file, 0, &mut magic)?;
+    if &magic != b"fLaC" {
+        return Err(invalid("not a FLAC stream"));
+    }
+    let mut start = 4;
+    let mut header = [0; 4];
+    loop {
+        read_at(file, start, &mut header)?;
+        let length = u64::from(u32::from_be_bytes([0, header[1], header[2], header[3]]));
+        start += 4 + length;
+        if header[0] & 0x80 != 0 {
+            break;
+        }
+        if start >= len {
+            return Err(invalid("FLAC metadata runs past the end"));
+        }
+    }
+    Ok(vec![start..len])
+}
+
+/// Every segment but the APPn (EXIF, XMP, ICC profile...) and comment ones,
+/// and the compressed image data after the start of scan
+fn jpeg(file: &mut File, len: u64) -> io::Result<Payload> {
+    let mut marker = [0; 4];
+    read_at(file, 0, &mut marker[..2])?;
+    if marker[..2] != [0xff, 0xd8] {
+        return Err(invalid("not a JPEG image"));
+    }
+    let mut ranges = vec![0..2];
+    let mut start = 2;
+    loop {
+        read_at(file, start, &mut marker)?;
+        if marker[0] != 0xff {
+            return Err(invalid("JPEG segment doesn't start with a marker"));
+        }
+        if marker[1] == 0xda {
+            ranges.push(start..len);
+            return Ok(ranges);
+        }
+        let end = start + 2 + u64::from(u16::from_be_bytes([marker[2], marker[3]]));
+        if !(0xe0..=0xef).contains(&marker[1]) && marker[1] != 0xfe {
+            ranges.push(start..end);
+        }
+        start = end;
+        if start >= len {
+            return Err(invalid("JPEG has no image data"));
+        }
+    }
+}
+
+/// The contents of the `mdat` boxes, leaving out the `moov` box's
+/// descriptions and tags
+fn mp4(file: &mut File, len: u64) -> io::Result<Payload> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    let mut header = [0; 8];
+    while start + 8 <= len {
+        read_at(file, start, &mut header)?;
+        let (size, header_len) =
+            match u32::from_be_bytes([header[0], header[1], header[2], header[3]]) {
+                0 => (len - start, 8),
+                1 => {
+                    let mut large = [0; 8];
+                    read_at(file, start + 8, &mut large)?;
+                    (u64::from_be_bytes(large), 16)
+                }
+                size => (u64::from(size), 8),
+            };
+        if size < header_len || start + size > len {
+            return Err(invalid("MP4 box runs past the end"));
+        }
+        if &header[4..] == b"mdat" {
+            ranges.push(start + header_len..start + size);
+        }
+        start += size;
+    }
+    if ranges.is_empty() {
+        return Err(invalid("MP4 has no media data"));
+    }
+    Ok(ranges)
+}
+
+/// Fill `buf` from `file` starting at `offset`
+fn read_at(file: &mut File, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(buf)
+}
+
+fn invalid(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// The bytes of `ranges` of the file at `path`
+    fn content(path: &Path, ranges: &[Range<u64>]) -> Vec<u8> {
+        let bytes = std::fs::read(path).unwrap();
+        ranges
+            .iter()
+            .flat_map(|range| bytes[range.start as usize..range.end as usize].to_vec())
+            .collect()
+    }
+
+    fn write(dir: &Path, name: &str, parts: &[&[u8]]) -> std::path::PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, parts.concat()).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_kind_from_extension() {
+        assert_eq!(
+            MediaKind::from_path(Path::new("a/Song.MP3")),
+            Some(MediaKind::Mp3)
+        );
+        assert_eq!(
+            MediaKind::from_path(Path::new("clip.mov")),
+            Some(MediaKind::Mp4)
+        );
+        assert_eq!(MediaKind::from_path(Path::new("notes.txt")), None);
+    }
+
+    #[test]
+    fn test_mp3_leaves_out_id3_tags() {
+        let dir = TempDir::new().unwrap();
+        let frames: &[u8] = &[0xff, 0xfb, 1, 2, 3, 4];
+        let tagged = write(
+            dir.path(),
+            "tagged.mp3",
+            &[
+                b"ID3\x04\x00\x00\x00\x00\x00\x03",
+                b"abc",
+                frames,
+                b"TAG",
+                &[0; 125],
+            ],
+        );
+        let bare = write(dir.path(), "bare.mp3", &[frames]);
+
+        let ranges = payload(MediaKind::Mp3, &tagged).unwrap();
+        assert_eq!(content(&tagged, &ranges), frames);
+        assert_eq!(payload(MediaKind::Mp3, &bare).unwrap(), vec![0..6]);
+    }
+
+    #[test]
+    fn test_flac_leaves_out_metadata_blocks() {
+        let dir = TempDir::new().unwrap();
+        let path = write(
+            dir.path(),
+            "a.flac",
+            &[
+                b"fLaC",
+                &[0x00, 0, 0, 2],
+                b"si",
+                &[0x84, 0, 0, 3],
+                b"tag",
+                b"audio",
+            ],
+        );
+        let ranges = payload(MediaKind::Flac, &path).unwrap();
+        assert_eq!(content(&path, &ranges), b"audio");
+    }
+
+    #[test]
+    fn test_jpeg_leaves_out_app_segments() {
+        let dir = TempDir::new().unwrap();
+        let quant: &[u8] = &[0xff, 0xdb, 0, 3, 9];
+        let scan: &[u8] = &[0xff, 0xda, 0, 2, 7, 7, 0xff, 0xd9];
+        let edited = write(
+            dir.path(),
+            "edited.jpg",
+            &[&[0xff, 0xd8], &[0xff, 0xe1, 0, 6], b"Exif", quant, scan],
+        );
+        let original = write(dir.path(), "original.jpg", &[&[0xff, 0xd8], quant, scan]);
+
+        let edited = content(&edited, &payload(MediaKind::Jpeg, &edited).unwrap());
+        let original = content(&original, &payload(MediaKind::Jpeg, &original).unwrap());
+        assert_eq!(edited, original);
+        assert!(payload(MediaKind::Jpeg, &write(dir.path(), "x.jpg", &[b"GIF89a"])).is_err());
+    }
+
+    #[test]
+    fn test_mp4_keeps_only_media_data() {
+        let dir = TempDir::new().unwrap();
+        let path = write(
+            dir.path(),
+            "a.m4a",
+            &[
+                b"\0\0\0\x0cftypM4A ",
+                b"\0\0\0\x0dmdatvideo",
+                b"\0\0\0\x0bmoovtag",
+            ],
+        );
+        let ranges = payload(MediaKind::Mp4, &path).unwrap();
+        assert_eq!(content(&path, &ranges), b"video");
+    }
+}
@@ -0,0 +1,149 @@
+//! Locations duplicate scans never propose deleting from
+//!
+//! Some folders hold copies on purpose or belong to an app that tracks its
+//! files in a database: a Photos library, Time Machine backups, Mail's
+//! store. Removing a "duplicate" there breaks the library or the backup, so
+//! files matching [`DEFAULT_PROTECTED`] are marked
+//! [`PathProtection::Preserved`] and always kept. The list can be replaced in
+//! `~/.dragonfly/config`:
+//!
+//! ```text
+//! duplicates_protect = ["*.photoslibrary", "~/Music/Music"]
+//! ```
+//!
+//! Patterns follow [`ScanOptions::is_excluded`](dragonfly_core::domain::scan_options::ScanOptions::is_excluded):
+//! one without a `/` matches any path component, one with a `/` a whole
+//! path and everything beneath it. An empty list turns the protection off.
+
+use dragonfly_core::domain::config::{is_set, quoted_list};
+use dragonfly_core::domain::protection::PathProtection;
+use dragonfly_core::domain::scan_options::matches_pattern;
+use dragonfly_core::domain::FileEntity;
+use dragonfly_core::error::Result;
+
+/// Config key replacing the protected locations of duplicate scans
+pub const PROTECT_KEY: &str = "duplicates_protect";
+
+/// Locations protected unless the config says otherwise
+pub const DEFAULT_PROTECTED: &[&str] = &[
+    "*.photoslibrary",
+    "Backups.backupdb",
+    "*.backupbundle",
+    "~/Library/Mail",
+];
+
+/// Path patterns whose files duplicate scans always keep
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtectedPaths {
+    patterns: Vec<String>,
+}
+
+impl ProtectedPaths {
+    /// Protect `patterns`, with a leading `~` standing for `home`
+    pub fn new<I, S>(patterns: I, home: &str) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let patterns = patterns
+            .into_iter()
+            .map(Into::into)
+            .map(|pattern: String| match pattern.strip_prefix('~') {
+                Some(rest) if rest.is_empty() || rest.starts_with('/') => format!("{home}{rest}"),
+                _ => pattern,
+            })
+            .collect();
+        Self { patterns }
+    }
+
+    /// The [`DEFAULT_PROTECTED`] locations
+    pub fn builtin(home: &str) -> Self {
+        Self::new(DEFAULT_PROTECTED.iter().copied(), home)
+    }
+
+    /// Read `duplicates_protect` from config file text, the built-in list if
+    /// it isn't set
+    ///
+    /// # Errors
+    ///
+    /// Returns [`dragonfly_core::Error::InvalidInput`] for a list that isn't
+    /// closed or entries that aren't quoted.
+    pub fn from_config(source: &str, home: &str) -> Result<Self> {
+        if !is_set(source, PROTECT_KEY) {
+            return Ok(Self::builtin(home));
+        }
+        Ok(Self::new(quoted_list(source, PROTECT_KEY)?, home))
+    }
+
+    /// The patterns, with `~` expanded
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// Whether `path` lies in a protected location
+    pub fn is_protected(&self, path: &str) -> bool {
+        self.patterns
+            .iter()
+            .any(|pattern| matches_pattern(pattern, path))
+    }
+
+    /// Mark `file` [`PathProtection::Preserved`] if it lies in a protected
+    /// location and isn't protected already
+    pub fn apply(&self, file: &mut FileEntity) {
+        if !file.protection.is_protected() && self.is_protected(&file.path) {
+            file.protection = PathProtection::Preserved;
+        }
+    }
+}
+
+impl Default for ProtectedPaths {
+    /// The built-in locations under the current user's home
+    fn default() -> Self {
+        let home = dirs::home_dir().unwrap_or_default();
+        Self::builtin(&home.to_string_lossy())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_locations() {
+        let protected = ProtectedPaths::builtin("/Users/me");
+        assert!(protected.is_protected(
+            "/Users/me/Pictures/Photos Library.photoslibrary/originals/A/IMG_1.heic"
+        ));
+        assert!(protected.is_protected("/Volumes/TM/Backups.backupdb/Mac/Latest/a.txt"));
+        assert!(protected.is_protected("/Users/me/Library/Mail/V10/a.emlx"));
+        assert!(!protected.is_protected("/Users/me/Pictures/IMG_1.heic"));
+        assert!(!protected.is_protected("/Users/other/Library/Mail/a.emlx"));
+    }
+
+    #[test]
+    fn test_from_config() {
+        let protected =
+            ProtectedPaths::from_config("duplicates_protect = [\"~/Music\"]", "/Users/me").unwrap();
+        assert_eq!(protected.patterns(), ["/Users/me/Music"]);
+        assert!(!protected.is_protected("/Users/me/Pictures/Photos.photoslibrary/a.jpg"));
+
+        let off = ProtectedPaths::from_config("duplicates_protect = []", "/Users/me").unwrap();
+        assert!(off.patterns().is_empty());
+        assert_eq!(
+            ProtectedPaths::from_config("locale = de", "/Users/me").unwrap(),
+            ProtectedPaths::builtin("/Users/me")
+        );
+        assert!(ProtectedPaths::from_config("duplicates_protect = [~/Music]", "/me").is_err());
+    }
+
+    #[test]
+    fn test_apply_keeps_stronger_protection() {
+        let protected = ProtectedPaths::new(["/System", "/data"], "/Users/me");
+        let mut system = FileEntity::new("/System/Library/a".to_string(), 1);
+        protected.apply(&mut system);
+        assert_eq!(system.protection, PathProtection::Sip);
+        let mut data = FileEntity::new("/data/a".to_string(), 1);
+        protected.apply(&mut data);
+        assert_eq!(data.protection, PathProtection::Preserved);
+    }
+}
@@ -0,0 +1,305 @@
+//! Finding visually similar images
+//!
+//! Byte hashes only match identical files. A photo saved again at another
+//! size or quality, or a screenshot of it, has entirely different bytes but
+//! looks the same. A difference hash (dHash) captures how an image looks: it
+//! is shrunk to 9x8 gray pixels, and each of the 64 bits records whether a
+//! pixel is brighter than its right neighbor. Similar images differ in few
+//! bits, so images whose hashes are within a Hamming distance threshold are
+//! grouped.
+
+use crate::image::{self, GrayImage};
+use dragonfly_core::domain::protection::SkippedPath;
+use dragonfly_core::domain::scan_options::ScanOptions;
+use dragonfly_core::error::{Error, Result};
+use dragonfly_core::ports::CancellationToken;
+use dragonfly_disk::walk::{skipped_from_walk_error, walker};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Default largest Hamming distance, out of 64 bits, between similar images
+pub const DEFAULT_THRESHOLD: u32 = 10;
+
+/// Extensions of the files treated as images
+pub const IMAGE_EXTENSIONS: &[&str] = &[
+    "jpg", "jpeg", "png", "heic", "heif", "tif", "tiff", "bmp", "gif", "webp", "pgm", "ppm",
+];
+
+/// Difference hash of `image`
+pub(crate) fn dhash(image: &GrayImage) -> u64 {
+    let small = image.resize(9, 8);
+    let mut hash = 0u64;
+    for row in small.pixels.chunks_exact(9) {
+        for pair in row.windows(2) {
+            hash = (hash << 1) | u64::from(pair[0] > pair[1]);
+        }
+    }
+    hash
+}
+
+/// Number of bits in which two hashes differ
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// An image in a group of similar ones
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SimilarImage {
+    /// Path of the image
+    pub path: String,
+    /// File size in bytes
+    pub size: u64,
+    /// Perceptual hash as 16 hex digits
+    pub hash: String,
+    /// Hamming distance to the first image of the group
+    pub distance: u32,
+}
+
+/// Images that look alike
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SimilarGroup {
+    /// The images, the largest file first and the others by distance to it
+    pub images: Vec<SimilarImage>,
+}
+
+/// Result of a similarity search
+#[derive(Debug, Clone, Default)]
+pub struct SimilarityResult {
+    /// Groups of similar images, largest group first
+    pub groups: Vec<SimilarGroup>,
+    /// Number of images hashed
+    pub images: u64,
+    /// Images that couldn't be decoded
+    pub undecodable: Vec<String>,
+    /// Paths that couldn't be read; unlike `undecodable`, these were never
+    /// opened as images
+    pub skipped: Vec<SkippedPath>,
+    /// Whether the search was cancelled before completing
+    pub interrupted: bool,
+}
+
+/// Finds images that look alike, whatever their bytes
+#[derive(Debug, Clone, Copy)]
+pub struct SimilarityDetector {
+    /// Largest Hamming distance between images considered similar
+    threshold: u32,
+}
+
+impl SimilarityDetector {
+    /// Create a detector with the [`DEFAULT_THRESHOLD`]
+    pub fn new() -> Self {
+        Self {
+            threshold: DEFAULT_THRESHOLD,
+        }
+    }
+
+    /// Set the largest Hamming distance (0-64) between similar images; lower
+    /// is stricter
+    pub fn with_threshold(mut self, threshold: u32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Find groups of similar images of at least `min_size` bytes under `path`
+    ///
+    /// Stops early with partial results if `cancel` fires.
+    pub fn find_similar(
+        &self,
+        path: &Path,
+        min_size: u64,
+        options: &ScanOptions,
+        cancel: &CancellationToken,
+    ) -> Result<SimilarityResult> {
+        if !path.exists() {
+            return Err(Error::NotFound(format!(
+                "Path does not exist: {}",
+                path.display()
+            )));
+        }
+
+        let mut result = SimilarityResult::default();
+        let mut candidates = Vec::new();
+        for entry in walker(path, options) {
+            if cancel.is_cancelled() {
+                break;
+            }
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    result.skipped.extend(skipped_from_walk_error(&err));
+                    continue;
+                }
+            };
+            let is_image = entry.path().extension().is_some_and(|e| {
+                let e = e.to_string_lossy().to_lowercase();
+                IMAGE_EXTENSIONS.contains(&e.as_str())
+            });
+            if !entry.file_type().is_file() || !is_image {
+                continue;
+            }
+            match entry.metadata() {
+                Ok(metadata) if metadata.len() >= min_size => {
+                    candidates.push((entry.path().to_string_lossy().into_owned(), metadata.len()));
+                }
+                Ok(_) => {}
+                Err(err) => result.skipped.extend(skipped_from_walk_error(&err)),
+            }
+        }
+
+        let hashed: Vec<_> = candidates
+            .into_par_iter()
+            .filter(|_| !cancel.is_cancelled())
+            .map(|(path, size)| {
+                let hash = image::load(Path::new(&path)).map(|image| dhash(&image));
+                (path, size, hash)
+            })
+            .collect();
+        let mut images = Vec::with_capacity(hashed.len());
+        for (path, size, hash) in hashed {
+            match hash {
+                Ok(hash) => images.push((path, size, hash)),
+                Err(_) => result.undecodable.push(path),
+            }
+        }
+        result.images = images.len() as u64;
+        result.groups = self.group(&images);
+        result.interrupted = cancel.is_cancelled();
+        Ok(result)
+    }
+
+    /// Group `(path, size, hash)` images within the threshold of each other
+    ///
+    /// Similarity is chained: an image joins a group if it is close to any
+    /// image in it. Every pair is compared, which is fast for the tens of
+    /// thousands of photos of a typical library.
+    fn group(&self, images: &[(String, u64, u64)]) -> Vec<SimilarGroup> {
+        let mut parent: Vec<usize> = (0..images.len()).collect();
+        fn root(parent: &mut [usize], mut i: usize) -> usize {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        }
+        for i in 0..images.len() {
+            for j in i + 1..images.len() {
+                if hamming_distance(images[i].2, images[j].2) <= self.threshold {
+                    let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                    parent[a.max(b)] = a.min(b);
+                }
+            }
+        }
+
+        let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+        for i in 0..images.len() {
+            let root = root(&mut parent, i);
+            members.entry(root).or_default().push(i);
+        }
+        let mut groups: Vec<SimilarGroup> = members
+            .into_values()
+            .filter(|members| members.len() > 1)
+            .map(|mut members| {
+                // The largest file is most likely the original
+                members.sort_by(|&a, &b| {
+                    images[b]
+                        .1
+                        .cmp(&images[a].1)
+                        .then_with(|| images[a].0.cmp(&images[b].0))
+                });
+                let reference = images[members[0]].2;
+                let mut group: Vec<SimilarImage> = members
+                    .iter()
+                    .map(|&i| SimilarImage {
+                        path: images[i].0.clone(),
+                        size: images[i].1,
+                        hash: format!("{:016x}", images[i].2),
+                        distance: hamming_distance(reference, images[i].2),
+                    })
+                    .collect();
+                group[1..].sort_by(|a, b| a.distance.cmp(&b.distance).then(a.path.cmp(&b.path)));
+                SimilarGroup { images: group }
+            })
+            .collect();
+        groups.sort_by(|a, b| {
+            b.images
+                .len()
+                .cmp(&a.images.len())
+                .then_with(|| a.images[0].path.cmp(&b.images[0].path))
+        });
+        groups
+    }
+}
+
+impl Default for SimilarityDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// A smooth pattern, sampled at `size` x `size`; `turned` rotates it
+    fn pattern(size: usize, turned: bool) -> GrayImage {
+        let mut pixels = Vec::with_capacity(size * size);
+        for y in 0..size {
+            for x in 0..size {
+                let (u, v) = (x as f64 / size as f64, y as f64 / size as f64);
+                let (u, v) = if turned { (v, u) } else { (u, v) };
+                let value = 128.0
+                    + 100.0
+                        * (u * std::f64::consts::TAU * 1.5).sin()
+                        * (v * std::f64::consts::PI).cos();
+                pixels.push(value as u8);
+            }
+        }
+        GrayImage {
+            width: size,
+            height: size,
+            pixels,
+        }
+    }
+
+    fn write_pgm(path: &Path, image: &GrayImage) {
+        let mut data = format!("P5\n{} {}\n255\n", image.width, image.height).into_bytes();
+        data.extend(&image.pixels);
+        std::fs::write(path, data).unwrap();
+    }
+
+    #[test]
+    fn test_dhash_survives_resizing() {
+        let original = dhash(&pattern(256, false));
+        assert!(hamming_distance(original, dhash(&pattern(48, false))) <= 4);
+        assert!(hamming_distance(original, dhash(&pattern(256, true))) > DEFAULT_THRESHOLD);
+    }
+
+    #[test]
+    fn test_find_similar_groups_resized_images() {
+        let dir = TempDir::new().unwrap();
+        write_pgm(&dir.path().join("photo.pgm"), &pattern(128, false));
+        write_pgm(&dir.path().join("photo-small.pgm"), &pattern(40, false));
+        write_pgm(&dir.path().join("other.pgm"), &pattern(128, true));
+        std::fs::write(dir.path().join("broken.ppm"), b"P6 nonsense").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), b"not an image").unwrap();
+
+        let result = SimilarityDetector::new()
+            .find_similar(
+                dir.path(),
+                0,
+                &ScanOptions::default(),
+                &CancellationToken::new(),
+            )
+            .unwrap();
+        assert_eq!(result.images, 3);
+        assert_eq!(result.undecodable.len(), 1);
+        assert_eq!(result.groups.len(), 1);
+        let images = &result.groups[0].images;
+        assert!(images[0].path.ends_with("photo.pgm"));
+        assert!(images[1].path.ends_with("photo-small.pgm"));
+        assert_eq!(images[0].distance, 0);
+    }
+}
@@ -0,0 +1,176 @@
+//! Aggregate statistics over duplicate groups
+
+use crate::detector::{DuplicateDetector, DuplicateGroup};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Number of groups listed as the most duplicated
+pub const MOST_DUPLICATED: usize = 10;
+
+/// Summary of one duplicate group
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GroupSummary {
+    /// First path of the group, standing for all of its copies
+    pub path: String,
+    /// Size of each copy in bytes
+    pub size: u64,
+    /// Number of copies, the original included
+    pub copies: usize,
+    /// Bytes taken by the copies beyond the first
+    pub wasted: u64,
+}
+
+/// Duplicates sharing a file extension
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TypeUsage {
+    /// Lowercased extension, empty for files without one
+    pub extension: String,
+    /// Number of groups
+    pub groups: usize,
+    /// Bytes taken by the extra copies
+    pub wasted: u64,
+}
+
+/// Totals over all groups found by a scan
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DuplicateStats {
+    /// Number of duplicate groups
+    pub group_count: usize,
+    /// Number of files that belong to a group
+    pub duplicate_files: usize,
+    /// Bytes taken by the copies beyond the first of each group
+    pub wasted_bytes: u64,
+    /// Group wasting the most space
+    pub largest_group: Option<GroupSummary>,
+    /// Wasted space per extension, largest first
+    pub by_type: Vec<TypeUsage>,
+    /// Groups with the most copies, at most [`MOST_DUPLICATED`]
+    pub most_duplicated: Vec<GroupSummary>,
+}
+
+impl DuplicateStats {
+    /// Compute statistics for `groups`
+    pub fn from_groups(groups: &[DuplicateGroup]) -> Self {
+        let mut summaries: Vec<GroupSummary> = groups
+            .iter()
+            .filter_map(|group| {
+                let first = group.files.iter().min_by(|a, b| a.path.cmp(&b.path))?;
+                Some(GroupSummary {
+                    path: first.path.clone(),
+                    size: first.size,
+                    copies: group.files.len(),
+                    wasted: DuplicateDetector::calculate_savings(std::slice::from_ref(group)),
+                })
+            })
+            .collect();
+
+        let mut types: HashMap<String, (usize, u64)> = HashMap::new();
+        for summary in &summaries {
+            let (count, wasted) = types.entry(extension(&summary.path)).or_default();
+            *count += 1;
+            *wasted += summary.wasted;
+        }
+        let mut by_type: Vec<TypeUsage> = types
+            .into_iter()
+            .map(|(extension, (groups, wasted))| TypeUsage {
+                extension,
+                groups,
+                wasted,
+            })
+            .collect();
+        by_type.sort_by(|a, b| {
+            b.wasted
+                .cmp(&a.wasted)
+                .then_with(|| a.extension.cmp(&b.extension))
+        });
+
+        let largest_group = summaries
+            .iter()
+            .max_by(|a, b| a.wasted.cmp(&b.wasted).then_with(|| b.path.cmp(&a.path)))
+            .cloned();
+        let group_count = summaries.len();
+        let duplicate_files = summaries.iter().map(|s| s.copies).sum();
+        let wasted_bytes = summaries.iter().map(|s| s.wasted).sum();
+        summaries.sort_by(|a, b| {
+            b.copies
+                .cmp(&a.copies)
+                .then_with(|| b.wasted.cmp(&a.wasted))
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        summaries.truncate(MOST_DUPLICATED);
+        Self {
+            group_count,
+            duplicate_files,
+            wasted_bytes,
+            largest_group,
+            by_type,
+            most_duplicated: summaries,
+        }
+    }
+}
+
+/// Lowercased extension of `path`, empty if it has none
+fn extension(path: &str) -> String {
+    Path::new(path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::DetectionStage;
+    use dragonfly_core::domain::entities::FileEntity;
+
+    fn group(paths: &[&str], size: u64) -> DuplicateGroup {
+        DuplicateGroup {
+            files: paths
+                .iter()
+                .map(|p| FileEntity::new((*p).to_string(), size))
+                .collect(),
+            hash: String::new(),
+            confirmed_by: DetectionStage::FullHash,
+            verification: None,
+        }
+    }
+
+    #[test]
+    fn test_stats_from_groups() {
+        let groups = [
+            group(&["/a/movie.MP4", "/b/movie.mp4"], 1000),
+            group(&["/a/x.jpg", "/b/x.jpg", "/c/x.jpg", "/d/x.jpg"], 10),
+            group(&["/a/y.jpg", "/b/y.jpg"], 50),
+            group(&["/a/README", "/b/README"], 5),
+        ];
+        let stats = DuplicateStats::from_groups(&groups);
+        assert_eq!(stats.group_count, 4);
+        assert_eq!(stats.duplicate_files, 10);
+        assert_eq!(stats.wasted_bytes, 1000 + 30 + 50 + 5);
+
+        let largest = stats.largest_group.unwrap();
+        assert_eq!(largest.path, "/a/movie.MP4");
+        assert_eq!(largest.wasted, 1000);
+
+        assert_eq!(stats.by_type[0].extension, "mp4");
+        assert_eq!(
+            stats.by_type[1],
+            TypeUsage {
+                extension: "jpg".into(),
+                groups: 2,
+                wasted: 80
+            }
+        );
+        assert_eq!(stats.by_type[2].extension, "");
+
+        assert_eq!(stats.most_duplicated[0].path, "/a/x.jpg");
+        assert_eq!(stats.most_duplicated[0].copies, 4);
+        assert_eq!(stats.most_duplicated[1].path, "/a/movie.MP4");
+    }
+
+    #[test]
+    fn test_stats_empty() {
+        assert_eq!(DuplicateStats::from_groups(&[]), DuplicateStats::default());
+    }
+}
@@ -0,0 +1,145 @@
+//! Detection strategies trading accuracy for speed
+
+use dragonfly_core::domain::entities::FileEntity;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+/// How thoroughly files are compared before they count as duplicates
+///
+/// Files are always grouped by size first, then by a hash of their first
+/// and last 64 KB. The strategy decides whether that is enough.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum DetectionStrategy {
+    /// Stop at the head and tail hash - fast, but large files that differ
+    /// only in the middle count as duplicates
+    Partial,
+    /// Confirm with a hash of every byte
+    #[default]
+    Full,
+}
+
+/// Comparison that confirmed a group of duplicates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DetectionStage {
+    /// Same size, first and last 64 KB; the middle wasn't compared
+    PartialHash,
+    /// Same hash of the whole content
+    FullHash,
+    /// Every byte compared
+    ByteComparison,
+    /// Same hash of the audio, image or video data; tags and other
+    /// metadata weren't compared, so sizes may differ
+    MediaHash,
+}
+
+/// Which copy of a duplicate group to keep when the others are removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum KeepStrategy {
+    /// The least recently modified copy
+    Oldest,
+    /// The most recently modified copy
+    Newest,
+    /// The copy with the shortest path, usually the one nearest the top
+    ShortestPath,
+    /// The first copy in path order
+    #[default]
+    First,
+    /// The copy under the earliest scan root, e.g. the primary folder rather
+    /// than its backup
+    Primary,
+}
+
+impl KeepStrategy {
+    /// Index of the copy in `files` to keep, `None` if `files` is empty
+    ///
+    /// A protected copy is never removed, so one is kept when the group has
+    /// any. Ties, and copies whose modification time is unknown, fall back to
+    /// path order, so the choice doesn't depend on the order of `files`.
+    pub fn pick(self, files: &[FileEntity]) -> Option<usize> {
+        let by_path = |a: &FileEntity, b: &FileEntity| a.path.cmp(&b.path);
+        let best = files.iter().enumerate().min_by(|(_, a), (_, b)| {
+            let protected = b
+                .protection
+                .is_protected()
+                .cmp(&a.protection.is_protected());
+            let preferred = match self {
+                // Known times come before unknown ones
+                Self::Oldest => match (a.modified, b.modified) {
+                    (Some(a), Some(b)) => a.cmp(&b),
+                    (a, b) => a.is_none().cmp(&b.is_none()),
+                },
+                Self::Newest => match (a.modified, b.modified) {
+                    (Some(a), Some(b)) => b.cmp(&a),
+                    (a, b) => a.is_none().cmp(&b.is_none()),
+                },
+                Self::ShortestPath => a.path.chars().count().cmp(&b.path.chars().count()),
+                Self::First => Ordering::Equal,
+                Self::Primary => match (a.root, b.root) {
+                    (Some(a), Some(b)) => a.cmp(&b),
+                    (a, b) => a.is_none().cmp(&b.is_none()),
+                },
+            };
+            protected.then(preferred).then_with(|| by_path(a, b))
+        });
+        best.map(|(index, _)| index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use dragonfly_core::domain::protection::PathProtection;
+
+    fn file(path: &str, modified: Option<i64>) -> FileEntity {
+        let mut file = FileEntity::new(path.to_string(), 10);
+        file.modified = modified.map(|secs| Utc.timestamp_opt(secs, 0).unwrap());
+        file
+    }
+
+    #[test]
+    fn test_keep_strategies() {
+        let files = [
+            file("/b/photo.jpg", Some(200)),
+            file("/a/deeper/photo.jpg", Some(100)),
+            file("/c/photo.jpg", None),
+            file("/a/photo.jpg", Some(300)),
+        ];
+        assert_eq!(KeepStrategy::Oldest.pick(&files), Some(1));
+        assert_eq!(KeepStrategy::Newest.pick(&files), Some(3));
+        assert_eq!(KeepStrategy::ShortestPath.pick(&files), Some(3));
+        assert_eq!(KeepStrategy::First.pick(&files), Some(1));
+        assert_eq!(KeepStrategy::First.pick(&[]), None);
+    }
+
+    #[test]
+    fn test_keep_unknown_times_last() {
+        let files = [file("/a", None), file("/b", Some(100))];
+        assert_eq!(KeepStrategy::Oldest.pick(&files), Some(1));
+        assert_eq!(KeepStrategy::Newest.pick(&files), Some(1));
+    }
+
+    #[test]
+    fn test_keep_primary_root() {
+        let mut files = [file("/backup/a", None), file("/primary/b", None)];
+        files[0].root = Some(1);
+        files[1].root = Some(0);
+        assert_eq!(KeepStrategy::Primary.pick(&files), Some(1));
+        // Without roots it keeps the first in path order
+        let files = [file("/b", None), file("/a", None)];
+        assert_eq!(KeepStrategy::Primary.pick(&files), Some(1));
+    }
+
+    #[test]
+    fn test_keep_protected_copy() {
+        let mut files = [file("/a", Some(100)), file("/b", Some(200))];
+        files[1].protection = PathProtection::Preserved;
+        assert_eq!(KeepStrategy::Oldest.pick(&files), Some(1));
+        assert_eq!(KeepStrategy::First.pick(&files), Some(1));
+    }
+
+    #[test]
+    fn test_strategy_default() {
+        assert_eq!(DetectionStrategy::default(), DetectionStrategy::Full);
+    }
+}
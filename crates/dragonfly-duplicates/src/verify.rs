@@ -0,0 +1,176 @@
+//! Byte-by-byte confirmation of duplicate groups
+//!
+//! Matching hashes make identical content overwhelmingly likely, but not
+//! certain: a partial hash skips the middle of large files, and a file may
+//! change or fail to read between hashing and deletion. Verification
+//! compares the files of a group byte for byte against each other and keeps
+//! only those that really are the same.
+
+use dragonfly_core::domain::entities::FileEntity;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Bytes read from each file at a time
+const CHUNK: usize = 64 * 1024;
+
+/// How a byte comparison of a group turned out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationStatus {
+    /// Every file matched byte for byte
+    Verified,
+    /// Some files differed and were taken out of the group
+    Mismatch,
+    /// Some files couldn't be read and were taken out of the group
+    Unreadable,
+}
+
+/// Result of comparing a group's files byte for byte
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Verification {
+    /// Overall outcome
+    pub status: VerificationStatus,
+    /// Files of the hash group whose bytes differ from this group's
+    pub mismatched: Vec<String>,
+    /// Files of the hash group that couldn't be read
+    pub unreadable: Vec<String>,
+}
+
+/// Split `files`, which hashed the same, into groups of byte-identical files
+///
+/// Files compare against the first file of each group found so far, two
+/// files open at a time. Groups of fewer than two files are dropped; their
+/// files show up as mismatched in the groups that remain.
+pub(crate) fn verify(files: Vec<FileEntity>) -> Vec<(Vec<FileEntity>, Verification)> {
+    let mut classes: Vec<Vec<FileEntity>> = Vec::new();
+    let mut unreadable = Vec::new();
+    'files: for file in files {
+        for class in &mut classes {
+            match same_content(Path::new(&class[0].path), Path::new(&file.path)) {
+                Ok(true) => {
+                    class.push(file);
+                    continue 'files;
+                }
+                Ok(false) => {}
+                Err(_) => {
+                    unreadable.push(file.path);
+                    continue 'files;
+                }
+            }
+        }
+        if File::open(&file.path).is_ok() {
+            classes.push(vec![file]);
+        } else {
+            unreadable.push(file.path);
+        }
+    }
+
+    let paths: Vec<Vec<String>> = classes
+        .iter()
+        .map(|class| class.iter().map(|f| f.path.clone()).collect())
+        .collect();
+    classes
+        .into_iter()
+        .enumerate()
+        .filter(|(_, class)| class.len() > 1)
+        .map(|(i, class)| {
+            let mismatched: Vec<String> = paths
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .flat_map(|(_, paths)| paths.iter().cloned())
+                .collect();
+            let status = if !mismatched.is_empty() {
+                VerificationStatus::Mismatch
+            } else if !unreadable.is_empty() {
+                VerificationStatus::Unreadable
+            } else {
+                VerificationStatus::Verified
+            };
+            let verification = Verification {
+                status,
+                mismatched,
+                unreadable: unreadable.clone(),
+            };
+            (class, verification)
+        })
+        .collect()
+}
+
+/// Whether the files at `a` and `b` hold the same bytes
+fn same_content(a: &Path, b: &Path) -> io::Result<bool> {
+    let (mut a, mut b) = (File::open(a)?, File::open(b)?);
+    if a.metadata()?.len() != b.metadata()?.len() {
+        return Ok(false);
+    }
+    let (mut chunk_a, mut chunk_b) = (vec![0; CHUNK], vec![0; CHUNK]);
+    loop {
+        let read = fill(&mut a, &mut chunk_a)?;
+        if fill(&mut b, &mut chunk_b)? != read || chunk_a[..read] != chunk_b[..read] {
+            return Ok(false);
+        }
+        if read == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+/// Read until `buf` is full or the file ends, returning the bytes read
+fn fill(file: &mut File, buf: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        match file.read(&mut buf[read..])? {
+            0 => break,
+            n => read += n,
+        }
+    }
+    Ok(read)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn file(dir: &Path, name: &str, content: &[u8]) -> FileEntity {
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        FileEntity::new(path.to_string_lossy().to_string(), content.len() as u64)
+    }
+
+    #[test]
+    fn test_verify_identical_files() {
+        let dir = TempDir::new().unwrap();
+        let content = vec![7u8; CHUNK * 2 + 5];
+        let files = vec![
+            file(dir.path(), "a", &content),
+            file(dir.path(), "b", &content),
+        ];
+        let groups = verify(files);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].0.len(), 2);
+        assert_eq!(groups[0].1.status, VerificationStatus::Verified);
+    }
+
+    #[test]
+    fn test_verify_splits_out_differing_files() {
+        let dir = TempDir::new().unwrap();
+        let mut other = vec![7u8; CHUNK * 2 + 5];
+        other[CHUNK + 1] = 8;
+        let files = vec![
+            file(dir.path(), "a", &[7u8; CHUNK * 2 + 5]),
+            file(dir.path(), "b", &other),
+            file(dir.path(), "c", &[7u8; CHUNK * 2 + 5]),
+            FileEntity::new(dir.path().join("gone").to_string_lossy().to_string(), 5),
+        ];
+        let groups = verify(files);
+        assert_eq!(groups.len(), 1);
+        let (files, verification) = &groups[0];
+        assert_eq!(files.len(), 2);
+        assert_eq!(verification.status, VerificationStatus::Mismatch);
+        assert!(verification.mismatched[0].ends_with("b"));
+        assert!(verification.unreadable[0].ends_with("gone"));
+    }
+}
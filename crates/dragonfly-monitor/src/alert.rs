@@ -0,0 +1,336 @@
+//! Threshold alerts
+//!
+//! An [`AlertRule`] such as `disk>90` or `battery<20` compares one figure of
+//! a [`SystemMetrics`] sample with a threshold. [`Alerts`] evaluates a set of
+//! rules sample after sample and reports when each one starts and stops
+//! holding. A rule that has fired only clears once its figure has moved
+//! [`REARM_MARGIN`] back past the threshold, so a value hovering around it
+//! doesn't fire again on every sample.
+
+use crate::metrics::SystemMetrics;
+use dragonfly_core::error::{Error, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// How far back past its threshold a figure must move, as a fraction of the
+/// threshold, before a fired rule clears
+pub const REARM_MARGIN: f64 = 0.05;
+
+/// The figure of a sample a rule looks at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertMetric {
+    /// CPU usage across all cores, in percent
+    Cpu,
+    /// Memory in use, in percent
+    Memory,
+    /// Swap in use, in percent
+    Swap,
+    /// Root volume in use, in percent
+    Disk,
+    /// One-minute load average
+    Load,
+    /// Battery charge left, in percent
+    Battery,
+}
+
+impl AlertMetric {
+    /// Name used in rule expressions
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Cpu => "cpu",
+            Self::Memory => "mem",
+            Self::Swap => "swap",
+            Self::Disk => "disk",
+            Self::Load => "load",
+            Self::Battery => "battery",
+        }
+    }
+
+    /// Unit the figure is shown in
+    pub fn unit(self) -> &'static str {
+        match self {
+            Self::Load => "",
+            _ => "%",
+        }
+    }
+
+    /// The figure in `metrics`, if the machine reports it
+    pub fn value(self, metrics: &SystemMetrics) -> Option<f64> {
+        let percent =
+            |used: u64, total: u64| (total > 0).then(|| used as f64 / total as f64 * 100.0);
+        match self {
+            Self::Cpu => Some(f64::from(metrics.cpu_usage_percent)),
+            Self::Memory => percent(metrics.memory_used_bytes, metrics.memory_total_bytes),
+            Self::Swap => percent(metrics.swap_used_bytes, metrics.swap_total_bytes),
+            Self::Disk => percent(metrics.disk_used_bytes, metrics.disk_total_bytes),
+            Self::Load => Some(metrics.load_average.one),
+            Self::Battery => metrics
+                .battery
+                .map(|battery| f64::from(battery.charge_percent)),
+        }
+    }
+}
+
+impl FromStr for AlertMetric {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "cpu" => Ok(Self::Cpu),
+            "mem" | "memory" => Ok(Self::Memory),
+            "swap" => Ok(Self::Swap),
+            "disk" => Ok(Self::Disk),
+            "load" => Ok(Self::Load),
+            "battery" => Ok(Self::Battery),
+            _ => Err(Error::InvalidInput(format!(
+                "Unknown alert metric '{}' (expected cpu, mem, swap, disk, load or battery)",
+                name
+            ))),
+        }
+    }
+}
+
+/// Which side of the threshold fires a rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    /// Fires when the figure rises above the threshold
+    Above,
+    /// Fires when the figure falls below the threshold
+    Below,
+}
+
+/// A threshold on one figure, written `<metric><op><threshold>` with `>` or
+/// `<`, e.g. `mem>95`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AlertRule {
+    /// Figure compared
+    pub metric: AlertMetric,
+    /// Side of the threshold that fires the rule
+    pub comparison: Comparison,
+    /// Threshold, in the figure's unit
+    pub threshold: f64,
+}
+
+impl AlertRule {
+    /// Whether `value` is past the threshold
+    pub fn is_crossed(&self, value: f64) -> bool {
+        match self.comparison {
+            Comparison::Above => value > self.threshold,
+            Comparison::Below => value < self.threshold,
+        }
+    }
+
+    /// Whether `value` is far enough back from the threshold for a fired
+    /// rule to clear
+    pub fn is_rearmed(&self, value: f64) -> bool {
+        let margin = self.threshold.abs() * REARM_MARGIN;
+        match self.comparison {
+            Comparison::Above => value <= self.threshold - margin,
+            Comparison::Below => value >= self.threshold + margin,
+        }
+    }
+}
+
+impl FromStr for AlertRule {
+    type Err = Error;
+
+    fn from_str(expression: &str) -> Result<Self> {
+        let Some(at) = expression.find(['>', '<']) else {
+            return Err(Error::InvalidInput(format!(
+                "Alert '{}' needs a > or < comparison, e.g. disk>90",
+                expression
+            )));
+        };
+        let comparison = if expression[at..].starts_with('>') {
+            Comparison::Above
+        } else {
+            Comparison::Below
+        };
+        let metric = expression[..at].trim().parse()?;
+        let threshold = expression[at + 1..]
+            .trim()
+            .trim_end_matches('%')
+            .parse::<f64>()
+            .ok()
+            .filter(|threshold| threshold.is_finite())
+            .ok_or_else(|| {
+                Error::InvalidInput(format!(
+                    "Alert '{}' needs a number to compare with",
+                    expression
+                ))
+            })?;
+        Ok(Self {
+            metric,
+            comparison,
+            threshold,
+        })
+    }
+}
+
+impl fmt::Display for AlertRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self.comparison {
+            Comparison::Above => '>',
+            Comparison::Below => '<',
+        };
+        write!(f, "{}{}{}", self.metric.name(), op, self.threshold)
+    }
+}
+
+/// Whether a rule started or stopped holding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertTransition {
+    /// The figure crossed the threshold
+    Fired,
+    /// The figure moved back past the threshold and its margin
+    Cleared,
+}
+
+/// A rule changing state on a sample
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AlertEvent {
+    /// Rule that changed state
+    pub rule: AlertRule,
+    /// What happened
+    pub transition: AlertTransition,
+    /// The figure in the sample that caused it
+    pub value: f64,
+}
+
+/// A set of rules and whether each has fired
+#[derive(Debug, Clone, Default)]
+pub struct Alerts {
+    rules: Vec<(AlertRule, bool)>,
+}
+
+impl Alerts {
+    /// Watch `rules`, none of them fired yet
+    pub fn new(rules: Vec<AlertRule>) -> Self {
+        Self {
+            rules: rules.into_iter().map(|rule| (rule, false)).collect(),
+        }
+    }
+
+    /// Whether there are no rules to evaluate
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Evaluate every rule against `metrics`, returning the rules that fired
+    /// or cleared on this sample
+    ///
+    /// Rules on a figure the sample doesn't have keep their state.
+    pub fn evaluate(&mut self, metrics: &SystemMetrics) -> Vec<AlertEvent> {
+        let mut events = Vec::new();
+        for (rule, fired) in &mut self.rules {
+            let Some(value) = rule.metric.value(metrics) else {
+                continue;
+            };
+            let transition = if !*fired && rule.is_crossed(value) {
+                AlertTransition::Fired
+            } else if *fired && rule.is_rearmed(value) {
+                AlertTransition::Cleared
+            } else {
+                continue;
+            };
+            *fired = transition == AlertTransition::Fired;
+            events.push(AlertEvent {
+                rule: *rule,
+                transition,
+                value,
+            });
+        }
+        events
+    }
+
+    /// Rules that have fired and not yet cleared
+    pub fn firing(&self) -> impl Iterator<Item = &AlertRule> {
+        self.rules
+            .iter()
+            .filter(|(_, fired)| *fired)
+            .map(|(rule, _)| rule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::{HostInfo, LoadAverage};
+
+    fn metrics(disk_used: u64, load: f64) -> SystemMetrics {
+        SystemMetrics::new(
+            10.0,
+            Vec::new(),
+            LoadAverage {
+                one: load,
+                five: 0.0,
+                fifteen: 0.0,
+            },
+            100,
+            50,
+            50,
+            None,
+            0,
+            0,
+            100,
+            disk_used,
+            100 - disk_used,
+            None,
+            None,
+            HostInfo::default(),
+            0,
+            0,
+            1,
+        )
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let rule: AlertRule = "disk>90".parse().unwrap();
+        assert_eq!(rule.metric, AlertMetric::Disk);
+        assert_eq!(rule.comparison, Comparison::Above);
+        assert_eq!(rule.threshold, 90.0);
+
+        let rule: AlertRule = " Battery < 20% ".parse().unwrap();
+        assert_eq!(rule.metric, AlertMetric::Battery);
+        assert_eq!(rule.comparison, Comparison::Below);
+        assert_eq!(rule.to_string(), "battery<20");
+        assert_eq!("load>2.5".parse::<AlertRule>().unwrap().threshold, 2.5);
+
+        assert!("disk".parse::<AlertRule>().is_err());
+        assert!("gpu>50".parse::<AlertRule>().is_err());
+        assert!("mem>lots".parse::<AlertRule>().is_err());
+    }
+
+    #[test]
+    fn should_fire_once_and_clear_past_the_margin() {
+        let mut alerts = Alerts::new(vec!["disk>90".parse().unwrap()]);
+
+        assert!(alerts.evaluate(&metrics(80, 0.0)).is_empty());
+        let events = alerts.evaluate(&metrics(93, 0.0));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].transition, AlertTransition::Fired);
+        assert_eq!(events[0].value, 93.0);
+        assert_eq!(alerts.firing().count(), 1);
+
+        // Hovering around the threshold neither fires again nor clears
+        assert!(alerts.evaluate(&metrics(89, 0.0)).is_empty());
+        assert!(alerts.evaluate(&metrics(91, 0.0)).is_empty());
+
+        let events = alerts.evaluate(&metrics(85, 0.0));
+        assert_eq!(events[0].transition, AlertTransition::Cleared);
+        assert_eq!(alerts.firing().count(), 0);
+    }
+
+    #[test]
+    fn should_skip_figures_the_machine_lacks() {
+        let mut alerts = Alerts::new(vec![
+            "battery<20".parse().unwrap(),
+            "swap>50".parse().unwrap(),
+            "load>4".parse().unwrap(),
+        ]);
+        let events = alerts.evaluate(&metrics(50, 6.0));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].rule.metric, AlertMetric::Load);
+    }
+}
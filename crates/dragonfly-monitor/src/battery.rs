@@ -0,0 +1,253 @@
+//! Battery charge and wear
+//!
+//! macOS keeps the figures in the `AppleSmartBattery` entry of the IOKit
+//! registry, Linux under `/sys/class/power_supply`. Either way a desktop has
+//! no battery and reads as `None`.
+
+use crate::metrics::{BatteryMetrics, ChargingState};
+
+/// The internal battery, if the machine has one
+#[cfg(target_os = "macos")]
+pub(crate) fn read_battery() -> Option<BatteryMetrics> {
+    use core_foundation_sys::base::{
+        kCFAllocatorDefault, CFAllocatorRef, CFGetTypeID, CFRelease, CFTypeRef,
+    };
+    use core_foundation_sys::dictionary::CFMutableDictionaryRef;
+    use core_foundation_sys::number::{
+        kCFNumberSInt64Type, CFBooleanGetTypeID, CFBooleanGetValue, CFNumberGetTypeID,
+        CFNumberGetValue,
+    };
+    use core_foundation_sys::string::{
+        kCFStringEncodingUTF8, CFStringCreateWithCString, CFStringRef,
+    };
+    use std::ffi::{c_char, CString};
+
+    #[link(name = "IOKit", kind = "framework")]
+    extern "C" {
+        fn IOServiceMatching(name: *const c_char) -> CFMutableDictionaryRef;
+        fn IOServiceGetMatchingService(
+            main_port: libc::mach_port_t,
+            matching: CFMutableDictionaryRef,
+        ) -> libc::mach_port_t;
+        fn IORegistryEntryCreateCFProperty(
+            entry: libc::mach_port_t,
+            key: CFStringRef,
+            allocator: CFAllocatorRef,
+            options: u32,
+        ) -> CFTypeRef;
+        fn IOObjectRelease(object: libc::mach_port_t) -> libc::kern_return_t;
+    }
+
+    /// A registry property of `entry`, released once `read` has looked at it
+    unsafe fn property<T>(
+        entry: libc::mach_port_t,
+        key: &str,
+        read: impl FnOnce(CFTypeRef) -> Option<T>,
+    ) -> Option<T> {
+        let key = CString::new(key).ok()?;
+        let key =
+            CFStringCreateWithCString(kCFAllocatorDefault, key.as_ptr(), kCFStringEncodingUTF8);
+        if key.is_null() {
+            return None;
+        }
+        let value = IORegistryEntryCreateCFProperty(entry, key, kCFAllocatorDefault, 0);
+        CFRelease(key.cast());
+        if value.is_null() {
+            return None;
+        }
+        let result = read(value);
+        CFRelease(value);
+        result
+    }
+
+    unsafe fn number(entry: libc::mach_port_t, key: &str) -> Option<u64> {
+        property(entry, key, |value| {
+            if CFGetTypeID(value) != CFNumberGetTypeID() {
+                return None;
+            }
+            let mut number: i64 = 0;
+            CFNumberGetValue(
+                value.cast(),
+                kCFNumberSInt64Type,
+                (&mut number as *mut i64).cast(),
+            )
+            .then(|| number.max(0) as u64)
+        })
+    }
+
+    unsafe fn flag(entry: libc::mach_port_t, key: &str) -> bool {
+        property(entry, key, |value| {
+            (CFGetTypeID(value) == CFBooleanGetTypeID()).then(|| CFBooleanGetValue(value.cast()))
+        })
+        .unwrap_or(false)
+    }
+
+    // SAFETY: the matching dictionary is consumed by IOServiceGetMatchingService
+    // and the service is released before returning
+    unsafe {
+        // Port 0 asks for the default main port
+        let battery = IOServiceGetMatchingService(
+            0,
+            IOServiceMatching(b"AppleSmartBattery\0".as_ptr().cast()),
+        );
+        if battery == 0 {
+            return None;
+        }
+        // On Apple silicon the capacities are percentages and the raw
+        // figures are in mAh; Intel Macs report mAh in both
+        let current = number(battery, "CurrentCapacity");
+        let max = number(battery, "MaxCapacity");
+        let raw_max = number(battery, "AppleRawMaxCapacity").or(max);
+        let design = number(battery, "DesignCapacity");
+        let cycle_count = number(battery, "CycleCount");
+        let external = flag(battery, "ExternalConnected");
+        let charging = flag(battery, "IsCharging");
+        let full = flag(battery, "FullyCharged");
+        IOObjectRelease(battery);
+
+        let state = if full {
+            ChargingState::Full
+        } else if charging {
+            ChargingState::Charging
+        } else if external {
+            ChargingState::NotCharging
+        } else {
+            ChargingState::Discharging
+        };
+        Some(BatteryMetrics {
+            charge_percent: percent(current?, max?)?,
+            state,
+            cycle_count: cycle_count.and_then(|count| u32::try_from(count).ok()),
+            health_percent: raw_max
+                .zip(design)
+                .and_then(|(max, design)| percent(max, design)),
+        })
+    }
+}
+
+/// The first system battery, as opposed to a mouse's or a UPS's
+#[cfg(target_os = "linux")]
+pub(crate) fn read_battery() -> Option<BatteryMetrics> {
+    read_power_supplies(std::path::Path::new("/sys/class/power_supply"))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+pub(crate) fn read_battery() -> Option<BatteryMetrics> {
+    None
+}
+
+/// The first battery among the power supplies listed under `root`
+#[cfg(target_os = "linux")]
+fn read_power_supplies(root: &std::path::Path) -> Option<BatteryMetrics> {
+    let mut supplies: Vec<_> = std::fs::read_dir(root)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .collect();
+    supplies.sort();
+    supplies.iter().find_map(|supply| {
+        let read = |name: &str| {
+            std::fs::read_to_string(supply.join(name))
+                .ok()
+                .map(|value| value.trim().to_string())
+        };
+        let number = |name: &str| read(name).and_then(|value| value.parse::<u64>().ok());
+        // Peripherals report a "Device" scope; the system battery has none
+        if read("type")? != "Battery" || read("scope").as_deref() == Some("Device") {
+            return None;
+        }
+        let state = match read("status").as_deref() {
+            Some("Charging") => ChargingState::Charging,
+            Some("Full") => ChargingState::Full,
+            Some("Not charging") => ChargingState::NotCharging,
+            _ => ChargingState::Discharging,
+        };
+        // Drivers report energy in µWh or charge in µAh
+        let health_percent = ["energy", "charge"].iter().find_map(|unit| {
+            percent(
+                number(&format!("{unit}_full"))?,
+                number(&format!("{unit}_full_design"))?,
+            )
+        });
+        Some(BatteryMetrics {
+            charge_percent: number("capacity")? as f32,
+            state,
+            // Drivers that don't count cycles report 0
+            cycle_count: number("cycle_count")
+                .filter(|&count| count > 0)
+                .and_then(|count| u32::try_from(count).ok()),
+            health_percent,
+        })
+    })
+}
+
+/// `part` as a percentage of `whole`, unless `whole` is zero
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn percent(part: u64, whole: u64) -> Option<f32> {
+    (whole > 0).then(|| (part as f64 / whole as f64 * 100.0) as f32)
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    fn supply(root: &Path, name: &str, files: &[(&str, &str)]) {
+        let dir = root.join(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        for (file, value) in files {
+            std::fs::write(dir.join(file), format!("{value}\n")).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_reads_system_battery() {
+        let root = TempDir::new().unwrap();
+        supply(root.path(), "AC", &[("type", "Mains"), ("online", "1")]);
+        supply(
+            root.path(),
+            "BAT0",
+            &[
+                ("type", "Battery"),
+                ("status", "Charging"),
+                ("capacity", "64"),
+                ("cycle_count", "312"),
+                ("energy_full", "43000000"),
+                ("energy_full_design", "50000000"),
+            ],
+        );
+
+        let battery = read_power_supplies(root.path()).unwrap();
+        assert_eq!(battery.charge_percent, 64.0);
+        assert_eq!(battery.state, ChargingState::Charging);
+        assert_eq!(battery.cycle_count, Some(312));
+        assert_eq!(battery.health_percent, Some(86.0));
+    }
+
+    #[test]
+    fn test_skips_peripherals_and_unknown_figures() {
+        let root = TempDir::new().unwrap();
+        supply(
+            root.path(),
+            "hidpp_battery_0",
+            &[("type", "Battery"), ("scope", "Device"), ("capacity", "20")],
+        );
+        assert!(read_power_supplies(root.path()).is_none());
+
+        supply(
+            root.path(),
+            "BAT1",
+            &[
+                ("type", "Battery"),
+                ("status", "Not charging"),
+                ("capacity", "80"),
+                ("cycle_count", "0"),
+            ],
+        );
+        let battery = read_power_supplies(root.path()).unwrap();
+        assert_eq!(battery.state, ChargingState::NotCharging);
+        assert_eq!(battery.cycle_count, None);
+        assert_eq!(battery.health_percent, None);
+    }
+}
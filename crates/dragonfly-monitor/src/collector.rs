@@ -1,9 +1,27 @@
 //! System metrics collection
+//!
+//! Refreshing everything `sysinfo` knows about (every process, every disk)
+//! costs far more than the handful of numbers in a [`SystemMetrics`], and
+//! `monitor` collects once a second. The collector refreshes CPU usage and
+//! memory on each sample, plus only the [`Extras`] its caller asked for, and
+//! times itself so that cost stays visible.
 
-use crate::metrics::SystemMetrics;
+use crate::battery::read_battery;
+use crate::gpu::read_gpu;
+use crate::host::{read_host, uptime};
+use crate::metrics::{
+    CollectorOverhead, HostInfo, LoadAverage, MemoryBreakdown, ProcessMetrics, ProcessSample,
+    SystemMetrics, TopProcesses,
+};
+use crate::process::{open_files, thread_count};
 use dragonfly_core::error::Result;
+use std::time::{Duration, Instant};
 use sysinfo::System;
 
+/// Shortest time between two refreshes for CPU usage to mean anything; wait
+/// this long after creating a collector or a first [`MetricsCollector::collect_processes`]
+pub const CPU_SAMPLE_INTERVAL: Duration = sysinfo::MINIMUM_CPU_UPDATE_INTERVAL;
+
 /// Get disk usage for root filesystem (returns (total_bytes, used_bytes))
 #[cfg(target_os = "macos")]
 fn get_disk_usage(_path: &str) -> Option<(u64, u64)> {
@@ -27,56 +45,327 @@ fn get_disk_usage(_path: &str) -> Option<(u64, u64)> {
     }
 }
 
-#[cfg(not(target_os = "macos"))]
+#[cfg(all(unix, not(target_os = "macos")))]
+#[allow(clippy::useless_conversion)] // statvfs field widths vary across platforms
+fn get_disk_usage(path: &str) -> Option<(u64, u64)> {
+    use std::ffi::CString;
+    use std::mem;
+
+    unsafe {
+        let mut stat: libc::statvfs = mem::zeroed();
+        let c_path = CString::new(path).ok()?;
+
+        if libc::statvfs(c_path.as_ptr(), &mut stat) == 0 {
+            let bsize = u64::from(stat.f_frsize);
+            let total = u64::from(stat.f_blocks) * bsize;
+            let free = u64::from(stat.f_bavail) * bsize;
+            let used = total.saturating_sub(free);
+            Some((total, used))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(not(unix))]
 fn get_disk_usage(_path: &str) -> Option<(u64, u64)> {
-    // Fallback for non-macOS: return None to use placeholder
+    // Fallback for non-Unix platforms: return None to use placeholder
     None
 }
 
+/// Split used memory into app, wired, compressed and cached, and read the
+/// kernel's memory pressure level
+#[cfg(target_os = "macos")]
+#[allow(deprecated)] // libc points at the mach2 crate for mach_host_self
+fn get_memory_breakdown() -> Option<MemoryBreakdown> {
+    use crate::metrics::MemoryPressure;
+    use std::mem;
+
+    unsafe {
+        let mut stats: libc::vm_statistics64 = mem::zeroed();
+        let mut count = libc::HOST_VM_INFO64_COUNT;
+        let result = libc::host_statistics64(
+            libc::mach_host_self(),
+            libc::HOST_VM_INFO64,
+            (&mut stats as *mut libc::vm_statistics64).cast(),
+            &mut count,
+        );
+        if result != libc::KERN_SUCCESS {
+            return None;
+        }
+
+        let mut level: libc::c_int = 0;
+        let mut size = mem::size_of::<libc::c_int>();
+        let name = b"kern.memorystatus_vm_pressure_level\0";
+        if libc::sysctlbyname(
+            name.as_ptr().cast(),
+            (&mut level as *mut libc::c_int).cast(),
+            &mut size,
+            std::ptr::null_mut(),
+            0,
+        ) != 0
+        {
+            return None;
+        }
+        // Levels from <sys/kern_memorystatus.h>
+        let pressure = match level {
+            4 => MemoryPressure::Critical,
+            2 => MemoryPressure::Warning,
+            _ => MemoryPressure::Normal,
+        };
+
+        let page_size = u64::try_from(libc::sysconf(libc::_SC_PAGESIZE)).ok()?;
+        let pages = |count: libc::natural_t| u64::from(count) * page_size;
+        Some(MemoryBreakdown {
+            pressure,
+            app_bytes: pages(
+                stats
+                    .internal_page_count
+                    .saturating_sub(stats.purgeable_count),
+            ),
+            wired_bytes: pages(stats.wire_count),
+            compressed_bytes: pages(stats.compressor_page_count),
+            cached_bytes: pages(stats.external_page_count) + pages(stats.purgeable_count),
+        })
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn get_memory_breakdown() -> Option<MemoryBreakdown> {
+    // Other platforms report used memory without the caches macOS counts
+    None
+}
+
+/// Subsystems refreshed on each collection beyond CPU and memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Extras {
+    /// Usage of the root volume
+    pub disk: bool,
+    /// The process table, for callers that inspect individual processes
+    pub processes: bool,
+    /// Charge and wear of the internal battery
+    pub battery: bool,
+    /// Utilisation and memory of the GPU, in builds with the `gpu` feature
+    pub gpu: bool,
+}
+
+impl Default for Extras {
+    fn default() -> Self {
+        Self {
+            disk: true,
+            processes: false,
+            battery: true,
+            gpu: true,
+        }
+    }
+}
+
 /// Collects system metrics
 #[derive(Debug)]
 pub struct MetricsCollector {
     system: System,
+    extras: Extras,
+    host: HostInfo,
+    overhead: CollectorOverhead,
+    total_micros: u64,
 }
 
 impl MetricsCollector {
-    /// Create a new metrics collector
+    /// Create a new metrics collector refreshing the default [`Extras`]
     pub fn new() -> Self {
-        let mut system = System::new_all();
-        system.refresh_all();
-        Self { system }
+        Self::with_extras(Extras::default())
+    }
+
+    /// Create a new metrics collector refreshing `extras` on each collection
+    pub fn with_extras(extras: Extras) -> Self {
+        let mut system = System::new();
+        // CPU usage is measured between refreshes, so take a baseline now
+        system.refresh_cpu_usage();
+        system.refresh_memory();
+        if extras.processes {
+            system.refresh_processes();
+        }
+        Self {
+            system,
+            extras,
+            host: read_host(),
+            overhead: CollectorOverhead::default(),
+            total_micros: 0,
+        }
+    }
+
+    /// Subsystems refreshed beyond CPU and memory
+    pub fn extras(&self) -> Extras {
+        self.extras
+    }
+
+    /// Time spent in [`Self::collect`] so far
+    pub fn overhead(&self) -> CollectorOverhead {
+        self.overhead
     }
 
     /// Collect current system metrics
     pub async fn collect(&mut self) -> Result<SystemMetrics> {
-        self.system.refresh_all();
+        let started = Instant::now();
+        self.system.refresh_cpu_usage();
+        self.system.refresh_memory();
+        if self.extras.processes {
+            self.system.refresh_processes();
+        }
 
         let cpu_usage = self.system.global_cpu_info().cpu_usage();
+        let cpu_per_core = self
+            .system
+            .cpus()
+            .iter()
+            .map(|cpu| cpu.cpu_usage())
+            .collect();
+        let load = System::load_average();
         let total_memory = self.system.total_memory();
         let used_memory = self.system.used_memory();
         let total_swap = self.system.total_swap();
         let used_swap = self.system.used_swap();
 
         // Get disk usage for root filesystem
-        let (disk_total, disk_used) = get_disk_usage("/").unwrap_or((0, 0));
+        let (disk_total, disk_used) = if self.extras.disk {
+            get_disk_usage("/").unwrap_or((0, 0))
+        } else {
+            (0, 0)
+        };
 
-        Ok(SystemMetrics {
+        let metrics = SystemMetrics {
             cpu_usage_percent: cpu_usage,
+            cpu_per_core_percent: cpu_per_core,
+            load_average: LoadAverage {
+                one: load.one,
+                five: load.five,
+                fifteen: load.fifteen,
+            },
             memory_total_bytes: total_memory,
             memory_used_bytes: used_memory,
             memory_available_bytes: total_memory.saturating_sub(used_memory),
+            memory_breakdown: get_memory_breakdown(),
             swap_total_bytes: total_swap,
             swap_used_bytes: used_swap,
             disk_total_bytes: disk_total,
             disk_used_bytes: disk_used,
             disk_available_bytes: disk_total.saturating_sub(disk_used),
+            battery: if self.extras.battery {
+                read_battery()
+            } else {
+                None
+            },
+            gpu: if self.extras.gpu { read_gpu() } else { None },
+            host: HostInfo {
+                uptime_seconds: uptime(),
+                ..self.host.clone()
+            },
             network_rx_bytes: 0, // Would need network monitoring
             network_tx_bytes: 0,
             timestamp: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_secs(),
-        })
+        };
+        self.record_overhead(started.elapsed().as_micros() as u64);
+        Ok(metrics)
+    }
+
+    /// Refresh the process table and return the `limit` processes using the
+    /// most CPU and the `limit` using the most memory
+    ///
+    /// A process's CPU usage is measured since the previous refresh of the
+    /// table, so the first call reports 0% for processes unless the
+    /// collector refreshes [`Extras::processes`]. Call it once and again
+    /// [`CPU_SAMPLE_INTERVAL`] later for a one-off reading.
+    pub async fn collect_processes(&mut self, limit: usize) -> Result<TopProcesses> {
+        self.system.refresh_processes();
+        let processes: Vec<ProcessMetrics> = self
+            .system
+            .processes()
+            .iter()
+            // Linux lists threads too, each with its process's memory
+            .filter(|(_, process)| process.thread_kind().is_none())
+            .map(|(pid, process)| ProcessMetrics {
+                pid: pid.as_u32(),
+                name: process.name().to_string(),
+                cpu_usage_percent: process.cpu_usage(),
+                rss_bytes: process.memory(),
+            })
+            .collect();
+        Ok(top_processes(processes, limit))
+    }
+
+    /// Refresh one process and return its resource use, or `None` once it
+    /// has exited
+    ///
+    /// CPU usage is measured since the previous call for the same process,
+    /// so the first one reports 0%.
+    pub async fn collect_process(&mut self, pid: u32) -> Result<Option<ProcessSample>> {
+        let sysinfo_pid = sysinfo::Pid::from_u32(pid);
+        if !self.system.refresh_process(sysinfo_pid) {
+            return Ok(None);
+        }
+        let Some(process) = self.system.process(sysinfo_pid) else {
+            return Ok(None);
+        };
+        Ok(Some(ProcessSample {
+            pid,
+            name: process.name().to_string(),
+            cpu_usage_percent: process.cpu_usage(),
+            rss_bytes: process.memory(),
+            threads: thread_count(pid),
+            open_files: open_files(pid),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+        }))
+    }
+
+    /// Processes whose name is `name`, ignoring case, lowest pid first
+    pub async fn find_processes(&mut self, name: &str) -> Result<Vec<ProcessMetrics>> {
+        self.system.refresh_processes();
+        let mut found: Vec<ProcessMetrics> = self
+            .system
+            .processes()
+            .iter()
+            .filter(|(_, process)| {
+                process.thread_kind().is_none() && process.name().eq_ignore_ascii_case(name)
+            })
+            .map(|(pid, process)| ProcessMetrics {
+                pid: pid.as_u32(),
+                name: process.name().to_string(),
+                cpu_usage_percent: process.cpu_usage(),
+                rss_bytes: process.memory(),
+            })
+            .collect();
+        found.sort_by_key(|process| process.pid);
+        Ok(found)
+    }
+
+    fn record_overhead(&mut self, micros: u64) {
+        self.total_micros += micros;
+        self.overhead.samples += 1;
+        self.overhead.last_micros = micros;
+        self.overhead.mean_micros = self.total_micros / self.overhead.samples;
+        self.overhead.max_micros = self.overhead.max_micros.max(micros);
+    }
+}
+
+/// The `limit` busiest and `limit` largest of `processes`
+fn top_processes(mut processes: Vec<ProcessMetrics>, limit: usize) -> TopProcesses {
+    processes.sort_by(|a, b| b.rss_bytes.cmp(&a.rss_bytes).then(a.pid.cmp(&b.pid)));
+    let by_memory = processes.iter().take(limit).cloned().collect();
+    processes.sort_by(|a, b| {
+        b.cpu_usage_percent
+            .total_cmp(&a.cpu_usage_percent)
+            .then(a.pid.cmp(&b.pid))
+    });
+    processes.truncate(limit);
+    TopProcesses {
+        by_cpu: processes,
+        by_memory,
     }
 }
 
@@ -100,6 +389,23 @@ mod tests {
         assert!(metrics.cpu_usage_percent <= 100.0);
     }
 
+    #[tokio::test]
+    async fn should_collect_per_core_and_load_metrics() {
+        let mut collector = MetricsCollector::new();
+        let metrics = collector.collect().await.unwrap();
+
+        assert_eq!(
+            metrics.cpu_per_core_percent.len(),
+            collector.system.cpus().len()
+        );
+        assert!(metrics
+            .cpu_per_core_percent
+            .iter()
+            .all(|usage| (0.0..=100.0).contains(usage)));
+        assert!(metrics.load_average.one >= 0.0);
+        assert!(metrics.load_average.fifteen >= 0.0);
+    }
+
     #[tokio::test]
     async fn should_collect_memory_metrics() {
         let mut collector = MetricsCollector::new();
@@ -116,6 +422,20 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn should_break_down_memory_on_macos() {
+        let mut collector = MetricsCollector::new();
+        let metrics = collector.collect().await.unwrap();
+
+        if cfg!(target_os = "macos") {
+            let breakdown = metrics.memory_breakdown.unwrap();
+            assert!(breakdown.wired_bytes > 0);
+            assert!(breakdown.app_bytes + breakdown.wired_bytes <= metrics.memory_total_bytes);
+        } else {
+            assert!(metrics.memory_breakdown.is_none());
+        }
+    }
+
     #[tokio::test]
     async fn should_collect_swap_metrics() {
         let mut collector = MetricsCollector::new();
@@ -172,4 +492,99 @@ mod tests {
         let collector = MetricsCollector::new();
         assert!(!collector.system.cpus().is_empty());
     }
+
+    #[tokio::test]
+    async fn should_describe_the_host() {
+        let mut collector = MetricsCollector::new();
+        let host = collector.collect().await.unwrap().host;
+
+        assert!(host.uptime_seconds > 0);
+        assert!(host.boot_time > 0);
+        assert!(host.os_version.is_some());
+        assert!(host.kernel_version.is_some());
+    }
+
+    #[tokio::test]
+    async fn should_skip_extras_not_requested() {
+        let mut collector = MetricsCollector::with_extras(Extras {
+            disk: false,
+            processes: false,
+            battery: false,
+            gpu: false,
+        });
+        let metrics = collector.collect().await.unwrap();
+
+        assert_eq!(metrics.disk_total_bytes, 0);
+        assert!(metrics.battery.is_none());
+        assert!(metrics.gpu.is_none());
+        assert!(metrics.memory_total_bytes > 0);
+        assert!(collector.system.processes().is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_follow_one_process() {
+        let mut collector = MetricsCollector::with_extras(Extras {
+            disk: false,
+            processes: false,
+            battery: false,
+            gpu: false,
+        });
+        let pid = std::process::id();
+        let sample = collector.collect_process(pid).await.unwrap().unwrap();
+
+        assert_eq!(sample.pid, pid);
+        assert!(sample.rss_bytes > 0);
+        let found = collector.find_processes(&sample.name).await.unwrap();
+        assert!(found.iter().any(|process| process.pid == pid));
+        assert!(collector.collect_process(u32::MAX).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn should_collect_top_processes() {
+        let mut collector = MetricsCollector::new();
+        let top = collector.collect_processes(3).await.unwrap();
+
+        assert!(!top.by_memory.is_empty() && top.by_memory.len() <= 3);
+        assert!(top.by_cpu.len() <= 3);
+        assert!(top
+            .by_memory
+            .windows(2)
+            .all(|pair| pair[0].rss_bytes >= pair[1].rss_bytes));
+    }
+
+    #[test]
+    fn test_top_processes_ranks_by_cpu_and_memory() {
+        let process = |pid, cpu_usage_percent, rss_bytes| ProcessMetrics {
+            pid,
+            name: format!("p{pid}"),
+            cpu_usage_percent,
+            rss_bytes,
+        };
+        let top = top_processes(
+            vec![
+                process(1, 5.0, 100),
+                process(2, 80.0, 10),
+                process(3, 0.0, 900),
+            ],
+            2,
+        );
+
+        let pids = |list: &[ProcessMetrics]| list.iter().map(|p| p.pid).collect::<Vec<_>>();
+        assert_eq!(pids(&top.by_cpu), [2, 1]);
+        assert_eq!(pids(&top.by_memory), [3, 1]);
+    }
+
+    #[tokio::test]
+    async fn should_measure_own_overhead() {
+        let mut collector = MetricsCollector::new();
+        assert_eq!(collector.overhead().samples, 0);
+
+        collector.collect().await.unwrap();
+        collector.collect().await.unwrap();
+        let overhead = collector.overhead();
+
+        assert_eq!(overhead.samples, 2);
+        assert!(overhead.max_micros >= overhead.mean_micros);
+        assert!(overhead.max_micros >= overhead.last_micros);
+    }
 }
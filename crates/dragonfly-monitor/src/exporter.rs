@@ -0,0 +1,240 @@
+//! Prometheus exporter
+//!
+//! A deliberately small HTTP/1.1 server answering `GET /metrics` with a
+//! fresh sample in the exposition format of [`crate::prometheus`], and 404
+//! for anything else. Each connection carries one request and is closed
+//! after the response, which is all a Prometheus scrape needs.
+//!
+//! Bound to a loopback address, the exporter also rejects requests whose
+//! `Host` header names another host, so a web page can't reach it through
+//! DNS rebinding. A client that doesn't finish its request within
+//! [`REQUEST_TIMEOUT`] is disconnected.
+
+use crate::collector::MetricsCollector;
+use crate::prometheus::{self, CONTENT_TYPE};
+use dragonfly_core::domain::is_local_host;
+use dragonfly_core::error::Result;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+
+/// Longest request head read before giving up on a connection
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+
+/// Longest wait for a request head before closing the connection
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Path the metrics are served on
+pub const METRICS_PATH: &str = "/metrics";
+
+/// Serve metrics from `collector` on `listener` until `shutdown` completes
+///
+/// # Errors
+///
+/// Fails if the listener's address can't be read. Errors on individual
+/// connections only end that connection.
+pub async fn serve(
+    listener: TcpListener,
+    collector: MetricsCollector,
+    shutdown: impl Future<Output = ()>,
+) -> Result<()> {
+    let local_only = listener.local_addr()?.ip().is_loopback();
+    let collector = Arc::new(Mutex::new(collector));
+    tokio::pin!(shutdown);
+    loop {
+        tokio::select! {
+            () = &mut shutdown => return Ok(()),
+            accepted = listener.accept() => {
+                let Ok((stream, _)) = accepted else {
+                    continue;
+                };
+                let collector = Arc::clone(&collector);
+                tokio::spawn(async move {
+                    if let Err(e) = handle(stream, &collector, local_only).await {
+                        tracing::debug!(error = %e, "Exporter connection failed");
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// Answer the one request on `stream`
+async fn handle(
+    mut stream: TcpStream,
+    collector: &Mutex<MetricsCollector>,
+    local_only: bool,
+) -> std::io::Result<()> {
+    let Ok(head) = tokio::time::timeout(REQUEST_TIMEOUT, read_head(&mut stream)).await else {
+        return stream.shutdown().await;
+    };
+    let head = head?;
+    let response = match parse_request(&head) {
+        None => response("400 Bad Request", "text/plain", "Bad request\n"),
+        Some(request) if local_only && !is_local_host(request.host) => {
+            response("403 Forbidden", "text/plain", "Forbidden\n")
+        }
+        Some(request) if request.method != "GET" && request.method != "HEAD" => response(
+            "405 Method Not Allowed",
+            "text/plain",
+            "Method not allowed\n",
+        ),
+        Some(request) if request.path != METRICS_PATH => {
+            response("404 Not Found", "text/plain", "Not found\n")
+        }
+        Some(request) => {
+            let mut collector = collector.lock().await;
+            let body = match collector.collect().await {
+                Ok(metrics) => prometheus::render(&metrics, collector.overhead()),
+                Err(e) => {
+                    let message = format!("Failed to collect metrics: {}\n", e);
+                    return write(
+                        &mut stream,
+                        response("500 Internal Server Error", "text/plain", &message),
+                    )
+                    .await;
+                }
+            };
+            let mut ok = response("200 OK", CONTENT_TYPE, &body);
+            if request.method == "HEAD" {
+                ok.truncate(ok.len() - body.len());
+            }
+            ok
+        }
+    };
+    write(&mut stream, response).await
+}
+
+async fn write(stream: &mut TcpStream, response: String) -> std::io::Result<()> {
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Read up to the blank line ending the request head
+async fn read_head(stream: &mut TcpStream) -> std::io::Result<String> {
+    let mut head = Vec::new();
+    let mut buf = [0; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        if head.len() > MAX_REQUEST_BYTES {
+            break;
+        }
+        let read = stream.read(&mut buf).await?;
+        if read == 0 {
+            break;
+        }
+        head.extend_from_slice(&buf[..read]);
+    }
+    Ok(String::from_utf8_lossy(&head).into_owned())
+}
+
+/// The parts of a request the exporter looks at
+#[derive(Debug, PartialEq, Eq)]
+struct Request<'a> {
+    method: &'a str,
+    path: &'a str,
+    host: &'a str,
+}
+
+/// Method, path without its query, and `Host` header of a request head
+fn parse_request(head: &str) -> Option<Request<'_>> {
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next()?.split(' ');
+    let method = request_line.next()?;
+    let target = request_line.next()?;
+    if !request_line.next()?.starts_with("HTTP/1.") {
+        return None;
+    }
+    let path = target.split('?').next().unwrap_or(target);
+    let host = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.eq_ignore_ascii_case("host"))
+        .map(|(_, value)| value.trim())
+        .unwrap_or_default();
+    Some(Request { method, path, host })
+}
+
+fn response(status: &str, content_type: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn get(addr: std::net::SocketAddr, request: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[test]
+    fn test_parse_request() {
+        let request =
+            parse_request("GET /metrics?x=1 HTTP/1.1\r\nhost: localhost:9095\r\n\r\n").unwrap();
+        assert_eq!(
+            request,
+            Request {
+                method: "GET",
+                path: "/metrics",
+                host: "localhost:9095"
+            }
+        );
+        assert!(parse_request("garbage").is_none());
+    }
+
+    #[tokio::test]
+    async fn should_serve_metrics_on_localhost() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(serve(listener, MetricsCollector::new(), async {
+            let _ = stopped.await;
+        }));
+
+        let metrics = get(addr, "GET /metrics HTTP/1.1\r\nHost: 127.0.0.1\r\n\r\n").await;
+        assert!(metrics.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(metrics.contains(CONTENT_TYPE));
+        assert!(metrics.contains("\ndragonfly_memory_total_bytes "));
+
+        let missing = get(addr, "GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").await;
+        assert!(missing.starts_with("HTTP/1.1 404"));
+        let rebound = get(addr, "GET /metrics HTTP/1.1\r\nHost: evil.example\r\n\r\n").await;
+        assert!(rebound.starts_with("HTTP/1.1 403"));
+
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_close_connections_that_never_send_a_request() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(serve(listener, MetricsCollector::new(), async {
+            let _ = stopped.await;
+        }));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\n")
+            .await
+            .unwrap();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await.unwrap();
+        assert!(response.is_empty());
+
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+    }
+}
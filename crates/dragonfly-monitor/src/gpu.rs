@@ -0,0 +1,165 @@
+//! GPU utilisation and memory
+//!
+//! macOS GPU drivers publish running statistics in the
+//! `PerformanceStatistics` property of their `IOAccelerator` entry in the
+//! IOKit registry, which any user may read. Other platforms, and builds
+//! without the `gpu` feature, read as `None`.
+
+use crate::metrics::GpuMetrics;
+
+/// The first GPU reporting statistics, if any
+#[cfg(all(feature = "gpu", target_os = "macos"))]
+pub(crate) fn read_gpu() -> Option<GpuMetrics> {
+    use core_foundation_sys::base::{
+        kCFAllocatorDefault, CFAllocatorRef, CFGetTypeID, CFRelease, CFTypeRef,
+    };
+    use core_foundation_sys::dictionary::{
+        CFDictionaryGetTypeID, CFDictionaryGetValue, CFDictionaryRef, CFMutableDictionaryRef,
+    };
+    use core_foundation_sys::number::{kCFNumberSInt64Type, CFNumberGetTypeID, CFNumberGetValue};
+    use core_foundation_sys::string::{
+        kCFStringEncodingUTF8, CFStringCreateWithCString, CFStringRef,
+    };
+    use std::ffi::{c_char, CString};
+
+    #[link(name = "IOKit", kind = "framework")]
+    extern "C" {
+        fn IOServiceMatching(name: *const c_char) -> CFMutableDictionaryRef;
+        fn IOServiceGetMatchingServices(
+            main_port: libc::mach_port_t,
+            matching: CFMutableDictionaryRef,
+            existing: *mut libc::mach_port_t,
+        ) -> libc::kern_return_t;
+        fn IOIteratorNext(iterator: libc::mach_port_t) -> libc::mach_port_t;
+        fn IORegistryEntryCreateCFProperty(
+            entry: libc::mach_port_t,
+            key: CFStringRef,
+            allocator: CFAllocatorRef,
+            options: u32,
+        ) -> CFTypeRef;
+        fn IOObjectRelease(object: libc::mach_port_t) -> libc::kern_return_t;
+    }
+
+    /// `text` as a CFString the caller releases
+    unsafe fn cf_string(text: &str) -> Option<CFStringRef> {
+        let text = CString::new(text).ok()?;
+        let string =
+            CFStringCreateWithCString(kCFAllocatorDefault, text.as_ptr(), kCFStringEncodingUTF8);
+        (!string.is_null()).then_some(string)
+    }
+
+    /// A number in `statistics`, which stays owned by the caller
+    unsafe fn number(statistics: CFDictionaryRef, key: &str) -> Option<u64> {
+        let key = cf_string(key)?;
+        let value = CFDictionaryGetValue(statistics, key.cast());
+        CFRelease(key.cast());
+        if value.is_null() || CFGetTypeID(value) != CFNumberGetTypeID() {
+            return None;
+        }
+        let mut number: i64 = 0;
+        CFNumberGetValue(
+            value.cast(),
+            kCFNumberSInt64Type,
+            (&mut number as *mut i64).cast(),
+        )
+        .then(|| number.max(0) as u64)
+    }
+
+    /// Utilisation and memory from the statistics of `accelerator`
+    unsafe fn statistics(accelerator: libc::mach_port_t) -> Option<GpuMetrics> {
+        let key = cf_string("PerformanceStatistics")?;
+        let statistics = IORegistryEntryCreateCFProperty(accelerator, key, kCFAllocatorDefault, 0);
+        CFRelease(key.cast());
+        if statistics.is_null() {
+            return None;
+        }
+        let gpu = if CFGetTypeID(statistics) == CFDictionaryGetTypeID() {
+            let dictionary: CFDictionaryRef = statistics.cast();
+            from_statistics(
+                number(dictionary, "Device Utilization %"),
+                number(dictionary, "In use system memory"),
+                number(dictionary, "Alloc system memory"),
+            )
+        } else {
+            None
+        };
+        CFRelease(statistics);
+        gpu
+    }
+
+    // SAFETY: the matching dictionary is consumed by
+    // IOServiceGetMatchingServices, and every entry and the iterator are
+    // released before returning
+    unsafe {
+        let mut iterator: libc::mach_port_t = 0;
+        // Port 0 asks for the default main port
+        if IOServiceGetMatchingServices(
+            0,
+            IOServiceMatching(b"IOAccelerator\0".as_ptr().cast()),
+            &mut iterator,
+        ) != 0
+        {
+            return None;
+        }
+        let mut gpu = None;
+        loop {
+            let accelerator = IOIteratorNext(iterator);
+            if accelerator == 0 {
+                break;
+            }
+            if gpu.is_none() {
+                gpu = statistics(accelerator);
+            }
+            IOObjectRelease(accelerator);
+        }
+        IOObjectRelease(iterator);
+        gpu
+    }
+}
+
+#[cfg(not(all(feature = "gpu", target_os = "macos")))]
+pub(crate) fn read_gpu() -> Option<GpuMetrics> {
+    None
+}
+
+/// Metrics from the `PerformanceStatistics` figures of a driver: `None`
+/// without a utilisation, which drivers of idle or virtual GPUs leave out
+#[cfg_attr(not(all(feature = "gpu", target_os = "macos")), allow(dead_code))]
+fn from_statistics(
+    utilization: Option<u64>,
+    in_use: Option<u64>,
+    allocated: Option<u64>,
+) -> Option<GpuMetrics> {
+    utilization.map(|utilization| GpuMetrics {
+        utilization_percent: utilization.min(100) as f32,
+        memory_in_use_bytes: in_use.unwrap_or(0),
+        memory_allocated_bytes: allocated.unwrap_or(0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_statistics() {
+        assert_eq!(
+            from_statistics(Some(42), Some(1_000), Some(4_000)),
+            Some(GpuMetrics {
+                utilization_percent: 42.0,
+                memory_in_use_bytes: 1_000,
+                memory_allocated_bytes: 4_000,
+            })
+        );
+        // Some drivers briefly report more than 100%
+        assert_eq!(
+            from_statistics(Some(130), None, None),
+            Some(GpuMetrics {
+                utilization_percent: 100.0,
+                memory_in_use_bytes: 0,
+                memory_allocated_bytes: 0,
+            })
+        );
+        assert_eq!(from_statistics(None, Some(1_000), Some(4_000)), None);
+    }
+}
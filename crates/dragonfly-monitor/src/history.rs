@@ -0,0 +1,467 @@
+//! Metrics history
+//!
+//! Samples taken by `monitor` and `health` are recorded in
+//! `~/.dragonfly/metrics.db`, so CPU, memory and disk use can be looked back
+//! on later. The store is a ring buffer: it keeps the latest
+//! [`DEFAULT_CAPACITY`] samples and at most one per [`RECORD_INTERVAL`], so a
+//! monitor left running at one-second updates neither floods nor grows it.
+//! [`HistorySummary`] turns a period of samples into ranges and daily rates.
+//! [`RecentSamples`] keeps the latest few in memory instead, for trends over
+//! a single monitor session.
+
+use crate::metrics::SystemMetrics;
+use chrono::{DateTime, Duration, SecondsFormat, TimeZone, Utc};
+use dragonfly_core::error::{Error, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS samples (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        recorded_at TEXT NOT NULL,
+        cpu_usage_percent REAL NOT NULL,
+        load_one REAL NOT NULL,
+        memory_used INTEGER NOT NULL,
+        memory_total INTEGER NOT NULL,
+        swap_used INTEGER NOT NULL,
+        disk_used INTEGER NOT NULL,
+        disk_total INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS samples_by_time ON samples (recorded_at);
+";
+
+/// Samples kept before the oldest are dropped; about five weeks at one a
+/// minute
+pub const DEFAULT_CAPACITY: usize = 50_000;
+
+/// Samples closer together than this are not recorded
+pub const RECORD_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Rates per day are only worked out over at least this long, since a few
+/// minutes of change says little about a day
+const MIN_RATE_SPAN_SECONDS: i64 = 3600;
+
+/// One recorded sample of system metrics
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct MetricsSample {
+    /// When the metrics were collected
+    pub at: DateTime<Utc>,
+    /// CPU usage percentage
+    pub cpu_usage_percent: f32,
+    /// One-minute load average
+    pub load_one: f64,
+    /// Used memory in bytes
+    pub memory_used_bytes: u64,
+    /// Total memory in bytes
+    pub memory_total_bytes: u64,
+    /// Used swap in bytes
+    pub swap_used_bytes: u64,
+    /// Used disk space in bytes
+    pub disk_used_bytes: u64,
+    /// Total disk space in bytes
+    pub disk_total_bytes: u64,
+}
+
+impl MetricsSample {
+    /// The recorded part of `metrics`
+    pub fn of(metrics: &SystemMetrics) -> Self {
+        Self {
+            at: Utc
+                .timestamp_opt(metrics.timestamp as i64, 0)
+                .single()
+                .unwrap_or_else(Utc::now),
+            cpu_usage_percent: metrics.cpu_usage_percent,
+            load_one: metrics.load_average.one,
+            memory_used_bytes: metrics.memory_used_bytes,
+            memory_total_bytes: metrics.memory_total_bytes,
+            swap_used_bytes: metrics.swap_used_bytes,
+            disk_used_bytes: metrics.disk_used_bytes,
+            disk_total_bytes: metrics.disk_total_bytes,
+        }
+    }
+
+    /// Memory usage percentage
+    pub fn memory_usage_percent(&self) -> f64 {
+        percent(self.memory_used_bytes, self.memory_total_bytes)
+    }
+
+    /// Disk usage percentage
+    pub fn disk_usage_percent(&self) -> f64 {
+        percent(self.disk_used_bytes, self.disk_total_bytes)
+    }
+}
+
+/// The latest samples of a session, oldest first, dropping the oldest once
+/// full
+#[derive(Debug, Clone, Default)]
+pub struct RecentSamples {
+    samples: VecDeque<MetricsSample>,
+    capacity: usize,
+}
+
+impl RecentSamples {
+    /// Keep at most `capacity` samples
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Most samples kept
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Add a sample of `metrics`, dropping the oldest if full
+    pub fn push(&mut self, metrics: &SystemMetrics) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(MetricsSample::of(metrics));
+    }
+
+    /// Samples kept, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &MetricsSample> {
+        self.samples.iter()
+    }
+}
+
+/// Recorded metrics samples
+#[derive(Debug)]
+pub struct MetricsHistory {
+    conn: Connection,
+    capacity: usize,
+    min_interval: Duration,
+}
+
+impl MetricsHistory {
+    /// Default history location (`~/.dragonfly/metrics.db`)
+    pub fn default_path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_else(|| PathBuf::from("~"))
+            .join(".dragonfly")
+            .join("metrics.db")
+    }
+
+    /// Open the history at `path`, creating it if needed
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path).map_err(db_error)?;
+        conn.execute_batch(SCHEMA).map_err(db_error)?;
+        Ok(Self {
+            conn,
+            capacity: DEFAULT_CAPACITY,
+            min_interval: Duration::from_std(RECORD_INTERVAL).unwrap_or_else(|_| Duration::zero()),
+        })
+    }
+
+    /// Keep only the latest `capacity` samples
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Record samples at most once per `interval`
+    pub fn with_min_interval(mut self, interval: std::time::Duration) -> Self {
+        self.min_interval = Duration::from_std(interval).unwrap_or_else(|_| Duration::zero());
+        self
+    }
+
+    /// Record `metrics`, dropping the oldest samples beyond the capacity
+    ///
+    /// Returns `false` without recording if the latest sample is more recent
+    /// than the minimum interval.
+    pub fn record(&self, metrics: &SystemMetrics) -> Result<bool> {
+        let sample = MetricsSample::of(metrics);
+        let latest: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT recorded_at FROM samples ORDER BY id DESC LIMIT 1",
+                [],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(db_error)?;
+        if let Some(latest) = latest {
+            if sample.at - parse_timestamp(&latest)? < self.min_interval {
+                return Ok(false);
+            }
+        }
+
+        let tx = self.conn.unchecked_transaction().map_err(db_error)?;
+        tx.execute(
+            "INSERT INTO samples (recorded_at, cpu_usage_percent, load_one, memory_used,
+                memory_total, swap_used, disk_used, disk_total)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                timestamp(sample.at),
+                sample.cpu_usage_percent,
+                sample.load_one,
+                sample.memory_used_bytes as i64,
+                sample.memory_total_bytes as i64,
+                sample.swap_used_bytes as i64,
+                sample.disk_used_bytes as i64,
+                sample.disk_total_bytes as i64
+            ],
+        )
+        .map_err(db_error)?;
+        tx.execute(
+            "DELETE FROM samples WHERE id <= (SELECT MAX(id) FROM samples) - ?1",
+            params![self.capacity as i64],
+        )
+        .map_err(db_error)?;
+        tx.commit().map_err(db_error)?;
+        Ok(true)
+    }
+
+    /// Samples recorded since `since`, oldest first
+    pub fn samples(&self, since: DateTime<Utc>) -> Result<Vec<MetricsSample>> {
+        let mut statement = self
+            .conn
+            .prepare(
+                "SELECT recorded_at, cpu_usage_percent, load_one, memory_used, memory_total,
+                    swap_used, disk_used, disk_total
+                 FROM samples WHERE recorded_at >= ?1 ORDER BY id",
+            )
+            .map_err(db_error)?;
+        let rows = statement
+            .query_map(params![timestamp(since)], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, f32>(1)?,
+                    row.get::<_, f64>(2)?,
+                    [
+                        row.get::<_, i64>(3)?,
+                        row.get::<_, i64>(4)?,
+                        row.get::<_, i64>(5)?,
+                        row.get::<_, i64>(6)?,
+                        row.get::<_, i64>(7)?,
+                    ],
+                ))
+            })
+            .map_err(db_error)?;
+
+        let mut samples = Vec::new();
+        for row in rows {
+            let (at, cpu_usage_percent, load_one, bytes) = row.map_err(db_error)?;
+            let [memory_used, memory_total, swap_used, disk_used, disk_total] =
+                bytes.map(|value| value.max(0) as u64);
+            samples.push(MetricsSample {
+                at: parse_timestamp(&at)?,
+                cpu_usage_percent,
+                load_one,
+                memory_used_bytes: memory_used,
+                memory_total_bytes: memory_total,
+                swap_used_bytes: swap_used,
+                disk_used_bytes: disk_used,
+                disk_total_bytes: disk_total,
+            });
+        }
+        Ok(samples)
+    }
+}
+
+/// Range of one metric over a period
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Series {
+    /// Value of the oldest sample
+    pub first: f64,
+    /// Value of the latest sample
+    pub last: f64,
+    /// Lowest value
+    pub min: f64,
+    /// Highest value
+    pub max: f64,
+    /// Mean value
+    pub mean: f64,
+    /// Average change per day from the first sample to the last, if they're
+    /// far enough apart to say
+    pub per_day: Option<f64>,
+}
+
+impl Series {
+    fn of(samples: &[MetricsSample], value: impl Fn(&MetricsSample) -> f64) -> Self {
+        let values: Vec<f64> = samples.iter().map(&value).collect();
+        let first = values.first().copied().unwrap_or(0.0);
+        let last = values.last().copied().unwrap_or(0.0);
+        let span = match (samples.first(), samples.last()) {
+            (Some(first), Some(last)) => (last.at - first.at).num_seconds(),
+            _ => 0,
+        };
+        Self {
+            first,
+            last,
+            min: values.iter().copied().fold(f64::INFINITY, f64::min),
+            max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            mean: values.iter().sum::<f64>() / values.len().max(1) as f64,
+            per_day: (span >= MIN_RATE_SPAN_SECONDS)
+                .then(|| (last - first) / (span as f64 / 86_400.0)),
+        }
+    }
+}
+
+/// How CPU, memory and disk use developed over a period
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistorySummary {
+    /// Number of samples summarised
+    pub samples: usize,
+    /// Time of the oldest sample
+    pub from: DateTime<Utc>,
+    /// Time of the latest sample
+    pub to: DateTime<Utc>,
+    /// CPU usage percentage
+    pub cpu_percent: Series,
+    /// One-minute load average
+    pub load_one: Series,
+    /// Memory usage percentage
+    pub memory_percent: Series,
+    /// Disk usage percentage
+    pub disk_percent: Series,
+    /// Used disk space in bytes
+    pub disk_used_bytes: Series,
+}
+
+impl HistorySummary {
+    /// Summary of `samples` (oldest first), or `None` if there are none
+    pub fn of(samples: &[MetricsSample]) -> Option<Self> {
+        Some(Self {
+            samples: samples.len(),
+            from: samples.first()?.at,
+            to: samples.last()?.at,
+            cpu_percent: Series::of(samples, |s| f64::from(s.cpu_usage_percent)),
+            load_one: Series::of(samples, |s| s.load_one),
+            memory_percent: Series::of(samples, MetricsSample::memory_usage_percent),
+            disk_percent: Series::of(samples, MetricsSample::disk_usage_percent),
+            disk_used_bytes: Series::of(samples, |s| s.disk_used_bytes as f64),
+        })
+    }
+}
+
+fn percent(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        return 0.0;
+    }
+    part as f64 / whole as f64 * 100.0
+}
+
+fn timestamp(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn parse_timestamp(value: &str) -> Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|date| date.with_timezone(&Utc))
+        .map_err(|e| Error::Internal(format!("Invalid metrics timestamp {}: {}", value, e)))
+}
+
+fn db_error(error: rusqlite::Error) -> Error {
+    Error::Internal(format!("Metrics history database error: {}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::{HostInfo, LoadAverage};
+    use tempfile::TempDir;
+
+    fn metrics(seconds_ago: i64, cpu: f32, disk_used: u64) -> SystemMetrics {
+        let at = (Utc::now() - Duration::seconds(seconds_ago)).timestamp() as u64;
+        SystemMetrics::new(
+            cpu,
+            vec![cpu],
+            LoadAverage::default(),
+            1000,
+            400,
+            600,
+            None,
+            0,
+            0,
+            1000,
+            disk_used,
+            1000 - disk_used,
+            None,
+            None,
+            HostInfo::default(),
+            0,
+            0,
+            at,
+        )
+    }
+
+    #[test]
+    fn test_recent_samples_keep_the_latest() {
+        let mut recent = RecentSamples::new(2);
+        for cpu in [10.0, 20.0, 30.0] {
+            recent.push(&metrics(0, cpu, 500));
+        }
+        let cpu: Vec<f32> = recent.iter().map(|s| s.cpu_usage_percent).collect();
+        assert_eq!(cpu, vec![20.0, 30.0]);
+
+        let mut none = RecentSamples::new(0);
+        none.push(&metrics(0, 10.0, 500));
+        assert_eq!(none.iter().count(), 0);
+    }
+
+    #[test]
+    fn test_record_skips_samples_too_close_together() {
+        let temp_dir = TempDir::new().unwrap();
+        let history = MetricsHistory::open(&temp_dir.path().join("state/metrics.db")).unwrap();
+
+        assert!(history.record(&metrics(300, 10.0, 500)).unwrap());
+        assert!(!history.record(&metrics(290, 20.0, 500)).unwrap());
+        assert!(history.record(&metrics(200, 30.0, 510)).unwrap());
+
+        let samples = history.samples(Utc::now() - Duration::hours(1)).unwrap();
+        let cpu: Vec<f32> = samples.iter().map(|s| s.cpu_usage_percent).collect();
+        assert_eq!(cpu, [10.0, 30.0]);
+        assert_eq!(samples[1].disk_used_bytes, 510);
+        assert!(history.samples(Utc::now()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_record_drops_oldest_beyond_capacity() {
+        let temp_dir = TempDir::new().unwrap();
+        let history = MetricsHistory::open(&temp_dir.path().join("metrics.db"))
+            .unwrap()
+            .with_capacity(3)
+            .with_min_interval(std::time::Duration::ZERO);
+
+        for (i, cpu) in [1.0, 2.0, 3.0, 4.0, 5.0].into_iter().enumerate() {
+            history.record(&metrics(50 - i as i64, cpu, 500)).unwrap();
+        }
+
+        let samples = history.samples(Utc::now() - Duration::hours(1)).unwrap();
+        let cpu: Vec<f32> = samples.iter().map(|s| s.cpu_usage_percent).collect();
+        assert_eq!(cpu, [3.0, 4.0, 5.0]);
+    }
+
+    #[test]
+    fn test_summary_reports_ranges_and_daily_rates() {
+        let samples: Vec<MetricsSample> =
+            [(2 * 86_400, 10.0, 500), (86_400, 50.0, 510), (0, 30.0, 520)]
+                .iter()
+                .map(|&(ago, cpu, disk)| MetricsSample::of(&metrics(ago, cpu, disk)))
+                .collect();
+
+        let summary = HistorySummary::of(&samples).unwrap();
+        assert_eq!(summary.samples, 3);
+        assert_eq!(summary.cpu_percent.max, 50.0);
+        assert_eq!(summary.cpu_percent.mean, 30.0);
+        assert_eq!(summary.disk_percent.first, 50.0);
+        let per_day = summary.disk_percent.per_day.unwrap();
+        assert!((per_day - 1.0).abs() < 0.01);
+
+        // Minutes apart is too short to extrapolate a day from
+        let recent = HistorySummary::of(&samples[2..]).unwrap();
+        assert_eq!(recent.disk_percent.per_day, None);
+        assert!(HistorySummary::of(&[]).is_none());
+    }
+}
@@ -0,0 +1,137 @@
+//! The machine samples are taken on
+//!
+//! sysinfo reports uptime, boot time and the OS and kernel versions. The
+//! hardware model comes from the `hw.model` sysctl on macOS (`Mac14,2`) and
+//! from the firmware's DMI or device tree entries on Linux.
+
+use crate::metrics::HostInfo;
+use sysinfo::System;
+
+/// What doesn't change while the machine is up: OS, kernel and model
+pub(crate) fn read_host() -> HostInfo {
+    HostInfo {
+        uptime_seconds: 0,
+        boot_time: System::boot_time(),
+        os_version: System::long_os_version(),
+        kernel_version: System::kernel_version(),
+        hardware_model: hardware_model(),
+    }
+}
+
+/// Seconds since boot
+pub(crate) fn uptime() -> u64 {
+    System::uptime()
+}
+
+#[cfg(target_os = "macos")]
+fn hardware_model() -> Option<String> {
+    let name = b"hw.model\0";
+    let mut size: libc::size_t = 0;
+    // SAFETY: the first call only asks for the size, and the second writes
+    // at most `size` bytes into a buffer that long
+    unsafe {
+        if libc::sysctlbyname(
+            name.as_ptr().cast(),
+            std::ptr::null_mut(),
+            &mut size,
+            std::ptr::null_mut(),
+            0,
+        ) != 0
+        {
+            return None;
+        }
+        let mut model = vec![0u8; size];
+        if libc::sysctlbyname(
+            name.as_ptr().cast(),
+            model.as_mut_ptr().cast(),
+            &mut size,
+            std::ptr::null_mut(),
+            0,
+        ) != 0
+        {
+            return None;
+        }
+        model.truncate(size);
+        clean_model(&String::from_utf8_lossy(&model))
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn hardware_model() -> Option<String> {
+    read_model(std::path::Path::new("/sys"))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn hardware_model() -> Option<String> {
+    None
+}
+
+/// The model named by the firmware under the sysfs mount `sys`: DMI on PCs,
+/// the device tree on boards such as the Raspberry Pi
+#[cfg(target_os = "linux")]
+fn read_model(sys: &std::path::Path) -> Option<String> {
+    [
+        "devices/virtual/dmi/id/product_name",
+        "firmware/devicetree/base/model",
+    ]
+    .iter()
+    .find_map(|entry| clean_model(&std::fs::read_to_string(sys.join(entry)).ok()?))
+}
+
+/// `model` without padding and NULs, unless it's a firmware placeholder
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn clean_model(model: &str) -> Option<String> {
+    let model = model.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    let placeholder = model.is_empty()
+        || model.eq_ignore_ascii_case("To Be Filled By O.E.M.")
+        || model.eq_ignore_ascii_case("System Product Name");
+    (!placeholder).then(|| model.to_string())
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(sys: &std::path::Path, entry: &str, value: &str) {
+        let path = sys.join(entry);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, value).unwrap();
+    }
+
+    #[test]
+    fn test_reads_model_from_firmware() {
+        let sys = TempDir::new().unwrap();
+        assert_eq!(read_model(sys.path()), None);
+
+        write(
+            sys.path(),
+            "firmware/devicetree/base/model",
+            "Raspberry Pi 4 Model B Rev 1.4\0",
+        );
+        assert_eq!(
+            read_model(sys.path()).as_deref(),
+            Some("Raspberry Pi 4 Model B Rev 1.4")
+        );
+
+        // DMI wins, unless it's left as a placeholder
+        write(
+            sys.path(),
+            "devices/virtual/dmi/id/product_name",
+            "To Be Filled By O.E.M.\n",
+        );
+        assert_eq!(
+            read_model(sys.path()).as_deref(),
+            Some("Raspberry Pi 4 Model B Rev 1.4")
+        );
+        write(
+            sys.path(),
+            "devices/virtual/dmi/id/product_name",
+            "ThinkPad X1 Carbon\n",
+        );
+        assert_eq!(
+            read_model(sys.path()).as_deref(),
+            Some("ThinkPad X1 Carbon")
+        );
+    }
+}
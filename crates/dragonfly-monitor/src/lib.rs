@@ -1,7 +1,11 @@
 //! System Monitoring Module
 //!
-//! This module provides real-time system metrics collection and monitoring.
-//! Collects CPU, memory, disk, and network statistics.
+//! This module provides real-time system metrics collection and monitoring,
+//! threshold alerts, and a history of samples to look back on.
+//! Collects CPU (overall and per core), load average, memory, disk, battery,
+//! uptime, host and network statistics, and GPU statistics with the `gpu`
+//! feature. [`MonitorRepository`] answers the core's `SystemRepository` port
+//! from them.
 
 #![warn(
     missing_docs,
@@ -9,11 +13,27 @@
     missing_copy_implementations
 )]
 
+pub mod alert;
+mod battery;
 pub mod collector;
+#[cfg(feature = "exporter")]
+pub mod exporter;
+mod gpu;
+pub mod history;
+mod host;
 pub mod metrics;
+mod process;
+pub mod prometheus;
+pub mod repository;
 
-pub use collector::MetricsCollector;
-pub use metrics::SystemMetrics;
+pub use alert::{AlertEvent, AlertMetric, AlertRule, AlertTransition, Alerts, Comparison};
+pub use collector::{Extras, MetricsCollector, CPU_SAMPLE_INTERVAL};
+pub use history::{HistorySummary, MetricsHistory, MetricsSample, RecentSamples, Series};
+pub use metrics::{
+    BatteryMetrics, ChargingState, CollectorOverhead, GpuMetrics, HostInfo, LoadAverage,
+    MemoryBreakdown, MemoryPressure, ProcessMetrics, ProcessSample, SystemMetrics, TopProcesses,
+};
+pub use repository::MonitorRepository;
 
 /// Module version
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
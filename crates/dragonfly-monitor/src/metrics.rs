@@ -3,16 +3,24 @@
 use serde::{Deserialize, Serialize};
 
 /// System metrics snapshot
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SystemMetrics {
     /// CPU usage percentage
     pub cpu_usage_percent: f32,
+    /// CPU usage percentage of each core, in the order the OS numbers them
+    pub cpu_per_core_percent: Vec<f32>,
+    /// Run-queue load averages
+    pub load_average: LoadAverage,
     /// Total memory in bytes
     pub memory_total_bytes: u64,
     /// Used memory in bytes
     pub memory_used_bytes: u64,
     /// Available memory in bytes
     pub memory_available_bytes: u64,
+    /// What used memory is made of and how hard the OS is working to free
+    /// it; only on macOS, where "used" counts caches the OS gives back freely
+    #[serde(default)]
+    pub memory_breakdown: Option<MemoryBreakdown>,
     /// Total swap in bytes
     pub swap_total_bytes: u64,
     /// Used swap in bytes
@@ -23,6 +31,16 @@ pub struct SystemMetrics {
     pub disk_used_bytes: u64,
     /// Available disk space in bytes
     pub disk_available_bytes: u64,
+    /// The internal battery; `None` on machines without one
+    #[serde(default)]
+    pub battery: Option<BatteryMetrics>,
+    /// Utilisation and memory of the GPU; only on macOS in builds with the
+    /// `gpu` feature
+    #[serde(default)]
+    pub gpu: Option<GpuMetrics>,
+    /// The machine, and how long it has been up
+    #[serde(default)]
+    pub host: HostInfo,
     /// Network received bytes
     pub network_rx_bytes: u64,
     /// Network transmitted bytes
@@ -36,28 +54,40 @@ impl SystemMetrics {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         cpu_usage_percent: f32,
+        cpu_per_core_percent: Vec<f32>,
+        load_average: LoadAverage,
         memory_total_bytes: u64,
         memory_used_bytes: u64,
         memory_available_bytes: u64,
+        memory_breakdown: Option<MemoryBreakdown>,
         swap_total_bytes: u64,
         swap_used_bytes: u64,
         disk_total_bytes: u64,
         disk_used_bytes: u64,
         disk_available_bytes: u64,
+        battery: Option<BatteryMetrics>,
+        gpu: Option<GpuMetrics>,
+        host: HostInfo,
         network_rx_bytes: u64,
         network_tx_bytes: u64,
         timestamp: u64,
     ) -> Self {
         Self {
             cpu_usage_percent,
+            cpu_per_core_percent,
+            load_average,
             memory_total_bytes,
             memory_used_bytes,
             memory_available_bytes,
+            memory_breakdown,
             swap_total_bytes,
             swap_used_bytes,
             disk_total_bytes,
             disk_used_bytes,
             disk_available_bytes,
+            battery,
+            gpu,
+            host,
             network_rx_bytes,
             network_tx_bytes,
             timestamp,
@@ -82,3 +112,159 @@ impl SystemMetrics {
         (self.disk_used_bytes as f32 / self.disk_total_bytes as f32) * 100.0
     }
 }
+
+/// How hard the OS is working to find free memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryPressure {
+    /// Memory is available without reclaiming anything in use
+    Normal,
+    /// The OS is compressing memory and asking apps to free caches
+    Warning,
+    /// The OS is swapping and may terminate apps to free memory
+    Critical,
+}
+
+/// Used memory split the way Activity Monitor shows it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryBreakdown {
+    /// The kernel's memory pressure level
+    pub pressure: MemoryPressure,
+    /// Memory apps have allocated, less what they marked purgeable
+    pub app_bytes: u64,
+    /// Memory the kernel keeps resident and can't compress or swap
+    pub wired_bytes: u64,
+    /// Memory held by the compressor, after compression
+    pub compressed_bytes: u64,
+    /// File caches and purgeable memory, given back as soon as apps need it
+    pub cached_bytes: u64,
+}
+
+/// Whether a battery is taking charge
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChargingState {
+    /// Plugged in and charging
+    Charging,
+    /// Running on battery
+    Discharging,
+    /// Plugged in and fully charged
+    Full,
+    /// Plugged in but holding its charge, as macOS does to spare the battery
+    NotCharging,
+}
+
+/// Charge and wear of a battery
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BatteryMetrics {
+    /// Charge left, as a percentage of a full charge
+    pub charge_percent: f32,
+    /// Whether it's charging
+    pub state: ChargingState,
+    /// Charge cycles completed, where the battery reports them
+    pub cycle_count: Option<u32>,
+    /// Capacity of a full charge as a percentage of the design capacity,
+    /// falling as the battery wears
+    pub health_percent: Option<f32>,
+}
+
+/// The machine a sample was taken on and how long it has been up
+///
+/// Figures the platform doesn't report are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HostInfo {
+    /// Seconds since boot
+    pub uptime_seconds: u64,
+    /// When the machine booted (Unix epoch seconds)
+    pub boot_time: u64,
+    /// OS name and version, e.g. `macOS 14.5 Sonoma`
+    pub os_version: Option<String>,
+    /// Kernel version, e.g. `23.5.0`
+    pub kernel_version: Option<String>,
+    /// Hardware model, e.g. `Mac14,2`
+    pub hardware_model: Option<String>,
+}
+
+/// How busy the GPU is and the memory it holds
+///
+/// On Apple silicon the GPU shares system memory, so its memory is part of
+/// the used memory figure rather than separate VRAM.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GpuMetrics {
+    /// Share of time the GPU was busy, in percent
+    pub utilization_percent: f32,
+    /// Memory the GPU's allocations are using now
+    pub memory_in_use_bytes: u64,
+    /// Memory allocated to the GPU, including what it isn't using
+    pub memory_allocated_bytes: u64,
+}
+
+/// Average number of processes running or waiting to run over the last 1, 5
+/// and 15 minutes
+///
+/// Always zero on Windows, which keeps no such figure.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct LoadAverage {
+    /// Over the last minute
+    pub one: f64,
+    /// Over the last five minutes
+    pub five: f64,
+    /// Over the last fifteen minutes
+    pub fifteen: f64,
+}
+
+/// Resource use of one process
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessMetrics {
+    /// Process ID
+    pub pid: u32,
+    /// Executable name
+    pub name: String,
+    /// CPU usage since the previous refresh; over 100% for a process busy on
+    /// several cores
+    pub cpu_usage_percent: f32,
+    /// Resident memory in bytes
+    pub rss_bytes: u64,
+}
+
+/// One sample of a single process followed over time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessSample {
+    /// Process ID
+    pub pid: u32,
+    /// Executable name
+    pub name: String,
+    /// CPU usage since the previous sample; over 100% for a process busy on
+    /// several cores
+    pub cpu_usage_percent: f32,
+    /// Resident memory in bytes
+    pub rss_bytes: u64,
+    /// Threads running, where the OS lets us count them
+    pub threads: Option<u32>,
+    /// Open file descriptors, where the OS lets us count them
+    pub open_files: Option<u32>,
+    /// Timestamp (Unix epoch seconds)
+    pub timestamp: u64,
+}
+
+/// The processes using the most CPU and the most memory
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TopProcesses {
+    /// Busiest processes first
+    pub by_cpu: Vec<ProcessMetrics>,
+    /// Largest processes first
+    pub by_memory: Vec<ProcessMetrics>,
+}
+
+/// Time a collector spends gathering metrics, in microseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CollectorOverhead {
+    /// Number of collections timed
+    pub samples: u64,
+    /// Duration of the latest collection
+    pub last_micros: u64,
+    /// Mean duration of a collection
+    pub mean_micros: u64,
+    /// Longest collection
+    pub max_micros: u64,
+}
@@ -0,0 +1,97 @@
+//! Thread and open file counts of a process
+//!
+//! sysinfo doesn't report either, so they come from `/proc/<pid>` on Linux
+//! and `proc_pidinfo` on macOS. Both read as `None` for a process the user
+//! may not inspect, and on other platforms.
+
+/// Number of threads `pid` is running
+#[cfg(target_os = "linux")]
+pub(crate) fn thread_count(pid: u32) -> Option<u32> {
+    let status = std::fs::read_to_string(format!("/proc/{pid}/status")).ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("Threads:"))?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Number of file descriptors `pid` has open
+#[cfg(target_os = "linux")]
+pub(crate) fn open_files(pid: u32) -> Option<u32> {
+    let entries = std::fs::read_dir(format!("/proc/{pid}/fd")).ok()?;
+    u32::try_from(entries.count()).ok()
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) fn thread_count(pid: u32) -> Option<u32> {
+    let pid = libc::c_int::try_from(pid).ok()?;
+    let size = std::mem::size_of::<libc::proc_taskinfo>() as libc::c_int;
+    // SAFETY: proc_pidinfo writes at most `size` bytes into the zeroed info
+    unsafe {
+        let mut info: libc::proc_taskinfo = std::mem::zeroed();
+        let written = libc::proc_pidinfo(
+            pid,
+            libc::PROC_PIDTASKINFO,
+            0,
+            (&mut info as *mut libc::proc_taskinfo).cast(),
+            size,
+        );
+        if written != size {
+            return None;
+        }
+        u32::try_from(info.pti_threadnum).ok()
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) fn open_files(pid: u32) -> Option<u32> {
+    let pid = libc::c_int::try_from(pid).ok()?;
+    // SAFETY: the first call only asks for the buffer size, and the second
+    // writes at most the buffer's length in bytes
+    unsafe {
+        // Without a buffer, the size needed now, with room for a few more
+        let needed = libc::proc_pidinfo(pid, libc::PROC_PIDLISTFDS, 0, std::ptr::null_mut(), 0);
+        if needed <= 0 {
+            return None;
+        }
+        let mut fds: Vec<libc::proc_fdinfo> =
+            Vec::with_capacity(needed as usize / libc::PROC_PIDLISTFD_SIZE as usize);
+        let written = libc::proc_pidinfo(
+            pid,
+            libc::PROC_PIDLISTFDS,
+            0,
+            fds.as_mut_ptr().cast(),
+            (fds.capacity() as libc::c_int) * libc::PROC_PIDLISTFD_SIZE,
+        );
+        if written <= 0 {
+            return None;
+        }
+        u32::try_from(written / libc::PROC_PIDLISTFD_SIZE).ok()
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub(crate) fn thread_count(_pid: u32) -> Option<u32> {
+    None
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub(crate) fn open_files(_pid: u32) -> Option<u32> {
+    None
+}
+
+#[cfg(all(test, any(target_os = "linux", target_os = "macos")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_own_threads_and_files() {
+        let pid = std::process::id();
+        let _file = std::fs::File::open(env!("CARGO_MANIFEST_DIR")).unwrap();
+        assert!(thread_count(pid).unwrap() >= 1);
+        // stdin, stdout, stderr and the file above at least
+        assert!(open_files(pid).unwrap() >= 4);
+        assert_eq!(thread_count(u32::MAX), None);
+    }
+}
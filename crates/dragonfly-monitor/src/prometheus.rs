@@ -0,0 +1,322 @@
+//! Prometheus text exposition format
+//!
+//! [`render`] writes a [`SystemMetrics`] snapshot as the plain-text format
+//! Prometheus scrapes (version 0.0.4), one `dragonfly_`-prefixed gauge per
+//! figure, with labels for per-core usage, load average periods and the
+//! parts of macOS and GPU memory. Figures a platform doesn't report are left
+//! out rather than exported as zero.
+
+use crate::metrics::{ChargingState, CollectorOverhead, MemoryPressure, SystemMetrics};
+use std::fmt::Write;
+
+/// Content type of the exposition format
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// `metrics` and the collector's `overhead` in the exposition format
+pub fn render(metrics: &SystemMetrics, overhead: CollectorOverhead) -> String {
+    let mut out = String::new();
+    gauge(
+        &mut out,
+        "dragonfly_cpu_usage_percent",
+        "CPU usage across all cores",
+        f64::from(metrics.cpu_usage_percent),
+    );
+    let cores: Vec<(String, f64)> = metrics
+        .cpu_per_core_percent
+        .iter()
+        .enumerate()
+        .map(|(core, usage)| (format!("core=\"{}\"", core), f64::from(*usage)))
+        .collect();
+    gauge_labelled(
+        &mut out,
+        "dragonfly_cpu_core_usage_percent",
+        "CPU usage of each core",
+        &cores,
+    );
+    let load = metrics.load_average;
+    gauge_labelled(
+        &mut out,
+        "dragonfly_load_average",
+        "Run-queue load average",
+        &[
+            ("period=\"1m\"".to_string(), load.one),
+            ("period=\"5m\"".to_string(), load.five),
+            ("period=\"15m\"".to_string(), load.fifteen),
+        ],
+    );
+
+    for (name, help, value) in [
+        (
+            "dragonfly_memory_total_bytes",
+            "Total memory",
+            metrics.memory_total_bytes,
+        ),
+        (
+            "dragonfly_memory_used_bytes",
+            "Used memory",
+            metrics.memory_used_bytes,
+        ),
+        (
+            "dragonfly_memory_available_bytes",
+            "Available memory",
+            metrics.memory_available_bytes,
+        ),
+        (
+            "dragonfly_swap_total_bytes",
+            "Total swap",
+            metrics.swap_total_bytes,
+        ),
+        (
+            "dragonfly_swap_used_bytes",
+            "Used swap",
+            metrics.swap_used_bytes,
+        ),
+        (
+            "dragonfly_disk_total_bytes",
+            "Size of the root volume",
+            metrics.disk_total_bytes,
+        ),
+        (
+            "dragonfly_disk_used_bytes",
+            "Used space on the root volume",
+            metrics.disk_used_bytes,
+        ),
+        (
+            "dragonfly_disk_available_bytes",
+            "Available space on the root volume",
+            metrics.disk_available_bytes,
+        ),
+    ] {
+        gauge(&mut out, name, help, value as f64);
+    }
+
+    if let Some(breakdown) = metrics.memory_breakdown {
+        let level = match breakdown.pressure {
+            MemoryPressure::Normal => 0.0,
+            MemoryPressure::Warning => 1.0,
+            MemoryPressure::Critical => 2.0,
+        };
+        gauge(
+            &mut out,
+            "dragonfly_memory_pressure_level",
+            "Kernel memory pressure: 0 normal, 1 warning, 2 critical",
+            level,
+        );
+        gauge_labelled(
+            &mut out,
+            "dragonfly_memory_breakdown_bytes",
+            "Used memory by kind",
+            &[
+                ("kind=\"app\"".to_string(), breakdown.app_bytes as f64),
+                ("kind=\"wired\"".to_string(), breakdown.wired_bytes as f64),
+                (
+                    "kind=\"compressed\"".to_string(),
+                    breakdown.compressed_bytes as f64,
+                ),
+                ("kind=\"cached\"".to_string(), breakdown.cached_bytes as f64),
+            ],
+        );
+    }
+
+    if let Some(battery) = metrics.battery {
+        gauge(
+            &mut out,
+            "dragonfly_battery_charge_percent",
+            "Battery charge left",
+            f64::from(battery.charge_percent),
+        );
+        let states: Vec<(String, f64)> = [
+            (ChargingState::Charging, "charging"),
+            (ChargingState::Discharging, "discharging"),
+            (ChargingState::Full, "full"),
+            (ChargingState::NotCharging, "not_charging"),
+        ]
+        .iter()
+        .map(|(state, name)| {
+            let value = if battery.state == *state { 1.0 } else { 0.0 };
+            (format!("state=\"{}\"", name), value)
+        })
+        .collect();
+        gauge_labelled(
+            &mut out,
+            "dragonfly_battery_state",
+            "1 for the battery's current charging state",
+            &states,
+        );
+        if let Some(cycles) = battery.cycle_count {
+            gauge(
+                &mut out,
+                "dragonfly_battery_cycle_count",
+                "Battery charge cycles completed",
+                f64::from(cycles),
+            );
+        }
+        if let Some(health) = battery.health_percent {
+            gauge(
+                &mut out,
+                "dragonfly_battery_health_percent",
+                "Battery full-charge capacity as a percentage of its design capacity",
+                f64::from(health),
+            );
+        }
+    }
+
+    if let Some(gpu) = metrics.gpu {
+        gauge(
+            &mut out,
+            "dragonfly_gpu_utilization_percent",
+            "Share of time the GPU was busy",
+            f64::from(gpu.utilization_percent),
+        );
+        gauge_labelled(
+            &mut out,
+            "dragonfly_gpu_memory_bytes",
+            "GPU memory in use and allocated",
+            &[
+                (
+                    "kind=\"in_use\"".to_string(),
+                    gpu.memory_in_use_bytes as f64,
+                ),
+                (
+                    "kind=\"allocated\"".to_string(),
+                    gpu.memory_allocated_bytes as f64,
+                ),
+            ],
+        );
+    }
+
+    gauge(
+        &mut out,
+        "dragonfly_boot_time_seconds",
+        "When the machine booted, in Unix epoch seconds",
+        metrics.host.boot_time as f64,
+    );
+    gauge(
+        &mut out,
+        "dragonfly_uptime_seconds",
+        "Seconds since the machine booted",
+        metrics.host.uptime_seconds as f64,
+    );
+
+    gauge(
+        &mut out,
+        "dragonfly_collector_last_duration_seconds",
+        "Time the collector took for the latest sample",
+        overhead.last_micros as f64 / 1e6,
+    );
+    let _ = writeln!(
+        out,
+        "# HELP dragonfly_collector_samples_total Samples the collector has taken\n\
+         # TYPE dragonfly_collector_samples_total counter\n\
+         dragonfly_collector_samples_total {}",
+        overhead.samples
+    );
+    out
+}
+
+/// A gauge with one unlabelled value
+fn gauge(out: &mut String, name: &str, help: &str, value: f64) {
+    gauge_labelled(out, name, help, &[(String::new(), value)]);
+}
+
+/// A gauge with a value for each set of labels (`key="value"`, comma
+/// separated); an empty set writes the bare name
+fn gauge_labelled(out: &mut String, name: &str, help: &str, values: &[(String, f64)]) {
+    if values.is_empty() {
+        return;
+    }
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+    for (labels, value) in values {
+        if labels.is_empty() {
+            let _ = writeln!(out, "{} {}", name, value);
+        } else {
+            let _ = writeln!(out, "{}{{{}}} {}", name, labels, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::{BatteryMetrics, GpuMetrics, HostInfo, LoadAverage};
+
+    fn metrics() -> SystemMetrics {
+        SystemMetrics::new(
+            25.0,
+            vec![10.0, 40.0],
+            LoadAverage {
+                one: 1.5,
+                five: 1.0,
+                fifteen: 0.5,
+            },
+            1000,
+            400,
+            600,
+            None,
+            0,
+            0,
+            2000,
+            500,
+            1500,
+            Some(BatteryMetrics {
+                charge_percent: 80.0,
+                state: ChargingState::Charging,
+                cycle_count: None,
+                health_percent: Some(90.0),
+            }),
+            Some(GpuMetrics {
+                utilization_percent: 12.0,
+                memory_in_use_bytes: 300,
+                memory_allocated_bytes: 700,
+            }),
+            HostInfo {
+                uptime_seconds: 3600,
+                boot_time: 1_700_000_000,
+                ..HostInfo::default()
+            },
+            0,
+            0,
+            1,
+        )
+    }
+
+    #[test]
+    fn test_render_exposition_format() {
+        let text = render(
+            &metrics(),
+            CollectorOverhead {
+                samples: 3,
+                last_micros: 250,
+                mean_micros: 200,
+                max_micros: 300,
+            },
+        );
+
+        assert!(text.contains(
+            "# HELP dragonfly_cpu_usage_percent CPU usage across all cores\n\
+             # TYPE dragonfly_cpu_usage_percent gauge\n\
+             dragonfly_cpu_usage_percent 25\n"
+        ));
+        assert!(text.contains("dragonfly_cpu_core_usage_percent{core=\"1\"} 40\n"));
+        assert!(text.contains("dragonfly_load_average{period=\"15m\"} 0.5\n"));
+        assert!(text.contains("dragonfly_disk_available_bytes 1500\n"));
+        assert!(text.contains("dragonfly_battery_state{state=\"charging\"} 1\n"));
+        assert!(text.contains("dragonfly_battery_state{state=\"full\"} 0\n"));
+        assert!(text.contains("dragonfly_battery_health_percent 90\n"));
+        assert!(text.contains("dragonfly_gpu_utilization_percent 12\n"));
+        assert!(text.contains("dragonfly_gpu_memory_bytes{kind=\"allocated\"} 700\n"));
+        assert!(text.contains("dragonfly_collector_last_duration_seconds 0.00025\n"));
+        assert!(text.contains("dragonfly_collector_samples_total 3\n"));
+        assert!(text.contains("dragonfly_uptime_seconds 3600\n"));
+        // Figures this machine doesn't report are left out
+        assert!(!text.contains("dragonfly_battery_cycle_count"));
+        assert!(!text.contains("dragonfly_memory_pressure_level"));
+
+        // Every sample line is a name, optional labels, and a number
+        for line in text.lines().filter(|line| !line.starts_with('#')) {
+            let (_, value) = line.rsplit_once(' ').unwrap();
+            assert!(value.parse::<f64>().is_ok(), "{}", line);
+        }
+    }
+}
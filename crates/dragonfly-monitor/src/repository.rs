@@ -0,0 +1,92 @@
+//! [`SystemRepository`] adapter
+//!
+//! Answers the core's system port from a [`MetricsCollector`], so services
+//! written against the port see the same figures `monitor` shows. Each call
+//! takes a fresh sample; a call right after creating the repository reports
+//! CPU usage over a very short interval.
+
+use crate::collector::MetricsCollector;
+use crate::host::uptime;
+use crate::metrics::SystemMetrics;
+use async_trait::async_trait;
+use dragonfly_core::domain::entities::SystemSnapshot;
+use dragonfly_core::error::Result;
+use dragonfly_core::ports::SystemRepository;
+use tokio::sync::Mutex;
+
+/// System information from the metrics collector
+#[derive(Debug)]
+pub struct MonitorRepository {
+    collector: Mutex<MetricsCollector>,
+}
+
+impl MonitorRepository {
+    /// Create a repository sampling with a default collector
+    pub fn new() -> Self {
+        Self::with_collector(MetricsCollector::new())
+    }
+
+    /// Create a repository sampling with `collector`
+    pub fn with_collector(collector: MetricsCollector) -> Self {
+        Self {
+            collector: Mutex::new(collector),
+        }
+    }
+
+    async fn sample(&self) -> Result<SystemMetrics> {
+        self.collector.lock().await.collect().await
+    }
+}
+
+impl Default for MonitorRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl SystemRepository for MonitorRepository {
+    async fn get_system_snapshot(&self) -> Result<SystemSnapshot> {
+        let metrics = self.sample().await?;
+        Ok(SystemSnapshot {
+            timestamp: metrics.timestamp,
+        })
+    }
+
+    async fn get_uptime(&self) -> Result<u64> {
+        Ok(uptime())
+    }
+
+    async fn get_available_disk_space(&self) -> Result<u64> {
+        Ok(self.sample().await?.disk_available_bytes)
+    }
+
+    async fn get_total_disk_space(&self) -> Result<u64> {
+        Ok(self.sample().await?.disk_total_bytes)
+    }
+
+    async fn get_cpu_usage(&self) -> Result<f32> {
+        Ok(self.sample().await?.cpu_usage_percent)
+    }
+
+    async fn get_memory_usage(&self) -> Result<f32> {
+        Ok(self.sample().await?.memory_usage_percent())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn should_answer_the_system_port() {
+        let repository = MonitorRepository::new();
+
+        assert!(repository.get_uptime().await.unwrap() > 0);
+        let total = repository.get_total_disk_space().await.unwrap();
+        assert!(repository.get_available_disk_space().await.unwrap() <= total);
+        let memory = repository.get_memory_usage().await.unwrap();
+        assert!((0.0..=100.0).contains(&memory));
+        assert!(repository.get_system_snapshot().await.unwrap().timestamp > 0);
+    }
+}
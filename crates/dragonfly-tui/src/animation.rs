@@ -8,9 +8,12 @@ use std::time::{Duration, Instant};
 /// Block state in the defrag grid
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BlockState {
-    Free,    // . (empty space)
-    Used,    // # (allocated)
-    Moving,  // @ (currently being "moved")
+    /// Empty space
+    Free,
+    /// Allocated block
+    Used,
+    /// Block currently being "moved"
+    Moving,
 }
 
 impl BlockState {
@@ -46,10 +49,10 @@ impl DefragAnimation {
         let mut grid = vec![vec![BlockState::Free; cols]; rows];
         
         // Initialize with some "used" blocks in a scattered pattern
-        for row in 0..rows {
-            for col in 0..cols {
+        for (row, blocks) in grid.iter_mut().enumerate() {
+            for (col, block) in blocks.iter_mut().enumerate() {
                 if (row + col) % 3 == 0 || (row * col) % 7 == 0 {
-                    grid[row][col] = BlockState::Used;
+                    *block = BlockState::Used;
                 }
             }
         }
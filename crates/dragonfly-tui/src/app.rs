@@ -3,13 +3,19 @@
 //! This module provides the full-screen terminal UI with defrag animation.
 
 use anyhow::Result;
+use dragonfly_core::domain::scan_options::ScanOptions;
+use dragonfly_core::domain::value_objects::FilePath;
+use dragonfly_core::ports::{CancellationToken, ProgressReporter, ProgressUpdate};
+use dragonfly_core::t;
+use dragonfly_disk::DiskAnalyzer;
+use dragonfly_duplicates::{phase, DuplicateDetector};
 use crossterm::{
     event::{self, Event, KeyCode, KeyEvent},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
-    backend::{Backend, CrosstermBackend},
+    backend::CrosstermBackend,
     layout::{Alignment, Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
     text::{Line, Span},
@@ -18,11 +24,36 @@ use ratatui::{
 };
 use std::{
     io,
+    sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
 
 use crate::animation::DefragAnimation;
 
+/// What the background scan looks for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScanKind {
+    /// Size up the tree
+    #[default]
+    Disk,
+    /// Find files with the same content
+    Duplicates,
+}
+
+/// How far the background scan has got, shared with the UI
+#[derive(Debug, Default)]
+struct ScanState {
+    /// Latest update from the scan
+    latest: ProgressUpdate,
+    /// Whether the scan has ended
+    finished: bool,
+    /// Why the scan failed, if it did
+    error: Option<String>,
+    /// Duplicate groups found and the bytes their extra copies take, once a
+    /// duplicate scan ends
+    duplicates: Option<(usize, u64)>,
+}
+
 /// Application state
 pub struct App {
     /// Should the app quit?
@@ -37,6 +68,12 @@ pub struct App {
     files_scanned: u64,
     /// Target path being scanned
     target_path: String,
+    /// File the scan reached most recently
+    current_path: Option<String>,
+    /// Stage the scan is in, if it has several
+    phase: Option<&'static str>,
+    /// Progress shared with the scan
+    scan: Arc<Mutex<ScanState>>,
 }
 
 impl App {
@@ -49,20 +86,43 @@ impl App {
             bytes_scanned: 0,
             files_scanned: 0,
             target_path,
+            current_path: None,
+            phase: None,
+            scan: Arc::default(),
         }
     }
-    
+
+    /// Reporter that feeds scan progress into this app
+    pub fn progress_reporter(&self) -> impl ProgressReporter {
+        let scan = Arc::clone(&self.scan);
+        move |update: &ProgressUpdate| scan.lock().unwrap().latest = update.clone()
+    }
+
+    /// Mark the scan as ended, with the reason if it failed
+    pub fn finish_scan(&self, error: Option<String>) {
+        let mut scan = self.scan.lock().unwrap();
+        scan.finished = true;
+        scan.error = error;
+    }
+
     /// Update the app state
     pub fn update(&mut self) {
         // Update animation
         self.animation.update();
-        
-        // Simulate scan progress (this will be real data from dragonfly-disk later)
-        if self.progress < 1.0 {
-            self.progress += 0.001;
-            self.bytes_scanned += 1024 * 1024; // 1MB per frame
-            self.files_scanned += 10;
-        }
+
+        // Pick up what the scan reported since the last tick
+        let scan = self.scan.lock().unwrap();
+        self.files_scanned = scan.latest.items_processed;
+        self.bytes_scanned = scan.latest.bytes_processed;
+        self.current_path = scan.latest.current_path.clone();
+        self.phase = scan.latest.phase;
+        self.progress = if scan.finished {
+            1.0
+        } else {
+            byte_fraction(&scan.latest)
+                .or_else(|| scan.latest.fraction())
+                .unwrap_or(0.0)
+        };
     }
     
     /// Handle input events
@@ -101,7 +161,7 @@ impl App {
             .split(frame.size());
         
         // Title
-        let title = Paragraph::new("🐉 DragonFly Defrag Theater")
+        let title = Paragraph::new(t!("tui.title"))
             .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
             .alignment(Alignment::Center)
             .block(Block::default().borders(Borders::ALL));
@@ -111,36 +171,67 @@ impl App {
         let animation_text = self.animation.render();
         let animation = Paragraph::new(animation_text)
             .style(Style::default().fg(Color::Green))
-            .block(Block::default().borders(Borders::ALL).title("Disk Allocation"));
+            .block(Block::default().borders(Borders::ALL).title(t!("tui.disk_allocation")));
         frame.render_widget(animation, chunks[1]);
         
         // Progress/stats
-        let progress_pct = (self.progress * 100.0) as u32;
         let bytes_gb = self.bytes_scanned as f64 / (1024.0 * 1024.0 * 1024.0);
-        let files_k = self.files_scanned / 1000;
-        
-        let progress_bar = "█".repeat(progress_pct.min(100) as usize / 2);
+        let status = {
+            let scan = self.scan.lock().unwrap();
+            match (&scan.error, scan.finished) {
+                (Some(error), _) => t!("tui.scan_failed", error = error),
+                (None, true) if scan.duplicates.is_some() => {
+                    let (groups, wasted) = scan.duplicates.unwrap_or_default();
+                    t!(
+                        "tui.duplicates_complete",
+                        groups = groups,
+                        path = self.target_path,
+                        size = format!("{:.1}", wasted as f64 / (1024.0 * 1024.0 * 1024.0)),
+                    )
+                }
+                (None, true) => t!(
+                    "tui.scan_complete",
+                    path = self.target_path,
+                    size = format!("{:.1}", bytes_gb),
+                    files = self.files_scanned,
+                ),
+                (None, false) if self.phase.is_some() => t!(
+                    "tui.duplicates_phase",
+                    phase = phase_label(self.phase.unwrap_or_default()),
+                    path = self.target_path,
+                    size = format!("{:.1}", bytes_gb),
+                    files = self.files_scanned,
+                ),
+                (None, false) => t!(
+                    "tui.scanning",
+                    path = self.target_path,
+                    size = format!("{:.1}", bytes_gb),
+                    files = self.files_scanned,
+                ),
+            }
+        };
+        // The walk can't know its total up front, so the bar fills at the end;
+        // hashing phases fill it as their bytes are read
+        let progress_bar = "█".repeat((self.progress * 50.0) as usize);
         let progress_text = format!(
-            "Scanning {} … {}% | {:.1} GB indexed | {}K files\n{}",
-            self.target_path,
-            progress_pct,
-            bytes_gb,
-            files_k,
+            "{}\n{}\n{}",
+            status,
+            self.current_path.as_deref().unwrap_or_default(),
             progress_bar
         );
-        
+
         let progress = Paragraph::new(progress_text)
             .style(Style::default().fg(Color::Yellow))
-            .block(Block::default().borders(Borders::ALL).title("Progress"));
+            .block(Block::default().borders(Borders::ALL).title(t!("tui.progress")));
         frame.render_widget(progress, chunks[2]);
         
         // Help text
         let help = Paragraph::new(vec![
             Line::from(vec![
                 Span::styled("Q", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
-                Span::raw(" = Quit  "),
+                Span::raw(format!(" = {}  ", t!("tui.quit"))),
                 Span::styled("Ctrl+C", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
-                Span::raw(" = Exit"),
+                Span::raw(format!(" = {}", t!("tui.exit"))),
             ]),
         ])
         .alignment(Alignment::Center)
@@ -149,8 +240,32 @@ impl App {
     }
 }
 
-/// Run the TUI application
-pub async fn run_app(target_path: String) -> Result<()> {
+/// Completed fraction of the bytes, when the total is known
+#[allow(clippy::cast_precision_loss)]
+fn byte_fraction(update: &ProgressUpdate) -> Option<f64> {
+    update.bytes_total.map(|total| {
+        if total == 0 {
+            1.0
+        } else {
+            (update.bytes_processed as f64 / total as f64).min(1.0)
+        }
+    })
+}
+
+/// What the user sees for a duplicate scan [`phase`]
+fn phase_label(name: &str) -> &str {
+    match name {
+        phase::WALK => t!("duplicates.phase_walk"),
+        phase::PARTIAL_HASH => t!("duplicates.phase_partial_hash"),
+        phase::FULL_HASH => t!("duplicates.phase_full_hash"),
+        phase::VERIFY => t!("duplicates.phase_verify"),
+        phase::MEDIA_HASH => t!("duplicates.phase_media_hash"),
+        other => other,
+    }
+}
+
+/// Run the TUI application, scanning `target_path` for `kind`
+pub async fn run_app(target_path: String, kind: ScanKind) -> Result<()> {
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -158,9 +273,44 @@ pub async fn run_app(target_path: String) -> Result<()> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
     
-    // Create app state
-    let mut app = App::new(target_path);
-    
+    // Create app state and start scanning in the background
+    let mut app = App::new(target_path.clone());
+    let cancel = CancellationToken::new();
+    let scan = {
+        let reporter = app.progress_reporter();
+        let scan_state = Arc::clone(&app.scan);
+        let cancel = cancel.clone();
+        // The walk blocks its thread, so keep it off the one drawing the UI
+        tokio::task::spawn_blocking(move || {
+            let path = FilePath::new(target_path);
+            let options = ScanOptions::default();
+            let runtime = tokio::runtime::Handle::current();
+            let (duplicates, error) = match kind {
+                ScanKind::Disk => {
+                    let result = runtime.block_on(DiskAnalyzer::new().analyze_with_progress(
+                        &path, &options, &reporter, &cancel,
+                    ));
+                    (None, result.err())
+                }
+                ScanKind::Duplicates => {
+                    let result = runtime.block_on(
+                        DuplicateDetector::new()
+                            .with_progress(Arc::new(reporter))
+                            .find_duplicates_with_options(&path, 1, &options, &cancel),
+                    );
+                    match result {
+                        Ok(found) => (Some((found.duplicates.len(), found.potential_savings)), None),
+                        Err(e) => (None, Some(e)),
+                    }
+                }
+            };
+            let mut scan = scan_state.lock().unwrap();
+            scan.finished = true;
+            scan.duplicates = duplicates;
+            scan.error = error.map(|e| e.to_string());
+        })
+    };
+
     // Event loop
     let tick_rate = Duration::from_millis(100);
     let mut last_tick = Instant::now();
@@ -191,6 +341,10 @@ pub async fn run_app(target_path: String) -> Result<()> {
         }
     }
     
+    // Stop the scan if it's still running
+    cancel.cancel();
+    let _ = scan.await;
+
     // Restore terminal
     disable_raw_mode()?;
     execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
@@ -213,11 +367,31 @@ mod tests {
     #[test]
     fn test_app_update() {
         let mut app = App::new("~/".to_string());
-        let initial_progress = app.progress;
+        app.progress_reporter()
+            .report(&ProgressUpdate::new(1_000, 4096).with_current_path("/tmp/a"));
         app.update();
-        assert!(app.progress > initial_progress);
+        assert_eq!((app.files_scanned, app.bytes_scanned), (1_000, 4096));
+        assert_eq!(app.current_path.as_deref(), Some("/tmp/a"));
+        assert_eq!(app.progress, 0.0);
+
+        app.finish_scan(None);
+        app.update();
+        assert_eq!(app.progress, 1.0);
     }
     
+    #[test]
+    fn test_app_update_shows_phase() {
+        let mut app = App::new("~/".to_string());
+        app.progress_reporter().report(
+            &ProgressUpdate::new(1, 256)
+                .with_bytes_total(1024)
+                .with_phase(phase::FULL_HASH),
+        );
+        app.update();
+        assert_eq!(app.phase, Some(phase::FULL_HASH));
+        assert_eq!(app.progress, 0.25);
+    }
+
     #[test]
     fn test_quit_on_q() {
         let mut app = App::new("~/".to_string());
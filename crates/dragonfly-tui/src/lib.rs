@@ -14,4 +14,4 @@ pub mod animation;
 pub mod app;
 
 // Re-export main entry point
-pub use app::run_app;
+pub use app::{run_app, ScanKind};